@@ -0,0 +1,161 @@
+import { existsSync, readFileSync, writeFileSync } from "node:fs";
+import { afterAll, beforeAll, expect, it } from "vitest";
+import { Hex, keccak256, maxUint256, pad, toFunctionSelector } from "viem";
+
+import { loadEnv, TestEnv } from "../setup.js";
+import { Check, CompOp, Opcode } from "../types.js";
+import {
+  POLICY_SUCCESS,
+  describeE2E,
+  estimateCheckUserOpPolicyGas,
+  installIntentPolicy,
+  makeBytes32Id,
+  makeCallData,
+  makePermissionId,
+} from "../tests/testUtils.js";
+
+// Gas benchmark for `checkUserOpPolicy` against the devnet policy and infra mocks.
+//
+// Every case is a passing program (the mocks' zero state satisfies the chosen bounds), so the
+// measured gas covers the whole evaluation rather than an early exit. Results are written to
+// `gas-report.json`; if `gas-baseline.json` exists, any case or per-opcode cost that grew by
+// more than GAS_TOLERANCE_PCT (default 5) fails the run. GAS_UPDATE_BASELINE=1 rewrites the
+// baseline from this run instead.
+
+const REPORT_PATH = new URL("../../gas-report.json", import.meta.url);
+const BASELINE_PATH = new URL("../../gas-baseline.json", import.meta.url);
+const TOLERANCE_PCT = Number(process.env.GAS_TOLERANCE_PCT ?? "5");
+const LENGTHS = [1, 8, 32, 64] as const;
+
+type Report = {
+  cases: Record<string, string>;
+  // Marginal cost of one check of each opcode over the empty program.
+  perOpcode: Record<string, string>;
+};
+
+function singleChecks(env: TestEnv, deadline: bigint, callData: Hex): Record<string, Check> {
+  const poolId = makeBytes32Id("bench:pool") as Hex;
+  const positionId = makeBytes32Id("bench:position") as Hex;
+  return {
+    deadline: { kind: Opcode.CheckDeadline, deadline },
+    nonce: { kind: Opcode.CheckNonce, expected: 0n },
+    callBundleHash: { kind: Opcode.CheckCallBundleHash, hash: keccak256(callData) },
+    slot0TickBounds: { kind: Opcode.CheckSlot0TickBounds, poolId, min: -887272, max: 887272 },
+    slot0SqrtPriceBounds: { kind: Opcode.CheckSlot0SqrtPriceBounds, poolId, min: 0n, max: maxUint256 },
+    rfsClosed: { kind: Opcode.CheckRfsClosed, positionId },
+    queueLte: { kind: Opcode.CheckQueueLte, lcc: env.liquidityHub, owner: env.owner.address, max: maxUint256 },
+    reserveGte: { kind: Opcode.CheckReserveGte, lcc: env.liquidityHub, min: 0n },
+    settledGte: { kind: Opcode.CheckSettledGte, positionId, minAmount0: 0n, minAmount1: 0n },
+    commitmentDeficitLte: {
+      kind: Opcode.CheckCommitmentDeficitLte,
+      positionId,
+      maxDeficit0: maxUint256,
+      maxDeficit1: maxUint256,
+    },
+    gracePeriodGte: { kind: Opcode.CheckGracePeriodGte, positionId, minSeconds: 0n },
+    staticCallU256: {
+      kind: Opcode.CheckStaticCallU256,
+      target: env.stateView,
+      selector: toFunctionSelector("getSlot0(bytes32)"),
+      args: pad(poolId),
+      op: CompOp.Gte,
+      rhs: 0n,
+    },
+  };
+}
+
+function growth(current: bigint, baseline: bigint): number {
+  if (baseline === 0n) return current === 0n ? 0 : Infinity;
+  return (Number(current - baseline) / Number(baseline)) * 100;
+}
+
+describeE2E("checkUserOpPolicy gas (bench)", () => {
+  let env: TestEnv;
+  const permissionId = makePermissionId("gas-bench");
+  const callData = makeCallData("gas-bench");
+  const deadline = BigInt(Number.MAX_SAFE_INTEGER);
+  const measured: Record<string, bigint> = {};
+
+  async function measure(name: string, checks: Check[]) {
+    const { gas, result } = await estimateCheckUserOpPolicyGas({
+      env,
+      permissionId,
+      wallet: env.owner.address,
+      callData,
+      checks,
+      nonce: 0n,
+      deadline,
+    });
+    expect(result, `${name} must pass to be comparable`).toBe(POLICY_SUCCESS);
+    measured[name] = gas;
+  }
+
+  beforeAll(async () => {
+    env = loadEnv();
+    await installIntentPolicy({ env, permissionId });
+  });
+
+  it("single opcodes", async () => {
+    await measure("empty", []);
+    for (const [name, check] of Object.entries(singleChecks(env, deadline, callData))) {
+      await measure(`single/${name}`, [check]);
+    }
+  });
+
+  it("program length and fact mix", async () => {
+    const { deadline: cheap, slot0TickBounds: fact } = singleChecks(env, deadline, callData);
+    for (const n of LENGTHS) {
+      await measure(`length/deadline x${n}`, Array(n).fill(cheap));
+      await measure(`length/slot0TickBounds x${n}`, Array(n).fill(fact));
+      // Half cheap, half fact-reading, interleaved.
+      if (n > 1) {
+        const mixed = Array.from({ length: n }, (_, i) => (i % 2 === 0 ? cheap : fact));
+        await measure(`mix/50% facts x${n}`, mixed);
+      }
+    }
+  });
+
+  afterAll(() => {
+    if (measured.empty == null) return;
+
+    const report: Report = { cases: {}, perOpcode: {} };
+    for (const [name, gas] of Object.entries(measured)) {
+      report.cases[name] = gas.toString();
+      if (name.startsWith("single/")) {
+        report.perOpcode[name.slice("single/".length)] = (gas - measured.empty).toString();
+      }
+    }
+    writeFileSync(REPORT_PATH, `${JSON.stringify(report, null, 2)}\n`);
+
+    const rows = Object.entries(report.perOpcode).map(([opcode, gas]) => ({ opcode, gas }));
+    console.table(rows);
+
+    if (process.env.GAS_UPDATE_BASELINE === "1") {
+      writeFileSync(BASELINE_PATH, `${JSON.stringify(report, null, 2)}\n`);
+      console.log(`Wrote gas baseline to ${BASELINE_PATH.pathname}`);
+      return;
+    }
+    if (!existsSync(BASELINE_PATH)) {
+      console.log("No gas-baseline.json; run with GAS_UPDATE_BASELINE=1 to record one.");
+      return;
+    }
+
+    const baseline = JSON.parse(readFileSync(BASELINE_PATH, "utf8")) as Report;
+    const regressions: string[] = [];
+    for (const section of ["cases", "perOpcode"] as const) {
+      for (const [name, gas] of Object.entries(report[section])) {
+        const previous = baseline[section][name];
+        if (previous == null) continue;
+        const pct = growth(BigInt(gas), BigInt(previous));
+        if (pct > TOLERANCE_PCT) {
+          regressions.push(`${section} ${name}: ${previous} -> ${gas} (+${pct.toFixed(1)}%)`);
+        }
+      }
+    }
+    if (regressions.length > 0) {
+      throw new Error(
+        `Gas regressions over ${TOLERANCE_PCT}%:\n${regressions.map((r) => `  ${r}`).join("\n")}`,
+      );
+    }
+  });
+});
@@ -110,6 +110,11 @@ export function encodeProgram(checks: Check[]): Uint8Array {
         chunks.push(writeB32(c.hash));
         break;
       }
+      case Opcode.CheckUserOpField: {
+        chunks.push(new Uint8Array([Opcode.CheckUserOpField, c.field, c.op]));
+        chunks.push(beU256(c.rhs));
+        break;
+      }
       case Opcode.CheckTokenAmountLte: {
         chunks.push(new Uint8Array([Opcode.CheckTokenAmountLte]));
         chunks.push(writeAddress(c.token));
@@ -126,6 +131,19 @@ export function encodeProgram(checks: Check[]): Uint8Array {
         chunks.push(beU128(c.max));
         break;
       }
+      case Opcode.CheckWalletTokenDeltaLte: {
+        chunks.push(new Uint8Array([Opcode.CheckWalletTokenDeltaLte]));
+        chunks.push(writeAddress(c.token));
+        chunks.push(beU256(c.maxOut));
+        break;
+      }
+      case Opcode.CheckWindowSpendLte: {
+        chunks.push(new Uint8Array([Opcode.CheckWindowSpendLte]));
+        chunks.push(writeAddress(c.token));
+        chunks.push(beU32(c.window));
+        chunks.push(beU256(c.max));
+        break;
+      }
       case Opcode.CheckSlot0TickBounds: {
         chunks.push(new Uint8Array([Opcode.CheckSlot0TickBounds]));
         chunks.push(writeB32(c.poolId));
@@ -140,6 +158,28 @@ export function encodeProgram(checks: Check[]): Uint8Array {
         chunks.push(beU256(c.max));
         break;
       }
+      case Opcode.CheckTwapTickBounds: {
+        chunks.push(new Uint8Array([Opcode.CheckTwapTickBounds]));
+        chunks.push(writeB32(c.poolId));
+        chunks.push(beU32(c.window));
+        chunks.push(beI32(c.min));
+        chunks.push(beI32(c.max));
+        break;
+      }
+      case Opcode.CheckOracleDeviationLte: {
+        chunks.push(new Uint8Array([Opcode.CheckOracleDeviationLte]));
+        chunks.push(writeB32(c.poolId));
+        chunks.push(writeAddress(c.oracle));
+        chunks.push(writeSelector(c.selector));
+        chunks.push(new Uint8Array([c.decimals]));
+        chunks.push(beU32(c.maxBps));
+        break;
+      }
+      case Opcode.CheckPoolAllowed: {
+        chunks.push(new Uint8Array([Opcode.CheckPoolAllowed]));
+        chunks.push(writeB32(c.poolId));
+        break;
+      }
       case Opcode.CheckRfsClosed: {
         chunks.push(new Uint8Array([Opcode.CheckRfsClosed]));
         chunks.push(writeB32(c.positionId));
@@ -178,6 +218,22 @@ export function encodeProgram(checks: Check[]): Uint8Array {
         chunks.push(beU64(c.minSeconds));
         break;
       }
+      case Opcode.CheckHubSolvency: {
+        chunks.push(new Uint8Array([Opcode.CheckHubSolvency]));
+        chunks.push(writeAddress(c.lcc));
+        chunks.push(beU32(c.minRatioBps));
+        break;
+      }
+      case Opcode.CheckQueueSumLte: {
+        chunks.push(new Uint8Array([Opcode.CheckQueueSumLte]));
+        chunks.push(writeAddress(c.lcc));
+        chunks.push(new Uint8Array([c.owners.length]));
+        for (const owner of c.owners) {
+          chunks.push(writeAddress(owner));
+        }
+        chunks.push(beU256(c.max));
+        break;
+      }
       case Opcode.CheckStaticCallU256: {
         chunks.push(new Uint8Array([Opcode.CheckStaticCallU256]));
         chunks.push(writeAddress(c.target));
@@ -189,6 +245,22 @@ export function encodeProgram(checks: Check[]): Uint8Array {
         chunks.push(beU256(c.rhs));
         break;
       }
+      case Opcode.CheckStaticCallCompare: {
+        chunks.push(new Uint8Array([Opcode.CheckStaticCallCompare]));
+        chunks.push(writeAddress(c.lhsTarget));
+        chunks.push(writeSelector(c.lhsSelector));
+        const lhsArgs = hexToBytes(c.lhsArgs);
+        chunks.push(beU16(lhsArgs.length));
+        chunks.push(lhsArgs);
+        chunks.push(new Uint8Array([c.op]));
+        chunks.push(writeAddress(c.rhsTarget));
+        chunks.push(writeSelector(c.rhsSelector));
+        const rhsArgs = hexToBytes(c.rhsArgs);
+        chunks.push(beU16(rhsArgs.length));
+        chunks.push(rhsArgs);
+        chunks.push(beU32(c.scaleBps));
+        break;
+      }
       default:
         throw new Error(`Unknown opcode ${(c as Check).kind}`);
     }
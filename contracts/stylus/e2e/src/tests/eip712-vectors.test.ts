@@ -0,0 +1,75 @@
+import { readFileSync } from "node:fs";
+import { describe, expect, it } from "vitest";
+import { Address, Hex, hashTypedData, hexToBytes, keccak256, toHex } from "viem";
+import { privateKeyToAccount } from "viem/accounts";
+import { encodeEnvelope, signEnvelope } from "../encoder.js";
+
+// Golden EIP-712 vectors shared with the Rust policy/encoder tests and Foundry
+// (`test/Eip712Vectors.t.sol`). Runs without a node.
+type Vector = {
+  name: string;
+  privateKey: Hex;
+  signer: Address;
+  chainId: string;
+  verifyingContract: Address;
+  wallet: Address;
+  permissionId: Hex;
+  nonce: string;
+  deadline: string;
+  callData: Hex;
+  callBundleHash: Hex;
+  programBytes: Hex;
+  programHash: Hex;
+  domainSeparator: Hex;
+  structHash: Hex;
+  digest: Hex;
+  signature: Hex;
+  envelope: Hex;
+};
+
+const { vectors } = JSON.parse(
+  readFileSync(new URL("../../../fixture/eip712-vectors.json", import.meta.url), "utf8"),
+) as { vectors: Vector[] };
+
+describe("policy envelope EIP-712 golden vectors", () => {
+  it("has vectors", () => {
+    expect(vectors.length).toBeGreaterThan(0);
+  });
+
+  for (const v of vectors) {
+    it(v.name, async () => {
+      const account = privateKeyToAccount(v.privateKey);
+      expect(account.address.toLowerCase()).toBe(v.signer.toLowerCase());
+      expect(keccak256(v.callData)).toBe(v.callBundleHash);
+      expect(keccak256(v.programBytes)).toBe(v.programHash);
+
+      const envelope = {
+        version: 1,
+        nonce: BigInt(v.nonce),
+        deadline: BigInt(v.deadline),
+        callBundleHash: v.callBundleHash,
+        programBytes: hexToBytes(v.programBytes),
+      };
+      const params = {
+        chainId: BigInt(v.chainId),
+        verifyingContract: v.verifyingContract,
+        wallet: v.wallet,
+        permissionId: v.permissionId,
+        envelope,
+      };
+
+      // `signEnvelope` builds the typed data; capture it to check the digest as well.
+      let typedData: any;
+      const signature = await signEnvelope({
+        ...params,
+        signTypedData: (args: any) => {
+          typedData = args;
+          return account.signTypedData(args);
+        },
+      });
+      expect(hashTypedData(typedData)).toBe(v.digest);
+      expect(toHex(signature)).toBe(v.signature);
+      expect(encodeEnvelope(envelope, signature)).toBe(v.envelope);
+    });
+  }
+});
@@ -201,7 +201,7 @@ export async function uninstallIntentPolicy(params: {
   });
 }
 
-export async function simulateCheckUserOpPolicy(params: {
+type CheckUserOpPolicyParams = {
   env: TestEnv;
   permissionId: Hex;
   wallet: Address;
@@ -213,7 +213,10 @@ export async function simulateCheckUserOpPolicy(params: {
   envelopeSignerPrivateKey?: Hex;
   // Override to intentionally test bundle mismatches.
   callBundleHashOverride?: Hex;
-}) {
+};
+
+/** Sign an envelope for `checks` and build the `checkUserOpPolicy` call from `wallet`. */
+async function buildCheckUserOpPolicyCall(params: CheckUserOpPolicyParams) {
   const {
     env,
     permissionId,
@@ -252,14 +255,35 @@ export async function simulateCheckUserOpPolicy(params: {
     signature: envelope,
   });
 
-  const sim = await publicClient.simulateContract({
-    account: wallet, // msg.sender should match the userOp sender in this simulation context
-    address: env.intentPolicy,
-    abi: IntentPolicyABI,
-    functionName: "checkUserOpPolicy",
-    args: [permissionId, userOp],
-  });
+  return {
+    publicClient,
+    call: {
+      account: wallet, // msg.sender should match the userOp sender in this simulation context
+      address: env.intentPolicy,
+      abi: IntentPolicyABI,
+      functionName: "checkUserOpPolicy",
+      args: [permissionId, userOp],
+    } as const,
+  };
+}
 
+export async function simulateCheckUserOpPolicy(params: CheckUserOpPolicyParams) {
+  const { publicClient, call } = await buildCheckUserOpPolicyCall(params);
+  const sim = await publicClient.simulateContract(call);
   return sim.result;
 }
 
+/**
+ * Gas estimate for `checkUserOpPolicy`, alongside the policy result for the same call.
+ *
+ * The estimate is for a top-level call, so it includes intrinsic and calldata gas on top of the
+ * policy's own execution.
+ */
+export async function estimateCheckUserOpPolicyGas(params: CheckUserOpPolicyParams) {
+  const { publicClient, call } = await buildCheckUserOpPolicyCall(params);
+  const [gas, sim] = await Promise.all([
+    publicClient.estimateContractGas(call),
+    publicClient.simulateContract(call),
+  ]);
+  return { gas, result: sim.result };
+}
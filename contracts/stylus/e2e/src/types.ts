@@ -9,17 +9,33 @@ export enum CompOp {
   Neq = 5,
 }
 
+export enum UserOpField {
+  Sender = 0,
+  Nonce = 1,
+  VerificationGasLimit = 2,
+  CallGasLimit = 3,
+  PreVerificationGas = 4,
+  MaxPriorityFeePerGas = 5,
+  MaxFeePerGas = 6,
+}
+
 export enum Opcode {
   CheckDeadline = 0x01,
   CheckNonce = 0x02,
   CheckCallBundleHash = 0x03,
+  CheckUserOpField = 0x04,
 
   CheckTokenAmountLte = 0x11,
   CheckNativeValueLte = 0x12,
   CheckLiquidityDeltaLte = 0x13,
+  CheckWalletTokenDeltaLte = 0x14,
+  CheckWindowSpendLte = 0x15,
 
   CheckSlot0TickBounds = 0x20,
   CheckSlot0SqrtPriceBounds = 0x21,
+  CheckPoolAllowed = 0x22,
+  CheckTwapTickBounds = 0x23,
+  CheckOracleDeviationLte = 0x24,
 
   CheckRfsClosed = 0x30,
   CheckQueueLte = 0x31,
@@ -27,26 +43,55 @@ export enum Opcode {
   CheckSettledGte = 0x33,
   CheckCommitmentDeficitLte = 0x34,
   CheckGracePeriodGte = 0x35,
+  CheckHubSolvency = 0x36,
+  CheckQueueSumLte = 0x37,
 
   CheckStaticCallU256 = 0xf0,
+  CheckStaticCallCompare = 0xf1,
 }
 
 export type Check =
   | { kind: Opcode.CheckDeadline; deadline: bigint }
   | { kind: Opcode.CheckNonce; expected: bigint }
   | { kind: Opcode.CheckCallBundleHash; hash: Hex }
+  | { kind: Opcode.CheckUserOpField; field: UserOpField; op: CompOp; rhs: bigint }
   | { kind: Opcode.CheckTokenAmountLte; token: Address; max: bigint }
   | { kind: Opcode.CheckNativeValueLte; max: bigint }
   | { kind: Opcode.CheckLiquidityDeltaLte; max: bigint }
+  | { kind: Opcode.CheckWalletTokenDeltaLte; token: Address; maxOut: bigint }
+  | { kind: Opcode.CheckWindowSpendLte; token: Address; window: number; max: bigint }
   | { kind: Opcode.CheckSlot0TickBounds; poolId: Hex; min: number; max: number }
   | { kind: Opcode.CheckSlot0SqrtPriceBounds; poolId: Hex; min: bigint; max: bigint }
+  | { kind: Opcode.CheckPoolAllowed; poolId: Hex }
+  | { kind: Opcode.CheckTwapTickBounds; poolId: Hex; window: number; min: number; max: number }
+  | {
+      kind: Opcode.CheckOracleDeviationLte;
+      poolId: Hex;
+      oracle: Address;
+      selector: Hex;
+      decimals: number;
+      maxBps: number;
+    }
   | { kind: Opcode.CheckRfsClosed; positionId: Hex }
   | { kind: Opcode.CheckQueueLte; lcc: Address; owner: Address; max: bigint }
   | { kind: Opcode.CheckReserveGte; lcc: Address; min: bigint }
   | { kind: Opcode.CheckSettledGte; positionId: Hex; minAmount0: bigint; minAmount1: bigint }
   | { kind: Opcode.CheckCommitmentDeficitLte; positionId: Hex; maxDeficit0: bigint; maxDeficit1: bigint }
   | { kind: Opcode.CheckGracePeriodGte; positionId: Hex; minSeconds: bigint }
-  | { kind: Opcode.CheckStaticCallU256; target: Address; selector: Hex; args: Hex; op: CompOp; rhs: bigint };
+  | { kind: Opcode.CheckHubSolvency; lcc: Address; minRatioBps: number }
+  | { kind: Opcode.CheckQueueSumLte; lcc: Address; owners: Address[]; max: bigint }
+  | { kind: Opcode.CheckStaticCallU256; target: Address; selector: Hex; args: Hex; op: CompOp; rhs: bigint }
+  | {
+      kind: Opcode.CheckStaticCallCompare;
+      lhsTarget: Address;
+      lhsSelector: Hex;
+      lhsArgs: Hex;
+      op: CompOp;
+      rhsTarget: Address;
+      rhsSelector: Hex;
+      rhsArgs: Hex;
+      scaleBps: number;
+    };
 
 export interface IntentEnvelope {
   version: number;
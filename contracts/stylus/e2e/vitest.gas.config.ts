@@ -0,0 +1,15 @@
+import "dotenv/config";
+
+import { defineConfig } from "vitest/config";
+
+// Gas benchmark (`bun run bench:gas`), kept out of the default test run.
+export default defineConfig({
+  test: {
+    globals: true,
+    environment: "node",
+    include: ["src/bench/**/*.gas.ts"],
+    setupFiles: ["./src/tests/setupEnv.ts"],
+    testTimeout: 600_000,
+    hookTimeout: 120_000,
+  },
+});
@@ -0,0 +1,46 @@
+//! Differential check between the off-chain encoder and the on-chain decoder.
+//!
+//! Random `Check` sequences (from the types crate's `Arbitrary` impl) are encoded with
+//! `fiet-maker-policy-encoder` and decoded with the policy's `decode_program`; the result must be
+//! the same sequence. The raw input is also fed to the decoder directly, and anything it accepts
+//! must re-encode to the same bytes, so the program format has exactly one encoding per check list.
+
+#![no_main]
+
+use arbitrary::{Result, Unstructured};
+use fiet_maker_policy::{
+    decoder::{decode_program, MAX_PROGRAM_LEN},
+    errors::DecodeError,
+};
+use fiet_maker_policy_encoder::{
+    encoder::encode_program,
+    opcodes::{call_target_count, Check, MAX_CALL_TARGETS},
+};
+use libfuzzer_sys::fuzz_target;
+
+/// `decode_program` rejects longer programs.
+const MAX_CHECKS: usize = 64;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(checks) = arbitrary_checks(&mut Unstructured::new(data)) {
+        let encoded = encode_program(&checks);
+        if encoded.len() > MAX_PROGRAM_LEN {
+            assert_eq!(decode_program(&encoded), Err(DecodeError::ProgramTooLarge(encoded.len())));
+        } else if call_target_count(&checks) > MAX_CALL_TARGETS {
+            let targets = call_target_count(&checks);
+            assert_eq!(decode_program(&encoded), Err(DecodeError::TooManyTargets(targets)));
+        } else {
+            let decoded = decode_program(&encoded).expect("decoder rejected an encoded program");
+            assert_eq!(decoded, checks);
+        }
+    }
+
+    if let Ok(checks) = decode_program(data) {
+        assert_eq!(encode_program(&checks), data);
+    }
+});
+
+fn arbitrary_checks(u: &mut Unstructured) -> Result<Vec<Check>> {
+    let len = u.int_in_range(0..=MAX_CHECKS)?;
+    (0..len).map(|_| u.arbitrary()).collect()
+}
@@ -0,0 +1,87 @@
+//! Big-endian byte reader shared by the policy's decoders and the off-chain encoder.
+//!
+//! One implementation keeps the on-chain and off-chain parsers from drifting apart. Bounds are
+//! checked with overflow-safe arithmetic, so malformed input (including lengths near `usize::MAX`)
+//! yields [`UnexpectedEnd`] rather than a panic.
+
+use alloy_primitives::{Address, FixedBytes, U256};
+
+/// The input ended before the field being read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnexpectedEnd;
+
+/// Cursor over a byte slice that reads fixed-width big-endian fields.
+#[derive(Clone, Debug)]
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Bytes consumed so far.
+    pub const fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub const fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// The next `len` bytes.
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], UnexpectedEnd> {
+        let end = self.pos.checked_add(len).ok_or(UnexpectedEnd)?;
+        let out = self.bytes.get(self.pos..end).ok_or(UnexpectedEnd)?;
+        self.pos = end;
+        Ok(out)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], UnexpectedEnd> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, UnexpectedEnd> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, UnexpectedEnd> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, UnexpectedEnd> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, UnexpectedEnd> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    pub fn u128(&mut self) -> Result<u128, UnexpectedEnd> {
+        Ok(u128::from_be_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, UnexpectedEnd> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    pub fn u256(&mut self) -> Result<U256, UnexpectedEnd> {
+        Ok(U256::from_be_slice(self.take(32)?))
+    }
+
+    pub fn b32(&mut self) -> Result<FixedBytes<32>, UnexpectedEnd> {
+        Ok(FixedBytes(self.array()?))
+    }
+
+    pub fn address(&mut self) -> Result<Address, UnexpectedEnd> {
+        Ok(Address::from_slice(self.take(20)?))
+    }
+}
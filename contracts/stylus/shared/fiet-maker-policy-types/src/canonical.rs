@@ -0,0 +1,39 @@
+//! Canonical program form.
+//!
+//! A program passes only if every check does, so the same checks in any order, or with a check
+//! repeated, mean the same thing but encode (and hash) differently. The canonical form sorts the
+//! checks by their encoding, which orders them by opcode first, and drops exact duplicates. Tools
+//! that canonicalize before encoding agree on the program hash of the same set of checks.
+//!
+//! The policy accepts any order; canonical form is a convention for builders, not a validity rule.
+
+use alloc::vec::Vec;
+
+use crate::bytes::ByteReader;
+use crate::opcodes::Check;
+
+/// `checks` in canonical order: sorted by encoding (`Check`'s `Ord`), exact duplicates removed.
+pub fn canonicalize(checks: &[Check]) -> Vec<Check> {
+    let mut sorted = checks.to_vec();
+    sorted.sort();
+    sorted.dedup();
+    sorted
+}
+
+/// Whether `program` is well-formed program bytes whose checks are in canonical order with no
+/// duplicates, ie encoding the checks after [`canonicalize`] gives `program` back.
+pub fn is_canonical(program: &[u8]) -> bool {
+    let mut r = ByteReader::new(program);
+    let mut checks = Vec::new();
+    while !r.is_empty() {
+        match Check::decode_from(&mut r) {
+            Ok(check) => checks.push(check),
+            Err(_) => return false,
+        }
+    }
+    let mut canonical = Vec::with_capacity(program.len());
+    for check in canonicalize(&checks) {
+        check.encode_into(&mut canonical);
+    }
+    canonical == program
+}
@@ -0,0 +1,405 @@
+//! Compact Borsh serialization of check programs and envelopes (feature `borsh`).
+//!
+//! Meant for message buses and archives, where a self-describing structured form beats re-parsing
+//! the policy's byte layout. It is not what the policy reads or what envelope signatures cover;
+//! `fiet_maker_policy_encoder::encoder::compact` converts to and from the canonical policy bytes.
+//!
+//! Layout follows Borsh conventions (little-endian integers, `u32` length prefixes), with two
+//! choices fixed here so the encoding stays stable as opcodes are added:
+//!
+//! - a [`Check`] is tagged with its [`Opcode`] byte rather than its variant index;
+//! - `U256` values are 32 little-endian bytes, addresses and hashes their raw bytes.
+
+#![cfg(feature = "borsh")]
+
+use alloc::vec::Vec;
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use borsh::io::{Error, ErrorKind, Read, Result, Write};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::opcodes::{Check, CompOp, Opcode, UserOpField};
+
+/// A policy envelope as carried in the signature slice, with its program decoded.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct CompactEnvelope {
+    pub version: u16,
+    #[borsh(serialize_with = "ser_u256", deserialize_with = "de_u256")]
+    pub nonce: U256,
+    pub deadline: u64,
+    #[borsh(serialize_with = "ser_b32", deserialize_with = "de_b32")]
+    pub call_bundle_hash: FixedBytes<32>,
+    pub program: Vec<Check>,
+    /// ECDSA signature (r||s||v), or an encoded WebAuthn assertion for passkey installs.
+    pub signature: Vec<u8>,
+}
+
+impl BorshSerialize for CompOp {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let tag: u8 = match self {
+            CompOp::Lt => 0,
+            CompOp::Lte => 1,
+            CompOp::Gt => 2,
+            CompOp::Gte => 3,
+            CompOp::Eq => 4,
+            CompOp::Neq => 5,
+        };
+        tag.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for CompOp {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(match u8::deserialize_reader(reader)? {
+            0 => CompOp::Lt,
+            1 => CompOp::Lte,
+            2 => CompOp::Gt,
+            3 => CompOp::Gte,
+            4 => CompOp::Eq,
+            5 => CompOp::Neq,
+            _ => return Err(Error::new(ErrorKind::InvalidData, "unknown comparison operator")),
+        })
+    }
+}
+
+impl BorshSerialize for UserOpField {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.code().serialize(writer)
+    }
+}
+
+impl BorshDeserialize for UserOpField {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        UserOpField::try_from(u8::deserialize_reader(reader)?)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "unknown UserOperation field"))
+    }
+}
+
+impl BorshSerialize for Check {
+    fn serialize<W: Write>(&self, w: &mut W) -> Result<()> {
+        match self {
+            Check::Deadline { deadline } => {
+                tag(w, Opcode::CheckDeadline)?;
+                deadline.serialize(w)
+            }
+            Check::Nonce { expected } => {
+                tag(w, Opcode::CheckNonce)?;
+                ser_u256(expected, w)
+            }
+            Check::CallBundleHash { hash } => {
+                tag(w, Opcode::CheckCallBundleHash)?;
+                ser_b32(hash, w)
+            }
+            Check::UserOpField { field, op, rhs } => {
+                tag(w, Opcode::CheckUserOpField)?;
+                field.serialize(w)?;
+                op.serialize(w)?;
+                ser_u256(rhs, w)
+            }
+            Check::TokenAmountLte { token, max } => {
+                tag(w, Opcode::CheckTokenAmountLte)?;
+                ser_address(token, w)?;
+                ser_u256(max, w)
+            }
+            Check::NativeValueLte { max } => {
+                tag(w, Opcode::CheckNativeValueLte)?;
+                ser_u256(max, w)
+            }
+            Check::LiquidityDeltaLte { max } => {
+                tag(w, Opcode::CheckLiquidityDeltaLte)?;
+                max.serialize(w)
+            }
+            Check::WalletTokenDeltaLte { token, max_out } => {
+                tag(w, Opcode::CheckWalletTokenDeltaLte)?;
+                ser_address(token, w)?;
+                ser_u256(max_out, w)
+            }
+            Check::WindowSpendLte { token, window, max } => {
+                tag(w, Opcode::CheckWindowSpendLte)?;
+                ser_address(token, w)?;
+                window.serialize(w)?;
+                ser_u256(max, w)
+            }
+            Check::Slot0TickBounds { pool_id, min, max } => {
+                tag(w, Opcode::CheckSlot0TickBounds)?;
+                ser_b32(pool_id, w)?;
+                min.serialize(w)?;
+                max.serialize(w)
+            }
+            Check::Slot0SqrtPriceBounds { pool_id, min, max } => {
+                tag(w, Opcode::CheckSlot0SqrtPriceBounds)?;
+                ser_b32(pool_id, w)?;
+                ser_u256(min, w)?;
+                ser_u256(max, w)
+            }
+            Check::PoolAllowed { pool_id } => {
+                tag(w, Opcode::CheckPoolAllowed)?;
+                ser_b32(pool_id, w)
+            }
+            Check::TwapTickBounds {
+                pool_id,
+                window,
+                min,
+                max,
+            } => {
+                tag(w, Opcode::CheckTwapTickBounds)?;
+                ser_b32(pool_id, w)?;
+                window.serialize(w)?;
+                min.serialize(w)?;
+                max.serialize(w)
+            }
+            Check::OracleDeviationLte {
+                pool_id,
+                oracle,
+                selector,
+                decimals,
+                max_bps,
+            } => {
+                tag(w, Opcode::CheckOracleDeviationLte)?;
+                ser_b32(pool_id, w)?;
+                ser_address(oracle, w)?;
+                selector.serialize(w)?;
+                decimals.serialize(w)?;
+                max_bps.serialize(w)
+            }
+            Check::RfsClosed { position_id } => {
+                tag(w, Opcode::CheckRfsClosed)?;
+                ser_b32(position_id, w)
+            }
+            Check::QueueLte { lcc, owner, max } => {
+                tag(w, Opcode::CheckQueueLte)?;
+                ser_address(lcc, w)?;
+                ser_address(owner, w)?;
+                ser_u256(max, w)
+            }
+            Check::ReserveGte { lcc, min } => {
+                tag(w, Opcode::CheckReserveGte)?;
+                ser_address(lcc, w)?;
+                ser_u256(min, w)
+            }
+            Check::SettledGte {
+                position_id,
+                min_amount0,
+                min_amount1,
+            } => {
+                tag(w, Opcode::CheckSettledGte)?;
+                ser_b32(position_id, w)?;
+                ser_u256(min_amount0, w)?;
+                ser_u256(min_amount1, w)
+            }
+            Check::CommitmentDeficitLte {
+                position_id,
+                max_deficit0,
+                max_deficit1,
+            } => {
+                tag(w, Opcode::CheckCommitmentDeficitLte)?;
+                ser_b32(position_id, w)?;
+                ser_u256(max_deficit0, w)?;
+                ser_u256(max_deficit1, w)
+            }
+            Check::GracePeriodGte {
+                position_id,
+                min_seconds,
+            } => {
+                tag(w, Opcode::CheckGracePeriodGte)?;
+                ser_b32(position_id, w)?;
+                min_seconds.serialize(w)
+            }
+            Check::HubSolvency { lcc, min_ratio_bps } => {
+                tag(w, Opcode::CheckHubSolvency)?;
+                ser_address(lcc, w)?;
+                min_ratio_bps.serialize(w)
+            }
+            Check::QueueSumLte { lcc, owners, max } => {
+                tag(w, Opcode::CheckQueueSumLte)?;
+                ser_address(lcc, w)?;
+                (owners.len() as u32).serialize(w)?;
+                for owner in owners {
+                    ser_address(owner, w)?;
+                }
+                ser_u256(max, w)
+            }
+            Check::StaticCallU256 {
+                target,
+                selector,
+                args,
+                op,
+                rhs,
+            } => {
+                tag(w, Opcode::CheckStaticCallU256)?;
+                ser_address(target, w)?;
+                selector.serialize(w)?;
+                args.serialize(w)?;
+                op.serialize(w)?;
+                ser_u256(rhs, w)
+            }
+            Check::StaticCallCompare {
+                lhs_target,
+                lhs_selector,
+                lhs_args,
+                op,
+                rhs_target,
+                rhs_selector,
+                rhs_args,
+                scale_bps,
+            } => {
+                tag(w, Opcode::CheckStaticCallCompare)?;
+                ser_address(lhs_target, w)?;
+                lhs_selector.serialize(w)?;
+                lhs_args.serialize(w)?;
+                op.serialize(w)?;
+                ser_address(rhs_target, w)?;
+                rhs_selector.serialize(w)?;
+                rhs_args.serialize(w)?;
+                scale_bps.serialize(w)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for Check {
+    fn deserialize_reader<R: Read>(r: &mut R) -> Result<Self> {
+        let opcode = Opcode::try_from(u8::deserialize_reader(r)?)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "unknown check opcode"))?;
+        Ok(match opcode {
+            Opcode::CheckDeadline => Check::Deadline {
+                deadline: u64::deserialize_reader(r)?,
+            },
+            Opcode::CheckNonce => Check::Nonce {
+                expected: de_u256(r)?,
+            },
+            Opcode::CheckCallBundleHash => Check::CallBundleHash { hash: de_b32(r)? },
+            Opcode::CheckUserOpField => Check::UserOpField {
+                field: UserOpField::deserialize_reader(r)?,
+                op: CompOp::deserialize_reader(r)?,
+                rhs: de_u256(r)?,
+            },
+            Opcode::CheckTokenAmountLte => Check::TokenAmountLte {
+                token: de_address(r)?,
+                max: de_u256(r)?,
+            },
+            Opcode::CheckNativeValueLte => Check::NativeValueLte { max: de_u256(r)? },
+            Opcode::CheckLiquidityDeltaLte => Check::LiquidityDeltaLte {
+                max: u128::deserialize_reader(r)?,
+            },
+            Opcode::CheckWalletTokenDeltaLte => Check::WalletTokenDeltaLte {
+                token: de_address(r)?,
+                max_out: de_u256(r)?,
+            },
+            Opcode::CheckWindowSpendLte => Check::WindowSpendLte {
+                token: de_address(r)?,
+                window: u32::deserialize_reader(r)?,
+                max: de_u256(r)?,
+            },
+            Opcode::CheckSlot0TickBounds => Check::Slot0TickBounds {
+                pool_id: de_b32(r)?,
+                min: i32::deserialize_reader(r)?,
+                max: i32::deserialize_reader(r)?,
+            },
+            Opcode::CheckSlot0SqrtPriceBounds => Check::Slot0SqrtPriceBounds {
+                pool_id: de_b32(r)?,
+                min: de_u256(r)?,
+                max: de_u256(r)?,
+            },
+            Opcode::CheckPoolAllowed => Check::PoolAllowed {
+                pool_id: de_b32(r)?,
+            },
+            Opcode::CheckTwapTickBounds => Check::TwapTickBounds {
+                pool_id: de_b32(r)?,
+                window: u32::deserialize_reader(r)?,
+                min: i32::deserialize_reader(r)?,
+                max: i32::deserialize_reader(r)?,
+            },
+            Opcode::CheckOracleDeviationLte => Check::OracleDeviationLte {
+                pool_id: de_b32(r)?,
+                oracle: de_address(r)?,
+                selector: <[u8; 4]>::deserialize_reader(r)?,
+                decimals: u8::deserialize_reader(r)?,
+                max_bps: u32::deserialize_reader(r)?,
+            },
+            Opcode::CheckRfsClosed => Check::RfsClosed {
+                position_id: de_b32(r)?,
+            },
+            Opcode::CheckQueueLte => Check::QueueLte {
+                lcc: de_address(r)?,
+                owner: de_address(r)?,
+                max: de_u256(r)?,
+            },
+            Opcode::CheckReserveGte => Check::ReserveGte {
+                lcc: de_address(r)?,
+                min: de_u256(r)?,
+            },
+            Opcode::CheckSettledGte => Check::SettledGte {
+                position_id: de_b32(r)?,
+                min_amount0: de_u256(r)?,
+                min_amount1: de_u256(r)?,
+            },
+            Opcode::CheckCommitmentDeficitLte => Check::CommitmentDeficitLte {
+                position_id: de_b32(r)?,
+                max_deficit0: de_u256(r)?,
+                max_deficit1: de_u256(r)?,
+            },
+            Opcode::CheckGracePeriodGte => Check::GracePeriodGte {
+                position_id: de_b32(r)?,
+                min_seconds: u64::deserialize_reader(r)?,
+            },
+            Opcode::CheckHubSolvency => Check::HubSolvency {
+                lcc: de_address(r)?,
+                min_ratio_bps: u32::deserialize_reader(r)?,
+            },
+            Opcode::CheckQueueSumLte => Check::QueueSumLte {
+                lcc: de_address(r)?,
+                owners: {
+                    let len = u32::deserialize_reader(r)?;
+                    (0..len).map(|_| de_address(r)).collect::<Result<Vec<_>>>()?
+                },
+                max: de_u256(r)?,
+            },
+            Opcode::CheckStaticCallU256 => Check::StaticCallU256 {
+                target: de_address(r)?,
+                selector: <[u8; 4]>::deserialize_reader(r)?,
+                args: Vec::<u8>::deserialize_reader(r)?,
+                op: CompOp::deserialize_reader(r)?,
+                rhs: de_u256(r)?,
+            },
+            Opcode::CheckStaticCallCompare => Check::StaticCallCompare {
+                lhs_target: de_address(r)?,
+                lhs_selector: <[u8; 4]>::deserialize_reader(r)?,
+                lhs_args: Vec::<u8>::deserialize_reader(r)?,
+                op: CompOp::deserialize_reader(r)?,
+                rhs_target: de_address(r)?,
+                rhs_selector: <[u8; 4]>::deserialize_reader(r)?,
+                rhs_args: Vec::<u8>::deserialize_reader(r)?,
+                scale_bps: u32::deserialize_reader(r)?,
+            },
+        })
+    }
+}
+
+fn tag<W: Write>(w: &mut W, opcode: Opcode) -> Result<()> {
+    (opcode as u8).serialize(w)
+}
+
+fn ser_u256<W: Write>(value: &U256, w: &mut W) -> Result<()> {
+    w.write_all(&value.to_le_bytes::<32>())
+}
+
+fn de_u256<R: Read>(r: &mut R) -> Result<U256> {
+    Ok(U256::from_le_bytes(<[u8; 32]>::deserialize_reader(r)?))
+}
+
+fn ser_b32<W: Write>(value: &FixedBytes<32>, w: &mut W) -> Result<()> {
+    w.write_all(value.as_slice())
+}
+
+fn de_b32<R: Read>(r: &mut R) -> Result<FixedBytes<32>> {
+    Ok(FixedBytes(<[u8; 32]>::deserialize_reader(r)?))
+}
+
+fn ser_address<W: Write>(value: &Address, w: &mut W) -> Result<()> {
+    w.write_all(value.as_slice())
+}
+
+fn de_address<R: Read>(r: &mut R) -> Result<Address> {
+    Ok(Address::from(<[u8; 20]>::deserialize_reader(r)?))
+}
@@ -1,20 +1,47 @@
-use alloy_primitives::{Address, FixedBytes, U256};
+use alloc::vec::Vec;
+
+use alloy_primitives::{Address, FixedBytes, U256, U512};
+
+use crate::opcodes::UserOpField;
 
 /// Errors during fact acquisition.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")
+)]
 pub enum FactsError {
     /// Used by off-chain mocks or partially implemented providers.
     NotImplemented,
     /// Attempted to `staticcall` a target/selector that is not allowlisted.
-    ForbiddenCall { target: Address, selector: [u8; 4] },
+    ForbiddenCall {
+        target: Address,
+        #[cfg_attr(feature = "serde", serde(with = "crate::opcodes::hex_bytes"))]
+        selector: [u8; 4],
+    },
     /// The underlying call failed.
     CallFailed,
     /// Return data was malformed or could not be decoded.
     MalformedReturn,
+    /// A fact source's `extcodehash` differs from the one pinned at install.
+    CodehashMismatch { target: Address },
+}
+
+/// Which of a permission's configured fact sources a check reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum FactSource {
+    StateView,
+    VtsOrchestrator,
+    LiquidityHub,
 }
 
 /// Slot0 snapshot for Uniswap v4 pool.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct Slot0 {
     pub sqrt_price_x96: U256,
     pub tick: i32,
@@ -22,14 +49,56 @@ pub struct Slot0 {
     pub lp_fee: u32,
 }
 
+/// The fields of the UserOperation being validated that `CheckUserOpField` reads, gas words still
+/// packed as in `PackedUserOperation`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct UserOpFields {
+    pub sender: Address,
+    pub nonce: U256,
+    /// `verificationGasLimit (uint128) || callGasLimit (uint128)`.
+    pub account_gas_limits: FixedBytes<32>,
+    pub pre_verification_gas: U256,
+    /// `maxPriorityFeePerGas (uint128) || maxFeePerGas (uint128)`.
+    pub gas_fees: FixedBytes<32>,
+}
+
+impl UserOpFields {
+    /// `field` as a `uint256`.
+    pub fn get(&self, field: UserOpField) -> U256 {
+        let high = |word: &FixedBytes<32>| U256::from_be_slice(&word[..16]);
+        let low = |word: &FixedBytes<32>| U256::from_be_slice(&word[16..]);
+        match field {
+            UserOpField::Sender => U256::from_be_slice(self.sender.as_slice()),
+            UserOpField::Nonce => self.nonce,
+            UserOpField::VerificationGasLimit => high(&self.account_gas_limits),
+            UserOpField::CallGasLimit => low(&self.account_gas_limits),
+            UserOpField::PreVerificationGas => self.pre_verification_gas,
+            UserOpField::MaxPriorityFeePerGas => high(&self.gas_fees),
+            UserOpField::MaxFeePerGas => low(&self.gas_fees),
+        }
+    }
+}
+
 /// Facts provider abstraction, implemented differently on-chain vs off-chain.
 pub trait FactsProvider {
     fn block_timestamp(&self) -> u64;
 
+    /// A field of the UserOperation being validated.
+    fn user_op_field(&self, _field: UserOpField) -> Result<U256, FactsError> {
+        Err(FactsError::NotImplemented)
+    }
+
     fn get_slot0(&self, _pool_id: FixedBytes<32>) -> Result<Slot0, FactsError> {
         Err(FactsError::NotImplemented)
     }
 
+    /// Time-weighted average tick of a pool over the trailing `window` seconds.
+    fn twap_tick(&self, _pool_id: FixedBytes<32>, _window: u32) -> Result<i32, FactsError> {
+        Err(FactsError::NotImplemented)
+    }
+
     fn is_rfs_closed(&self, _position_id: FixedBytes<32>) -> Result<bool, FactsError> {
         Err(FactsError::NotImplemented)
     }
@@ -42,6 +111,11 @@ pub trait FactsProvider {
         Err(FactsError::NotImplemented)
     }
 
+    /// Settlement queue of the `lcc`'s underlying, across every LCC that shares it.
+    fn queue_of_underlying(&self, _lcc: Address) -> Result<U256, FactsError> {
+        Err(FactsError::NotImplemented)
+    }
+
     /// Get settled amounts for a position (amount0, amount1).
     fn get_settled_amounts(
         &self,
@@ -74,3 +148,100 @@ pub trait FactsProvider {
     }
 }
 
+/// Signature of the StateView oracle getter backing [`FactsProvider::twap_tick`]; it returns
+/// `(int56[] tickCumulatives, uint160[] secondsPerLiquidityCumulativeX128s)`.
+pub const OBSERVE_SIG: &str = "observe(bytes32,uint32[])";
+
+/// `observe` arguments asking for the tick cumulatives `window` seconds ago and now.
+pub fn observe_args(pool_id: FixedBytes<32>, window: u32) -> Vec<u8> {
+    let mut args = Vec::with_capacity(32 * 5);
+    args.extend_from_slice(pool_id.as_slice());
+    args.extend_from_slice(&U256::from(0x40u64).to_be_bytes::<32>());
+    args.extend_from_slice(&U256::from(2u64).to_be_bytes::<32>());
+    args.extend_from_slice(&U256::from(window).to_be_bytes::<32>());
+    args.extend_from_slice(&[0u8; 32]);
+    args
+}
+
+/// Average tick from the return of an [`observe_args`] call, rounded towards negative infinity as
+/// Uniswap's `OracleLibrary.consult` does.
+pub fn twap_tick_from_observe(out: &[u8], window: u32) -> Result<i32, FactsError> {
+    if window == 0 {
+        return Err(FactsError::MalformedReturn);
+    }
+    let word = |index: usize| -> Result<&[u8], FactsError> {
+        out.get(32 * index..32 * (index + 1)).ok_or(FactsError::MalformedReturn)
+    };
+    let offset = U256::from_be_slice(word(0)?);
+    if offset % U256::from(32u64) != U256::ZERO || offset > U256::from(out.len()) {
+        return Err(FactsError::MalformedReturn);
+    }
+    let start = offset.to::<usize>() / 32;
+    if U256::from_be_slice(word(start)?) < U256::from(2u64) {
+        return Err(FactsError::MalformedReturn);
+    }
+    let then = int56_word(word(start + 1)?)?;
+    let now = int56_word(word(start + 2)?)?;
+
+    let delta = now - then;
+    let window = i64::from(window);
+    let mut tick = delta / window;
+    if delta < 0 && delta % window != 0 {
+        tick -= 1;
+    }
+    i32::try_from(tick).map_err(|_| FactsError::MalformedReturn)
+}
+
+/// A sign-extended `int56` ABI word.
+fn int56_word(word: &[u8]) -> Result<i64, FactsError> {
+    let value = i64::from_be_bytes(word[24..32].try_into().unwrap());
+    let extension = if value < 0 { 0xff } else { 0x00 };
+    let in_range = (-(1i64 << 55)..1i64 << 55).contains(&value);
+    if !in_range || word[..24].iter().any(|byte| *byte != extension) {
+        return Err(FactsError::MalformedReturn);
+    }
+    Ok(value)
+}
+
+/// `1e18`, the fixed-point scale prices are compared in.
+pub const WAD: u64 = 1_000_000_000_000_000_000;
+
+/// Price of token0 in token1 (raw units) as an 18-decimal fixed point, from `sqrtPriceX96`;
+/// `None` if it is not a `uint160` or the price does not fit.
+pub fn sqrt_price_x96_to_wad(sqrt_price_x96: U256) -> Option<U256> {
+    if sqrt_price_x96.bit_len() > 160 {
+        return None;
+    }
+    let sqrt = U512::from(sqrt_price_x96);
+    let wad: U512 = (sqrt * sqrt * U512::from(WAD)) >> 192;
+    (wad.bit_len() <= 256).then(|| U256::from_limbs_slice(&wad.as_limbs()[..4]))
+}
+
+/// Rescale a `decimals`-decimal fixed-point value to 18 decimals; `None` on overflow.
+pub fn to_wad(value: U256, decimals: u8) -> Option<U256> {
+    if decimals <= 18 {
+        let factor = U256::from(10u64).checked_pow(U256::from(18 - decimals))?;
+        value.checked_mul(factor)
+    } else {
+        let factor = U256::from(10u64).checked_pow(U256::from(decimals - 18))?;
+        Some(value / factor)
+    }
+}
+
+/// `|price - reference| * 10_000 / reference`; `None` for a zero reference.
+pub fn deviation_bps(price: U256, reference: U256) -> Option<U256> {
+    if reference == U256::ZERO {
+        return None;
+    }
+    let diff = if price > reference { price - reference } else { reference - price };
+    Some(diff.saturating_mul(U256::from(10_000u64)) / reference)
+}
+
+/// `reserve * 10_000 / queue`, saturating; `None` for an empty queue, which any reserve covers.
+pub fn solvency_ratio_bps(reserve: U256, queue: U256) -> Option<U256> {
+    if queue == U256::ZERO {
+        return None;
+    }
+    let ratio = U512::from(reserve) * U512::from(10_000u64) / U512::from(queue);
+    Some(if ratio.bit_len() <= 256 { U256::from_limbs_slice(&ratio.as_limbs()[..4]) } else { U256::MAX })
+}
@@ -0,0 +1,191 @@
+//! `arbitrary::Arbitrary` implementations for fuzz targets and property tests (feature
+//! `arbitrary`).
+//!
+//! Generated values always fit the program format: `StaticCallU256` args stay within their `u16`
+//! length prefix, `QueueSumLte` owner lists within their bound and envelope signatures are 65
+//! bytes. Program length is left to the caller, since the decoders apply the check cap
+//! (`MAX_CHECKS`).
+
+#![cfg(feature = "arbitrary")]
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::opcodes::{Check, CompOp, UserOpField, MAX_QUEUE_SUM_OWNERS};
+
+impl<'a> Arbitrary<'a> for CompOp {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[
+            CompOp::Lt,
+            CompOp::Lte,
+            CompOp::Gt,
+            CompOp::Gte,
+            CompOp::Eq,
+            CompOp::Neq,
+        ])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for UserOpField {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[
+            UserOpField::Sender,
+            UserOpField::Nonce,
+            UserOpField::VerificationGasLimit,
+            UserOpField::CallGasLimit,
+            UserOpField::PreVerificationGas,
+            UserOpField::MaxPriorityFeePerGas,
+            UserOpField::MaxFeePerGas,
+        ])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Check {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=23u8)? {
+            0 => Check::Deadline {
+                deadline: u.arbitrary()?,
+            },
+            1 => Check::Nonce {
+                expected: u256(u)?,
+            },
+            2 => Check::CallBundleHash { hash: b32(u)? },
+            3 => Check::TokenAmountLte {
+                token: address(u)?,
+                max: u256(u)?,
+            },
+            4 => Check::NativeValueLte { max: u256(u)? },
+            5 => Check::LiquidityDeltaLte {
+                max: u.arbitrary()?,
+            },
+            6 => Check::Slot0TickBounds {
+                pool_id: b32(u)?,
+                min: u.arbitrary()?,
+                max: u.arbitrary()?,
+            },
+            7 => Check::Slot0SqrtPriceBounds {
+                pool_id: b32(u)?,
+                min: u256(u)?,
+                max: u256(u)?,
+            },
+            8 => Check::RfsClosed {
+                position_id: b32(u)?,
+            },
+            9 => Check::QueueLte {
+                lcc: address(u)?,
+                owner: address(u)?,
+                max: u256(u)?,
+            },
+            10 => Check::ReserveGte {
+                lcc: address(u)?,
+                min: u256(u)?,
+            },
+            11 => Check::SettledGte {
+                position_id: b32(u)?,
+                min_amount0: u256(u)?,
+                min_amount1: u256(u)?,
+            },
+            12 => Check::CommitmentDeficitLte {
+                position_id: b32(u)?,
+                max_deficit0: u256(u)?,
+                max_deficit1: u256(u)?,
+            },
+            13 => Check::GracePeriodGte {
+                position_id: b32(u)?,
+                min_seconds: u.arbitrary()?,
+            },
+            14 => Check::WalletTokenDeltaLte {
+                token: address(u)?,
+                max_out: u256(u)?,
+            },
+            15 => Check::WindowSpendLte {
+                token: address(u)?,
+                window: u.arbitrary()?,
+                max: u256(u)?,
+            },
+            16 => Check::PoolAllowed { pool_id: b32(u)? },
+            18 => Check::TwapTickBounds {
+                pool_id: b32(u)?,
+                window: u.arbitrary()?,
+                min: u.arbitrary()?,
+                max: u.arbitrary()?,
+            },
+            19 => Check::OracleDeviationLte {
+                pool_id: b32(u)?,
+                oracle: address(u)?,
+                selector: u.arbitrary()?,
+                decimals: u.arbitrary()?,
+                max_bps: u.arbitrary()?,
+            },
+            20 => Check::UserOpField {
+                field: u.arbitrary()?,
+                op: u.arbitrary()?,
+                rhs: u256(u)?,
+            },
+            21 => Check::HubSolvency {
+                lcc: address(u)?,
+                min_ratio_bps: u.arbitrary()?,
+            },
+            22 => {
+                let len = u.int_in_range(1..=MAX_QUEUE_SUM_OWNERS)?;
+                Check::QueueSumLte {
+                    lcc: address(u)?,
+                    owners: (0..len).map(|_| address(u)).collect::<Result<_>>()?,
+                    max: u256(u)?,
+                }
+            }
+            17 => {
+                let lhs_len = u.int_in_range(0..=u16::MAX as usize)?;
+                let lhs_args = u.bytes(lhs_len)?.to_vec();
+                let rhs_len = u.int_in_range(0..=u16::MAX as usize)?;
+                Check::StaticCallCompare {
+                    lhs_target: address(u)?,
+                    lhs_selector: u.arbitrary()?,
+                    lhs_args,
+                    op: u.arbitrary()?,
+                    rhs_target: address(u)?,
+                    rhs_selector: u.arbitrary()?,
+                    rhs_args: u.bytes(rhs_len)?.to_vec(),
+                    scale_bps: u.arbitrary()?,
+                }
+            }
+            _ => {
+                // Args are length-prefixed with a u16.
+                let args_len = u.int_in_range(0..=u16::MAX as usize)?;
+                Check::StaticCallU256 {
+                    target: address(u)?,
+                    selector: u.arbitrary()?,
+                    args: u.bytes(args_len)?.to_vec(),
+                    op: u.arbitrary()?,
+                    rhs: u256(u)?,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<'a> Arbitrary<'a> for crate::compact::CompactEnvelope {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            version: u.arbitrary()?,
+            nonce: u256(u)?,
+            deadline: u.arbitrary()?,
+            call_bundle_hash: b32(u)?,
+            program: u.arbitrary()?,
+            signature: u.arbitrary::<[u8; 65]>()?.to_vec(),
+        })
+    }
+}
+
+fn u256(u: &mut Unstructured) -> Result<U256> {
+    Ok(U256::from_be_bytes::<32>(u.arbitrary()?))
+}
+
+fn b32(u: &mut Unstructured) -> Result<FixedBytes<32>> {
+    Ok(FixedBytes(u.arbitrary()?))
+}
+
+fn address(u: &mut Unstructured) -> Result<Address> {
+    Ok(Address::from(u.arbitrary::<[u8; 20]>()?))
+}
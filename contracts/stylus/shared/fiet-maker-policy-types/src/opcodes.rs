@@ -1,9 +1,16 @@
 use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
 
-use alloy_primitives::{Address, FixedBytes, U256};
+use alloy_primitives::{keccak256, Address, FixedBytes, U256};
 
-/// Comparison operators for numeric checks.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use crate::bytes::{ByteReader, UnexpectedEnd};
+use crate::facts::FactSource;
+
+/// Comparison operators for numeric checks. Ordered by their wire byte ([`CompOp::code`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub enum CompOp {
     Lt,
     Lte,
@@ -13,20 +20,47 @@ pub enum CompOp {
     Neq,
 }
 
-/// Opcodes supported by the v0 check program.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// `PackedUserOperation` fields `CheckUserOpField` compares, with the packed gas words unpacked.
+/// Ordered by their wire byte ([`UserOpField::code`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum UserOpField {
+    /// The account, as a `uint160`.
+    Sender,
+    Nonce,
+    /// High 128 bits of `accountGasLimits`.
+    VerificationGasLimit,
+    /// Low 128 bits of `accountGasLimits`.
+    CallGasLimit,
+    PreVerificationGas,
+    /// High 128 bits of `gasFees`.
+    MaxPriorityFeePerGas,
+    /// Low 128 bits of `gasFees`.
+    MaxFeePerGas,
+}
+
+/// Opcodes supported by the v0 check program. Ordered by their byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Opcode {
     CheckDeadline = 0x01,
     CheckNonce = 0x02,
     CheckCallBundleHash = 0x03,
+    CheckUserOpField = 0x04,
 
     CheckTokenAmountLte = 0x11,
     CheckNativeValueLte = 0x12,
     CheckLiquidityDeltaLte = 0x13,
+    CheckWalletTokenDeltaLte = 0x14,
+    CheckWindowSpendLte = 0x15,
 
     CheckSlot0TickBounds = 0x20,
     CheckSlot0SqrtPriceBounds = 0x21,
+    CheckPoolAllowed = 0x22,
+    CheckTwapTickBounds = 0x23,
+    CheckOracleDeviationLte = 0x24,
 
     CheckRfsClosed = 0x30,
     CheckQueueLte = 0x31,
@@ -34,20 +68,77 @@ pub enum Opcode {
     CheckSettledGte = 0x33,
     CheckCommitmentDeficitLte = 0x34,
     CheckGracePeriodGte = 0x35,
+    CheckHubSolvency = 0x36,
+    CheckQueueSumLte = 0x37,
 
     CheckStaticCallU256 = 0xF0,
+    CheckStaticCallCompare = 0xF1,
+}
+
+/// Most checks a program may hold.
+pub const MAX_CHECKS: usize = 64;
+
+/// Longest program accepted, in bytes. Bounds the work spent on an envelope independently of the
+/// check cap, since static-call arguments make a single check up to 64 KiB.
+pub const MAX_PROGRAM_LEN: usize = 16 * 1024;
+
+/// Most owners a `CheckQueueSumLte` may sum over.
+pub const MAX_QUEUE_SUM_OWNERS: usize = 16;
+
+/// Cap on the distinct contracts a program calls out to during validation (see
+/// [`routed_call_target_count`]).
+///
+/// Fixed for every install: the policy applies it to each envelope whatever the permission's
+/// config, so a program that fits is valid under any install of the same deployment.
+pub const MAX_CALL_TARGETS: usize = 8;
+
+/// `scale_bps` leaving the right-hand side of `CheckStaticCallCompare` unscaled.
+pub const SCALE_BPS_ONE: u32 = 10_000;
+
+/// `value * bps / 10_000`, or `None` on overflow.
+pub fn scale_bps(value: U256, bps: u32) -> Option<U256> {
+    value
+        .checked_mul(U256::from(bps))
+        .map(|scaled| scaled / U256::from(SCALE_BPS_ONE))
 }
 
 /// Decoded representation of a single check.
+///
+/// With the `serde` feature a check is a JSON object tagged by `kind`, with camelCase names
+/// (`{"kind": "slot0TickBounds", "poolId": "0x..", "min": -60, "max": 60}`). Addresses, `U256`
+/// values, the `u128` liquidity cap and byte strings are `0x` hex; the integers also accept decimal
+/// strings.
+///
+/// Checks order and hash by their program encoding, tagged by the opcode byte rather than the
+/// variant's position, so both are stable as opcodes are added: sorting orders by opcode, then by
+/// the fields as encoded (the order [`canonicalize`](crate::canonical::canonicalize) uses), and
+/// hashes can be persisted.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")
+)]
 pub enum Check {
     Deadline { deadline: u64 },
     Nonce { expected: U256 },
     CallBundleHash { hash: FixedBytes<32> },
+    /// A field of the UserOperation being validated compared as `field op rhs`.
+    UserOpField { field: UserOpField, op: CompOp, rhs: U256 },
 
     TokenAmountLte { token: Address, max: U256 },
     NativeValueLte { max: U256 },
-    LiquidityDeltaLte { max: u128 },
+    LiquidityDeltaLte {
+        #[cfg_attr(feature = "serde", serde(with = "u128_hex"))]
+        max: u128,
+    },
+    /// Bound the wallet's net outflow of `token` across execution: the policy records
+    /// `balanceOf(wallet)` during validation and its paired hook reverts if the balance after
+    /// execution fell by more than `max_out`.
+    WalletTokenDeltaLte { token: Address, max_out: U256 },
+    /// Bound the wallet's outflow of `token` under this permission over the trailing `window`
+    /// seconds. Measured like `WalletTokenDeltaLte` and accumulated in time buckets by the hook.
+    WindowSpendLte { token: Address, window: u32, max: U256 },
 
     Slot0TickBounds {
         pool_id: FixedBytes<32>,
@@ -59,6 +150,26 @@ pub enum Check {
         min: U256,
         max: U256,
     },
+    /// The pool the intent declares it is for is on the permission's install-time pool allowlist,
+    /// as is every pool the call bundle passes.
+    PoolAllowed { pool_id: FixedBytes<32> },
+    /// Time-weighted average tick over the trailing `window` seconds within `[min, max]`.
+    TwapTickBounds {
+        pool_id: FixedBytes<32>,
+        window: u32,
+        min: i32,
+        max: i32,
+    },
+    /// Spot price (from `sqrtPriceX96`) within `max_bps` of the `decimals`-decimal price returned
+    /// by `oracle.staticcall(selector)`, an oracle call allowlisted at install.
+    OracleDeviationLte {
+        pool_id: FixedBytes<32>,
+        oracle: Address,
+        #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+        selector: [u8; 4],
+        decimals: u8,
+        max_bps: u32,
+    },
 
     RfsClosed { position_id: FixedBytes<32> },
     QueueLte { lcc: Address, owner: Address, max: U256 },
@@ -77,14 +188,37 @@ pub enum Check {
         position_id: FixedBytes<32>,
         min_seconds: u64,
     },
+    /// The hub's reserve of the `lcc`'s underlying covers at least `min_ratio_bps` of the
+    /// settlement queue of that underlying: `reserve * 10_000 >= queue * min_ratio_bps`.
+    HubSolvency { lcc: Address, min_ratio_bps: u32 },
+    /// `settleQueue[lcc][owner]` summed over `owners` (1 to [`MAX_QUEUE_SUM_OWNERS`]) is at most
+    /// `max`.
+    QueueSumLte { lcc: Address, owners: Vec<Address>, max: U256 },
 
     StaticCallU256 {
         target: Address,
+        #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
         selector: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
         args: Vec<u8>,
         op: CompOp,
         rhs: U256,
     },
+    /// Two allowlisted `uint256` staticcalls compared as `lhs op rhs * scale_bps / 10_000`.
+    StaticCallCompare {
+        lhs_target: Address,
+        #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+        lhs_selector: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+        lhs_args: Vec<u8>,
+        op: CompOp,
+        rhs_target: Address,
+        #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+        rhs_selector: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+        rhs_args: Vec<u8>,
+        scale_bps: u32,
+    },
 }
 
 impl TryFrom<u8> for Opcode {
@@ -96,21 +230,893 @@ impl TryFrom<u8> for Opcode {
             0x01 => CheckDeadline,
             0x02 => CheckNonce,
             0x03 => CheckCallBundleHash,
+            0x04 => CheckUserOpField,
             0x11 => CheckTokenAmountLte,
             0x12 => CheckNativeValueLte,
             0x13 => CheckLiquidityDeltaLte,
+            0x14 => CheckWalletTokenDeltaLte,
+            0x15 => CheckWindowSpendLte,
             0x20 => CheckSlot0TickBounds,
             0x21 => CheckSlot0SqrtPriceBounds,
+            0x22 => CheckPoolAllowed,
+            0x23 => CheckTwapTickBounds,
+            0x24 => CheckOracleDeviationLte,
             0x30 => CheckRfsClosed,
             0x31 => CheckQueueLte,
             0x32 => CheckReserveGte,
             0x33 => CheckSettledGte,
             0x34 => CheckCommitmentDeficitLte,
             0x35 => CheckGracePeriodGte,
+            0x36 => CheckHubSolvency,
+            0x37 => CheckQueueSumLte,
             0xF0 => CheckStaticCallU256,
+            0xF1 => CheckStaticCallCompare,
             _ => return Err(()),
         };
         Ok(op)
     }
 }
 
+impl Opcode {
+    /// Variant name (`"CheckDeadline"`), the `kind` of the check's EIP-712 [`Eip712Check`].
+    pub const fn name(self) -> &'static str {
+        use Opcode::*;
+        match self {
+            CheckDeadline => "CheckDeadline",
+            CheckNonce => "CheckNonce",
+            CheckCallBundleHash => "CheckCallBundleHash",
+            CheckUserOpField => "CheckUserOpField",
+            CheckTokenAmountLte => "CheckTokenAmountLte",
+            CheckNativeValueLte => "CheckNativeValueLte",
+            CheckLiquidityDeltaLte => "CheckLiquidityDeltaLte",
+            CheckWalletTokenDeltaLte => "CheckWalletTokenDeltaLte",
+            CheckWindowSpendLte => "CheckWindowSpendLte",
+            CheckSlot0TickBounds => "CheckSlot0TickBounds",
+            CheckSlot0SqrtPriceBounds => "CheckSlot0SqrtPriceBounds",
+            CheckPoolAllowed => "CheckPoolAllowed",
+            CheckTwapTickBounds => "CheckTwapTickBounds",
+            CheckOracleDeviationLte => "CheckOracleDeviationLte",
+            CheckRfsClosed => "CheckRfsClosed",
+            CheckQueueLte => "CheckQueueLte",
+            CheckReserveGte => "CheckReserveGte",
+            CheckSettledGte => "CheckSettledGte",
+            CheckCommitmentDeficitLte => "CheckCommitmentDeficitLte",
+            CheckGracePeriodGte => "CheckGracePeriodGte",
+            CheckHubSolvency => "CheckHubSolvency",
+            CheckQueueSumLte => "CheckQueueSumLte",
+            CheckStaticCallU256 => "CheckStaticCallU256",
+            CheckStaticCallCompare => "CheckStaticCallCompare",
+        }
+    }
+
+    /// The configured fact source the check reads, if any; the only opcodes an install can route
+    /// to another source. `CheckOracleDeviationLte` reads the pool from the state view (its oracle
+    /// is named in the check).
+    pub const fn fact_source(self) -> Option<FactSource> {
+        use Opcode::*;
+        match self {
+            CheckSlot0TickBounds
+            | CheckSlot0SqrtPriceBounds
+            | CheckTwapTickBounds
+            | CheckOracleDeviationLte => Some(FactSource::StateView),
+            CheckRfsClosed | CheckSettledGte | CheckCommitmentDeficitLte | CheckGracePeriodGte => {
+                Some(FactSource::VtsOrchestrator)
+            }
+            CheckQueueLte | CheckReserveGte | CheckHubSolvency | CheckQueueSumLte => {
+                Some(FactSource::LiquidityHub)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl CompOp {
+    /// The operator with wire byte `code`.
+    pub const fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(CompOp::Lt),
+            1 => Some(CompOp::Lte),
+            2 => Some(CompOp::Gt),
+            3 => Some(CompOp::Gte),
+            4 => Some(CompOp::Eq),
+            5 => Some(CompOp::Neq),
+            _ => None,
+        }
+    }
+
+    /// Wire byte in static-call checks.
+    pub const fn code(self) -> u8 {
+        match self {
+            CompOp::Lt => 0,
+            CompOp::Lte => 1,
+            CompOp::Gt => 2,
+            CompOp::Gte => 3,
+            CompOp::Eq => 4,
+            CompOp::Neq => 5,
+        }
+    }
+
+    /// Lower-case mnemonic (`"lte"`), as in the JSON form.
+    pub const fn name(self) -> &'static str {
+        match self {
+            CompOp::Lt => "lt",
+            CompOp::Lte => "lte",
+            CompOp::Gt => "gt",
+            CompOp::Gte => "gte",
+            CompOp::Eq => "eq",
+            CompOp::Neq => "neq",
+        }
+    }
+}
+
+impl TryFrom<u8> for UserOpField {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        use UserOpField::*;
+        let field = match value {
+            0 => Sender,
+            1 => Nonce,
+            2 => VerificationGasLimit,
+            3 => CallGasLimit,
+            4 => PreVerificationGas,
+            5 => MaxPriorityFeePerGas,
+            6 => MaxFeePerGas,
+            _ => return Err(()),
+        };
+        Ok(field)
+    }
+}
+
+impl UserOpField {
+    /// Wire byte in `CheckUserOpField`.
+    pub const fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// `PackedUserOperation` field name (`"maxFeePerGas"`), as in the JSON form.
+    pub const fn name(self) -> &'static str {
+        use UserOpField::*;
+        match self {
+            Sender => "sender",
+            Nonce => "nonce",
+            VerificationGasLimit => "verificationGasLimit",
+            CallGasLimit => "callGasLimit",
+            PreVerificationGas => "preVerificationGas",
+            MaxPriorityFeePerGas => "maxPriorityFeePerGas",
+            MaxFeePerGas => "maxFeePerGas",
+        }
+    }
+}
+
+/// Why [`Check::decode_from`] rejected a check. The program decoders map these onto their own
+/// error codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckDecodeError {
+    Truncated,
+    UnknownOpcode(u8),
+    UnknownCompOp(u8),
+    BadUserOpField(u8),
+    /// A `CheckQueueSumLte` owner count outside `1..=MAX_QUEUE_SUM_OWNERS`.
+    BadOwnerCount(u8),
+}
+
+impl From<UnexpectedEnd> for CheckDecodeError {
+    fn from(_: UnexpectedEnd) -> Self {
+        CheckDecodeError::Truncated
+    }
+}
+
+impl Check {
+    /// The opcode the check encodes as.
+    pub const fn opcode(&self) -> Opcode {
+        match self {
+            Check::Deadline { .. } => Opcode::CheckDeadline,
+            Check::Nonce { .. } => Opcode::CheckNonce,
+            Check::CallBundleHash { .. } => Opcode::CheckCallBundleHash,
+            Check::UserOpField { .. } => Opcode::CheckUserOpField,
+            Check::TokenAmountLte { .. } => Opcode::CheckTokenAmountLte,
+            Check::NativeValueLte { .. } => Opcode::CheckNativeValueLte,
+            Check::LiquidityDeltaLte { .. } => Opcode::CheckLiquidityDeltaLte,
+            Check::WalletTokenDeltaLte { .. } => Opcode::CheckWalletTokenDeltaLte,
+            Check::WindowSpendLte { .. } => Opcode::CheckWindowSpendLte,
+            Check::Slot0TickBounds { .. } => Opcode::CheckSlot0TickBounds,
+            Check::Slot0SqrtPriceBounds { .. } => Opcode::CheckSlot0SqrtPriceBounds,
+            Check::PoolAllowed { .. } => Opcode::CheckPoolAllowed,
+            Check::TwapTickBounds { .. } => Opcode::CheckTwapTickBounds,
+            Check::OracleDeviationLte { .. } => Opcode::CheckOracleDeviationLte,
+            Check::RfsClosed { .. } => Opcode::CheckRfsClosed,
+            Check::QueueLte { .. } => Opcode::CheckQueueLte,
+            Check::ReserveGte { .. } => Opcode::CheckReserveGte,
+            Check::SettledGte { .. } => Opcode::CheckSettledGte,
+            Check::CommitmentDeficitLte { .. } => Opcode::CheckCommitmentDeficitLte,
+            Check::GracePeriodGte { .. } => Opcode::CheckGracePeriodGte,
+            Check::HubSolvency { .. } => Opcode::CheckHubSolvency,
+            Check::QueueSumLte { .. } => Opcode::CheckQueueSumLte,
+            Check::StaticCallU256 { .. } => Opcode::CheckStaticCallU256,
+            Check::StaticCallCompare { .. } => Opcode::CheckStaticCallCompare,
+        }
+    }
+
+    /// Contracts the check names itself and calls during validation: static-call targets, the
+    /// oracle, and tokens read with `balanceOf`. Fact sources are fixed at install and not counted.
+    pub const fn call_targets(&self) -> [Option<Address>; 2] {
+        match self {
+            Check::WalletTokenDeltaLte { token, .. } | Check::WindowSpendLte { token, .. } => {
+                [Some(*token), None]
+            }
+            Check::OracleDeviationLte { oracle, .. } => [Some(*oracle), None],
+            Check::StaticCallU256 { target, .. } => [Some(*target), None],
+            Check::StaticCallCompare { lhs_target, rhs_target, .. } => {
+                [Some(*lhs_target), Some(*rhs_target)]
+            }
+            _ => [None, None],
+        }
+    }
+
+    /// Append the check's program encoding (opcode byte, then its fields big-endian) to `buf`.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        match self {
+            Check::Deadline { deadline } => {
+                buf.push(Opcode::CheckDeadline as u8);
+                buf.extend_from_slice(&deadline.to_be_bytes());
+            }
+            Check::Nonce { expected } => {
+                buf.push(Opcode::CheckNonce as u8);
+                buf.extend_from_slice(&expected.to_be_bytes::<32>());
+            }
+            Check::CallBundleHash { hash } => {
+                buf.push(Opcode::CheckCallBundleHash as u8);
+                buf.extend_from_slice(hash.as_slice());
+            }
+            Check::UserOpField { field, op, rhs } => {
+                buf.push(Opcode::CheckUserOpField as u8);
+                buf.push(field.code());
+                buf.push(op.code());
+                buf.extend_from_slice(&rhs.to_be_bytes::<32>());
+            }
+            Check::TokenAmountLte { token, max } => {
+                buf.push(Opcode::CheckTokenAmountLte as u8);
+                buf.extend_from_slice(token.as_slice());
+                buf.extend_from_slice(&max.to_be_bytes::<32>());
+            }
+            Check::NativeValueLte { max } => {
+                buf.push(Opcode::CheckNativeValueLte as u8);
+                buf.extend_from_slice(&max.to_be_bytes::<32>());
+            }
+            Check::LiquidityDeltaLte { max } => {
+                buf.push(Opcode::CheckLiquidityDeltaLte as u8);
+                buf.extend_from_slice(&max.to_be_bytes());
+            }
+            Check::WalletTokenDeltaLte { token, max_out } => {
+                buf.push(Opcode::CheckWalletTokenDeltaLte as u8);
+                buf.extend_from_slice(token.as_slice());
+                buf.extend_from_slice(&max_out.to_be_bytes::<32>());
+            }
+            Check::WindowSpendLte { token, window, max } => {
+                buf.push(Opcode::CheckWindowSpendLte as u8);
+                buf.extend_from_slice(token.as_slice());
+                buf.extend_from_slice(&window.to_be_bytes());
+                buf.extend_from_slice(&max.to_be_bytes::<32>());
+            }
+            Check::Slot0TickBounds { pool_id, min, max } => {
+                buf.push(Opcode::CheckSlot0TickBounds as u8);
+                buf.extend_from_slice(pool_id.as_slice());
+                buf.extend_from_slice(&min.to_be_bytes());
+                buf.extend_from_slice(&max.to_be_bytes());
+            }
+            Check::Slot0SqrtPriceBounds { pool_id, min, max } => {
+                buf.push(Opcode::CheckSlot0SqrtPriceBounds as u8);
+                buf.extend_from_slice(pool_id.as_slice());
+                buf.extend_from_slice(&min.to_be_bytes::<32>());
+                buf.extend_from_slice(&max.to_be_bytes::<32>());
+            }
+            Check::PoolAllowed { pool_id } => {
+                buf.push(Opcode::CheckPoolAllowed as u8);
+                buf.extend_from_slice(pool_id.as_slice());
+            }
+            Check::TwapTickBounds { pool_id, window, min, max } => {
+                buf.push(Opcode::CheckTwapTickBounds as u8);
+                buf.extend_from_slice(pool_id.as_slice());
+                buf.extend_from_slice(&window.to_be_bytes());
+                buf.extend_from_slice(&min.to_be_bytes());
+                buf.extend_from_slice(&max.to_be_bytes());
+            }
+            Check::OracleDeviationLte { pool_id, oracle, selector, decimals, max_bps } => {
+                buf.push(Opcode::CheckOracleDeviationLte as u8);
+                buf.extend_from_slice(pool_id.as_slice());
+                buf.extend_from_slice(oracle.as_slice());
+                buf.extend_from_slice(selector);
+                buf.push(*decimals);
+                buf.extend_from_slice(&max_bps.to_be_bytes());
+            }
+            Check::RfsClosed { position_id } => {
+                buf.push(Opcode::CheckRfsClosed as u8);
+                buf.extend_from_slice(position_id.as_slice());
+            }
+            Check::QueueLte { lcc, owner, max } => {
+                buf.push(Opcode::CheckQueueLte as u8);
+                buf.extend_from_slice(lcc.as_slice());
+                buf.extend_from_slice(owner.as_slice());
+                buf.extend_from_slice(&max.to_be_bytes::<32>());
+            }
+            Check::ReserveGte { lcc, min } => {
+                buf.push(Opcode::CheckReserveGte as u8);
+                buf.extend_from_slice(lcc.as_slice());
+                buf.extend_from_slice(&min.to_be_bytes::<32>());
+            }
+            Check::SettledGte { position_id, min_amount0, min_amount1 } => {
+                buf.push(Opcode::CheckSettledGte as u8);
+                buf.extend_from_slice(position_id.as_slice());
+                buf.extend_from_slice(&min_amount0.to_be_bytes::<32>());
+                buf.extend_from_slice(&min_amount1.to_be_bytes::<32>());
+            }
+            Check::CommitmentDeficitLte { position_id, max_deficit0, max_deficit1 } => {
+                buf.push(Opcode::CheckCommitmentDeficitLte as u8);
+                buf.extend_from_slice(position_id.as_slice());
+                buf.extend_from_slice(&max_deficit0.to_be_bytes::<32>());
+                buf.extend_from_slice(&max_deficit1.to_be_bytes::<32>());
+            }
+            Check::GracePeriodGte { position_id, min_seconds } => {
+                buf.push(Opcode::CheckGracePeriodGte as u8);
+                buf.extend_from_slice(position_id.as_slice());
+                buf.extend_from_slice(&min_seconds.to_be_bytes());
+            }
+            Check::HubSolvency { lcc, min_ratio_bps } => {
+                buf.push(Opcode::CheckHubSolvency as u8);
+                buf.extend_from_slice(lcc.as_slice());
+                buf.extend_from_slice(&min_ratio_bps.to_be_bytes());
+            }
+            Check::QueueSumLte { lcc, owners, max } => {
+                buf.push(Opcode::CheckQueueSumLte as u8);
+                buf.extend_from_slice(lcc.as_slice());
+                buf.push(owners.len() as u8);
+                for owner in owners {
+                    buf.extend_from_slice(owner.as_slice());
+                }
+                buf.extend_from_slice(&max.to_be_bytes::<32>());
+            }
+            Check::StaticCallU256 { target, selector, args, op, rhs } => {
+                buf.push(Opcode::CheckStaticCallU256 as u8);
+                buf.extend_from_slice(target.as_slice());
+                buf.extend_from_slice(selector);
+                buf.extend_from_slice(&(args.len() as u16).to_be_bytes());
+                buf.extend_from_slice(args);
+                buf.push(op.code());
+                buf.extend_from_slice(&rhs.to_be_bytes::<32>());
+            }
+            Check::StaticCallCompare {
+                lhs_target,
+                lhs_selector,
+                lhs_args,
+                op,
+                rhs_target,
+                rhs_selector,
+                rhs_args,
+                scale_bps,
+            } => {
+                buf.push(Opcode::CheckStaticCallCompare as u8);
+                buf.extend_from_slice(lhs_target.as_slice());
+                buf.extend_from_slice(lhs_selector);
+                buf.extend_from_slice(&(lhs_args.len() as u16).to_be_bytes());
+                buf.extend_from_slice(lhs_args);
+                buf.push(op.code());
+                buf.extend_from_slice(rhs_target.as_slice());
+                buf.extend_from_slice(rhs_selector);
+                buf.extend_from_slice(&(rhs_args.len() as u16).to_be_bytes());
+                buf.extend_from_slice(rhs_args);
+                buf.extend_from_slice(&scale_bps.to_be_bytes());
+            }
+        }
+    }
+
+    /// Read one check in its program encoding (the inverse of [`Check::encode_into`]). The only
+    /// parser of the format: the policy's and the encoder's program decoders and
+    /// [`is_canonical`](crate::canonical::is_canonical) all go through it.
+    pub fn decode_from(r: &mut ByteReader) -> Result<Check, CheckDecodeError> {
+        let byte = r.u8()?;
+        let opcode = Opcode::try_from(byte).map_err(|_| CheckDecodeError::UnknownOpcode(byte))?;
+        let check = match opcode {
+            Opcode::CheckDeadline => Check::Deadline { deadline: r.u64()? },
+            Opcode::CheckNonce => Check::Nonce { expected: r.u256()? },
+            Opcode::CheckCallBundleHash => Check::CallBundleHash { hash: r.b32()? },
+            Opcode::CheckUserOpField => {
+                let b = r.u8()?;
+                let field =
+                    UserOpField::try_from(b).map_err(|_| CheckDecodeError::BadUserOpField(b))?;
+                Check::UserOpField { field, op: read_comp_op(r)?, rhs: r.u256()? }
+            }
+            Opcode::CheckTokenAmountLte => Check::TokenAmountLte {
+                token: r.address()?,
+                max: r.u256()?,
+            },
+            Opcode::CheckNativeValueLte => Check::NativeValueLte { max: r.u256()? },
+            Opcode::CheckLiquidityDeltaLte => Check::LiquidityDeltaLte { max: r.u128()? },
+            Opcode::CheckWalletTokenDeltaLte => Check::WalletTokenDeltaLte {
+                token: r.address()?,
+                max_out: r.u256()?,
+            },
+            Opcode::CheckWindowSpendLte => Check::WindowSpendLte {
+                token: r.address()?,
+                window: r.u32()?,
+                max: r.u256()?,
+            },
+            Opcode::CheckSlot0TickBounds => Check::Slot0TickBounds {
+                pool_id: r.b32()?,
+                min: r.i32()?,
+                max: r.i32()?,
+            },
+            Opcode::CheckSlot0SqrtPriceBounds => Check::Slot0SqrtPriceBounds {
+                pool_id: r.b32()?,
+                min: r.u256()?,
+                max: r.u256()?,
+            },
+            Opcode::CheckPoolAllowed => Check::PoolAllowed { pool_id: r.b32()? },
+            Opcode::CheckTwapTickBounds => Check::TwapTickBounds {
+                pool_id: r.b32()?,
+                window: r.u32()?,
+                min: r.i32()?,
+                max: r.i32()?,
+            },
+            Opcode::CheckOracleDeviationLte => Check::OracleDeviationLte {
+                pool_id: r.b32()?,
+                oracle: r.address()?,
+                selector: r.array()?,
+                decimals: r.u8()?,
+                max_bps: r.u32()?,
+            },
+            Opcode::CheckRfsClosed => Check::RfsClosed { position_id: r.b32()? },
+            Opcode::CheckQueueLte => Check::QueueLte {
+                lcc: r.address()?,
+                owner: r.address()?,
+                max: r.u256()?,
+            },
+            Opcode::CheckReserveGte => Check::ReserveGte { lcc: r.address()?, min: r.u256()? },
+            Opcode::CheckSettledGte => Check::SettledGte {
+                position_id: r.b32()?,
+                min_amount0: r.u256()?,
+                min_amount1: r.u256()?,
+            },
+            Opcode::CheckCommitmentDeficitLte => Check::CommitmentDeficitLte {
+                position_id: r.b32()?,
+                max_deficit0: r.u256()?,
+                max_deficit1: r.u256()?,
+            },
+            Opcode::CheckGracePeriodGte => Check::GracePeriodGte {
+                position_id: r.b32()?,
+                min_seconds: r.u64()?,
+            },
+            Opcode::CheckHubSolvency => Check::HubSolvency {
+                lcc: r.address()?,
+                min_ratio_bps: r.u32()?,
+            },
+            Opcode::CheckQueueSumLte => {
+                let lcc = r.address()?;
+                let count = r.u8()?;
+                if count == 0 || count as usize > MAX_QUEUE_SUM_OWNERS {
+                    return Err(CheckDecodeError::BadOwnerCount(count));
+                }
+                let owners = (0..count).map(|_| r.address()).collect::<Result<Vec<_>, _>>()?;
+                Check::QueueSumLte { lcc, owners, max: r.u256()? }
+            }
+            Opcode::CheckStaticCallU256 => {
+                let (target, selector, args) = read_call(r)?;
+                let op = read_comp_op(r)?;
+                Check::StaticCallU256 { target, selector, args, op, rhs: r.u256()? }
+            }
+            Opcode::CheckStaticCallCompare => {
+                let (lhs_target, lhs_selector, lhs_args) = read_call(r)?;
+                let op = read_comp_op(r)?;
+                let (rhs_target, rhs_selector, rhs_args) = read_call(r)?;
+                Check::StaticCallCompare {
+                    lhs_target,
+                    lhs_selector,
+                    lhs_args,
+                    op,
+                    rhs_target,
+                    rhs_selector,
+                    rhs_args,
+                    scale_bps: r.u32()?,
+                }
+            }
+        };
+        Ok(check)
+    }
+
+    /// Stable discriminant: the opcode byte the check encodes with.
+    pub const fn discriminant(&self) -> u8 {
+        self.opcode() as u8
+    }
+
+    fn encoded(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    /// Static-call argument lengths, which disambiguate encodings whose `u16` lengths wrapped.
+    fn args_lens(&self) -> (usize, usize) {
+        match self {
+            Check::StaticCallU256 { args, .. } => (args.len(), 0),
+            Check::StaticCallCompare { lhs_args, rhs_args, .. } => (lhs_args.len(), rhs_args.len()),
+            _ => (0, 0),
+        }
+    }
+}
+
+fn read_comp_op(r: &mut ByteReader) -> Result<CompOp, CheckDecodeError> {
+    let b = r.u8()?;
+    CompOp::from_code(b).ok_or(CheckDecodeError::UnknownCompOp(b))
+}
+
+/// `target || selector || uint16 argsLen || args`.
+fn read_call(r: &mut ByteReader) -> Result<(Address, [u8; 4], Vec<u8>), CheckDecodeError> {
+    let target = r.address()?;
+    let selector = r.array()?;
+    let args_len = r.u16()? as usize;
+    Ok((target, selector, r.take(args_len)?.to_vec()))
+}
+
+impl Ord for Check {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.encoded().cmp(&other.encoded()).then_with(|| self.args_lens().cmp(&other.args_lens()))
+    }
+}
+
+impl PartialOrd for Check {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Check {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(&self.encoded());
+    }
+}
+
+/// EIP-712 type of a check in a typed-checks envelope, which signs `Check[] checks` in place of
+/// `bytes32 programHash` so wallets can show each check to the signer.
+pub const EIP712_CHECK_TYPE: &str = "Check(string kind,bytes32 id,address target,address account,uint256 min,uint256 max,uint256 min1,uint256 max1,int24 tickMin,int24 tickMax,uint32 window,uint32 bps,uint8 decimals,string op,uint256 rhs,bytes call,bytes rhsCall)";
+
+/// A check as the EIP-712 `Check` struct ([`EIP712_CHECK_TYPE`]).
+///
+/// Every kind shares the one struct; fields a kind does not use stay zero or empty:
+///
+/// | `kind` | fields |
+/// |---|---|
+/// | `CheckDeadline` | `max` = deadline |
+/// | `CheckNonce` | `op` = `"eq"`, `rhs` = expected |
+/// | `CheckCallBundleHash` | `id` = hash |
+/// | `CheckUserOpField` | `decimals` = field ([`UserOpField::code`]), `op`, `rhs` |
+/// | `CheckTokenAmountLte`, `CheckWalletTokenDeltaLte` | `target` = token, `max` |
+/// | `CheckNativeValueLte`, `CheckLiquidityDeltaLte` | `max` |
+/// | `CheckWindowSpendLte` | `target` = token, `window`, `max` |
+/// | `CheckSlot0TickBounds` | `id` = pool, `tickMin`, `tickMax` |
+/// | `CheckSlot0SqrtPriceBounds` | `id` = pool, `min`, `max` |
+/// | `CheckPoolAllowed`, `CheckRfsClosed` | `id` = pool / position |
+/// | `CheckTwapTickBounds` | `id` = pool, `window`, `tickMin`, `tickMax` |
+/// | `CheckOracleDeviationLte` | `id` = pool, `target` = oracle, `call` = selector, `decimals`, `bps` |
+/// | `CheckQueueLte` | `target` = lcc, `account` = owner, `max` |
+/// | `CheckReserveGte` | `target` = lcc, `min` |
+/// | `CheckSettledGte` | `id` = position, `min`, `min1` |
+/// | `CheckCommitmentDeficitLte` | `id` = position, `max`, `max1` |
+/// | `CheckGracePeriodGte` | `id` = position, `min` = seconds |
+/// | `CheckHubSolvency` | `target` = lcc, `bps` = minimum ratio |
+/// | `CheckQueueSumLte` | `target` = lcc, `call` = owners (20 bytes each), `max` |
+/// | `CheckStaticCallU256` | `target`, `call` = selector ‖ args, `op`, `rhs` |
+/// | `CheckStaticCallCompare` | `target`/`call` = lhs, `op`, `account`/`rhsCall` = rhs, `bps` = scale |
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Eip712Check {
+    pub kind: &'static str,
+    pub id: FixedBytes<32>,
+    pub target: Address,
+    pub account: Address,
+    pub min: U256,
+    pub max: U256,
+    pub min1: U256,
+    pub max1: U256,
+    pub tick_min: i32,
+    pub tick_max: i32,
+    pub window: u32,
+    pub bps: u32,
+    pub decimals: u8,
+    pub op: &'static str,
+    pub rhs: U256,
+    pub call: Vec<u8>,
+    pub rhs_call: Vec<u8>,
+}
+
+impl From<&Check> for Eip712Check {
+    fn from(check: &Check) -> Self {
+        let base = Self {
+            kind: check.opcode().name(),
+            ..Self::default()
+        };
+        match check {
+            Check::Deadline { deadline } => Self {
+                max: U256::from(*deadline),
+                ..base
+            },
+            Check::Nonce { expected } => Self {
+                op: CompOp::Eq.name(),
+                rhs: *expected,
+                ..base
+            },
+            Check::CallBundleHash { hash } => Self { id: *hash, ..base },
+            Check::UserOpField { field, op, rhs } => Self {
+                decimals: field.code(),
+                op: op.name(),
+                rhs: *rhs,
+                ..base
+            },
+            Check::TokenAmountLte { token, max } => Self {
+                target: *token,
+                max: *max,
+                ..base
+            },
+            Check::NativeValueLte { max } => Self { max: *max, ..base },
+            Check::LiquidityDeltaLte { max } => Self {
+                max: U256::from(*max),
+                ..base
+            },
+            Check::WalletTokenDeltaLte { token, max_out } => Self {
+                target: *token,
+                max: *max_out,
+                ..base
+            },
+            Check::WindowSpendLte { token, window, max } => Self {
+                target: *token,
+                window: *window,
+                max: *max,
+                ..base
+            },
+            Check::Slot0TickBounds { pool_id, min, max } => Self {
+                id: *pool_id,
+                tick_min: *min,
+                tick_max: *max,
+                ..base
+            },
+            Check::Slot0SqrtPriceBounds { pool_id, min, max } => Self {
+                id: *pool_id,
+                min: *min,
+                max: *max,
+                ..base
+            },
+            Check::PoolAllowed { pool_id } => Self { id: *pool_id, ..base },
+            Check::TwapTickBounds {
+                pool_id,
+                window,
+                min,
+                max,
+            } => Self {
+                id: *pool_id,
+                window: *window,
+                tick_min: *min,
+                tick_max: *max,
+                ..base
+            },
+            Check::OracleDeviationLte {
+                pool_id,
+                oracle,
+                selector,
+                decimals,
+                max_bps,
+            } => Self {
+                id: *pool_id,
+                target: *oracle,
+                call: selector.to_vec(),
+                decimals: *decimals,
+                bps: *max_bps,
+                ..base
+            },
+            Check::RfsClosed { position_id } => Self {
+                id: *position_id,
+                ..base
+            },
+            Check::QueueLte { lcc, owner, max } => Self {
+                target: *lcc,
+                account: *owner,
+                max: *max,
+                ..base
+            },
+            Check::ReserveGte { lcc, min } => Self {
+                target: *lcc,
+                min: *min,
+                ..base
+            },
+            Check::SettledGte {
+                position_id,
+                min_amount0,
+                min_amount1,
+            } => Self {
+                id: *position_id,
+                min: *min_amount0,
+                min1: *min_amount1,
+                ..base
+            },
+            Check::CommitmentDeficitLte {
+                position_id,
+                max_deficit0,
+                max_deficit1,
+            } => Self {
+                id: *position_id,
+                max: *max_deficit0,
+                max1: *max_deficit1,
+                ..base
+            },
+            Check::GracePeriodGte {
+                position_id,
+                min_seconds,
+            } => Self {
+                id: *position_id,
+                min: U256::from(*min_seconds),
+                ..base
+            },
+            Check::HubSolvency { lcc, min_ratio_bps } => Self {
+                target: *lcc,
+                bps: *min_ratio_bps,
+                ..base
+            },
+            Check::QueueSumLte { lcc, owners, max } => Self {
+                target: *lcc,
+                call: owners.iter().flat_map(|owner| owner.0).collect(),
+                max: *max,
+                ..base
+            },
+            Check::StaticCallU256 {
+                target,
+                selector,
+                args,
+                op,
+                rhs,
+            } => Self {
+                target: *target,
+                call: [selector.as_slice(), args].concat(),
+                op: op.name(),
+                rhs: *rhs,
+                ..base
+            },
+            Check::StaticCallCompare {
+                lhs_target,
+                lhs_selector,
+                lhs_args,
+                op,
+                rhs_target,
+                rhs_selector,
+                rhs_args,
+                scale_bps,
+            } => Self {
+                target: *lhs_target,
+                call: [lhs_selector.as_slice(), lhs_args].concat(),
+                op: op.name(),
+                account: *rhs_target,
+                rhs_call: [rhs_selector.as_slice(), rhs_args].concat(),
+                bps: *scale_bps,
+                ..base
+            },
+        }
+    }
+}
+
+impl Eip712Check {
+    /// EIP-712 `hashStruct` of the check.
+    pub fn struct_hash(&self) -> FixedBytes<32> {
+        fn word(bytes: &[u8]) -> [u8; 32] {
+            let mut out = [0u8; 32];
+            out[32 - bytes.len()..].copy_from_slice(bytes);
+            out
+        }
+        fn int24(value: i32) -> [u8; 32] {
+            // Sign-extended to the full word, as EIP-712 encodes signed integers.
+            let fill = if value < 0 { 0xff } else { 0x00 };
+            let mut out = [fill; 32];
+            out[28..].copy_from_slice(&value.to_be_bytes());
+            out
+        }
+
+        let mut buf = Vec::with_capacity(32 * 18);
+        buf.extend_from_slice(keccak256(EIP712_CHECK_TYPE).as_slice());
+        buf.extend_from_slice(keccak256(self.kind).as_slice());
+        buf.extend_from_slice(self.id.as_slice());
+        buf.extend_from_slice(&word(self.target.as_slice()));
+        buf.extend_from_slice(&word(self.account.as_slice()));
+        for value in [self.min, self.max, self.min1, self.max1] {
+            buf.extend_from_slice(&value.to_be_bytes::<32>());
+        }
+        buf.extend_from_slice(&int24(self.tick_min));
+        buf.extend_from_slice(&int24(self.tick_max));
+        buf.extend_from_slice(&word(&self.window.to_be_bytes()));
+        buf.extend_from_slice(&word(&self.bps.to_be_bytes()));
+        buf.extend_from_slice(&word(&[self.decimals]));
+        buf.extend_from_slice(keccak256(self.op).as_slice());
+        buf.extend_from_slice(&self.rhs.to_be_bytes::<32>());
+        buf.extend_from_slice(keccak256(&self.call).as_slice());
+        buf.extend_from_slice(keccak256(&self.rhs_call).as_slice());
+        keccak256(buf)
+    }
+}
+
+/// EIP-712 encoding of `Check[] checks`: the hash of the concatenated check struct hashes.
+pub fn eip712_checks_hash(checks: &[Check]) -> FixedBytes<32> {
+    let mut buf = Vec::with_capacity(32 * checks.len());
+    for check in checks {
+        buf.extend_from_slice(Eip712Check::from(check).struct_hash().as_slice());
+    }
+    keccak256(buf)
+}
+
+/// Number of distinct [`Check::call_targets`] across `checks`.
+pub fn call_target_count(checks: &[Check]) -> usize {
+    routed_call_target_count(checks, core::iter::empty())
+}
+
+/// [`call_target_count`] plus the sources of the install's `(opcode, source)` overrides that
+/// `checks` route to: an override is only called by a program using its opcode, and then counts
+/// like any other target.
+pub fn routed_call_target_count(
+    checks: &[Check],
+    overrides: impl IntoIterator<Item = (u8, Address)>,
+) -> usize {
+    let mut seen: Vec<Address> = Vec::new();
+    let routed = overrides
+        .into_iter()
+        .filter(|(opcode, _)| checks.iter().any(|check| check.opcode() as u8 == *opcode))
+        .map(|(_, source)| source);
+    for target in checks.iter().flat_map(Check::call_targets).flatten().chain(routed) {
+        if !seen.contains(&target) {
+            seen.push(target);
+        }
+    }
+    seen.len()
+}
+
+/// Byte strings (`[u8; N]`, `Vec<u8>`) as `0x` hex; the prefix is optional when parsing.
+#[cfg(feature = "serde")]
+pub(crate) mod hex_bytes {
+    use alloc::{string::String, vec::Vec};
+
+    use alloy_primitives::hex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<[u8]>,
+    {
+        serializer.serialize_str(&hex::encode_prefixed(value))
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        let bytes = hex::decode(&raw).map_err(D::Error::custom)?;
+        let len = bytes.len();
+        T::try_from(bytes).map_err(|_| D::Error::custom(format_args!("unexpected length {len}")))
+    }
+}
+
+/// `u128` as a `0x` hex string, like `U256`. JSON numbers cannot hold it, and serde's buffering of
+/// internally tagged enums does not support 128-bit integers.
+#[cfg(feature = "serde")]
+mod u128_hex {
+    use alloc::string::String;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{value:#x}"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        let parsed = match raw.strip_prefix("0x") {
+            Some(hex) => u128::from_str_radix(hex, 16),
+            None => raw.parse(),
+        };
+        parsed.map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,140 @@
+//! WebAuthn (passkey) assertions carried as envelope signatures.
+//!
+//! A passkey cannot sign the policy digest directly: the authenticator signs
+//! `sha256(authenticatorData || sha256(clientDataJSON))` with P-256, where `clientDataJSON` embeds
+//! the digest as the base64url `challenge`. The envelope therefore carries the whole assertion, in
+//! this layout (big-endian lengths and indices):
+//!
+//! - u16 authenticator_data_len
+//! - bytes authenticator_data
+//! - u16 client_data_json_len
+//! - bytes client_data_json
+//! - u16 challenge_index (offset of `"challenge":"` in `client_data_json`)
+//! - u16 type_index (offset of `"type":"webauthn.get"` in `client_data_json`)
+//! - bytes32 r
+//! - bytes32 s
+//!
+//! The indices let the verifier check the JSON without parsing it. Hashing and the P-256 check
+//! itself are left to the caller (precompiles on chain, native code off chain).
+
+use alloc::vec::Vec;
+
+use alloy_primitives::FixedBytes;
+
+use crate::bytes::ByteReader;
+
+/// Authenticator data flag: user present.
+pub const AUTH_DATA_FLAG_UP: u8 = 0x01;
+/// Authenticator data flag: user verified.
+pub const AUTH_DATA_FLAG_UV: u8 = 0x04;
+
+/// `rpIdHash (32) || flags (1) || signCount (4)`.
+const MIN_AUTHENTICATOR_DATA_LEN: usize = 37;
+
+const TYPE_FIELD: &[u8] = b"\"type\":\"webauthn.get\"";
+const CHALLENGE_FIELD: &[u8] = b"\"challenge\":\"";
+
+/// A WebAuthn assertion over a policy digest, signed by a P-256 passkey.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebAuthnAssertion {
+    pub authenticator_data: Vec<u8>,
+    pub client_data_json: Vec<u8>,
+    pub challenge_index: u16,
+    pub type_index: u16,
+    pub r: FixedBytes<32>,
+    pub s: FixedBytes<32>,
+}
+
+impl WebAuthnAssertion {
+    /// Decode an assertion, rejecting truncated input, trailing bytes and authenticator data too
+    /// short to carry its flags.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut r = ByteReader::new(bytes);
+        let auth_len = r.u16().ok()? as usize;
+        let authenticator_data = r.take(auth_len).ok()?.to_vec();
+        let client_len = r.u16().ok()? as usize;
+        let client_data_json = r.take(client_len).ok()?.to_vec();
+        let challenge_index = r.u16().ok()?;
+        let type_index = r.u16().ok()?;
+        let sig_r = r.b32().ok()?;
+        let sig_s = r.b32().ok()?;
+        if !r.is_empty() || authenticator_data.len() < MIN_AUTHENTICATOR_DATA_LEN {
+            return None;
+        }
+        Some(Self {
+            authenticator_data,
+            client_data_json,
+            challenge_index,
+            type_index,
+            r: sig_r,
+            s: sig_s,
+        })
+    }
+
+    /// Inverse of [`WebAuthnAssertion::decode`].
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            2 + self.authenticator_data.len() + 2 + self.client_data_json.len() + 4 + 64,
+        );
+        out.extend_from_slice(&(self.authenticator_data.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.authenticator_data);
+        out.extend_from_slice(&(self.client_data_json.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.client_data_json);
+        out.extend_from_slice(&self.challenge_index.to_be_bytes());
+        out.extend_from_slice(&self.type_index.to_be_bytes());
+        out.extend_from_slice(self.r.as_slice());
+        out.extend_from_slice(self.s.as_slice());
+        out
+    }
+
+    /// Authenticator flags (the byte after `rpIdHash`).
+    pub fn flags(&self) -> u8 {
+        self.authenticator_data[32]
+    }
+
+    /// Whether `client_data_json` is a `webauthn.get` response whose challenge is `challenge`,
+    /// and the authenticator reports user presence.
+    pub fn is_for(&self, challenge: &FixedBytes<32>) -> bool {
+        if self.flags() & AUTH_DATA_FLAG_UP == 0 {
+            return false;
+        }
+        let json = self.client_data_json.as_slice();
+        if !field_at(json, self.type_index as usize, TYPE_FIELD) {
+            return false;
+        }
+        let encoded = base64url_challenge(challenge);
+        let start = self.challenge_index as usize;
+        let value = start + CHALLENGE_FIELD.len();
+        field_at(json, start, CHALLENGE_FIELD)
+            && field_at(json, value, &encoded)
+            && json.get(value + encoded.len()) == Some(&b'"')
+    }
+}
+
+/// The `challenge` a browser puts in `clientDataJSON` for a 32-byte challenge: unpadded base64url.
+pub fn base64url_challenge(challenge: &FixedBytes<32>) -> [u8; 43] {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let bytes = challenge.as_slice();
+    let mut out = [0u8; 43];
+    let mut o = 0;
+    for chunk in bytes.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+        let n = (b0 << 16) | (b1 << 8) | b2;
+        // 32 bytes = 10 full groups of 4 characters plus 3 characters for the last 2 bytes.
+        let chars = chunk.len() + 1;
+        for i in 0..chars {
+            out[o] = ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize];
+            o += 1;
+        }
+    }
+    out
+}
+
+fn field_at(haystack: &[u8], at: usize, needle: &[u8]) -> bool {
+    at.checked_add(needle.len())
+        .and_then(|end| haystack.get(at..end))
+        .is_some_and(|slice| slice == needle)
+}
@@ -0,0 +1,93 @@
+//! JSON ABI for viem/ethers consumers, who want a machine-readable ABI rather than the Solidity
+//! interface `export-abi` renders.
+//!
+//! `cargo stylus export-abi --json` needs `solc`; here the interface's `function` and `error`
+//! declarations are parsed as human-readable ABI instead. Stylus leaves events out of the
+//! interface, so they are appended from [`EVENTS`], each checked against its `sol!` definition.
+
+use core::{fmt, marker::PhantomData};
+
+use alloy_json_abi::{Event, JsonAbi};
+use stylus_sdk::{
+    abi::export::GenerateAbi, alloy_primitives::FixedBytes, alloy_sol_types::SolEvent,
+};
+
+use fiet_maker_policy::intent_policy::{
+    IntentValidated, NonceRevoked, PermissionInstalled, PermissionUninstalled,
+};
+
+/// Events the policy emits, with the topic their `sol!` definition hashes to.
+const EVENTS: &[(&str, FixedBytes<32>)] = &[
+    (
+        "event IntentValidated(address indexed wallet, bytes32 indexed permissionId, bytes32 indexed envelopeDigest, bytes32 programHash, uint256 nonce, uint64 deadline)",
+        IntentValidated::SIGNATURE_HASH,
+    ),
+    (
+        "event PermissionInstalled(address indexed wallet, bytes32 indexed permissionId, address signer, address stateView, address vtsOrchestrator, address liquidityHub)",
+        PermissionInstalled::SIGNATURE_HASH,
+    ),
+    (
+        "event PermissionUninstalled(address indexed wallet, bytes32 indexed permissionId)",
+        PermissionUninstalled::SIGNATURE_HASH,
+    ),
+    (
+        "event NonceRevoked(address indexed wallet, bytes32 indexed permissionId, address indexed caller, uint256 nonce)",
+        NonceRevoked::SIGNATURE_HASH,
+    ),
+];
+
+/// `T`'s JSON ABI, pretty-printed.
+pub fn json_abi<T: GenerateAbi>() -> Result<String, String> {
+    let interface = Interface::<T>(PhantomData).to_string();
+    let mut declarations: Vec<String> = interface
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("function ") || line.starts_with("error "))
+        // Data locations do not change the ABI, and the parser rejects them inside tuples.
+        .map(|line| parenthesise_returns(&line.trim_end_matches(';').replace(" memory", "")))
+        .collect();
+    for (declaration, topic) in EVENTS {
+        let event = Event::parse(declaration).map_err(|err| err.to_string())?;
+        if event.selector() != *topic {
+            return Err(format!(
+                "`{declaration}` does not match the policy's `{}` event",
+                event.name
+            ));
+        }
+        declarations.push(declaration.to_string());
+    }
+
+    let abi =
+        JsonAbi::parse(declarations.iter().map(String::as_str)).map_err(|err| err.to_string())?;
+    serde_json::to_string_pretty(&abi).map_err(|err| err.to_string())
+}
+
+/// Stylus renders a single array-of-tuples return as `returns (address,bytes4)[]`; wrap it
+/// in the parentheses a return list needs.
+fn parenthesise_returns(declaration: &str) -> String {
+    let Some((head, returns)) = declaration.split_once(" returns ") else {
+        return declaration.to_string();
+    };
+    let mut depth = 0usize;
+    let closes_at_end = returns.char_indices().all(|(i, c)| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        depth > 0 || i + 1 == returns.len()
+    });
+    if returns.starts_with('(') && closes_at_end {
+        declaration.to_string()
+    } else {
+        format!("{head} returns ({returns})")
+    }
+}
+
+struct Interface<T>(PhantomData<T>);
+
+impl<T: GenerateAbi> fmt::Display for Interface<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        T::fmt_abi(f)
+    }
+}
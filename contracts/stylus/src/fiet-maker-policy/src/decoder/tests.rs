@@ -0,0 +1,140 @@
+//! Decoder ordering tests.
+
+use alloc::{vec, vec::Vec};
+
+use stylus_sdk::alloy_primitives::{Address, FixedBytes, U256};
+
+use super::{check_cost, decode_program, decode_program_with_limits, order_by_cost, MAX_PROGRAM_LEN};
+use crate::{
+    errors::DecodeError,
+    types::opcodes::{Check, CompOp, UserOpField, MAX_CALL_TARGETS, MAX_QUEUE_SUM_OWNERS},
+};
+
+fn program() -> Vec<u8> {
+    let mut bytes = vec![0xf0];
+    bytes.extend_from_slice(Address::repeat_byte(0x09).as_slice());
+    bytes.extend_from_slice(&[0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x00, 0x04]);
+    bytes.extend_from_slice(&U256::from(1u64).to_be_bytes::<32>());
+    bytes.push(0x30);
+    bytes.extend_from_slice(FixedBytes::<32>::repeat_byte(0x02).as_slice());
+    bytes.push(0x01);
+    bytes.extend_from_slice(&7u64.to_be_bytes());
+    bytes.push(0x20);
+    bytes.extend_from_slice(FixedBytes::<32>::repeat_byte(0x01).as_slice());
+    bytes.extend_from_slice(&(-1i32).to_be_bytes());
+    bytes.extend_from_slice(&1i32.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn order_by_cost_puts_cheap_checks_first() {
+    let raw = decode_program(&program()).unwrap();
+    assert!(matches!(raw[0], Check::StaticCallU256 { .. }));
+
+    let mut ordered = raw.clone();
+    order_by_cost(&mut ordered);
+    assert_eq!(ordered.len(), raw.len());
+    assert_eq!(ordered[0], Check::Deadline { deadline: 7 });
+    // Same-cost checks keep program order.
+    assert_eq!(ordered[1], Check::RfsClosed { position_id: FixedBytes::repeat_byte(0x02) });
+    assert!(matches!(ordered[2], Check::Slot0TickBounds { .. }));
+    assert_eq!(
+        ordered[3],
+        Check::StaticCallU256 {
+            target: Address::repeat_byte(0x09),
+            selector: [0xaa, 0xbb, 0xcc, 0xdd],
+            args: vec![],
+            op: CompOp::Eq,
+            rhs: U256::from(1u64),
+        }
+    );
+    assert!(ordered.windows(2).all(|pair| check_cost(&pair[0]) <= check_cost(&pair[1])));
+}
+
+#[test]
+fn decode_rejects_oversized_programs_before_decoding() {
+    // Valid deadline checks, but far more bytes than the cap: rejected on length alone.
+    let mut program = Vec::new();
+    while program.len() <= MAX_PROGRAM_LEN {
+        program.push(0x01);
+        program.extend_from_slice(&7u64.to_be_bytes());
+    }
+    assert_eq!(
+        decode_program(&program),
+        Err(DecodeError::ProgramTooLarge(program.len()))
+    );
+}
+
+#[test]
+fn decode_user_op_field_checks() {
+    let check = Check::UserOpField {
+        field: UserOpField::PreVerificationGas,
+        op: CompOp::Lte,
+        rhs: U256::from(80_000u64),
+    };
+    let mut bytes = Vec::new();
+    check.encode_into(&mut bytes);
+    assert_eq!(bytes[..3], [0x04, 0x04, 0x01]);
+    assert_eq!(decode_program(&bytes).unwrap(), vec![check]);
+
+    // Field bytes past the last field are rejected with their own error.
+    bytes[1] = 0x07;
+    assert_eq!(decode_program(&bytes), Err(DecodeError::BadUserOpField(0x07)));
+    assert_eq!(DecodeError::BadUserOpField(0x07).code(), 110);
+
+    // As are operator bytes past `Neq`, with the code the encoder uses.
+    bytes[1] = 0x04;
+    bytes[2] = 0x06;
+    assert_eq!(decode_program(&bytes), Err(DecodeError::UnknownCompOp(0x06)));
+    assert_eq!(DecodeError::UnknownCompOp(0x06).code(), 104);
+}
+
+#[test]
+fn decode_queue_sum_checks_bound_their_owners() {
+    let check = |owners: usize| Check::QueueSumLte {
+        lcc: Address::repeat_byte(0x07),
+        owners: vec![Address::repeat_byte(0x01); owners],
+        max: U256::from(10u64),
+    };
+    let mut bytes = Vec::new();
+    check(MAX_QUEUE_SUM_OWNERS).encode_into(&mut bytes);
+    assert_eq!(decode_program(&bytes).unwrap(), vec![check(MAX_QUEUE_SUM_OWNERS)]);
+
+    for owners in [0, MAX_QUEUE_SUM_OWNERS + 1] {
+        let mut bytes = Vec::new();
+        check(owners).encode_into(&mut bytes);
+        assert_eq!(decode_program(&bytes), Err(DecodeError::BadOwnerCount(owners as u8)));
+    }
+}
+
+#[test]
+fn decode_caps_distinct_call_targets() {
+    let balance_check = |i: u8| Check::WalletTokenDeltaLte {
+        token: Address::repeat_byte(i),
+        max_out: U256::from(1u64),
+    };
+    let encode = |checks: &[Check]| {
+        let mut bytes = Vec::new();
+        checks.iter().for_each(|c| c.encode_into(&mut bytes));
+        bytes
+    };
+
+    // Repeated targets count once.
+    let mut checks: Vec<Check> = (1..=MAX_CALL_TARGETS as u8).map(balance_check).collect();
+    checks.push(Check::StaticCallCompare {
+        lhs_target: Address::repeat_byte(1),
+        lhs_selector: [0xaa; 4],
+        lhs_args: Vec::new(),
+        op: CompOp::Lte,
+        rhs_target: Address::repeat_byte(2),
+        rhs_selector: [0xbb; 4],
+        rhs_args: Vec::new(),
+        scale_bps: 10_000,
+    });
+    assert_eq!(decode_program(&encode(&checks)).unwrap(), checks);
+
+    checks.push(balance_check(MAX_CALL_TARGETS as u8 + 1));
+    let bytes = encode(&checks);
+    assert_eq!(decode_program(&bytes), Err(DecodeError::TooManyTargets(MAX_CALL_TARGETS + 1)));
+    assert!(decode_program_with_limits(&bytes, 64, MAX_CALL_TARGETS + 1).is_ok());
+}
@@ -1,15 +1,84 @@
+use core::fmt;
+
+use fiet_maker_policy_types::{CheckDecodeError, UnexpectedEnd};
+
 /// Errors during program decoding.
+///
+/// [`DecodeError::code`] values are stable (1xx) and shared with the off-chain encoder's decoder;
+/// never renumber a variant, only append.
 #[derive(Debug, PartialEq, Eq)]
 pub enum DecodeError {
     UnknownOpcode(u8),
     Truncated,
     TooManyChecks,
+    /// The program is longer than `MAX_PROGRAM_LEN` bytes.
+    ProgramTooLarge(usize),
+    /// A `CheckQueueSumLte` owner count outside `1..=MAX_QUEUE_SUM_OWNERS`.
+    BadOwnerCount(u8),
+    /// The program calls out to more distinct contracts than the target cap.
+    TooManyTargets(usize),
+    /// A `CheckUserOpField` field byte that names no `UserOpField`.
+    BadUserOpField(u8),
+    /// A comparison operator byte past `CompOp::Neq` (same code as the encoder's).
+    UnknownCompOp(u8),
+}
+
+impl DecodeError {
+    pub const fn code(&self) -> u16 {
+        match self {
+            DecodeError::UnknownOpcode(_) => 101,
+            DecodeError::Truncated => 102,
+            DecodeError::TooManyChecks => 103,
+            DecodeError::ProgramTooLarge(_) => 107,
+            DecodeError::BadOwnerCount(_) => 108,
+            DecodeError::TooManyTargets(_) => 109,
+            DecodeError::BadUserOpField(_) => 110,
+            DecodeError::UnknownCompOp(_) => 104,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnknownOpcode(op) => write!(f, "unknown opcode 0x{op:02x}"),
+            DecodeError::Truncated => f.write_str("program truncated"),
+            DecodeError::TooManyChecks => f.write_str("too many checks"),
+            DecodeError::ProgramTooLarge(len) => write!(f, "program is {len} bytes, too large"),
+            DecodeError::BadOwnerCount(count) => write!(f, "queue sum over {count} owners"),
+            DecodeError::TooManyTargets(count) => write!(f, "program calls {count} distinct targets"),
+            DecodeError::BadUserOpField(field) => write!(f, "unknown userOp field {field}"),
+            DecodeError::UnknownCompOp(op) => write!(f, "unknown comparison operator {op}"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+impl From<UnexpectedEnd> for DecodeError {
+    fn from(_: UnexpectedEnd) -> Self {
+        DecodeError::Truncated
+    }
+}
+
+impl From<CheckDecodeError> for DecodeError {
+    fn from(err: CheckDecodeError) -> Self {
+        match err {
+            CheckDecodeError::Truncated => DecodeError::Truncated,
+            CheckDecodeError::UnknownOpcode(op) => DecodeError::UnknownOpcode(op),
+            CheckDecodeError::UnknownCompOp(op) => DecodeError::UnknownCompOp(op),
+            CheckDecodeError::BadUserOpField(field) => DecodeError::BadUserOpField(field),
+            CheckDecodeError::BadOwnerCount(count) => DecodeError::BadOwnerCount(count),
+        }
+    }
 }
 
 /// Errors during fact acquisition.
 pub use fiet_maker_policy_types::FactsError;
 
 /// Errors during validation/evaluation.
+///
+/// [`ValidationError::code`] values are stable (2xx); never renumber a variant, only append.
 #[derive(Debug, PartialEq, Eq)]
 pub enum ValidationError {
     UnsupportedCheck,
@@ -26,5 +95,58 @@ pub enum ValidationError {
     QueueExceeded,
     ReserveTooLow,
     StaticCallFailed,
+    OracleDeviationExceeded,
+    UserOpFieldOutOfBounds,
+    HubInsolvent,
+}
+
+impl ValidationError {
+    pub const fn code(&self) -> u16 {
+        match self {
+            ValidationError::UnsupportedCheck => 201,
+            ValidationError::DeadlineExpired => 202,
+            ValidationError::NonceMismatch => 203,
+            ValidationError::CallBundleMismatch => 204,
+            ValidationError::TokenNotAllowed => 205,
+            ValidationError::TokenAmountExceeded => 206,
+            ValidationError::NativeValueExceeded => 207,
+            ValidationError::LiquidityDeltaExceeded => 208,
+            ValidationError::TickOutOfBounds => 209,
+            ValidationError::PriceOutOfBounds => 210,
+            ValidationError::RfsNotClosed => 211,
+            ValidationError::QueueExceeded => 212,
+            ValidationError::ReserveTooLow => 213,
+            ValidationError::StaticCallFailed => 214,
+            ValidationError::OracleDeviationExceeded => 215,
+            ValidationError::UserOpFieldOutOfBounds => 216,
+            ValidationError::HubInsolvent => 217,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            ValidationError::UnsupportedCheck => "check is not supported by this policy",
+            ValidationError::DeadlineExpired => "deadline has passed",
+            ValidationError::NonceMismatch => "nonce does not match",
+            ValidationError::CallBundleMismatch => "call bundle hash does not match",
+            ValidationError::TokenNotAllowed => "token is not allowed",
+            ValidationError::TokenAmountExceeded => "token amount exceeds the limit",
+            ValidationError::NativeValueExceeded => "native value exceeds the limit",
+            ValidationError::LiquidityDeltaExceeded => "liquidity delta exceeds the limit",
+            ValidationError::TickOutOfBounds => "pool tick is out of bounds",
+            ValidationError::PriceOutOfBounds => "pool price is out of bounds",
+            ValidationError::RfsNotClosed => "RFS is not closed",
+            ValidationError::QueueExceeded => "settle queue exceeds the limit",
+            ValidationError::ReserveTooLow => "reserve is below the minimum",
+            ValidationError::StaticCallFailed => "static call check failed",
+            ValidationError::OracleDeviationExceeded => "pool price is too far from the oracle's",
+            ValidationError::UserOpFieldOutOfBounds => "UserOperation field is out of bounds",
+            ValidationError::HubInsolvent => "hub reserve is below the solvency ratio",
+        };
+        f.write_str(msg)
+    }
 }
 
+impl core::error::Error for ValidationError {}
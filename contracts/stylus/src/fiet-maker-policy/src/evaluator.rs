@@ -1,9 +1,10 @@
 use crate::{
     errors::ValidationError,
     types::{
-        facts::FactsProvider,
-        opcodes::{Check, CompOp},
+        facts::{deviation_bps, solvency_ratio_bps, sqrt_price_x96_to_wad, to_wad, FactsProvider},
+        opcodes::{scale_bps, Check, CompOp},
     },
+    utils::token_delta::MAX_SPEND_WINDOW,
 };
 
 use stylus_sdk::alloy_primitives::U256;
@@ -26,6 +27,14 @@ pub fn evaluate_program<F: FactsProvider>(
             Check::CallBundleHash { .. } => {
                 // Call bundle hash binding is enforced by caller.
             }
+            Check::UserOpField { field, op, rhs } => {
+                let value = facts
+                    .user_op_field(*field)
+                    .map_err(|_| ValidationError::UserOpFieldOutOfBounds)?;
+                if !compare(value, *op, *rhs) {
+                    return Err(ValidationError::UserOpFieldOutOfBounds);
+                }
+            }
             Check::TokenAmountLte { token, max } => {
                 // NOTE: requires execution-context parsing (call bundle -> token+amount). Fail closed for now.
                 let _ = token;
@@ -40,6 +49,15 @@ pub fn evaluate_program<F: FactsProvider>(
                 let _ = max;
                 return Err(ValidationError::UnsupportedCheck);
             }
+            Check::WalletTokenDeltaLte { .. } => {
+                // Recorded by the caller and enforced by the paired hook after execution.
+            }
+            Check::WindowSpendLte { window, .. } => {
+                // Likewise; only the window itself is validated here.
+                if *window == 0 || *window > MAX_SPEND_WINDOW {
+                    return Err(ValidationError::UnsupportedCheck);
+                }
+            }
             Check::Slot0TickBounds { pool_id, min, max } => {
                 let slot0 = facts
                     .get_slot0(*pool_id)
@@ -56,6 +74,36 @@ pub fn evaluate_program<F: FactsProvider>(
                     return Err(ValidationError::PriceOutOfBounds);
                 }
             }
+            Check::TwapTickBounds { pool_id, window, min, max } => {
+                if *window == 0 {
+                    return Err(ValidationError::UnsupportedCheck);
+                }
+                let tick = facts
+                    .twap_tick(*pool_id, *window)
+                    .map_err(|_| ValidationError::TickOutOfBounds)?;
+                if tick < *min || tick > *max {
+                    return Err(ValidationError::TickOutOfBounds);
+                }
+            }
+            Check::OracleDeviationLte { pool_id, oracle, selector, decimals, max_bps } => {
+                let slot0 = facts
+                    .get_slot0(*pool_id)
+                    .map_err(|_| ValidationError::OracleDeviationExceeded)?;
+                let reference = facts
+                    .staticcall_u256(*oracle, *selector, &[])
+                    .map_err(|_| ValidationError::OracleDeviationExceeded)?;
+                // Fail closed on prices that cannot be compared.
+                let deviation = sqrt_price_x96_to_wad(slot0.sqrt_price_x96)
+                    .zip(to_wad(reference, *decimals))
+                    .and_then(|(spot, reference)| deviation_bps(spot, reference))
+                    .ok_or(ValidationError::OracleDeviationExceeded)?;
+                if deviation > U256::from(*max_bps) {
+                    return Err(ValidationError::OracleDeviationExceeded);
+                }
+            }
+            Check::PoolAllowed { .. } => {
+                // Enforced by caller against the install-time pool allowlist.
+            }
             Check::RfsClosed { position_id } => {
                 let closed = facts
                     .is_rfs_closed(*position_id)
@@ -132,6 +180,32 @@ pub fn evaluate_program<F: FactsProvider>(
                     return Err(ValidationError::StaticCallFailed);
                 }
             }
+            Check::QueueSumLte { lcc, owners, max } => {
+                let mut total = U256::ZERO;
+                for owner in owners {
+                    let queued = facts
+                        .queue_amount(*lcc, *owner)
+                        .map_err(|_| ValidationError::QueueExceeded)?;
+                    total = total.saturating_add(queued);
+                }
+                if total > *max {
+                    return Err(ValidationError::QueueExceeded);
+                }
+            }
+            Check::HubSolvency { lcc, min_ratio_bps } => {
+                let reserve = facts
+                    .reserve_of(*lcc)
+                    .map_err(|_| ValidationError::HubInsolvent)?;
+                let queue = facts
+                    .queue_of_underlying(*lcc)
+                    .map_err(|_| ValidationError::HubInsolvent)?;
+                // An empty queue is covered by any reserve.
+                if let Some(ratio) = solvency_ratio_bps(reserve, queue) {
+                    if ratio < U256::from(*min_ratio_bps) {
+                        return Err(ValidationError::HubInsolvent);
+                    }
+                }
+            }
             Check::StaticCallU256 {
                 target,
                 selector,
@@ -146,6 +220,28 @@ pub fn evaluate_program<F: FactsProvider>(
                     return Err(ValidationError::StaticCallFailed);
                 }
             }
+            Check::StaticCallCompare {
+                lhs_target,
+                lhs_selector,
+                lhs_args,
+                op,
+                rhs_target,
+                rhs_selector,
+                rhs_args,
+                scale_bps: bps,
+            } => {
+                let lhs = facts
+                    .staticcall_u256(*lhs_target, *lhs_selector, lhs_args)
+                    .map_err(|_| ValidationError::StaticCallFailed)?;
+                let rhs = facts
+                    .staticcall_u256(*rhs_target, *rhs_selector, rhs_args)
+                    .map_err(|_| ValidationError::StaticCallFailed)?;
+                // Fail closed if scaling overflows.
+                let rhs = scale_bps(rhs, *bps).ok_or(ValidationError::StaticCallFailed)?;
+                if !compare(lhs, *op, rhs) {
+                    return Err(ValidationError::StaticCallFailed);
+                }
+            }
         }
     }
     Ok(())
@@ -2,12 +2,16 @@ use alloc::{collections::BTreeSet, vec::Vec};
 
 use stylus_sdk::{
     alloy_primitives::{keccak256, Address, FixedBytes, U256},
-    call::RawCall,
+    stylus_core::{calls::context::Call, Host},
 };
 
 use crate::{
     errors::FactsError,
-    types::facts::{FactsProvider, Slot0},
+    types::facts::{
+        observe_args, twap_tick_from_observe, FactSource, FactsProvider, Slot0, UserOpFields,
+        OBSERVE_SIG,
+    },
+    types::opcodes::UserOpField,
 };
 
 /// Canonical fact sources for the validator (per Kernel smart account).
@@ -18,63 +22,163 @@ pub struct FactSources {
     pub liquidity_hub: Address,
 }
 
+impl FactSources {
+    pub fn get(&self, source: FactSource) -> Address {
+        match source {
+            FactSource::StateView => self.state_view,
+            FactSource::VtsOrchestrator => self.vts_orchestrator,
+            FactSource::LiquidityHub => self.liquidity_hub,
+        }
+    }
+
+    /// These sources with `source` read from `target` instead.
+    pub fn with(mut self, source: FactSource, target: Address) -> Self {
+        match source {
+            FactSource::StateView => self.state_view = target,
+            FactSource::VtsOrchestrator => self.vts_orchestrator = target,
+            FactSource::LiquidityHub => self.liquidity_hub = target,
+        }
+        self
+    }
+}
+
+/// Every kind of fact source.
+pub const FACT_SOURCES: [FactSource; 3] = [
+    FactSource::StateView,
+    FactSource::VtsOrchestrator,
+    FactSource::LiquidityHub,
+];
+
+/// The calls each kind of fact source is allowlisted for.
+pub fn source_calls(source: FactSource) -> &'static [&'static str] {
+    match source {
+        FactSource::StateView => &["getSlot0(bytes32)", OBSERVE_SIG],
+        FactSource::VtsOrchestrator => &[
+            "positionToCheckpoint(bytes32)",
+            "getPositionSettledAmounts(bytes32)",
+            "getCommitmentMaxima(bytes32)",
+            "getPosition(bytes32)",
+            // PoolId is bytes32.
+            "getPool(bytes32)",
+        ],
+        FactSource::LiquidityHub => &[
+            "reserveOfUnderlying(address)",
+            "settleQueue(address,address)",
+            "queueOfUnderlying(address)",
+        ],
+    }
+}
+
+/// Expected `extcodehash` of each fact source; zero leaves that source unpinned.
+///
+/// Pinning catches the code at a source address changing, eg a non-proxy orchestrator replaced by
+/// a proxy or a metamorphic redeploy. The code of an ERC-1967 proxy itself stays the same when its
+/// implementation is upgraded, so configure sources that are not behind such a proxy where the
+/// implementation must not change under signed intents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FactSourceCodehashes {
+    pub state_view: FixedBytes<32>,
+    pub vts_orchestrator: FixedBytes<32>,
+    pub liquidity_hub: FixedBytes<32>,
+}
+
 /// On-chain facts provider that uses `staticcall` with a strict allowlist and per-call gas cap.
-pub struct OnchainFactsProvider {
+pub struct OnchainFactsProvider<'a> {
+    pub vm: &'a dyn Host,
     pub sources: FactSources,
+    /// The configured sources `codehashes` pin, which [`Self::routed`] leaves in place.
+    pub pinned: FactSources,
+    pub codehashes: FactSourceCodehashes,
+    /// The override source [`Self::routed`] reads from and the codehash it was pinned to.
+    pub routed_pin: Option<(Address, FixedBytes<32>)>,
     pub gas_cap: u64,
     pub now: u64,
     pub allowlist: BTreeSet<(Address, [u8; 4])>,
+    /// The UserOperation being validated, for `CheckUserOpField`.
+    pub user_op: Option<UserOpFields>,
 }
 
-impl OnchainFactsProvider {
-    pub fn new(sources: FactSources, gas_cap: u64, now: u64) -> Self {
+impl<'a> OnchainFactsProvider<'a> {
+    pub fn new(vm: &'a dyn Host, sources: FactSources, gas_cap: u64, now: u64) -> Self {
         let mut allowlist = BTreeSet::new();
-
-        // StateView.getSlot0(bytes32)
-        allowlist.insert((sources.state_view, selector("getSlot0(bytes32)")));
-
-        // VTSOrchestrator.positionToCheckpoint(bytes32)
-        allowlist.insert((
-            sources.vts_orchestrator,
-            selector("positionToCheckpoint(bytes32)"),
-        ));
-        // VTSOrchestrator.getPositionSettledAmounts(bytes32)
-        allowlist.insert((
-            sources.vts_orchestrator,
-            selector("getPositionSettledAmounts(bytes32)"),
-        ));
-        // VTSOrchestrator.getCommitmentMaxima(bytes32)
-        allowlist.insert((
-            sources.vts_orchestrator,
-            selector("getCommitmentMaxima(bytes32)"),
-        ));
-        // VTSOrchestrator.getPosition(bytes32)
-        allowlist.insert((
-            sources.vts_orchestrator,
-            selector("getPosition(bytes32)"),
-        ));
-        // VTSOrchestrator.getPool(bytes32)  (PoolId is bytes32)
-        allowlist.insert((sources.vts_orchestrator, selector("getPool(bytes32)")));
-
-        // LiquidityHub.reserveOfUnderlying(address)
-        allowlist.insert((
-            sources.liquidity_hub,
-            selector("reserveOfUnderlying(address)"),
-        ));
-        // LiquidityHub.settleQueue(address,address)
-        allowlist.insert((
-            sources.liquidity_hub,
-            selector("settleQueue(address,address)"),
-        ));
+        for source in FACT_SOURCES {
+            for sig in source_calls(source) {
+                allowlist.insert((sources.get(source), selector(sig)));
+            }
+        }
 
         Self {
+            vm,
             sources,
+            pinned: sources,
+            codehashes: FactSourceCodehashes::default(),
+            routed_pin: None,
             gas_cap,
             now,
             allowlist,
+            user_op: None,
         }
     }
 
+    /// Answer `CheckUserOpField` from `user_op`; without it those checks fail.
+    pub fn with_user_op(mut self, user_op: UserOpFields) -> Self {
+        self.user_op = Some(user_op);
+        self
+    }
+
+    /// Also allow `calls`, eg the oracle calls a permission allowlisted at install.
+    pub fn with_allowed_calls(
+        mut self,
+        calls: impl IntoIterator<Item = (Address, [u8; 4])>,
+    ) -> Self {
+        self.allowlist.extend(calls);
+        self
+    }
+
+    /// Verify each source's `extcodehash` against `codehashes` before trusting its return data.
+    pub fn with_codehashes(mut self, codehashes: FactSourceCodehashes) -> Self {
+        self.codehashes = codehashes;
+        self
+    }
+
+    /// A copy reading `source` from `target`, for the checks an install-time source override
+    /// routes there. `target` takes over that source's calls, so the configured address loses
+    /// them, and its code must still hash to `codehash`, the pin taken at install. The other
+    /// pins stay on the configured addresses.
+    pub fn routed(&self, source: FactSource, target: Address, codehash: FixedBytes<32>) -> Self {
+        let mut allowlist = self.allowlist.clone();
+        for sig in source_calls(source) {
+            allowlist.remove(&(self.sources.get(source), selector(sig)));
+            allowlist.insert((target, selector(sig)));
+        }
+        Self {
+            vm: self.vm,
+            sources: self.sources.with(source, target),
+            pinned: self.pinned,
+            codehashes: self.codehashes,
+            routed_pin: Some((target, codehash)),
+            gas_cap: self.gas_cap,
+            now: self.now,
+            allowlist,
+            user_op: self.user_op,
+        }
+    }
+
+    fn check_codehash(&self, target: Address) -> Result<(), FactsError> {
+        let pins = [
+            (self.pinned.state_view, self.codehashes.state_view),
+            (self.pinned.vts_orchestrator, self.codehashes.vts_orchestrator),
+            (self.pinned.liquidity_hub, self.codehashes.liquidity_hub),
+        ];
+        for (source, expected) in pins.into_iter().chain(self.routed_pin) {
+            let pinned = source == target && expected != FixedBytes::ZERO;
+            if pinned && self.vm.code_hash(target) != expected {
+                return Err(FactsError::CodehashMismatch { target });
+            }
+        }
+        Ok(())
+    }
+
     fn staticcall(
         &self,
         target: Address,
@@ -84,22 +188,31 @@ impl OnchainFactsProvider {
         if !self.allowlist.contains(&(target, selector)) {
             return Err(FactsError::ForbiddenCall { target, selector });
         }
+        self.check_codehash(target)?;
         let mut data = Vec::with_capacity(4 + args.len());
         data.extend_from_slice(&selector);
         data.extend_from_slice(args);
 
         // bytes-in, bytes-out staticcall with gas cap.
-        let out = unsafe { RawCall::new_static().gas(self.gas_cap).call(target, &data) }
+        let out = self
+            .vm
+            .static_call(&Call::new().gas(self.gas_cap), target, &data)
             .map_err(|_| FactsError::CallFailed)?;
         Ok(out)
     }
 }
 
-impl FactsProvider for OnchainFactsProvider {
+impl FactsProvider for OnchainFactsProvider<'_> {
     fn block_timestamp(&self) -> u64 {
         self.now
     }
 
+    fn user_op_field(&self, field: UserOpField) -> Result<U256, FactsError> {
+        self.user_op
+            .map(|user_op| user_op.get(field))
+            .ok_or(FactsError::NotImplemented)
+    }
+
     fn get_slot0(&self, pool_id: FixedBytes<32>) -> Result<Slot0, FactsError> {
         let out = self.staticcall(
             self.sources.state_view,
@@ -128,6 +241,15 @@ impl FactsProvider for OnchainFactsProvider {
         })
     }
 
+    fn twap_tick(&self, pool_id: FixedBytes<32>, window: u32) -> Result<i32, FactsError> {
+        let out = self.staticcall(
+            self.sources.state_view,
+            selector(OBSERVE_SIG),
+            &observe_args(pool_id, window),
+        )?;
+        twap_tick_from_observe(&out, window)
+    }
+
     fn is_rfs_closed(&self, position_id: FixedBytes<32>) -> Result<bool, FactsError> {
         // positionToCheckpoint(bytes32) returns (uint256 timeOfLastTransition, bool isOpen, uint256, uint256)
         let out = self.staticcall(
@@ -174,6 +296,20 @@ impl FactsProvider for OnchainFactsProvider {
         Ok(U256::from_be_slice(&out[0..32]))
     }
 
+    fn queue_of_underlying(&self, lcc: Address) -> Result<U256, FactsError> {
+        let mut args = [0u8; 32];
+        args[12..32].copy_from_slice(lcc.as_slice());
+        let out = self.staticcall(
+            self.sources.liquidity_hub,
+            selector("queueOfUnderlying(address)"),
+            &args,
+        )?;
+        if out.len() < 32 {
+            return Err(FactsError::MalformedReturn);
+        }
+        Ok(U256::from_be_slice(&out[0..32]))
+    }
+
     fn get_settled_amounts(&self, position_id: FixedBytes<32>) -> Result<(U256, U256), FactsError> {
         // getPositionSettledAmounts(bytes32) returns (uint256 amount0, uint256 amount1)
         let out = self.staticcall(
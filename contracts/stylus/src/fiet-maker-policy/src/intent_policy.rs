@@ -9,46 +9,130 @@
 //!   and evaluates a check-program over on-chain facts.
 //! - Kernel slices a per-policy signature blob into `userOp.signature` before calling
 //!   `checkUserOpPolicy`; this policy treats `userOp.signature` as its envelope payload.
+//! - The same contract is also an ERC-7579 hook (module type 4) for checks that can only be
+//!   decided after execution: validation records the wallet's balances and `postCheck` bounds how
+//!   far they fell.
 
 use alloc::vec::Vec;
 
 use stylus_sdk::{
+    abi::Bytes,
     alloy_primitives::{keccak256, Address, FixedBytes, U256},
     prelude::*,
+    stylus_core::log,
 };
 
 use alloy_sol_types::sol;
+use fiet_maker_policy_types::{ByteReader, UnexpectedEnd, UserOpFields};
 use stylus_sdk::stylus_proc::SolidityError;
 
 use crate::{
-    decoder::decode_program,
+    decoder::{decode_program, order_by_cost},
     evaluator::evaluate_program,
-    facts::onchain::{FactSources, OnchainFactsProvider},
-    kernel::constants::{MODULE_TYPE_POLICY, POLICY_FAILED_UINT, POLICY_SUCCESS_UINT},
+    facts::onchain::{FactSourceCodehashes, FactSources, OnchainFactsProvider},
+    kernel::constants::{
+        MODULE_TYPE_HOOK, MODULE_TYPE_POLICY, POLICY_FAILED_UINT, POLICY_SUCCESS_UINT,
+    },
+    types::opcodes::{routed_call_target_count, Check, Opcode, MAX_CALL_TARGETS},
     utils::{
-        crypto::ecrecover_address,
-        kernel::{composite_key, split_policy_install_data},
-        policy_envelope::{parse_policy_envelope, policy_intent_digest},
+        crypto::{ecrecover_address, verify_threshold, verify_webauthn, SignerSet},
+        init_config::{
+            is_abi_init_data, packed_init_data, INIT_EXT_CODEHASHES, INIT_EXT_DOMAIN,
+            INIT_EXT_DOMAIN_HASHES, INIT_EXT_FREEZE, INIT_EXT_ORACLES, INIT_EXT_POOLS,
+            INIT_EXT_SIGNER_SET, INIT_EXT_SOURCE_OVERRIDES,
+        },
+        kernel::{
+            composite_key, execute_calls, hooked_execution, is_bootstrap_install_data,
+            permission_id_slot, split_batch_install_data, split_bootstrap_install_data,
+            split_policy_install_data, BATCH_INSTALL_ID,
+        },
+        policy_envelope::{
+            parse_policy_envelope, policy_intent_digest_in, policy_typed_intent_digest_in,
+            EnvelopeSignature, IntentDomain, ENVELOPE_VERSION_PROGRAM_HASH,
+            ENVELOPE_VERSION_TYPED_CHECKS,
+        },
+        pool_allowlist::{
+            call_pools, declares_pool, pack_oracle_call, pack_source_override, pool_allowed_key,
+            pool_allowlist_slot, referenced_pools, unpack_oracle_call, unpack_source_override,
+        },
+        token_delta::{
+            erc20_balance_of, pending_delta_key, pending_deltas, pending_op_key, spend_bucket_key,
+            window_buckets, PendingDelta, DAY, HOUR,
+        },
     },
 };
 
 sol! {
     error AlreadyInitialized(address smartAccount);
     error NotInitialized(address smartAccount);
+    error Unauthorized(address caller);
+    error TokenOutflowExceeded(address token, uint256 outflow, uint256 maxOut);
+    error BalanceUnavailable(address token);
+    error WindowSpendExceeded(address token, uint256 spent, uint256 max);
+    error PermissionFrozen(address smartAccount, bytes32 permissionId);
+
+    /// Emitted when `checkUserOpPolicy` passes, so executed UserOps can be matched to the signed
+    /// intent that authorised them.
+    event IntentValidated(
+        address indexed wallet,
+        bytes32 indexed permissionId,
+        bytes32 indexed envelopeDigest,
+        bytes32 programHash,
+        uint256 nonce,
+        uint64 deadline
+    );
+
+    /// Emitted for every permission `onInstall` configures (once per entry of a batch install).
+    /// `signer` is zero for passkey installs.
+    event PermissionInstalled(
+        address indexed wallet,
+        bytes32 indexed permissionId,
+        address signer,
+        address stateView,
+        address vtsOrchestrator,
+        address liquidityHub
+    );
+
+    /// Emitted when `onUninstall` clears a permission.
+    event PermissionUninstalled(address indexed wallet, bytes32 indexed permissionId);
+
+    /// Emitted by `revokeNonce`; `nonce` is the new replay nonce, so every envelope signed for a
+    /// lower one is void.
+    event NonceRevoked(
+        address indexed wallet,
+        bytes32 indexed permissionId,
+        address indexed caller,
+        uint256 nonce
+    );
+
+    /// Emitted when a permission's config is frozen, by the `0x07` install extension or by
+    /// `freezeConfig`.
+    event ConfigFrozen(address indexed wallet, bytes32 indexed permissionId);
 }
 
 #[derive(SolidityError)]
 pub enum ModuleError {
     AlreadyInitialized(AlreadyInitialized),
     NotInitialized(NotInitialized),
+    Unauthorized(Unauthorized),
+    TokenOutflowExceeded(TokenOutflowExceeded),
+    BalanceUnavailable(BalanceUnavailable),
+    WindowSpendExceeded(WindowSpendExceeded),
+    PermissionFrozen(PermissionFrozen),
 }
 
 sol_storage! {
     /// Kernel-compatible policy storage (scoped by wallet + permissionId).
     #[entrypoint]
     pub struct IntentPolicy {
-        /// Number of installed permission ids for a wallet (for `isInitialized`).
+        /// Number of installed permission ids for a wallet (for `isInitialized`), which is also
+        /// the length of its `permissionIdsOf` list.
         mapping(address => uint256) used_ids;
+        /// Installed permission ids by `permission_id_slot(wallet, index)`, and each one's
+        /// position in that list plus one by composite key (zero = not listed). Uninstalling
+        /// moves the last id into the freed slot, so the list stays dense but unordered.
+        mapping(bytes32 => bytes32) permission_id_at;
+        mapping(bytes32 => uint256) permission_index_of;
 
         /// Replay nonce for (wallet, permissionId).
         mapping(bytes32 => uint256) nonce_of;
@@ -60,10 +144,75 @@ sol_storage! {
         /// signature slice (e.g. weaken `program_bytes`) without changing `callData`.
         mapping(bytes32 => address) signer_of;
 
+        /// Authorised passkey (P-256 public key `x`, `y`) for (wallet, permissionId), set instead
+        /// of `signer_of` by a v2 install. Envelopes are then signed as WebAuthn assertions.
+        mapping(bytes32 => bytes32) passkey_x_of;
+        mapping(bytes32 => bytes32) passkey_y_of;
+
+        /// k-of-n signer set for (wallet, permissionId) from the `0x08` extension: the threshold
+        /// (zero when there is none), the member count and the members by
+        /// `pool_allowlist_slot(key, index)`. With a set, envelopes need threshold signatures.
+        mapping(bytes32 => uint256) signer_threshold_of;
+        mapping(bytes32 => uint256) signer_set_len_of;
+        mapping(bytes32 => address) signer_set_at;
+
+        /// EIP-712 domain name/version hashes for (wallet, permissionId); zero means the default
+        /// ("Fiet Maker Intent Policy", "1").
+        mapping(bytes32 => bytes32) domain_name_hash_of;
+        mapping(bytes32 => bytes32) domain_version_hash_of;
+
         /// Canonical fact sources for (wallet, permissionId).
         mapping(bytes32 => address) state_view_of;
         mapping(bytes32 => address) vts_orchestrator_of;
         mapping(bytes32 => address) liquidity_hub_of;
+
+        /// Expected `extcodehash` of each fact source for (wallet, permissionId); zero = unpinned.
+        mapping(bytes32 => bytes32) state_view_codehash_of;
+        mapping(bytes32 => bytes32) vts_orchestrator_codehash_of;
+        mapping(bytes32 => bytes32) liquidity_hub_codehash_of;
+
+        /// Install-time pool allowlist for (wallet, permissionId): its length (zero = no
+        /// allowlist), the pools by `pool_allowlist_slot(key, index)` and membership by
+        /// `pool_allowed_key(key, poolId)`.
+        mapping(bytes32 => uint256) pool_allowlist_len_of;
+        mapping(bytes32 => bytes32) pool_allowlist_at;
+        mapping(bytes32 => bool) pool_allowed;
+
+        /// Oracle calls (`pack_oracle_call(target, selector)`) allowlisted at install for
+        /// (wallet, permissionId), by `pool_allowlist_slot(key, index)`.
+        mapping(bytes32 => uint256) oracle_call_count_of;
+        mapping(bytes32 => bytes32) oracle_call_at;
+
+        /// Fact-source overrides (`pack_source_override(opcode, source)`) installed for
+        /// (wallet, permissionId), by `pool_allowlist_slot(key, index)`.
+        mapping(bytes32 => uint256) source_override_count_of;
+        mapping(bytes32 => bytes32) source_override_at;
+        /// The `extcodehash` each override's source had at install, at the same index.
+        mapping(bytes32 => bytes32) source_override_codehash_at;
+
+        /// Whether the wallet has installed this contract as a hook (module type 4).
+        mapping(address => bool) hook_installed_of;
+        /// Whether the wallet is between the hook's `preCheck` and `postCheck`.
+        mapping(address => bool) hook_executing_of;
+
+        /// `CheckWalletTokenDeltaLte` / `CheckWindowSpendLte` bounds recorded during validation
+        /// for the hook to enforce after execution: the count per `pending_op_key(wallet,
+        /// executionHash)`, and per `pending_delta_key(opKey, index)` the token, its balance then,
+        /// the bound, the spend window (zero for a per-execution bound) and the permission's key.
+        mapping(bytes32 => uint256) pending_delta_count;
+        mapping(bytes32 => address) pending_delta_token;
+        mapping(bytes32 => uint256) pending_delta_before;
+        mapping(bytes32 => uint256) pending_delta_max_out;
+        mapping(bytes32 => uint256) pending_delta_window;
+        mapping(bytes32 => bytes32) pending_delta_permission;
+
+        /// Token outflow per `spend_bucket_key(permissionKey, token, size, index)`, hourly and
+        /// daily, for `CheckWindowSpendLte`.
+        mapping(bytes32 => uint256) spend_bucket_of;
+
+        /// Whether (wallet, permissionId)'s config is frozen. Never cleared, not even by
+        /// uninstall, so the permission id cannot be reinstalled with a different config.
+        mapping(bytes32 => bool) config_frozen_of;
     }
 }
 
@@ -73,59 +222,102 @@ impl IntentPolicy {
     ///
     /// Mirrors Kernel `PolicyBase` packing: `bytes data = bytes32 permissionId || initData`.
     ///
-    /// `initData` layout:
+    /// `initData` layout (v1, secp256k1 envelope signer):
     /// - `uint8 version = 1`
     /// - `bytes20 signer` (authorised envelope signer)
     /// - `bytes20 stateView`
     /// - `bytes20 vtsOrchestrator`
     /// - `bytes20 liquidityHub`
+    ///
+    /// `initData` layout (v2, passkey envelope signer):
+    /// - `uint8 version = 2`
+    /// - `bytes32 passkeyX`, `bytes32 passkeyY` (P-256 public key)
+    /// - `bytes20 stateView`
+    /// - `bytes20 vtsOrchestrator`
+    /// - `bytes20 liquidityHub`
+    ///
+    /// Either version may be followed by optional extensions, each `uint8 tag || payload`, in
+    /// increasing tag order:
+    /// - `0x01` EIP-712 domain override, for white-labelled deployments or a new envelope revision
+    ///   (reinstall to rotate it): `uint8 nameLen || name || uint8 versionLen || version`
+    /// - `0x02` fact-source codehash pins: `bytes32 stateView || bytes32 vtsOrchestrator ||
+    ///   bytes32 liquidityHub` expected `extcodehash` values (zero leaves a source unpinned)
+    /// - `0x03` pool allowlist: `uint8 count || bytes32[count] poolIds` (`count > 0`); programs
+    ///   may then only reference these pools and must declare one with `CheckPoolAllowed`, and
+    ///   `callData` must be a Kernel `execute` whose every call passes only these pools
+    /// - `0x04` oracle calls: `uint8 count || (bytes20 target || bytes4 selector)[count]`
+    ///   (`count > 0`), staticcalls allowed as `CheckOracleDeviationLte` price sources
+    /// - `0x05` fact-source overrides: `uint8 count || (uint8 opcode || bytes20 source)[count]`
+    ///   (`count > 0`); checks with `opcode` read the facts they would read from the configured
+    ///   source of that kind from `source` instead, eg a second orchestrator during a migration.
+    ///   Each opcode at most once, only opcodes that read a fact source, and each source must
+    ///   have code: its `extcodehash` is pinned at install and checked on every routed read
+    /// - `0x06` EIP-712 domain override as hashes: `bytes32 nameHash || bytes32 versionHash`
+    ///   (non-zero name hash, not combined with `0x01`); what `exportConfig` writes, since only
+    ///   the hashes are stored
+    /// - `0x07` freeze (no payload): the config can never change, see `freezeConfig`
+    /// - `0x08` signer set: `uint8 threshold || uint8 count || bytes20[count] signers`, distinct
+    ///   non-zero signers with `1 <= threshold <= count`, v1 installs only, and `signer` must be
+    ///   one of them. Envelopes must then carry a threshold signature (`0xff || (r||s||v)[n]`)
+    ///   from at least `threshold` members; a lone 65-byte signature no longer validates.
+    ///   `signer` stays the member that may call `revokeNonce`
+    ///
+    /// `initData` may instead be `abi.encode(InitConfigV2)` (see `utils::init_config`), told apart
+    /// by its leading zero byte and installed exactly as the equivalent packed layout.
+    ///
+    /// Several permissions install in one call when `permissionId` is `BATCH_INSTALL_ID`
+    /// (`0xff..ff`) and is followed by `uint8 count || (bytes32 permissionId || uint16 initDataLen
+    /// || initData)[count]` (`count > 0`). Each entry installs as above, and any failure reverts
+    /// them all.
+    ///
+    /// Empty `data` installs the contract as the wallet's hook, which needs no configuration.
+    /// Programs with token-delta or window-spend checks only validate while it is installed.
+    ///
+    /// ERC-7579 bootstrap / launchpad payloads are unwrapped first: `abi.encode(uint256
+    /// moduleTypeId, bytes data)` or `abi.encode(uint256[] moduleTypeIds, bytes[] datas)` (see
+    /// `utils::kernel::is_bootstrap_install_data`). Each policy-type entry's `data` installs as
+    /// above, hook-type entries are accepted as is, and any other module type reverts.
     #[payable]
     pub fn on_install(&mut self, data: Vec<u8>) -> Result<(), ModuleError> {
         let wallet = self.vm().msg_sender();
-        // Keep revert semantics deterministic; panic on malformed init data.
-        let (permission_id, init_data) =
-            split_policy_install_data(&data).unwrap_or_else(|_| panic!("Invalid init data"));
-
-        let key = composite_key(wallet, permission_id);
-        if self._is_installed_key(key) {
-            return Err(ModuleError::AlreadyInitialized(AlreadyInitialized {
-                smartAccount: wallet,
-            }));
-        }
-
-        if init_data.len() != 1 + 20 + 20 + 20 + 20 {
-            panic!("Invalid init data length");
+        if data.is_empty() {
+            self.hook_installed_of.insert(wallet, true);
+            return Ok(());
         }
-        let version = init_data[0];
-        if version != 1 {
-            panic!("Unsupported init version");
+        if !is_bootstrap_install_data(&data) {
+            return self._install_data(wallet, &data);
         }
 
-        let signer = Address::from_slice(&init_data[1..21]);
-        let state_view = Address::from_slice(&init_data[21..41]);
-        let vts_orchestrator = Address::from_slice(&init_data[41..61]);
-        let liquidity_hub = Address::from_slice(&init_data[61..81]);
-
-        if signer == Address::ZERO {
-            panic!("Invalid signer");
+        let entries = split_bootstrap_install_data(&data)
+            .unwrap_or_else(|| panic!("Invalid bootstrap data"));
+        for (module_type, data) in entries {
+            match module_type {
+                MODULE_TYPE_POLICY => {
+                    if is_bootstrap_install_data(&data) {
+                        panic!("Invalid bootstrap data");
+                    }
+                    self._install_data(wallet, &data)?;
+                }
+                // Hook installs carry no configuration.
+                MODULE_TYPE_HOOK => self.hook_installed_of.insert(wallet, true),
+                _ => panic!("Unsupported module type"),
+            }
         }
-        if state_view == Address::ZERO || vts_orchestrator == Address::ZERO || liquidity_hub == Address::ZERO {
-            panic!("Invalid fact sources");
-        }
-
-        self.nonce_of.insert(key, U256::ZERO);
-        self.signer_of.insert(key, signer);
-        self.state_view_of.insert(key, state_view);
-        self.vts_orchestrator_of.insert(key, vts_orchestrator);
-        self.liquidity_hub_of.insert(key, liquidity_hub);
-        self.used_ids.insert(wallet, self.used_ids.get(wallet).saturating_add(U256::from(1u64)));
         Ok(())
     }
 
     /// ERC-7579 uninstall hook.
+    ///
+    /// Empty `data` uninstalls the hook, after which programs with token-delta or window-spend
+    /// checks fail validation. Uninstalling a frozen permission is allowed, but its freeze stays
+    /// (see `freezeConfig`).
     #[payable]
     pub fn on_uninstall(&mut self, data: Vec<u8>) -> Result<(), ModuleError> {
         let wallet = self.vm().msg_sender();
+        if data.is_empty() {
+            self.hook_installed_of.insert(wallet, false);
+            return Ok(());
+        }
         // Keep revert semantics deterministic; panic on malformed uninstall data.
         let (permission_id, _init_data) =
             split_policy_install_data(&data).unwrap_or_else(|_| panic!("Invalid uninstall data"));
@@ -139,16 +331,28 @@ impl IntentPolicy {
 
         self.nonce_of.insert(key, U256::ZERO);
         self.signer_of.insert(key, Address::ZERO);
+        self.passkey_x_of.insert(key, FixedBytes::ZERO);
+        self.passkey_y_of.insert(key, FixedBytes::ZERO);
+        self.domain_name_hash_of.insert(key, FixedBytes::ZERO);
+        self.domain_version_hash_of.insert(key, FixedBytes::ZERO);
+        self.state_view_codehash_of.insert(key, FixedBytes::ZERO);
+        self.vts_orchestrator_codehash_of.insert(key, FixedBytes::ZERO);
+        self.liquidity_hub_codehash_of.insert(key, FixedBytes::ZERO);
+        self._clear_pool_allowlist(key);
+        self._clear_oracle_calls(key);
+        self._clear_source_overrides(key);
+        self._clear_signer_set(key);
         self.state_view_of.insert(key, Address::ZERO);
         self.vts_orchestrator_of.insert(key, Address::ZERO);
         self.liquidity_hub_of.insert(key, Address::ZERO);
-        self.used_ids.insert(wallet, self.used_ids.get(wallet).saturating_sub(U256::from(1u64)));
+        self._unlist_permission(wallet, key);
+        log(self.vm(), PermissionUninstalled { wallet, permissionId: permission_id });
         Ok(())
     }
 
     /// ERC-7579 module-type detection.
     pub fn is_module_type(&self, module_type_id: U256) -> bool {
-        module_type_id == MODULE_TYPE_POLICY
+        module_type_id == MODULE_TYPE_POLICY || module_type_id == MODULE_TYPE_HOOK
     }
 
     /// ERC-7579 initialisation check (wallet-level).
@@ -156,10 +360,285 @@ impl IntentPolicy {
         self.used_ids.get(wallet) != U256::ZERO
     }
 
+    /// Permission ids installed for `wallet`, in no particular order.
+    pub fn permission_ids_of(&self, wallet: Address) -> Vec<FixedBytes<32>> {
+        let len = self.used_ids.get(wallet);
+        let mut ids = Vec::new();
+        let mut index = U256::ZERO;
+        while index < len {
+            ids.push(self.permission_id_at.get(permission_id_slot(wallet, index)));
+            index += U256::from(1u64);
+        }
+        ids
+    }
+
+    /// Authorised envelope signer for (wallet, permissionId); zero when not installed or when a
+    /// passkey signs instead.
+    pub fn signer_of(&self, wallet: Address, permission_id: FixedBytes<32>) -> Address {
+        self.signer_of.get(composite_key(wallet, permission_id))
+    }
+
+    /// Replay nonce the next envelope for (wallet, permissionId) must carry.
+    pub fn nonce_of(&self, wallet: Address, permission_id: FixedBytes<32>) -> U256 {
+        self.nonce_of.get(composite_key(wallet, permission_id))
+    }
+
+    /// Authorised passkey `(x, y)` for (wallet, permissionId); zero when not installed or when a
+    /// secp256k1 signer signs instead.
+    pub fn passkey_of(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> (FixedBytes<32>, FixedBytes<32>) {
+        let key = composite_key(wallet, permission_id);
+        (self.passkey_x_of.get(key), self.passkey_y_of.get(key))
+    }
+
+    /// k-of-n signer set `(threshold, signers)` for (wallet, permissionId); `(0, [])` when it has
+    /// none.
+    pub fn signer_set_of(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> (u8, Vec<Address>) {
+        let key = composite_key(wallet, permission_id);
+        (self.signer_threshold_of.get(key).to::<u8>(), self._signer_set(key))
+    }
+
+    /// EIP-712 domain `(nameHash, versionHash)` envelopes for (wallet, permissionId) are signed
+    /// under; all zero when not installed.
+    pub fn domain_of(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> (FixedBytes<32>, FixedBytes<32>) {
+        let key = composite_key(wallet, permission_id);
+        if !self._is_installed_key(key) {
+            return (FixedBytes::ZERO, FixedBytes::ZERO);
+        }
+        let domain = self._domain(key);
+        (domain.name_hash, domain.version_hash)
+    }
+
+    /// Fact sources `(stateView, vtsOrchestrator, liquidityHub)` for (wallet, permissionId);
+    /// all zero when not installed.
+    pub fn fact_sources_of(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> (Address, Address, Address) {
+        let key = composite_key(wallet, permission_id);
+        (
+            self.state_view_of.get(key),
+            self.vts_orchestrator_of.get(key),
+            self.liquidity_hub_of.get(key),
+        )
+    }
+
+    /// Pinned fact-source codehashes `(stateView, vtsOrchestrator, liquidityHub)` for
+    /// (wallet, permissionId); zero for unpinned sources and when not installed.
+    pub fn fact_source_codehashes_of(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> (FixedBytes<32>, FixedBytes<32>, FixedBytes<32>) {
+        let codehashes = self._codehashes(composite_key(wallet, permission_id));
+        (
+            codehashes.state_view,
+            codehashes.vts_orchestrator,
+            codehashes.liquidity_hub,
+        )
+    }
+
+    /// Install-time pool allowlist for (wallet, permissionId); empty when there is none.
+    pub fn pool_allowlist_of(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> Vec<FixedBytes<32>> {
+        let key = composite_key(wallet, permission_id);
+        let len = self.pool_allowlist_len_of.get(key);
+        let mut pools = Vec::new();
+        let mut index = U256::ZERO;
+        while index < len {
+            pools.push(self.pool_allowlist_at.get(pool_allowlist_slot(key, index)));
+            index += U256::from(1u64);
+        }
+        pools
+    }
+
+    /// Oracle calls `(target, selector)` allowlisted at install for (wallet, permissionId).
+    pub fn oracle_calls_of(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> Vec<(Address, FixedBytes<4>)> {
+        self._oracle_calls(composite_key(wallet, permission_id))
+            .into_iter()
+            .map(|(target, selector)| (target, FixedBytes(selector)))
+            .collect()
+    }
+
+    /// Fact-source overrides `(opcode, source)` installed for (wallet, permissionId).
+    pub fn source_overrides_of(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> Vec<(u8, Address)> {
+        self._source_overrides(composite_key(wallet, permission_id))
+    }
+
+    /// The install config of (wallet, permissionId) as packed `initData`: the signer or passkey,
+    /// the fact sources and every extension it was installed with (the domain as `0x06` hashes).
+    ///
+    /// Installing it on a redeployed policy, in the same batch that uninstalls this one,
+    /// migrates the permission in one transaction. The replay nonce is not part of it: envelopes
+    /// are bound to the policy address, so the new install starts at zero.
+    pub fn export_config(
+        &self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+    ) -> Result<Bytes, ModuleError> {
+        let key = composite_key(wallet, permission_id);
+        if !self._is_installed_key(key) {
+            return Err(ModuleError::NotInitialized(NotInitialized {
+                smartAccount: wallet,
+            }));
+        }
+
+        let mut out = Vec::new();
+        let signer = self.signer_of.get(key);
+        if signer != Address::ZERO {
+            out.push(1);
+            out.extend_from_slice(signer.as_slice());
+        } else {
+            out.push(2);
+            out.extend_from_slice(self.passkey_x_of.get(key).as_slice());
+            out.extend_from_slice(self.passkey_y_of.get(key).as_slice());
+        }
+        out.extend_from_slice(self.state_view_of.get(key).as_slice());
+        out.extend_from_slice(self.vts_orchestrator_of.get(key).as_slice());
+        out.extend_from_slice(self.liquidity_hub_of.get(key).as_slice());
+
+        let codehashes = self._codehashes(key);
+        if codehashes != FactSourceCodehashes::default() {
+            out.push(INIT_EXT_CODEHASHES);
+            out.extend_from_slice(codehashes.state_view.as_slice());
+            out.extend_from_slice(codehashes.vts_orchestrator.as_slice());
+            out.extend_from_slice(codehashes.liquidity_hub.as_slice());
+        }
+        // Each list was installed from a `uint8` count, so its length fits one.
+        let pools = self.pool_allowlist_of(wallet, permission_id);
+        if !pools.is_empty() {
+            out.push(INIT_EXT_POOLS);
+            out.push(pools.len() as u8);
+            for pool in pools {
+                out.extend_from_slice(pool.as_slice());
+            }
+        }
+        let calls = self._oracle_calls(key);
+        if !calls.is_empty() {
+            out.push(INIT_EXT_ORACLES);
+            out.push(calls.len() as u8);
+            for (target, selector) in calls {
+                out.extend_from_slice(target.as_slice());
+                out.extend_from_slice(&selector);
+            }
+        }
+        let overrides = self._source_overrides(key);
+        if !overrides.is_empty() {
+            out.push(INIT_EXT_SOURCE_OVERRIDES);
+            out.push(overrides.len() as u8);
+            for (opcode, source) in overrides {
+                out.push(opcode);
+                out.extend_from_slice(source.as_slice());
+            }
+        }
+        let name_hash = self.domain_name_hash_of.get(key);
+        if name_hash != FixedBytes::ZERO {
+            out.push(INIT_EXT_DOMAIN_HASHES);
+            out.extend_from_slice(name_hash.as_slice());
+            out.extend_from_slice(self.domain_version_hash_of.get(key).as_slice());
+        }
+        if self.config_frozen_of.get(key) {
+            out.push(INIT_EXT_FREEZE);
+        }
+        let signers = self._signer_set(key);
+        if !signers.is_empty() {
+            out.push(INIT_EXT_SIGNER_SET);
+            out.push(self.signer_threshold_of.get(key).to::<u8>());
+            out.push(signers.len() as u8);
+            for signer in signers {
+                out.extend_from_slice(signer.as_slice());
+            }
+        }
+        Ok(out.into())
+    }
+
+    /// Whether (wallet, permissionId)'s config is frozen.
+    pub fn is_config_frozen(&self, wallet: Address, permission_id: FixedBytes<32>) -> bool {
+        self.config_frozen_of.get(composite_key(wallet, permission_id))
+    }
+
+    /// Permanently freeze the calling wallet's config for `permissionId`.
+    ///
+    /// The signer and fact sources can only change by reinstalling, so a frozen permission id
+    /// cannot be installed again: it can still be uninstalled, but then stays unusable. Meant for
+    /// wallets that want a non-upgradable policy. The nonce can still be revoked.
+    pub fn freeze_config(&mut self, permission_id: FixedBytes<32>) -> Result<(), ModuleError> {
+        let wallet = self.vm().msg_sender();
+        let key = composite_key(wallet, permission_id);
+        if !self._is_installed_key(key) {
+            return Err(ModuleError::NotInitialized(NotInitialized {
+                smartAccount: wallet,
+            }));
+        }
+        if !self.config_frozen_of.get(key) {
+            self._freeze(wallet, key, permission_id);
+        }
+        Ok(())
+    }
+
+    /// Raise the replay nonce for (wallet, permissionId) to `max(nonce + 1, min_nonce)`,
+    /// invalidating every envelope signed for a lower nonce; returns the new nonce.
+    ///
+    /// Envelopes are often signed ahead (nonces N, N+1, ... queued at once), and burning only the
+    /// current nonce would leave the queued ones executable in turn. Pass one past the highest
+    /// nonce handed out to cancel all of them; zero burns just the current one.
+    ///
+    /// Callable by the wallet or by the permission's envelope signer, so a risk monitor holding
+    /// the signer key can cancel outstanding intents without a UserOp from the account. Passkey
+    /// installs have no signer address, so only the wallet can revoke.
+    pub fn revoke_nonce(
+        &mut self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        min_nonce: U256,
+    ) -> Result<U256, ModuleError> {
+        let key = composite_key(wallet, permission_id);
+        if !self._is_installed_key(key) {
+            return Err(ModuleError::NotInitialized(NotInitialized {
+                smartAccount: wallet,
+            }));
+        }
+        let caller = self.vm().msg_sender();
+        if caller != wallet && caller != self.signer_of.get(key) {
+            return Err(ModuleError::Unauthorized(Unauthorized { caller }));
+        }
+
+        let next = self.nonce_of.get(key).saturating_add(U256::from(1u64)).max(min_nonce);
+        self.nonce_of.insert(key, next);
+        log(self.vm(), NonceRevoked { wallet, permissionId: permission_id, caller, nonce: next });
+        Ok(next)
+    }
+
     /// Kernel `IPolicy.checkUserOpPolicy`.
     ///
     /// `user_op.signature` here is the policy-specific signature slice provided by Kernel’s
     /// PermissionValidator pipeline.
+    ///
+    /// Envelope version 1 signs `keccak256(program)`; version 2 signs the decoded checks as an
+    /// EIP-712 `Check[]` (see `policy_typed_intent_digest_in`) for wallets that display them.
     #[payable]
     pub fn check_user_op_policy(
         &mut self,
@@ -188,13 +667,13 @@ impl IntentPolicy {
         }
 
         let (
-            _sender,
-            _nonce,
+            sender,
+            nonce,
             _init_code,
             call_data,
-            _account_gas_limits,
-            _pre_verification_gas,
-            _gas_fees,
+            account_gas_limits,
+            pre_verification_gas,
+            gas_fees,
             _paymaster_and_data,
             policy_sig_bytes,
         ) = user_op;
@@ -204,7 +683,9 @@ impl IntentPolicy {
             Err(_) => return POLICY_FAILED_UINT,
         };
 
-        if env.version != 1u16 {
+        if env.version != ENVELOPE_VERSION_PROGRAM_HASH
+            && env.version != ENVELOPE_VERSION_TYPED_CHECKS
+        {
             return POLICY_FAILED_UINT;
         }
         if self.vm().block_timestamp() > env.deadline {
@@ -228,33 +709,68 @@ impl IntentPolicy {
         // Purpose: Kernel's permission pipeline passes each policy a policy-local signature slice.
         // Without an explicit signature over the envelope fields, an attacker could tamper with
         // `program_bytes` while keeping `callData` constant, effectively bypassing validation.
-        let expected_signer = self.signer_of.get(key);
-        if expected_signer == Address::ZERO {
-            return POLICY_FAILED_UINT;
-        }
-        let digest = policy_intent_digest(
-            self.vm().chain_id(),
-            self.vm().contract_address(),
-            wallet,
-            permission_id,
-            env.nonce,
-            env.deadline,
-            env.call_bundle_hash,
-            &env.program_bytes,
-        );
-        let recovered = match ecrecover_address(digest, &env.signature) {
-            Ok(a) => a,
+        // Typed-checks envelopes sign the decoded program, so it is decoded first.
+        let mut checks = match decode_program(&env.program_bytes) {
+            Ok(c) => c,
             Err(_) => return POLICY_FAILED_UINT,
         };
-        if recovered != expected_signer {
+        let domain = self._domain(key);
+        let digest = if env.version == ENVELOPE_VERSION_TYPED_CHECKS {
+            policy_typed_intent_digest_in(
+                &domain,
+                wallet,
+                permission_id,
+                env.nonce,
+                env.deadline,
+                env.call_bundle_hash,
+                &checks,
+            )
+        } else {
+            policy_intent_digest_in(
+                &domain,
+                wallet,
+                permission_id,
+                env.nonce,
+                env.deadline,
+                env.call_bundle_hash,
+                &env.program_bytes,
+            )
+        };
+        let threshold = self.signer_threshold_of.get(key);
+        let authorised = match &env.signature {
+            EnvelopeSignature::Ecdsa(signature) => {
+                let expected_signer = self.signer_of.get(key);
+                threshold == U256::ZERO
+                    && expected_signer != Address::ZERO
+                    && ecrecover_address(self.vm(), digest, signature) == Ok(expected_signer)
+            }
+            EnvelopeSignature::Threshold(signatures) => {
+                let signers = self._signer_set(key);
+                let set = SignerSet { signers: &signers, threshold: threshold.to::<usize>() };
+                verify_threshold(self.vm(), digest, signatures, &set)
+            }
+            EnvelopeSignature::WebAuthn(assertion) => {
+                let x = self.passkey_x_of.get(key);
+                let y = self.passkey_y_of.get(key);
+                (x != FixedBytes::ZERO || y != FixedBytes::ZERO)
+                    && verify_webauthn(self.vm(), digest, assertion, x, y)
+            }
+        };
+        if !authorised {
             return POLICY_FAILED_UINT;
         }
 
-        // Decode + evaluate program against atomic facts.
-        let checks = match decode_program(&env.program_bytes) {
-            Ok(c) => c,
-            Err(_) => return POLICY_FAILED_UINT,
-        };
+        // Order cheapest first and evaluate the program against atomic facts.
+        order_by_cost(&mut checks);
+        if !self._pools_allowed(key, &checks, &call_data) {
+            return POLICY_FAILED_UINT;
+        }
+        // Decoding capped the targets the program names; the overrides it routes to count too.
+        let overrides = self._pinned_source_overrides(key);
+        let routed_sources = overrides.iter().map(|&(opcode, source, _)| (opcode, source));
+        if routed_call_target_count(&checks, routed_sources) > MAX_CALL_TARGETS {
+            return POLICY_FAILED_UINT;
+        }
 
         let sources = FactSources {
             state_view: self.state_view_of.get(key),
@@ -268,15 +784,68 @@ impl IntentPolicy {
             return POLICY_FAILED_UINT;
         }
 
-        let facts = OnchainFactsProvider::new(sources, 200_000, self.vm().block_timestamp());
-        let ok = evaluate_program(&checks, &facts);
+        let facts = OnchainFactsProvider::new(
+            self.vm(),
+            sources,
+            200_000,
+            self.vm().block_timestamp(),
+        )
+        .with_codehashes(self._codehashes(key))
+        .with_allowed_calls(self._oracle_calls(key))
+        .with_user_op(UserOpFields {
+            sender,
+            nonce,
+            account_gas_limits,
+            pre_verification_gas,
+            gas_fees,
+        });
+        // Checks of an overridden opcode read its source from the override instead.
+        let routed: Vec<_> = overrides
+            .into_iter()
+            .filter_map(|(opcode, target, codehash)| {
+                let source = Opcode::try_from(opcode).ok()?.fact_source()?;
+                Some((opcode, facts.routed(source, target, codehash)))
+            })
+            .collect();
+        let ok = checks.iter().try_for_each(|check| {
+            let opcode = check.opcode() as u8;
+            let facts = routed
+                .iter()
+                .find(|(routed_opcode, _)| *routed_opcode == opcode)
+                .map_or(&facts, |(_, routed)| routed);
+            evaluate_program(core::slice::from_ref(check), facts)
+        });
         if ok.is_err() {
             return POLICY_FAILED_UINT;
         }
 
+        // Outflow bounds are enforced by the hook; without it they would pass unchecked. Kernel
+        // only runs the hook for `executeUserOp` UserOps, handing it the calldata they execute.
+        let deltas = pending_deltas(&checks);
+        if !deltas.is_empty() {
+            let recorded = hooked_execution(&call_data).is_some_and(|execution| {
+                self.hook_installed_of.get(wallet)
+                    && self._record_pending_deltas(wallet, keccak256(execution), key, &deltas)
+            });
+            if !recorded {
+                return POLICY_FAILED_UINT;
+            }
+        }
+
         // All checks passed; consume nonce.
         self.nonce_of
             .insert(key, expected_nonce.saturating_add(U256::from(1u64)));
+        log(
+            self.vm(),
+            IntentValidated {
+                wallet,
+                permissionId: permission_id,
+                envelopeDigest: digest,
+                programHash: keccak256(&env.program_bytes),
+                nonce: expected_nonce,
+                deadline: env.deadline,
+            },
+        );
 
         POLICY_SUCCESS_UINT
     }
@@ -293,12 +862,668 @@ impl IntentPolicy {
     ) -> U256 {
         POLICY_SUCCESS_UINT
     }
+
+    /// ERC-7579 `IHook.preCheck`. Balances are recorded during validation; this only passes
+    /// `keccak256(msgData)`, the hash the bounds were recorded under, on to `postCheck`. For a
+    /// UserOp, Kernel's `msgData` is the calldata it executes (`userOp.callData[4:]`).
+    #[payable]
+    pub fn pre_check(
+        &mut self,
+        _msg_sender: Address,
+        _msg_value: U256,
+        msg_data: Vec<u8>,
+    ) -> Vec<u8> {
+        let wallet = self.vm().msg_sender();
+        self.hook_executing_of.insert(wallet, true);
+        keccak256(&msg_data).to_vec()
+    }
+
+    /// ERC-7579 `IHook.postCheck`: enforce the outflow bounds pending for the calling wallet's
+    /// execution (its calldata hash is `hook_data`), then clear them. Reverts if a token's balance fell by
+    /// more than its `CheckWalletTokenDeltaLte` bound, if the outflow takes a
+    /// `CheckWindowSpendLte` window over its limit, or if a balance can no longer be read. Window
+    /// spend is booked once per (permission, token).
+    ///
+    /// A UserOp whose execution reverted leaves its bounds pending, and a UserOp executing the
+    /// same calldata fails validation until the wallet drops them with `discardPendingDeltas`.
+    #[payable]
+    pub fn post_check(&mut self, hook_data: Vec<u8>) -> Result<(), ModuleError> {
+        let wallet = self.vm().msg_sender();
+        self.hook_executing_of.insert(wallet, false);
+        if hook_data.len() != 32 {
+            return Ok(());
+        }
+        let op_key = pending_op_key(wallet, FixedBytes::from_slice(&hook_data));
+        let count = self.pending_delta_count.get(op_key);
+        let now = self.vm().block_timestamp();
+        let mut booked: Vec<(FixedBytes<32>, Address)> = Vec::new();
+        let mut index = U256::ZERO;
+        while index < count {
+            let key = pending_delta_key(op_key, index);
+            let token = self.pending_delta_token.get(key);
+            let after = erc20_balance_of(self.vm(), token, wallet)
+                .ok_or(ModuleError::BalanceUnavailable(BalanceUnavailable { token }))?;
+            let outflow = self.pending_delta_before.get(key).saturating_sub(after);
+            let max = self.pending_delta_max_out.get(key);
+            let window = self.pending_delta_window.get(key).saturating_to::<u32>();
+            if window == 0 {
+                if outflow > max {
+                    return Err(ModuleError::TokenOutflowExceeded(TokenOutflowExceeded {
+                        token,
+                        outflow,
+                        maxOut: max,
+                    }));
+                }
+            } else {
+                let permission = self.pending_delta_permission.get(key);
+                if !booked.contains(&(permission, token)) {
+                    self._book_spend(permission, token, now, outflow);
+                    booked.push((permission, token));
+                }
+                let spent = self._window_spend(permission, token, now, window);
+                if spent > max {
+                    return Err(ModuleError::WindowSpendExceeded(WindowSpendExceeded {
+                        token,
+                        spent,
+                        max,
+                    }));
+                }
+            }
+            index += U256::from(1u64);
+        }
+        self._clear_pending_deltas(op_key);
+        Ok(())
+    }
+
+    /// Drop the outflow bounds pending for the caller's execution of calldata hashing to
+    /// `execution_hash`, left by a UserOp whose execution reverted. Reverts while the hook is
+    /// running, so an execution cannot drop its own or another pending UserOp's bounds.
+    pub fn discard_pending_deltas(
+        &mut self,
+        execution_hash: FixedBytes<32>,
+    ) -> Result<(), ModuleError> {
+        let wallet = self.vm().msg_sender();
+        if self.hook_executing_of.get(wallet) {
+            return Err(ModuleError::Unauthorized(Unauthorized { caller: wallet }));
+        }
+        self._clear_pending_deltas(pending_op_key(wallet, execution_hash));
+        Ok(())
+    }
+
+    /// Number of token-delta bounds awaiting the hook for `wallet`'s execution of calldata
+    /// hashing to `execution_hash` (`keccak256(userOp.callData[4:])`).
+    pub fn pending_deltas_of(&self, wallet: Address, execution_hash: FixedBytes<32>) -> U256 {
+        self.pending_delta_count.get(pending_op_key(wallet, execution_hash))
+    }
+
+    /// Whether `wallet` has installed this contract as its hook.
+    pub fn is_hook_installed(&self, wallet: Address) -> bool {
+        self.hook_installed_of.get(wallet)
+    }
 }
 
 impl IntentPolicy {
+    /// Record `balanceOf(wallet)` for each bound for `post_check`, keyed by the hash of the
+    /// calldata the UserOp executes. Fails when a balance cannot be read, or when bounds are
+    /// already pending for the same calldata: the hook could not tell the two executions apart.
+    fn _record_pending_deltas(
+        &mut self,
+        wallet: Address,
+        execution_hash: FixedBytes<32>,
+        permission_key: FixedBytes<32>,
+        deltas: &[PendingDelta],
+    ) -> bool {
+        let op_key = pending_op_key(wallet, execution_hash);
+        if self.pending_delta_count.get(op_key) != U256::ZERO {
+            return false;
+        }
+        let mut balances = Vec::with_capacity(deltas.len());
+        for delta in deltas {
+            match erc20_balance_of(self.vm(), delta.token, wallet) {
+                Some(balance) => balances.push(balance),
+                None => return false,
+            }
+        }
+
+        for (i, (delta, before)) in deltas.iter().zip(balances).enumerate() {
+            let key = pending_delta_key(op_key, U256::from(i));
+            self.pending_delta_token.insert(key, delta.token);
+            self.pending_delta_before.insert(key, before);
+            self.pending_delta_max_out.insert(key, delta.max);
+            self.pending_delta_window.insert(key, U256::from(delta.window));
+            self.pending_delta_permission.insert(key, permission_key);
+        }
+        self.pending_delta_count.insert(op_key, U256::from(deltas.len()));
+        true
+    }
+
+    fn _clear_pending_deltas(&mut self, op_key: FixedBytes<32>) {
+        let count = self.pending_delta_count.get(op_key);
+        let mut index = U256::ZERO;
+        while index < count {
+            let key = pending_delta_key(op_key, index);
+            self.pending_delta_token.insert(key, Address::ZERO);
+            self.pending_delta_before.insert(key, U256::ZERO);
+            self.pending_delta_max_out.insert(key, U256::ZERO);
+            self.pending_delta_window.insert(key, U256::ZERO);
+            self.pending_delta_permission.insert(key, FixedBytes::ZERO);
+            index += U256::from(1u64);
+        }
+        self.pending_delta_count.insert(op_key, U256::ZERO);
+    }
+
+    /// Add `amount` to the hourly and daily spend buckets of (permission, token) at `now`.
+    fn _book_spend(
+        &mut self,
+        permission_key: FixedBytes<32>,
+        token: Address,
+        now: u64,
+        amount: U256,
+    ) {
+        if amount == U256::ZERO {
+            return;
+        }
+        for size in [HOUR, DAY] {
+            let bucket = spend_bucket_key(permission_key, token, size, now / size);
+            let total = self.spend_bucket_of.get(bucket).saturating_add(amount);
+            self.spend_bucket_of.insert(bucket, total);
+        }
+    }
+
+    /// Spend of (permission, token) in the buckets overlapping the `window` seconds up to `now`.
+    fn _window_spend(
+        &self,
+        permission_key: FixedBytes<32>,
+        token: Address,
+        now: u64,
+        window: u32,
+    ) -> U256 {
+        let (size, buckets) = window_buckets(window);
+        let current = now / size;
+        let mut total = U256::ZERO;
+        for back in 0..buckets.min(current + 1) {
+            let bucket = spend_bucket_key(permission_key, token, size, current - back);
+            total = total.saturating_add(self.spend_bucket_of.get(bucket));
+        }
+        total
+    }
+
+    /// Install `permissionId || initData` (or a batch, see `on_install`) for `wallet`.
+    fn _install_data(&mut self, wallet: Address, data: &[u8]) -> Result<(), ModuleError> {
+        // Keep revert semantics deterministic; panic on malformed init data.
+        let (permission_id, init_data) =
+            split_policy_install_data(data).unwrap_or_else(|_| panic!("Invalid init data"));
+
+        if permission_id == BATCH_INSTALL_ID {
+            let entries = split_batch_install_data(init_data)
+                .unwrap_or_else(|| panic!("Invalid batch install data"));
+            for (permission_id, init_data) in entries {
+                self._install(wallet, permission_id, init_data)?;
+            }
+            return Ok(());
+        }
+        self._install(wallet, permission_id, init_data)
+    }
+
+    /// Install one permission's `initData` (see `on_install`) for `wallet`.
+    fn _install(
+        &mut self,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        init_data: &[u8],
+    ) -> Result<(), ModuleError> {
+        let key = composite_key(wallet, permission_id);
+        if self._is_installed_key(key) {
+            return Err(ModuleError::AlreadyInitialized(AlreadyInitialized {
+                smartAccount: wallet,
+            }));
+        }
+        if self.config_frozen_of.get(key) {
+            return Err(ModuleError::PermissionFrozen(PermissionFrozen {
+                smartAccount: wallet,
+                permissionId: permission_id,
+            }));
+        }
+
+        let abi_init_data;
+        let init_data = if is_abi_init_data(init_data) {
+            abi_init_data = packed_init_data(init_data).unwrap_or_else(|| panic!("Invalid init config"));
+            &abi_init_data[..]
+        } else {
+            init_data
+        };
+
+        let Some(&version) = init_data.first() else {
+            panic!("Invalid init data length");
+        };
+        let key_len = match version {
+            1 => 20,
+            2 => 64,
+            _ => panic!("Unsupported init version"),
+        };
+        let fixed_len = 1 + key_len + 20 + 20 + 20;
+        if init_data.len() < fixed_len {
+            panic!("Invalid init data length");
+        }
+
+        let (signer_key, sources) = init_data[1..fixed_len].split_at(key_len);
+        let state_view = Address::from_slice(&sources[0..20]);
+        let vts_orchestrator = Address::from_slice(&sources[20..40]);
+        let liquidity_hub = Address::from_slice(&sources[40..60]);
+
+        if version == 1 {
+            let signer = Address::from_slice(signer_key);
+            if signer == Address::ZERO {
+                panic!("Invalid signer");
+            }
+            self.signer_of.insert(key, signer);
+        } else {
+            let x = FixedBytes::<32>::from_slice(&signer_key[0..32]);
+            let y = FixedBytes::<32>::from_slice(&signer_key[32..64]);
+            if x == FixedBytes::ZERO && y == FixedBytes::ZERO {
+                panic!("Invalid passkey");
+            }
+            self.passkey_x_of.insert(key, x);
+            self.passkey_y_of.insert(key, y);
+        }
+        if state_view == Address::ZERO || vts_orchestrator == Address::ZERO || liquidity_hub == Address::ZERO {
+            panic!("Invalid fact sources");
+        }
+
+        let extensions = parse_init_extensions(&init_data[fixed_len..]);
+        if let Some((name, version)) = extensions.domain {
+            self.domain_name_hash_of.insert(key, keccak256(name));
+            self.domain_version_hash_of.insert(key, keccak256(version));
+        }
+        if let Some((name_hash, version_hash)) = extensions.domain_hashes {
+            self.domain_name_hash_of.insert(key, name_hash);
+            self.domain_version_hash_of.insert(key, version_hash);
+        }
+        if let Some(codehashes) = extensions.codehashes {
+            self.state_view_codehash_of.insert(key, codehashes.state_view);
+            self.vts_orchestrator_codehash_of.insert(key, codehashes.vts_orchestrator);
+            self.liquidity_hub_codehash_of.insert(key, codehashes.liquidity_hub);
+        }
+        if let Some(pools) = extensions.pools {
+            self._install_pool_allowlist(key, pools);
+        }
+        if let Some(calls) = extensions.oracle_calls {
+            for (index, call) in calls.chunks_exact(24).enumerate() {
+                let mut selector = [0u8; 4];
+                selector.copy_from_slice(&call[20..24]);
+                let packed = pack_oracle_call(Address::from_slice(&call[..20]), selector);
+                self.oracle_call_at.insert(pool_allowlist_slot(key, U256::from(index)), packed);
+            }
+            self.oracle_call_count_of.insert(key, U256::from(calls.len() / 24));
+        }
+        if let Some((threshold, signers)) = extensions.signer_set {
+            let signer = self.signer_of.get(key);
+            let members = signers.chunks_exact(20).map(Address::from_slice);
+            if version != 1 || !members.clone().any(|member| member == signer) {
+                panic!("Invalid signer set");
+            }
+            for (index, member) in members.enumerate() {
+                self.signer_set_at.insert(pool_allowlist_slot(key, U256::from(index)), member);
+            }
+            self.signer_set_len_of.insert(key, U256::from(signers.len() / 20));
+            self.signer_threshold_of.insert(key, U256::from(threshold));
+        }
+        if let Some(overrides) = extensions.source_overrides {
+            for (index, entry) in overrides.chunks_exact(21).enumerate() {
+                let source = Address::from_slice(&entry[1..21]);
+                if self.vm().code_size(source) == 0 {
+                    panic!("Source override has no code");
+                }
+                let slot = pool_allowlist_slot(key, U256::from(index));
+                self.source_override_at.insert(slot, pack_source_override(entry[0], source));
+                self.source_override_codehash_at.insert(slot, self.vm().code_hash(source));
+            }
+            self.source_override_count_of.insert(key, U256::from(overrides.len() / 21));
+        }
+
+        self.nonce_of.insert(key, U256::ZERO);
+        self.state_view_of.insert(key, state_view);
+        self.vts_orchestrator_of.insert(key, vts_orchestrator);
+        self.liquidity_hub_of.insert(key, liquidity_hub);
+        self._list_permission(wallet, key, permission_id);
+        log(
+            self.vm(),
+            PermissionInstalled {
+                wallet,
+                permissionId: permission_id,
+                signer: self.signer_of.get(key),
+                stateView: state_view,
+                vtsOrchestrator: vts_orchestrator,
+                liquidityHub: liquidity_hub,
+            },
+        );
+        if extensions.freeze {
+            self._freeze(wallet, key, permission_id);
+        }
+        Ok(())
+    }
+
+    fn _freeze(&mut self, wallet: Address, key: FixedBytes<32>, permission_id: FixedBytes<32>) {
+        self.config_frozen_of.insert(key, true);
+        log(self.vm(), ConfigFrozen { wallet, permissionId: permission_id });
+    }
+
+    /// Append `permission_id` (stored under `key`) to `wallet`'s installed ids.
+    fn _list_permission(&mut self, wallet: Address, key: FixedBytes<32>, permission_id: FixedBytes<32>) {
+        let len = self.used_ids.get(wallet);
+        self.permission_id_at.insert(permission_id_slot(wallet, len), permission_id);
+        self.permission_index_of.insert(key, len + U256::from(1u64));
+        self.used_ids.insert(wallet, len + U256::from(1u64));
+    }
+
+    /// Remove the permission stored under `key` from `wallet`'s installed ids, moving the last id
+    /// into its slot.
+    fn _unlist_permission(&mut self, wallet: Address, key: FixedBytes<32>) {
+        let position = self.permission_index_of.get(key);
+        let len = self.used_ids.get(wallet);
+        if position == U256::ZERO || len == U256::ZERO {
+            return;
+        }
+        let last = len - U256::from(1u64);
+        let index = position - U256::from(1u64);
+        if index != last {
+            let moved = self.permission_id_at.get(permission_id_slot(wallet, last));
+            self.permission_id_at.insert(permission_id_slot(wallet, index), moved);
+            self.permission_index_of.insert(composite_key(wallet, moved), position);
+        }
+        self.permission_id_at.insert(permission_id_slot(wallet, last), FixedBytes::ZERO);
+        self.permission_index_of.insert(key, U256::ZERO);
+        self.used_ids.insert(wallet, last);
+    }
+
+    fn _install_pool_allowlist(&mut self, key: FixedBytes<32>, pools: &[u8]) {
+        let mut len = U256::ZERO;
+        for pool in pools.chunks_exact(32) {
+            let pool = FixedBytes::<32>::from_slice(pool);
+            let allowed_key = pool_allowed_key(key, pool);
+            if self.pool_allowed.get(allowed_key) {
+                continue;
+            }
+            self.pool_allowed.insert(allowed_key, true);
+            self.pool_allowlist_at.insert(pool_allowlist_slot(key, len), pool);
+            len += U256::from(1u64);
+        }
+        self.pool_allowlist_len_of.insert(key, len);
+    }
+
+    fn _clear_pool_allowlist(&mut self, key: FixedBytes<32>) {
+        let len = self.pool_allowlist_len_of.get(key);
+        let mut index = U256::ZERO;
+        while index < len {
+            let slot = pool_allowlist_slot(key, index);
+            let pool = self.pool_allowlist_at.get(slot);
+            self.pool_allowed.insert(pool_allowed_key(key, pool), false);
+            self.pool_allowlist_at.insert(slot, FixedBytes::ZERO);
+            index += U256::from(1u64);
+        }
+        self.pool_allowlist_len_of.insert(key, U256::ZERO);
+    }
+
+    fn _oracle_calls(&self, key: FixedBytes<32>) -> Vec<(Address, [u8; 4])> {
+        let count = self.oracle_call_count_of.get(key);
+        let mut calls = Vec::new();
+        let mut index = U256::ZERO;
+        while index < count {
+            let packed = self.oracle_call_at.get(pool_allowlist_slot(key, index));
+            calls.push(unpack_oracle_call(packed));
+            index += U256::from(1u64);
+        }
+        calls
+    }
+
+    fn _clear_oracle_calls(&mut self, key: FixedBytes<32>) {
+        let count = self.oracle_call_count_of.get(key);
+        let mut index = U256::ZERO;
+        while index < count {
+            self.oracle_call_at.insert(pool_allowlist_slot(key, index), FixedBytes::ZERO);
+            index += U256::from(1u64);
+        }
+        self.oracle_call_count_of.insert(key, U256::ZERO);
+    }
+
+    fn _source_overrides(&self, key: FixedBytes<32>) -> Vec<(u8, Address)> {
+        let count = self.source_override_count_of.get(key);
+        let mut overrides = Vec::new();
+        let mut index = U256::ZERO;
+        while index < count {
+            let packed = self.source_override_at.get(pool_allowlist_slot(key, index));
+            overrides.push(unpack_source_override(packed));
+            index += U256::from(1u64);
+        }
+        overrides
+    }
+
+    /// [`Self::_source_overrides`] with the codehash each source was pinned to at install.
+    fn _pinned_source_overrides(&self, key: FixedBytes<32>) -> Vec<(u8, Address, FixedBytes<32>)> {
+        let count = self.source_override_count_of.get(key);
+        let mut overrides = Vec::new();
+        let mut index = U256::ZERO;
+        while index < count {
+            let slot = pool_allowlist_slot(key, index);
+            let (opcode, source) = unpack_source_override(self.source_override_at.get(slot));
+            overrides.push((opcode, source, self.source_override_codehash_at.get(slot)));
+            index += U256::from(1u64);
+        }
+        overrides
+    }
+
+    fn _signer_set(&self, key: FixedBytes<32>) -> Vec<Address> {
+        let len = self.signer_set_len_of.get(key);
+        let mut signers = Vec::new();
+        let mut index = U256::ZERO;
+        while index < len {
+            signers.push(self.signer_set_at.get(pool_allowlist_slot(key, index)));
+            index += U256::from(1u64);
+        }
+        signers
+    }
+
+    fn _clear_signer_set(&mut self, key: FixedBytes<32>) {
+        let len = self.signer_set_len_of.get(key);
+        let mut index = U256::ZERO;
+        while index < len {
+            self.signer_set_at.insert(pool_allowlist_slot(key, index), Address::ZERO);
+            index += U256::from(1u64);
+        }
+        self.signer_set_len_of.insert(key, U256::ZERO);
+        self.signer_threshold_of.insert(key, U256::ZERO);
+    }
+
+    fn _clear_source_overrides(&mut self, key: FixedBytes<32>) {
+        let count = self.source_override_count_of.get(key);
+        let mut index = U256::ZERO;
+        while index < count {
+            let slot = pool_allowlist_slot(key, index);
+            self.source_override_at.insert(slot, FixedBytes::ZERO);
+            self.source_override_codehash_at.insert(slot, FixedBytes::ZERO);
+            index += U256::from(1u64);
+        }
+        self.source_override_count_of.insert(key, U256::ZERO);
+    }
+
+    /// Without an allowlist only `CheckPoolAllowed` fails (nothing is approved); with one, every
+    /// referenced pool must be on it and the program must declare a pool. So must every pool the
+    /// call bundle passes, and each of its calls must pass one (see `utils::pool_allowlist`).
+    fn _pools_allowed(&self, key: FixedBytes<32>, checks: &[Check], call_data: &[u8]) -> bool {
+        if self.pool_allowlist_len_of.get(key) == U256::ZERO {
+            return !declares_pool(checks);
+        }
+        let allowed = |pool: &FixedBytes<32>| self.pool_allowed.get(pool_allowed_key(key, *pool));
+        let Some(calls) = execute_calls(call_data) else {
+            return false;
+        };
+        declares_pool(checks)
+            && referenced_pools(checks).iter().all(allowed)
+            && calls.iter().all(|(_, call)| {
+                let pools = call_pools(call);
+                !pools.is_empty() && pools.iter().all(allowed)
+            })
+    }
+
     fn _is_installed_key(&self, key: FixedBytes<32>) -> bool {
         self.state_view_of.get(key) != Address::ZERO
     }
+
+    fn _codehashes(&self, key: FixedBytes<32>) -> FactSourceCodehashes {
+        FactSourceCodehashes {
+            state_view: self.state_view_codehash_of.get(key),
+            vts_orchestrator: self.vts_orchestrator_codehash_of.get(key),
+            liquidity_hub: self.liquidity_hub_codehash_of.get(key),
+        }
+    }
+
+    fn _domain(&self, key: FixedBytes<32>) -> IntentDomain {
+        let mut domain = IntentDomain::new(self.vm().chain_id(), self.vm().contract_address());
+        let name_hash = self.domain_name_hash_of.get(key);
+        if name_hash != FixedBytes::ZERO {
+            domain.name_hash = name_hash;
+            domain.version_hash = self.domain_version_hash_of.get(key);
+        }
+        domain
+    }
+}
+
+/// Optional `initData` extensions following the fixed fields.
+#[derive(Default)]
+struct InitExtensions<'a> {
+    /// Domain name and version.
+    domain: Option<(&'a [u8], &'a [u8])>,
+    codehashes: Option<FactSourceCodehashes>,
+    /// Concatenated 32-byte poolIds.
+    pools: Option<&'a [u8]>,
+    /// Concatenated 24-byte `target || selector` oracle calls.
+    oracle_calls: Option<&'a [u8]>,
+    /// Concatenated 21-byte `opcode || source` fact-source overrides.
+    source_overrides: Option<&'a [u8]>,
+    /// Domain name and version hashes.
+    domain_hashes: Option<(FixedBytes<32>, FixedBytes<32>)>,
+    freeze: bool,
+    /// Threshold and concatenated 20-byte signers.
+    signer_set: Option<(u8, &'a [u8])>,
+}
+
+/// Parse the extensions; panics (reverting the install) on malformed, unknown or out-of-order
+/// tags.
+fn parse_init_extensions(bytes: &[u8]) -> InitExtensions<'_> {
+    let mut r = ByteReader::new(bytes);
+    let mut extensions = InitExtensions::default();
+    let mut last_tag = 0u8;
+    while !r.is_empty() {
+        let tag = r.u8().unwrap_or_else(|_| panic!("Invalid init extension"));
+        if tag <= last_tag {
+            panic!("Invalid init extension order");
+        }
+        last_tag = tag;
+        match tag {
+            INIT_EXT_DOMAIN => {
+                let domain = read_domain(&mut r).unwrap_or_else(|_| panic!("Invalid domain"));
+                extensions.domain = Some(domain);
+            }
+            INIT_EXT_CODEHASHES => {
+                let codehashes =
+                    read_codehashes(&mut r).unwrap_or_else(|_| panic!("Invalid codehash pins"));
+                extensions.codehashes = Some(codehashes);
+            }
+            INIT_EXT_POOLS => {
+                let pools =
+                    read_list(&mut r, 32).unwrap_or_else(|_| panic!("Invalid pool allowlist"));
+                if pools.is_empty() {
+                    panic!("Invalid pool allowlist");
+                }
+                extensions.pools = Some(pools);
+            }
+            INIT_EXT_ORACLES => {
+                let calls =
+                    read_list(&mut r, 24).unwrap_or_else(|_| panic!("Invalid oracle calls"));
+                if calls.is_empty() {
+                    panic!("Invalid oracle calls");
+                }
+                extensions.oracle_calls = Some(calls);
+            }
+            INIT_EXT_SOURCE_OVERRIDES => {
+                let overrides = read_list(&mut r, 21)
+                    .ok()
+                    .filter(|overrides| valid_source_overrides(overrides))
+                    .unwrap_or_else(|| panic!("Invalid source overrides"));
+                extensions.source_overrides = Some(overrides);
+            }
+            INIT_EXT_DOMAIN_HASHES => {
+                let hashes = r
+                    .b32()
+                    .and_then(|name_hash| Ok((name_hash, r.b32()?)))
+                    .ok()
+                    .filter(|(name_hash, _)| *name_hash != FixedBytes::ZERO)
+                    .filter(|_| extensions.domain.is_none())
+                    .unwrap_or_else(|| panic!("Invalid domain"));
+                extensions.domain_hashes = Some(hashes);
+            }
+            INIT_EXT_FREEZE => extensions.freeze = true,
+            INIT_EXT_SIGNER_SET => {
+                let signer_set = r
+                    .u8()
+                    .and_then(|threshold| Ok((threshold, read_list(&mut r, 20)?)))
+                    .ok()
+                    .filter(|(threshold, signers)| valid_signer_set(*threshold, signers))
+                    .unwrap_or_else(|| panic!("Invalid signer set"));
+                extensions.signer_set = Some(signer_set);
+            }
+            _ => panic!("Unknown init extension"),
+        }
+    }
+    extensions
 }
 
+/// `1 <= threshold <= count`, and distinct non-zero signers.
+fn valid_signer_set(threshold: u8, signers: &[u8]) -> bool {
+    let members = || signers.chunks_exact(20);
+    threshold >= 1
+        && usize::from(threshold) <= signers.len() / 20
+        && members().enumerate().all(|(index, member)| {
+            member != [0u8; 20] && members().take(index).all(|earlier| earlier != member)
+        })
+}
+
+/// Non-empty, each opcode once, each reading a fact source, and no zero source.
+fn valid_source_overrides(overrides: &[u8]) -> bool {
+    let entries = || overrides.chunks_exact(21);
+    !overrides.is_empty()
+        && entries().enumerate().all(|(index, entry)| {
+            let reads_source = Opcode::try_from(entry[0])
+                .ok()
+                .and_then(Opcode::fact_source)
+                .is_some();
+            reads_source
+                && entry[1..21] != [0u8; 20]
+                && entries().take(index).all(|earlier| earlier[0] != entry[0])
+        })
+}
+
+fn read_domain<'a>(r: &mut ByteReader<'a>) -> Result<(&'a [u8], &'a [u8]), UnexpectedEnd> {
+    let name_len = r.u8()? as usize;
+    let name = r.take(name_len)?;
+    let version_len = r.u8()? as usize;
+    let version = r.take(version_len)?;
+    Ok((name, version))
+}
+
+fn read_codehashes(r: &mut ByteReader) -> Result<FactSourceCodehashes, UnexpectedEnd> {
+    Ok(FactSourceCodehashes {
+        state_view: r.b32()?,
+        vts_orchestrator: r.b32()?,
+        liquidity_hub: r.b32()?,
+    })
+}
+
+/// `uint8 count || count` items of `item_len` bytes.
+fn read_list<'a>(r: &mut ByteReader<'a>, item_len: usize) -> Result<&'a [u8], UnexpectedEnd> {
+    let count = r.u8()? as usize;
+    r.take(count * item_len)
+}
 
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,2448 @@
+//! Entrypoint tests for `IntentPolicy` on the Stylus test VM.
+//!
+//! The `ecrecover` precompile is mocked per digest: a signature "recovers" to the signer only for
+//! the exact envelope it was registered for, so any tampering with the signed fields fails
+//! recovery just as it would on chain. Passkey envelopes mock the SHA-256 and P-256 verifiers the
+//! same way.
+
+use alloc::{string::String, vec, vec::Vec};
+
+use stylus_sdk::{
+    alloy_primitives::{keccak256, Address, Bytes, FixedBytes, I256, U256},
+    testing::*,
+};
+
+use alloy_sol_types::{sol_data, SolEvent, SolType};
+use fiet_maker_policy_types::{base64url_challenge, WebAuthnAssertion};
+
+use super::{
+    ConfigFrozen, IntentPolicy, IntentValidated, ModuleError, NonceRevoked, PermissionInstalled,
+    PermissionUninstalled,
+};
+use crate::{
+    decoder::decode_program,
+    kernel::constants::{
+        MODULE_TYPE_HOOK, MODULE_TYPE_POLICY, POLICY_FAILED_UINT, POLICY_SUCCESS_UINT,
+    },
+    types::opcodes::{Check, CompOp, UserOpField, MAX_CALL_TARGETS},
+    utils::{
+        crypto::{P256_VERIFIER_FALLBACK, P256_VERIFY_PRECOMPILE},
+        init_config::{InitConfigV2, OracleCall},
+        kernel::{BATCH_INSTALL_ID, EXECUTE_USER_OP_SELECTOR},
+        policy_envelope::{
+            policy_intent_digest, policy_intent_digest_in, policy_typed_intent_digest_in,
+            IntentDomain,
+        },
+    },
+};
+
+mod invariants;
+
+const CHAIN_ID: u64 = 42161;
+const NOW: u64 = 1_700_000_000;
+
+type UserOp = (
+    Address,
+    U256,
+    Vec<u8>,
+    Vec<u8>,
+    FixedBytes<32>,
+    U256,
+    FixedBytes<32>,
+    Vec<u8>,
+    Vec<u8>,
+);
+
+fn wallet() -> Address {
+    Address::repeat_byte(0xaa)
+}
+
+fn signer() -> Address {
+    Address::repeat_byte(0x51)
+}
+
+fn policy_address() -> Address {
+    Address::repeat_byte(0x90)
+}
+
+fn permission_id() -> FixedBytes<32> {
+    FixedBytes::repeat_byte(0x11)
+}
+
+fn setup() -> (TestVM, IntentPolicy) {
+    let vm = TestVM::new();
+    vm.set_sender(wallet());
+    vm.set_block_timestamp(NOW);
+    vm.set_chain_id(CHAIN_ID);
+    vm.set_contract_address(policy_address());
+    let policy = IntentPolicy::from(&vm);
+    (vm, policy)
+}
+
+fn install_data(permission_id: FixedBytes<32>, signer: Address) -> Vec<u8> {
+    let mut data = permission_id.to_vec();
+    data.push(1);
+    data.extend_from_slice(signer.as_slice());
+    data.extend_from_slice(Address::repeat_byte(0x01).as_slice());
+    data.extend_from_slice(Address::repeat_byte(0x02).as_slice());
+    data.extend_from_slice(Address::repeat_byte(0x03).as_slice());
+    data
+}
+
+fn passkey() -> (FixedBytes<32>, FixedBytes<32>) {
+    (FixedBytes::repeat_byte(0x71), FixedBytes::repeat_byte(0x72))
+}
+
+fn passkey_install_data(permission_id: FixedBytes<32>) -> Vec<u8> {
+    let (x, y) = passkey();
+    let mut data = permission_id.to_vec();
+    data.push(2);
+    data.extend_from_slice(x.as_slice());
+    data.extend_from_slice(y.as_slice());
+    data.extend_from_slice(Address::repeat_byte(0x01).as_slice());
+    data.extend_from_slice(Address::repeat_byte(0x02).as_slice());
+    data.extend_from_slice(Address::repeat_byte(0x03).as_slice());
+    data
+}
+
+/// Which P-256 verifier answers for a passkey envelope.
+#[derive(Clone, Copy)]
+enum P256Verifier {
+    Precompile,
+    Fallback,
+    None,
+}
+
+fn install(policy: &mut IntentPolicy) {
+    assert!(policy
+        .on_install(install_data(permission_id(), signer()))
+        .is_ok());
+}
+
+/// Fields of a v1 envelope, signed over with [`Intent::envelope`].
+struct Intent {
+    nonce: U256,
+    deadline: u64,
+    call_data: Vec<u8>,
+    program: Vec<u8>,
+}
+
+impl Intent {
+    fn new(nonce: u64) -> Self {
+        let deadline = NOW + 60;
+        let mut program = vec![0x01];
+        program.extend_from_slice(&deadline.to_be_bytes());
+        Self {
+            nonce: U256::from(nonce),
+            deadline,
+            call_data: vec![0xde, 0xad, 0xbe, 0xef],
+            program,
+        }
+    }
+
+    fn digest(&self, wallet: Address, permission_id: FixedBytes<32>) -> FixedBytes<32> {
+        policy_intent_digest(
+            CHAIN_ID,
+            policy_address(),
+            wallet,
+            permission_id,
+            self.nonce,
+            self.deadline,
+            keccak256(&self.call_data),
+            &self.program,
+        )
+    }
+
+    /// Serialise the envelope and register `recovered` as the `ecrecover` result for its digest.
+    fn envelope(&self, vm: &TestVM, recovered: Address) -> Vec<u8> {
+        self.envelope_for(vm, wallet(), permission_id(), recovered)
+    }
+
+    /// [`Intent::envelope`] signed for another (wallet, permissionId) instance.
+    fn envelope_for(
+        &self,
+        vm: &TestVM,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        recovered: Address,
+    ) -> Vec<u8> {
+        self.signed(vm, self.digest(wallet, permission_id), recovered)
+    }
+
+    /// [`Intent::envelope`] signed under `domain` rather than the default one.
+    fn envelope_in(&self, vm: &TestVM, domain: &IntentDomain, recovered: Address) -> Vec<u8> {
+        let digest = policy_intent_digest_in(
+            domain,
+            wallet(),
+            permission_id(),
+            self.nonce,
+            self.deadline,
+            keccak256(&self.call_data),
+            &self.program,
+        );
+        self.signed(vm, digest, recovered)
+    }
+
+    /// Serialise the envelope with a threshold signature, one signature per entry of `signers`.
+    fn threshold_envelope(&self, vm: &TestVM, signers: &[Address]) -> Vec<u8> {
+        let digest = self.digest(wallet(), permission_id());
+        let mut signature = vec![0xff];
+        for (index, recovered) in signers.iter().enumerate() {
+            let sig = [0x60 + index as u8; 65];
+            mock_ecrecover(vm, digest, &sig, *recovered);
+            signature.extend_from_slice(&sig);
+        }
+        self.encode(&signature)
+    }
+
+    fn signed(&self, vm: &TestVM, digest: FixedBytes<32>, recovered: Address) -> Vec<u8> {
+        let signature = [0x5a; 65];
+        mock_ecrecover(vm, digest, &signature, recovered);
+        self.encode(&signature)
+    }
+
+    /// Serialise the envelope with a WebAuthn assertion over its digest from [`passkey`], mocking
+    /// the hashing and `verifier` to accept it.
+    fn passkey_envelope(&self, vm: &TestVM, verifier: P256Verifier) -> Vec<u8> {
+        let digest = self.digest(wallet(), permission_id());
+        let mut client_data_json = br#"{"type":"webauthn.get","challenge":""#.to_vec();
+        client_data_json.extend_from_slice(&base64url_challenge(&digest));
+        client_data_json.extend_from_slice(br#"","origin":"https://app.fiet.finance"}"#);
+        let mut authenticator_data = vec![0x3c; 37];
+        authenticator_data[32] = 0x05; // UP | UV
+        let assertion = WebAuthnAssertion {
+            authenticator_data,
+            client_data_json,
+            challenge_index: 23,
+            type_index: 1,
+            r: FixedBytes::repeat_byte(0x0a),
+            s: FixedBytes::repeat_byte(0x0b),
+        };
+        mock_webauthn(vm, &assertion, verifier);
+        self.encode(&assertion.encode())
+    }
+
+    fn encode(&self, signature: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&self.nonce.to_be_bytes::<32>());
+        out.extend_from_slice(&self.deadline.to_be_bytes());
+        out.extend_from_slice(keccak256(&self.call_data).as_slice());
+        out.extend_from_slice(&(self.program.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.program);
+        out.extend_from_slice(&(signature.len() as u16).to_be_bytes());
+        out.extend_from_slice(signature);
+        out
+    }
+
+    fn user_op(&self, envelope: Vec<u8>) -> UserOp {
+        (
+            wallet(),
+            U256::ZERO,
+            Vec::new(),
+            self.call_data.clone(),
+            FixedBytes::ZERO,
+            U256::ZERO,
+            FixedBytes::ZERO,
+            Vec::new(),
+            envelope,
+        )
+    }
+}
+
+fn mock_ecrecover(vm: &TestVM, digest: FixedBytes<32>, sig: &[u8; 65], recovered: Address) {
+    // v = 0x5a is not a recognised recovery id, so the policy tries 27 and then 28.
+    let mut input = [0u8; 128];
+    input[0..32].copy_from_slice(digest.as_slice());
+    input[63] = 27;
+    input[64..128].copy_from_slice(&sig[0..64]);
+    let mut out = vec![0u8; 32];
+    out[12..32].copy_from_slice(recovered.as_slice());
+    vm.mock_static_call(Address::with_last_byte(1), input.to_vec(), Ok(out));
+}
+
+fn mock_webauthn(vm: &TestVM, assertion: &WebAuthnAssertion, verifier: P256Verifier) {
+    let sha256 = Address::with_last_byte(2);
+    let client_data_hash = FixedBytes::<32>::repeat_byte(0xc1);
+    let message_hash = FixedBytes::<32>::repeat_byte(0xc2);
+    vm.mock_static_call(
+        sha256,
+        assertion.client_data_json.clone(),
+        Ok(client_data_hash.to_vec()),
+    );
+    let mut message = assertion.authenticator_data.clone();
+    message.extend_from_slice(client_data_hash.as_slice());
+    vm.mock_static_call(sha256, message, Ok(message_hash.to_vec()));
+
+    let (x, y) = passkey();
+    let mut input = message_hash.to_vec();
+    for word in [assertion.r, assertion.s, x, y] {
+        input.extend_from_slice(word.as_slice());
+    }
+    let valid = U256::from(1u64).to_be_bytes::<32>().to_vec();
+    // Unmocked calls return empty output, as an invalid signature or a missing verifier does.
+    match verifier {
+        P256Verifier::Precompile => vm.mock_static_call(P256_VERIFY_PRECOMPILE, input, Ok(valid)),
+        P256Verifier::Fallback => vm.mock_static_call(P256_VERIFIER_FALLBACK, input, Ok(valid)),
+        P256Verifier::None => {}
+    }
+}
+
+/// `abi.encode(InitConfigV2)` install data with the sources of [`install_data`] and no extensions.
+fn abi_init_config(signer: Address) -> InitConfigV2 {
+    InitConfigV2 {
+        signer,
+        passkeyX: FixedBytes::ZERO,
+        passkeyY: FixedBytes::ZERO,
+        stateView: Address::repeat_byte(0x01),
+        vtsOrchestrator: Address::repeat_byte(0x02),
+        liquidityHub: Address::repeat_byte(0x03),
+        domainName: String::new(),
+        domainVersion: String::new(),
+        factSourceCodehashes: [FixedBytes::ZERO; 3],
+        poolIds: Vec::new(),
+        oracleCalls: Vec::new(),
+    }
+}
+
+fn abi_install_data(permission_id: FixedBytes<32>, config: &InitConfigV2) -> Vec<u8> {
+    let mut data = permission_id.to_vec();
+    data.extend_from_slice(&<InitConfigV2 as SolType>::abi_encode(config));
+    data
+}
+
+#[test]
+fn install_records_signer_and_sources() {
+    let (_vm, mut policy) = setup();
+    assert!(!policy.is_initialized(wallet()));
+
+    install(&mut policy);
+
+    assert!(policy.is_initialized(wallet()));
+    assert_eq!(policy.signer_of(wallet(), permission_id()), signer());
+    assert_eq!(
+        policy.fact_sources_of(wallet(), permission_id()),
+        (
+            Address::repeat_byte(0x01),
+            Address::repeat_byte(0x02),
+            Address::repeat_byte(0x03)
+        )
+    );
+}
+
+#[test]
+fn install_is_scoped_by_msg_sender() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let other = Address::repeat_byte(0xbb);
+    assert!(!policy.is_initialized(other));
+    vm.set_sender(other);
+    assert!(policy
+        .on_install(install_data(permission_id(), signer()))
+        .is_ok());
+    assert!(policy.is_initialized(other));
+}
+
+#[test]
+fn install_twice_is_rejected() {
+    let (_vm, mut policy) = setup();
+    install(&mut policy);
+
+    assert!(matches!(
+        policy.on_install(install_data(permission_id(), signer())),
+        Err(ModuleError::AlreadyInitialized(_))
+    ));
+}
+
+#[test]
+#[should_panic(expected = "Invalid signer")]
+fn install_rejects_zero_signer() {
+    let (_vm, mut policy) = setup();
+    let _ = policy.on_install(install_data(permission_id(), Address::ZERO));
+}
+
+#[test]
+fn install_accepts_abi_encoded_config() {
+    let (_vm, mut policy) = setup();
+    let oracle = (Address::repeat_byte(0x0a), FixedBytes([0x50, 0xd2, 0x5b, 0xcd]));
+    let config = InitConfigV2 {
+        domainName: "Acme Policy".into(),
+        domainVersion: "2".into(),
+        factSourceCodehashes: [FixedBytes::ZERO, FixedBytes::ZERO, FixedBytes::repeat_byte(0x0c)],
+        poolIds: vec![FixedBytes::repeat_byte(0xa1), FixedBytes::repeat_byte(0xa2)],
+        oracleCalls: vec![OracleCall { target: oracle.0, selector: oracle.1 }],
+        ..abi_init_config(signer())
+    };
+    assert!(policy.on_install(abi_install_data(permission_id(), &config)).is_ok());
+
+    assert_eq!(policy.signer_of(wallet(), permission_id()), signer());
+    assert_eq!(
+        policy.fact_sources_of(wallet(), permission_id()),
+        (Address::repeat_byte(0x01), Address::repeat_byte(0x02), Address::repeat_byte(0x03))
+    );
+    assert_eq!(
+        policy.domain_of(wallet(), permission_id()),
+        (keccak256(b"Acme Policy"), keccak256(b"2"))
+    );
+    assert_eq!(
+        policy.fact_source_codehashes_of(wallet(), permission_id()),
+        (FixedBytes::ZERO, FixedBytes::ZERO, FixedBytes::repeat_byte(0x0c))
+    );
+    assert_eq!(policy.pool_allowlist_of(wallet(), permission_id()), config.poolIds);
+    assert_eq!(policy.oracle_calls_of(wallet(), permission_id()), vec![oracle]);
+
+    // A passkey config installs like the packed v2 layout.
+    let (x, y) = passkey();
+    let other = FixedBytes::repeat_byte(0x22);
+    let config = InitConfigV2 { passkeyX: x, passkeyY: y, ..abi_init_config(Address::ZERO) };
+    assert!(policy.on_install(abi_install_data(other, &config)).is_ok());
+    assert_eq!(policy.passkey_of(wallet(), other), (x, y));
+    assert_eq!(policy.signer_of(wallet(), other), Address::ZERO);
+}
+
+#[test]
+#[should_panic(expected = "Invalid init config")]
+fn install_rejects_abi_config_with_signer_and_passkey() {
+    let (_vm, mut policy) = setup();
+    let (x, y) = passkey();
+    let config = InitConfigV2 { passkeyX: x, passkeyY: y, ..abi_init_config(signer()) };
+    let _ = policy.on_install(abi_install_data(permission_id(), &config));
+}
+
+#[test]
+#[should_panic(expected = "Invalid signer")]
+fn install_validates_abi_config_like_packed_data() {
+    let (_vm, mut policy) = setup();
+    let _ = policy.on_install(abi_install_data(permission_id(), &abi_init_config(Address::ZERO)));
+}
+
+#[test]
+fn uninstall_clears_config() {
+    let (_vm, mut policy) = setup();
+    install(&mut policy);
+
+    assert!(policy.on_uninstall(permission_id().to_vec()).is_ok());
+    assert!(!policy.is_initialized(wallet()));
+    assert_eq!(policy.signer_of(wallet(), permission_id()), Address::ZERO);
+    assert!(matches!(
+        policy.on_uninstall(permission_id().to_vec()),
+        Err(ModuleError::NotInitialized(_))
+    ));
+}
+
+#[test]
+fn permission_ids_of_lists_installed_permissions() {
+    let (_vm, mut policy) = setup();
+    assert!(policy.permission_ids_of(wallet()).is_empty());
+
+    let ids = [permission_id(), FixedBytes([0x22; 32]), FixedBytes([0x33; 32])];
+    for id in ids {
+        assert!(policy.on_install(install_data(id, signer())).is_ok());
+    }
+    assert_eq!(policy.permission_ids_of(wallet()), ids.to_vec());
+    assert!(policy.permission_ids_of(Address::repeat_byte(0xbb)).is_empty());
+
+    // Uninstalling moves the last id into the freed slot.
+    assert!(policy.on_uninstall(ids[0].to_vec()).is_ok());
+    assert_eq!(policy.permission_ids_of(wallet()), vec![ids[2], ids[1]]);
+
+    // The moved id's position is tracked, so it can be uninstalled and reinstalled in turn.
+    assert!(policy.on_uninstall(ids[2].to_vec()).is_ok());
+    assert_eq!(policy.permission_ids_of(wallet()), vec![ids[1]]);
+    assert!(policy.on_install(install_data(ids[0], signer())).is_ok());
+    assert_eq!(policy.permission_ids_of(wallet()), vec![ids[1], ids[0]]);
+
+    assert!(policy.on_uninstall(ids[1].to_vec()).is_ok());
+    assert!(policy.on_uninstall(ids[0].to_vec()).is_ok());
+    assert!(policy.permission_ids_of(wallet()).is_empty());
+    assert!(!policy.is_initialized(wallet()));
+}
+
+#[test]
+fn check_passes_and_consumes_nonce() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let first = Intent::new(0);
+    let envelope = first.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), first.user_op(envelope.clone())),
+        POLICY_SUCCESS_UINT
+    );
+    // Replaying the same envelope now fails the nonce check.
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), first.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    assert_eq!(policy.nonce_of(wallet(), permission_id()), U256::from(1));
+
+    let second = Intent::new(1);
+    let envelope = second.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), second.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+    assert_eq!(policy.nonce_of(wallet(), permission_id()), U256::from(2));
+}
+
+#[test]
+fn check_compares_user_op_fields() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    // maxFeePerGas <= 100 gwei and callGasLimit >= 50_000.
+    let mut intent = Intent::new(0);
+    for (field, op, rhs) in [
+        (UserOpField::MaxFeePerGas, CompOp::Lte, 100_000_000_000u64),
+        (UserOpField::CallGasLimit, CompOp::Gte, 50_000),
+    ] {
+        Check::UserOpField { field, op, rhs: U256::from(rhs) }.encode_into(&mut intent.program);
+    }
+    let packed = |high: u128, low: u128| {
+        let mut word = [0u8; 32];
+        word[..16].copy_from_slice(&high.to_be_bytes());
+        word[16..].copy_from_slice(&low.to_be_bytes());
+        FixedBytes(word)
+    };
+    let envelope = intent.envelope(&vm, signer());
+    let user_op = |account_gas_limits, gas_fees| {
+        let mut user_op = intent.user_op(envelope.clone());
+        user_op.4 = account_gas_limits;
+        user_op.6 = gas_fees;
+        user_op
+    };
+
+    // The priority fee (high half of `gasFees`) is not what is bounded.
+    let over_fee = user_op(packed(90_000, 60_000), packed(200_000_000_000, 100_000_000_001));
+    assert_eq!(policy.check_user_op_policy(permission_id(), over_fee), POLICY_FAILED_UINT);
+    let under_gas = user_op(packed(90_000, 49_999), packed(1, 100_000_000_000));
+    assert_eq!(policy.check_user_op_policy(permission_id(), under_gas), POLICY_FAILED_UINT);
+    assert_eq!(policy.nonce_of(wallet(), permission_id()), U256::ZERO);
+
+    let within = user_op(packed(0, 50_000), packed(200_000_000_000, 100_000_000_000));
+    assert_eq!(policy.check_user_op_policy(permission_id(), within), POLICY_SUCCESS_UINT);
+}
+
+#[test]
+fn check_emits_intent_validated() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope.clone())),
+        POLICY_SUCCESS_UINT
+    );
+    // A failed check (here a replay) logs nothing.
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    let logs: Vec<_> = vm
+        .get_emitted_logs()
+        .into_iter()
+        .filter(|(topics, _)| topics[0] == IntentValidated::SIGNATURE_HASH)
+        .collect();
+    assert_eq!(logs.len(), 1);
+    let (topics, data) = &logs[0];
+    assert_eq!(
+        topics,
+        &vec![
+            IntentValidated::SIGNATURE_HASH,
+            wallet().into_word(),
+            permission_id(),
+            intent.digest(wallet(), permission_id()),
+        ]
+    );
+    let mut expected = keccak256(&intent.program).to_vec();
+    expected.extend_from_slice(&intent.nonce.to_be_bytes::<32>());
+    expected.extend_from_slice(&U256::from(intent.deadline).to_be_bytes::<32>());
+    assert_eq!(data, &expected);
+}
+
+#[test]
+fn permission_lifecycle_emits_events() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    vm.set_sender(signer());
+    assert!(policy.revoke_nonce(wallet(), permission_id(), U256::ZERO).is_ok());
+    vm.set_sender(wallet());
+    assert!(policy.on_uninstall(permission_id().to_vec()).is_ok());
+
+    let word = |address: Address| address.into_word().to_vec();
+    let logs = vm.get_emitted_logs();
+    assert_eq!(logs.len(), 3);
+    assert_eq!(
+        logs[0].0,
+        vec![PermissionInstalled::SIGNATURE_HASH, wallet().into_word(), permission_id()]
+    );
+    let mut installed = word(signer());
+    for source in [0x01, 0x02, 0x03] {
+        installed.extend(word(Address::repeat_byte(source)));
+    }
+    assert_eq!(logs[0].1, installed);
+    assert_eq!(
+        logs[1].0,
+        vec![
+            NonceRevoked::SIGNATURE_HASH,
+            wallet().into_word(),
+            permission_id(),
+            signer().into_word(),
+        ]
+    );
+    assert_eq!(logs[1].1, U256::from(1u64).to_be_bytes::<32>().to_vec());
+    assert_eq!(
+        logs[2].0,
+        vec![PermissionUninstalled::SIGNATURE_HASH, wallet().into_word(), permission_id()]
+    );
+    assert!(logs[2].1.is_empty());
+}
+
+#[test]
+fn check_fails_when_not_installed() {
+    let (vm, mut policy) = setup();
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn check_fails_after_deadline() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    vm.set_block_timestamp(intent.deadline + 1);
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn check_fails_on_call_data_mismatch() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    let mut user_op = intent.user_op(envelope);
+    user_op.3 = vec![0x00];
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), user_op),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn check_fails_on_nonce_mismatch() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let intent = Intent::new(1);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn check_fails_for_wrong_signer() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, Address::repeat_byte(0x66));
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn check_fails_on_tampered_program() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let mut intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    // Swap the signed program for an empty one: the digest changes, so recovery fails.
+    let program_len = intent.program.len();
+    let mut tampered = envelope[..2 + 32 + 8 + 32].to_vec();
+    tampered.extend_from_slice(&0u32.to_be_bytes());
+    tampered.extend_from_slice(&envelope[2 + 32 + 8 + 32 + 4 + program_len..]);
+    intent.program.clear();
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(tampered)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn check_accepts_typed_checks_envelope() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let intent = Intent::new(0);
+    let as_v2 = |mut envelope: Vec<u8>| {
+        envelope[..2].copy_from_slice(&2u16.to_be_bytes());
+        envelope
+    };
+    // Relabelling a program-hash envelope as version 2 changes the digest it must be signed over.
+    let relabelled = as_v2(intent.envelope(&vm, signer()));
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(relabelled)),
+        POLICY_FAILED_UINT
+    );
+
+    let checks = decode_program(&intent.program).unwrap();
+    let digest = policy_typed_intent_digest_in(
+        &IntentDomain::new(CHAIN_ID, policy_address()),
+        wallet(),
+        permission_id(),
+        intent.nonce,
+        intent.deadline,
+        keccak256(&intent.call_data),
+        &checks,
+    );
+    assert_ne!(digest, intent.digest(wallet(), permission_id()));
+    let envelope = as_v2(intent.signed(&vm, digest, signer()));
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn check_rejects_unknown_envelope_version() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let intent = Intent::new(0);
+    let mut envelope = intent.envelope(&vm, signer());
+    envelope[..2].copy_from_slice(&3u16.to_be_bytes());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn check_fails_after_uninstall() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.on_uninstall(permission_id().to_vec()).is_ok());
+
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn revoke_nonce_invalidates_outstanding_envelope() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+
+    // The envelope signer revokes; the wallet itself may too.
+    vm.set_sender(signer());
+    assert_eq!(
+        policy.revoke_nonce(wallet(), permission_id(), U256::ZERO).ok(),
+        Some(U256::from(1u64))
+    );
+    vm.set_sender(wallet());
+    assert_eq!(
+        policy.revoke_nonce(wallet(), permission_id(), U256::ZERO).ok(),
+        Some(U256::from(2u64))
+    );
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    let next = Intent::new(2);
+    let envelope = next.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), next.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn revoke_nonce_past_queued_envelopes() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    // Envelopes for nonces 0 and 1 are signed up front; revoking must kill both, not just the
+    // one at the head of the queue.
+    let head = Intent::new(0);
+    let head_envelope = head.envelope(&vm, signer());
+    let queued = Intent::new(1);
+    let queued_envelope = queued.envelope(&vm, signer());
+
+    vm.set_sender(signer());
+    assert_eq!(
+        policy.revoke_nonce(wallet(), permission_id(), U256::from(2u64)).ok(),
+        Some(U256::from(2u64))
+    );
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), head.user_op(head_envelope)),
+        POLICY_FAILED_UINT
+    );
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), queued.user_op(queued_envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    // A target at or below the current nonce still burns one, never lowers it.
+    assert_eq!(
+        policy.revoke_nonce(wallet(), permission_id(), U256::from(1u64)).ok(),
+        Some(U256::from(3u64))
+    );
+}
+
+#[test]
+fn revoke_nonce_requires_wallet_or_signer() {
+    let (vm, mut policy) = setup();
+
+    assert!(matches!(
+        policy.revoke_nonce(wallet(), permission_id(), U256::ZERO),
+        Err(ModuleError::NotInitialized(_))
+    ));
+
+    install(&mut policy);
+    vm.set_sender(Address::repeat_byte(0x66));
+    assert!(matches!(
+        policy.revoke_nonce(wallet(), permission_id(), U256::ZERO),
+        Err(ModuleError::Unauthorized(_))
+    ));
+}
+
+#[test]
+fn freeze_config_blocks_reinstall_for_good() {
+    let (vm, mut policy) = setup();
+    assert!(matches!(
+        policy.freeze_config(permission_id()),
+        Err(ModuleError::NotInitialized(_))
+    ));
+
+    install(&mut policy);
+    assert!(!policy.is_config_frozen(wallet(), permission_id()));
+    assert!(policy.freeze_config(permission_id()).is_ok());
+    assert!(policy.is_config_frozen(wallet(), permission_id()));
+    // Freezing twice is a no-op, not a second event.
+    assert!(policy.freeze_config(permission_id()).is_ok());
+    let logs = vm.get_emitted_logs();
+    assert_eq!(logs.len(), 2);
+    assert_eq!(
+        logs[1].0,
+        vec![ConfigFrozen::SIGNATURE_HASH, wallet().into_word(), permission_id()]
+    );
+
+    // Uninstalling still works, but the id can never be configured again (rotating the signer).
+    assert!(policy.on_uninstall(permission_id().to_vec()).is_ok());
+    assert!(policy.is_config_frozen(wallet(), permission_id()));
+    assert!(matches!(
+        policy.on_install(install_data(permission_id(), Address::repeat_byte(0x52))),
+        Err(ModuleError::PermissionFrozen(_))
+    ));
+
+    // Other permission ids, and other wallets' instances of this one, are unaffected.
+    let other_id = FixedBytes::repeat_byte(0x22);
+    assert!(policy.on_install(install_data(other_id, signer())).is_ok());
+    vm.set_sender(Address::repeat_byte(0xbb));
+    install(&mut policy);
+}
+
+#[test]
+fn freeze_extension_freezes_at_install_and_is_exported() {
+    let (_vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data.push(0x07);
+    assert!(policy.on_install(data.clone()).is_ok());
+    assert!(policy.is_config_frozen(wallet(), permission_id()));
+    assert_eq!(
+        policy.export_config(wallet(), permission_id()).ok().map(|c| c.to_vec()),
+        Some(data[32..].to_vec())
+    );
+}
+
+#[test]
+#[should_panic(expected = "Invalid init extension order")]
+fn freeze_extension_has_no_payload() {
+    let (_vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x07, 0x01]);
+    let _ = policy.on_install(data);
+}
+
+#[test]
+fn passkey_install_records_key_and_clears_on_uninstall() {
+    let (_vm, mut policy) = setup();
+    assert!(policy.on_install(passkey_install_data(permission_id())).is_ok());
+
+    assert!(policy.is_initialized(wallet()));
+    assert_eq!(policy.signer_of(wallet(), permission_id()), Address::ZERO);
+    assert_eq!(policy.passkey_of(wallet(), permission_id()), passkey());
+
+    assert!(policy.on_uninstall(permission_id().to_vec()).is_ok());
+    assert_eq!(
+        policy.passkey_of(wallet(), permission_id()),
+        (FixedBytes::ZERO, FixedBytes::ZERO)
+    );
+}
+
+#[test]
+#[should_panic(expected = "Invalid init data length")]
+fn passkey_install_rejects_v1_length() {
+    let (_vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data[32] = 2;
+    let _ = policy.on_install(data);
+}
+
+#[test]
+fn check_passes_with_passkey_via_precompile() {
+    let (vm, mut policy) = setup();
+    assert!(policy.on_install(passkey_install_data(permission_id())).is_ok());
+
+    let intent = Intent::new(0);
+    let envelope = intent.passkey_envelope(&vm, P256Verifier::Precompile);
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn check_passes_with_passkey_via_fallback_verifier() {
+    let (vm, mut policy) = setup();
+    assert!(policy.on_install(passkey_install_data(permission_id())).is_ok());
+
+    let intent = Intent::new(0);
+    let envelope = intent.passkey_envelope(&vm, P256Verifier::Fallback);
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn check_fails_closed_without_p256_verifier() {
+    let (vm, mut policy) = setup();
+    assert!(policy.on_install(passkey_install_data(permission_id())).is_ok());
+
+    let intent = Intent::new(0);
+    let envelope = intent.passkey_envelope(&vm, P256Verifier::None);
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn check_fails_on_passkey_challenge_mismatch() {
+    let (vm, mut policy) = setup();
+    assert!(policy.on_install(passkey_install_data(permission_id())).is_ok());
+
+    // The assertion's challenge is the digest of nonce 0; the envelope claims nonce 0 but a
+    // different deadline, so the digest no longer matches the challenge.
+    let intent = Intent::new(0);
+    let envelope = intent.passkey_envelope(&vm, P256Verifier::Precompile);
+    let mut tampered = envelope.clone();
+    tampered[2 + 32 + 7] ^= 1;
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(tampered)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn signature_scheme_must_match_install() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    let intent = Intent::new(0);
+    let envelope = intent.passkey_envelope(&vm, P256Verifier::Precompile);
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    let other = FixedBytes::repeat_byte(0x22);
+    assert!(policy.on_install(passkey_install_data(other)).is_ok());
+    let envelope = intent.envelope_for(&vm, wallet(), other, signer());
+    assert_eq!(
+        policy.check_user_op_policy(other, intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+fn signer_set_install_data(threshold: u8, members: &[Address]) -> Vec<u8> {
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x08, threshold, members.len() as u8]);
+    for member in members {
+        data.extend_from_slice(member.as_slice());
+    }
+    data
+}
+
+#[test]
+fn signer_set_requires_threshold_signatures() {
+    let (vm, mut policy) = setup();
+    let members = [signer(), Address::repeat_byte(0x52), Address::repeat_byte(0x53)];
+    let data = signer_set_install_data(2, &members);
+    assert!(policy.on_install(data.clone()).is_ok());
+    assert_eq!(policy.signer_set_of(wallet(), permission_id()), (2, members.to_vec()));
+    assert_eq!(
+        policy.export_config(wallet(), permission_id()).ok().map(|c| c.to_vec()),
+        Some(data[32..].to_vec())
+    );
+
+    // A lone signature, even from `signer`, then too few, repeated and stray members.
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+    let rejected: [&[Address]; 3] = [
+        &[members[2]],
+        &[members[1], members[1]],
+        &[members[0], members[1], Address::repeat_byte(0x66)],
+    ];
+    for signers in rejected {
+        let envelope = intent.threshold_envelope(&vm, signers);
+        assert_eq!(
+            policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+            POLICY_FAILED_UINT
+        );
+    }
+
+    let envelope = intent.threshold_envelope(&vm, &[members[2], members[0]]);
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    assert!(policy.on_uninstall(permission_id().to_vec()).is_ok());
+    assert_eq!(policy.signer_set_of(wallet(), permission_id()), (0, Vec::new()));
+}
+
+#[test]
+#[should_panic(expected = "Invalid signer set")]
+fn signer_set_rejects_threshold_above_size() {
+    let (_vm, mut policy) = setup();
+    let _ = policy.on_install(signer_set_install_data(3, &[signer(), Address::repeat_byte(0x52)]));
+}
+
+#[test]
+#[should_panic(expected = "Invalid signer set")]
+fn signer_set_must_include_the_signer() {
+    let (_vm, mut policy) = setup();
+    let members = [Address::repeat_byte(0x52), Address::repeat_byte(0x53)];
+    let _ = policy.on_install(signer_set_install_data(1, &members));
+}
+
+#[test]
+#[should_panic(expected = "Invalid signer set")]
+fn signer_set_rejects_passkey_installs() {
+    let (_vm, mut policy) = setup();
+    let mut data = passkey_install_data(permission_id());
+    data.extend_from_slice(&[0x08, 1, 1]);
+    data.extend_from_slice(signer().as_slice());
+    let _ = policy.on_install(data);
+}
+
+#[test]
+fn install_domain_override_changes_the_signed_domain() {
+    let (vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data.push(0x01);
+    data.push(11);
+    data.extend_from_slice(b"Acme Policy");
+    data.push(1);
+    data.extend_from_slice(b"2");
+    assert!(policy.on_install(data).is_ok());
+
+    let domain = IntentDomain {
+        name_hash: keccak256(b"Acme Policy"),
+        version_hash: keccak256(b"2"),
+        chain_id: CHAIN_ID,
+        verifying_contract: policy_address(),
+    };
+    assert_eq!(
+        policy.domain_of(wallet(), permission_id()),
+        (domain.name_hash, domain.version_hash)
+    );
+
+    // Signed under the default domain: recovers to nobody for the overridden digest.
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+    let envelope = intent.envelope_in(&vm, &domain, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn install_defaults_the_domain() {
+    let (_vm, mut policy) = setup();
+    assert_eq!(
+        policy.domain_of(wallet(), permission_id()),
+        (FixedBytes::ZERO, FixedBytes::ZERO)
+    );
+    install(&mut policy);
+    let domain = IntentDomain::new(CHAIN_ID, policy_address());
+    assert_eq!(
+        policy.domain_of(wallet(), permission_id()),
+        (domain.name_hash, domain.version_hash)
+    );
+}
+
+#[test]
+fn domain_separator_follows_the_chain_id() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    let intent = Intent::new(0);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope.clone())),
+        POLICY_SUCCESS_UINT
+    );
+
+    // After a chain-id change, envelopes signed for the old chain stop validating and ones for
+    // the new chain validate.
+    vm.set_chain_id(CHAIN_ID + 1);
+    let intent = Intent::new(1);
+    let stale = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(stale)),
+        POLICY_FAILED_UINT
+    );
+    let domain = IntentDomain::new(CHAIN_ID + 1, policy_address());
+    let envelope = intent.envelope_in(&vm, &domain, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn custom_domain_follows_the_chain_id() {
+    let (vm, mut policy) = setup();
+    install_with_hub_pin(&mut policy, FixedBytes::ZERO);
+    let intent = Intent::new(0);
+    let envelope = intent.envelope_in(&vm, &install_domain(), signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // The custom domain is hashed with the current chain id.
+    vm.set_chain_id(CHAIN_ID + 1);
+    let domain = IntentDomain { chain_id: CHAIN_ID + 1, ..install_domain() };
+    for nonce in [1, 2] {
+        let intent = Intent::new(nonce);
+        let envelope = intent.envelope_in(&vm, &domain, signer());
+        assert_eq!(
+            policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+            POLICY_SUCCESS_UINT
+        );
+    }
+
+    // Uninstall drops the custom domain: a default-domain reinstall back on the install chain
+    // takes only default-domain envelopes.
+    assert!(policy.on_uninstall(install_data(permission_id(), signer())).is_ok());
+    vm.set_chain_id(CHAIN_ID);
+    install(&mut policy);
+    let intent = Intent::new(0);
+    let custom = intent.envelope_in(&vm, &install_domain(), signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(custom)),
+        POLICY_FAILED_UINT
+    );
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn exported_config_reinstalls_the_same_permission() {
+    let (vm, mut policy) = setup();
+    vm.set_code(Address::repeat_byte(0x13), vec![0x00]);
+    assert!(policy.export_config(wallet(), permission_id()).is_err());
+
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x01, 1, b'F', 1, b'1']);
+    data.push(0x02);
+    data.extend_from_slice(&[0u8; 64]);
+    data.extend_from_slice(&[0x0c; 32]);
+    data.extend_from_slice(&[0x03, 1]);
+    data.extend_from_slice(pool(0xa1).as_slice());
+    data.extend_from_slice(&[0x04, 1]);
+    data.extend_from_slice(oracle().as_slice());
+    data.extend_from_slice(&latest_answer());
+    data.extend_from_slice(&[0x05, 1, 0x32]);
+    data.extend_from_slice(Address::repeat_byte(0x13).as_slice());
+    assert!(policy.on_install(data).is_ok());
+
+    let config_of = |policy: &IntentPolicy| {
+        (
+            policy.signer_of(wallet(), permission_id()),
+            policy.domain_of(wallet(), permission_id()),
+            policy.fact_sources_of(wallet(), permission_id()),
+            policy.fact_source_codehashes_of(wallet(), permission_id()),
+            policy.pool_allowlist_of(wallet(), permission_id()),
+            policy.oracle_calls_of(wallet(), permission_id()),
+            policy.source_overrides_of(wallet(), permission_id()),
+        )
+    };
+    let before = config_of(&policy);
+    let exported = policy.export_config(wallet(), permission_id()).ok().unwrap();
+
+    // Installed again (as on a redeployed policy), the permission is configured the same way.
+    let uninstall = install_data(permission_id(), signer());
+    assert!(policy.on_uninstall(uninstall).is_ok());
+    let mut reinstall = permission_id().to_vec();
+    reinstall.extend_from_slice(&exported);
+    assert!(policy.on_install(reinstall).is_ok());
+    assert_eq!(config_of(&policy), before);
+    assert_eq!(policy.export_config(wallet(), permission_id()).ok(), Some(exported));
+}
+
+fn batch_install_data(entries: &[(FixedBytes<32>, Vec<u8>)]) -> Vec<u8> {
+    let mut data = BATCH_INSTALL_ID.to_vec();
+    data.push(entries.len() as u8);
+    for (permission_id, init_data) in entries {
+        data.extend_from_slice(permission_id.as_slice());
+        data.extend_from_slice(&(init_data.len() as u16).to_be_bytes());
+        data.extend_from_slice(init_data);
+    }
+    data
+}
+
+#[test]
+fn batch_install_installs_each_permission() {
+    let (_vm, mut policy) = setup();
+    let other_id = FixedBytes::repeat_byte(0x22);
+    let other_signer = Address::repeat_byte(0x52);
+    let data = batch_install_data(&[
+        (permission_id(), install_data(permission_id(), signer())[32..].to_vec()),
+        (other_id, install_data(other_id, other_signer)[32..].to_vec()),
+    ]);
+    assert!(policy.on_install(data).is_ok());
+    assert_eq!(policy.signer_of(wallet(), permission_id()), signer());
+    assert_eq!(policy.signer_of(wallet(), other_id), other_signer);
+
+    // Each permission uninstalls on its own; the wallet stays initialised until both are gone.
+    assert!(policy.on_uninstall(permission_id().to_vec()).is_ok());
+    assert!(policy.is_initialized(wallet()));
+    assert!(policy.on_uninstall(other_id.to_vec()).is_ok());
+    assert!(!policy.is_initialized(wallet()));
+
+    // A repeated permission fails the batch.
+    let init_data = install_data(permission_id(), signer())[32..].to_vec();
+    let data = batch_install_data(&[(other_id, init_data.clone()), (other_id, init_data)]);
+    assert!(policy.on_install(data).is_err());
+}
+
+fn bootstrap_install_data(module_type: U256, data: Vec<u8>) -> Vec<u8> {
+    <(sol_data::Uint<256>, sol_data::Bytes) as SolType>::abi_encode_params(&(module_type, Bytes::from(data)))
+}
+
+fn multi_type_install_data(entries: Vec<(U256, Vec<u8>)>) -> Vec<u8> {
+    let (types, datas): (Vec<U256>, Vec<_>) =
+        entries.into_iter().map(|(module_type, data)| (module_type, Bytes::from(data))).unzip();
+    type MultiType = (sol_data::Array<sol_data::Uint<256>>, sol_data::Array<sol_data::Bytes>);
+    <MultiType as SolType>::abi_encode_params(&(types, datas))
+}
+
+#[test]
+fn bootstrap_install_unwraps_module_type_entries() {
+    let (vm, mut policy) = setup();
+    let data = bootstrap_install_data(MODULE_TYPE_POLICY, install_data(permission_id(), signer()));
+    assert!(policy.on_install(data).is_ok());
+    assert_eq!(policy.signer_of(wallet(), permission_id()), signer());
+
+    // A launchpad installing the contract as both policy and hook in one multi-type call.
+    let other = Address::repeat_byte(0xbb);
+    vm.set_sender(other);
+    let data = multi_type_install_data(vec![
+        (MODULE_TYPE_HOOK, Vec::new()),
+        (MODULE_TYPE_POLICY, install_data(permission_id(), signer())),
+    ]);
+    assert!(policy.on_install(data).is_ok());
+    assert_eq!(policy.signer_of(other, permission_id()), signer());
+    assert_eq!(policy.permission_ids_of(other), vec![permission_id()]);
+    assert!(policy.is_hook_installed(other));
+    assert!(!policy.is_hook_installed(wallet()));
+
+    // Unwrapped entries install like direct ones, so a repeat is still rejected.
+    let data = bootstrap_install_data(MODULE_TYPE_POLICY, install_data(permission_id(), signer()));
+    assert!(matches!(policy.on_install(data), Err(ModuleError::AlreadyInitialized(_))));
+}
+
+#[test]
+#[should_panic(expected = "Unsupported module type")]
+fn bootstrap_install_rejects_other_module_types() {
+    let (_vm, mut policy) = setup();
+    let data = bootstrap_install_data(U256::from(1u64), install_data(permission_id(), signer()));
+    let _ = policy.on_install(data);
+}
+
+#[test]
+#[should_panic(expected = "Invalid bootstrap data")]
+fn bootstrap_install_rejects_mismatched_lists() {
+    let (_vm, mut policy) = setup();
+    let mut data =
+        multi_type_install_data(vec![(MODULE_TYPE_POLICY, install_data(permission_id(), signer()))]);
+    // Append a second type id to the `uint256[]` (its length word follows the two head offsets),
+    // moving the `bytes[]` after it along.
+    data[95] = 2;
+    data.splice(128..128, U256::from(4u64).to_be_bytes::<32>());
+    data[63] += 32;
+    let _ = policy.on_install(data);
+}
+
+#[test]
+#[should_panic(expected = "Invalid batch install data")]
+fn batch_install_rejects_trailing_bytes() {
+    let (_vm, mut policy) = setup();
+    let mut data = batch_install_data(&[(
+        permission_id(),
+        install_data(permission_id(), signer())[32..].to_vec(),
+    )]);
+    data.push(0);
+    let _ = policy.on_install(data);
+}
+
+#[test]
+#[should_panic(expected = "Invalid domain")]
+fn install_rejects_domain_and_domain_hashes_together() {
+    let (_vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x01, 1, b'F', 1, b'1']);
+    data.push(0x06);
+    data.extend_from_slice(keccak256(b"F").as_slice());
+    data.extend_from_slice(keccak256(b"1").as_slice());
+    let _ = policy.on_install(data);
+}
+
+#[test]
+#[should_panic(expected = "Invalid domain")]
+fn install_rejects_malformed_domain_override() {
+    let (_vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x01, 5, b'A']);
+    let _ = policy.on_install(data);
+}
+
+fn hub() -> Address {
+    Address::repeat_byte(0x03)
+}
+
+/// Pin the liquidity hub (source 0x03) to `codehash`, with the domain override before it.
+fn install_with_hub_pin(policy: &mut IntentPolicy, codehash: FixedBytes<32>) {
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x01, 1, b'F', 1, b'1']);
+    data.push(0x02);
+    data.extend_from_slice(&[0u8; 64]);
+    data.extend_from_slice(codehash.as_slice());
+    assert!(policy.on_install(data).is_ok());
+}
+
+/// An intent whose program reads the hub's reserve, mocked to pass.
+fn reserve_intent(vm: &TestVM) -> Intent {
+    let lcc = Address::repeat_byte(0x77);
+    let mut intent = Intent::new(0);
+    intent.program.push(0x32);
+    intent.program.extend_from_slice(lcc.as_slice());
+    intent.program.extend_from_slice(&U256::from(1u64).to_be_bytes::<32>());
+
+    let mut call = keccak256(b"reserveOfUnderlying(address)")[..4].to_vec();
+    call.extend_from_slice(&[0u8; 12]);
+    call.extend_from_slice(lcc.as_slice());
+    vm.mock_static_call(hub(), call, Ok(U256::from(5u64).to_be_bytes::<32>().to_vec()));
+    intent
+}
+
+#[test]
+fn check_enforces_hub_solvency_ratio() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let lcc = Address::repeat_byte(0x77);
+    let hub_call = |sig: &[u8]| {
+        let mut call = keccak256(sig)[..4].to_vec();
+        call.extend_from_slice(&[0u8; 12]);
+        call.extend_from_slice(lcc.as_slice());
+        call
+    };
+    let mock = |reserve: u64, queue: u64| {
+        let word = |value: u64| Ok(U256::from(value).to_be_bytes::<32>().to_vec());
+        vm.mock_static_call(hub(), hub_call(b"reserveOfUnderlying(address)"), word(reserve));
+        vm.mock_static_call(hub(), hub_call(b"queueOfUnderlying(address)"), word(queue));
+    };
+    // reserve / queue >= 120%.
+    let intent = |nonce: u64| {
+        let mut intent = Intent::new(nonce);
+        Check::HubSolvency { lcc, min_ratio_bps: 12_000 }.encode_into(&mut intent.program);
+        intent
+    };
+    let check = |policy: &mut IntentPolicy, intent: &Intent| {
+        let envelope = intent.envelope(&vm, signer());
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope))
+    };
+
+    mock(119, 100);
+    assert_eq!(check(&mut policy, &intent(0)), POLICY_FAILED_UINT);
+    mock(120, 100);
+    assert_eq!(check(&mut policy, &intent(0)), POLICY_SUCCESS_UINT);
+    // Nothing queued: any reserve is solvent.
+    mock(0, 0);
+    assert_eq!(check(&mut policy, &intent(1)), POLICY_SUCCESS_UINT);
+}
+
+#[test]
+fn check_caps_queue_sum_over_owners() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    let lcc = Address::repeat_byte(0x77);
+    let owners = vec![Address::repeat_byte(0x01), Address::repeat_byte(0x02)];
+    let mock = |owner: Address, queued: u64| {
+        let mut call = keccak256(b"settleQueue(address,address)")[..4].to_vec();
+        call.extend_from_slice(&[0u8; 12]);
+        call.extend_from_slice(lcc.as_slice());
+        call.extend_from_slice(&[0u8; 12]);
+        call.extend_from_slice(owner.as_slice());
+        vm.mock_static_call(hub(), call, Ok(U256::from(queued).to_be_bytes::<32>().to_vec()));
+    };
+    let mut intent = Intent::new(0);
+    Check::QueueSumLte { lcc, owners: owners.clone(), max: U256::from(10u64) }
+        .encode_into(&mut intent.program);
+    let check = |policy: &mut IntentPolicy| {
+        let envelope = intent.envelope(&vm, signer());
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope))
+    };
+
+    // Each owner is under the cap; together they are not.
+    mock(owners[0], 6);
+    mock(owners[1], 5);
+    assert_eq!(check(&mut policy), POLICY_FAILED_UINT);
+    mock(owners[1], 4);
+    assert_eq!(check(&mut policy), POLICY_SUCCESS_UINT);
+}
+
+#[test]
+fn pinned_fact_source_must_keep_its_codehash() {
+    let (vm, mut policy) = setup();
+    let code = vec![0x60, 0x00, 0x60, 0x00, 0xf3];
+    vm.set_code(hub(), code.clone());
+    install_with_hub_pin(&mut policy, keccak256(&code));
+    assert_eq!(
+        policy.fact_source_codehashes_of(wallet(), permission_id()),
+        (FixedBytes::ZERO, FixedBytes::ZERO, keccak256(&code))
+    );
+
+    let intent = reserve_intent(&vm);
+    let envelope = intent.envelope_in(&vm, &install_domain(), signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope.clone())),
+        POLICY_SUCCESS_UINT
+    );
+
+    // The hub's code changes under the same address: its facts are no longer trusted.
+    let mut intent = reserve_intent(&vm);
+    intent.nonce = U256::from(1u64);
+    let envelope = intent.envelope_in(&vm, &install_domain(), signer());
+    vm.set_code(hub(), vec![0x00]);
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn unpinned_fact_sources_are_not_checked() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert_eq!(
+        policy.fact_source_codehashes_of(wallet(), permission_id()),
+        (FixedBytes::ZERO, FixedBytes::ZERO, FixedBytes::ZERO)
+    );
+
+    let intent = reserve_intent(&vm);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn source_override_routes_an_opcode_to_its_source() {
+    let (vm, mut policy) = setup();
+    let migrated_hub = Address::repeat_byte(0x13);
+    vm.set_code(migrated_hub, vec![0x00]);
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x05, 1, 0x32]);
+    data.extend_from_slice(migrated_hub.as_slice());
+    assert!(policy.on_install(data).is_ok());
+    assert_eq!(
+        policy.source_overrides_of(wallet(), permission_id()),
+        vec![(0x32, migrated_hub)]
+    );
+
+    // The configured hub would pass; the migrated one reports too small a reserve.
+    let intent = reserve_intent(&vm);
+    let mut call = keccak256(b"reserveOfUnderlying(address)")[..4].to_vec();
+    call.extend_from_slice(&[0u8; 12]);
+    call.extend_from_slice(Address::repeat_byte(0x77).as_slice());
+    vm.mock_static_call(migrated_hub, call.clone(), Ok(U256::ZERO.to_be_bytes::<32>().to_vec()));
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    vm.mock_static_call(migrated_hub, call, Ok(U256::from(5u64).to_be_bytes::<32>().to_vec()));
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    let uninstall = install_data(permission_id(), signer());
+    assert!(policy.on_uninstall(uninstall).is_ok());
+    assert!(policy.source_overrides_of(wallet(), permission_id()).is_empty());
+}
+
+#[test]
+fn source_override_is_pinned_to_its_install_codehash() {
+    let (vm, mut policy) = setup();
+    let migrated_hub = Address::repeat_byte(0x13);
+    vm.set_code(migrated_hub, vec![0x00]);
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x05, 1, 0x32]);
+    data.extend_from_slice(migrated_hub.as_slice());
+    assert!(policy.on_install(data).is_ok());
+
+    let mut call = keccak256(b"reserveOfUnderlying(address)")[..4].to_vec();
+    call.extend_from_slice(&[0u8; 12]);
+    call.extend_from_slice(Address::repeat_byte(0x77).as_slice());
+    vm.mock_static_call(migrated_hub, call, Ok(U256::from(5u64).to_be_bytes::<32>().to_vec()));
+    let intent = reserve_intent(&vm);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // The override's code changes under the same address: its facts are no longer trusted.
+    vm.set_code(migrated_hub, vec![0x60, 0x00]);
+    let mut intent = reserve_intent(&vm);
+    intent.nonce = U256::from(1u64);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn source_override_counts_toward_the_call_target_cap() {
+    let (vm, mut policy) = setup();
+    let migrated_hub = Address::repeat_byte(0x13);
+    vm.set_code(migrated_hub, vec![0x00]);
+    let mut reserve_call = keccak256(b"reserveOfUnderlying(address)")[..4].to_vec();
+    reserve_call.extend_from_slice(&[0u8; 12]);
+    reserve_call.extend_from_slice(Address::repeat_byte(0x77).as_slice());
+    let five = U256::from(5u64).to_be_bytes::<32>().to_vec();
+    vm.mock_static_call(migrated_hub, reserve_call, Ok(five));
+
+    // The hub read plus `targets` static calls of the program's own, each mocked to pass.
+    let intent = |nonce: u64, targets: usize| {
+        let mut intent = reserve_intent(&vm);
+        intent.nonce = U256::from(nonce);
+        for i in 0..targets {
+            let target = Address::repeat_byte(0xc0 + i as u8);
+            vm.mock_static_call(target, vec![0xaa; 4], Ok(vec![0u8; 32]));
+            Check::StaticCallU256 {
+                target,
+                selector: [0xaa; 4],
+                args: Vec::new(),
+                op: CompOp::Eq,
+                rhs: U256::ZERO,
+            }
+            .encode_into(&mut intent.program);
+        }
+        intent
+    };
+    let check = |policy: &mut IntentPolicy, intent: Intent| {
+        let envelope = intent.envelope(&vm, signer());
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope))
+    };
+
+    // Static calls must be allowlisted, as oracle calls.
+    let install_data = |with_override: bool| {
+        let mut data = install_data(permission_id(), signer());
+        data.extend_from_slice(&[0x04, MAX_CALL_TARGETS as u8]);
+        for i in 0..MAX_CALL_TARGETS {
+            data.extend_from_slice(Address::repeat_byte(0xc0 + i as u8).as_slice());
+            data.extend_from_slice(&[0xaa; 4]);
+        }
+        if with_override {
+            data.extend_from_slice(&[0x05, 1, 0x32]);
+            data.extend_from_slice(migrated_hub.as_slice());
+        }
+        data
+    };
+
+    assert!(policy.on_install(install_data(true)).is_ok());
+    assert_eq!(check(&mut policy, intent(0, MAX_CALL_TARGETS - 1)), POLICY_SUCCESS_UINT);
+    // A full set of the program's own targets plus the hub override it routes to is one too many.
+    assert_eq!(check(&mut policy, intent(1, MAX_CALL_TARGETS)), POLICY_FAILED_UINT);
+
+    // Without the override the same program fits.
+    assert!(policy.on_uninstall(install_data(false)).is_ok());
+    assert!(policy.on_install(install_data(false)).is_ok());
+    assert_eq!(check(&mut policy, intent(0, MAX_CALL_TARGETS)), POLICY_SUCCESS_UINT);
+}
+
+#[test]
+#[should_panic(expected = "Source override has no code")]
+fn install_rejects_override_without_code() {
+    let (_vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x05, 1, 0x32]);
+    data.extend_from_slice(Address::repeat_byte(0x13).as_slice());
+    let _ = policy.on_install(data);
+}
+
+#[test]
+#[should_panic(expected = "Invalid source overrides")]
+fn install_rejects_override_of_an_opcode_without_fact_source() {
+    let (_vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x05, 1, 0x01]);
+    data.extend_from_slice(Address::repeat_byte(0x13).as_slice());
+    let _ = policy.on_install(data);
+}
+
+#[test]
+#[should_panic(expected = "Invalid init extension order")]
+fn install_rejects_out_of_order_extensions() {
+    let (_vm, mut policy) = setup();
+    let mut data = install_data(permission_id(), signer());
+    data.push(0x02);
+    data.extend_from_slice(&[0u8; 96]);
+    data.extend_from_slice(&[0x01, 1, b'F', 1, b'1']);
+    let _ = policy.on_install(data);
+}
+
+/// The domain set by [`install_with_hub_pin`].
+fn install_domain() -> IntentDomain {
+    IntentDomain {
+        name_hash: keccak256(b"F"),
+        version_hash: keccak256(b"1"),
+        chain_id: CHAIN_ID,
+        verifying_contract: policy_address(),
+    }
+}
+
+fn token() -> Address {
+    Address::repeat_byte(0x7e)
+}
+
+/// Mock `token.balanceOf(wallet)`.
+fn mock_balance(vm: &TestVM, balance: u64) {
+    let mut call = keccak256(b"balanceOf(address)")[..4].to_vec();
+    call.extend_from_slice(&[0u8; 12]);
+    call.extend_from_slice(wallet().as_slice());
+    vm.mock_static_call(token(), call, Ok(U256::from(balance).to_be_bytes::<32>().to_vec()));
+}
+
+fn entry_point() -> Address {
+    Address::repeat_byte(0xe4)
+}
+
+/// Kernel `execute(bytes32 mode, bytes executionCalldata)` calldata making the single call
+/// `target.call(call)`.
+fn kernel_execute(target: Address, call: &[u8]) -> Vec<u8> {
+    let mut execution = target.to_vec();
+    execution.extend_from_slice(&[0u8; 32]);
+    execution.extend_from_slice(call);
+    let mut data = keccak256(b"execute(bytes32,bytes)")[..4].to_vec();
+    data.extend_from_slice(&<(sol_data::FixedBytes<32>, sol_data::Bytes)>::abi_encode_params(&(
+        FixedBytes::<32>::ZERO,
+        Bytes::from(execution),
+    )));
+    data
+}
+
+/// UserOp `callData` running a distinct call per `nonce` through Kernel's `executeUserOp`, so
+/// the wallet's hook runs around it.
+fn hooked_call_data(nonce: u64) -> Vec<u8> {
+    let mut call = vec![0xde, 0xad, 0xbe, 0xef];
+    call.extend_from_slice(&nonce.to_be_bytes());
+    let mut data = EXECUTE_USER_OP_SELECTOR.to_vec();
+    data.extend_from_slice(&kernel_execute(Address::repeat_byte(0x5e), &call));
+    data
+}
+
+/// The hash `intent`'s outflow bounds are pending under: that of the calldata Kernel executes.
+fn execution_hash(intent: &Intent) -> FixedBytes<32> {
+    keccak256(&intent.call_data[4..])
+}
+
+/// An intent bounding the wallet's outflow of [`token`] to `max_out`.
+fn delta_intent(nonce: u64, max_out: u64) -> Intent {
+    let mut intent = Intent::new(nonce);
+    intent.call_data = hooked_call_data(nonce);
+    intent.program.push(0x14);
+    intent.program.extend_from_slice(token().as_slice());
+    intent.program.extend_from_slice(&U256::from(max_out).to_be_bytes::<32>());
+    intent
+}
+
+/// Run the hook around the execution of `intent`'s UserOp, with the arguments Kernel's
+/// `executeUserOp` passes it: the EntryPoint as sender and `userOp.callData[4:]` as `msgData`.
+fn run_hook(policy: &mut IntentPolicy, intent: &Intent) -> Result<(), ModuleError> {
+    let hook_data = policy.pre_check(entry_point(), U256::ZERO, intent.call_data[4..].to_vec());
+    policy.post_check(hook_data)
+}
+
+#[test]
+fn hook_enforces_the_recorded_token_outflow() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.is_module_type(U256::from(4u64)));
+    assert!(policy.on_install(Vec::new()).is_ok());
+    assert!(policy.is_hook_installed(wallet()));
+
+    mock_balance(&vm, 1_000);
+    let intent = delta_intent(0, 300);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+    assert_eq!(policy.pending_deltas_of(wallet(), execution_hash(&intent)), U256::from(1u64));
+
+    // Other executions the hook runs around have nothing pending.
+    let other = policy.pre_check(entry_point(), U256::ZERO, hooked_call_data(9)[4..].to_vec());
+    assert!(policy.post_check(other).is_ok());
+    assert_eq!(policy.pending_deltas_of(wallet(), execution_hash(&intent)), U256::from(1u64));
+
+    // Execution spends more than the bound: the hook reverts it.
+    mock_balance(&vm, 600);
+    assert!(matches!(
+        run_hook(&mut policy, &intent),
+        Err(ModuleError::TokenOutflowExceeded(_))
+    ));
+
+    mock_balance(&vm, 700);
+    assert!(run_hook(&mut policy, &intent).is_ok());
+    assert_eq!(policy.pending_deltas_of(wallet(), execution_hash(&intent)), U256::ZERO);
+}
+
+#[test]
+fn hook_holds_each_user_op_to_its_own_bounds() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.on_install(Vec::new()).is_ok());
+
+    // Two delta-checked UserOps validated together, as in one bundle.
+    mock_balance(&vm, 1_000);
+    let first = delta_intent(0, 0);
+    let envelope = first.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), first.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+    let second = delta_intent(1, 300);
+    let envelope = second.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), second.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // The first execution reverted, so its hook never ran; the second is held to its own bound.
+    mock_balance(&vm, 800);
+    assert!(run_hook(&mut policy, &second).is_ok());
+    assert_eq!(policy.pending_deltas_of(wallet(), execution_hash(&first)), U256::from(1u64));
+    assert_eq!(policy.pending_deltas_of(wallet(), execution_hash(&second)), U256::ZERO);
+
+    // The same calldata cannot validate again while the first bounds are pending.
+    let mut retry = delta_intent(2, 0);
+    retry.call_data = first.call_data.clone();
+    let envelope = retry.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), retry.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    // An execution cannot drop pending bounds; the wallet can outside of one.
+    policy.pre_check(entry_point(), U256::ZERO, hooked_call_data(9)[4..].to_vec());
+    assert!(matches!(
+        policy.discard_pending_deltas(execution_hash(&first)),
+        Err(ModuleError::Unauthorized(_))
+    ));
+    assert!(policy.post_check(Vec::new()).is_ok());
+    assert!(policy.discard_pending_deltas(execution_hash(&first)).is_ok());
+    assert_eq!(policy.pending_deltas_of(wallet(), execution_hash(&first)), U256::ZERO);
+    let envelope = retry.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), retry.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn delta_checks_need_an_execute_user_op() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.on_install(Vec::new()).is_ok());
+    mock_balance(&vm, 1_000);
+
+    // Kernel skips the hook for a plain `execute`, so nothing would enforce the bound.
+    let mut intent = delta_intent(0, 300);
+    intent.call_data = intent.call_data[4..].to_vec();
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+    assert_eq!(policy.pending_deltas_of(wallet(), keccak256(&intent.call_data)), U256::ZERO);
+
+    let mut intent = window_intent(0, NOW, 86_400, 100);
+    intent.call_data = intent.call_data[4..].to_vec();
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn delta_checks_need_the_hook_and_a_readable_balance() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.on_install(Vec::new()).is_ok());
+
+    // The token returns nothing.
+    let intent = delta_intent(0, 10);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    mock_balance(&vm, 50);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // Without the hook nothing would enforce the bound, so validation fails.
+    assert!(policy.on_uninstall(Vec::new()).is_ok());
+    assert!(!policy.is_hook_installed(wallet()));
+    let intent = delta_intent(1, 10);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+/// An intent signed at `now`, capping the wallet's [`token`] outflow at `max` per `window`
+/// seconds.
+fn window_intent(nonce: u64, now: u64, window: u32, max: u64) -> Intent {
+    let mut intent = Intent::new(nonce);
+    intent.call_data = hooked_call_data(nonce);
+    intent.deadline = now + 60;
+    intent.program[1..9].copy_from_slice(&intent.deadline.to_be_bytes());
+    intent.program.push(0x15);
+    intent.program.extend_from_slice(token().as_slice());
+    intent.program.extend_from_slice(&window.to_be_bytes());
+    intent.program.extend_from_slice(&U256::from(max).to_be_bytes::<32>());
+    intent
+}
+
+/// Validate `intent` with the wallet holding `before`, then run the hook with it holding `after`.
+fn spend(
+    vm: &TestVM,
+    policy: &mut IntentPolicy,
+    intent: &Intent,
+    before: u64,
+    after: u64,
+) -> Result<(), ModuleError> {
+    mock_balance(vm, before);
+    let envelope = intent.envelope(vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+    mock_balance(vm, after);
+    run_hook(policy, intent)
+}
+
+#[test]
+fn window_spend_accumulates_until_the_window_slides_past() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.on_install(Vec::new()).is_ok());
+
+    assert!(spend(&vm, &mut policy, &window_intent(0, NOW, 86_400, 100), 1_000, 940).is_ok());
+    vm.set_block_timestamp(NOW + 3_600);
+    let intent = window_intent(1, NOW + 3_600, 86_400, 100);
+    assert!(spend(&vm, &mut policy, &intent, 940, 900).is_ok());
+
+    // 60 + 40 spent in the last day; one more unit is over the limit.
+    vm.set_block_timestamp(NOW + 7_200);
+    let intent = window_intent(2, NOW + 7_200, 86_400, 100);
+    assert!(matches!(
+        spend(&vm, &mut policy, &intent, 900, 899),
+        Err(ModuleError::WindowSpendExceeded(_))
+    ));
+
+    // Two days on, the earlier spend is outside the window.
+    vm.set_block_timestamp(NOW + 2 * 86_400);
+    let intent = window_intent(3, NOW + 2 * 86_400, 86_400, 100);
+    assert!(spend(&vm, &mut policy, &intent, 900, 810).is_ok());
+}
+
+#[test]
+fn window_spend_books_user_ops_validated_together() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.on_install(Vec::new()).is_ok());
+
+    // Both UserOps of one bundle validate before either executes.
+    mock_balance(&vm, 1_000);
+    let first = window_intent(0, NOW, 86_400, 100);
+    let envelope = first.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), first.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+    let second = window_intent(1, NOW, 86_400, 100);
+    let envelope = second.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), second.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // Each execution's outflow is booked, so the second takes the window over its limit.
+    mock_balance(&vm, 940);
+    assert!(run_hook(&mut policy, &first).is_ok());
+    assert!(matches!(
+        run_hook(&mut policy, &second),
+        Err(ModuleError::WindowSpendExceeded(_))
+    ));
+}
+
+#[test]
+fn window_spend_fails_without_the_hook() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    mock_balance(&vm, 1_000);
+
+    // Nothing would book the spend, so the window cannot be held.
+    let intent = window_intent(0, NOW, 86_400, 100);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+    assert_eq!(policy.pending_deltas_of(wallet(), execution_hash(&intent)), U256::ZERO);
+
+    assert!(policy.on_install(Vec::new()).is_ok());
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn window_spend_rejects_unsupported_windows() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.on_install(Vec::new()).is_ok());
+    mock_balance(&vm, 1_000);
+    for window in [0, 31 * 86_400] {
+        let intent = window_intent(0, NOW, window, 100);
+        let envelope = intent.envelope(&vm, signer());
+        assert_eq!(
+            policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+            POLICY_FAILED_UINT
+        );
+    }
+}
+
+fn pool(byte: u8) -> FixedBytes<32> {
+    FixedBytes::repeat_byte(byte)
+}
+
+/// Install with a pool allowlist of `pools`.
+fn install_with_pools(policy: &mut IntentPolicy, pools: &[FixedBytes<32>]) {
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x03, pools.len() as u8]);
+    for pool in pools {
+        data.extend_from_slice(pool.as_slice());
+    }
+    assert!(policy.on_install(data).is_ok());
+}
+
+/// ABI-encoded Uniswap v4 `PoolKey` of test pool `byte`: currencies `byte` and `byte + 1`, a
+/// 0.3% fee, tick spacing 60 and no hooks.
+fn pool_key(byte: u8) -> Vec<u8> {
+    let mut key = Vec::new();
+    for currency in [byte, byte + 1] {
+        key.extend_from_slice(&[0u8; 12]);
+        key.extend_from_slice(Address::repeat_byte(currency).as_slice());
+    }
+    key.extend_from_slice(&U256::from(3_000u64).to_be_bytes::<32>());
+    key.extend_from_slice(&U256::from(60u64).to_be_bytes::<32>());
+    key.extend_from_slice(&[0u8; 32]);
+    key
+}
+
+/// The poolId of test pool `byte`.
+fn pool_id(byte: u8) -> FixedBytes<32> {
+    keccak256(pool_key(byte))
+}
+
+fn router() -> Address {
+    Address::repeat_byte(0x40)
+}
+
+/// A router call swapping on test pool `byte`: `swap(PoolKey, uint256 amountIn)`.
+fn swap_call(byte: u8) -> Vec<u8> {
+    let mut call = keccak256(b"swap((address,address,uint24,int24,address),uint256)")[..4].to_vec();
+    call.extend_from_slice(&pool_key(byte));
+    call.extend_from_slice(&U256::from(1_000u64).to_be_bytes::<32>());
+    call
+}
+
+/// Kernel batch `execute` calldata making each `(target, call)` of `calls`.
+fn kernel_execute_batch(calls: &[(Address, Vec<u8>)]) -> Vec<u8> {
+    type Executions = (sol_data::Array<(sol_data::Address, sol_data::Uint<256>, sol_data::Bytes)>,);
+    let executions = calls
+        .iter()
+        .map(|(target, call)| (*target, U256::ZERO, Bytes::from(call.clone())))
+        .collect::<Vec<_>>();
+    let mut mode = [0u8; 32];
+    mode[0] = 0x01;
+    let mut data = keccak256(b"execute(bytes32,bytes)")[..4].to_vec();
+    data.extend_from_slice(&<(sol_data::FixedBytes<32>, sol_data::Bytes)>::abi_encode_params(&(
+        FixedBytes::from(mode),
+        Bytes::from(<Executions as SolType>::abi_encode_params(&(executions,))),
+    )));
+    data
+}
+
+/// An intent declaring test pool `byte` with `CheckPoolAllowed` and swapping on it.
+fn pool_intent(nonce: u64, byte: u8) -> Intent {
+    let mut intent = Intent::new(nonce);
+    intent.call_data = kernel_execute(router(), &swap_call(byte));
+    intent.program.push(0x22);
+    intent.program.extend_from_slice(pool_id(byte).as_slice());
+    intent
+}
+
+#[test]
+fn pool_allowlist_contains_the_named_pools() {
+    let (vm, mut policy) = setup();
+    install_with_pools(&mut policy, &[pool_id(0xa1), pool_id(0xa2), pool_id(0xa1)]);
+    assert_eq!(
+        policy.pool_allowlist_of(wallet(), permission_id()),
+        vec![pool_id(0xa1), pool_id(0xa2)]
+    );
+
+    let intent = pool_intent(0, 0xa2);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // A pool off the list, declared and traded or only read by another check.
+    let intent = pool_intent(1, 0xb0);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+    let mut intent = pool_intent(1, 0xa1);
+    intent.program.push(0x20);
+    intent.program.extend_from_slice(pool_id(0xb0).as_slice());
+    intent.program.extend_from_slice(&i32::MIN.to_be_bytes());
+    intent.program.extend_from_slice(&i32::MAX.to_be_bytes());
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    // With an allowlist, the program must declare a pool.
+    let mut intent = Intent::new(1);
+    intent.call_data = kernel_execute(router(), &swap_call(0xa1));
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    let uninstall = install_data(permission_id(), signer());
+    assert!(policy.on_uninstall(uninstall).is_ok());
+    assert!(policy.pool_allowlist_of(wallet(), permission_id()).is_empty());
+}
+
+#[test]
+fn pool_allowlist_binds_the_call_bundle() {
+    let (vm, mut policy) = setup();
+    install_with_pools(&mut policy, &[pool_id(0xa1)]);
+
+    // The program declares allowed pool A while the bundle swaps on pool B.
+    let mut intent = pool_intent(0, 0xa1);
+    intent.call_data = kernel_execute(router(), &swap_call(0xb0));
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    // Behind `executeUserOp` the bundle is read the same way.
+    let mut hooked = EXECUTE_USER_OP_SELECTOR.to_vec();
+    hooked.extend_from_slice(&intent.call_data);
+    intent.call_data = hooked;
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    // Calldata that is not a Kernel `execute` cannot be matched with the allowlist.
+    let mut intent = pool_intent(0, 0xa1);
+    intent.call_data = swap_call(0xa1);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    let mut intent = pool_intent(0, 0xa1);
+    let mut hooked = EXECUTE_USER_OP_SELECTOR.to_vec();
+    hooked.extend_from_slice(&intent.call_data);
+    intent.call_data = hooked;
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+#[test]
+fn pool_allowlist_reads_every_call_of_a_batch() {
+    let (vm, mut policy) = setup();
+    install_with_pools(&mut policy, &[pool_id(0xa1), pool_id(0xa2)]);
+
+    // A key nested in a `bytes` argument counts, like a position manager's `unlockData`.
+    let mut nested = keccak256(b"modifyLiquidities(bytes,uint256)")[..4].to_vec();
+    nested.extend_from_slice(&<(sol_data::Bytes, sol_data::Uint<256>)>::abi_encode_params(&(
+        Bytes::from(pool_key(0xa2)),
+        U256::from(NOW),
+    )));
+    let mut intent = pool_intent(0, 0xa1);
+    intent.call_data =
+        kernel_execute_batch(&[(router(), swap_call(0xa1)), (router(), nested.clone())]);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // Every call must pass a pool, and only allowed ones.
+    let approve = keccak256(b"approve(address,uint256)")[..4].to_vec();
+    for calls in [
+        vec![(router(), swap_call(0xa1)), (token(), approve)],
+        vec![(router(), swap_call(0xa1)), (router(), swap_call(0xb0))],
+    ] {
+        let mut intent = pool_intent(1, 0xa1);
+        intent.call_data = kernel_execute_batch(&calls);
+        let envelope = intent.envelope(&vm, signer());
+        assert_eq!(
+            policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+            POLICY_FAILED_UINT
+        );
+    }
+
+    // A delegatecall's effects cannot be read off its calldata.
+    let mut intent = pool_intent(1, 0xa1);
+    intent.call_data[4] = 0xff; // the mode's call type
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+fn pool_allowed_fails_without_an_allowlist() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    assert!(policy.pool_allowlist_of(wallet(), permission_id()).is_empty());
+
+    let intent = pool_intent(0, 0xa1);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+#[test]
+#[should_panic(expected = "Invalid pool allowlist")]
+fn install_rejects_an_empty_pool_allowlist() {
+    let (_vm, mut policy) = setup();
+    install_with_pools(&mut policy, &[]);
+}
+
+/// An intent requiring the hub's reserve of `lcc` to cover `scale_bps` of the wallet's queue.
+fn compare_intent(nonce: u64, scale_bps: u32) -> Intent {
+    let lcc = Address::repeat_byte(0x77);
+    let mut intent = Intent::new(nonce);
+    intent.program.push(0xf1);
+    intent.program.extend_from_slice(hub().as_slice());
+    intent.program.extend_from_slice(&keccak256(b"reserveOfUnderlying(address)")[..4]);
+    intent.program.extend_from_slice(&32u16.to_be_bytes());
+    intent.program.extend_from_slice(&[0u8; 12]);
+    intent.program.extend_from_slice(lcc.as_slice());
+    intent.program.push(3); // Gte
+    intent.program.extend_from_slice(hub().as_slice());
+    intent.program.extend_from_slice(&keccak256(b"settleQueue(address,address)")[..4]);
+    intent.program.extend_from_slice(&64u16.to_be_bytes());
+    intent.program.extend_from_slice(&[0u8; 12]);
+    intent.program.extend_from_slice(lcc.as_slice());
+    intent.program.extend_from_slice(&[0u8; 12]);
+    intent.program.extend_from_slice(wallet().as_slice());
+    intent.program.extend_from_slice(&scale_bps.to_be_bytes());
+    intent
+}
+
+/// Mock the hub's reserve of and the wallet's queue in the LCC of [`compare_intent`].
+fn mock_reserve_and_queue(vm: &TestVM, reserve: u64, queued: u64) {
+    let lcc = Address::repeat_byte(0x77);
+    let mut call = keccak256(b"reserveOfUnderlying(address)")[..4].to_vec();
+    call.extend_from_slice(&[0u8; 12]);
+    call.extend_from_slice(lcc.as_slice());
+    vm.mock_static_call(hub(), call, Ok(U256::from(reserve).to_be_bytes::<32>().to_vec()));
+
+    let mut call = keccak256(b"settleQueue(address,address)")[..4].to_vec();
+    call.extend_from_slice(&[0u8; 12]);
+    call.extend_from_slice(lcc.as_slice());
+    call.extend_from_slice(&[0u8; 12]);
+    call.extend_from_slice(wallet().as_slice());
+    vm.mock_static_call(hub(), call, Ok(U256::from(queued).to_be_bytes::<32>().to_vec()));
+}
+
+#[test]
+fn static_call_compare_scales_the_right_hand_side() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    // 500 >= 400 * 1.2
+    mock_reserve_and_queue(&vm, 500, 400);
+    let intent = compare_intent(0, 12_000);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // 500 < 450 * 1.2
+    mock_reserve_and_queue(&vm, 500, 450);
+    let intent = compare_intent(1, 12_000);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+    let intent = compare_intent(1, 10_000);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+}
+
+/// An intent bounding the pool's average tick over the last `window` seconds.
+fn twap_intent(nonce: u64, window: u32, min: i32, max: i32) -> Intent {
+    let mut intent = Intent::new(nonce);
+    intent.program.push(0x23);
+    intent.program.extend_from_slice(pool(0xa1).as_slice());
+    intent.program.extend_from_slice(&window.to_be_bytes());
+    intent.program.extend_from_slice(&min.to_be_bytes());
+    intent.program.extend_from_slice(&max.to_be_bytes());
+    intent
+}
+
+/// Mock `stateView.observe(pool, [window, 0])` to return the tick cumulatives `then` and `now`.
+fn mock_observe(vm: &TestVM, window: u32, then: i64, now: i64) {
+    let int56 =
+        |value: i64| U256::from_be_bytes(I256::try_from(value).unwrap().to_be_bytes::<32>());
+    let mut call = keccak256(b"observe(bytes32,uint32[])")[..4].to_vec();
+    call.extend_from_slice(pool(0xa1).as_slice());
+    for word in [U256::from(0x40u64), U256::from(2u64), U256::from(window), U256::ZERO] {
+        call.extend_from_slice(&word.to_be_bytes::<32>());
+    }
+    let mut out = Vec::new();
+    let tick_cumulatives = [U256::from(2u64), int56(then), int56(now)];
+    for word in [U256::from(0x40u64), U256::from(0xa0u64)].into_iter().chain(tick_cumulatives) {
+        out.extend_from_slice(&word.to_be_bytes::<32>());
+    }
+    out.extend_from_slice(&U256::from(2u64).to_be_bytes::<32>());
+    out.extend_from_slice(&[0u8; 64]);
+    vm.mock_static_call(Address::repeat_byte(0x01), call, Ok(out));
+}
+
+#[test]
+fn twap_tick_bounds_use_the_average_over_the_window() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+
+    // (-1_000 - 200) / 600 = -2, inside [-2, 2].
+    mock_observe(&vm, 600, 200, -1_000);
+    let intent = twap_intent(0, 600, -2, 2);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+
+    // -1_201 / 600 rounds down to -3.
+    mock_observe(&vm, 600, 200, -1_001);
+    let intent = twap_intent(1, 600, -2, 2);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    let intent = twap_intent(1, 0, -2, 2);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
+
+fn oracle() -> Address {
+    Address::repeat_byte(0x0a)
+}
+
+fn latest_answer() -> [u8; 4] {
+    keccak256(b"latestAnswer()")[..4].try_into().unwrap()
+}
+
+/// Install allowlisting [`oracle`]'s `latestAnswer()` as a price source.
+fn install_with_oracle(policy: &mut IntentPolicy) {
+    let mut data = install_data(permission_id(), signer());
+    data.extend_from_slice(&[0x04, 1]);
+    data.extend_from_slice(oracle().as_slice());
+    data.extend_from_slice(&latest_answer());
+    assert!(policy.on_install(data).is_ok());
+}
+
+/// An intent keeping the pool's spot price within `max_bps` of the 8-decimal oracle price.
+fn deviation_intent(nonce: u64, max_bps: u32) -> Intent {
+    let mut intent = Intent::new(nonce);
+    intent.program.push(0x24);
+    intent.program.extend_from_slice(pool(0xa1).as_slice());
+    intent.program.extend_from_slice(oracle().as_slice());
+    intent.program.extend_from_slice(&latest_answer());
+    intent.program.push(8);
+    intent.program.extend_from_slice(&max_bps.to_be_bytes());
+    intent
+}
+
+/// Mock a spot price of exactly 1 (`sqrtPriceX96 = 2^96`) and the oracle's `answer`.
+fn mock_prices(vm: &TestVM, answer: u64) {
+    let mut call = keccak256(b"getSlot0(bytes32)")[..4].to_vec();
+    call.extend_from_slice(pool(0xa1).as_slice());
+    let mut slot0 = (U256::from(1u64) << 96usize).to_be_bytes::<32>().to_vec();
+    slot0.extend_from_slice(&[0u8; 96]);
+    vm.mock_static_call(Address::repeat_byte(0x01), call, Ok(slot0));
+    vm.mock_static_call(
+        oracle(),
+        latest_answer().to_vec(),
+        Ok(U256::from(answer).to_be_bytes::<32>().to_vec()),
+    );
+}
+
+#[test]
+fn oracle_deviation_bounds_spot_against_an_allowlisted_oracle() {
+    let (vm, mut policy) = setup();
+    install_with_oracle(&mut policy);
+    assert_eq!(
+        policy.oracle_calls_of(wallet(), permission_id()),
+        vec![(oracle(), FixedBytes(latest_answer()))]
+    );
+
+    // The oracle reads 1.01: spot is 99 bps below it.
+    mock_prices(&vm, 101_000_000);
+    let intent = deviation_intent(0, 100);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_SUCCESS_UINT
+    );
+    let intent = deviation_intent(1, 50);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+
+    let uninstall = install_data(permission_id(), signer());
+    assert!(policy.on_uninstall(uninstall).is_ok());
+    assert!(policy.oracle_calls_of(wallet(), permission_id()).is_empty());
+}
+
+#[test]
+fn oracle_deviation_needs_the_oracle_allowlisted() {
+    let (vm, mut policy) = setup();
+    install(&mut policy);
+    mock_prices(&vm, 100_000_000);
+    let intent = deviation_intent(0, 100);
+    let envelope = intent.envelope(&vm, signer());
+    assert_eq!(
+        policy.check_user_op_policy(permission_id(), intent.user_op(envelope)),
+        POLICY_FAILED_UINT
+    );
+}
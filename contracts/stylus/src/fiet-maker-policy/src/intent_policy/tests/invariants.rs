@@ -0,0 +1,177 @@
+//! Nonce and replay invariants under random interleavings of passing and failing checks.
+//!
+//! Several (wallet, permissionId) instances share one policy. After every call, each instance's
+//! stored nonce must equal a model that advances by exactly one on success and never moves on
+//! failure, and no envelope that already passed may pass again, in any instance.
+
+use alloc::{vec, vec::Vec};
+
+use proptest::prelude::*;
+use stylus_sdk::{
+    alloy_primitives::{Address, FixedBytes, U256},
+    testing::*,
+};
+
+use super::{install_data, setup, signer, Intent, UserOp, NOW};
+use crate::{
+    kernel::constants::{POLICY_FAILED_UINT, POLICY_SUCCESS_UINT},
+    utils::kernel::composite_key,
+};
+
+const INSTANCES: usize = 4;
+
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    /// Correctly signed envelope for the instance's next nonce.
+    Valid,
+    /// Nonce ahead of the stored one.
+    FutureNonce(u8),
+    /// Nonce behind the stored one (wraps to `U256::MAX` before the first success).
+    StaleNonce,
+    WrongSigner,
+    Expired,
+    CallDataMismatch,
+    /// Authenticated envelope whose program fails evaluation.
+    FailingProgram,
+    /// Resubmit an envelope that already passed (in any instance) from this instance.
+    Replay(usize),
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        4 => Just(Step::Valid),
+        1 => (1u8..=3).prop_map(Step::FutureNonce),
+        1 => Just(Step::StaleNonce),
+        1 => Just(Step::WrongSigner),
+        1 => Just(Step::Expired),
+        1 => Just(Step::CallDataMismatch),
+        1 => Just(Step::FailingProgram),
+        2 => any::<usize>().prop_map(Step::Replay),
+    ]
+}
+
+/// Two wallets, each with two permission ids.
+fn instances() -> [(Address, FixedBytes<32>); INSTANCES] {
+    let (a, b) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+    let (p, q) = (FixedBytes::repeat_byte(0x11), FixedBytes::repeat_byte(0x22));
+    [(a, p), (a, q), (b, p), (b, q)]
+}
+
+/// Build the user op for `step`, and whether the policy must accept it.
+fn submission(
+    vm: &TestVM,
+    index: usize,
+    (wallet, permission_id): (Address, FixedBytes<32>),
+    nonce: u64,
+    step: Step,
+    passed: &[UserOp],
+) -> (UserOp, bool) {
+    let mut intent = Intent::new(nonce);
+    // Unique call data per step keeps every digest (and its ecrecover mock) distinct.
+    intent.call_data = (index as u64).to_be_bytes().to_vec();
+    let mut recovered = signer();
+    let mut call_data = None;
+
+    match step {
+        Step::Valid => {}
+        Step::FutureNonce(ahead) => intent.nonce += U256::from(ahead),
+        Step::StaleNonce => intent.nonce = U256::from(nonce).wrapping_sub(U256::from(1)),
+        Step::WrongSigner => recovered = Address::repeat_byte(0x66),
+        Step::Expired => intent.deadline = NOW - 1,
+        Step::CallDataMismatch => call_data = Some(vec![0x00]),
+        Step::FailingProgram => {
+            intent.program = vec![0x01];
+            intent.program.extend_from_slice(&(NOW - 1).to_be_bytes());
+        }
+        Step::Replay(pick) if !passed.is_empty() => {
+            return (passed[pick % passed.len()].clone(), false);
+        }
+        // Nothing has passed yet: submit a valid envelope instead.
+        Step::Replay(_) => {}
+    }
+
+    let envelope = intent.envelope_for(vm, wallet, permission_id, recovered);
+    let mut user_op = intent.user_op(envelope);
+    user_op.0 = wallet;
+    if let Some(call_data) = call_data {
+        user_op.3 = call_data;
+    }
+    let accept = matches!(step, Step::Valid | Step::Replay(_));
+    (user_op, accept)
+}
+
+fn run(steps: &[(usize, Step)]) -> Result<(), TestCaseError> {
+    let (vm, mut policy) = setup();
+    let instances = instances();
+    for (wallet, permission_id) in instances {
+        vm.set_sender(wallet);
+        prop_assert!(policy
+            .on_install(install_data(permission_id, signer()))
+            .is_ok());
+    }
+
+    let mut expected = [0u64; INSTANCES];
+    let mut passed: Vec<UserOp> = Vec::new();
+
+    for (index, &(instance, step)) in steps.iter().enumerate() {
+        let (wallet, permission_id) = instances[instance];
+        vm.set_sender(wallet);
+        let (user_op, accept) = submission(
+            &vm,
+            index,
+            instances[instance],
+            expected[instance],
+            step,
+            &passed,
+        );
+
+        let result = policy.check_user_op_policy(permission_id, user_op.clone());
+        if accept {
+            prop_assert_eq!(result, POLICY_SUCCESS_UINT, "step {}: {:?}", index, step);
+            expected[instance] += 1;
+            passed.push(user_op);
+        } else {
+            prop_assert_eq!(result, POLICY_FAILED_UINT, "step {}: {:?}", index, step);
+        }
+
+        for (i, (wallet, permission_id)) in instances.into_iter().enumerate() {
+            prop_assert_eq!(
+                policy.nonce_of.get(composite_key(wallet, permission_id)),
+                U256::from(expected[i]),
+                "instance {} after step {}: {:?}",
+                i,
+                index,
+                step
+            );
+        }
+    }
+
+    // Every envelope that passed fails when resubmitted, in its own instance and every other.
+    for user_op in &passed {
+        for (wallet, permission_id) in instances {
+            vm.set_sender(wallet);
+            prop_assert_eq!(
+                policy.check_user_op_policy(permission_id, user_op.clone()),
+                POLICY_FAILED_UINT
+            );
+        }
+    }
+    for (i, (wallet, permission_id)) in instances.into_iter().enumerate() {
+        prop_assert_eq!(
+            policy.nonce_of.get(composite_key(wallet, permission_id)),
+            U256::from(expected[i])
+        );
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn nonce_and_replay_invariants(
+        steps in prop::collection::vec((0..INSTANCES, step()), 1..48)
+    ) {
+        run(&steps)?;
+    }
+}
@@ -2,7 +2,8 @@
 
 use stylus_sdk::alloy_primitives::U256;
 
-// ERC-7579 module type IDs (Kernel v3 uses Policy = 5).
+// ERC-7579 module type IDs (Kernel v3 uses Hook = 4, Policy = 5).
+pub const MODULE_TYPE_HOOK: U256 = U256::from_limbs([4, 0, 0, 0]);
 pub const MODULE_TYPE_POLICY: U256 = U256::from_limbs([5, 0, 0, 0]);
 
 // Policy return codes (Kernel treats non-zero validation data as failure).
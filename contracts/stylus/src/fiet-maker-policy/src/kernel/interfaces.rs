@@ -33,6 +33,14 @@ sol! {
             view
             returns (uint256);
     }
+
+    interface IHook is IModule {
+        function preCheck(address msgSender, uint256 msgValue, bytes msgData)
+            external
+            payable
+            returns (bytes hookData);
+        function postCheck(bytes hookData) external payable;
+    }
 }
 
 
@@ -1,10 +1,15 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 
+#[cfg(feature = "export-abi")]
+mod abi_json;
+
 #[cfg(not(any(test, feature = "export-abi")))]
 #[no_mangle]
 pub extern "C" fn main() {}
 
 /// ABI export entrypoint used by `cargo stylus export-abi`.
+///
+/// Prints the Solidity interface, or with `--json` a standard JSON ABI (see [`abi_json`]).
 #[cfg(feature = "export-abi")]
 fn main() {
     use stylus_sdk::abi::export::print_abi;
@@ -12,7 +17,15 @@ fn main() {
     // The ABI surface is derived from the `#[public]` impls on `IntentPolicy`.
     use fiet_maker_policy::IntentPolicy;
 
+    if std::env::args().skip(1).any(|arg| arg == "--json") {
+        match abi_json::json_abi::<IntentPolicy>() {
+            Ok(abi) => println!("{abi}"),
+            Err(err) => {
+                eprintln!("failed deriving the JSON ABI: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
     print_abi::<IntentPolicy>("BUSL-1.1", "pragma solidity ^0.8.23;");
 }
-
-
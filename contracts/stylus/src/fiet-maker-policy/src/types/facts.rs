@@ -1,2 +1,4 @@
-pub use fiet_maker_policy_types::{FactsProvider, Slot0};
-
+pub use fiet_maker_policy_types::{
+    deviation_bps, observe_args, solvency_ratio_bps, sqrt_price_x96_to_wad, to_wad,
+    twap_tick_from_observe, FactSource, FactsProvider, Slot0, UserOpFields, OBSERVE_SIG,
+};
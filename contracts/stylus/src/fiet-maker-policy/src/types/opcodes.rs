@@ -1,2 +1,4 @@
-pub use fiet_maker_policy_types::{Check, CompOp, Opcode};
-
+pub use fiet_maker_policy_types::{
+    call_target_count, routed_call_target_count, scale_bps, Check, CompOp, Opcode, UserOpField,
+    MAX_CALL_TARGETS, MAX_CHECKS, MAX_PROGRAM_LEN, MAX_QUEUE_SUM_OWNERS,
+};
@@ -5,38 +5,85 @@
 
 use alloc::vec::Vec;
 
+use fiet_maker_policy_types::WebAuthnAssertion;
 use stylus_sdk::{
-    alloy_primitives::{Address, FixedBytes},
-    call::RawCall,
+    alloy_primitives::{address, Address, FixedBytes},
+    stylus_core::{calls::context::Call, Host},
 };
 
+/// RIP-7212 `P256VERIFY` precompile (live on Arbitrum since ArbOS 31).
+pub const P256_VERIFY_PRECOMPILE: Address = address!("0000000000000000000000000000000000000100");
+
+/// Daimo's `P256Verifier`, deployed at the same CREATE2 address on most chains, with the same
+/// calldata and return format as the precompile. Used when the precompile is absent.
+pub const P256_VERIFIER_FALLBACK: Address = address!("c2b78104907F722DABAc4C69f826a522B2754De4");
+
+/// Gas for the Solidity fallback verifier (~330k per verification).
+const P256_FALLBACK_GAS: u64 = 400_000;
+
 /// Recover an EOA address from a 32-byte digest and an ECDSA signature.
 ///
 /// Notes:
-/// - We use the EVM `ecrecover` precompile at address `0x01`.
+/// - We use the EVM `ecrecover` precompile at address `0x01`, called through `vm` so tests can
+///   mock it.
 /// - We accept signatures with v in {0,1,27,28}. If v is not recognised, we try both 27 and 28.
-pub fn ecrecover_address(digest: FixedBytes<32>, sig: &[u8; 65]) -> Result<Address, ()> {
-    // Precompile address 0x01.
-    let mut precompile = [0u8; 20];
-    precompile[19] = 1;
-    let to = Address::from_slice(&precompile);
+pub fn ecrecover_address(
+    vm: &dyn Host,
+    digest: FixedBytes<32>,
+    sig: &[u8; 65],
+) -> Result<Address, ()> {
+    recover_with(vm, digest, sig, |_| true)
+}
 
-    let r = &sig[0..32];
-    let s = &sig[32..64];
-    let v_raw = sig[64];
+/// A k-of-n signer set: at least `threshold` distinct members must sign.
+pub struct SignerSet<'a> {
+    pub signers: &'a [Address],
+    pub threshold: usize,
+}
 
-    let mut candidates: Vec<u8> = Vec::new();
-    match v_raw {
-        27 | 28 => candidates.extend_from_slice(&[v_raw]),
-        0 | 1 => candidates.extend_from_slice(&[v_raw + 27]),
-        _ => {}
+/// Verify that `signatures` over `digest` come from at least `set.threshold` distinct members of
+/// `set`.
+///
+/// Notes:
+/// - Every signature must recover to a member not already counted. A stray or duplicate
+///   signature fails the whole list rather than being skipped, so callers cannot pad it.
+/// - v is handled as in [`ecrecover_address`], except that when both 27 and 28 are tried the
+///   first one recovering to an uncounted member is taken.
+/// - A zero threshold, or one larger than the set, never verifies.
+pub fn verify_threshold(
+    vm: &dyn Host,
+    digest: FixedBytes<32>,
+    signatures: &[[u8; 65]],
+    set: &SignerSet,
+) -> bool {
+    if set.threshold == 0 || set.threshold > set.signers.len() || signatures.len() < set.threshold {
+        return false;
     }
-    // If v isn't provided/usable, try both.
-    if candidates.is_empty() {
-        candidates.extend_from_slice(&[27u8, 28u8]);
+    let mut counted: Vec<Address> = Vec::with_capacity(signatures.len());
+    for sig in signatures {
+        let accept = |a: Address| set.signers.contains(&a) && !counted.contains(&a);
+        match recover_with(vm, digest, sig, accept) {
+            Ok(signer) => counted.push(signer),
+            Err(_) => return false,
+        }
     }
+    true
+}
+
+/// `ecrecover` `sig` under each candidate v and return the first non-zero address `accept` takes.
+fn recover_with(
+    vm: &dyn Host,
+    digest: FixedBytes<32>,
+    sig: &[u8; 65],
+    accept: impl Fn(Address) -> bool,
+) -> Result<Address, ()> {
+    // Precompile address 0x01.
+    let to = Address::with_last_byte(1);
+
+    let r = &sig[0..32];
+    let s = &sig[32..64];
 
-    for v in candidates {
+    for &v in recovery_candidates(sig[64]) {
         let mut input = [0u8; 128];
         input[0..32].copy_from_slice(digest.as_slice());
         // v as 32-byte big-endian word.
@@ -44,13 +91,15 @@ pub fn ecrecover_address(digest: FixedBytes<32>, sig: &[u8; 65]) -> Result<Addre
         input[64..96].copy_from_slice(r);
         input[96..128].copy_from_slice(s);
 
-        let out = unsafe { RawCall::new_static().gas(50_000).call(to, &input) }.map_err(|_| ())?;
+        let out = vm
+            .static_call(&Call::new().gas(50_000), to, &input)
+            .map_err(|_| ())?;
         if out.len() < 32 {
             continue;
         }
         // precompile returns 32-byte word with address in the low 20 bytes.
         let recovered = Address::from_slice(&out[12..32]);
-        if recovered != Address::ZERO {
+        if recovered != Address::ZERO && accept(recovered) {
             return Ok(recovered);
         }
     }
@@ -58,3 +107,87 @@ pub fn ecrecover_address(digest: FixedBytes<32>, sig: &[u8; 65]) -> Result<Addre
     Err(())
 }
 
+/// Normalised v values to try: {0,1,27,28} map to one; if v isn't provided/usable, try both.
+fn recovery_candidates(v_raw: u8) -> &'static [u8] {
+    match v_raw {
+        0 | 27 => &[27],
+        1 | 28 => &[28],
+        _ => &[27, 28],
+    }
+}
+
+/// Verify a P-256 signature `(r, s)` over `hash` for the public key `(x, y)`.
+///
+/// Notes:
+/// - Tries the RIP-7212 precompile first. It returns a 32-byte `1` on success and nothing
+///   otherwise, which is indistinguishable from the precompile not existing, so empty output falls
+///   back to [`P256_VERIFIER_FALLBACK`]. An invalid signature therefore pays for both calls.
+/// - If neither is available (empty output, or no code at the fallback address) the signature is
+///   treated as invalid: verification fails closed.
+pub fn p256_verify(
+    vm: &dyn Host,
+    hash: FixedBytes<32>,
+    r: FixedBytes<32>,
+    s: FixedBytes<32>,
+    x: FixedBytes<32>,
+    y: FixedBytes<32>,
+) -> bool {
+    let mut input = [0u8; 160];
+    input[0..32].copy_from_slice(hash.as_slice());
+    input[32..64].copy_from_slice(r.as_slice());
+    input[64..96].copy_from_slice(s.as_slice());
+    input[96..128].copy_from_slice(x.as_slice());
+    input[128..160].copy_from_slice(y.as_slice());
+
+    let verified = |to: Address, gas: u64| match vm.static_call(&Call::new().gas(gas), to, &input) {
+        Ok(out) if !out.is_empty() => Some(is_one_word(&out)),
+        _ => None,
+    };
+    verified(P256_VERIFY_PRECOMPILE, 50_000)
+        .or_else(|| verified(P256_VERIFIER_FALLBACK, P256_FALLBACK_GAS))
+        .unwrap_or(false)
+}
+
+/// Verify a WebAuthn assertion whose challenge is `digest`, signed by the passkey `(x, y)`.
+///
+/// Notes:
+/// - The authenticator must report user presence; `clientDataJSON` must be a `webauthn.get`
+///   response carrying `digest` as its challenge (see [`WebAuthnAssertion::is_for`]).
+/// - The signed message is `sha256(authenticatorData || sha256(clientDataJSON))`, hashed with the
+///   SHA-256 precompile at `0x02`.
+pub fn verify_webauthn(
+    vm: &dyn Host,
+    digest: FixedBytes<32>,
+    assertion: &WebAuthnAssertion,
+    x: FixedBytes<32>,
+    y: FixedBytes<32>,
+) -> bool {
+    if !assertion.is_for(&digest) {
+        return false;
+    }
+    let Some(client_data_hash) = sha256(vm, &assertion.client_data_json) else {
+        return false;
+    };
+    let mut message = Vec::with_capacity(assertion.authenticator_data.len() + 32);
+    message.extend_from_slice(&assertion.authenticator_data);
+    message.extend_from_slice(client_data_hash.as_slice());
+    let Some(message_hash) = sha256(vm, &message) else {
+        return false;
+    };
+    p256_verify(vm, message_hash, assertion.r, assertion.s, x, y)
+}
+
+/// SHA-256 via the precompile at `0x02`.
+pub fn sha256(vm: &dyn Host, data: &[u8]) -> Option<FixedBytes<32>> {
+    let out = vm
+        .static_call(&Call::new().gas(50_000), Address::with_last_byte(2), data)
+        .ok()?;
+    (out.len() == 32).then(|| FixedBytes::from_slice(&out))
+}
+
+fn is_one_word(out: &[u8]) -> bool {
+    out.len() == 32 && out[..31].iter().all(|b| *b == 0) && out[31] == 1
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,97 @@
+//! Signature verification against a mocked `ecrecover` precompile.
+
+use alloc::vec;
+
+use stylus_sdk::{
+    alloy_primitives::{Address, FixedBytes},
+    testing::*,
+};
+
+use super::{ecrecover_address, verify_threshold, SignerSet};
+
+fn digest() -> FixedBytes<32> {
+    FixedBytes::repeat_byte(0xd1)
+}
+
+fn member(i: u8) -> Address {
+    Address::repeat_byte(0x50 + i)
+}
+
+/// A signature whose `r` identifies it, and register what it recovers to under `v`.
+fn signature(vm: &TestVM, id: u8, v: u8, recovered: Address) -> [u8; 65] {
+    let mut sig = [id; 65];
+    sig[64] = v;
+    let mut input = [0u8; 128];
+    input[0..32].copy_from_slice(digest().as_slice());
+    input[63] = v;
+    input[64..128].copy_from_slice(&sig[0..64]);
+    let mut out = vec![0u8; 32];
+    out[12..32].copy_from_slice(recovered.as_slice());
+    vm.mock_static_call(Address::with_last_byte(1), input.to_vec(), Ok(out));
+    sig
+}
+
+#[test]
+fn ecrecover_normalises_v() {
+    let vm = TestVM::new();
+    let mut sig = signature(&vm, 0x01, 28, member(1));
+    assert_eq!(ecrecover_address(&vm, digest(), &sig), Ok(member(1)));
+    sig[64] = 1;
+    assert_eq!(ecrecover_address(&vm, digest(), &sig), Ok(member(1)));
+    // Unrecognised v: 27 recovers nothing, so 28 is tried.
+    sig[64] = 0x5a;
+    assert_eq!(ecrecover_address(&vm, digest(), &sig), Ok(member(1)));
+    sig[64] = 0;
+    assert_eq!(ecrecover_address(&vm, digest(), &sig), Err(()));
+}
+
+#[test]
+fn threshold_counts_distinct_members() {
+    let vm = TestVM::new();
+    let signers = [member(1), member(2), member(3)];
+    let set = SignerSet {
+        signers: &signers,
+        threshold: 2,
+    };
+    let a = signature(&vm, 0x01, 27, member(1));
+    let b = signature(&vm, 0x02, 28, member(3));
+    let a_again = signature(&vm, 0x03, 27, member(1));
+    let outsider = signature(&vm, 0x04, 27, Address::repeat_byte(0x66));
+
+    assert!(verify_threshold(&vm, digest(), &[a, b], &set));
+    assert!(verify_threshold(&vm, digest(), &[b, a], &set));
+    assert!(!verify_threshold(&vm, digest(), &[a], &set));
+    assert!(!verify_threshold(&vm, digest(), &[a, a_again], &set));
+    // A stray signature fails the list even when the threshold is otherwise met.
+    assert!(!verify_threshold(&vm, digest(), &[a, b, outsider], &set));
+}
+
+#[test]
+fn threshold_tries_both_v_for_a_member() {
+    let vm = TestVM::new();
+    // With an unrecognised v, 27 recovers to a non-member and 28 to the member.
+    let mut sig = signature(&vm, 0x01, 27, Address::repeat_byte(0x66));
+    signature(&vm, 0x01, 28, member(1));
+    sig[64] = 0x5a;
+
+    let signers = [member(1)];
+    let set = SignerSet {
+        signers: &signers,
+        threshold: 1,
+    };
+    assert!(verify_threshold(&vm, digest(), &[sig], &set));
+}
+
+#[test]
+fn threshold_rejects_misconfigured_sets() {
+    let vm = TestVM::new();
+    let sig = signature(&vm, 0x01, 27, member(1));
+    let signers = [member(1)];
+    for threshold in [0, 2] {
+        let set = SignerSet {
+            signers: &signers,
+            threshold,
+        };
+        assert!(!verify_threshold(&vm, digest(), &[sig], &set));
+    }
+}
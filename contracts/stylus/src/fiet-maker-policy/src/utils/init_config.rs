@@ -0,0 +1,119 @@
+//! `initData` layouts accepted by `onInstall`.
+//!
+//! The native layout is packed (`uint8 version || key || sources || extensions`). Wallet SDKs
+//! that can only produce standard module init data send `abi.encode(InitConfigV2)` instead; it
+//! is recognised by its leading zero byte (the head offset of the dynamic struct, where packed
+//! data starts with its non-zero version) and translated into the packed layout, so both go
+//! through the same validation.
+
+use alloc::vec::Vec;
+
+use stylus_sdk::{
+    alloy_primitives::{Address, FixedBytes},
+    alloy_sol_types::{sol, SolType},
+};
+
+/// `initData` extension tag: EIP-712 domain override.
+pub const INIT_EXT_DOMAIN: u8 = 0x01;
+/// `initData` extension tag: fact-source codehash pins.
+pub const INIT_EXT_CODEHASHES: u8 = 0x02;
+/// `initData` extension tag: pool allowlist.
+pub const INIT_EXT_POOLS: u8 = 0x03;
+/// `initData` extension tag: oracle-call allowlist.
+pub const INIT_EXT_ORACLES: u8 = 0x04;
+/// `initData` extension tag: per-opcode fact-source overrides.
+pub const INIT_EXT_SOURCE_OVERRIDES: u8 = 0x05;
+/// `initData` extension tag: EIP-712 domain override as `(nameHash, versionHash)`, as
+/// `exportConfig` writes it.
+pub const INIT_EXT_DOMAIN_HASHES: u8 = 0x06;
+/// `initData` extension tag: freeze the permission's config at install (no payload).
+pub const INIT_EXT_FREEZE: u8 = 0x07;
+/// `initData` extension tag: k-of-n signer set for threshold envelopes.
+pub const INIT_EXT_SIGNER_SET: u8 = 0x08;
+
+sol! {
+    /// An oracle call allowlisted for `CheckOracleDeviationLte`.
+    struct OracleCall {
+        address target;
+        bytes4 selector;
+    }
+
+    /// ABI-encoded install config, the struct form of the packed `initData`.
+    ///
+    /// Set `signer` for a secp256k1 envelope signer, or `passkeyX`/`passkeyY` (leaving `signer`
+    /// zero) for a passkey. Empty or zero optional fields are left out: an empty domain keeps the
+    /// default, zero codehashes leave sources unpinned, and empty lists install no allowlist.
+    struct InitConfigV2 {
+        address signer;
+        bytes32 passkeyX;
+        bytes32 passkeyY;
+        address stateView;
+        address vtsOrchestrator;
+        address liquidityHub;
+        string domainName;
+        string domainVersion;
+        bytes32[3] factSourceCodehashes;
+        bytes32[] poolIds;
+        OracleCall[] oracleCalls;
+    }
+}
+
+/// Whether `init_data` is `abi.encode(InitConfigV2)` rather than the packed layout.
+pub fn is_abi_init_data(init_data: &[u8]) -> bool {
+    init_data.first() == Some(&0)
+}
+
+/// Translate `abi.encode(InitConfigV2)` into the packed layout.
+///
+/// Returns `None` when the data does not decode, both a signer and a passkey are set, or a
+/// domain string or list does not fit its packed `uint8` length.
+pub fn packed_init_data(init_data: &[u8]) -> Option<Vec<u8>> {
+    let config = <InitConfigV2 as SolType>::abi_decode(init_data, true).ok()?;
+    let has_passkey = config.passkeyX != FixedBytes::ZERO || config.passkeyY != FixedBytes::ZERO;
+
+    let mut out = Vec::with_capacity(1 + 64 + 60);
+    if has_passkey {
+        if config.signer != Address::ZERO {
+            return None;
+        }
+        out.push(2);
+        out.extend_from_slice(config.passkeyX.as_slice());
+        out.extend_from_slice(config.passkeyY.as_slice());
+    } else {
+        out.push(1);
+        out.extend_from_slice(config.signer.as_slice());
+    }
+    out.extend_from_slice(config.stateView.as_slice());
+    out.extend_from_slice(config.vtsOrchestrator.as_slice());
+    out.extend_from_slice(config.liquidityHub.as_slice());
+
+    if !config.domainName.is_empty() || !config.domainVersion.is_empty() {
+        out.push(INIT_EXT_DOMAIN);
+        for part in [config.domainName.as_bytes(), config.domainVersion.as_bytes()] {
+            out.push(u8::try_from(part.len()).ok()?);
+            out.extend_from_slice(part);
+        }
+    }
+    if config.factSourceCodehashes.iter().any(|codehash| *codehash != FixedBytes::ZERO) {
+        out.push(INIT_EXT_CODEHASHES);
+        for codehash in config.factSourceCodehashes {
+            out.extend_from_slice(codehash.as_slice());
+        }
+    }
+    if !config.poolIds.is_empty() {
+        out.push(INIT_EXT_POOLS);
+        out.push(u8::try_from(config.poolIds.len()).ok()?);
+        for pool_id in &config.poolIds {
+            out.extend_from_slice(pool_id.as_slice());
+        }
+    }
+    if !config.oracleCalls.is_empty() {
+        out.push(INIT_EXT_ORACLES);
+        out.push(u8::try_from(config.oracleCalls.len()).ok()?);
+        for call in &config.oracleCalls {
+            out.extend_from_slice(call.target.as_slice());
+            out.extend_from_slice(call.selector.as_slice());
+        }
+    }
+    Some(out)
+}
@@ -2,7 +2,17 @@
 
 use alloc::vec::Vec;
 
-use stylus_sdk::alloy_primitives::{keccak256, Address, FixedBytes};
+use fiet_maker_policy_types::ByteReader;
+use stylus_sdk::{
+    alloy_primitives::{keccak256, Address, FixedBytes, U256},
+    alloy_sol_types::{sol_data, SolType},
+};
+
+/// Install `permissionId` that marks the rest of the data as a batch of permissions.
+///
+/// Kernel permission ids are a `bytes4` left-aligned in the `bytes32`, so they never collide
+/// with it.
+pub const BATCH_INSTALL_ID: FixedBytes<32> = FixedBytes([0xff; 32]);
 
 /// Composite storage key = keccak256(wallet || permissionId).
 ///
@@ -14,6 +24,14 @@ pub fn composite_key(wallet: Address, permission_id: FixedBytes<32>) -> FixedByt
     keccak256(buf)
 }
 
+/// Storage key of the `index`th installed permission id of `wallet` = keccak256(wallet || index).
+pub fn permission_id_slot(wallet: Address, index: U256) -> FixedBytes<32> {
+    let mut buf = [0u8; 20 + 32];
+    buf[0..20].copy_from_slice(wallet.as_slice());
+    buf[20..52].copy_from_slice(&index.to_be_bytes::<32>());
+    keccak256(buf)
+}
+
 /// Split Kernel policy install bytes into `(permissionId, initData)`.
 ///
 /// Kernel `PolicyBase` uses `bytes data = bytes32 id || _data`.
@@ -26,3 +44,105 @@ pub fn split_policy_install_data(data: &[u8]) -> Result<(FixedBytes<32>, &[u8]),
     Ok((FixedBytes(id_buf), &data[32..]))
 }
 
+
+/// Split a batch install payload (the bytes after `BATCH_INSTALL_ID`) into its
+/// `(permissionId, initData)` entries.
+///
+/// Layout: `uint8 count || (bytes32 permissionId || uint16 initDataLen || initData)[count]`.
+/// Returns `None` for an empty batch, a nested batch id, truncated entries or trailing bytes.
+pub fn split_batch_install_data(data: &[u8]) -> Option<Vec<(FixedBytes<32>, &[u8])>> {
+    let mut r = ByteReader::new(data);
+    let count = r.u8().ok().filter(|count| *count > 0)?;
+    let mut entries = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let permission_id = r.b32().ok().filter(|id| *id != BATCH_INSTALL_ID)?;
+        let len = r.u16().ok()? as usize;
+        entries.push((permission_id, r.take(len).ok()?));
+    }
+    r.is_empty().then_some(entries)
+}
+
+/// Head word of a multi-type bootstrap payload: the offset of its first array.
+const BOOTSTRAP_MULTI_TYPE_HEAD: U256 = U256::from_limbs([0x40, 0, 0, 0]);
+
+/// Whether install `data` is an ERC-7579 bootstrap payload rather than `permissionId || ...`.
+///
+/// Bootstrap and launchpad contracts that install modules during account creation wrap each
+/// module's data with its module type, as either `abi.encode(uint256 moduleTypeId, bytes data)`
+/// or the multi-type `abi.encode(uint256[] moduleTypeIds, bytes[] datas)`. Their head word is a
+/// small integer (`1`-`255`, or the `0x40` array offset), which no Kernel permission id can be:
+/// those are a `bytes4` left-aligned in the `bytes32`, so only their first four bytes are set.
+pub fn is_bootstrap_install_data(data: &[u8]) -> bool {
+    data.len() >= 64 && data[..31].iter().all(|b| *b == 0) && data[31] != 0
+}
+
+/// Unwrap an ERC-7579 bootstrap payload (see `is_bootstrap_install_data`) into its
+/// `(moduleTypeId, data)` entries, each `data` being what a plain `installModule` of that type
+/// would pass to `onInstall`.
+///
+/// Returns `None` when the payload does not decode, is an empty multi-type list, or the type
+/// and data lists differ in length.
+pub fn split_bootstrap_install_data(data: &[u8]) -> Option<Vec<(U256, Vec<u8>)>> {
+    let head = U256::from_be_slice(&data[..32]);
+    if head == BOOTSTRAP_MULTI_TYPE_HEAD {
+        type MultiType = (sol_data::Array<sol_data::Uint<256>>, sol_data::Array<sol_data::Bytes>);
+        let (types, datas) = <MultiType as SolType>::abi_decode_params(data, true).ok()?;
+        if types.is_empty() || types.len() != datas.len() {
+            return None;
+        }
+        return Some(types.into_iter().zip(datas.into_iter().map(|data| data.to_vec())).collect());
+    }
+    type SingleType = (sol_data::Uint<256>, sol_data::Bytes);
+    let (module_type, data) = <SingleType as SolType>::abi_decode_params(data, true).ok()?;
+    Some(Vec::from([(module_type, data.to_vec())]))
+}
+
+/// Kernel's `executeUserOp(PackedUserOperation,bytes32)` selector.
+pub const EXECUTE_USER_OP_SELECTOR: [u8; 4] = [0x8d, 0xd7, 0x71, 0x2f];
+
+/// The calldata Kernel executes for a UserOp whose `callData` is `executeUserOp || execution`,
+/// or `None` for any other `callData`.
+///
+/// Kernel runs its hook only for such UserOps, and hands `preCheck` exactly `execution`
+/// (`userOp.callData[4:]`). A plain `execute` UserOp skips the hook.
+pub fn hooked_execution(call_data: &[u8]) -> Option<&[u8]> {
+    call_data.strip_prefix(&EXECUTE_USER_OP_SELECTOR)
+}
+
+/// ERC-7579 `execute(bytes32 mode, bytes executionCalldata)` selector.
+pub const EXECUTE_SELECTOR: [u8; 4] = [0xe9, 0xae, 0x5c, 0x53];
+
+/// ERC-7579 call types (the mode's first byte) the policy can decode.
+const CALL_TYPE_SINGLE: u8 = 0x00;
+const CALL_TYPE_BATCH: u8 = 0x01;
+
+/// The `(target, callData)` calls of a Kernel `execute` calldata, optionally behind
+/// `executeUserOp` (see `hooked_execution`).
+///
+/// A single call is `target(20) || value(32) || callData` and a batch is
+/// `abi.encode(Execution[] (address target, uint256 value, bytes callData))`. Returns `None` for
+/// any other calldata, including delegatecall and unknown call types, whose effects cannot be
+/// read off the calldata.
+pub fn execute_calls(call_data: &[u8]) -> Option<Vec<(Address, Vec<u8>)>> {
+    let call_data = hooked_execution(call_data).unwrap_or(call_data);
+    let args = call_data.strip_prefix(&EXECUTE_SELECTOR)?;
+    type Execute = (sol_data::FixedBytes<32>, sol_data::Bytes);
+    let (mode, execution) = <Execute as SolType>::abi_decode_params(args, true).ok()?;
+    match mode[0] {
+        CALL_TYPE_SINGLE => {
+            let target = Address::from_slice(execution.get(..20)?);
+            let call = execution.get(52..)?;
+            Some(Vec::from([(target, call.to_vec())]))
+        }
+        CALL_TYPE_BATCH => {
+            type Executions =
+                (sol_data::Array<(sol_data::Address, sol_data::Uint<256>, sol_data::Bytes)>,);
+            let (calls,) = <Executions as SolType>::abi_decode_params(&execution, true).ok()?;
+            if calls.is_empty() {
+                return None;
+            }
+            Some(calls.into_iter().map(|(target, _, call)| (target, call.to_vec())).collect())
+        }
+        _ => None,
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! These helpers are intentionally small and deterministic, as they run inside Stylus / WASM.
 
-pub mod bytes;
 pub mod crypto;
+pub mod init_config;
 pub mod kernel;
 pub mod policy_envelope;
+pub mod pool_allowlist;
+pub mod token_delta;
 
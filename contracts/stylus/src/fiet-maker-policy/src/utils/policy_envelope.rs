@@ -7,16 +7,33 @@ use alloc::vec::Vec;
 
 use stylus_sdk::alloy_primitives::{keccak256, Address, FixedBytes, U256};
 
-use crate::utils::bytes::{read_b32, read_u16_be, read_u32_be, read_u64_be, read_u256_be, read_vec};
+use fiet_maker_policy_types::{
+    eip712_checks_hash, ByteReader, Check, WebAuthnAssertion, EIP712_CHECK_TYPE,
+};
 
-/// Parsed policy envelope (v1).
+/// Envelope signature, by scheme.
+pub enum EnvelopeSignature {
+    /// secp256k1 ECDSA (r||s||v) over the digest, for an installed `signer`.
+    Ecdsa([u8; 65]),
+    /// P-256 WebAuthn assertion whose challenge is the digest, for an installed passkey.
+    WebAuthn(WebAuthnAssertion),
+    /// secp256k1 ECDSA signatures over the digest from members of an installed signer set,
+    /// encoded as `THRESHOLD_SIGNATURE_TAG || (r||s||v)[n]`.
+    Threshold(Vec<[u8; 65]>),
+}
+
+/// First byte of a threshold signature. An encoded WebAuthn assertion starting with it would
+/// declare at least 65280 bytes of authenticator data, more than the envelope can carry.
+pub const THRESHOLD_SIGNATURE_TAG: u8 = 0xff;
+
+/// Parsed policy envelope.
 pub struct ParsedPolicyIntent {
     pub version: u16,
     pub nonce: U256,
     pub deadline: u64,
     pub call_bundle_hash: FixedBytes<32>,
     pub program_bytes: Vec<u8>,
-    pub signature: [u8; 65],
+    pub signature: EnvelopeSignature,
 }
 
 /// Parse the policy-specific `userOp.signature` slice into an intent envelope.
@@ -28,31 +45,42 @@ pub struct ParsedPolicyIntent {
 /// - bytes32 call_bundle_hash
 /// - u32 program_len
 /// - bytes program_bytes
-/// - u16 sig_len (must be 65)
-/// - bytes signature (r||s||v)
+/// - u16 sig_len
+/// - bytes signature: 65 bytes of ECDSA (r||s||v), [`THRESHOLD_SIGNATURE_TAG`] followed by one
+///   or more of those for a signer-set install, or any other length holding an encoded
+///   [`WebAuthnAssertion`]
 pub fn parse_policy_envelope(sig: &[u8]) -> Result<ParsedPolicyIntent, ()> {
-    let mut i = 0usize;
     if sig.len() < 2 + 32 + 8 + 32 + 4 + 2 {
         return Err(());
     }
+    let mut r = ByteReader::new(sig);
 
-    let version = read_u16_be(sig, &mut i)?;
-    let nonce = read_u256_be(sig, &mut i)?;
-    let deadline = read_u64_be(sig, &mut i)?;
-    let call_bundle_hash = read_b32(sig, &mut i)?;
-    let program_len = read_u32_be(sig, &mut i)? as usize;
-    let program_bytes = read_vec(sig, &mut i, program_len)?;
-    let sig_len = read_u16_be(sig, &mut i)? as usize;
-    if sig_len != 65 {
-        return Err(());
-    }
-    let sig_bytes = read_vec(sig, &mut i, sig_len)?;
-    if i != sig.len() {
+    let version = r.u16().map_err(|_| ())?;
+    let nonce = r.u256().map_err(|_| ())?;
+    let deadline = r.u64().map_err(|_| ())?;
+    let call_bundle_hash = r.b32().map_err(|_| ())?;
+    let program_len = r.u32().map_err(|_| ())? as usize;
+    let program_bytes = r.take(program_len).map_err(|_| ())?.to_vec();
+    let sig_len = r.u16().map_err(|_| ())? as usize;
+    let raw = r.take(sig_len).map_err(|_| ())?;
+    let signature = if let Ok(signature) = <[u8; 65]>::try_from(raw) {
+        EnvelopeSignature::Ecdsa(signature)
+    } else if let [THRESHOLD_SIGNATURE_TAG, signatures @ ..] = raw {
+        if signatures.is_empty() || signatures.len() % 65 != 0 {
+            return Err(());
+        }
+        let mut parsed = Vec::with_capacity(signatures.len() / 65);
+        for sig in signatures.chunks_exact(65) {
+            parsed.push(sig.try_into().map_err(|_| ())?);
+        }
+        EnvelopeSignature::Threshold(parsed)
+    } else {
+        EnvelopeSignature::WebAuthn(WebAuthnAssertion::decode(raw).ok_or(())?)
+    };
+    if !r.is_empty() {
         // reject trailing bytes for determinism
         return Err(());
     }
-    let mut signature = [0u8; 65];
-    signature.copy_from_slice(&sig_bytes);
 
     Ok(ParsedPolicyIntent {
         version,
@@ -64,7 +92,53 @@ pub fn parse_policy_envelope(sig: &[u8]) -> Result<ParsedPolicyIntent, ()> {
     })
 }
 
-/// Compute the EIP-712 digest that must be signed by the configured policy signer.
+/// EIP-712 domain name used when an install does not set one.
+pub const DEFAULT_DOMAIN_NAME: &[u8] = b"Fiet Maker Intent Policy";
+/// EIP-712 domain version used when an install does not set one.
+pub const DEFAULT_DOMAIN_VERSION: &[u8] = b"1";
+
+/// EIP-712 domain the envelope is signed under. Name and version are kept as hashes, which is all
+/// the separator needs, so they can be stored per install.
+pub struct IntentDomain {
+    pub name_hash: FixedBytes<32>,
+    pub version_hash: FixedBytes<32>,
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl IntentDomain {
+    /// The default name and version on `chain_id` / `verifying_contract`.
+    pub fn new(chain_id: u64, verifying_contract: Address) -> Self {
+        Self {
+            name_hash: keccak256(DEFAULT_DOMAIN_NAME),
+            version_hash: keccak256(DEFAULT_DOMAIN_VERSION),
+            chain_id,
+            verifying_contract,
+        }
+    }
+
+    /// EIP-712 domain separator.
+    pub fn separator(&self) -> FixedBytes<32> {
+        // Domain type hash: keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)")
+        let domain_type_hash = keccak256(
+            b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+        );
+
+        // Domain separator encoding
+        let mut domain_buf = Vec::with_capacity(32 * 5);
+        domain_buf.extend_from_slice(domain_type_hash.as_slice());
+        domain_buf.extend_from_slice(self.name_hash.as_slice());
+        domain_buf.extend_from_slice(self.version_hash.as_slice());
+        domain_buf.extend_from_slice(&U256::from(self.chain_id).to_be_bytes::<32>());
+        let mut vc_padded = [0u8; 32];
+        vc_padded[12..32].copy_from_slice(self.verifying_contract.as_slice());
+        domain_buf.extend_from_slice(&vc_padded);
+        keccak256(domain_buf)
+    }
+}
+
+/// Compute the EIP-712 digest that must be signed by the configured policy signer, under the
+/// default domain name and version.
 ///
 /// Purpose: authenticate the policy envelope payload (nonce/deadline/bundle binding/program hash)
 /// so it cannot be replaced inside the permission pipeline.
@@ -77,34 +151,97 @@ pub fn policy_intent_digest(
     deadline: u64,
     call_bundle_hash: FixedBytes<32>,
     program_bytes: &[u8],
+) -> FixedBytes<32> {
+    policy_intent_digest_in(
+        &IntentDomain::new(chain_id, verifying_contract),
+        wallet,
+        permission_id,
+        nonce,
+        deadline,
+        call_bundle_hash,
+        program_bytes,
+    )
+}
+
+/// [`policy_intent_digest`] under an explicit `domain`.
+pub fn policy_intent_digest_in(
+    domain: &IntentDomain,
+    wallet: Address,
+    permission_id: FixedBytes<32>,
+    nonce: U256,
+    deadline: u64,
+    call_bundle_hash: FixedBytes<32>,
+    program_bytes: &[u8],
 ) -> FixedBytes<32> {
     // Hash the program bytes so the typed message stays fixed-size and unambiguous.
     let program_hash: FixedBytes<32> = keccak256(program_bytes);
 
-    // Domain type hash: keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)")
-    let domain_type_hash = keccak256(
-        b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
-    );
-    let domain_name_hash = keccak256(b"Fiet Maker Intent Policy");
-    let domain_version_hash = keccak256(b"1");
-
-    // Domain separator encoding
-    let mut domain_buf = Vec::with_capacity(32 * 5);
-    domain_buf.extend_from_slice(domain_type_hash.as_slice());
-    domain_buf.extend_from_slice(domain_name_hash.as_slice());
-    domain_buf.extend_from_slice(domain_version_hash.as_slice());
-    domain_buf.extend_from_slice(&U256::from(chain_id).to_be_bytes::<32>());
-    let mut vc_padded = [0u8; 32];
-    vc_padded[12..32].copy_from_slice(verifying_contract.as_slice());
-    domain_buf.extend_from_slice(&vc_padded);
-    let domain_separator = keccak256(domain_buf);
-
     // Message type hash:
     // keccak256("IntentPolicyEnvelope(address wallet,bytes32 permissionId,uint256 nonce,uint64 deadline,bytes32 callBundleHash,bytes32 programHash)")
     let msg_type_hash = keccak256(
         b"IntentPolicyEnvelope(address wallet,bytes32 permissionId,uint256 nonce,uint64 deadline,bytes32 callBundleHash,bytes32 programHash)",
     );
 
+    envelope_digest(
+        domain,
+        msg_type_hash,
+        wallet,
+        permission_id,
+        nonce,
+        deadline,
+        call_bundle_hash,
+        program_hash,
+    )
+}
+
+/// Envelope version whose digest signs the program hash.
+pub const ENVELOPE_VERSION_PROGRAM_HASH: u16 = 1;
+/// Envelope version whose digest signs the decoded checks as a typed `Check[]`.
+pub const ENVELOPE_VERSION_TYPED_CHECKS: u16 = 2;
+
+/// Message type of a typed-checks envelope, without the referenced `Check` type.
+const TYPED_ENVELOPE_TYPE: &[u8] = b"IntentPolicyEnvelope(address wallet,bytes32 permissionId,uint256 nonce,uint64 deadline,bytes32 callBundleHash,Check[] checks)";
+
+/// Digest of a typed-checks (version 2) envelope under `domain`.
+///
+/// Same message as [`policy_intent_digest_in`] with `Check[] checks` in place of
+/// `bytes32 programHash`, so a wallet rendering the typed data shows every check field by field.
+/// `checks` are the envelope's program decoded, in program order.
+pub fn policy_typed_intent_digest_in(
+    domain: &IntentDomain,
+    wallet: Address,
+    permission_id: FixedBytes<32>,
+    nonce: U256,
+    deadline: u64,
+    call_bundle_hash: FixedBytes<32>,
+    checks: &[Check],
+) -> FixedBytes<32> {
+    let msg_type_hash = keccak256([TYPED_ENVELOPE_TYPE, EIP712_CHECK_TYPE.as_bytes()].concat());
+    envelope_digest(
+        domain,
+        msg_type_hash,
+        wallet,
+        permission_id,
+        nonce,
+        deadline,
+        call_bundle_hash,
+        eip712_checks_hash(checks),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn envelope_digest(
+    domain: &IntentDomain,
+    msg_type_hash: FixedBytes<32>,
+    wallet: Address,
+    permission_id: FixedBytes<32>,
+    nonce: U256,
+    deadline: u64,
+    call_bundle_hash: FixedBytes<32>,
+    program_word: FixedBytes<32>,
+) -> FixedBytes<32> {
+    let domain_separator = domain.separator();
+
     // Struct hash
     let mut struct_buf = Vec::with_capacity(32 * 7);
     struct_buf.extend_from_slice(msg_type_hash.as_slice());
@@ -117,7 +254,7 @@ pub fn policy_intent_digest(
     deadline_padded[24..32].copy_from_slice(&deadline.to_be_bytes());
     struct_buf.extend_from_slice(&deadline_padded);
     struct_buf.extend_from_slice(call_bundle_hash.as_slice());
-    struct_buf.extend_from_slice(program_hash.as_slice());
+    struct_buf.extend_from_slice(program_word.as_slice());
     let struct_hash = keccak256(struct_buf);
 
     // Final digest: keccak256("\x19\x01" || domainSeparator || structHash)
@@ -128,3 +265,5 @@ pub fn policy_intent_digest(
     keccak256(final_buf)
 }
 
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,97 @@
+//! Golden EIP-712 vectors shared with the encoder, viem and Foundry tests.
+
+use alloc::{vec, vec::Vec};
+
+use stylus_sdk::alloy_primitives::{hex, keccak256, Address, FixedBytes, U256};
+
+use fiet_maker_policy_types::WebAuthnAssertion;
+
+use super::{parse_policy_envelope, policy_intent_digest, EnvelopeSignature};
+
+const EIP712_VECTORS: &str = include_str!("../../../../../fixture/eip712-vectors.json");
+
+fn hex_field(vector: &serde_json::Value, key: &str) -> Vec<u8> {
+    hex::decode(vector[key].as_str().unwrap()).unwrap()
+}
+
+fn num_field<T: core::str::FromStr>(vector: &serde_json::Value, key: &str) -> T
+where
+    T::Err: core::fmt::Debug,
+{
+    vector[key].as_str().unwrap().parse().unwrap()
+}
+
+#[test]
+fn digest_matches_golden_vectors() {
+    let fixture: serde_json::Value = serde_json::from_str(EIP712_VECTORS).unwrap();
+    let vectors = fixture["vectors"].as_array().unwrap();
+    assert!(!vectors.is_empty());
+
+    for vector in vectors {
+        let name = vector["name"].as_str().unwrap();
+        let digest = policy_intent_digest(
+            num_field(vector, "chainId"),
+            Address::from_slice(&hex_field(vector, "verifyingContract")),
+            Address::from_slice(&hex_field(vector, "wallet")),
+            FixedBytes::from_slice(&hex_field(vector, "permissionId")),
+            num_field::<U256>(vector, "nonce"),
+            num_field(vector, "deadline"),
+            keccak256(hex_field(vector, "callData")),
+            &hex_field(vector, "programBytes"),
+        );
+        assert_eq!(digest.as_slice(), hex_field(vector, "digest").as_slice(), "{name}: digest");
+    }
+}
+
+#[test]
+fn envelope_parses_golden_vectors() {
+    let fixture: serde_json::Value = serde_json::from_str(EIP712_VECTORS).unwrap();
+
+    for vector in fixture["vectors"].as_array().unwrap() {
+        let name = vector["name"].as_str().unwrap();
+        let env = parse_policy_envelope(&hex_field(vector, "envelope")).unwrap();
+        assert_eq!(env.version, 1, "{name}: version");
+        assert_eq!(env.nonce, num_field::<U256>(vector, "nonce"), "{name}: nonce");
+        assert_eq!(env.deadline, num_field::<u64>(vector, "deadline"), "{name}: deadline");
+        assert_eq!(
+            env.call_bundle_hash.as_slice(),
+            hex_field(vector, "callBundleHash").as_slice(),
+            "{name}: callBundleHash"
+        );
+        assert_eq!(env.program_bytes, hex_field(vector, "programBytes"), "{name}: programBytes");
+        let EnvelopeSignature::Ecdsa(signature) = env.signature else {
+            panic!("{name}: expected an ECDSA signature");
+        };
+        assert_eq!(signature.as_slice(), hex_field(vector, "signature").as_slice(), "{name}: signature");
+    }
+}
+
+fn envelope_with_signature(signature: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&[0u8; 32 + 8 + 32]);
+    out.extend_from_slice(&0u32.to_be_bytes());
+    out.extend_from_slice(&(signature.len() as u16).to_be_bytes());
+    out.extend_from_slice(signature);
+    out
+}
+
+#[test]
+fn envelope_parses_webauthn_signature() {
+    let assertion = WebAuthnAssertion {
+        authenticator_data: vec![0x01; 37],
+        client_data_json: br#"{"type":"webauthn.get"}"#.to_vec(),
+        challenge_index: 0,
+        type_index: 1,
+        r: FixedBytes::repeat_byte(0x0a),
+        s: FixedBytes::repeat_byte(0x0b),
+    };
+    let env = parse_policy_envelope(&envelope_with_signature(&assertion.encode())).unwrap();
+    assert!(matches!(env.signature, EnvelopeSignature::WebAuthn(parsed) if parsed == assertion));
+
+    // Neither 65 bytes of ECDSA nor a well-formed assertion.
+    assert!(parse_policy_envelope(&envelope_with_signature(&[0x5a; 64])).is_err());
+    let mut short_auth_data = assertion.clone();
+    short_auth_data.authenticator_data.truncate(36);
+    assert!(parse_policy_envelope(&envelope_with_signature(&short_auth_data.encode())).is_err());
+}
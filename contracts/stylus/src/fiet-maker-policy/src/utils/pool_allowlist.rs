@@ -0,0 +1,120 @@
+//! Install-time pool allowlist.
+//!
+//! A permission may be installed with the poolIds it is allowed to touch. Once it is, every pool a
+//! program references must be on the list, the program must declare the pool it is for with
+//! `CheckPoolAllowed`, and so must every pool the call bundle passes.
+//!
+//! Calls name a Uniswap v4 pool by its `PoolKey`, whose hash is the poolId. The bundle's calls
+//! are decoded from Kernel's `execute` (see `utils::kernel::execute_calls`) and each call's
+//! arguments are scanned for ABI-encoded `PoolKey`s, nested ones included. A call naming no pool
+//! cannot be matched with the allowlist, so it fails like an unlisted pool.
+//!
+//! The oracle-call allowlist (`CheckOracleDeviationLte` price sources) and the fact-source
+//! overrides are stored the same way.
+
+use alloc::vec::Vec;
+
+use stylus_sdk::alloy_primitives::{keccak256, Address, FixedBytes, U256};
+
+use crate::types::opcodes::Check;
+
+/// The pools `checks` reference, in program order (duplicates kept).
+pub fn referenced_pools(checks: &[Check]) -> Vec<FixedBytes<32>> {
+    checks
+        .iter()
+        .filter_map(|check| match check {
+            Check::Slot0TickBounds { pool_id, .. }
+            | Check::Slot0SqrtPriceBounds { pool_id, .. }
+            | Check::TwapTickBounds { pool_id, .. }
+            | Check::OracleDeviationLte { pool_id, .. }
+            | Check::PoolAllowed { pool_id } => Some(*pool_id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Words in an ABI-encoded `PoolKey (currency0, currency1, fee, tickSpacing, hooks)`.
+const POOL_KEY_WORDS: usize = 5;
+
+/// Largest Uniswap v4 tick spacing (`TickMath.MAX_TICK_SPACING`).
+const MAX_TICK_SPACING: u64 = 32_767;
+
+/// The poolIds of the `PoolKey`s ABI-encoded in `call`'s arguments (after its selector), in
+/// calldata order.
+///
+/// Every run of five aligned words shaped like a `PoolKey` counts: two sorted currencies, a
+/// `uint24` fee, a tick spacing in `1..=MAX_TICK_SPACING` and a hooks address. Nested `bytes`
+/// arguments keep their word alignment, so keys encoded inside them are found too. Words that
+/// only look like a key are treated as one, which can only fail validation.
+pub fn call_pools(call: &[u8]) -> Vec<FixedBytes<32>> {
+    let words: Vec<&[u8]> = call.get(4..).unwrap_or_default().chunks_exact(32).collect();
+    words
+        .windows(POOL_KEY_WORDS)
+        .filter(|key| is_pool_key(key))
+        .map(|key| keccak256(key.concat()))
+        .collect()
+}
+
+fn is_pool_key(words: &[&[u8]]) -> bool {
+    let fits = |word: &[u8], bytes: usize| word[..32 - bytes].iter().all(|b| *b == 0);
+    let tick_spacing = U256::from_be_slice(words[3]);
+    fits(words[0], 20)
+        && fits(words[1], 20)
+        && words[0] < words[1]
+        && fits(words[2], 3)
+        && tick_spacing >= U256::from(1u64)
+        && tick_spacing <= U256::from(MAX_TICK_SPACING)
+        && fits(words[4], 20)
+}
+
+/// Whether `checks` declare a pool with `CheckPoolAllowed`.
+pub fn declares_pool(checks: &[Check]) -> bool {
+    checks.iter().any(|check| matches!(check, Check::PoolAllowed { .. }))
+}
+
+/// Storage key of an allowlist entry = keccak256(permissionKey || poolId).
+pub fn pool_allowed_key(permission_key: FixedBytes<32>, pool_id: FixedBytes<32>) -> FixedBytes<32> {
+    let mut buf = Vec::with_capacity(32 + 32);
+    buf.extend_from_slice(permission_key.as_slice());
+    buf.extend_from_slice(pool_id.as_slice());
+    keccak256(buf)
+}
+
+/// Storage key of the `index`th allowlisted pool, oracle call or source override =
+/// keccak256(permissionKey || index).
+///
+/// Each list has its own mapping, so the keys do not collide.
+pub fn pool_allowlist_slot(permission_key: FixedBytes<32>, index: U256) -> FixedBytes<32> {
+    let mut buf = Vec::with_capacity(32 + 32);
+    buf.extend_from_slice(permission_key.as_slice());
+    buf.extend_from_slice(&index.to_be_bytes::<32>());
+    keccak256(buf)
+}
+
+/// An oracle call packed into a word: `target || selector`, left-aligned.
+pub fn pack_oracle_call(target: Address, selector: [u8; 4]) -> FixedBytes<32> {
+    let mut word = [0u8; 32];
+    word[..20].copy_from_slice(target.as_slice());
+    word[20..24].copy_from_slice(&selector);
+    FixedBytes(word)
+}
+
+/// Inverse of [`pack_oracle_call`].
+pub fn unpack_oracle_call(word: FixedBytes<32>) -> (Address, [u8; 4]) {
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&word[20..24]);
+    (Address::from_slice(&word[..20]), selector)
+}
+
+/// A fact-source override packed into a word: `source || opcode`, left-aligned.
+pub fn pack_source_override(opcode: u8, source: Address) -> FixedBytes<32> {
+    let mut word = [0u8; 32];
+    word[..20].copy_from_slice(source.as_slice());
+    word[20] = opcode;
+    FixedBytes(word)
+}
+
+/// Inverse of [`pack_source_override`].
+pub fn unpack_source_override(word: FixedBytes<32>) -> (u8, Address) {
+    (word[20], Address::from_slice(&word[..20]))
+}
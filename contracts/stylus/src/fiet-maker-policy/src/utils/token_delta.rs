@@ -0,0 +1,114 @@
+//! Wallet token-delta bookkeeping shared by the validation phase and the paired hook.
+//!
+//! `CheckWalletTokenDeltaLte` cannot be decided during validation: it bounds what execution does
+//! to the wallet's balance. The policy records `balanceOf(wallet)` while validating, keyed by the
+//! hash of the calldata Kernel will execute for the UserOp, and the hook (`preCheck` / `postCheck`,
+//! around that execution) recomputes the hash from its `msgData` and compares the balance.
+//!
+//! `CheckWindowSpendLte` measures the outflow the same way and adds it to per-(permission, token)
+//! spend buckets: hourly for windows up to a day, daily beyond. The window total is the sum of the
+//! buckets it overlaps, so it can count up to one bucket of older spend, never less.
+
+use alloc::vec::Vec;
+
+use stylus_sdk::{
+    alloy_primitives::{keccak256, Address, FixedBytes, U256},
+    stylus_core::{calls::context::Call, Host},
+};
+
+use crate::types::opcodes::Check;
+
+/// Gas for each `balanceOf` call.
+pub const BALANCE_OF_GAS: u64 = 100_000;
+
+/// `balanceOf(address)`.
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+pub const HOUR: u64 = 3_600;
+pub const DAY: u64 = 86_400;
+
+/// Longest `CheckWindowSpendLte` window (30 days of daily buckets).
+pub const MAX_SPEND_WINDOW: u32 = 30 * DAY as u32;
+
+/// An outflow bound awaiting the hook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PendingDelta {
+    pub token: Address,
+    /// Largest outflow of this execution, or of the window when `window` is set.
+    pub max: U256,
+    /// Trailing window in seconds; zero bounds this execution alone.
+    pub window: u32,
+}
+
+/// The outflow bounds of `checks` (`CheckWalletTokenDeltaLte`, `CheckWindowSpendLte`), in program
+/// order.
+pub fn pending_deltas(checks: &[Check]) -> Vec<PendingDelta> {
+    checks
+        .iter()
+        .filter_map(|check| match check {
+            Check::WalletTokenDeltaLte { token, max_out } => Some(PendingDelta {
+                token: *token,
+                max: *max_out,
+                window: 0,
+            }),
+            Check::WindowSpendLte { token, window, max } => Some(PendingDelta {
+                token: *token,
+                max: *max,
+                window: *window,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `(bucket length, buckets summed)` for a window: hourly up to a day, daily beyond. The current,
+/// partial bucket is one of them, so the buckets always reach back at least `window` seconds.
+pub fn window_buckets(window: u32) -> (u64, u64) {
+    let window = window as u64;
+    let size = if window <= DAY { HOUR } else { DAY };
+    (size, window.div_ceil(size) + 1)
+}
+
+/// Storage key of a spend bucket = keccak256(permissionKey || token || size || index), where
+/// `index = timestamp / size`.
+pub fn spend_bucket_key(
+    permission_key: FixedBytes<32>,
+    token: Address,
+    size: u64,
+    index: u64,
+) -> FixedBytes<32> {
+    let mut buf = [0u8; 32 + 20 + 8 + 8];
+    buf[0..32].copy_from_slice(permission_key.as_slice());
+    buf[32..52].copy_from_slice(token.as_slice());
+    buf[52..60].copy_from_slice(&size.to_be_bytes());
+    buf[60..68].copy_from_slice(&index.to_be_bytes());
+    keccak256(buf)
+}
+
+/// ERC-20 `balanceOf(owner)` on `token`; `None` if the call fails or returns less than a word.
+pub fn erc20_balance_of(vm: &dyn Host, token: Address, owner: Address) -> Option<U256> {
+    let mut data = [0u8; 36];
+    data[0..4].copy_from_slice(&BALANCE_OF_SELECTOR);
+    data[16..36].copy_from_slice(owner.as_slice());
+    let out = vm
+        .static_call(&Call::new().gas(BALANCE_OF_GAS), token, &data)
+        .ok()?;
+    (out.len() >= 32).then(|| U256::from_be_slice(&out[0..32]))
+}
+
+/// Key of `wallet`'s execution of calldata hashing to `execution_hash` =
+/// keccak256(wallet || executionHash). Pending deltas are stored under it.
+pub fn pending_op_key(wallet: Address, execution_hash: FixedBytes<32>) -> FixedBytes<32> {
+    let mut buf = [0u8; 20 + 32];
+    buf[0..20].copy_from_slice(wallet.as_slice());
+    buf[20..52].copy_from_slice(execution_hash.as_slice());
+    keccak256(buf)
+}
+
+/// Storage key of the `index`th pending delta of an execution = keccak256(opKey || index).
+pub fn pending_delta_key(op_key: FixedBytes<32>, index: U256) -> FixedBytes<32> {
+    let mut buf = [0u8; 32 + 32];
+    buf[0..32].copy_from_slice(op_key.as_slice());
+    buf[32..64].copy_from_slice(&index.to_be_bytes::<32>());
+    keccak256(buf)
+}
@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: BUSL-1.1
+pragma solidity ^0.8.26;
+
+/// @notice Settable stand-in for the Fiet `LiquidityHub`: `reserveOfUnderlying`, `settleQueue` and
+///         `queueOfUnderlying`.
+contract MockLiquidityHub {
+    mapping(address => uint256) public reserveOfUnderlying;
+    mapping(address => mapping(address => uint256)) public settleQueue;
+    mapping(address => uint256) public queueOfUnderlying;
+
+    function setReserve(address underlying, uint256 amount) external {
+        reserveOfUnderlying[underlying] = amount;
+    }
+
+    function setQueueAmount(address lcc, address owner, uint256 amount) external {
+        settleQueue[lcc][owner] = amount;
+    }
+
+    function setQueueOfUnderlying(address lcc, uint256 amount) external {
+        queueOfUnderlying[lcc] = amount;
+    }
+}
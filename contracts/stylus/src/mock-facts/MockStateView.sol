@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: BUSL-1.1
+pragma solidity ^0.8.26;
+
+/// @notice Settable stand-in for Uniswap v4 `StateView`: the `getSlot0` fact read by the intent policy.
+contract MockStateView {
+    struct Slot0 {
+        uint160 sqrtPriceX96;
+        int24 tick;
+        uint24 protocolFee;
+        uint24 lpFee;
+    }
+
+    mapping(bytes32 => Slot0) internal slot0Of;
+
+    function setSlot0(bytes32 poolId, Slot0 calldata s) external {
+        slot0Of[poolId] = s;
+    }
+
+    function getSlot0(bytes32 poolId) external view returns (uint160, int24, uint24, uint24) {
+        Slot0 memory s = slot0Of[poolId];
+        // Provide sensible defaults if unset.
+        if (s.sqrtPriceX96 == 0) {
+            s.sqrtPriceX96 = uint160(79228162514264337593543950336); // 1:1 sqrtPriceX96
+        }
+        return (s.sqrtPriceX96, s.tick, s.protocolFee, s.lpFee);
+    }
+}
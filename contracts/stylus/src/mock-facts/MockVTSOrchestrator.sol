@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: BUSL-1.1
+pragma solidity ^0.8.26;
+
+/// @notice Settable stand-in for the Fiet `VTSOrchestrator`: checkpoint, position, pool, settled-amount
+/// and commitment reads used by the intent policy.
+contract MockVTSOrchestrator {
+    struct Checkpoint {
+        uint256 timeOfLastTransition;
+        bool isOpen;
+        uint256 gracePeriodExtension0;
+        uint256 gracePeriodExtension1;
+    }
+
+    struct Position {
+        address owner;
+        bytes32 poolId;
+    }
+
+    struct PoolConfig {
+        uint256 grace0;
+        uint256 grace1;
+        bool isPaused;
+    }
+
+    mapping(bytes32 => Checkpoint) internal checkpointOf;
+    mapping(bytes32 => Position) internal positionOf;
+    mapping(bytes32 => PoolConfig) internal poolOf;
+    mapping(bytes32 => uint256) internal settled0Of;
+    mapping(bytes32 => uint256) internal settled1Of;
+    mapping(bytes32 => uint256) internal commitMax0Of;
+    mapping(bytes32 => uint256) internal commitMax1Of;
+
+    // --- setters (for tests / local setup) ---
+    function setCheckpoint(bytes32 positionId, Checkpoint calldata c) external {
+        checkpointOf[positionId] = c;
+    }
+
+    function setPosition(bytes32 positionId, address owner, bytes32 poolId) external {
+        positionOf[positionId] = Position({owner: owner, poolId: poolId});
+    }
+
+    function setPool(bytes32 poolId, uint256 grace0, uint256 grace1, bool isPaused) external {
+        poolOf[poolId] = PoolConfig({grace0: grace0, grace1: grace1, isPaused: isPaused});
+    }
+
+    function setSettledAmounts(bytes32 positionId, uint256 a0, uint256 a1) external {
+        settled0Of[positionId] = a0;
+        settled1Of[positionId] = a1;
+    }
+
+    function setCommitmentMaxima(bytes32 positionId, uint256 c0, uint256 c1) external {
+        commitMax0Of[positionId] = c0;
+        commitMax1Of[positionId] = c1;
+    }
+
+    // --- policy-required getters (must match selectors exactly) ---
+    function positionToCheckpoint(bytes32 positionId) external view returns (uint256, bool, uint256, uint256) {
+        Checkpoint memory c = checkpointOf[positionId];
+        // Default: closed (isOpen=false) so grace period checks treat as "infinite".
+        return (c.timeOfLastTransition, c.isOpen, c.gracePeriodExtension0, c.gracePeriodExtension1);
+    }
+
+    function getPositionSettledAmounts(bytes32 positionId) external view returns (uint256 amount0, uint256 amount1) {
+        return (settled0Of[positionId], settled1Of[positionId]);
+    }
+
+    function getCommitmentMaxima(bytes32 positionId) external view returns (uint256 commitment0, uint256 commitment1) {
+        return (commitMax0Of[positionId], commitMax1Of[positionId]);
+    }
+
+    function getPosition(bytes32 positionId) external view returns (address owner, bytes32 poolId) {
+        Position memory p = positionOf[positionId];
+        return (p.owner, p.poolId);
+    }
+
+    function getPool(bytes32 poolId)
+        external
+        view
+        returns (
+            bytes32 id,
+            address currency0,
+            address currency1,
+            uint256 token0GracePeriodTime,
+            uint256 token0BaseVTSRate,
+            uint256 token0MaxGracePeriodTime,
+            uint256 token1GracePeriodTime,
+            uint256 token1BaseVTSRate,
+            uint256 token1MaxGracePeriodTime,
+            uint256 coverageFeeShare,
+            uint256 minResidualUnits,
+            bool isPaused
+        )
+    {
+        PoolConfig memory c = poolOf[poolId];
+        // Default grace periods (seconds)
+        uint256 g0 = c.grace0 == 0 ? 3600 : c.grace0;
+        uint256 g1 = c.grace1 == 0 ? 3600 : c.grace1;
+        return (poolId, address(0), address(0), g0, 0, g0, g1, 0, g1, 0, 0, c.isPaused);
+    }
+}
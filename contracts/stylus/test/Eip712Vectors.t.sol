@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: BUSL-1.1
+pragma solidity ^0.8.23;
+
+import "forge-std/Test.sol";
+
+/// Golden EIP-712 vectors for the intent policy envelope (`fixture/eip712-vectors.json`).
+///
+/// The same file is checked by the Rust policy/encoder tests and the viem E2E helpers, so a digest
+/// or envelope change on any stack fails here too. No Stylus deployment is involved.
+contract Eip712VectorsTest is Test {
+    string internal constant VECTORS_PATH = "fixture/eip712-vectors.json";
+
+    bytes32 internal constant DOMAIN_TYPEHASH =
+        keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)");
+    bytes32 internal constant DOMAIN_NAME_HASH = keccak256("Fiet Maker Intent Policy");
+    bytes32 internal constant DOMAIN_VERSION_HASH = keccak256("1");
+    bytes32 internal constant ENVELOPE_TYPEHASH = keccak256(
+        "IntentPolicyEnvelope(address wallet,bytes32 permissionId,uint256 nonce,uint64 deadline,bytes32 callBundleHash,bytes32 programHash)"
+    );
+
+    struct Vector {
+        uint256 privateKey;
+        address signer;
+        uint256 chainId;
+        address verifyingContract;
+        address wallet;
+        bytes32 permissionId;
+        uint256 nonce;
+        uint64 deadline;
+        bytes callData;
+        bytes programBytes;
+    }
+
+    function test_goldenVectors() public {
+        string memory json = vm.readFile(string.concat(vm.projectRoot(), "/", VECTORS_PATH));
+        string[] memory names = vm.parseJsonStringArray(json, ".vectors[*].name");
+        assertGt(names.length, 0, "no vectors");
+
+        for (uint256 i = 0; i < names.length; i++) {
+            string memory p = string.concat(".vectors[", vm.toString(i), "]");
+            Vector memory v = _vector(json, p);
+
+            bytes32 callBundleHash = keccak256(v.callData);
+            bytes32 programHash = keccak256(v.programBytes);
+            assertEq(callBundleHash, vm.parseJsonBytes32(json, string.concat(p, ".callBundleHash")), names[i]);
+            assertEq(programHash, vm.parseJsonBytes32(json, string.concat(p, ".programHash")), names[i]);
+
+            bytes32 domainSeparator = keccak256(
+                abi.encode(DOMAIN_TYPEHASH, DOMAIN_NAME_HASH, DOMAIN_VERSION_HASH, v.chainId, v.verifyingContract)
+            );
+            bytes32 structHash = keccak256(
+                abi.encode(ENVELOPE_TYPEHASH, v.wallet, v.permissionId, v.nonce, v.deadline, callBundleHash, programHash)
+            );
+            bytes32 digest = keccak256(abi.encodePacked("\x19\x01", domainSeparator, structHash));
+            assertEq(domainSeparator, vm.parseJsonBytes32(json, string.concat(p, ".domainSeparator")), names[i]);
+            assertEq(structHash, vm.parseJsonBytes32(json, string.concat(p, ".structHash")), names[i]);
+            assertEq(digest, vm.parseJsonBytes32(json, string.concat(p, ".digest")), names[i]);
+
+            (uint8 sigV, bytes32 r, bytes32 s) = vm.sign(v.privateKey, digest);
+            bytes memory signature = abi.encodePacked(r, s, sigV);
+            assertEq(signature, vm.parseJsonBytes(json, string.concat(p, ".signature")), names[i]);
+            assertEq(ecrecover(digest, sigV, r, s), v.signer, names[i]);
+
+            bytes memory envelope = abi.encodePacked(
+                uint16(1),
+                v.nonce,
+                v.deadline,
+                callBundleHash,
+                uint32(v.programBytes.length),
+                v.programBytes,
+                uint16(signature.length),
+                signature
+            );
+            assertEq(envelope, vm.parseJsonBytes(json, string.concat(p, ".envelope")), names[i]);
+        }
+    }
+
+    function _vector(string memory json, string memory p) internal pure returns (Vector memory v) {
+        v.privateKey = vm.parseJsonUint(json, string.concat(p, ".privateKey"));
+        v.signer = vm.parseJsonAddress(json, string.concat(p, ".signer"));
+        v.chainId = vm.parseJsonUint(json, string.concat(p, ".chainId"));
+        v.verifyingContract = vm.parseJsonAddress(json, string.concat(p, ".verifyingContract"));
+        v.wallet = vm.parseJsonAddress(json, string.concat(p, ".wallet"));
+        v.permissionId = vm.parseJsonBytes32(json, string.concat(p, ".permissionId"));
+        v.nonce = vm.parseJsonUint(json, string.concat(p, ".nonce"));
+        v.deadline = uint64(vm.parseJsonUint(json, string.concat(p, ".deadline")));
+        v.callData = vm.parseJsonBytes(json, string.concat(p, ".callData"));
+        v.programBytes = vm.parseJsonBytes(json, string.concat(p, ".programBytes"));
+    }
+}
@@ -42,21 +42,33 @@ struct PackedUserOperation {
 }
 
 interface IIntentPolicy {
+    event IntentValidated(
+        address indexed wallet,
+        bytes32 indexed permissionId,
+        bytes32 indexed envelopeDigest,
+        bytes32 programHash,
+        uint256 nonce,
+        uint64 deadline
+    );
+
     function onInstall(bytes calldata data) external payable;
     function onUninstall(bytes calldata data) external payable;
     function isModuleType(uint256 moduleTypeId) external view returns (bool);
     function isInitialized(address smartAccount) external view returns (bool);
+    function permissionIdsOf(address wallet) external view returns (bytes32[] memory);
     function checkUserOpPolicy(bytes32 id, PackedUserOperation calldata userOp) external payable returns (uint256);
     function checkSignaturePolicy(bytes32 id, address sender, bytes32 hash, bytes calldata sig)
         external
         view
         returns (uint256);
+    function revokeNonce(address wallet, bytes32 permissionId, uint256 minNonce) external returns (uint256);
 }
 
 contract IntentPolicyTest is Test {
     string internal constant WASM_FIXTURE_PATH =
         "src/fiet-maker-policy/target/wasm32-unknown-unknown/release/fiet_maker_policy.wasm";
 
+    uint256 internal constant MODULE_TYPE_HOOK = 4;
     uint256 internal constant MODULE_TYPE_POLICY = 5;
     uint256 internal constant POLICY_SUCCESS_UINT = 0;
     uint256 internal constant POLICY_FAILED_UINT = 1;
@@ -64,6 +76,7 @@ contract IntentPolicyTest is Test {
     // Errors defined by the Stylus policy (mirrors `error AlreadyInitialized(address)` / `error NotInitialized(address)`).
     bytes4 internal constant ALREADY_INITIALIZED_SELECTOR = bytes4(keccak256("AlreadyInitialized(address)"));
     bytes4 internal constant NOT_INITIALIZED_SELECTOR = bytes4(keccak256("NotInitialized(address)"));
+    bytes4 internal constant UNAUTHORIZED_SELECTOR = bytes4(keccak256("Unauthorized(address)"));
 
     bytes32 internal constant DOMAIN_TYPEHASH =
         keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)");
@@ -184,14 +197,14 @@ contract IntentPolicyTest is Test {
         });
     }
 
-    function test_isModuleType_policyOnly() public {
+    function test_isModuleType_policyAndHook() public {
         IIntentPolicy policy = _deployPolicy();
 
         assertTrue(policy.isModuleType(MODULE_TYPE_POLICY));
+        assertTrue(policy.isModuleType(MODULE_TYPE_HOOK));
         assertFalse(policy.isModuleType(1));
         assertFalse(policy.isModuleType(2));
         assertFalse(policy.isModuleType(3));
-        assertFalse(policy.isModuleType(4));
         assertFalse(policy.isModuleType(6));
     }
 
@@ -265,15 +278,24 @@ contract IntentPolicyTest is Test {
         policy.onInstall(_installData(permissionIdB, signer, stateView, vtsOrchestrator, liquidityHub));
         assertTrue(policy.isInitialized(wallet));
 
+        bytes32[] memory ids = policy.permissionIdsOf(wallet);
+        assertEq(ids.length, 2);
+        assertEq(ids[0], permissionIdA);
+        assertEq(ids[1], permissionIdB);
+
         // Uninstall only one permission id: wallet should still be considered "initialised".
         vm.prank(wallet);
         policy.onUninstall(abi.encodePacked(permissionIdA));
         assertTrue(policy.isInitialized(wallet));
+        ids = policy.permissionIdsOf(wallet);
+        assertEq(ids.length, 1);
+        assertEq(ids[0], permissionIdB);
 
         // Uninstall the final permission id: wallet should no longer be initialised.
         vm.prank(wallet);
         policy.onUninstall(abi.encodePacked(permissionIdB));
         assertFalse(policy.isInitialized(wallet));
+        assertEq(policy.permissionIdsOf(wallet).length, 0);
     }
 
     function test_install_reverts_invalidInitDataLength() public {
@@ -453,6 +475,8 @@ contract IntentPolicyTest is Test {
         bytes memory signature0 = _signDigest(signerKey, digest0);
         bytes memory envelope0 = _encodeEnvelope(1, 0, deadline, callBundleHash, "", signature0);
 
+        vm.expectEmit(true, true, true, true, address(policy));
+        emit IIntentPolicy.IntentValidated(wallet, permissionId, digest0, keccak256(""), 0, deadline);
         vm.prank(wallet);
         uint256 first = policy.checkUserOpPolicy(permissionId, _userOp(wallet, callData, envelope0));
         assertEq(first, POLICY_SUCCESS_UINT);
@@ -470,6 +494,45 @@ contract IntentPolicyTest is Test {
         assertEq(third, POLICY_SUCCESS_UINT);
     }
 
+    function test_revokeNonce_invalidatesOutstandingEnvelope() public {
+        IIntentPolicy policy = _deployPolicy();
+        address wallet = makeAddr("kernel-wallet");
+        bytes32 permissionId = keccak256("permission-id-1");
+        uint256 signerKey = 0xA11CE;
+        address signer = vm.addr(signerKey);
+        (address stateView, address vtsOrchestrator, address liquidityHub) = _defaultFactSources();
+
+        vm.prank(wallet);
+        policy.onInstall(_installData(permissionId, signer, stateView, vtsOrchestrator, liquidityHub));
+
+        bytes memory callData = hex"1234";
+        uint64 deadline = uint64(block.timestamp + 1);
+        bytes32 callBundleHash = keccak256(callData);
+        bytes32 digest0 = _policyDigest(address(policy), wallet, permissionId, 0, deadline, callBundleHash, "");
+        bytes memory envelope0 = _encodeEnvelope(1, 0, deadline, callBundleHash, "", _signDigest(signerKey, digest0));
+
+        address stranger = makeAddr("stranger");
+        vm.prank(stranger);
+        vm.expectRevert(abi.encodeWithSelector(UNAUTHORIZED_SELECTOR, stranger));
+        policy.revokeNonce(wallet, permissionId, 0);
+
+        vm.prank(signer);
+        assertEq(policy.revokeNonce(wallet, permissionId, 0), 1);
+
+        vm.prank(wallet);
+        uint256 result = policy.checkUserOpPolicy(permissionId, _userOp(wallet, callData, envelope0));
+        assertEq(result, POLICY_FAILED_UINT);
+    }
+
+    function test_revokeNonce_revertsWhenNotInstalled() public {
+        IIntentPolicy policy = _deployPolicy();
+        address wallet = makeAddr("kernel-wallet");
+
+        vm.prank(wallet);
+        vm.expectRevert(abi.encodeWithSelector(NOT_INITIALIZED_SELECTOR, wallet));
+        policy.revokeNonce(wallet, keccak256("permission-id-1"), 0);
+    }
+
     function test_checkSignaturePolicy_alwaysPasses() public {
         IIntentPolicy policy = _deployPolicy();
         bytes32 permissionId = keccak256("permission-id-1");
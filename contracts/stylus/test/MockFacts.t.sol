@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: BUSL-1.1
+pragma solidity ^0.8.26;
+
+import "forge-std/Test.sol";
+
+import {MockLiquidityHub} from "../src/mock-facts/MockLiquidityHub.sol";
+import {MockStateView} from "../src/mock-facts/MockStateView.sol";
+import {MockVTSOrchestrator} from "../src/mock-facts/MockVTSOrchestrator.sol";
+
+/// The fact-source mocks must expose exactly the selectors the policy allowlists in
+/// `facts/onchain.rs`, with the defaults the E2E tests rely on. Plain EVM; no Stylus involved.
+contract MockFactsTest is Test {
+    MockStateView internal stateView;
+    MockVTSOrchestrator internal vts;
+    MockLiquidityHub internal hub;
+
+    bytes32 internal constant POOL_ID = keccak256("mock-facts:pool");
+    bytes32 internal constant POSITION_ID = keccak256("mock-facts:position");
+
+    function setUp() public {
+        stateView = new MockStateView();
+        vts = new MockVTSOrchestrator();
+        hub = new MockLiquidityHub();
+    }
+
+    function test_selectorsMatchPolicyAllowlist() public view {
+        assertEq(stateView.getSlot0.selector, bytes4(keccak256("getSlot0(bytes32)")));
+        assertEq(vts.positionToCheckpoint.selector, bytes4(keccak256("positionToCheckpoint(bytes32)")));
+        assertEq(
+            vts.getPositionSettledAmounts.selector,
+            bytes4(keccak256("getPositionSettledAmounts(bytes32)"))
+        );
+        assertEq(vts.getCommitmentMaxima.selector, bytes4(keccak256("getCommitmentMaxima(bytes32)")));
+        assertEq(vts.getPosition.selector, bytes4(keccak256("getPosition(bytes32)")));
+        assertEq(vts.getPool.selector, bytes4(keccak256("getPool(bytes32)")));
+        assertEq(hub.reserveOfUnderlying.selector, bytes4(keccak256("reserveOfUnderlying(address)")));
+        assertEq(hub.settleQueue.selector, bytes4(keccak256("settleQueue(address,address)")));
+        assertEq(hub.queueOfUnderlying.selector, bytes4(keccak256("queueOfUnderlying(address)")));
+    }
+
+    function test_defaults() public view {
+        (uint160 sqrtPriceX96, int24 tick,,) = stateView.getSlot0(POOL_ID);
+        assertEq(sqrtPriceX96, 79228162514264337593543950336);
+        assertEq(tick, 0);
+
+        (, bool isOpen,,) = vts.positionToCheckpoint(POSITION_ID);
+        assertFalse(isOpen);
+
+        (,,, uint256 grace0,,, uint256 grace1,,,,, bool isPaused) = vts.getPool(POOL_ID);
+        assertEq(grace0, 3600);
+        assertEq(grace1, 3600);
+        assertFalse(isPaused);
+    }
+
+    function test_settersRoundTrip() public {
+        stateView.setSlot0(
+            POOL_ID, MockStateView.Slot0({sqrtPriceX96: 2 ** 96, tick: -120, protocolFee: 1, lpFee: 3000})
+        );
+        (uint160 sqrtPriceX96, int24 tick, uint24 protocolFee, uint24 lpFee) = stateView.getSlot0(POOL_ID);
+        assertEq(sqrtPriceX96, 2 ** 96);
+        assertEq(tick, -120);
+        assertEq(protocolFee, 1);
+        assertEq(lpFee, 3000);
+
+        vts.setCheckpoint(
+            POSITION_ID,
+            MockVTSOrchestrator.Checkpoint({
+                timeOfLastTransition: 100, isOpen: true, gracePeriodExtension0: 5, gracePeriodExtension1: 6
+            })
+        );
+        (uint256 t, bool isOpen, uint256 ext0, uint256 ext1) = vts.positionToCheckpoint(POSITION_ID);
+        assertEq(t, 100);
+        assertTrue(isOpen);
+        assertEq(ext0, 5);
+        assertEq(ext1, 6);
+
+        vts.setPosition(POSITION_ID, address(this), POOL_ID);
+        (address owner, bytes32 poolId) = vts.getPosition(POSITION_ID);
+        assertEq(owner, address(this));
+        assertEq(poolId, POOL_ID);
+
+        vts.setPool(POOL_ID, 60, 120, true);
+        (,,, uint256 grace0,,, uint256 grace1,,,,, bool isPaused) = vts.getPool(POOL_ID);
+        assertEq(grace0, 60);
+        assertEq(grace1, 120);
+        assertTrue(isPaused);
+
+        vts.setSettledAmounts(POSITION_ID, 7, 8);
+        (uint256 a0, uint256 a1) = vts.getPositionSettledAmounts(POSITION_ID);
+        assertEq(a0, 7);
+        assertEq(a1, 8);
+
+        vts.setCommitmentMaxima(POSITION_ID, 9, 10);
+        (uint256 c0, uint256 c1) = vts.getCommitmentMaxima(POSITION_ID);
+        assertEq(c0, 9);
+        assertEq(c1, 10);
+
+        hub.setReserve(address(0xA), 11);
+        hub.setQueueAmount(address(0xA), address(this), 12);
+        assertEq(hub.reserveOfUnderlying(address(0xA)), 11);
+        assertEq(hub.settleQueue(address(0xA), address(this)), 12);
+
+        hub.setQueueOfUnderlying(address(0xA), 13);
+        assertEq(hub.queueOfUnderlying(address(0xA)), 13);
+    }
+}
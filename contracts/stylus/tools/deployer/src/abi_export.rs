@@ -0,0 +1,132 @@
+//! ABI export stored next to the deployments file.
+//!
+//! `cargo stylus export-abi` renders the contract's Solidity interface; `--json` additionally
+//! compiles it with `solc` into a JSON ABI. When `solc` is unavailable the contract's own
+//! `export-abi` binary is asked for one (`-- --json`, as the intent policy's supports), and failing
+//! that the JSON ABI is derived from the interface's `function` / `event` / `error` lines. Files
+//! are keyed by the deployed code hash so an ABI can never be paired with the wrong build.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{abi::AbiParser, types::H256};
+use serde_json::Value;
+
+/// Paths written for one export.
+#[derive(Debug)]
+pub struct AbiFiles {
+    pub solidity: PathBuf,
+    pub json: Option<PathBuf>,
+}
+
+/// Export the ABI of the crate in `contract_dir` into `<out_dir>/abi/`.
+pub fn export(
+    contract_dir: &Path,
+    out_dir: &Path,
+    contract_key: &str,
+    code_hash: H256,
+) -> Result<AbiFiles> {
+    let solidity = cargo_stylus_export_abi(contract_dir, false)?;
+    let json = cargo_stylus_export_abi(contract_dir, true)
+        .and_then(|out| json_abi_from_solc_output(&out))
+        .or_else(|_| export_abi_binary_json(contract_dir))
+        .or_else(|_| json_abi_from_interface(&solidity))
+        .ok();
+
+    let dir = out_dir.join("abi");
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("failed creating directory {}", dir.display()))?;
+    let stem = format!("{contract_key}.{code_hash:?}");
+
+    let sol_path = dir.join(format!("{stem}.sol"));
+    fs::write(&sol_path, &solidity)
+        .with_context(|| format!("failed writing {}", sol_path.display()))?;
+
+    let json_path = match json {
+        Some(abi) => {
+            let path = dir.join(format!("{stem}.abi.json"));
+            let serialised =
+                serde_json::to_string_pretty(&abi).context("failed serialising JSON ABI")?;
+            fs::write(&path, serialised)
+                .with_context(|| format!("failed writing {}", path.display()))?;
+            Some(path)
+        }
+        None => None,
+    };
+
+    Ok(AbiFiles {
+        solidity: sol_path,
+        json: json_path,
+    })
+}
+
+fn cargo_stylus_export_abi(contract_dir: &Path, json: bool) -> Result<String> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(contract_dir)
+        .arg("stylus")
+        .arg("export-abi");
+    if json {
+        cmd.arg("--json");
+    }
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("failed to run `cargo stylus export-abi`")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`cargo stylus export-abi` failed (exit {}):\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Run the crate's `export-abi` binary with `--json`.
+fn export_abi_binary_json(contract_dir: &Path) -> Result<Value> {
+    let output = Command::new("cargo")
+        .current_dir(contract_dir)
+        .args(["run", "--quiet", "--features", "export-abi", "--", "--json"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("failed to run the `export-abi` binary")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`export-abi` binary failed (exit {}):\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    serde_json::from_slice(&output.stdout).context("invalid JSON ABI from the `export-abi` binary")
+}
+
+/// `solc --abi` output is banner lines followed by the JSON array.
+fn json_abi_from_solc_output(output: &str) -> Result<Value> {
+    let start = output
+        .find('[')
+        .ok_or_else(|| anyhow!("no JSON ABI in `cargo stylus export-abi --json` output"))?;
+    serde_json::from_str(output[start..].trim()).context("invalid JSON ABI from solc")
+}
+
+/// Parse the interface's declarations as human-readable ABI.
+fn json_abi_from_interface(solidity: &str) -> Result<Value> {
+    let declarations: Vec<String> = solidity
+        .lines()
+        .map(str::trim)
+        .filter(|l| {
+            l.starts_with("function ") || l.starts_with("event ") || l.starts_with("error ")
+        })
+        .map(|l| l.trim_end_matches(';').to_string())
+        .collect();
+    let refs: Vec<&str> = declarations.iter().map(String::as_str).collect();
+    let abi = AbiParser::default()
+        .parse(&refs)
+        .map_err(|e| anyhow!("failed parsing exported interface: {e}"))?;
+    serde_json::to_value(&abi).context("failed serialising JSON ABI")
+}
@@ -0,0 +1,201 @@
+//! Activating a deployed program, and the CacheManager bid placed once it is active.
+//!
+//! A deployed but inactive program rejects every call, so activation failures are retried and,
+//! when they persist, recorded as [`ActivationStatus::Failed`] rather than returned: the deploy
+//! already landed and must still be written down.
+
+use std::{thread, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::types::H256;
+use regex::Regex;
+use tracing::{debug, info, instrument, warn};
+
+use crate::cargo_stylus::{combined_output, truncate_output, CargoStylus};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActivationStatus {
+    /// Activated during this run (by the deploy or a separate `cargo stylus activate`).
+    Activated,
+    /// ArbOS reported the program as already activated (eg identical code hash).
+    AlreadyActivated,
+    /// Every activation attempt failed; the program is deployed but not callable.
+    Failed,
+}
+
+impl ActivationStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ActivationStatus::Activated => "activated",
+            ActivationStatus::AlreadyActivated => "already_activated",
+            ActivationStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Activation {
+    pub status: ActivationStatus,
+    pub tx_hash: Option<String>,
+    /// Number of separate activation attempts (0 when the deploy activated).
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+impl Activation {
+    /// The deploy itself activated the program in `tx_hash`.
+    pub fn by_deploy(tx_hash: String) -> Self {
+        Activation {
+            status: ActivationStatus::Activated,
+            tx_hash: Some(tx_hash),
+            attempts: 0,
+            last_error: None,
+        }
+    }
+
+    /// Outcome of a single in-process `activateProgram` (see [`crate::direct::activate`]).
+    pub fn direct(result: Result<Option<H256>>) -> Self {
+        match result {
+            Ok(Some(tx)) => Activation {
+                status: ActivationStatus::Activated,
+                tx_hash: Some(format!("{tx:?}")),
+                attempts: 1,
+                last_error: None,
+            },
+            Ok(None) => Activation {
+                status: ActivationStatus::AlreadyActivated,
+                tx_hash: None,
+                attempts: 1,
+                last_error: None,
+            },
+            Err(err) => Activation {
+                status: ActivationStatus::Failed,
+                tx_hash: None,
+                attempts: 1,
+                last_error: Some(format!("{err:#}")),
+            },
+        }
+    }
+}
+
+/// Activate a deployed-but-inactive program with `cargo stylus activate`, retrying up to
+/// `retries` times with exponential backoff from `backoff`.
+///
+/// Devnet activations flake (nonce races, sequencer hiccups), so a single failure should not
+/// leave the deployment unrecorded.
+#[instrument(name = "activate", skip(stylus))]
+pub fn cargo_stylus_activate(
+    stylus: &CargoStylus,
+    address: &str,
+    retries: u32,
+    mut backoff: Duration,
+) -> Result<Activation> {
+    let re_tx = Regex::new(r"(?i)tx(?:\s+hash)?\s*:?\s*(0x[a-fA-F0-9]{64})")?;
+
+    let attempts = retries.max(1);
+    let mut last_error = None;
+
+    for attempt in 1..=attempts {
+        let output = stylus
+            .on_chain(&["activate"])
+            .arg("--address")
+            .arg(address)
+            .args(stylus.key_args()?)
+            .output()
+            .context("failed to run `cargo stylus activate`")?;
+        let combined = combined_output(&output);
+
+        debug!(attempt, attempts, output = %combined, "cargo stylus activate output");
+
+        let lower = combined.to_ascii_lowercase();
+        if lower.contains("already activated") || lower.contains("programuptodate") {
+            return Ok(Activation {
+                status: ActivationStatus::AlreadyActivated,
+                tx_hash: None,
+                attempts: attempt,
+                last_error: None,
+            });
+        }
+
+        if output.status.success() {
+            return Ok(Activation {
+                status: ActivationStatus::Activated,
+                tx_hash: re_tx
+                    .captures(&combined)
+                    .and_then(|c| c.get(1))
+                    .map(|m| m.as_str().to_string()),
+                attempts: attempt,
+                last_error: None,
+            });
+        }
+
+        last_error = Some(truncate_output(&combined, 4_000).to_string());
+        if attempt < attempts {
+            warn!(
+                "activation attempt {attempt}/{attempts} for {address} failed; retrying in {}s",
+                backoff.as_secs()
+            );
+            thread::sleep(backoff);
+            backoff *= 2;
+        }
+    }
+
+    Ok(Activation {
+        status: ActivationStatus::Failed,
+        tx_hash: None,
+        attempts,
+        last_error,
+    })
+}
+
+/// Outcome of a CacheManager bid for the deployed program.
+#[derive(Debug)]
+pub struct CacheBid {
+    pub bid: u128,
+    pub tx_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+/// `cargo stylus cache bid`; failures are recorded rather than fatal (the program works uncached).
+#[instrument(name = "cache_bid", skip(stylus))]
+pub fn cargo_stylus_cache_bid(stylus: &CargoStylus, address: &str, bid: u128) -> CacheBid {
+    let result = (|| -> Result<Option<String>> {
+        let output = stylus
+            .on_chain(&["cache", "bid"])
+            .args(stylus.key_args()?)
+            .arg(address)
+            .arg(bid.to_string())
+            .output()
+            .context("failed to run `cargo stylus cache bid`")?;
+        let combined = combined_output(&output);
+        debug!(output = %combined, "cargo stylus cache bid output");
+        if !output.status.success() {
+            return Err(anyhow!(
+                "`cargo stylus cache bid` failed (exit {}): {}",
+                output.status,
+                truncate_output(&combined, 4_000)
+            ));
+        }
+        let re_tx = Regex::new(r"0x[a-fA-F0-9]{64}\b")?;
+        Ok(re_tx.find(&combined).map(|m| m.as_str().to_string()))
+    })();
+
+    match result {
+        Ok(tx_hash) => {
+            info!("placed cache bid of {bid} wei for {address}");
+            CacheBid {
+                bid,
+                tx_hash,
+                error: None,
+            }
+        }
+        Err(err) => {
+            warn!("cache bid for {address} failed: {err:#}");
+            CacheBid {
+                bid,
+                tx_hash: None,
+                error: Some(format!("{err:#}")),
+            }
+        }
+    }
+}
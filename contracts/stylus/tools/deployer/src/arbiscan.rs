@@ -0,0 +1,203 @@
+//! Arbiscan source verification for the deployed Stylus program.
+//!
+//! Stylus verification on Arbiscan is repo-based: the explorer rebuilds the program from a public
+//! git repository at a given commit with a given cargo-stylus version and compares code hashes.
+//! With an API key the request is submitted through the Etherscan v2 API (`codeformat=stylus`)
+//! and the returned GUID is polled; without one, everything the manual form asks for is recorded
+//! so verification can be finished in the browser.
+
+use std::{path::Path, process::Command, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Everything Arbiscan needs to rebuild the program.
+#[derive(Debug)]
+pub struct StylusSource {
+    pub repo_url: Option<String>,
+    pub commit: Option<String>,
+    /// Contract crate path relative to the repository root.
+    pub contract_path: Option<String>,
+    pub cargo_stylus_version: Option<String>,
+}
+
+impl StylusSource {
+    /// Collect repository and toolchain details for `contract_dir` (best-effort).
+    pub fn detect(contract_dir: &Path) -> Self {
+        let git = |args: &[&str]| run(Command::new("git").current_dir(contract_dir).args(args));
+        StylusSource {
+            repo_url: git(&["remote", "get-url", "origin"]).map(|u| https_repo_url(&u)),
+            commit: git(&["rev-parse", "HEAD"]),
+            contract_path: git(&["rev-parse", "--show-prefix"])
+                .map(|p| p.trim_end_matches('/').to_string()),
+            cargo_stylus_version: run(Command::new("cargo").args(["stylus", "--version"]))
+                .and_then(|v| v.split_whitespace().last().map(str::to_string)),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "repo_url": self.repo_url,
+            "commit": self.commit,
+            "contract_path": self.contract_path,
+            "cargo_stylus_version": self.cargo_stylus_version,
+        })
+    }
+}
+
+/// Etherscan-style `{status, message, result}` envelope.
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    status: String,
+    result: String,
+}
+
+pub struct Client {
+    pub api_url: String,
+    pub api_key: String,
+    pub chain_id: u64,
+    http: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(api_url: &str, api_key: &str, chain_id: u64) -> Self {
+        Client {
+            api_url: api_url.to_string(),
+            api_key: api_key.to_string(),
+            chain_id,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Submit a verification request and return its GUID.
+    pub async fn submit(
+        &self,
+        address: &str,
+        contract_name: &str,
+        license_type: u8,
+        source: &StylusSource,
+    ) -> Result<String> {
+        let missing = |what: &str| anyhow!("cannot submit Arbiscan verification: unknown {what}");
+        let repo_url = source
+            .repo_url
+            .as_deref()
+            .ok_or_else(|| missing("git remote"))?;
+        let commit = source
+            .commit
+            .as_deref()
+            .ok_or_else(|| missing("git commit"))?;
+        let version = source
+            .cargo_stylus_version
+            .as_deref()
+            .ok_or_else(|| missing("cargo-stylus version"))?;
+
+        let chain_id = self.chain_id.to_string();
+        let license = license_type.to_string();
+        let compiler = format!("stylus:{version}");
+        let source_code = match source.contract_path.as_deref() {
+            Some(path) if !path.is_empty() => format!("{repo_url}/tree/{commit}/{path}"),
+            _ => format!("{repo_url}/tree/{commit}"),
+        };
+        let form = [
+            ("apikey", self.api_key.as_str()),
+            ("module", "contract"),
+            ("action", "verifysourcecode"),
+            ("contractaddress", address),
+            ("sourceCode", source_code.as_str()),
+            ("codeformat", "stylus"),
+            ("contractname", contract_name),
+            ("compilerversion", compiler.as_str()),
+            ("licenseType", license.as_str()),
+        ];
+        let response: ApiResponse = self
+            .http
+            .post(&self.api_url)
+            .query(&[("chainid", chain_id.as_str())])
+            .form(&form)
+            .send()
+            .await
+            .context("Arbiscan verification request failed")?
+            .json()
+            .await
+            .context("Arbiscan returned a malformed response")?;
+        if response.status != "1" {
+            return Err(anyhow!(
+                "Arbiscan rejected verification: {}",
+                response.result
+            ));
+        }
+        Ok(response.result)
+    }
+
+    /// Poll `checkverifystatus` until the request leaves the queue (or `attempts` run out).
+    pub async fn wait_for_status(
+        &self,
+        guid: &str,
+        attempts: u32,
+        delay: Duration,
+    ) -> Result<String> {
+        let chain_id = self.chain_id.to_string();
+        let mut last = String::new();
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                tokio::time::sleep(delay).await;
+            }
+            let response: ApiResponse = self
+                .http
+                .get(&self.api_url)
+                .query(&[
+                    ("chainid", chain_id.as_str()),
+                    ("apikey", self.api_key.as_str()),
+                    ("module", "contract"),
+                    ("action", "checkverifystatus"),
+                    ("guid", guid),
+                ])
+                .send()
+                .await
+                .context("Arbiscan status request failed")?
+                .json()
+                .await
+                .context("Arbiscan returned a malformed response")?;
+            last = response.result;
+            if !last.to_ascii_lowercase().contains("pending") {
+                break;
+            }
+        }
+        Ok(last)
+    }
+}
+
+/// Explorer page for `address` on the chains we deploy to.
+pub fn explorer_address_url(chain_id: u64, address: &str) -> Option<String> {
+    explorer_base(chain_id).map(|base| format!("{base}/address/{address}#code"))
+}
+
+/// Explorer page for transaction `tx_hash` on the chains we deploy to.
+pub fn explorer_tx_url(chain_id: u64, tx_hash: &str) -> Option<String> {
+    explorer_base(chain_id).map(|base| format!("{base}/tx/{tx_hash}"))
+}
+
+fn explorer_base(chain_id: u64) -> Option<&'static str> {
+    match chain_id {
+        42161 => Some("https://arbiscan.io"),
+        42170 => Some("https://nova.arbiscan.io"),
+        421614 => Some("https://sepolia.arbiscan.io"),
+        _ => None,
+    }
+}
+
+/// `git@github.com:org/repo.git` / `https://github.com/org/repo.git` -> `https://github.com/org/repo`.
+fn https_repo_url(remote: &str) -> String {
+    let url = match remote.strip_prefix("git@") {
+        Some(rest) => format!("https://{}", rest.replacen(':', "/", 1)),
+        None => remote.to_string(),
+    };
+    url.trim_end_matches(".git").to_string()
+}
+
+fn run(cmd: &mut Command) -> Option<String> {
+    let out = cmd.output().ok()?;
+    let text = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (out.status.success() && !text.is_empty()).then_some(text)
+}
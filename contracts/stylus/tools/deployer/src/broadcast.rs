@@ -0,0 +1,151 @@
+//! Foundry `broadcast/` compatible export of a deployment.
+//!
+//! Foundry scripts write `broadcast/<script>/<chainId>/run-<timestamp>.json` plus a
+//! `run-latest.json` copy. Downstream tooling (explorers, Solidity deploy pipelines, address
+//! book generators) already parses that layout, so the Stylus deploy is emitted the same way with
+//! `<contract_key>` standing in for the script name.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Address, Transaction, H256},
+};
+use serde_json::{json, Value};
+
+use crate::rpc::ARB_WASM;
+
+/// What to export for one deployment run.
+pub struct BroadcastRun<'a> {
+    pub contract_key: &'a str,
+    pub contract_name: &'a str,
+    pub address: Address,
+    pub tx_hashes: &'a [H256],
+}
+
+/// Fetch the run's transactions and receipts and write `run-<ts>.json` / `run-latest.json`.
+///
+/// Returns the path of the timestamped file.
+pub async fn export(
+    provider: &Provider<Http>,
+    dir: &Path,
+    run: &BroadcastRun<'_>,
+) -> Result<PathBuf> {
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .context("failed fetching chain id")?
+        .as_u64();
+
+    let mut transactions = Vec::new();
+    let mut receipts = Vec::new();
+    for hash in run.tx_hashes {
+        let tx = provider
+            .get_transaction(*hash)
+            .await
+            .with_context(|| format!("failed fetching tx {hash:?}"))?
+            .ok_or_else(|| anyhow!("tx {hash:?} not found"))?;
+        let receipt = provider
+            .get_transaction_receipt(*hash)
+            .await
+            .with_context(|| format!("failed fetching receipt for {hash:?}"))?
+            .ok_or_else(|| anyhow!("no receipt for {hash:?}"))?;
+        transactions.push(transaction_entry(&tx, receipt.contract_address, run));
+        receipts.push(serde_json::to_value(&receipt).context("failed serialising receipt")?);
+    }
+
+    let timestamp = time::OffsetDateTime::now_utc().unix_timestamp();
+    let document = json!({
+        "transactions": transactions,
+        "receipts": receipts,
+        "libraries": [],
+        "pending": [],
+        "returns": {},
+        "timestamp": timestamp,
+        "chain": chain_id,
+        "commit": git_commit(),
+    });
+
+    let out_dir = dir.join(run.contract_key).join(chain_id.to_string());
+    fs::create_dir_all(&out_dir)
+        .with_context(|| format!("failed creating directory {}", out_dir.display()))?;
+    let serialised =
+        serde_json::to_string_pretty(&document).context("failed serialising broadcast JSON")?;
+    let run_path = out_dir.join(format!("run-{timestamp}.json"));
+    fs::write(&run_path, &serialised)
+        .with_context(|| format!("failed writing {}", run_path.display()))?;
+    let latest = out_dir.join("run-latest.json");
+    fs::write(&latest, &serialised)
+        .with_context(|| format!("failed writing {}", latest.display()))?;
+    Ok(run_path)
+}
+
+/// One `transactions[]` entry in Foundry's shape.
+fn transaction_entry(tx: &Transaction, created: Option<Address>, run: &BroadcastRun<'_>) -> Value {
+    let (transaction_type, contract_name, contract_address, function, additional) =
+        match (created, tx.to) {
+            // Plain CREATE of the Stylus init code.
+            (Some(address), _) => (
+                "CREATE",
+                Some(run.contract_name),
+                Some(address),
+                None,
+                json!([]),
+            ),
+            (None, Some(to)) if to == ARB_WASM => (
+                "CALL",
+                None,
+                Some(ARB_WASM),
+                Some("activateProgram(address)"),
+                json!([]),
+            ),
+            // `StylusDeployer` factory call: the policy is an additional CREATE2'd contract.
+            (None, to) => (
+                "CALL",
+                None,
+                to,
+                None,
+                json!([{
+                    "transactionType": "CREATE2",
+                    "address": run.address,
+                    "initCode": null,
+                }]),
+            ),
+        };
+
+    json!({
+        "hash": tx.hash,
+        "transactionType": transaction_type,
+        "contractName": contract_name,
+        "contractAddress": contract_address,
+        "function": function,
+        "arguments": null,
+        "transaction": {
+            "from": tx.from,
+            "to": tx.to,
+            "gas": tx.gas,
+            "value": tx.value,
+            "input": tx.input,
+            "nonce": tx.nonce,
+            "chainId": tx.chain_id,
+        },
+        "additionalContracts": additional,
+        "isFixedGasLimit": false,
+    })
+}
+
+/// Short HEAD commit, when run inside a git checkout.
+fn git_commit() -> Option<String> {
+    let out = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
@@ -0,0 +1,91 @@
+//! Running `cargo stylus` in the contract crate.
+//!
+//! Subcommands that talk to the chain get the same `-e <rpc>`, and those that send transactions
+//! also get the deployer key flags; [`CargoStylus`] carries both so the deploy, activation and
+//! verification steps only add their own arguments.
+
+use std::{
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+use tracing::instrument;
+
+use crate::redact;
+
+/// Where `cargo stylus` runs and what it signs with.
+#[derive(Debug)]
+pub struct CargoStylus {
+    /// Contract crate the subcommands run in.
+    pub contract_dir: PathBuf,
+    pub rpc_url: String,
+    /// `--private-key-path` / `--private-key` / keystore flags, or why there are none (a Ledger
+    /// signer, no key configured). Only subcommands that send transactions need them.
+    pub key_args: Result<Vec<String>, String>,
+}
+
+impl CargoStylus {
+    /// `cargo stylus <args>` in the contract crate, with stdout and stderr captured.
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut cmd = Command::new("cargo");
+        cmd.current_dir(&self.contract_dir);
+        cmd.arg("stylus").args(args);
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        cmd
+    }
+
+    /// [`Self::command`] against the RPC (`-e <rpc>`).
+    pub fn on_chain(&self, args: &[&str]) -> Command {
+        let mut cmd = self.command(args);
+        cmd.arg("-e").arg(&self.rpc_url);
+        cmd
+    }
+
+    /// Deployer key flags understood by every subcommand that sends transactions.
+    pub fn key_args(&self) -> Result<&[String]> {
+        self.key_args.as_deref().map_err(|e| anyhow!("{e}"))
+    }
+
+    /// Stylus init code (deploy prelude + compressed WASM) from `cargo stylus get-initcode`.
+    #[instrument(name = "build", skip_all)]
+    pub fn initcode(&self) -> Result<Vec<u8>> {
+        let output = self
+            .command(&["get-initcode"])
+            .output()
+            .context("failed to run `cargo stylus get-initcode`")?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "`cargo stylus get-initcode` failed (exit {}):\n{}\n{}",
+                output.status,
+                stdout,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        // The init code is the (only) long hex run in stdout; ignore any build chatter around it.
+        let hex = stdout
+            .split_whitespace()
+            .map(|w| w.trim_start_matches("0x"))
+            .filter(|w| w.len() > 64 && w.chars().all(|c| c.is_ascii_hexdigit()))
+            .max_by_key(|w| w.len())
+            .ok_or_else(|| anyhow!("no init code found in `cargo stylus get-initcode` output"))?;
+        ethers::utils::hex::decode(hex).context("invalid hex from `cargo stylus get-initcode`")
+    }
+}
+
+/// stdout then stderr of a finished run, the way the output parsers expect it.
+pub fn combined_output(output: &Output) -> String {
+    format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/// `raw` trimmed and cut to at most `max` bytes, for error messages.
+pub fn truncate_output(raw: &str, max: usize) -> &str {
+    redact::truncate_utf8(raw.trim(), max)
+}
@@ -0,0 +1,85 @@
+//! Named network profiles from a `deployer.toml`.
+//!
+//! ```toml
+//! [profiles.arb-sepolia]
+//! rpc_url          = "https://sepolia-rollup.arbitrum.io/rpc"
+//! chain_id         = 421614
+//! keystore_path    = ".keys/arb-sepolia-deployer.json"
+//! deployments_path = "deployments/stylus.arb-sepolia.json"
+//! contract_key     = "intent-policy"
+//! ```
+//!
+//! `--network <name>` selects `[profiles.<name>]`. Profile values only replace the built-in
+//! defaults: anything passed as a flag or env var still wins. Raw private keys are deliberately
+//! not accepted here; point at a key file, keystore, or Ledger instead.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Config file looked up in the working directory when `--config` is not given.
+pub const DEFAULT_CONFIG_PATH: &str = "deployer.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeployerConfig {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub rpc_url: Option<String>,
+    pub contract_dir: Option<String>,
+    pub private_key_path: Option<String>,
+    pub keystore_path: Option<String>,
+    pub keystore_password_file: Option<String>,
+    pub ledger: Option<bool>,
+    pub ledger_index: Option<usize>,
+    pub deployments_path: Option<String>,
+    pub contract_key: Option<String>,
+    pub stylus_deployer: Option<String>,
+    pub create2_salt: Option<String>,
+    pub chain_id: Option<u64>,
+    /// Deployer address `verify-attestation` expects when `--signer` is not given.
+    pub attestation_signer: Option<String>,
+}
+
+impl Profile {
+    /// `(clap arg id, value)` pairs to install as defaults.
+    pub fn defaults(&self) -> Vec<(&'static str, String)> {
+        let strings = [
+            ("rpc_url", &self.rpc_url),
+            ("contract_dir", &self.contract_dir),
+            ("private_key_path", &self.private_key_path),
+            ("keystore_path", &self.keystore_path),
+            ("keystore_password_file", &self.keystore_password_file),
+            ("deployments_path", &self.deployments_path),
+            ("contract_key", &self.contract_key),
+            ("stylus_deployer", &self.stylus_deployer),
+            ("create2_salt", &self.create2_salt),
+        ];
+        let mut out: Vec<(&'static str, String)> = strings
+            .into_iter()
+            .filter_map(|(id, v)| v.clone().map(|v| (id, v)))
+            .collect();
+        if let Some(ledger) = self.ledger {
+            out.push(("ledger", ledger.to_string()));
+        }
+        if let Some(index) = self.ledger_index {
+            out.push(("ledger_index", index.to_string()));
+        }
+        if let Some(chain_id) = self.chain_id {
+            out.push(("chain_id", chain_id.to_string()));
+        }
+        out
+    }
+}
+
+pub fn load(path: &Path) -> Result<DeployerConfig> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("failed reading {}", path.display()))?;
+    toml::from_str(&raw).with_context(|| format!("failed parsing {}", path.display()))
+}
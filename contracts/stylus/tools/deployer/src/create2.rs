@@ -0,0 +1,30 @@
+//! Deterministic (CREATE2) deployment through the Stylus `StylusDeployer` factory.
+//!
+//! cargo-stylus deploys via `StylusDeployer.deploy(bytecode, initData, initValue, salt)` when given
+//! `--deployer-address` / `--deployer-salt`. The factory binds non-zero salts to the constructor
+//! calldata (`keccak256(salt || initData)`) so nobody can squat the address with different init
+//! data, then `CREATE2`s the Stylus init code. With identical source, salt, and factory address
+//! the policy lands at the same address on every network.
+
+use ethers::{
+    types::{Address, H256},
+    utils::{get_create2_address_from_hash, keccak256},
+};
+
+/// Effective CREATE2 salt used by `StylusDeployer` (`initSalt` in the factory).
+pub fn stylus_deployer_salt(salt: H256, init_data: &[u8]) -> H256 {
+    if salt.is_zero() {
+        return salt;
+    }
+    let mut buf = Vec::with_capacity(32 + init_data.len());
+    buf.extend_from_slice(salt.as_bytes());
+    buf.extend_from_slice(init_data);
+    H256(keccak256(buf))
+}
+
+/// Address the factory will deploy `init_code` to for `salt` (the policy has no constructor, so
+/// `initData` is empty).
+pub fn predict_address(factory: Address, salt: H256, init_code: &[u8]) -> Address {
+    let effective_salt = stylus_deployer_salt(salt, &[]);
+    get_create2_address_from_hash(factory, effective_salt.as_bytes(), keccak256(init_code))
+}
@@ -0,0 +1,468 @@
+//! Getting the program on-chain: `cargo stylus deploy` (optionally through a CREATE2 factory,
+//! with send retries), its `--estimate-gas` dry run, and the in-process paths for signers
+//! cargo-stylus cannot drive.
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{Address, H256},
+    utils::parse_ether,
+};
+use regex::Regex;
+use tracing::{debug, info, instrument, warn};
+
+use crate::{
+    activation::Activation,
+    cargo_stylus::{combined_output, truncate_output, CargoStylus},
+    create2, deploy_wasm, direct,
+    failure::FailureClass,
+    rpc,
+};
+
+/// CREATE2 parameters for a deterministic deploy.
+#[derive(Debug)]
+pub struct Create2Deployment {
+    pub factory: Address,
+    pub salt: H256,
+    pub predicted_address: Address,
+}
+
+impl Create2Deployment {
+    /// Where `init_code` lands when deployed through `factory` with `salt`.
+    pub fn new(factory: Address, salt: H256, init_code: &[u8]) -> Self {
+        Create2Deployment {
+            factory,
+            salt,
+            predicted_address: create2::predict_address(factory, salt, init_code),
+        }
+    }
+}
+
+/// Where the program was deployed and by which transactions.
+#[derive(Debug)]
+pub struct DeployOutcome {
+    pub address: String,
+    /// Every tx hash reported by the deploy (deployment first, then activation if any).
+    pub tx_hashes: Vec<String>,
+    /// Activation tx hash, when the deploy itself activated the program.
+    pub activation_tx: Option<String>,
+    /// Where `address` came from: `receipt` (confirmed over RPC) or `output` (regex fallback).
+    pub address_source: &'static str,
+    pub raw_output: String,
+}
+
+impl DeployOutcome {
+    /// Fees from receipts, falling back to the data fee `cargo stylus` printed.
+    pub async fn fees(
+        &self,
+        rpc_url: &str,
+        activation: &Activation,
+    ) -> Option<rpc::DeploymentFees> {
+        let deployment_tx: H256 = self.tx_hashes.first()?.parse().ok()?;
+        let activation_tx: Option<H256> =
+            activation.tx_hash.as_deref().and_then(|h| h.parse().ok());
+        let provider = rpc::provider(rpc_url).ok()?;
+        let mut fees = match rpc::deployment_fees(&provider, deployment_tx, activation_tx).await {
+            Ok(fees) => fees,
+            Err(err) => {
+                warn!("could not read deployment fees from receipts: {err:#}");
+                rpc::DeploymentFees::default()
+            }
+        };
+        if fees.data_fee.is_none() {
+            let re_data_fee = Regex::new(r"(?i)(?:wasm )?data fee\s*:?\s*([0-9.]+)\s*ETH").ok()?;
+            fees.data_fee = re_data_fee
+                .captures(&self.raw_output)
+                .and_then(|c| c.get(1))
+                .and_then(|m| parse_ether(m.as_str()).ok());
+        }
+        Some(fees)
+    }
+
+    /// keccak256 of the code now at the deployed address.
+    pub async fn code_hash(&self, rpc_url: &str) -> Option<H256> {
+        let provider = rpc::provider(rpc_url).ok()?;
+        let address = self.address.parse().ok()?;
+        rpc::code_hash(&provider, address).await.ok().flatten()
+    }
+}
+
+/// Re-sending `cargo stylus deploy` after a transient rejection (see [`SendFailure`]).
+#[derive(Clone, Copy, Debug)]
+pub struct SendRetry {
+    /// Re-sends after the first attempt.
+    pub retries: u32,
+    /// Fee bump per underpriced retry, as a percentage of the current gas price.
+    pub fee_bump_percent: u32,
+}
+
+/// `cargo stylus deploy`, through the CREATE2 factory when `create2` is set, with `passthrough`
+/// appended to the command line.
+#[instrument(name = "deploy", skip_all)]
+pub async fn cargo_stylus_deploy(
+    stylus: &CargoStylus,
+    create2: Option<&Create2Deployment>,
+    retry: SendRetry,
+    passthrough: &[String],
+) -> Result<DeployOutcome> {
+    // Example output lines we parse (as shown in the repo README):
+    //   Deploying program to address 0x...
+    //   Confirmed tx 0x...
+    //
+    // Newer cargo-stylus versions tweak wording, so accept common variants.
+    let re_address_primary = Regex::new(
+        r"(?i)(?:Deploying program to address|Deployed program to address|Deployed contract to address|Contract deployed at|Program deployed at|Deployed code at address)\s*:?\s*(0x[a-fA-F0-9]{40})",
+    )?;
+    // Fallback: look for "address: 0x..." in deploy output.
+    let re_address_fallback = Regex::new(r"(?i)address\s*:?\s*(0x[a-fA-F0-9]{40})")?;
+    let re_any_address = Regex::new(r"0x[a-fA-F0-9]{40}")?;
+    let re_tx = Regex::new(
+        r"(?i)(?:Confirmed tx|deployment tx hash|contract activated and ready onchain with tx hash|activated.*tx hash)\s*:?\s*(0x[a-fA-F0-9]{64})",
+    )?;
+    let re_activation_tx = Regex::new(
+        r"(?i)(?:contract activated and ready onchain with tx hash|activated.*tx hash)\s*:?\s*(0x[a-fA-F0-9]{64})",
+    )?;
+    let re_any_hash = Regex::new(r"0x[a-fA-F0-9]{64}\b")?;
+
+    let attempts = retry.retries + 1;
+    let mut attempt = 1;
+    let mut max_fee_gwei: Option<u128> = None;
+    let (output, combined) = loop {
+        let mut cmd = stylus.on_chain(&["deploy"]);
+        cmd.args(stylus.key_args()?);
+
+        if let Some(c) = create2 {
+            cmd.arg("--deployer-address")
+                .arg(format!("{:?}", c.factory));
+            cmd.arg("--deployer-salt").arg(format!("{:?}", c.salt));
+        }
+        if let Some(gwei) = max_fee_gwei {
+            cmd.arg("--max-fee-per-gas-gwei").arg(gwei.to_string());
+        }
+
+        // Allow passing flags like --estimate-gas, --mode, etc.
+        cmd.args(passthrough);
+
+        let output = cmd
+            .output()
+            .context("failed to run `cargo stylus deploy`")?;
+        let combined = combined_output(&output);
+
+        debug!(attempt, output = %combined, "cargo stylus deploy output");
+
+        // Only retry sends that never produced a deployment; re-running after the deployment tx
+        // landed would deploy a second copy.
+        let failure = (!output.status.success() && !re_address_primary.is_match(&combined))
+            .then(|| SendFailure::classify(&combined))
+            .flatten();
+        match failure {
+            Some(failure) if attempt < attempts => {
+                if failure == SendFailure::Underpriced {
+                    max_fee_gwei = Some(
+                        bumped_max_fee_gwei(&stylus.rpc_url, retry.fee_bump_percent, attempt)
+                            .await?,
+                    );
+                }
+                warn!(
+                    "`cargo stylus deploy` attempt {attempt}/{attempts} failed ({}); retrying{}",
+                    failure.as_str(),
+                    max_fee_gwei.map_or(String::new(), |g| format!(" with max fee {g} gwei"))
+                );
+                attempt += 1;
+            }
+            _ => break (output, combined),
+        }
+    };
+
+    // Preferred path: every 32-byte hash cargo-stylus printed is a candidate tx; the receipts tell
+    // us which one deployed (and which one activated) regardless of how the output is worded.
+    let mut candidate_hashes = Vec::new();
+    for m in re_any_hash.find_iter(&combined) {
+        if let Ok(hash) = m.as_str().parse::<H256>() {
+            if !candidate_hashes.contains(&hash) {
+                candidate_hashes.push(hash);
+            }
+        }
+    }
+    if !candidate_hashes.is_empty() {
+        let from_receipts = match rpc::provider(&stylus.rpc_url) {
+            Ok(provider) => rpc::deployment_from_receipts(&provider, &candidate_hashes).await,
+            Err(e) => Err(e),
+        };
+        match from_receipts {
+            Ok(Some(d)) => {
+                let mut tx_hashes = vec![format!("{:?}", d.deployment_tx)];
+                if let Some(tx) = d.activation_tx.filter(|tx| *tx != d.deployment_tx) {
+                    tx_hashes.push(format!("{tx:?}"));
+                }
+                return Ok(DeployOutcome {
+                    address: ethers::utils::to_checksum(&d.address, None),
+                    tx_hashes,
+                    activation_tx: d.activation_tx.map(|tx| format!("{tx:?}")),
+                    address_source: "receipt",
+                    raw_output: combined,
+                });
+            }
+            Ok(None) => {
+                warn!("no deployment receipt found for the reported tx hashes; parsing output instead");
+            }
+            Err(e) => {
+                warn!(
+                    "could not confirm deployment via RPC receipts ({e:#}); parsing output instead"
+                );
+            }
+        }
+    }
+
+    let address = [re_address_primary, re_address_fallback]
+        .iter()
+        .find_map(|re| {
+            re.captures_iter(&combined)
+                .next()
+                .and_then(|c| c.get(1).map(|m| m.as_str().to_string()))
+        })
+        .or_else(|| {
+            // As a last resort, find the first 0x40 in any line mentioning deploy/address.
+            combined.lines().find_map(|line| {
+                let lower = line.to_ascii_lowercase();
+                if lower.contains("deploy") && lower.contains("address") {
+                    re_any_address.find(line).map(|m| m.as_str().to_string())
+                } else {
+                    None
+                }
+            })
+        });
+
+    // A failed deploy that still reports an address got as far as the deployment tx (typically
+    // activation is what failed). Keep going so the activation step can finish the job and the
+    // half-deployed state is recorded rather than lost.
+    let address = match (output.status.success(), address) {
+        (_, Some(address)) => {
+            if !output.status.success() {
+                warn!(
+                    "`cargo stylus deploy` exited with {} after deploying to {}; continuing with activation",
+                    output.status, address
+                );
+            }
+            address
+        }
+        (true, None) => {
+            return Err(anyhow!(
+                "could not parse deployed address from `cargo stylus deploy` output. Output (truncated):\n{}",
+                truncate_output(&combined, 4_000)
+            ))
+        }
+        (false, None) => {
+            // cargo-stylus compiles before it sends anything; a compiler error is a build failure.
+            let class = if combined.contains("could not compile") || combined.contains("error[E")
+            {
+                FailureClass::BuildFailed
+            } else {
+                FailureClass::DeployFailed
+            };
+            return Err(anyhow!(
+                "`cargo stylus deploy` failed (exit {}):\n{}",
+                output.status,
+                combined
+            )
+            .context(class));
+        }
+    };
+
+    let tx_hashes: Vec<String> = re_tx
+        .captures_iter(&combined)
+        .filter_map(|c| c.get(1))
+        .map(|m| m.as_str().to_string())
+        .collect();
+
+    let activation_tx = re_activation_tx
+        .captures(&combined)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().to_string());
+
+    Ok(DeployOutcome {
+        address,
+        tx_hashes,
+        activation_tx,
+        address_source: "output",
+        raw_output: combined,
+    })
+}
+
+/// Transient send failures that a fresh `cargo stylus deploy` usually gets past.
+///
+/// Devnet sequencers frequently reject the first send (fee moved between estimate and send, or a
+/// nonce race with another sender); cargo-stylus refetches the nonce on every run, so a retry
+/// (with a bumped fee where needed) is enough.
+///
+/// "already known" is deliberately not retried: the deploy tx is already in the mempool, and a
+/// re-run with a fresh nonce could deploy a second copy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SendFailure {
+    Underpriced,
+    NonceConflict,
+}
+
+impl SendFailure {
+    fn classify(output: &str) -> Option<Self> {
+        let lower = output.to_ascii_lowercase();
+        if lower.contains("underpriced")
+            || lower.contains("max fee per gas less than block base fee")
+            || lower.contains("fee cap less than block base fee")
+        {
+            Some(SendFailure::Underpriced)
+        } else if lower.contains("nonce too low")
+            || lower.contains("nonce too high")
+            || lower.contains("invalid transaction nonce")
+        {
+            Some(SendFailure::NonceConflict)
+        } else {
+            None
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            SendFailure::Underpriced => "underpriced",
+            SendFailure::NonceConflict => "nonce conflict",
+        }
+    }
+}
+
+/// Current gas price bumped by `fee_bump_percent` per failed attempt, in whole gwei (min 1).
+async fn bumped_max_fee_gwei(
+    rpc_url: &str,
+    fee_bump_percent: u32,
+    failed_attempts: u32,
+) -> Result<u128> {
+    let provider = rpc::provider(rpc_url)?;
+    let gas_price = provider
+        .get_gas_price()
+        .await
+        .context("failed fetching gas price for the fee bump")?;
+    let bump = 100 + u64::from(fee_bump_percent) * u64::from(failed_attempts);
+    let wei = gas_price.as_u128() * u128::from(bump) / 100;
+    Ok(wei.div_ceil(1_000_000_000).max(1))
+}
+
+/// Predicted costs parsed from `cargo stylus deploy --estimate-gas`.
+///
+/// Figures are kept as the decimal strings cargo-stylus prints so no precision is lost.
+#[derive(Debug, Default)]
+pub struct CostEstimate {
+    pub deployment_gas: Option<u64>,
+    pub gas_price_gwei: Option<String>,
+    pub deployment_cost_eth: Option<String>,
+    /// Activation gas, when the cargo-stylus version reports it.
+    pub activation_gas: Option<u64>,
+    /// ArbOS data fee charged at activation.
+    pub data_fee_eth: Option<String>,
+    pub raw_output: String,
+}
+
+/// `cargo stylus deploy --estimate-gas` with `passthrough` appended; sends nothing.
+#[instrument(name = "estimate", skip_all)]
+pub fn cargo_stylus_estimate(stylus: &CargoStylus, passthrough: &[String]) -> Result<CostEstimate> {
+    // Example `--estimate-gas` output (cargo-stylus 0.5.x):
+    //   wasm data fee: 0.000094 ETH (originally 0.000078 ETH with 20% bump)
+    //   deployment tx gas: 7123737
+    //   gas price: "0.100000000" gwei
+    //   deployment tx total cost: "0.000712373700000000" ETH
+    let re_deploy_gas = Regex::new(r"(?i)deployment tx gas\s*:?\s*(\d+)")?;
+    let re_activation_gas = Regex::new(r"(?i)activation tx gas\s*:?\s*(\d+)")?;
+    let re_gas_price = Regex::new(r#"(?i)gas price\s*:?\s*"?([0-9.]+)"?\s*gwei"#)?;
+    let re_cost = Regex::new(r#"(?i)deployment tx total cost\s*:?\s*"?([0-9.]+)"?\s*ETH"#)?;
+    let re_data_fee = Regex::new(r"(?i)(?:wasm )?data fee\s*:?\s*([0-9.]+)\s*ETH")?;
+
+    let output = stylus
+        .on_chain(&["deploy", "--estimate-gas"])
+        .args(stylus.key_args()?)
+        .args(passthrough.iter().filter(|a| *a != "--estimate-gas"))
+        .output()
+        .context("failed to run `cargo stylus deploy --estimate-gas`")?;
+    let combined = combined_output(&output);
+
+    debug!(output = %combined, "cargo stylus deploy --estimate-gas output");
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`cargo stylus deploy --estimate-gas` failed (exit {}):\n{}",
+            output.status,
+            combined
+        ));
+    }
+
+    let capture = |re: &Regex| {
+        re.captures(&combined)
+            .and_then(|c| c.get(1))
+            .map(|m| m.as_str().to_string())
+    };
+
+    let estimate = CostEstimate {
+        deployment_gas: capture(&re_deploy_gas).and_then(|g| g.parse().ok()),
+        gas_price_gwei: capture(&re_gas_price),
+        deployment_cost_eth: capture(&re_cost),
+        activation_gas: capture(&re_activation_gas).and_then(|g| g.parse().ok()),
+        data_fee_eth: capture(&re_data_fee),
+        raw_output: combined.clone(),
+    };
+
+    if estimate.deployment_gas.is_none() {
+        return Err(anyhow!(
+            "could not parse a gas estimate from `cargo stylus deploy --estimate-gas` output. Output (truncated):\n{}",
+            truncate_output(&combined, 4_000)
+        ));
+    }
+
+    Ok(estimate)
+}
+
+/// Deploy and activate a compiled WASM with [`deploy_wasm`], without invoking cargo-stylus.
+#[instrument(name = "deploy", skip_all, fields(signer = "direct"))]
+pub async fn direct<S: Signer + 'static>(
+    client: &direct::Client<S>,
+    wasm: &[u8],
+) -> Result<(DeployOutcome, Activation)> {
+    let deployed = deploy_wasm(client, wasm).await?;
+    let activation = Activation::direct(Ok(deployed.activation_tx));
+    let mut tx_hashes = vec![format!("{:?}", deployed.deployment_tx)];
+    tx_hashes.extend(activation.tx_hash.clone());
+    let deploy = DeployOutcome {
+        address: format!("{:?}", deployed.address),
+        tx_hashes,
+        activation_tx: activation.tx_hash.clone(),
+        address_source: "receipt",
+        raw_output: String::new(),
+    };
+    Ok((deploy, activation))
+}
+
+/// Send `init_code` and activate it once from `client`, for signers that confirm every
+/// transaction (a Ledger): a failed activation is recorded rather than retried, since each retry
+/// would need another confirmation on the device.
+#[instrument(name = "deploy", skip_all, fields(signer = "external"))]
+pub async fn send_init_code<S: Signer + 'static>(
+    client: &direct::Client<S>,
+    init_code: Vec<u8>,
+) -> Result<(DeployOutcome, Activation)> {
+    let deployed = direct::deploy(client, init_code).await?;
+    let mut deploy = DeployOutcome {
+        address: format!("{:?}", deployed.address),
+        tx_hashes: vec![format!("{:?}", deployed.deployment_tx)],
+        activation_tx: None,
+        address_source: "receipt",
+        raw_output: String::new(),
+    };
+
+    info!(
+        "deployed to {}; confirm the activation on the signer",
+        deploy.address
+    );
+    let activation = Activation::direct(direct::activate(client, deployed.address).await);
+    if let Some(ref tx) = activation.tx_hash {
+        deploy.tx_hashes.push(tx.clone());
+        deploy.activation_tx = Some(tx.clone());
+    }
+    Ok((deploy, activation))
+}
@@ -0,0 +1,452 @@
+//! Typed deployments JSON (`deployments.stylus.<network>.json`).
+//!
+//! Files carry a `schema_version`. Files without one are the original ad-hoc layout (schema 1)
+//! and are migrated in memory on load; the next write persists the current schema. Fields this
+//! build does not know about are kept in `extra` and written back unchanged, so a newer deployer
+//! can add fields without an older one dropping them.
+//!
+//! The deployer signs every file it writes (see [`attest`]): `attestation` holds an EIP-191
+//! signature by the deployer key over the keccak256 of the rest of the file in canonical JSON
+//! (keys sorted, no whitespace). [`verify_attestation`] checks it against the deployer a consumer
+//! trusts, so a file edited (or re-signed by another key) after CI wrote it is rejected.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, Signature, H256},
+    utils::{hash_message, keccak256},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Schema written by this build.
+pub const SCHEMA_VERSION: u64 = 2;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeploymentsFile {
+    pub schema_version: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// Current deployment per contract key.
+    pub deployments: BTreeMap<String, DeploymentEntry>,
+    /// Every version per contract key (append-only).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub history: BTreeMap<String, Vec<DeploymentEntry>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub rollbacks: BTreeMap<String, Vec<Rollback>>,
+    /// `--estimate-only` reports, kept apart from real deployments.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub estimates: BTreeMap<String, Estimate>,
+    /// Deployer signature over the rest of the file (absent when written without a local key).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeploymentEntry {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpc_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployed_at: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tx_hashes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upgraded_from: Option<UpgradedFrom>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create2: Option<Create2>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activation: Option<Activation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wasm_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<Fees>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abi: Option<Abi>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulation: Option<Simulation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Verification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reproducible_build: Option<ReproducibleBuild>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoke_test: Option<SmokeTest>,
+    /// Explorer submission details; shape follows the explorer response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arbiscan: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<Cache>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub funding: Vec<Funding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install: Option<Install>,
+    /// Truncated raw `cargo stylus deploy` output (current entry only, not history).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cargo_stylus_output: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradedFrom {
+    pub version: u64,
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Create2 {
+    pub factory: Address,
+    pub salt: H256,
+    pub predicted_address: Address,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Activation {
+    /// `activated`, `already_activated`, or `failed`.
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Fees paid, as exact wei decimal strings plus ETH floats for quick aggregation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fees {
+    pub deployment_gas_used: Option<u64>,
+    pub deployment_cost_wei: Option<String>,
+    pub deployment_cost_eth: Option<f64>,
+    pub activation_gas_used: Option<u64>,
+    pub activation_cost_wei: Option<String>,
+    pub activation_cost_eth: Option<f64>,
+    pub data_fee_wei: Option<String>,
+    pub data_fee_eth: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Abi {
+    pub solidity: PathBuf,
+    pub json: Option<PathBuf>,
+}
+
+/// Pre-deploy fork simulation that gated this deployment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Simulation {
+    /// `passed` (a failed simulation aborts the deploy, so nothing is recorded).
+    pub status: String,
+    /// Where the program landed on the fork.
+    pub fork_address: Address,
+    pub checked_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verification {
+    /// `verified` or `failed`.
+    pub status: String,
+    /// Absent when no deployment tx was parsed from `cargo stylus deploy` output.
+    pub deployment_tx: Option<String>,
+    pub checked_at: String,
+    #[serde(default)]
+    pub cargo_stylus_output: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Rebuild of the contract in a pinned toolchain container, compared with the deployed code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproducibleBuild {
+    /// `match`, `mismatch`, or `failed` (the rebuild itself did not complete).
+    pub status: String,
+    pub image: String,
+    pub wasm_hash: Option<H256>,
+    /// Codehash of the rebuilt program.
+    pub code_hash: Option<H256>,
+    /// Codehash found at the deployment address (absent when it has no code).
+    pub deployed_code_hash: Option<H256>,
+    pub checked_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmokeTest {
+    /// `passed` or `failed`.
+    pub status: String,
+    pub checked_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cache {
+    pub bid_wei: String,
+    /// `bid placed` or `failed`.
+    pub status: String,
+    pub tx_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Funding {
+    pub address: Address,
+    pub amount_eth: String,
+    /// `sent` or `failed`.
+    pub status: String,
+    pub tx_hash: Option<H256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Install {
+    pub account: Address,
+    pub permission_id: Option<H256>,
+    /// `installed` or `failed`.
+    pub status: String,
+    pub tx_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rollback {
+    pub from_version: u64,
+    pub from_address: String,
+    pub to_version: u64,
+    pub to_address: String,
+    pub rolled_back_at: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attestation {
+    pub signer: Address,
+    /// keccak256 of the canonical JSON of the file without `attestation`.
+    pub digest: H256,
+    /// 65-byte EIP-191 (`personal_sign`) signature over `digest`, 0x-hex.
+    pub signature: String,
+    pub signed_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Estimate {
+    pub network: String,
+    pub rpc_url: String,
+    pub estimated_at: String,
+    pub deployment: EstimatedDeployment,
+    pub activation: EstimatedActivation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cargo_stylus_output: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EstimatedDeployment {
+    pub gas: Option<u64>,
+    pub gas_price_gwei: Option<String>,
+    pub cost_eth: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EstimatedActivation {
+    pub gas: Option<u64>,
+    pub data_fee_eth: Option<String>,
+}
+
+impl DeploymentsFile {
+    /// An empty file at the current schema.
+    pub fn new() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            ..Self::default()
+        }
+    }
+}
+
+/// Load `path`, migrating older layouts (missing/empty files start empty).
+pub fn load(path: &Path) -> Result<DeploymentsFile> {
+    let existing = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("failed reading {}", path.display()))?
+    } else {
+        String::new()
+    };
+    if existing.trim().is_empty() {
+        return Ok(DeploymentsFile::new());
+    }
+    let root: Value = serde_json::from_str(&existing)
+        .with_context(|| format!("failed parsing JSON in {}", path.display()))?;
+    migrate(root).with_context(|| format!("unsupported deployments file {}", path.display()))
+}
+
+/// Bring a parsed deployments JSON up to [`SCHEMA_VERSION`].
+pub fn migrate(mut root: Value) -> Result<DeploymentsFile> {
+    if !root.is_object() {
+        return Ok(DeploymentsFile::new());
+    }
+    let version = match root.get("schema_version") {
+        None => 1,
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| anyhow!("schema_version must be an integer, got {v}"))?,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "schema_version {version} was written by a newer deployer (this one supports up to {SCHEMA_VERSION})"
+        ));
+    }
+
+    if version < 2 {
+        // Schema 1 had no explicit versions: an entry without one is the first deployment.
+        if let Some(deployments) = root.get_mut("deployments").and_then(Value::as_object_mut) {
+            for entry in deployments.values_mut().filter_map(Value::as_object_mut) {
+                entry.entry("version").or_insert(Value::from(1u64));
+            }
+        }
+    }
+
+    root["schema_version"] = Value::from(SCHEMA_VERSION);
+    serde_json::from_value(root).context("deployments JSON does not match the expected layout")
+}
+
+/// Write `file` atomically (temp file + rename), creating parent directories as needed.
+pub fn save(path: &Path, file: &DeploymentsFile) -> Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    if !parent.exists() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating directory {}", parent.display()))?;
+    }
+
+    let serialised =
+        serde_json::to_string_pretty(file).context("failed serialising deployments JSON")?;
+    let tmp_path = tmp_path_for(path);
+    fs::write(&tmp_path, serialised.as_bytes())
+        .with_context(|| format!("failed writing temp file {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path).with_context(|| format!("failed replacing {}", path.display()))?;
+    Ok(())
+}
+
+/// keccak256 of `file` (minus its attestation) as canonical JSON.
+pub fn digest(file: &DeploymentsFile) -> Result<H256> {
+    let mut value = serde_json::to_value(file).context("failed serialising deployments JSON")?;
+    if let Some(root) = value.as_object_mut() {
+        root.remove("attestation");
+    }
+    let mut canonical = String::new();
+    write_canonical(&value, &mut canonical);
+    Ok(H256(keccak256(canonical.as_bytes())))
+}
+
+/// Sign `file` with `wallet`, replacing any previous attestation.
+pub fn attest(file: &mut DeploymentsFile, wallet: &LocalWallet, signed_at: String) -> Result<()> {
+    let digest = digest(file)?;
+    let signature = wallet
+        .sign_hash(hash_message(digest))
+        .context("failed signing deployments digest")?;
+    file.attestation = Some(Attestation {
+        signer: wallet.address(),
+        digest,
+        signature: format!("0x{signature}"),
+        signed_at,
+    });
+    Ok(())
+}
+
+/// Check that `file` is attested by `expected` and that the signature covers its contents.
+pub fn verify_attestation(file: &DeploymentsFile, expected: Address) -> Result<()> {
+    let attestation = file
+        .attestation
+        .as_ref()
+        .ok_or_else(|| anyhow!("deployments file has no attestation"))?;
+    let digest = digest(file)?;
+    if digest != attestation.digest {
+        return Err(anyhow!(
+            "deployments file was modified after signing (digest {digest:?}, signed {:?})",
+            attestation.digest
+        ));
+    }
+    let signature: Signature = attestation
+        .signature
+        .parse()
+        .context("invalid attestation signature")?;
+    let recovered = signature
+        .recover(hash_message(digest))
+        .context("failed recovering attestation signer")?;
+    if recovered != attestation.signer {
+        return Err(anyhow!(
+            "attestation signature recovers to {recovered:?}, not the recorded signer {:?}",
+            attestation.signer
+        ));
+    }
+    if recovered != expected {
+        return Err(anyhow!(
+            "deployments file is signed by {recovered:?}, expected {expected:?}"
+        ));
+    }
+    Ok(())
+}
+
+/// Compact JSON with object keys sorted, independent of serde_json's map ordering.
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+fn tmp_path_for(path: &Path) -> PathBuf {
+    let mut tmp = path.as_os_str().to_os_string();
+    tmp.push(".tmp");
+    PathBuf::from(tmp)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,71 @@
+//! Attestation round-trip and tamper tests.
+
+use ethers::signers::{LocalWallet, Signer};
+
+use super::{attest, verify_attestation, DeploymentEntry, DeploymentsFile};
+
+fn wallet(byte: u8) -> LocalWallet {
+    LocalWallet::from_bytes(&[byte; 32]).unwrap()
+}
+
+fn signed_file(signer: &LocalWallet) -> DeploymentsFile {
+    let mut file = DeploymentsFile::new();
+    file.network = Some("arb-sepolia".into());
+    file.deployments.insert(
+        "intent-policy".into(),
+        DeploymentEntry {
+            address: "0x1111111111111111111111111111111111111111".into(),
+            version: Some(1),
+            ..DeploymentEntry::default()
+        },
+    );
+    file.extra
+        .insert("future_field".into(), serde_json::json!({"b": 2, "a": 1}));
+    attest(&mut file, signer, "2026-01-01T00:00:00Z".into()).unwrap();
+    file
+}
+
+#[test]
+fn attestation_survives_a_json_round_trip() {
+    let signer = wallet(1);
+    let file = signed_file(&signer);
+    verify_attestation(&file, signer.address()).unwrap();
+
+    let json = serde_json::to_string_pretty(&file).unwrap();
+    let reloaded: DeploymentsFile = serde_json::from_str(&json).unwrap();
+    verify_attestation(&reloaded, signer.address()).unwrap();
+}
+
+#[test]
+fn attestation_rejects_edited_files() {
+    let signer = wallet(1);
+    let mut file = signed_file(&signer);
+    file.deployments.get_mut("intent-policy").unwrap().address =
+        "0x2222222222222222222222222222222222222222".into();
+    assert!(verify_attestation(&file, signer.address()).is_err());
+
+    let mut file = signed_file(&signer);
+    file.extra
+        .insert("injected".into(), serde_json::json!(true));
+    assert!(verify_attestation(&file, signer.address()).is_err());
+}
+
+#[test]
+fn attestation_rejects_other_signers() {
+    let deployer = wallet(1);
+    let attacker = wallet(2);
+
+    // Re-signed by another key after editing: internally consistent, but not the deployer's.
+    let mut file = signed_file(&deployer);
+    file.network = Some("arb-one".into());
+    attest(&mut file, &attacker, "2026-01-02T00:00:00Z".into()).unwrap();
+    verify_attestation(&file, attacker.address()).unwrap();
+    assert!(verify_attestation(&file, deployer.address()).is_err());
+
+    // A recorded signer that does not match the signature.
+    let mut file = signed_file(&attacker);
+    file.attestation.as_mut().unwrap().signer = deployer.address();
+    assert!(verify_attestation(&file, deployer.address()).is_err());
+
+    assert!(verify_attestation(&DeploymentsFile::new(), deployer.address()).is_err());
+}
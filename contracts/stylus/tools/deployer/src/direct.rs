@@ -0,0 +1,133 @@
+//! Deploying without `cargo stylus deploy`: the deployer sends the transactions itself.
+//!
+//! cargo-stylus can only sign with a raw key or keystore. For signers it cannot drive (Ledger)
+//! we take the init code from `cargo stylus get-initcode`, send it as a plain `CREATE`, then call
+//! `ArbWasm.activateProgram` with the quoted data fee, the same two steps cargo-stylus performs.
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::Signer,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, TransactionReceipt, TransactionRequest,
+        H256, U256, U64,
+    },
+    utils::id,
+};
+
+use crate::rpc::ARB_WASM;
+
+/// cargo-stylus pads the quoted activation data fee by 20%; ArbWasm refunds the excess.
+const DATA_FEE_BUMP_PERCENT: u64 = 20;
+
+pub type Client<S> = SignerMiddleware<Provider<Http>, S>;
+
+/// Result of a direct `CREATE` of the Stylus init code.
+#[derive(Debug)]
+pub struct DirectDeploy {
+    pub address: Address,
+    pub deployment_tx: H256,
+}
+
+/// Send `init_code` as a contract-creation transaction and wait for the receipt.
+pub async fn deploy<S: Signer + 'static>(
+    client: &Client<S>,
+    init_code: Vec<u8>,
+) -> Result<DirectDeploy> {
+    let tx = TransactionRequest::new().data(init_code);
+    let receipt = send(client, tx, "deployment").await?;
+    let address = receipt
+        .contract_address
+        .ok_or_else(|| anyhow!("deployment receipt has no contract address"))?;
+    Ok(DirectDeploy {
+        address,
+        deployment_tx: receipt.transaction_hash,
+    })
+}
+
+/// Activate `program`, returning the activation tx, or `None` when ArbOS already has it active.
+pub async fn activate<S: Signer + 'static>(
+    client: &Client<S>,
+    program: Address,
+) -> Result<Option<H256>> {
+    // `programVersion` reverts for programs that still need activation.
+    let version_call = call_data("programVersion(address)", &[Token::Address(program)]);
+    let version_tx: TypedTransaction = TransactionRequest::new()
+        .to(ARB_WASM)
+        .data(version_call)
+        .into();
+    if client.provider().call(&version_tx, None).await.is_ok() {
+        return Ok(None);
+    }
+
+    let activate_call = call_data("activateProgram(address)", &[Token::Address(program)]);
+    let data_fee = quote_data_fee(client, activate_call.clone()).await?;
+    let value = data_fee + data_fee * DATA_FEE_BUMP_PERCENT / 100;
+
+    let tx = TransactionRequest::new()
+        .to(ARB_WASM)
+        .data(activate_call)
+        .value(value);
+    let receipt = send(client, tx, "activation").await?;
+    Ok(Some(receipt.transaction_hash))
+}
+
+/// Simulate `activateProgram` with the sender's whole balance attached and read back `dataFee`.
+async fn quote_data_fee<S: Signer + 'static>(
+    client: &Client<S>,
+    activate_call: Vec<u8>,
+) -> Result<U256> {
+    let from = client.address();
+    let balance = client
+        .provider()
+        .get_balance(from, None)
+        .await
+        .context("failed fetching deployer balance")?;
+    let tx: TypedTransaction = TransactionRequest::new()
+        .from(from)
+        .to(ARB_WASM)
+        .data(activate_call)
+        .value(balance)
+        .into();
+    let out = client
+        .provider()
+        .call(&tx, None)
+        .await
+        .context("failed quoting the activation data fee")?;
+    // `returns (uint16 version, uint256 dataFee)`
+    match abi::decode(&[ParamType::Uint(16), ParamType::Uint(256)], &out)
+        .context("activateProgram returned malformed data")?
+        .as_slice()
+    {
+        [_, Token::Uint(fee)] => Ok(*fee),
+        _ => Err(anyhow!("activateProgram returned malformed data: {out}")),
+    }
+}
+
+pub(crate) async fn send<S: Signer + 'static>(
+    client: &Client<S>,
+    tx: TransactionRequest,
+    what: &str,
+) -> Result<TransactionReceipt> {
+    let pending = client
+        .send_transaction(tx, None)
+        .await
+        .with_context(|| format!("failed sending {what} transaction"))?;
+    let tx_hash = pending.tx_hash();
+    let receipt = pending
+        .await
+        .with_context(|| format!("failed waiting for {what} receipt"))?
+        .ok_or_else(|| anyhow!("{what} tx {tx_hash:?} was dropped"))?;
+    if receipt.status != Some(U64::from(1u64)) {
+        return Err(anyhow!("{what} tx {tx_hash:?} reverted"));
+    }
+    Ok(receipt)
+}
+
+pub(crate) fn call_data(signature: &str, args: &[Token]) -> Vec<u8> {
+    let mut data = id(signature).to_vec();
+    data.extend_from_slice(&abi::encode(args));
+    data
+}
@@ -0,0 +1,81 @@
+//! `.env` export of deployed addresses for services that do not parse the deployments JSON.
+//!
+//! Each `deployments.<key>` becomes `<KEY>_ADDRESS=0x...` (`intent-policy` ->
+//! `INTENT_POLICY_ADDRESS`). Existing lines for other variables are kept, so the file can be
+//! shared with hand-written settings.
+
+use std::{fs, path::Path};
+
+use crate::deployments::DeploymentsFile;
+use anyhow::{Context, Result};
+
+/// `intent-policy` -> `INTENT_POLICY_ADDRESS`.
+pub fn var_name(contract_key: &str) -> String {
+    let mut name: String = contract_key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.push_str("_ADDRESS");
+    name
+}
+
+/// `(VAR, address)` for every deployment in the deployments file, in key order.
+pub fn address_vars(file: &DeploymentsFile) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = file
+        .deployments
+        .iter()
+        .filter(|(_, entry)| !entry.address.is_empty())
+        .map(|(key, entry)| (var_name(key), entry.address.clone()))
+        .collect();
+    vars.sort();
+    vars
+}
+
+/// Merge `vars` into the `.env` at `path`, replacing existing assignments in place.
+pub fn write(path: &Path, vars: &[(String, String)]) -> Result<()> {
+    let existing = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("failed reading {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let mut pending: Vec<&(String, String)> = vars.iter().collect();
+    let mut lines: Vec<String> = existing
+        .lines()
+        .map(|line| {
+            let name = line
+                .trim_start()
+                .trim_start_matches("export ")
+                .split('=')
+                .next()
+                .unwrap_or_default()
+                .trim();
+            match pending.iter().position(|(var, _)| var == name) {
+                Some(i) => {
+                    let (var, address) = pending.remove(i);
+                    format!("{var}={address}")
+                }
+                None => line.to_string(),
+            }
+        })
+        .collect();
+    lines.extend(
+        pending
+            .iter()
+            .map(|(var, address)| format!("{var}={address}")),
+    );
+
+    let mut out = lines.join("\n");
+    out.push('\n');
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating directory {}", parent.display()))?;
+    }
+    fs::write(path, out).with_context(|| format!("failed writing {}", path.display()))
+}
@@ -0,0 +1,96 @@
+//! Failure classes and exit codes, so orchestration scripts can branch on why a run failed.
+//!
+//! Steps tag their errors with a [`FailureClass`] as `anyhow` context; `main` maps the class to
+//! the process exit code and, with `--json-errors`, prints [`error_json`] on stdout. Errors no
+//! step classified (bad flags, a failed smoke test, ...) exit with [`EXIT_OTHER`].
+
+use std::fmt;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Exit code of a failure no step classified.
+pub const EXIT_OTHER: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureClass {
+    /// The contract could not be compiled, or no compiled WASM was found.
+    BuildFailed,
+    /// The deployment transaction was not sent or did not land.
+    DeployFailed,
+    /// The program was deployed but could not be activated.
+    ActivationFailed,
+    /// The deployment happened but the deployments file or an export could not be written.
+    RecordFailed,
+}
+
+impl FailureClass {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            FailureClass::BuildFailed => 10,
+            FailureClass::DeployFailed => 11,
+            FailureClass::ActivationFailed => 12,
+            FailureClass::RecordFailed => 13,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureClass::BuildFailed => "build-failed",
+            FailureClass::DeployFailed => "deploy-failed",
+            FailureClass::ActivationFailed => "activation-failed",
+            FailureClass::RecordFailed => "record-failed",
+        }
+    }
+}
+
+impl fmt::Display for FailureClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Tag an error with a [`FailureClass`], keeping any class a step inside already gave it.
+pub trait Classify<T> {
+    fn classify(self, class: FailureClass) -> Result<T>;
+}
+
+impl<T> Classify<T> for Result<T> {
+    fn classify(self, class: FailureClass) -> Result<T> {
+        self.map_err(|err| match class_of(&err) {
+            Some(_) => err,
+            None => err.context(class),
+        })
+    }
+}
+
+/// The class `err` was tagged with, if any.
+pub fn class_of(err: &anyhow::Error) -> Option<FailureClass> {
+    err.downcast_ref::<FailureClass>().copied()
+}
+
+/// Process exit code for `err`.
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    class_of(err).map_or(EXIT_OTHER, FailureClass::exit_code)
+}
+
+/// `{"error": {"class", "exit_code", "message", "causes"}}`; `class` is `null` when unclassified
+/// and `causes` lists the error chain outermost first, without the class itself.
+pub fn error_json(err: &anyhow::Error) -> Value {
+    let class = class_of(err);
+    let causes: Vec<String> = err
+        .chain()
+        .map(ToString::to_string)
+        .filter(|cause| class.is_none_or(|c| cause != c.as_str()))
+        .collect();
+    json!({
+        "error": {
+            "class": class,
+            "exit_code": exit_code(err),
+            "message": causes.first().cloned().unwrap_or_default(),
+            "causes": causes,
+        }
+    })
+}
@@ -0,0 +1,104 @@
+//! Devnet test accounts: fresh keys, funded from the deployer key, optionally turned into Kernel
+//! accounts with the policy installed.
+//!
+//! A fresh EOA has no code, so `installModule` needs it delegated to a Kernel implementation
+//! first. ethers cannot send EIP-7702 (type 4) transactions, so the delegation is a self-sponsored
+//! `cast send --auth` from the account; the install itself is a normal call from the account to
+//! itself (see [`crate::install`]).
+
+use std::{fs, io::Write, path::Path, process::Command};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    core::rand::thread_rng,
+    signers::{LocalWallet, Signer},
+    types::{Address, H256},
+    utils::hex,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// One generated account and what was done with it.
+#[derive(Clone, Debug, Serialize)]
+pub struct TestAccount {
+    pub address: Address,
+    pub private_key: String,
+    pub funding_tx: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegation_tx: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_tx: Option<H256>,
+    /// First step that failed; later steps are skipped for this account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TestAccount {
+    pub fn new(wallet: &LocalWallet) -> Self {
+        Self {
+            address: wallet.address(),
+            private_key: format!("0x{}", hex::encode(wallet.signer().to_bytes())),
+            funding_tx: None,
+            delegation_tx: None,
+            install_tx: None,
+            error: None,
+        }
+    }
+}
+
+/// `count` random keys.
+pub fn generate(count: usize) -> Vec<LocalWallet> {
+    let mut rng = thread_rng();
+    (0..count).map(|_| LocalWallet::new(&mut rng)).collect()
+}
+
+/// Delegate the account behind `key` to `implementation` with an EIP-7702 authorization, sent by
+/// the account itself (`cast send <account> --auth <implementation>`).
+pub fn delegate(rpc_url: &str, key: &str, implementation: Address) -> Result<H256> {
+    let account = key
+        .parse::<LocalWallet>()
+        .context("invalid account key")?
+        .address();
+    let output = Command::new("cast")
+        .arg("send")
+        .arg(format!("{account:?}"))
+        .arg("--auth")
+        .arg(format!("{implementation:?}"))
+        .args(["--private-key", key, "--rpc-url", rpc_url, "--json"])
+        .output()
+        .context("failed to run `cast send --auth` (is Foundry installed?)")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`cast send --auth` failed for {account:?} (exit {}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let receipt: Value =
+        serde_json::from_slice(&output.stdout).context("unexpected `cast send --json` output")?;
+    let tx_hash: H256 = receipt["transactionHash"]
+        .as_str()
+        .and_then(|h| h.parse().ok())
+        .ok_or_else(|| anyhow!("no transactionHash in `cast send --json` output"))?;
+    if receipt["status"].as_str() != Some("0x1") {
+        return Err(anyhow!("delegation tx {tx_hash:?} reverted"));
+    }
+    Ok(tx_hash)
+}
+
+/// Write the accounts (keys included) as JSON, readable by the owner only.
+pub fn write_accounts(path: &Path, accounts: &[TestAccount]) -> Result<()> {
+    let json = serde_json::to_string_pretty(accounts).context("failed serialising accounts")?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed creating {}", path.display()))?;
+    file.write_all(json.as_bytes())
+        .with_context(|| format!("failed writing {}", path.display()))
+}
@@ -0,0 +1,138 @@
+//! Post-deploy funding of devnet accounts (test wallets, paymaster) from the deployer key.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    types::{Address, TransactionRequest, H256, U256, U64},
+    utils::{format_ether, parse_ether},
+};
+use tracing::{info, instrument, warn};
+
+use crate::rpc;
+
+/// `<address>:<amount in ETH>`, eg `0xabc...:1.5`.
+#[derive(Clone, Copy, Debug)]
+pub struct FundTarget {
+    pub address: Address,
+    pub amount: U256,
+}
+
+impl FromStr for FundTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, amount) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected <address>:<amount>, got `{s}`"))?;
+        let address = address
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid address `{address}`: {e}"))?;
+        let amount = parse_ether(amount.trim())
+            .map_err(|e| format!("invalid ETH amount `{amount}`: {e}"))?;
+        Ok(FundTarget { address, amount })
+    }
+}
+
+/// Outcome of one transfer.
+#[derive(Debug)]
+pub struct Funding {
+    pub target: FundTarget,
+    pub tx_hash: Option<H256>,
+    pub error: Option<String>,
+}
+
+impl Funding {
+    pub fn amount_eth(&self) -> String {
+        format_ether(self.target.amount)
+    }
+}
+
+/// Send each transfer in order; a failed transfer is recorded and does not stop the rest.
+pub async fn fund(
+    provider: Provider<Http>,
+    key: &str,
+    targets: &[FundTarget],
+) -> Result<Vec<Funding>> {
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .context("failed fetching chain id")?;
+    let wallet: LocalWallet = key.trim().parse().context("invalid deployer private key")?;
+    let client = SignerMiddleware::new(provider, wallet.with_chain_id(chain_id.as_u64()));
+
+    let mut out = Vec::with_capacity(targets.len());
+    for target in targets {
+        let result = transfer(&client, target).await;
+        out.push(Funding {
+            target: *target,
+            tx_hash: result.as_ref().ok().copied(),
+            error: result.err().map(|e| format!("{e:#}")),
+        });
+    }
+    Ok(out)
+}
+
+/// [`fund`] from `rpc_url`, for after a deploy. Failures, including not reaching the RPC or a
+/// missing `key`, are recorded on each target rather than returned, so a deploy that already
+/// landed still gets written down.
+#[instrument(name = "fund", skip_all)]
+pub async fn fund_recorded(
+    rpc_url: &str,
+    key: Result<String>,
+    targets: &[FundTarget],
+) -> Vec<Funding> {
+    let result = async { fund(rpc::provider(rpc_url)?, &key?, targets).await }.await;
+    let funding = result.unwrap_or_else(|err| {
+        let error = format!("{err:#}");
+        targets
+            .iter()
+            .map(|target| Funding {
+                target: *target,
+                tx_hash: None,
+                error: Some(error.clone()),
+            })
+            .collect()
+    });
+    for f in &funding {
+        match (&f.tx_hash, &f.error) {
+            (Some(tx), _) => info!(
+                "funded {:?} with {} ETH (tx {tx:?})",
+                f.target.address,
+                f.amount_eth()
+            ),
+            (None, err) => warn!(
+                "funding {:?} failed: {}",
+                f.target.address,
+                err.as_deref().unwrap_or("unknown error")
+            ),
+        }
+    }
+    funding
+}
+
+async fn transfer(
+    client: &SignerMiddleware<Provider<Http>, LocalWallet>,
+    target: &FundTarget,
+) -> Result<H256> {
+    let tx = TransactionRequest::new()
+        .to(target.address)
+        .value(target.amount);
+    let pending = client
+        .send_transaction(tx, None)
+        .await
+        .with_context(|| format!("failed sending funds to {:?}", target.address))?;
+    let tx_hash = pending.tx_hash();
+    let receipt = pending
+        .await
+        .context("failed waiting for funding receipt")?
+        .ok_or_else(|| anyhow!("funding tx {tx_hash:?} was dropped"))?;
+    if receipt.status != Some(U64::from(1u64)) {
+        return Err(anyhow!("funding tx {tx_hash:?} reverted"));
+    }
+    Ok(tx_hash)
+}
@@ -0,0 +1,120 @@
+//! `healthcheck`: environment checks to run before spending time on a build.
+//!
+//! Each check is independent and reported on its own line, so one bad setting (a stale RPC URL, a
+//! profile pointing at the wrong chain) does not hide the others.
+
+use std::fmt;
+
+use ethers::{types::U256, utils::parse_ether};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::deployments::Estimate;
+
+/// Oldest cargo-stylus with `get-initcode` and the `--deployer-address` / `--deployer-salt`
+/// flags used for CREATE2 deploys through `StylusDeployer`.
+pub const MIN_CARGO_STYLUS_VERSION: (u64, u64, u64) = (0, 5, 8);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = if self.ok { "ok  " } else { "FAIL" };
+        write!(f, "{mark} {:<13} {}", self.name, self.detail)
+    }
+}
+
+/// `major.minor.patch` from `cargo stylus --version` output (eg `cargo-stylus 0.5.8`).
+pub fn parse_cargo_stylus_version(output: &str) -> Option<(u64, u64, u64)> {
+    let re = Regex::new(r"(\d+)\.(\d+)\.(\d+)").expect("static regex");
+    let caps = re.captures(output)?;
+    let part = |i: usize| caps[i].parse::<u64>().ok();
+    Some((part(1)?, part(2)?, part(3)?))
+}
+
+pub fn cargo_stylus_version(output: &str) -> Check {
+    const NAME: &str = "cargo-stylus";
+    let (a, b, c) = MIN_CARGO_STYLUS_VERSION;
+    match parse_cargo_stylus_version(output) {
+        Some(v) if v >= MIN_CARGO_STYLUS_VERSION => {
+            Check::pass(NAME, format!("{}.{}.{}", v.0, v.1, v.2))
+        }
+        Some(v) => Check::fail(
+            NAME,
+            format!("{}.{}.{} is older than {a}.{b}.{c}", v.0, v.1, v.2),
+        ),
+        None => Check::fail(
+            NAME,
+            format!(
+                "no version in `cargo stylus --version` output: {}",
+                output.trim()
+            ),
+        ),
+    }
+}
+
+pub fn chain_id(actual: u64, expected: Option<u64>) -> Check {
+    const NAME: &str = "chain id";
+    match expected {
+        Some(e) if e != actual => Check::fail(NAME, format!("RPC is on {actual}, expected {e}")),
+        Some(_) => Check::pass(NAME, actual.to_string()),
+        None => Check::pass(NAME, format!("{actual} (no expected chain id configured)")),
+    }
+}
+
+/// Deployment cost plus activation data fee of a recorded `--estimate-only` run, in wei.
+///
+/// `None` when the estimate lacks either figure (older cargo-stylus output).
+pub fn estimated_fees(estimate: &Estimate) -> Option<U256> {
+    let deploy = parse_ether(estimate.deployment.cost_eth.as_deref()?).ok()?;
+    let activation = parse_ether(estimate.activation.data_fee_eth.as_deref()?).ok()?;
+    Some(deploy.saturating_add(activation))
+}
+
+/// `balance` against `required` (the estimated fees); without an estimate only an empty account
+/// fails.
+pub fn balance(balance: U256, required: Option<U256>) -> Check {
+    const NAME: &str = "balance";
+    let eth = ethers::utils::format_ether;
+    match required {
+        Some(r) if balance < r => Check::fail(
+            NAME,
+            format!("{} ETH, estimated fees need {} ETH", eth(balance), eth(r)),
+        ),
+        Some(r) => Check::pass(
+            NAME,
+            format!("{} ETH (estimated fees {} ETH)", eth(balance), eth(r)),
+        ),
+        None if balance.is_zero() => Check::fail(NAME, "0 ETH"),
+        None => Check::pass(
+            NAME,
+            format!(
+                "{} ETH (no recorded estimate; run --estimate-only to compare)",
+                eth(balance)
+            ),
+        ),
+    }
+}
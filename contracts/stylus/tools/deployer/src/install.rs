@@ -0,0 +1,182 @@
+//! Post-deploy installation of the policy onto a Kernel account.
+//!
+//! Kernel v3 forwards `installModule(MODULE_TYPE_POLICY, policy, data)` straight to
+//! `policy.onInstall(data)`, where `data = bytes32 permissionId || initData` (Kernel `PolicyBase`
+//! packing). `installModule` is `onlyEntryPointOrSelfOrRoot`, so the transaction is sent by the
+//! account itself (under EIP-7702 the delegated EOA signs a call to its own address).
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    abi::{self, Token},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, TransactionRequest, H256, U256, U64},
+    utils::id,
+};
+use serde::Deserialize;
+
+/// ERC-7579 module type id for validators.
+pub const MODULE_TYPE_VALIDATOR: u64 = 1;
+
+/// ERC-7579 module type id for policies (Kernel v3).
+pub const MODULE_TYPE_POLICY: u64 = 5;
+
+/// `initData` version understood by `IntentPolicy::on_install`.
+pub const POLICY_INIT_VERSION: u8 = 1;
+
+/// Policy configuration written at install time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyInitConfig {
+    pub signer: Address,
+    pub state_view: Address,
+    pub vts_orchestrator: Address,
+    pub liquidity_hub: Address,
+}
+
+/// Packed `initData` (v1): `uint8 version || signer || stateView || vtsOrchestrator || liquidityHub`.
+pub fn policy_init_data(config: &PolicyInitConfig) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + 20 * 4);
+    out.push(POLICY_INIT_VERSION);
+    out.extend_from_slice(config.signer.as_bytes());
+    out.extend_from_slice(config.state_view.as_bytes());
+    out.extend_from_slice(config.vts_orchestrator.as_bytes());
+    out.extend_from_slice(config.liquidity_hub.as_bytes());
+    out
+}
+
+/// Kernel `installModule(uint256 moduleType, address module, bytes initData)` arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleInstall {
+    pub module_type: u64,
+    pub module: Address,
+    pub init_data: Vec<u8>,
+}
+
+impl ModuleInstall {
+    /// This policy as a standalone policy module: Kernel calls `onInstall(initData)` directly,
+    /// with `initData = bytes32 permissionId || policy initData`. No hook or selector applies.
+    pub fn policy(policy: Address, permission_id: H256, config: &PolicyInitConfig) -> Self {
+        let mut init_data = permission_id.as_bytes().to_vec();
+        init_data.extend_from_slice(&policy_init_data(config));
+        Self {
+            module_type: MODULE_TYPE_POLICY,
+            module: policy,
+            init_data,
+        }
+    }
+
+    /// A validator module. Kernel reads `initData` as
+    /// `hook(20) || abi.encode(validatorData, hookData, selectorData)`; `hook = None` installs
+    /// without a hook, and `selector` (4 bytes) grants the validator that selector.
+    pub fn validator(
+        validator: Address,
+        hook: Option<Address>,
+        validator_data: Vec<u8>,
+        hook_data: Vec<u8>,
+        selector: Option<[u8; 4]>,
+    ) -> Self {
+        let mut init_data = hook.unwrap_or_default().as_bytes().to_vec();
+        init_data.extend_from_slice(&abi::encode(&[
+            Token::Bytes(validator_data),
+            Token::Bytes(hook_data),
+            Token::Bytes(selector.map(|s| s.to_vec()).unwrap_or_default()),
+        ]));
+        Self {
+            module_type: MODULE_TYPE_VALIDATOR,
+            module: validator,
+            init_data,
+        }
+    }
+
+    /// Calldata for the account (send it to the Kernel account itself).
+    pub fn calldata(&self) -> Bytes {
+        let mut calldata = id("installModule(uint256,address,bytes)").to_vec();
+        calldata.extend_from_slice(&abi::encode(&[
+            Token::Uint(U256::from(self.module_type)),
+            Token::Address(self.module),
+            Token::Bytes(self.init_data.clone()),
+        ]));
+        calldata.into()
+    }
+}
+
+/// Kernel `installModule(uint256,address,bytes)` calldata for this policy.
+pub fn install_module_calldata(
+    policy: Address,
+    permission_id: H256,
+    config: &PolicyInitConfig,
+) -> Bytes {
+    ModuleInstall::policy(policy, permission_id, config).calldata()
+}
+
+/// Send the install transaction from `account` (which must be the wallet behind `account_key`).
+pub async fn submit_install(
+    provider: Provider<Http>,
+    account_key: &str,
+    account: Address,
+    calldata: Bytes,
+) -> Result<H256> {
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .context("failed fetching chain id")?;
+    let wallet: LocalWallet = account_key
+        .trim()
+        .parse()
+        .context("invalid install private key")?;
+    let wallet = wallet.with_chain_id(chain_id.as_u64());
+    if wallet.address() != account {
+        return Err(anyhow!(
+            "install key controls {:?}, not the Kernel account {:?}; installModule must be sent by the account itself",
+            wallet.address(),
+            account
+        ));
+    }
+
+    let client = SignerMiddleware::new(provider, wallet);
+    let tx = TransactionRequest::new().to(account).data(calldata);
+    let pending = client
+        .send_transaction(tx, None)
+        .await
+        .context("failed sending installModule transaction")?;
+    let tx_hash = pending.tx_hash();
+    let receipt = pending
+        .await
+        .context("failed waiting for installModule receipt")?
+        .ok_or_else(|| anyhow!("installModule tx {tx_hash:?} was dropped"))?;
+    if receipt.status != Some(U64::from(1u64)) {
+        return Err(anyhow!("installModule tx {tx_hash:?} reverted"));
+    }
+    Ok(tx_hash)
+}
+
+/// Result of installing the freshly deployed policy on a Kernel account.
+#[derive(Debug)]
+pub struct Install {
+    pub account: Address,
+    pub permission_id: Option<H256>,
+    pub tx_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Install {
+    /// Record the outcome of [`submit_install`]; a failure is kept rather than returned.
+    pub fn new(account: Address, permission_id: Option<H256>, result: Result<H256>) -> Self {
+        match result {
+            Ok(tx) => Install {
+                account,
+                permission_id,
+                tx_hash: Some(format!("{tx:?}")),
+                error: None,
+            },
+            Err(e) => Install {
+                account,
+                permission_id,
+                tx_hash: None,
+                error: Some(format!("{e:#}")),
+            },
+        }
+    }
+}
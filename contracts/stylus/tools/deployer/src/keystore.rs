@@ -0,0 +1,136 @@
+//! Encrypted (eth-keystore V3 JSON) deployer keys.
+//!
+//! The keystore is decrypted once up front so a wrong password fails before anything is sent.
+//! `cargo stylus` is then handed the keystore itself (`--keystore-path` /
+//! `--keystore-password-path`), so the raw key never appears on a command line. When the
+//! password came from the environment or a prompt it is written to a private temp file for the
+//! duration of the run and removed on drop.
+
+use std::{
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::Address,
+    utils::hex,
+};
+
+/// Password env var consulted when no password file is given (never accepted as a flag).
+pub const PASSWORD_ENV: &str = "KEYSTORE_PASSWORD";
+
+/// A decrypted keystore plus a password file `cargo stylus` can read.
+pub struct UnlockedKeystore {
+    pub path: PathBuf,
+    pub password_path: PathBuf,
+    pub address: Address,
+    private_key: String,
+    owns_password_file: bool,
+}
+
+impl UnlockedKeystore {
+    /// Hex private key for the in-process signers (install, funding, ...).
+    pub fn private_key(&self) -> &str {
+        &self.private_key
+    }
+
+    /// Key flags for `cargo stylus` subcommands that send transactions.
+    pub fn cargo_stylus_args(&self) -> Vec<String> {
+        vec![
+            "--keystore-path".to_string(),
+            self.path.display().to_string(),
+            "--keystore-password-path".to_string(),
+            self.password_path.display().to_string(),
+        ]
+    }
+}
+
+impl fmt::Debug for UnlockedKeystore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnlockedKeystore")
+            .field("path", &self.path)
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for UnlockedKeystore {
+    fn drop(&mut self) {
+        if self.owns_password_file {
+            let _ = fs::remove_file(&self.password_path);
+        }
+    }
+}
+
+/// Decrypt `path`, taking the password from `password_file`, `KEYSTORE_PASSWORD`, or a prompt.
+pub fn unlock(path: &Path, password_file: Option<&Path>) -> Result<UnlockedKeystore> {
+    let (password, password_path, owns_password_file) = match password_file {
+        Some(p) => {
+            let password = fs::read_to_string(p).with_context(|| {
+                format!("failed reading keystore password from {}", p.display())
+            })?;
+            (
+                password.trim_end_matches(['\r', '\n']).to_string(),
+                p.to_path_buf(),
+                false,
+            )
+        }
+        None => {
+            let password = match std::env::var(PASSWORD_ENV) {
+                Ok(p) => p,
+                Err(_) => rpassword::prompt_password(format!(
+                    "Password for keystore {}: ",
+                    path.display()
+                ))
+                .with_context(|| {
+                    format!(
+                        "failed reading keystore password (set {PASSWORD_ENV} when not on a TTY)"
+                    )
+                })?,
+            };
+            let tmp = write_private_temp(&password)?;
+            (password, tmp, true)
+        }
+    };
+
+    // Build the guard before decrypting so a temp password file is cleaned up on failure too.
+    let mut unlocked = UnlockedKeystore {
+        path: path.to_path_buf(),
+        password_path,
+        address: Address::zero(),
+        private_key: String::new(),
+        owns_password_file,
+    };
+    let wallet = LocalWallet::decrypt_keystore(path, &password).map_err(|e| {
+        anyhow!(
+            "failed decrypting keystore {} (wrong password?): {e}",
+            path.display()
+        )
+    })?;
+    unlocked.address = wallet.address();
+    unlocked.private_key = format!("0x{}", hex::encode(wallet.signer().to_bytes()));
+    Ok(unlocked)
+}
+
+fn write_private_temp(contents: &str) -> Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!(
+        "stylus-deployer-keystore-{}.pass",
+        std::process::id()
+    ));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .with_context(|| format!("failed creating {}", path.display()))?;
+    file.write_all(contents.as_bytes())
+        .with_context(|| format!("failed writing {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,74 @@
+//! Stylus deployment as a library.
+//!
+//! The `stylus-deployer` binary wraps `cargo stylus` for the Nitro/E2E workflow; services that
+//! need to deploy from Rust can instead hand [`deploy_wasm`] a compiled WASM and a signer and get
+//! the program deployed and activated directly over RPC. The binary itself is only argument
+//! parsing and orchestration: the deploy ([`deploy`]), activation ([`activation`]), post-deploy
+//! checks ([`verify`]), funding ([`fund`]) and the deployments record ([`record`]) live here, next
+//! to the smaller building blocks (CREATE2 prediction, Foundry broadcast export, locking, ...).
+
+use anyhow::Result;
+use ethers::{
+    signers::Signer,
+    types::{Address, H256},
+    utils::keccak256,
+};
+
+pub mod abi_export;
+pub mod activation;
+pub mod arbiscan;
+pub mod broadcast;
+pub mod cargo_stylus;
+pub mod create2;
+pub mod deploy;
+pub mod deployments;
+pub mod direct;
+pub mod env_out;
+pub mod failure;
+pub mod faucet;
+pub mod fund;
+pub mod healthcheck;
+pub mod install;
+pub mod keystore;
+pub mod lock;
+pub mod plan;
+pub mod record;
+pub mod redact;
+pub mod report;
+pub mod reproducible;
+pub mod rpc;
+pub mod safe;
+pub mod simulate;
+pub mod status;
+pub mod verify;
+pub mod wasm;
+
+pub use direct::Client;
+
+/// A program deployed and activated by [`deploy_wasm`].
+#[derive(Debug, Clone)]
+pub struct WasmDeployment {
+    pub address: Address,
+    pub deployment_tx: H256,
+    /// `None` when ArbOS already had an identical program activated.
+    pub activation_tx: Option<H256>,
+    /// `keccak256` of the deployed program code.
+    pub code_hash: H256,
+}
+
+/// Deploy `wasm` with a plain `CREATE` and activate it, without shelling out to cargo-stylus.
+pub async fn deploy_wasm<S: Signer + 'static>(
+    client: &Client<S>,
+    wasm: &[u8],
+) -> Result<WasmDeployment> {
+    let init_code = wasm::init_code(wasm)?;
+    let code_hash = H256(keccak256(&init_code[wasm::PRELUDE_LEN..]));
+    let deployed = direct::deploy(client, init_code).await?;
+    let activation_tx = direct::activate(client, deployed.address).await?;
+    Ok(WasmDeployment {
+        address: deployed.address,
+        deployment_tx: deployed.deployment_tx,
+        activation_tx,
+        code_hash,
+    })
+}
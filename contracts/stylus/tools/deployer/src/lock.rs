@@ -0,0 +1,58 @@
+//! Advisory lock serialising read-modify-write cycles on a deployments JSON.
+//!
+//! The atomic rename keeps the file from being torn, but two pipelines deploying different
+//! contract keys to the same file would still each write back a stale copy of the other's entry.
+//! Holding an exclusive `flock` on `<path>.lock` across load-modify-save prevents that; the OS
+//! releases it if the process dies, so there is no stale-lock cleanup to get wrong.
+
+use std::{
+    fs::{File, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use tracing::info;
+
+/// Held for as long as the deployments file is being updated; unlocks on drop.
+#[derive(Debug)]
+pub struct DeploymentsLock {
+    file: File,
+}
+
+impl Drop for DeploymentsLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// Block until the lock for `deployments_path` is held.
+pub fn lock(deployments_path: &Path) -> Result<DeploymentsLock> {
+    let lock_path = lock_path_for(deployments_path);
+    if let Some(parent) = lock_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating directory {}", parent.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("failed opening lock file {}", lock_path.display()))?;
+
+    if file.try_lock_exclusive().is_err() {
+        info!(
+            "waiting for another deploy to release {}",
+            lock_path.display()
+        );
+        file.lock_exclusive()
+            .with_context(|| format!("failed locking {}", lock_path.display()))?;
+    }
+    Ok(DeploymentsLock { file })
+}
+
+fn lock_path_for(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_os_string();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
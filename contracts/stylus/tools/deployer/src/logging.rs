@@ -0,0 +1,38 @@
+//! `tracing` setup for the deployer.
+//!
+//! Logs go to stderr so stdout stays reserved for command results (`predict-address`, `plan`).
+//! Each stage runs in its own span (`build`, `deploy`, `activate`, `verify`, `record`, ...), so a
+//! CI log shows which step a failure came from without rerunning locally.
+
+use clap::ValueEnum;
+use tracing_subscriber::{fmt, EnvFilter};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// `verbosity` counts `-v`: 0 = info, 1 = debug (full `cargo stylus` output), 2+ = trace.
+/// `RUST_LOG` overrides the level entirely when set.
+pub fn init(verbosity: u8, format: LogFormat) {
+    let default_level = match verbosity {
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        // Keep dependency noise (hyper, reqwest, ...) out unless RUST_LOG asks for it.
+        EnvFilter::new(format!("warn,stylus_deployer={default_level}"))
+    });
+
+    let builder = fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(false);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+}
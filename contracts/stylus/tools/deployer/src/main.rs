@@ -1,14 +1,45 @@
 use std::{
     fs,
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::{Command, ExitCode},
+    time::Duration,
 };
 
+use alloy_primitives::keccak256;
 use anyhow::{anyhow, Context, Result};
-use clap::Parser;
-use regex::Regex;
-use serde_json::{json, Value};
-use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, H256, U256},
+    utils::parse_ether,
+};
+use fiet_intent_sdk::{inspect, kernel::VALIDATION_MODE_ENABLE};
+use fiet_maker_policy_encoder::encoder::PolicyDomain;
+use serde_json::json;
+use time::OffsetDateTime;
+use tracing::{info, instrument, warn};
+
+use stylus_deployer::{
+    abi_export,
+    activation::{self, Activation, ActivationStatus},
+    cargo_stylus::CargoStylus,
+    create2,
+    deploy::{self, Create2Deployment, DeployOutcome},
+    deployments, direct,
+    failure::{self, Classify, FailureClass},
+    faucet, fund, healthcheck,
+    install::{self, Install},
+    keystore, plan,
+    record::{self, DeploymentRecord, Recorder},
+    redact, rpc, safe, simulate, status,
+    verify::{self, SmokeTest},
+    wasm,
+};
+
+mod config;
+mod logging;
 
 /// Deploy the Stylus contract using `cargo stylus deploy`, then write/update a deployments JSON.
 ///
@@ -28,28 +59,230 @@ struct Cli {
     rpc_url: String,
 
     /// Path to a file containing the deployer private key.
-    #[arg(long, env = "PRIV_KEY_PATH", conflicts_with = "private_key")]
+    #[arg(long, env = "PRIV_KEY_PATH", conflicts_with_all = ["private_key", "keystore_path"])]
     private_key_path: Option<String>,
 
     /// Private key (hex string, 0x...).
-    #[arg(long, env = "PKEY", conflicts_with = "private_key_path")]
+    #[arg(long, env = "PKEY", conflicts_with_all = ["private_key_path", "keystore_path"])]
     private_key: Option<String>,
 
+    /// Encrypted deployer key (eth-keystore V3 JSON).
+    ///
+    /// The password is read from `--keystore-password-file`, then KEYSTORE_PASSWORD, then an
+    /// interactive prompt.
+    #[arg(long, env = "KEYSTORE_PATH")]
+    keystore_path: Option<PathBuf>,
+
+    /// File containing the keystore password.
+    #[arg(long, env = "KEYSTORE_PASSWORD_FILE", requires = "keystore_path")]
+    keystore_password_file: Option<PathBuf>,
+
+    /// Sign the deployment and activation with a Ledger instead of a local key.
+    ///
+    /// cargo-stylus cannot drive hardware wallets, so the deployer sends both transactions itself
+    /// (init code from `cargo stylus get-initcode`). Requires building with `--features ledger`.
+    #[arg(long, conflicts_with_all = ["private_key", "private_key_path", "keystore_path", "create2_salt", "estimate_only"])]
+    ledger: bool,
+
+    /// Deploy the compiled WASM (`--wasm-path` or the release artefact) through the library path
+    /// (plain `CREATE` + `activateProgram`) instead of `cargo stylus deploy`.
+    #[arg(long, conflicts_with_all = ["ledger", "create2_salt", "estimate_only"])]
+    direct: bool,
+
+    /// Fork RPC (Nitro dev node or Tenderly fork; anvil cannot run Stylus) to rehearse on first.
+    ///
+    /// The compiled WASM is deployed there, installed on the deployer account, and checked with a
+    /// signed sample `checkUserOpPolicy` call; the real deploy only proceeds if that passes.
+    #[arg(long, env = "SIMULATE_RPC_URL", hide_env_values = true, conflicts_with_all = ["ledger", "estimate_only"])]
+    simulate_rpc: Option<String>,
+
+    /// Ledger Live account index used with `--ledger` (`m/44'/60'/<index>'/0/0`).
+    #[arg(long, default_value_t = 0, requires = "ledger")]
+    ledger_index: usize,
+
+    /// Decrypted `--keystore-path`, filled in after parsing.
+    #[arg(skip)]
+    keystore: Option<keystore::UnlockedKeystore>,
+
     /// Path to write deployment info (eg, deployments.devnet.json).
-    #[arg(long, default_value = "deployments.devnet.json")]
+    #[arg(long, default_value = "deployments.devnet.json", global = true)]
     deployments_path: PathBuf,
 
     /// Key under `deployments` to store this contract (eg, intent-policy).
-    #[arg(long, default_value = "intent-policy")]
+    #[arg(long, default_value = "intent-policy", global = true)]
     contract_key: String,
 
     /// Optional network name (eg, devnet, arb-sepolia).
+    ///
+    /// Also selects `[profiles.<network>]` from the config file, when present.
     #[arg(long, default_value = "devnet")]
     network: String,
 
-    /// Print full `cargo stylus deploy` output for debugging.
-    #[arg(long, env = "STYLUS_DEPLOYER_VERBOSE")]
-    verbose: bool,
+    /// Chain id the RPC is expected to serve (`healthcheck` fails on a mismatch).
+    #[arg(long, env = "CHAIN_ID")]
+    chain_id: Option<u64>,
+
+    /// Config file with named network profiles (see `deployer.example.toml`).
+    ///
+    /// Defaults to `deployer.toml` in the working directory, ignored when absent.
+    #[arg(long, env = "DEPLOYER_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Run `cargo stylus verify` against the deployment tx after deploying and record the result.
+    ///
+    /// Requires Docker (cargo-stylus performs a reproducible build to compare source hashes).
+    #[arg(long, env = "STYLUS_DEPLOYER_VERIFY")]
+    verify: bool,
+
+    /// Rebuild the contract with `--locked` in a pinned toolchain container after deploying and
+    /// record whether its codehash matches the deployed code (requires Docker).
+    #[arg(long, env = "STYLUS_DEPLOYER_REPRODUCE")]
+    reproduce: bool,
+
+    /// Container image for `--reproduce` and `verify-build` (defaults to `rust:<channel>` from the
+    /// contract's `rust-toolchain.toml`; pass a digest-pinned image for stricter pinning).
+    #[arg(long, global = true)]
+    reproduce_image: Option<String>,
+
+    /// Path to the compiled contract WASM used for the recorded `wasm_hash`.
+    ///
+    /// Defaults to the single `*.wasm` under `<contract_dir>/target/wasm32-unknown-unknown/release/`.
+    #[arg(long)]
+    wasm_path: Option<PathBuf>,
+
+    /// How many times to attempt `cargo stylus activate` when the deploy did not activate the program.
+    #[arg(long, default_value_t = 3)]
+    activation_retries: u32,
+
+    /// Initial backoff between activation attempts (doubles after each failed attempt).
+    #[arg(long, default_value_t = 2)]
+    activation_backoff_secs: u64,
+
+    /// How many times to re-send `cargo stylus deploy` after an underpriced / nonce-conflict
+    /// rejection (only when no deployment landed).
+    #[arg(long, default_value_t = 2)]
+    send_retries: u32,
+
+    /// Fee bump applied per underpriced retry, as a percentage of the current gas price.
+    #[arg(long, default_value_t = 20)]
+    fee_bump_percent: u32,
+
+    /// Only estimate deployment costs (`cargo stylus deploy --estimate-gas`) and record them under
+    /// `estimates` in the deployments JSON. The `deployments` map is left untouched.
+    #[arg(long)]
+    estimate_only: bool,
+
+    /// Skip the post-deploy `isModuleType(5)` / `isInitialized(0x0)` smoke test.
+    #[arg(long)]
+    skip_smoke_test: bool,
+
+    /// Install the deployed policy on this Kernel account after deploying.
+    ///
+    /// Sends `installModule(5, policy, permissionId || initData)` from the account itself, so
+    /// `--install-private-key` must be the account's key (eg the EIP-7702 delegated EOA).
+    #[arg(long)]
+    install_to: Option<Address>,
+
+    /// bytes32 permission id to install under (Kernel PermissionId, left-aligned + zero-padded).
+    #[arg(long, env = "PERMISSION_ID", global = true)]
+    permission_id: Option<H256>,
+
+    /// Key of the Kernel account used for `--install-to` (defaults to the deployer key).
+    #[arg(long, env = "OWNER_PRIVATE_KEY")]
+    install_private_key: Option<String>,
+
+    /// Authorised envelope signer for the installed permission (defaults to the account).
+    #[arg(long, env = "POLICY_SIGNER_ADDRESS")]
+    policy_signer: Option<Address>,
+
+    /// StateView fact source for the installed permission.
+    #[arg(long, env = "STATE_VIEW_ADDRESS")]
+    state_view: Option<Address>,
+
+    /// VTSOrchestrator fact source for the installed permission.
+    #[arg(long, env = "VTS_ORCHESTRATOR_ADDRESS")]
+    vts_orchestrator: Option<Address>,
+
+    /// LiquidityHub fact source for the installed permission.
+    #[arg(long, env = "LIQUIDITY_HUB_ADDRESS")]
+    liquidity_hub: Option<Address>,
+
+    /// Record Arbiscan verification details after activation, and submit them when an API key is
+    /// set (status and GUID land under `arbiscan` in the deployments entry).
+    #[arg(long)]
+    arbiscan: bool,
+
+    /// Etherscan v2 API key used to submit the Arbiscan verification.
+    #[arg(long, env = "ARBISCAN_API_KEY", hide_env_values = true)]
+    arbiscan_api_key: Option<String>,
+
+    /// Etherscan-compatible API endpoint (chain selected via `chainid`).
+    #[arg(long, default_value = "https://api.etherscan.io/v2/api")]
+    arbiscan_api_url: String,
+
+    /// Etherscan license type id submitted with the source (14 = BUSL-1.1).
+    #[arg(long, default_value_t = 14)]
+    arbiscan_license_type: u8,
+
+    /// Place a Stylus CacheManager bid (in wei) for the program after activation.
+    ///
+    /// Cached programs skip the per-call init cost, which dominates `checkUserOpPolicy` gas.
+    #[arg(long, value_name = "WEI")]
+    cache_bid: Option<u128>,
+
+    /// Send ETH from the deployer key after deploying (`<address>:<amount in ETH>`, repeatable).
+    ///
+    /// Meant for devnet bootstrap (test wallets, paymaster deposits).
+    #[arg(long = "fund", value_name = "ADDRESS:AMOUNT")]
+    fund: Vec<fund::FundTarget>,
+
+    /// Deploy deterministically via CREATE2 with this bytes32 salt (requires `--stylus-deployer`).
+    ///
+    /// The same source, salt, and factory yield the same policy address on every network.
+    #[arg(long, env = "STYLUS_CREATE2_SALT")]
+    create2_salt: Option<H256>,
+
+    /// `StylusDeployer` factory address used for CREATE2 deploys and `predict-address`.
+    #[arg(long, env = "STYLUS_DEPLOYER_ADDRESS")]
+    stylus_deployer: Option<Address>,
+
+    /// Skip writing the exported ABI (`abi/<contract_key>.<codehash>.{sol,abi.json}`) next to the
+    /// deployments file.
+    #[arg(long)]
+    skip_abi_export: bool,
+
+    /// Also write `<KEY>_ADDRESS=0x...` lines for every recorded deployment to this `.env` file
+    /// (eg `.env.devnet`); other lines in the file are preserved.
+    #[arg(long, global = true)]
+    env_out: Option<PathBuf>,
+
+    /// Also write a markdown summary of the run (addresses, tx links, fees, WASM hash,
+    /// verification status) to this file, for release notes and ops channels.
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// Also write the run in Foundry's broadcast layout under this directory
+    /// (`<dir>/<contract_key>/<chainId>/run-latest.json`).
+    #[arg(long)]
+    foundry_broadcast: Option<PathBuf>,
+
+    /// Contract name recorded in the Foundry broadcast export.
+    #[arg(long, default_value = "IntentPolicy")]
+    contract_name: String,
+
+    /// Increase log verbosity (`-v` debug incl. full `cargo stylus` output, `-vv` trace).
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// On failure, print `{"error": {"class", "exit_code", "message", "causes"}}` on stdout
+    /// instead of the plain error. The exit code is 10 build-failed, 11 deploy-failed,
+    /// 12 activation-failed, 13 record-failed, and 1 for anything else.
+    #[arg(long, global = true)]
+    json_errors: bool,
+
+    /// Log output format (logs go to stderr; `RUST_LOG` overrides the level).
+    #[arg(long, value_enum, env = "DEPLOYER_LOG_FORMAT", default_value_t = logging::LogFormat::Text)]
+    log_format: logging::LogFormat,
 
     /// Extra args to pass through to `cargo stylus deploy` (after `--`).
     ///
@@ -57,205 +290,1524 @@ struct Cli {
     /// `-- --estimate-gas`
     #[arg(last = true)]
     passthrough: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Action>,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Print the address a CREATE2 deploy with `--salt` would land at (no transactions sent).
+    ///
+    /// Uses `--stylus-deployer` (or STYLUS_DEPLOYER_ADDRESS) as the factory.
+    PredictAddress {
+        /// bytes32 salt passed to `StylusDeployer`.
+        #[arg(long)]
+        salt: H256,
+    },
+    /// Deploy a new version of an already-deployed contract key.
+    ///
+    /// The new deployment becomes `latest`; earlier versions stay under `history` for rollback.
+    Upgrade,
+    /// Build the contract and report whether the recorded deployment is up to date, needs a
+    /// deploy, or has drifted from what was recorded. Sends no transactions.
+    Plan,
+    /// Read the installed config of a (wallet, permission id) back from the policy and compare it
+    /// with an expected config file, failing with a diff on drift.
+    ///
+    /// The expected file (JSON, or TOML by extension) has `signer`, `state_view`,
+    /// `vts_orchestrator`, and `liquidity_hub`. Uses `--permission-id` (or PERMISSION_ID).
+    VerifyConfig {
+        /// Kernel account the permission is installed on.
+        #[arg(long)]
+        wallet: Address,
+
+        /// Expected policy config.
+        #[arg(long)]
+        expected: PathBuf,
 
-    let (address, tx_hashes, raw_output) = run_cargo_stylus_deploy(&cli)?;
-    write_deployments_json(&cli, &address, &tx_hashes, &raw_output)?;
+        /// Policy address (defaults to the recorded `--contract-key` deployment).
+        #[arg(long)]
+        policy: Option<Address>,
+    },
+    /// Print the status of the deployed policy: code, activation, and (with `--wallet`) whether a
+    /// permission instance is installed and how many intents it consumed. `--permission-id` picks
+    /// the instance; without it the wallet's only installed permission is used.
+    ///
+    /// With `--follow`, keep polling and print every change and every policy log as it happens.
+    Status {
+        /// Keep watching after the first snapshot.
+        #[arg(long)]
+        follow: bool,
 
-    println!("Deployed `{}` to {}", cli.contract_key, address);
-    Ok(())
+        /// Kernel account whose permission instance to track (see `--permission-id`).
+        #[arg(long)]
+        wallet: Option<Address>,
+
+        /// Policy address (defaults to the recorded `--contract-key` deployment).
+        #[arg(long)]
+        policy: Option<Address>,
+
+        /// Seconds between polls with `--follow`.
+        #[arg(long, default_value_t = 2)]
+        interval_secs: u64,
+
+        /// Rewrite this JSON file with the latest status after every poll.
+        #[arg(long)]
+        status_file: Option<PathBuf>,
+    },
+    /// Restore a previously recorded version of `--contract-key` as the current deployment.
+    ///
+    /// Only the deployments file changes (nothing is sent on-chain); every rollback is appended to
+    /// `rollbacks` for the audit trail.
+    Rollback {
+        /// History version to restore (defaults to the one before the current version).
+        #[arg(long)]
+        to_version: Option<u64>,
+
+        /// Why the rollback happened (recorded in the audit trail).
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Print the Kernel `installModule(5, policy, permissionId || initData)` calldata for the
+    /// policy, to be sent from the Kernel account itself (eg `cast send <account> <calldata>`).
+    ///
+    /// Uses `--permission-id`, `--policy-signer`, `--state-view`, `--vts-orchestrator` and
+    /// `--liquidity-hub` (or their env vars). Sends nothing.
+    InstallCalldata {
+        /// Policy address (defaults to the recorded `--contract-key` deployment).
+        #[arg(long)]
+        policy: Option<Address>,
+    },
+    /// Write a Safe Transaction Builder batch for the deploy (and, with `--install-to`, the
+    /// install) instead of sending anything, for deployments governed by a multisig.
+    ///
+    /// The deploy is a `StylusDeployer.deploy` call (CREATE2 + activation in one transaction), so
+    /// `--create2-salt` and `--stylus-deployer` are required. The install call goes to the
+    /// `--install-to` account and only succeeds if that account accepts `installModule` from the
+    /// Safe. Nothing is recorded in the deployments file.
+    SafeProposal {
+        /// Safe that will execute the batch.
+        #[arg(long)]
+        safe: Address,
+
+        /// Wei attached to the deploy call to pay the activation data fee (see `--estimate-only`);
+        /// the factory refunds the excess to the Safe.
+        #[arg(long)]
+        activation_value: U256,
+
+        /// Write the batch here instead of stdout.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Check the environment before a build: the RPC responds, its chain id matches `--chain-id`,
+    /// the deployer account can pay the recorded `--estimate-only` fees, and cargo-stylus is recent
+    /// enough. Sends nothing; fails if any check fails.
+    Healthcheck,
+    /// Check the attestation the deployer embedded in the deployments file: the signature must
+    /// cover the current contents. Sends nothing.
+    VerifyAttestation {
+        /// Deployer address the file must be signed by (or `attestation_signer` in the profile).
+        #[arg(long)]
+        signer: Address,
+    },
+    /// Create funded test accounts on a devnet, funded from the deployer key, and write their
+    /// keys to `--out`.
+    ///
+    /// With `--delegate-to`, each account is delegated to that Kernel implementation (EIP-7702,
+    /// through Foundry's `cast send --auth`); `--install` then installs the recorded policy on each
+    /// account, using `--permission-id` and the fact-source flags like `--install-to`.
+    Faucet {
+        /// Number of accounts to create.
+        #[arg(long, default_value_t = 5)]
+        count: usize,
+
+        /// ETH sent to each account.
+        #[arg(long, default_value = "1")]
+        amount: String,
+
+        /// JSON file for the generated accounts and keys (written with mode 0600).
+        #[arg(long, default_value = "test-accounts.json")]
+        out: PathBuf,
+
+        /// Kernel implementation to delegate each account to.
+        #[arg(long)]
+        delegate_to: Option<Address>,
+
+        /// Install the policy on each delegated account.
+        #[arg(long, requires = "delegate_to")]
+        install: bool,
+    },
+    /// Rebuild the contract with `--locked` in a pinned toolchain container (Docker) and check
+    /// that the recorded deployment runs exactly that code, so signers can verify what they sign.
+    ///
+    /// The result is recorded under `reproducible_build` in the deployments entry; a mismatch
+    /// fails the command. Sends no transactions.
+    VerifyBuild,
+    /// Decode the UserOperations of a mined `EntryPoint.handleOps` transaction: the permission,
+    /// each policy's signature slice, and the intent envelope with its disassembled program and
+    /// the signer it recovers to. Sends nothing.
+    ///
+    /// The signer is recovered under the policy's default EIP-712 domain and compared with the
+    /// signer currently installed for the op's (wallet, permission id).
+    InspectTx {
+        /// Transaction hash of the `handleOps` call.
+        hash: H256,
+
+        /// Policy address (defaults to the recorded `--contract-key` deployment).
+        #[arg(long)]
+        policy: Option<Address>,
+    },
 }
 
-fn run_cargo_stylus_deploy(cli: &Cli) -> Result<(String, Vec<String>, String)> {
-    // Example output lines we parse (as shown in the repo README):
-    //   Deploying program to address 0x...
-    //   Confirmed tx 0x...
-    //
-    // Newer cargo-stylus versions tweak wording, so accept common variants.
-    let re_address_primary = Regex::new(
-        r"(?i)(?:Deploying program to address|Deployed program to address|Deployed contract to address|Contract deployed at|Program deployed at|Deployed code at address)\s*:?\s*(0x[a-fA-F0-9]{40})",
-    )?;
-    // Fallback: look for "address: 0x..." in deploy output.
-    let re_address_fallback = Regex::new(r"(?i)address\s*:?\s*(0x[a-fA-F0-9]{40})")?;
-    let re_any_address = Regex::new(r"0x[a-fA-F0-9]{40}")?;
-    let re_tx = Regex::new(
-        r"(?i)(?:Confirmed tx|deployment tx hash|contract activated and ready onchain with tx hash|activated.*tx hash)\s*:?\s*(0x[a-fA-F0-9]{64})",
-    )?;
-
-    let mut cmd = Command::new("cargo");
-    cmd.current_dir(&cli.contract_dir);
-    cmd.arg("stylus").arg("deploy");
-    cmd.arg("-e").arg(&cli.rpc_url);
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = match parse_cli() {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            return ExitCode::from(failure::EXIT_OTHER);
+        }
+    };
+    logging::init(cli.verbose, cli.log_format);
 
-    if let Some(ref pk_path) = cli.private_key_path {
-        cmd.arg("--private-key-path").arg(pk_path);
-    } else if let Some(ref pk) = cli.private_key {
-        cmd.arg("--private-key").arg(pk);
-    } else {
-        return Err(anyhow!(
-            "missing deployer key: provide --private-key-path or --private-key (or set PRIV_KEY_PATH/PKEY)"
-        ));
+    let json_errors = cli.json_errors;
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            if json_errors {
+                println!("{}", failure::error_json(&err));
+            } else {
+                eprintln!("Error: {err:?}");
+            }
+            ExitCode::from(failure::exit_code(&err))
+        }
     }
+}
 
-    // Keep stdout/stderr for parsing and for debugging when runs fail.
-    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+async fn run(mut cli: Cli) -> Result<()> {
+    if let Some(Action::PredictAddress { salt }) = cli.command {
+        return predict_address(&cli, salt).await;
+    }
 
-    // Allow passing flags like --estimate-gas, --mode, etc.
-    if !cli.passthrough.is_empty() {
-        // clap includes the leading `--` separator in last=true? It does not; it gives args after it.
-        cmd.args(&cli.passthrough);
+    if let Some(Action::Plan) = cli.command {
+        return run_plan(&cli).await;
     }
 
-    let output = cmd
-        .output()
-        .context("failed to run `cargo stylus deploy`")?;
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-    let combined = format!("{stdout}\n{stderr}");
+    if let Some(Action::VerifyConfig {
+        wallet,
+        ref expected,
+        policy,
+    }) = cli.command
+    {
+        return verify_config(&cli, wallet, expected, policy).await;
+    }
 
-    if cli.verbose {
-        eprintln!("--- cargo stylus deploy output ---\n{combined}\n--- end output ---");
+    if let Some(Action::Status {
+        follow,
+        wallet,
+        policy,
+        interval_secs,
+        ref status_file,
+    }) = cli.command
+    {
+        return run_status(
+            &cli,
+            follow,
+            wallet,
+            policy,
+            Duration::from_secs(interval_secs),
+            status_file.as_deref(),
+        )
+        .await;
     }
 
-    if !output.status.success() {
-        return Err(anyhow!(
-            "`cargo stylus deploy` failed (exit {}):\n{}",
-            output.status,
-            combined
-        ));
+    if let Some(Action::InstallCalldata { policy }) = cli.command {
+        return print_install_calldata(&cli, policy);
     }
 
-    let address = [re_address_primary, re_address_fallback]
-        .iter()
-        .find_map(|re| {
-            re.captures_iter(&combined)
-                .next()
-                .and_then(|c| c.get(1).map(|m| m.as_str().to_string()))
-        })
-        .or_else(|| {
-            // As a last resort, find the first 0x40 in any line mentioning deploy/address.
-            combined.lines().find_map(|line| {
-                let lower = line.to_ascii_lowercase();
-                if lower.contains("deploy") && lower.contains("address") {
-                    re_any_address
-                        .find(line)
-                        .map(|m| m.as_str().to_string())
-                } else {
+    if let Some(Action::SafeProposal {
+        safe,
+        activation_value,
+        ref out,
+    }) = cli.command
+    {
+        return write_safe_proposal(&cli, safe, activation_value, out.as_deref()).await;
+    }
+
+    if let Some(Action::VerifyAttestation { signer }) = cli.command {
+        return verify_attestation(&cli, signer);
+    }
+
+    if let Some(Action::VerifyBuild) = cli.command {
+        return verify_build(&cli).await;
+    }
+
+    if let Some(Action::InspectTx { hash, policy }) = cli.command {
+        return inspect_tx(&cli, hash, policy).await;
+    }
+
+    if let Some(Action::Rollback {
+        to_version,
+        ref reason,
+    }) = cli.command
+    {
+        recorder(&cli).rollback(to_version, reason.as_deref())?;
+        return write_env_out(&cli);
+    }
+
+    if let Some(ref path) = cli.keystore_path {
+        let unlocked = keystore::unlock(path, cli.keystore_password_file.as_deref())?;
+        info!(deployer = ?unlocked.address, "unlocked keystore");
+        cli.keystore = Some(unlocked);
+    }
+
+    if let Some(Action::Faucet {
+        count,
+        ref amount,
+        ref out,
+        delegate_to,
+        install,
+    }) = cli.command
+    {
+        return run_faucet(&cli, count, amount, out, delegate_to, install).await;
+    }
+
+    if let Some(Action::Healthcheck) = cli.command {
+        return run_healthcheck(&cli).await;
+    }
+
+    let stylus = cargo_stylus(&cli);
+    let recorder = recorder(&cli);
+
+    if cli.estimate_only {
+        let estimate = deploy::cargo_stylus_estimate(&stylus, &cli.passthrough)?;
+        recorder.write_estimate(&estimate)?;
+        info!(
+            "estimated `{}` deployment: {} gas, {} ETH (+ {} ETH activation data fee)",
+            cli.contract_key,
+            estimate
+                .deployment_gas
+                .map_or("?".to_string(), |g| g.to_string()),
+            estimate.deployment_cost_eth.as_deref().unwrap_or("?"),
+            estimate.data_fee_eth.as_deref().unwrap_or("?"),
+        );
+        return Ok(());
+    }
+
+    let upgrade_from = match cli.command {
+        Some(Action::Upgrade) => Some(recorder.latest()?.ok_or_else(|| {
+            anyhow!(
+                "nothing to upgrade: no `{}` deployment in {} (run a plain deploy first)",
+                cli.contract_key,
+                cli.deployments_path.display()
+            )
+        })?),
+        _ => None,
+    };
+
+    let simulation = match cli.simulate_rpc {
+        Some(ref fork_rpc) => Some(
+            run_simulation(&cli, fork_rpc)
+                .await
+                .context("fork simulation failed; not deploying")?,
+        ),
+        None => None,
+    };
+
+    let create2 = match cli.create2_salt {
+        Some(salt) => Some(prepare_create2(&cli, salt)?),
+        None => None,
+    };
+
+    let (deploy, activation) = if cli.ledger {
+        run_ledger_deploy(&cli)
+            .await
+            .classify(FailureClass::DeployFailed)?
+    } else if cli.direct {
+        run_direct_deploy(&cli)
+            .await
+            .classify(FailureClass::DeployFailed)?
+    } else {
+        let retry = deploy::SendRetry {
+            retries: cli.send_retries,
+            fee_bump_percent: cli.fee_bump_percent,
+        };
+        let deploy =
+            deploy::cargo_stylus_deploy(&stylus, create2.as_ref(), retry, &cli.passthrough)
+                .await
+                .classify(FailureClass::DeployFailed)?;
+        let activation = match deploy.activation_tx {
+            Some(ref tx) => Activation::by_deploy(tx.clone()),
+            None => activation::cargo_stylus_activate(
+                &stylus,
+                &deploy.address,
+                cli.activation_retries,
+                Duration::from_secs(cli.activation_backoff_secs),
+            )
+            .classify(FailureClass::ActivationFailed)?,
+        };
+        (deploy, activation)
+    };
+    if let Some(ref c) = create2 {
+        let deployed: Option<Address> = deploy.address.parse().ok();
+        if deployed != Some(c.predicted_address) {
+            return Err(anyhow!(
+                "CREATE2 deploy landed at {} but {:?} was predicted (factory {:?}, salt {:?}); not recording",
+                deploy.address,
+                c.predicted_address,
+                c.factory,
+                c.salt
+            )
+            .context(FailureClass::DeployFailed));
+        }
+    }
+
+    let activated = activation.status != ActivationStatus::Failed;
+    let wasm_hash = wasm::local_hash(cli.wasm_path.as_deref(), &cli.contract_dir)
+        .classify(FailureClass::RecordFailed)?;
+    let code_hash = deploy.code_hash(&cli.rpc_url).await;
+    let fees = deploy.fees(&cli.rpc_url, &activation).await;
+    let abi = match code_hash {
+        Some(hash) if !cli.skip_abi_export => {
+            let out_dir = cli
+                .deployments_path
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new("."));
+            match abi_export::export(&cli.contract_dir, out_dir, &cli.contract_key, hash) {
+                Ok(files) => Some(files),
+                Err(err) => {
+                    warn!("ABI export failed: {err:#}");
                     None
                 }
-            })
-        })
-        .ok_or_else(|| {
-            let trimmed = combined.trim();
-            let max = 4000usize;
-            let snippet = if trimmed.len() > max {
-                &trimmed[..max]
-            } else {
-                trimmed
-            };
-            anyhow!(
-                "could not parse deployed address from `cargo stylus deploy` output. Output (truncated):\n{}",
-                snippet
+            }
+        }
+        _ => None,
+    };
+    let verification = if cli.verify && activated {
+        Some(verify::cargo_stylus_verify(
+            &stylus,
+            &deploy.tx_hashes,
+            &recorder.redactor,
+        ))
+    } else {
+        None
+    };
+    let reproducible_build = if cli.reproduce && activated {
+        Some(verify::reproduce(
+            &cli.contract_dir,
+            &cli.contract_key,
+            cli.reproduce_image.as_deref(),
+            code_hash,
+            &recorder.redactor,
+        ))
+    } else {
+        None
+    };
+    // An inactive program rejects every call, so only smoke test once activation succeeded.
+    let smoke_test = if !cli.skip_smoke_test && activated {
+        Some(verify::smoke_test(&cli.rpc_url, &deploy.address).await)
+    } else {
+        None
+    };
+
+    let arbiscan = if cli.arbiscan && activated {
+        Some(
+            verify::arbiscan(
+                &cli.contract_dir,
+                &cli.rpc_url,
+                &deploy.address,
+                &arbiscan_options(&cli),
             )
-        })?;
+            .await,
+        )
+    } else {
+        None
+    };
 
-    let tx_hashes: Vec<String> = re_tx
-        .captures_iter(&combined)
-        .filter_map(|c| c.get(1))
-        .map(|m| m.as_str().to_string())
-        .collect();
+    let cache_bid = match cli.cache_bid {
+        Some(bid) if activated => Some(activation::cargo_stylus_cache_bid(
+            &stylus,
+            &deploy.address,
+            bid,
+        )),
+        _ => None,
+    };
 
-    Ok((address, tx_hashes, combined))
+    let smoke_test_passed = smoke_test.as_ref().is_none_or(|t| t.error.is_none());
+    let install = match cli.install_to {
+        Some(account) if activated && smoke_test_passed => {
+            Some(run_install(&cli, &deploy.address, account).await)
+        }
+        _ => None,
+    };
+
+    let funding = if cli.fund.is_empty() {
+        Vec::new()
+    } else {
+        fund::fund_recorded(&cli.rpc_url, deployer_private_key(&cli), &cli.fund).await
+    };
+
+    let record = DeploymentRecord {
+        deploy,
+        upgrade_from,
+        create2,
+        activation,
+        wasm_hash,
+        code_hash,
+        fees,
+        abi,
+        simulation,
+        verification,
+        reproducible_build,
+        smoke_test,
+        arbiscan,
+        cache_bid,
+        install,
+        funding,
+    };
+    recorder
+        .write(&record)
+        .classify(FailureClass::RecordFailed)?;
+    write_env_out(&cli).classify(FailureClass::RecordFailed)?;
+    if let Some(ref path) = cli.report {
+        recorder
+            .write_report(path)
+            .await
+            .classify(FailureClass::RecordFailed)?;
+        info!(path = %path.display(), "wrote deployment report");
+    }
+    if let Some(ref dir) = cli.foundry_broadcast {
+        let path = recorder
+            .export_foundry_broadcast(dir, &cli.contract_name, &record.deploy)
+            .await
+            .classify(FailureClass::RecordFailed)?;
+        info!(path = %path.display(), "wrote Foundry broadcast artifact");
+    }
+    let DeploymentRecord {
+        deploy,
+        activation,
+        verification,
+        reproducible_build,
+        smoke_test,
+        install,
+        ..
+    } = record;
+    let address = &deploy.address;
+
+    if activation.status == ActivationStatus::Failed {
+        return Err(anyhow!(
+            "deployed `{}` to {} but activation failed after {} attempt(s) (recorded in {}): {}",
+            cli.contract_key,
+            address,
+            activation.attempts,
+            cli.deployments_path.display(),
+            activation.last_error.as_deref().unwrap_or("unknown error")
+        )
+        .context(FailureClass::ActivationFailed));
+    }
+
+    if let Some(SmokeTest {
+        error: Some(ref err),
+    }) = smoke_test
+    {
+        return Err(anyhow!(
+            "deployed `{}` to {} but the smoke test failed (recorded in {}): {}",
+            cli.contract_key,
+            address,
+            cli.deployments_path.display(),
+            err
+        ));
+    }
+
+    info!("deployed `{}` to {}", cli.contract_key, address);
+    if let Some(ref i) = install {
+        match i.tx_hash {
+            Some(ref tx) => info!(
+                "installed `{}` on {:?} under permission {:?} (tx {})",
+                cli.contract_key, i.account, i.permission_id, tx
+            ),
+            None => {
+                return Err(anyhow!(
+                    "deployed `{}` to {} but installing on {:?} failed (recorded in {}): {}",
+                    cli.contract_key,
+                    address,
+                    i.account,
+                    cli.deployments_path.display(),
+                    i.error.as_deref().unwrap_or("unknown error")
+                ))
+            }
+        }
+    }
+    if let Some(ref v) = verification {
+        if let Some(ref err) = v.error {
+            return Err(anyhow!(
+                "deployed `{}` to {} but `cargo stylus verify` could not run (recorded in {}): {}",
+                cli.contract_key,
+                address,
+                cli.deployments_path.display(),
+                err
+            ));
+        }
+        if !v.verified {
+            return Err(anyhow!(
+                "`cargo stylus verify` did not match the deployed code at {} (result recorded in {})",
+                address,
+                cli.deployments_path.display()
+            ));
+        }
+        info!(
+            "verified `{}` against the local source tree",
+            cli.contract_key
+        );
+    }
+    if let Some(ref r) = reproducible_build {
+        r.ensure_matches(&cli.contract_key, &cli.deployments_path, address)?;
+    }
+    Ok(())
 }
 
-fn write_deployments_json(
+/// Parse the CLI, using the selected network profile (if any) as the argument defaults.
+fn parse_cli() -> Result<Cli> {
+    // First pass only to learn `--network` / `--config`; errors surface in the real parse below.
+    let pre = Cli::command().ignore_errors(true).get_matches();
+    let network = pre
+        .get_one::<String>("network")
+        .cloned()
+        .unwrap_or_else(|| "devnet".to_string());
+    let explicit = pre.get_one::<PathBuf>("config").cloned();
+    let path = explicit
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(config::DEFAULT_CONFIG_PATH));
+
+    let mut cmd = Cli::command();
+    if explicit.is_some() || path.exists() {
+        let mut file = config::load(&path)?;
+        match file.profiles.remove(&network) {
+            Some(profile) => {
+                for (id, value) in profile.defaults() {
+                    cmd = cmd.mut_arg(id, |a| a.default_value(value).required(false));
+                }
+                if let Some(signer) = profile.attestation_signer {
+                    cmd = cmd.mut_subcommand("verify-attestation", |s| {
+                        s.mut_arg("signer", |a| a.default_value(signer).required(false))
+                    });
+                }
+            }
+            None if explicit.is_some() => {
+                return Err(anyhow!("no [profiles.{network}] in {}", path.display()));
+            }
+            None => {}
+        }
+    }
+
+    // Rolling back only edits the deployments file, so it should not demand an RPC URL.
+    if pre.subcommand_name() == Some("rollback") {
+        cmd = cmd.mut_arg("rpc_url", |a| a.required(false).default_value(""));
+    }
+
+    let mut matches = cmd.get_matches();
+    Ok(Cli::from_arg_matches_mut(&mut matches)?)
+}
+
+#[instrument(name = "install", skip(cli))]
+async fn run_faucet(
     cli: &Cli,
-    address: &str,
-    tx_hashes: &[String],
-    raw_output: &str,
+    count: usize,
+    amount: &str,
+    out: &Path,
+    delegate_to: Option<Address>,
+    install: bool,
 ) -> Result<()> {
-    let now = OffsetDateTime::now_utc()
-        .format(&Rfc3339)
-        .unwrap_or_else(|_| "unknown".to_string());
-
-    let existing = if cli.deployments_path.exists() {
-        fs::read_to_string(&cli.deployments_path)
-            .with_context(|| format!("failed reading {}", cli.deployments_path.display()))?
+    let amount =
+        parse_ether(amount.trim()).with_context(|| format!("invalid ETH amount {amount}"))?;
+    let install = if install {
+        let missing = |name: &str| anyhow!("faucet --install requires --{name}");
+        let permission_id = cli
+            .permission_id
+            .ok_or_else(|| missing("permission-id (or PERMISSION_ID)"))?;
+        let config = install::PolicyInitConfig {
+            signer: cli.policy_signer.unwrap_or_default(),
+            state_view: cli.state_view.ok_or_else(|| missing("state-view"))?,
+            vts_orchestrator: cli
+                .vts_orchestrator
+                .ok_or_else(|| missing("vts-orchestrator"))?,
+            liquidity_hub: cli.liquidity_hub.ok_or_else(|| missing("liquidity-hub"))?,
+        };
+        Some((recorded_policy(cli)?, permission_id, config))
     } else {
-        String::new()
+        None
     };
 
-    let mut root: Value = if existing.trim().is_empty() {
-        json!({})
-    } else {
-        serde_json::from_str(&existing)
-            .with_context(|| format!("failed parsing JSON in {}", cli.deployments_path.display()))?
+    let wallets = faucet::generate(count);
+    let mut accounts: Vec<faucet::TestAccount> =
+        wallets.iter().map(faucet::TestAccount::new).collect();
+    let targets: Vec<fund::FundTarget> = accounts
+        .iter()
+        .map(|a| fund::FundTarget {
+            address: a.address,
+            amount,
+        })
+        .collect();
+    let funding = fund::fund(
+        rpc::provider(&cli.rpc_url)?,
+        &deployer_private_key(cli)?,
+        &targets,
+    )
+    .await?;
+
+    for (account, funded) in accounts.iter_mut().zip(funding) {
+        account.funding_tx = funded.tx_hash;
+        account.error = funded.error;
+        let Some(kernel) = delegate_to.filter(|_| account.error.is_none()) else {
+            continue;
+        };
+        let result = async {
+            account.delegation_tx = Some(faucet::delegate(
+                &cli.rpc_url,
+                &account.private_key,
+                kernel,
+            )?);
+            if let Some((policy, permission_id, mut config)) = install {
+                // Like `--install-to`, the account is its own envelope signer by default.
+                if cli.policy_signer.is_none() {
+                    config.signer = account.address;
+                }
+                let calldata = install::install_module_calldata(policy, permission_id, &config);
+                account.install_tx = Some(
+                    install::submit_install(
+                        rpc::provider(&cli.rpc_url)?,
+                        &account.private_key,
+                        account.address,
+                        calldata,
+                    )
+                    .await?,
+                );
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        account.error = result.err().map(|e| format!("{e:#}"));
+    }
+
+    faucet::write_accounts(out, &accounts)?;
+    let failed = accounts.iter().filter(|a| a.error.is_some()).count();
+    for a in &accounts {
+        match a.error {
+            None => info!("{:?} ready", a.address),
+            Some(ref e) => warn!("{:?}: {e}", a.address),
+        }
+    }
+    info!(
+        "wrote {} test accounts ({failed} failed) to {}",
+        accounts.len(),
+        out.display()
+    );
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} of {count} test accounts were not fully provisioned"
+        ));
+    }
+    Ok(())
+}
+
+async fn run_install(cli: &Cli, policy: &str, account: Address) -> Install {
+    let result = async {
+        let permission_id = cli
+            .permission_id
+            .ok_or_else(|| anyhow!("--install-to requires --permission-id (or PERMISSION_ID)"))?;
+        let missing = |name: &str| anyhow!("--install-to requires --{name}");
+        let account_key = match cli.install_private_key {
+            Some(ref k) => k.clone(),
+            None => deployer_private_key(cli)?,
+        };
+        let config = install::PolicyInitConfig {
+            signer: cli.policy_signer.unwrap_or(account),
+            state_view: cli.state_view.ok_or_else(|| missing("state-view"))?,
+            vts_orchestrator: cli
+                .vts_orchestrator
+                .ok_or_else(|| missing("vts-orchestrator"))?,
+            liquidity_hub: cli.liquidity_hub.ok_or_else(|| missing("liquidity-hub"))?,
+        };
+        let policy: Address = policy
+            .parse()
+            .with_context(|| format!("invalid deployed address {policy}"))?;
+
+        let calldata = install::install_module_calldata(policy, permission_id, &config);
+        let provider = rpc::provider(&cli.rpc_url)?;
+        install::submit_install(provider, &account_key, account, calldata).await
+    }
+    .await;
+
+    Install::new(account, cli.permission_id, result)
+}
+
+async fn run_plan(cli: &Cli) -> Result<()> {
+    let init_code = cargo_stylus(cli)
+        .initcode()
+        .classify(FailureClass::BuildFailed)?;
+    let local = plan::local_code_hash(&init_code)
+        .ok_or_else(|| anyhow!("`cargo stylus get-initcode` output has no Stylus code prefix"))?;
+
+    let file = deployments::load(&cli.deployments_path)?;
+    let entry = file.deployments.get(&cli.contract_key);
+    let address: Option<Address> = entry.and_then(|e| e.address.parse().ok());
+    let recorded: Option<H256> = entry.and_then(|e| e.code_hash);
+
+    let status = match address {
+        None => plan::PlanStatus::NeedsDeploy("no recorded deployment"),
+        Some(address) => {
+            let provider = rpc::provider(&cli.rpc_url)?;
+            let onchain = rpc::code_hash(&provider, address).await?;
+            plan::classify(local, onchain, recorded)
+        }
     };
 
-    // Ensure root object
-    if !root.is_object() {
-        root = json!({});
+    println!(
+        "{} ({}): {}",
+        cli.contract_key,
+        cli.network,
+        status.as_str()
+    );
+    if let plan::PlanStatus::NeedsDeploy(reason) = status {
+        println!("  reason:    {reason}");
+    }
+    println!("  local:     {local:?}");
+    if let Some(address) = address {
+        println!("  address:   {address:?}");
+    }
+    if let Some(recorded) = recorded {
+        println!("  recorded:  {recorded:?}");
     }
+    Ok(())
+}
 
-    // root.network / root.updated_at
-    root["network"] = json!(cli.network);
-    root["updated_at"] = json!(now);
+async fn inspect_tx(cli: &Cli, hash: H256, policy: Option<Address>) -> Result<()> {
+    // Without a policy address the envelope still decodes; only the signer cannot be recovered.
+    let policy = policy.or_else(|| recorded_policy(cli).ok());
+    let provider = rpc::provider(&cli.rpc_url)?;
+    let ops = inspect::inspect_tx(&provider, hash, policy, &PolicyDomain::default()).await?;
 
-    // root.deployments[contract_key] = { address, tx hashes, ... }
-    if root.get("deployments").and_then(Value::as_object).is_none() {
-        root["deployments"] = json!({});
+    println!("{hash:?}: {} UserOperation(s)", ops.len());
+    for (i, op) in ops.iter().enumerate() {
+        println!("op {i}: {:?}", op.user_op_hash);
+        println!("  sender:      {:?}", op.user_op.sender);
+        println!("  nonce:       {:#x}", op.user_op.nonce);
+        let Some(permission_id) = op.permission_id else {
+            println!(
+                "  validation:  type {:#04x} (not a permission; nothing to decode)",
+                op.validation_type
+            );
+            continue;
+        };
+        let mode = if op.validation_mode == VALIDATION_MODE_ENABLE {
+            " (enable mode)"
+        } else {
+            ""
+        };
+        println!("  permission:  {permission_id:?}{mode}");
+        for (index, sig) in &op.policy_signatures {
+            println!("  policy {index:>3}:  {} byte signature", sig.len());
+        }
+        println!("  signer sig:  {}", op.signer_signature);
+
+        let Some(ref intent) = op.intent else {
+            println!("  intent:      no policy signature decodes as an envelope");
+            continue;
+        };
+        let envelope = &intent.envelope;
+        let bundle_hash = keccak256(&op.user_op.call_data);
+        println!("  intent:      policy index {}", intent.policy_index);
+        println!("    version:   {}", envelope.version);
+        println!("    nonce:     {}", envelope.nonce);
+        println!("    deadline:  {}", envelope.deadline);
+        println!(
+            "    bundle:    {} ({})",
+            envelope.call_bundle_hash,
+            if bundle_hash == envelope.call_bundle_hash {
+                "matches callData"
+            } else {
+                "does NOT match callData"
+            }
+        );
+        if let Some(digest) = intent.digest {
+            println!("    digest:    {digest:?}");
+        }
+        match (intent.signer, policy) {
+            (Some(signer), Some(policy)) => {
+                let installed =
+                    rpc::policy_config(&provider, policy, op.user_op.sender, permission_id)
+                        .await
+                        .map(|config| config.signer);
+                match installed {
+                    Ok(installed) if installed == signer => {
+                        println!("    signer:    {signer:?} (the installed signer)")
+                    }
+                    Ok(installed) => println!(
+                        "    signer:    {signer:?} (installed signer is {installed:?}; a custom \
+                         domain or a reinstall also changes this)"
+                    ),
+                    Err(err) => println!("    signer:    {signer:?} (installed signer: {err:#})"),
+                }
+            }
+            (None, Some(_)) => println!("    signer:    not an ECDSA signature (passkey?)"),
+            (_, None) => println!("    signer:    unknown (pass --policy to recover it)"),
+        }
+        println!(
+            "    program:   {} check(s), hash {}",
+            intent.checks.len(),
+            keccak256(&envelope.program_bytes)
+        );
+        for line in inspect::disassemble(&intent.checks) {
+            println!("    {line}");
+        }
     }
+    Ok(())
+}
+
+fn print_install_calldata(cli: &Cli, policy: Option<Address>) -> Result<()> {
+    let missing = |name: &str| anyhow!("install-calldata requires --{name}");
+    let permission_id = cli
+        .permission_id
+        .ok_or_else(|| missing("permission-id (or PERMISSION_ID)"))?;
+    let config = install::PolicyInitConfig {
+        signer: cli.policy_signer.ok_or_else(|| missing("policy-signer"))?,
+        state_view: cli.state_view.ok_or_else(|| missing("state-view"))?,
+        vts_orchestrator: cli
+            .vts_orchestrator
+            .ok_or_else(|| missing("vts-orchestrator"))?,
+        liquidity_hub: cli.liquidity_hub.ok_or_else(|| missing("liquidity-hub"))?,
+    };
+    let policy = match policy {
+        Some(p) => p,
+        None => recorded_policy(cli)?,
+    };
+    let calldata = install::install_module_calldata(policy, permission_id, &config);
+    println!("{calldata}");
+    Ok(())
+}
+
+async fn write_safe_proposal(
+    cli: &Cli,
+    safe: Address,
+    activation_value: U256,
+    out: Option<&Path>,
+) -> Result<()> {
+    let salt = cli
+        .create2_salt
+        .ok_or_else(|| anyhow!("safe-proposal requires --create2-salt (or STYLUS_CREATE2_SALT)"))?;
+    let factory = cli.stylus_deployer.ok_or_else(|| {
+        anyhow!("safe-proposal requires --stylus-deployer (or STYLUS_DEPLOYER_ADDRESS)")
+    })?;
+    let init_code = cargo_stylus(cli)
+        .initcode()
+        .classify(FailureClass::BuildFailed)?;
+    let policy = create2::predict_address(factory, salt, &init_code);
+    let mut calls = vec![safe::factory_deploy(
+        factory,
+        &init_code,
+        salt,
+        activation_value,
+    )];
+
+    if let Some(account) = cli.install_to {
+        let missing = |name: &str| anyhow!("safe-proposal with --install-to requires --{name}");
+        let permission_id = cli
+            .permission_id
+            .ok_or_else(|| missing("permission-id (or PERMISSION_ID)"))?;
+        let config = install::PolicyInitConfig {
+            signer: cli.policy_signer.unwrap_or(account),
+            state_view: cli.state_view.ok_or_else(|| missing("state-view"))?,
+            vts_orchestrator: cli
+                .vts_orchestrator
+                .ok_or_else(|| missing("vts-orchestrator"))?,
+            liquidity_hub: cli.liquidity_hub.ok_or_else(|| missing("liquidity-hub"))?,
+        };
+        let calldata = install::install_module_calldata(policy, permission_id, &config);
+        calls.push(safe::account_call(account, calldata));
+    }
+
+    let chain_id = rpc::provider(&cli.rpc_url)?
+        .get_chainid()
+        .await
+        .context("failed fetching chain id")?
+        .as_u64();
+    let created_at_ms = (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as u64;
+    let batch = safe::transaction_builder_batch(
+        chain_id,
+        safe,
+        &format!("Deploy {}", cli.contract_key),
+        &format!(
+            "CREATE2 deploy of `{}` to {:?} via StylusDeployer {:?} (salt {:?})",
+            cli.contract_key, policy, factory, salt
+        ),
+        &calls,
+        created_at_ms,
+    );
+    let rendered = serde_json::to_string_pretty(&batch)?;
+    match out {
+        Some(path) => {
+            fs::write(path, format!("{rendered}\n"))
+                .with_context(|| format!("failed writing {}", path.display()))?;
+            info!(
+                "wrote Safe batch ({} transactions) for {:?} to {}",
+                calls.len(),
+                policy,
+                path.display()
+            );
+        }
+        None => println!("{rendered}"),
+    }
+    Ok(())
+}
 
-    let mut entry = json!({
-        "address": address,
-        "rpc_url": cli.rpc_url,
-        "deployed_at": now,
+async fn run_healthcheck(cli: &Cli) -> Result<()> {
+    let mut checks = Vec::new();
+    let provider = rpc::provider(&cli.rpc_url)?;
+    match provider.get_chainid().await {
+        Ok(id) => {
+            let block = provider
+                .get_block_number()
+                .await
+                .map_or("?".to_string(), |b| b.to_string());
+            checks.push(healthcheck::Check::pass(
+                "rpc",
+                format!("responds (block {block})"),
+            ));
+            checks.push(healthcheck::chain_id(id.as_u64(), cli.chain_id));
+
+            let required = deployments::load(&cli.deployments_path)
+                .ok()
+                .and_then(|f| f.estimates.get(&cli.contract_key).cloned())
+                .filter(|e| e.network == cli.network)
+                .and_then(|e| healthcheck::estimated_fees(&e));
+            let balance = async {
+                let deployer = deployer_address(cli, id.as_u64()).await?;
+                let balance = provider
+                    .get_balance(deployer, None)
+                    .await
+                    .context("failed fetching balance")?;
+                Ok::<_, anyhow::Error>((deployer, balance))
+            }
+            .await;
+            checks.push(match balance {
+                Ok((deployer, balance)) => {
+                    let mut check = healthcheck::balance(balance, required);
+                    check.detail = format!("{deployer:?}: {}", check.detail);
+                    check
+                }
+                Err(e) => healthcheck::Check::fail("balance", format!("{e:#}")),
+            });
+        }
+        Err(e) => checks.push(healthcheck::Check::fail(
+            "rpc",
+            redactor(cli).scrub(&format!("{e}")),
+        )),
+    }
+
+    let version = Command::new("cargo").args(["stylus", "--version"]).output();
+    checks.push(match version {
+        Ok(out) if out.status.success() => {
+            healthcheck::cargo_stylus_version(&String::from_utf8_lossy(&out.stdout))
+        }
+        Ok(out) => healthcheck::Check::fail(
+            "cargo-stylus",
+            format!("`cargo stylus --version` exited with {}", out.status),
+        ),
+        Err(e) => healthcheck::Check::fail("cargo-stylus", format!("failed to run cargo: {e}")),
     });
 
-    if !tx_hashes.is_empty() {
-        entry["tx_hashes"] = json!(tx_hashes);
+    for check in &checks {
+        println!("{check}");
+    }
+    let failed = checks.iter().filter(|c| !c.ok).count();
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} of {} healthchecks failed on {}",
+            checks.len(),
+            cli.network
+        ));
+    }
+    Ok(())
+}
+
+/// Address of the deployer key (asks the Ledger with `--ledger`).
+async fn deployer_address(cli: &Cli, chain_id: u64) -> Result<Address> {
+    if cli.ledger {
+        return Ok(ledger_signer(cli, chain_id).await?.address());
     }
+    let wallet: LocalWallet = deployer_private_key(cli)?
+        .parse()
+        .context("invalid deployer key")?;
+    Ok(wallet.address())
+}
+
+fn verify_attestation(cli: &Cli, expected: Address) -> Result<()> {
+    let file = deployments::load(&cli.deployments_path)?;
+    deployments::verify_attestation(&file, expected)
+        .with_context(|| format!("{} failed verification", cli.deployments_path.display()))?;
+    println!("{expected:?}");
+    Ok(())
+}
 
-    // Preserve raw output for audit/debugging, but truncate so we don't bloat git history.
-    // (Still useful when a devnet deployment behaves unexpectedly.)
-    let trimmed = raw_output.trim();
-    if !trimmed.is_empty() {
-        let max = 16_000usize;
-        let s = if trimmed.len() > max {
-            &trimmed[..max]
+async fn verify_config(
+    cli: &Cli,
+    wallet: Address,
+    expected_path: &Path,
+    policy: Option<Address>,
+) -> Result<()> {
+    let permission_id = cli
+        .permission_id
+        .ok_or_else(|| anyhow!("verify-config requires --permission-id (or PERMISSION_ID)"))?;
+    let policy = match policy {
+        Some(p) => p,
+        None => recorded_policy(cli)?,
+    };
+
+    let raw = fs::read_to_string(expected_path)
+        .with_context(|| format!("failed reading {}", expected_path.display()))?;
+    let expected: install::PolicyInitConfig =
+        if expected_path.extension().is_some_and(|e| e == "toml") {
+            toml::from_str(&raw)
+                .with_context(|| format!("failed parsing {}", expected_path.display()))?
         } else {
-            trimmed
+            serde_json::from_str(&raw)
+                .with_context(|| format!("failed parsing {}", expected_path.display()))?
+        };
+
+    let provider = rpc::provider(&cli.rpc_url)?;
+    let onchain = rpc::policy_config(&provider, policy, wallet, permission_id).await?;
+    if onchain == expected {
+        info!("config for {wallet:?} / {permission_id:?} on {policy:?} matches");
+        return Ok(());
+    }
+
+    let mut diff = String::new();
+    for (field, want, got) in [
+        ("signer", expected.signer, onchain.signer),
+        ("state_view", expected.state_view, onchain.state_view),
+        (
+            "vts_orchestrator",
+            expected.vts_orchestrator,
+            onchain.vts_orchestrator,
+        ),
+        (
+            "liquidity_hub",
+            expected.liquidity_hub,
+            onchain.liquidity_hub,
+        ),
+    ] {
+        if want != got {
+            diff.push_str(&format!("\n  {field}: expected {want:?}, on-chain {got:?}"));
+        }
+    }
+    if onchain.state_view.is_zero() {
+        diff.push_str("\n  (permission is not installed for this wallet)");
+    }
+    Err(anyhow!(
+        "config drift for {wallet:?} / {permission_id:?} on {policy:?}:{diff}"
+    ))
+}
+
+/// Address of the recorded `--contract-key` deployment.
+fn recorded_policy(cli: &Cli) -> Result<Address> {
+    recorder(cli)
+        .latest()?
+        .ok_or_else(|| {
+            anyhow!(
+                "no `{}` deployment in {}; pass --policy",
+                cli.contract_key,
+                cli.deployments_path.display()
+            )
+        })?
+        .address
+        .parse()
+        .context("recorded policy address is invalid")
+}
+
+async fn run_status(
+    cli: &Cli,
+    follow: bool,
+    wallet: Option<Address>,
+    policy: Option<Address>,
+    interval: Duration,
+    status_file: Option<&Path>,
+) -> Result<()> {
+    let policy = match policy {
+        Some(p) => p,
+        None => recorded_policy(cli)?,
+    };
+    let provider = rpc::provider(&cli.rpc_url)?;
+    let instance = match (wallet, cli.permission_id) {
+        (Some(wallet), Some(permission_id)) => Some(status::Instance {
+            wallet,
+            permission_id,
+        }),
+        (Some(wallet), None) => {
+            let ids = rpc::permission_ids_of(&provider, policy, wallet).await?;
+            match ids.as_slice() {
+                [permission_id] => Some(status::Instance {
+                    wallet,
+                    permission_id: *permission_id,
+                }),
+                [] => {
+                    return Err(anyhow!(
+                        "no permission is installed for {wallet:?} on {policy:?}"
+                    ))
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "{wallet:?} has {} permissions installed ({}); pick one with \
+                         --permission-id (or PERMISSION_ID)",
+                        ids.len(),
+                        ids.iter()
+                            .map(|id| format!("{id:?}"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                }
+            }
+        }
+        (None, _) => None,
+    };
+
+    let mut current = status::snapshot(&provider, policy, instance).await?;
+    print_status(cli, &current);
+    write_status_file(status_file, &current)?;
+    if !follow {
+        return Ok(());
+    }
+
+    loop {
+        tokio::time::sleep(interval).await;
+        let mut next = match status::snapshot(&provider, policy, instance).await {
+            Ok(next) => next,
+            Err(err) => {
+                warn!("status poll failed: {err:#}");
+                continue;
+            }
         };
-        entry["cargo_stylus_output"] = json!(s);
+        if next.block <= current.block {
+            continue;
+        }
+
+        next.logs_seen = current.logs_seen;
+        match status::policy_logs(&provider, policy, current.block + 1, next.block).await {
+            Ok(logs) => {
+                for log in &logs {
+                    println!(
+                        "[block {}] log {} (tx {})",
+                        log.block_number.map(|b| b.as_u64()).unwrap_or(next.block),
+                        log.topics
+                            .first()
+                            .map(|t| format!("{t:?}"))
+                            .unwrap_or_else(|| "(anonymous)".to_string()),
+                        log.transaction_hash
+                            .map(|t| format!("{t:?}"))
+                            .unwrap_or_else(|| "?".to_string())
+                    );
+                }
+                next.logs_seen += logs.len() as u64;
+            }
+            Err(err) => warn!("{err:#}"),
+        }
+        for change in status::changes(&current, &next) {
+            println!("[block {}] {change}", next.block);
+        }
+        write_status_file(status_file, &next)?;
+        current = next;
     }
+}
 
-    root["deployments"][&cli.contract_key] = entry;
+fn print_status(cli: &Cli, s: &status::PolicyStatus) {
+    println!(
+        "{} ({}) at {:?}, block {}",
+        cli.contract_key, cli.network, s.policy, s.block
+    );
+    match s.code_hash {
+        Some(hash) => println!("  code:      {hash:?}"),
+        None => println!("  code:      none"),
+    }
+    match s.program_version {
+        Some(v) => println!("  activated: yes (version {v})"),
+        None => println!("  activated: no"),
+    }
+    if let Some(ref i) = s.instance {
+        println!(
+            "  instance:  {:?} / {:?}",
+            i.instance.wallet, i.instance.permission_id
+        );
+        if i.installed {
+            println!("  installed: yes (signer {:?})", i.signer);
+        } else {
+            println!("  installed: no");
+        }
+        println!("  consumed:  {} intent(s)", i.nonce);
+    }
+}
+
+fn write_status_file(path: Option<&Path>, s: &status::PolicyStatus) -> Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let json = serde_json::to_string_pretty(&json!({
+        "updated_at": record::now_rfc3339(),
+        "status": s,
+    }))
+    .context("failed serialising status")?;
+    fs::write(path, json).with_context(|| format!("failed writing {}", path.display()))
+}
 
-    write_json_atomic(&cli.deployments_path, &root)?;
+fn prepare_create2(cli: &Cli, salt: H256) -> Result<Create2Deployment> {
+    let factory = cli.stylus_deployer.ok_or_else(|| {
+        anyhow!("--create2-salt requires --stylus-deployer (or STYLUS_DEPLOYER_ADDRESS)")
+    })?;
+    let init_code = cargo_stylus(cli)
+        .initcode()
+        .classify(FailureClass::BuildFailed)?;
+    Ok(Create2Deployment::new(factory, salt, &init_code))
+}
+
+async fn predict_address(cli: &Cli, salt: H256) -> Result<()> {
+    let c = prepare_create2(cli, salt)?;
+    println!("{}", ethers::utils::to_checksum(&c.predicted_address, None));
+
+    // Best-effort: tell the operator if something already lives there on this network.
+    if let Ok(provider) = rpc::provider(&cli.rpc_url) {
+        if let Ok(code) = provider.get_code(c.predicted_address, None).await {
+            if !code.is_empty() {
+                warn!(
+                    "{:?} already has code on {} ({} bytes)",
+                    c.predicted_address,
+                    cli.network,
+                    code.len()
+                );
+            }
+        }
+    }
     Ok(())
 }
 
-fn write_json_atomic(path: &Path, value: &Value) -> Result<()> {
-    let parent = path.parent().unwrap_or_else(|| Path::new("."));
-    if !parent.exists() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("failed creating directory {}", parent.display()))?;
+/// The deployer key as a hex string (reading `--private-key-path` if that is the source).
+fn deployer_private_key(cli: &Cli) -> Result<String> {
+    if cli.ledger {
+        return Err(anyhow!(
+            "the deployer key lives on a Ledger; pass --install-private-key for --install-to"
+        ));
+    }
+    if let Some(ref ks) = cli.keystore {
+        return Ok(ks.private_key().to_string());
+    }
+    if let Some(ref pk) = cli.private_key {
+        return Ok(pk.trim().to_string());
+    }
+    if let Some(ref pk_path) = cli.private_key_path {
+        let pk = fs::read_to_string(pk_path)
+            .with_context(|| format!("failed reading deployer key from {pk_path}"))?;
+        return Ok(pk.trim().to_string());
     }
+    Err(anyhow!(
+        "missing deployer key: provide --private-key-path, --private-key, or --keystore-path (or set PRIV_KEY_PATH/PKEY/KEYSTORE_PATH)"
+    ))
+}
 
-    let serialised =
-        serde_json::to_string_pretty(value).context("failed serialising deployments JSON")?;
-    let tmp_path = tmp_path_for(path);
-    fs::write(&tmp_path, serialised.as_bytes())
-        .with_context(|| format!("failed writing temp file {}", tmp_path.display()))?;
-    fs::rename(&tmp_path, path).with_context(|| format!("failed replacing {}", path.display()))?;
-    Ok(())
+/// Deployer key flags understood by every `cargo stylus` subcommand that sends transactions.
+fn key_args(cli: &Cli) -> Result<Vec<String>> {
+    if cli.ledger {
+        Err(anyhow!("`cargo stylus` cannot sign with a Ledger"))
+    } else if let Some(ref ks) = cli.keystore {
+        Ok(ks.cargo_stylus_args())
+    } else if let Some(ref pk_path) = cli.private_key_path {
+        Ok(vec!["--private-key-path".to_string(), pk_path.clone()])
+    } else if let Some(ref pk) = cli.private_key {
+        Ok(vec!["--private-key".to_string(), pk.clone()])
+    } else {
+        Err(anyhow!(
+            "missing deployer key: provide --private-key-path, --private-key, or --keystore-path (or set PRIV_KEY_PATH/PKEY/KEYSTORE_PATH)"
+        ))
+    }
+}
+
+/// The compiled WASM (`--wasm-path`, or the single release artefact).
+fn compiled_wasm(cli: &Cli, flag: &str) -> Result<Vec<u8>> {
+    let path = match cli.wasm_path {
+        Some(ref p) => p.clone(),
+        None => wasm::release_wasm(&cli.contract_dir)?.ok_or_else(|| {
+            anyhow!("{flag} needs a compiled WASM; pass --wasm-path or run `cargo stylus check`")
+        })?,
+    };
+    fs::read(&path).with_context(|| format!("failed reading {}", path.display()))
+}
+
+/// The deployer key as an in-process signer on `rpc_url`.
+async fn local_client(cli: &Cli, rpc_url: &str) -> Result<direct::Client<LocalWallet>> {
+    let provider = rpc::provider(rpc_url)?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .context("failed fetching chain id")?;
+    let wallet: LocalWallet = deployer_private_key(cli)?
+        .parse()
+        .context("invalid deployer private key")?;
+    Ok(SignerMiddleware::new(
+        provider,
+        wallet.with_chain_id(chain_id.as_u64()),
+    ))
+}
+
+/// Rehearse the deploy on `--simulate-rpc`; any error aborts the real deploy.
+#[instrument(name = "simulate", skip_all)]
+async fn run_simulation(cli: &Cli, fork_rpc: &str) -> Result<simulate::Simulation> {
+    let wasm = compiled_wasm(cli, "--simulate-rpc").classify(FailureClass::BuildFailed)?;
+    let client = local_client(cli, fork_rpc).await?;
+    // Any id works on the fork; reuse the configured one so the rehearsal matches the install.
+    let permission_id = cli
+        .permission_id
+        .unwrap_or_else(|| H256::from_low_u64_be(1));
+    let sources = match (cli.state_view, cli.vts_orchestrator, cli.liquidity_hub) {
+        (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+        _ => None,
+    };
+    let sim = simulate::simulate(&client, &wasm, permission_id, sources).await?;
+    info!(
+        fork_address = ?sim.address,
+        "fork simulation passed (deploy, activate, onInstall, checkUserOpPolicy)"
+    );
+    Ok(sim)
+}
+
+/// Deploy and activate the compiled WASM with the library, without invoking cargo-stylus.
+async fn run_direct_deploy(cli: &Cli) -> Result<(DeployOutcome, Activation)> {
+    let wasm = compiled_wasm(cli, "--direct").classify(FailureClass::BuildFailed)?;
+    let client = local_client(cli, &cli.rpc_url).await?;
+    deploy::direct(&client, &wasm).await
+}
+
+/// Deploy and activate with a Ledger, sending both transactions from this process.
+async fn run_ledger_deploy(cli: &Cli) -> Result<(DeployOutcome, Activation)> {
+    let init_code = cargo_stylus(cli)
+        .initcode()
+        .classify(FailureClass::BuildFailed)?;
+    let provider = rpc::provider(&cli.rpc_url)?;
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .context("failed fetching chain id")?;
+    let signer = ledger_signer(cli, chain_id.as_u64()).await?;
+    info!(
+        "confirm the deployment on the Ledger ({:?})",
+        ethers::signers::Signer::address(&signer)
+    );
+    let client = SignerMiddleware::new(provider, signer);
+    deploy::send_init_code(&client, init_code).await
+}
+
+#[cfg(feature = "ledger")]
+async fn ledger_signer(cli: &Cli, chain_id: u64) -> Result<ethers::signers::Ledger> {
+    use ethers::signers::{HDPath, Ledger};
+    Ledger::new(HDPath::LedgerLive(cli.ledger_index), chain_id)
+        .await
+        .context("failed connecting to the Ledger (unlocked, Ethereum app open?)")
+}
+
+#[cfg(not(feature = "ledger"))]
+async fn ledger_signer(_cli: &Cli, _chain_id: u64) -> Result<ethers::signers::LocalWallet> {
+    Err(anyhow!(
+        "--ledger requires a deployer built with `--features ledger`"
+    ))
 }
 
-fn tmp_path_for(path: &Path) -> PathBuf {
-    let mut tmp = path.as_os_str().to_os_string();
-    tmp.push(".tmp");
-    PathBuf::from(tmp)
+/// `verify-build`: reproduce the recorded deployment and record the result on its entry.
+async fn verify_build(cli: &Cli) -> Result<()> {
+    let address = recorded_policy(cli)?;
+    let provider = rpc::provider(&cli.rpc_url)?;
+    let deployed = rpc::code_hash(&provider, address).await?;
+    let result = verify::reproduce(
+        &cli.contract_dir,
+        &cli.contract_key,
+        cli.reproduce_image.as_deref(),
+        deployed,
+        &redactor(cli),
+    );
+
+    println!(
+        "{} ({}): {}",
+        cli.contract_key,
+        cli.network,
+        result.status()
+    );
+    println!("  image:     {}", result.image);
+    println!("  address:   {address:?}");
+    if let Some(deployed) = deployed {
+        println!("  deployed:  {deployed:?}");
+    }
+    if let Some(ref rebuild) = result.rebuild {
+        println!("  rebuilt:   {:?}", rebuild.code_hash);
+    }
+
+    recorder(cli).write_reproducible_build(&result)?;
+    result.ensure_matches(
+        &cli.contract_key,
+        &cli.deployments_path,
+        &format!("{address:?}"),
+    )
+}
+
+/// Refresh `--env-out` from the deployments file (no-op when the flag is unset).
+fn write_env_out(cli: &Cli) -> Result<()> {
+    match cli.env_out {
+        Some(ref path) => recorder(cli).write_env_out(path),
+        None => Ok(()),
+    }
+}
+
+/// How `--arbiscan` submits the source.
+fn arbiscan_options(cli: &Cli) -> verify::ArbiscanOptions<'_> {
+    verify::ArbiscanOptions {
+        api_url: &cli.arbiscan_api_url,
+        api_key: cli.arbiscan_api_key.as_deref(),
+        contract_name: &cli.contract_name,
+        license_type: cli.arbiscan_license_type,
+    }
+}
+
+/// `cargo stylus` in `--contract-dir` against `--rpc-url`, signing with the deployer key.
+fn cargo_stylus(cli: &Cli) -> CargoStylus {
+    CargoStylus {
+        contract_dir: cli.contract_dir.clone(),
+        rpc_url: cli.rpc_url.clone(),
+        key_args: key_args(cli).map_err(|e| format!("{e:#}")),
+    }
+}
+
+/// The `--contract-key` entry of `--deployments-path`, attested with the deployer key.
+fn recorder(cli: &Cli) -> Recorder {
+    Recorder {
+        path: cli.deployments_path.clone(),
+        contract_key: cli.contract_key.clone(),
+        network: cli.network.clone(),
+        rpc_url: cli.rpc_url.clone(),
+        attester: deployer_private_key(cli)
+            .and_then(|k| {
+                k.parse::<LocalWallet>()
+                    .map_err(|e| anyhow!("invalid deployer key: {e}"))
+            })
+            .map_err(|e| format!("{e:#}")),
+        redactor: redactor(cli),
+    }
+}
+
+/// Redactor that also knows the literal secrets this run was given.
+fn redactor(cli: &Cli) -> redact::Redactor {
+    let mut redactor = redact::Redactor::new();
+    let known = [
+        deployer_private_key(cli).ok(),
+        cli.install_private_key.clone(),
+        cli.arbiscan_api_key.clone(),
+    ];
+    for secret in known.iter().flatten() {
+        redactor = redactor.secret(secret);
+    }
+    redactor
 }
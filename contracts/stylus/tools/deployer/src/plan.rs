@@ -0,0 +1,55 @@
+//! `plan`: compare the local build with what is deployed, without sending anything.
+//!
+//! Stylus programs are stored on-chain as `0xEFF000 || dictionary byte || brotli(wasm)`. The
+//! init code from `cargo stylus get-initcode` is a small EVM prelude that returns exactly those
+//! bytes, so hashing the part after the prelude gives the codehash a fresh deploy would produce.
+
+use ethers::{types::H256, utils::keccak256};
+
+/// Stylus EOF-style code prefix (`EFF000`).
+const STYLUS_PREFIX: [u8; 3] = [0xEF, 0xF0, 0x00];
+
+/// Runtime code a deployment of `init_code` would leave at the new address.
+pub fn runtime_code(init_code: &[u8]) -> Option<&[u8]> {
+    init_code
+        .windows(STYLUS_PREFIX.len())
+        .position(|w| w == STYLUS_PREFIX)
+        .map(|start| &init_code[start..])
+}
+
+/// Codehash a deployment of `init_code` would produce.
+pub fn local_code_hash(init_code: &[u8]) -> Option<H256> {
+    runtime_code(init_code).map(|code| H256(keccak256(code)))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlanStatus {
+    /// On-chain code at the recorded address matches the local build.
+    UpToDate,
+    /// The local build differs from what was deployed (or nothing is deployed yet).
+    NeedsDeploy(&'static str),
+    /// On-chain code matches neither the local build nor the recorded `code_hash`.
+    Drift,
+}
+
+impl PlanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlanStatus::UpToDate => "up to date",
+            PlanStatus::NeedsDeploy(_) => "needs deploy",
+            PlanStatus::Drift => "drift detected",
+        }
+    }
+}
+
+/// Decide the plan from the local codehash, the on-chain codehash at the recorded address
+/// (`None` when there is no recorded address or it has no code), and the recorded `code_hash`.
+pub fn classify(local: H256, onchain: Option<H256>, recorded: Option<H256>) -> PlanStatus {
+    match (onchain, recorded) {
+        (None, _) => PlanStatus::NeedsDeploy("no code at the recorded address"),
+        (Some(onchain), _) if onchain == local => PlanStatus::UpToDate,
+        (Some(onchain), Some(recorded)) if onchain != recorded => PlanStatus::Drift,
+        (Some(_), Some(_)) => PlanStatus::NeedsDeploy("local build changed since the last deploy"),
+        (Some(_), None) => PlanStatus::NeedsDeploy("local build differs from on-chain code"),
+    }
+}
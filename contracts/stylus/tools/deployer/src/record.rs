@@ -0,0 +1,457 @@
+//! Writing deployment runs into the deployments JSON.
+//!
+//! Every write takes the file lock, reloads the file, applies one change (a deployment entry, an
+//! estimate, a rollback, a reproducible-build result) and saves it re-attested with the deployer
+//! key. Subprocess output is scrubbed of secrets on the way in, since the file is committed.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    providers::Middleware,
+    signers::LocalWallet,
+    types::{H256, U256},
+    utils::format_ether,
+};
+use serde_json::Value;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing::{info, instrument, warn};
+
+use crate::{
+    abi_export,
+    activation::{Activation, CacheBid},
+    broadcast,
+    deploy::{CostEstimate, Create2Deployment, DeployOutcome},
+    deployments, env_out, fund,
+    install::Install,
+    lock,
+    redact::Redactor,
+    report, rpc, simulate,
+    verify::{ReproducibleBuild, SmokeTest, Verification},
+};
+
+/// Everything learned about a deployment during this run (written as one deployments entry).
+#[derive(Debug)]
+pub struct DeploymentRecord {
+    pub deploy: DeployOutcome,
+    /// Version being replaced, for `upgrade`.
+    pub upgrade_from: Option<PreviousDeployment>,
+    pub create2: Option<Create2Deployment>,
+    pub activation: Activation,
+    pub wasm_hash: Option<String>,
+    /// keccak256 of the on-chain code (compressed WASM), used by `plan` to detect drift.
+    pub code_hash: Option<H256>,
+    pub fees: Option<rpc::DeploymentFees>,
+    pub abi: Option<abi_export::AbiFiles>,
+    pub simulation: Option<simulate::Simulation>,
+    pub verification: Option<Verification>,
+    pub reproducible_build: Option<ReproducibleBuild>,
+    pub smoke_test: Option<SmokeTest>,
+    pub arbiscan: Option<Value>,
+    pub cache_bid: Option<CacheBid>,
+    pub install: Option<Install>,
+    pub funding: Vec<fund::Funding>,
+}
+
+/// The currently recorded (latest) deployment of a contract key.
+#[derive(Debug)]
+pub struct PreviousDeployment {
+    pub version: u64,
+    pub address: String,
+}
+
+/// Where and how one contract key's deployments are recorded.
+pub struct Recorder {
+    /// The deployments JSON.
+    pub path: PathBuf,
+    pub contract_key: String,
+    pub network: String,
+    pub rpc_url: String,
+    /// Key the file is attested with (see [`deployments::attest`]), or why there is none.
+    pub attester: Result<LocalWallet, String>,
+    /// Applied to every piece of subprocess output before it is written.
+    pub redactor: Redactor,
+}
+
+impl Recorder {
+    /// Record `record` as the new latest `contract_key` deployment, keeping the previous one in
+    /// history.
+    #[instrument(name = "record", skip_all, fields(path = %self.path.display()))]
+    pub fn write(&self, record: &DeploymentRecord) -> Result<()> {
+        let DeploymentRecord {
+            deploy,
+            upgrade_from,
+            create2,
+            activation,
+            wasm_hash,
+            code_hash,
+            fees,
+            abi,
+            simulation,
+            verification,
+            reproducible_build,
+            smoke_test,
+            arbiscan,
+            cache_bid,
+            install,
+            funding,
+        } = record;
+        let now = now_rfc3339();
+        let scrub = |text: &Option<String>| text.as_deref().map(|t| self.redactor.scrub(t));
+        let _lock = lock::lock(&self.path)?;
+        let mut file = deployments::load(&self.path)?;
+
+        file.network = Some(self.network.clone());
+        file.updated_at = Some(now.clone());
+
+        // Exact wei as decimal strings, plus ETH floats for quick aggregation across networks.
+        let wei = |v: Option<U256>| v.map(|v| v.to_string());
+        let eth = |v: Option<U256>| v.and_then(|v| format_ether(v).parse::<f64>().ok());
+
+        // Preserve raw output for audit/debugging, but truncate so we don't bloat git history.
+        // (Still useful when a devnet deployment behaves unexpectedly.)
+        let trimmed = self.redactor.persist(&deploy.raw_output, 16_000);
+
+        let mut entry = deployments::DeploymentEntry {
+            address: deploy.address.clone(),
+            address_source: Some(deploy.address_source.to_string()),
+            rpc_url: Some(self.rpc_url.clone()),
+            deployed_at: Some(now.clone()),
+            tx_hashes: deploy.tx_hashes.clone(),
+            upgraded_from: upgrade_from.as_ref().map(|prev| deployments::UpgradedFrom {
+                version: prev.version,
+                address: prev.address.clone(),
+            }),
+            create2: create2.as_ref().map(|c| deployments::Create2 {
+                factory: c.factory,
+                salt: c.salt,
+                predicted_address: c.predicted_address,
+            }),
+            activation: Some(deployments::Activation {
+                status: activation.status.as_str().to_string(),
+                tx_hash: activation.tx_hash.clone(),
+                attempts: activation.attempts,
+                last_error: scrub(&activation.last_error),
+            }),
+            wasm_hash: wasm_hash.clone(),
+            code_hash: *code_hash,
+            fees: fees.as_ref().map(|f| deployments::Fees {
+                deployment_gas_used: f.deployment_gas_used.map(|g| g.as_u64()),
+                deployment_cost_wei: wei(f.deployment_cost),
+                deployment_cost_eth: eth(f.deployment_cost),
+                activation_gas_used: f.activation_gas_used.map(|g| g.as_u64()),
+                activation_cost_wei: wei(f.activation_cost),
+                activation_cost_eth: eth(f.activation_cost),
+                data_fee_wei: wei(f.data_fee),
+                data_fee_eth: eth(f.data_fee),
+            }),
+            abi: abi.as_ref().map(|files| deployments::Abi {
+                solidity: files.solidity.clone(),
+                json: files.json.clone(),
+            }),
+            simulation: simulation.as_ref().map(|sim| deployments::Simulation {
+                status: "passed".to_string(),
+                fork_address: sim.address,
+                checked_at: now.clone(),
+            }),
+            verification: verification.as_ref().map(|v| deployments::Verification {
+                status: if v.verified { "verified" } else { "failed" }.to_string(),
+                deployment_tx: v.deployment_tx.clone(),
+                checked_at: now.clone(),
+                cargo_stylus_output: self.redactor.persist(&v.output, 4_000),
+                error: v.error.clone(),
+            }),
+            reproducible_build: reproducible_build.as_ref().map(|r| r.record(now.clone())),
+            smoke_test: smoke_test.as_ref().map(|t| deployments::SmokeTest {
+                status: if t.error.is_none() {
+                    "passed"
+                } else {
+                    "failed"
+                }
+                .to_string(),
+                checked_at: now.clone(),
+                error: t.error.clone(),
+            }),
+            arbiscan: arbiscan.clone(),
+            cache: cache_bid.as_ref().map(|c| deployments::Cache {
+                bid_wei: c.bid.to_string(),
+                status: if c.error.is_none() {
+                    "bid placed"
+                } else {
+                    "failed"
+                }
+                .to_string(),
+                tx_hash: c.tx_hash.clone(),
+                error: scrub(&c.error),
+            }),
+            funding: funding
+                .iter()
+                .map(|f| deployments::Funding {
+                    address: f.target.address,
+                    amount_eth: f.amount_eth(),
+                    status: if f.tx_hash.is_some() {
+                        "sent"
+                    } else {
+                        "failed"
+                    }
+                    .to_string(),
+                    tx_hash: f.tx_hash,
+                    error: f.error.clone(),
+                })
+                .collect(),
+            install: install.as_ref().map(|i| deployments::Install {
+                account: i.account,
+                permission_id: i.permission_id,
+                status: if i.tx_hash.is_some() {
+                    "installed"
+                } else {
+                    "failed"
+                }
+                .to_string(),
+                tx_hash: i.tx_hash.clone(),
+                error: i.error.clone(),
+            }),
+            cargo_stylus_output: (!trimmed.is_empty()).then_some(trimmed),
+            ..Default::default()
+        };
+
+        append_history(&mut file, &self.contract_key, &mut entry);
+        file.deployments.insert(self.contract_key.clone(), entry);
+
+        self.save(&mut file)
+    }
+
+    /// Record a `--estimate-only` run under `estimates`, leaving the deployments untouched.
+    pub fn write_estimate(&self, estimate: &CostEstimate) -> Result<()> {
+        let now = now_rfc3339();
+        let _lock = lock::lock(&self.path)?;
+        let mut file = deployments::load(&self.path)?;
+
+        // file.estimates[contract_key]; deliberately separate from file.deployments so an estimate
+        // never looks like (or overwrites) a real deployment.
+        let trimmed = self.redactor.persist(&estimate.raw_output, 4_000);
+        let report = deployments::Estimate {
+            network: self.network.clone(),
+            rpc_url: self.rpc_url.clone(),
+            estimated_at: now,
+            deployment: deployments::EstimatedDeployment {
+                gas: estimate.deployment_gas,
+                gas_price_gwei: estimate.gas_price_gwei.clone(),
+                cost_eth: estimate.deployment_cost_eth.clone(),
+            },
+            activation: deployments::EstimatedActivation {
+                gas: estimate.activation_gas,
+                data_fee_eth: estimate.data_fee_eth.clone(),
+            },
+            cargo_stylus_output: (!trimmed.is_empty()).then_some(trimmed),
+        };
+        file.estimates.insert(self.contract_key.clone(), report);
+
+        self.save(&mut file)
+    }
+
+    /// Record `result` on the current `contract_key` entry (`verify-build`).
+    pub fn write_reproducible_build(&self, result: &ReproducibleBuild) -> Result<()> {
+        let _lock = lock::lock(&self.path)?;
+        let mut file = deployments::load(&self.path)?;
+        if let Some(entry) = file.deployments.get_mut(&self.contract_key) {
+            entry.reproducible_build = Some(result.record(now_rfc3339()));
+        }
+        self.save(&mut file)
+    }
+
+    /// Sign `file` with [`Self::attester`] and write it.
+    ///
+    /// Without a local key (Ledger, or a keystore not unlocked for this command) the file is
+    /// written unsigned, since an attestation over the previous contents would no longer verify.
+    pub fn save(&self, file: &mut deployments::DeploymentsFile) -> Result<()> {
+        file.attestation = None;
+        match self.attester {
+            Ok(ref wallet) => deployments::attest(file, wallet, now_rfc3339())?,
+            Err(ref e) => warn!(
+                "writing {} without an attestation: {e}",
+                self.path.display()
+            ),
+        }
+        deployments::save(&self.path, file)
+    }
+
+    /// Point `deployments[key]` back at an earlier history version (the one before the current
+    /// one when `to_version` is `None`) and record the rollback.
+    pub fn rollback(&self, to_version: Option<u64>, reason: Option<&str>) -> Result<()> {
+        let key = &self.contract_key;
+        let path = &self.path;
+        let _lock = lock::lock(path)?;
+        let mut file = deployments::load(path)?;
+
+        let current = self
+            .latest()?
+            .ok_or_else(|| anyhow!("no `{key}` deployment in {}", path.display()))?;
+        let history = file
+            .history
+            .get_mut(key)
+            .filter(|h| !h.is_empty())
+            .ok_or_else(|| anyhow!("no history for `{key}` in {}", path.display()))?;
+
+        let target = match to_version {
+            Some(v) => history.iter().find(|h| h.version == Some(v)),
+            None => history
+                .iter()
+                .filter(|h| h.version.is_some_and(|v| v < current.version))
+                .max_by_key(|h| h.version),
+        }
+        .cloned()
+        .ok_or_else(|| match to_version {
+            Some(v) => anyhow!("`{key}` has no version {v} in history"),
+            None => anyhow!(
+                "`{key}` is at version {}; there is no earlier version to roll back to",
+                current.version
+            ),
+        })?;
+        let target_version = target.version.unwrap_or_default();
+        if target_version == current.version {
+            return Err(anyhow!("`{key}` is already at version {target_version}"));
+        }
+
+        let now = now_rfc3339();
+        for h in history.iter_mut() {
+            h.latest = Some(h.version == Some(target_version));
+        }
+
+        let mut restored = target;
+        restored.latest = Some(true);
+        restored.restored_at = Some(now.clone());
+        let target_address = restored.address.clone();
+        file.deployments.insert(key.clone(), restored);
+
+        file.rollbacks
+            .entry(key.clone())
+            .or_default()
+            .push(deployments::Rollback {
+                from_version: current.version,
+                from_address: current.address.clone(),
+                to_version: target_version,
+                to_address: target_address.clone(),
+                rolled_back_at: now.clone(),
+                reason: reason.map(str::to_string),
+            });
+        file.updated_at = Some(now);
+
+        self.save(&mut file)?;
+        info!(
+            "rolled back `{key}` from version {} ({}) to version {target_version} ({target_address})",
+            current.version, current.address,
+        );
+        Ok(())
+    }
+
+    /// The currently recorded deployment of `contract_key`, if any.
+    pub fn latest(&self) -> Result<Option<PreviousDeployment>> {
+        let file = deployments::load(&self.path)?;
+        Ok(file
+            .deployments
+            .get(&self.contract_key)
+            .map(|entry| PreviousDeployment {
+                // Entries written before history tracking count as version 1.
+                version: entry.version.unwrap_or(1),
+                address: entry.address.clone(),
+            }))
+    }
+
+    /// Render the just-recorded deployment as a markdown report at `path`.
+    pub async fn write_report(&self, path: &Path) -> Result<()> {
+        let file = deployments::load(&self.path)?;
+        let entry = file.deployments.get(&self.contract_key).ok_or_else(|| {
+            anyhow!(
+                "no `{}` deployment in {}",
+                self.contract_key,
+                self.path.display()
+            )
+        })?;
+        let chain_id = match rpc::provider(&self.rpc_url) {
+            Ok(p) => p.get_chainid().await.ok().map(|c| c.as_u64()),
+            Err(_) => None,
+        };
+        let markdown = report::markdown(&self.network, &self.contract_key, chain_id, entry);
+        report::write(path, &markdown)
+    }
+
+    /// Write every recorded address to `path` as env vars.
+    pub fn write_env_out(&self, path: &Path) -> Result<()> {
+        let file = deployments::load(&self.path)?;
+        let vars = env_out::address_vars(&file);
+        env_out::write(path, &vars)?;
+        info!(path = %path.display(), count = vars.len(), "wrote deployed addresses");
+        Ok(())
+    }
+
+    /// Write `deploy` as a Foundry broadcast artifact under `dir`, returning its path.
+    pub async fn export_foundry_broadcast(
+        &self,
+        dir: &Path,
+        contract_name: &str,
+        deploy: &DeployOutcome,
+    ) -> Result<PathBuf> {
+        let provider = rpc::provider(&self.rpc_url)?;
+        let address = deploy
+            .address
+            .parse()
+            .with_context(|| format!("invalid deployed address {}", deploy.address))?;
+        let tx_hashes = deploy
+            .tx_hashes
+            .iter()
+            .map(|h| h.parse())
+            .collect::<Result<Vec<H256>, _>>()
+            .context("invalid tx hash in deploy output")?;
+        let run = broadcast::BroadcastRun {
+            contract_key: &self.contract_key,
+            contract_name,
+            address,
+            tx_hashes: &tx_hashes,
+        };
+        broadcast::export(&provider, dir, &run).await
+    }
+}
+
+pub fn now_rfc3339() -> String {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Append `entry` to `file.history[key]` as the new `latest` version.
+///
+/// History is append-only: earlier versions are never removed, only un-flagged as `latest`, so a
+/// rollback can always find the previous address. `cargo_stylus_output` stays on the current entry
+/// only to keep the file small.
+fn append_history(
+    file: &mut deployments::DeploymentsFile,
+    key: &str,
+    entry: &mut deployments::DeploymentEntry,
+) {
+    let history = file.history.entry(key.to_string()).or_insert_with(|| {
+        // Seed from a pre-history entry so the first upgrade does not lose it.
+        match file.deployments.get(key) {
+            Some(prev) => {
+                let mut prev = prev.clone();
+                prev.version = Some(1);
+                vec![prev]
+            }
+            None => Vec::new(),
+        }
+    });
+    for h in history.iter_mut() {
+        h.latest = Some(false);
+        h.cargo_stylus_output = None;
+    }
+
+    entry.version = Some(history.len() as u64 + 1);
+    entry.latest = Some(true);
+
+    let mut summary = entry.clone();
+    summary.cargo_stylus_output = None;
+    history.push(summary);
+}
+
+#[cfg(test)]
+mod tests;
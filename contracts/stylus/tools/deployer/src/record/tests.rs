@@ -0,0 +1,105 @@
+//! History and rollback round trips through a deployments file on disk.
+
+use std::{fs, path::Path};
+
+use ethers::signers::{LocalWallet, Signer};
+
+use super::{DeploymentRecord, Recorder};
+use crate::{
+    activation::{Activation, ActivationStatus},
+    deploy::DeployOutcome,
+    deployments,
+    redact::Redactor,
+};
+
+fn recorder(name: &str) -> Recorder {
+    let dir = std::env::temp_dir().join(format!("stylus-deployer-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    Recorder {
+        path: dir.join("deployments.json"),
+        contract_key: "intent-policy".into(),
+        network: "devnet".into(),
+        rpc_url: "http://localhost:8547".into(),
+        attester: Ok(LocalWallet::from_bytes(&[1; 32]).unwrap()),
+        redactor: Redactor::new().secret(&"ab".repeat(32)),
+    }
+}
+
+fn record(address: &str, raw_output: &str) -> DeploymentRecord {
+    DeploymentRecord {
+        deploy: DeployOutcome {
+            address: address.into(),
+            tx_hashes: vec![format!("0x{}", "11".repeat(32))],
+            activation_tx: None,
+            address_source: "output",
+            raw_output: raw_output.into(),
+        },
+        upgrade_from: None,
+        create2: None,
+        activation: Activation {
+            status: ActivationStatus::Activated,
+            tx_hash: None,
+            attempts: 1,
+            last_error: None,
+        },
+        wasm_hash: None,
+        code_hash: None,
+        fees: None,
+        abi: None,
+        simulation: None,
+        verification: None,
+        reproducible_build: None,
+        smoke_test: None,
+        arbiscan: None,
+        cache_bid: None,
+        install: None,
+        funding: Vec::new(),
+    }
+}
+
+fn cleanup(path: &Path) {
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+}
+
+const FIRST: &str = "0x1111111111111111111111111111111111111111";
+const SECOND: &str = "0x2222222222222222222222222222222222222222";
+
+#[test]
+fn writes_append_history_and_rollback_restores_the_previous_version() {
+    let recorder = recorder("rollback");
+    recorder.write(&record(FIRST, "first")).unwrap();
+    recorder.write(&record(SECOND, "second")).unwrap();
+
+    let latest = recorder.latest().unwrap().unwrap();
+    assert_eq!((latest.version, latest.address.as_str()), (2, SECOND));
+    let file = deployments::load(&recorder.path).unwrap();
+    let history = &file.history["intent-policy"];
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].latest, Some(false));
+    assert!(history.iter().all(|h| h.cargo_stylus_output.is_none()));
+
+    recorder.rollback(None, Some("bad upgrade")).unwrap();
+    let latest = recorder.latest().unwrap().unwrap();
+    assert_eq!((latest.version, latest.address.as_str()), (1, FIRST));
+    let file = deployments::load(&recorder.path).unwrap();
+    let rollback = &file.rollbacks["intent-policy"][0];
+    assert_eq!((rollback.from_version, rollback.to_version), (2, 1));
+    deployments::verify_attestation(&file, recorder.attester.as_ref().unwrap().address()).unwrap();
+
+    assert!(recorder.rollback(None, None).is_err());
+    cleanup(&recorder.path);
+}
+
+#[test]
+fn persisted_output_is_scrubbed() {
+    let recorder = recorder("scrub");
+    let key = "ab".repeat(32);
+    recorder
+        .write(&record(FIRST, &format!("signing with 0x{key}\ndone")))
+        .unwrap();
+
+    let raw = fs::read_to_string(&recorder.path).unwrap();
+    assert!(!raw.contains(&key));
+    assert!(raw.contains(crate::redact::REDACTED));
+    cleanup(&recorder.path);
+}
@@ -0,0 +1,144 @@
+//! Markdown summary of a deployment (`--report`), for release notes and ops channels.
+//!
+//! Rendered from the deployments entry the run just recorded, so the report always agrees with
+//! the deployments file. Transaction hashes and the address link to the explorer when the chain
+//! has one.
+
+use std::{fmt::Write as _, fs, path::Path};
+
+use anyhow::{Context, Result};
+
+use crate::{arbiscan, deployments::DeploymentEntry};
+
+/// Render `entry` (the current `contract_key` deployment on `network`) as markdown.
+pub fn markdown(
+    network: &str,
+    contract_key: &str,
+    chain_id: Option<u64>,
+    entry: &DeploymentEntry,
+) -> String {
+    let address_link =
+        |address: &str| match chain_id.and_then(|c| arbiscan::explorer_address_url(c, address)) {
+            Some(url) => format!("[`{address}`]({url})"),
+            None => format!("`{address}`"),
+        };
+    let tx_link = |tx: &str| match chain_id.and_then(|c| arbiscan::explorer_tx_url(c, tx)) {
+        Some(url) => format!("[`{tx}`]({url})"),
+        None => format!("`{tx}`"),
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "## `{contract_key}` on {network}");
+    out.push('\n');
+    let _ = writeln!(out, "| | |");
+    let _ = writeln!(out, "|---|---|");
+    let _ = writeln!(out, "| Address | {} |", address_link(&entry.address));
+    if let Some(chain_id) = chain_id {
+        let _ = writeln!(out, "| Chain id | {chain_id} |");
+    }
+    if let Some(version) = entry.version {
+        let _ = writeln!(out, "| Version | {version} |");
+    }
+    if let Some(ref prev) = entry.upgraded_from {
+        let _ = writeln!(
+            out,
+            "| Upgraded from | v{} ({}) |",
+            prev.version,
+            address_link(&prev.address)
+        );
+    }
+    if let Some(ref at) = entry.deployed_at {
+        let _ = writeln!(out, "| Deployed at | {at} |");
+    }
+    if let Some(ref hash) = entry.wasm_hash {
+        let _ = writeln!(out, "| WASM hash | `{hash}` |");
+    }
+    if let Some(hash) = entry.code_hash {
+        let _ = writeln!(out, "| Code hash | `{hash:?}` |");
+    }
+    if let Some(ref c) = entry.create2 {
+        let _ = writeln!(
+            out,
+            "| CREATE2 | factory `{:?}`, salt `{:?}` |",
+            c.factory, c.salt
+        );
+    }
+
+    if !entry.tx_hashes.is_empty() {
+        out.push_str("\n### Transactions\n\n");
+        for tx in &entry.tx_hashes {
+            let _ = writeln!(out, "- {}", tx_link(tx));
+        }
+    }
+
+    out.push_str("\n### Status\n\n");
+    if let Some(ref a) = entry.activation {
+        let tx = a.tx_hash.as_deref().map(|tx| format!(" ({})", tx_link(tx)));
+        let _ = writeln!(out, "- Activation: {}{}", a.status, tx.unwrap_or_default());
+    }
+    if let Some(ref sim) = entry.simulation {
+        let _ = writeln!(out, "- Fork simulation: {}", sim.status);
+    }
+    if let Some(ref t) = entry.smoke_test {
+        let _ = writeln!(out, "- Smoke test: {}", t.status);
+    }
+    match entry.verification {
+        Some(ref v) => {
+            let _ = writeln!(out, "- `cargo stylus verify`: {}", v.status);
+        }
+        None => {
+            let _ = writeln!(out, "- `cargo stylus verify`: not run");
+        }
+    }
+    if let Some(ref a) = entry.arbiscan {
+        let status = a
+            .get("status")
+            .and_then(|s| s.as_str())
+            .unwrap_or("unknown");
+        let _ = writeln!(out, "- Arbiscan: {status}");
+    }
+    if let Some(ref c) = entry.cache {
+        let _ = writeln!(out, "- Cache bid ({} wei): {}", c.bid_wei, c.status);
+    }
+    if let Some(ref i) = entry.install {
+        let _ = writeln!(out, "- Install on `{:?}`: {}", i.account, i.status);
+    }
+    for f in &entry.funding {
+        let _ = writeln!(
+            out,
+            "- Funded `{:?}` with {} ETH: {}",
+            f.address, f.amount_eth, f.status
+        );
+    }
+
+    if let Some(ref fees) = entry.fees {
+        out.push_str("\n### Fees\n\n");
+        let _ = writeln!(out, "| | Gas used | Cost (ETH) |");
+        let _ = writeln!(out, "|---|---|---|");
+        let gas = |g: Option<u64>| g.map_or("-".to_string(), |g| g.to_string());
+        let eth = |e: Option<f64>| e.map_or("-".to_string(), |e| e.to_string());
+        let _ = writeln!(
+            out,
+            "| Deployment | {} | {} |",
+            gas(fees.deployment_gas_used),
+            eth(fees.deployment_cost_eth)
+        );
+        let _ = writeln!(
+            out,
+            "| Activation | {} | {} |",
+            gas(fees.activation_gas_used),
+            eth(fees.activation_cost_eth)
+        );
+        let _ = writeln!(out, "| Data fee | - | {} |", eth(fees.data_fee_eth));
+    }
+    out
+}
+
+/// Write `report` to `path`, creating parent directories as needed.
+pub fn write(path: &Path, report: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating directory {}", parent.display()))?;
+    }
+    fs::write(path, report).with_context(|| format!("failed writing {}", path.display()))
+}
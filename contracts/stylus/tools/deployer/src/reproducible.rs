@@ -0,0 +1,156 @@
+//! Reproducible-build check: rebuild the contract in a pinned toolchain container and compare the
+//! resulting code hash with what is deployed.
+//!
+//! The rebuild runs `cargo build --release --locked` inside `rust:<channel>` (the channel comes
+//! from the contract's `rust-toolchain.toml`), so the lockfile and compiler are both fixed. The
+//! WASM is then compressed exactly like a deploy would ([`wasm::program_code`]), and its keccak
+//! is the codehash the deployed program must have if it was built from this source tree.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{types::H256, utils::keccak256};
+use serde::Deserialize;
+
+use crate::wasm;
+
+/// Target directory for container builds, relative to the contract directory. Kept apart from
+/// `target/` so host and container builds never share artefacts.
+const TARGET_DIR: &str = "target/reproducible";
+
+/// Where the mounted source tree lives inside the container.
+const MOUNT: &str = "/workspace";
+
+/// Outcome of rebuilding the contract in a pinned container.
+#[derive(Debug, Clone)]
+pub struct Rebuild {
+    /// Container image the build ran in.
+    pub image: String,
+    /// keccak256 of the rebuilt (uncompressed) WASM.
+    pub wasm_hash: H256,
+    /// keccak256 of the compressed program, ie the codehash a deploy of this build would have.
+    pub code_hash: H256,
+}
+
+impl Rebuild {
+    /// Whether `deployed` (the on-chain codehash) is this build.
+    pub fn matches(&self, deployed: Option<H256>) -> bool {
+        deployed == Some(self.code_hash)
+    }
+}
+
+#[derive(Deserialize)]
+struct ToolchainFile {
+    toolchain: Toolchain,
+}
+
+#[derive(Deserialize)]
+struct Toolchain {
+    channel: String,
+}
+
+/// `rust:<channel>` for the channel pinned in `<contract_dir>/rust-toolchain.toml`.
+pub fn toolchain_image(contract_dir: &Path) -> Result<String> {
+    let path = contract_dir.join("rust-toolchain.toml");
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("failed reading {}", path.display()))?;
+    let file: ToolchainFile =
+        toml::from_str(&text).with_context(|| format!("failed parsing {}", path.display()))?;
+    let channel = file.toolchain.channel;
+    // A moving channel would make the "pinned" container drift between runs.
+    if !channel.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(anyhow!(
+            "{} pins `{channel}`, not a versioned toolchain; pass an explicit image",
+            path.display()
+        ));
+    }
+    Ok(format!("rust:{channel}"))
+}
+
+/// Directory to mount into the container: the enclosing git checkout, so path dependencies
+/// outside the contract directory resolve, or the contract directory itself outside a checkout.
+pub fn mount_root(contract_dir: &Path) -> Result<PathBuf> {
+    let contract_dir = contract_dir
+        .canonicalize()
+        .with_context(|| format!("failed resolving {}", contract_dir.display()))?;
+    Ok(contract_dir
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(&contract_dir)
+        .to_path_buf())
+}
+
+/// Rebuild the contract with `--locked` in `image` (Docker) and hash the result.
+pub fn rebuild(contract_dir: &Path, image: &str) -> Result<Rebuild> {
+    let root = mount_root(contract_dir)?;
+    let contract_dir = contract_dir.canonicalize()?;
+    let relative = contract_dir
+        .strip_prefix(&root)
+        .expect("mount root is an ancestor of the contract directory");
+    let workdir = Path::new(MOUNT).join(relative);
+
+    let output = Command::new("docker")
+        .arg("run")
+        .arg("--rm")
+        .arg("-v")
+        .arg(format!("{}:{MOUNT}", root.display()))
+        .arg("-w")
+        .arg(&workdir)
+        .arg("-e")
+        .arg(format!(
+            "CARGO_TARGET_DIR={}",
+            workdir.join(TARGET_DIR).display()
+        ))
+        .arg(image)
+        .arg("sh")
+        .arg("-c")
+        .arg(
+            "rustup target add wasm32-unknown-unknown && \
+             cargo build --release --locked --target wasm32-unknown-unknown",
+        )
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("failed to run `docker` (is it installed and running?)")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "reproducible build in {image} failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let wasm_path = built_wasm(&contract_dir.join(TARGET_DIR))?;
+    let wasm =
+        fs::read(&wasm_path).with_context(|| format!("failed reading {}", wasm_path.display()))?;
+    Ok(Rebuild {
+        image: image.to_string(),
+        wasm_hash: H256(keccak256(&wasm)),
+        code_hash: H256(keccak256(wasm::program_code(&wasm)?)),
+    })
+}
+
+/// The single `*.wasm` the container build left under `target_dir`.
+fn built_wasm(target_dir: &Path) -> Result<PathBuf> {
+    let release_dir = target_dir.join("wasm32-unknown-unknown/release");
+    let mut wasm_files = Vec::new();
+    for entry in fs::read_dir(&release_dir)
+        .with_context(|| format!("failed listing {}", release_dir.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("wasm") {
+            wasm_files.push(path);
+        }
+    }
+    match wasm_files.len() {
+        1 => Ok(wasm_files.remove(0)),
+        n => Err(anyhow!(
+            "expected one WASM file in {}, found {n}",
+            release_dir.display()
+        )),
+    }
+}
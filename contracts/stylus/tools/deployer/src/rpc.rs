@@ -0,0 +1,298 @@
+//! JSON-RPC helpers used to confirm deploy results from receipts instead of tool output.
+//!
+//! `cargo stylus` output wording changes between versions; receipts do not. Everything here is
+//! best-effort: callers fall back to parsing the raw output when the RPC cannot answer.
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    abi::{self, Token},
+    providers::{Http, Middleware, Provider},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Bytes, TransactionReceipt,
+        TransactionRequest, H160, H256, U256, U64,
+    },
+    utils::{id, keccak256},
+};
+
+use crate::install::{PolicyInitConfig, MODULE_TYPE_POLICY};
+
+/// ArbOS `ArbWasm` precompile (program activation + Stylus params).
+pub const ARB_WASM: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x71,
+]);
+
+pub fn provider(rpc_url: &str) -> Result<Provider<Http>> {
+    Provider::<Http>::try_from(rpc_url).with_context(|| format!("invalid RPC URL {rpc_url}"))
+}
+
+/// Deployment facts recovered from transaction receipts.
+#[derive(Debug)]
+pub struct ReceiptDeployment {
+    pub address: Address,
+    pub deployment_tx: H256,
+    /// Tx in which ArbWasm emitted an activation log (may equal `deployment_tx` when the
+    /// deployer contract activates in the same transaction).
+    pub activation_tx: Option<H256>,
+}
+
+/// Work out the deployed address (and activation tx) from the receipts of `tx_hashes`.
+///
+/// Handles both deployment shapes used by cargo-stylus:
+/// - plain `CREATE` (receipt `contractAddress`)
+/// - the `StylusDeployer` factory (`ContractDeployed(address)` log)
+///
+/// Returns `Ok(None)` when no successful receipt identifies a deployment.
+pub async fn deployment_from_receipts(
+    provider: &Provider<Http>,
+    tx_hashes: &[H256],
+) -> Result<Option<ReceiptDeployment>> {
+    let contract_deployed_topic = H256(keccak256("ContractDeployed(address)"));
+
+    let mut deployment: Option<(Address, H256)> = None;
+    let mut activation_tx = None;
+
+    for hash in tx_hashes {
+        let Some(receipt) = provider
+            .get_transaction_receipt(*hash)
+            .await
+            .with_context(|| format!("failed fetching receipt for {hash:?}"))?
+        else {
+            continue;
+        };
+        if receipt.status != Some(U64::from(1u64)) {
+            continue;
+        }
+
+        if deployment.is_none() {
+            if let Some(address) = deployed_address(&receipt, contract_deployed_topic) {
+                deployment = Some((address, *hash));
+            }
+        }
+        if activation_tx.is_none() && receipt.logs.iter().any(|log| log.address == ARB_WASM) {
+            activation_tx = Some(*hash);
+        }
+    }
+
+    Ok(
+        deployment.map(|(address, deployment_tx)| ReceiptDeployment {
+            address,
+            deployment_tx,
+            activation_tx,
+        }),
+    )
+}
+
+fn deployed_address(
+    receipt: &TransactionReceipt,
+    contract_deployed_topic: H256,
+) -> Option<Address> {
+    if let Some(address) = receipt.contract_address {
+        return Some(address);
+    }
+    receipt.logs.iter().find_map(|log| {
+        if log.topics.first() != Some(&contract_deployed_topic) {
+            return None;
+        }
+        // `address deployedContract` is the first (non-indexed) word of the log data.
+        let data = log.data.as_ref();
+        (data.len() >= 32).then(|| Address::from_slice(&data[12..32]))
+    })
+}
+
+/// Costs of a deployment, read from receipts (all amounts in wei).
+#[derive(Debug, Default)]
+pub struct DeploymentFees {
+    pub deployment_gas_used: Option<U256>,
+    pub deployment_cost: Option<U256>,
+    pub activation_gas_used: Option<U256>,
+    pub activation_cost: Option<U256>,
+    /// ArbOS data fee from the `ProgramActivated` log.
+    pub data_fee: Option<U256>,
+}
+
+/// Gas costs of the deployment / activation txs plus the ArbOS data fee.
+///
+/// When the deploy activated in the same tx, only the deployment figures are filled and the data
+/// fee comes from that receipt.
+pub async fn deployment_fees(
+    provider: &Provider<Http>,
+    deployment_tx: H256,
+    activation_tx: Option<H256>,
+) -> Result<DeploymentFees> {
+    let program_activated = H256(keccak256(
+        "ProgramActivated(bytes32,bytes32,address,uint256,uint16)",
+    ));
+    let mut fees = DeploymentFees::default();
+
+    let deployment = receipt(provider, deployment_tx).await?;
+    fees.deployment_gas_used = deployment.gas_used;
+    fees.deployment_cost = tx_cost(&deployment);
+    fees.data_fee = data_fee(&deployment, program_activated);
+
+    if let Some(tx) = activation_tx.filter(|tx| *tx != deployment_tx) {
+        let activation = receipt(provider, tx).await?;
+        fees.activation_gas_used = activation.gas_used;
+        fees.activation_cost = tx_cost(&activation);
+        fees.data_fee = fees.data_fee.or(data_fee(&activation, program_activated));
+    }
+    Ok(fees)
+}
+
+async fn receipt(provider: &Provider<Http>, hash: H256) -> Result<TransactionReceipt> {
+    provider
+        .get_transaction_receipt(hash)
+        .await
+        .with_context(|| format!("failed fetching receipt for {hash:?}"))?
+        .ok_or_else(|| anyhow!("no receipt for {hash:?}"))
+}
+
+fn tx_cost(receipt: &TransactionReceipt) -> Option<U256> {
+    Some(receipt.gas_used? * receipt.effective_gas_price?)
+}
+
+fn data_fee(receipt: &TransactionReceipt, program_activated: H256) -> Option<U256> {
+    receipt.logs.iter().find_map(|log| {
+        if log.address != ARB_WASM || log.topics.first() != Some(&program_activated) {
+            return None;
+        }
+        // data = moduleHash || program || dataFee || version
+        let data = log.data.as_ref();
+        (data.len() >= 96).then(|| U256::from_big_endian(&data[64..96]))
+    })
+}
+
+/// keccak256 of the code at `address`, or `None` when the account has no code.
+pub async fn code_hash(provider: &Provider<Http>, address: Address) -> Result<Option<H256>> {
+    let code = provider
+        .get_code(address, None)
+        .await
+        .with_context(|| format!("failed fetching code at {address:?}"))?;
+    Ok((!code.is_empty()).then(|| H256(keccak256(&code))))
+}
+
+/// Probe the deployed policy's Kernel `IPolicy` views.
+pub async fn smoke_test_policy(provider: &Provider<Http>, policy: Address) -> Result<()> {
+    let is_policy = call_bool(
+        provider,
+        policy,
+        "isModuleType(uint256)",
+        &[Token::Uint(U256::from(MODULE_TYPE_POLICY))],
+    )
+    .await?;
+    if !is_policy {
+        return Err(anyhow!("isModuleType({MODULE_TYPE_POLICY}) returned false"));
+    }
+
+    let zero_initialized = call_bool(
+        provider,
+        policy,
+        "isInitialized(address)",
+        &[Token::Address(Address::zero())],
+    )
+    .await?;
+    if zero_initialized {
+        return Err(anyhow!("isInitialized(address(0)) returned true"));
+    }
+
+    Ok(())
+}
+
+/// Read the installed config of (wallet, permissionId) via `signerOf` / `factSourcesOf`.
+pub async fn policy_config(
+    provider: &Provider<Http>,
+    policy: Address,
+    wallet: Address,
+    permission_id: H256,
+) -> Result<PolicyInitConfig> {
+    let args = [
+        Token::Address(wallet),
+        Token::FixedBytes(permission_id.as_bytes().to_vec()),
+    ];
+    let signer_out = eth_call(provider, policy, "signerOf(address,bytes32)", &args).await?;
+    let sources_out = eth_call(provider, policy, "factSourcesOf(address,bytes32)", &args).await?;
+
+    let address_at = |tokens: &[Token], i: usize, what: &str| match tokens.get(i) {
+        Some(Token::Address(a)) => Ok(*a),
+        _ => Err(anyhow!("{what} returned malformed data")),
+    };
+    let signer = abi::decode(&[abi::ParamType::Address], &signer_out)
+        .context("signerOf returned malformed data")?;
+    let sources = abi::decode(
+        &[
+            abi::ParamType::Address,
+            abi::ParamType::Address,
+            abi::ParamType::Address,
+        ],
+        &sources_out,
+    )
+    .context("factSourcesOf returned malformed data")?;
+    Ok(PolicyInitConfig {
+        signer: address_at(&signer, 0, "signerOf")?,
+        state_view: address_at(&sources, 0, "factSourcesOf")?,
+        vts_orchestrator: address_at(&sources, 1, "factSourcesOf")?,
+        liquidity_hub: address_at(&sources, 2, "factSourcesOf")?,
+    })
+}
+
+/// The permission ids installed for `wallet` (`permissionIdsOf`).
+pub async fn permission_ids_of(
+    provider: &Provider<Http>,
+    policy: Address,
+    wallet: Address,
+) -> Result<Vec<H256>> {
+    let out = eth_call(
+        provider,
+        policy,
+        "permissionIdsOf(address)",
+        &[Token::Address(wallet)],
+    )
+    .await?;
+    let decoded = abi::decode(
+        &[abi::ParamType::Array(Box::new(abi::ParamType::FixedBytes(
+            32,
+        )))],
+        &out,
+    )
+    .context("permissionIdsOf returned malformed data")?;
+    match decoded.into_iter().next() {
+        Some(Token::Array(ids)) => ids
+            .into_iter()
+            .map(|id| match id {
+                Token::FixedBytes(id) if id.len() == 32 => Ok(H256::from_slice(&id)),
+                _ => Err(anyhow!("permissionIdsOf returned malformed data")),
+            })
+            .collect(),
+        _ => Err(anyhow!("permissionIdsOf returned malformed data")),
+    }
+}
+
+/// `eth_call` a view returning a single `bool`.
+async fn call_bool(
+    provider: &Provider<Http>,
+    to: Address,
+    signature: &str,
+    args: &[Token],
+) -> Result<bool> {
+    let out = eth_call(provider, to, signature, args).await?;
+    let decoded = abi::decode(&[abi::ParamType::Bool], &out)
+        .with_context(|| format!("{signature} returned malformed data: {out}"))?;
+    match decoded.first() {
+        Some(Token::Bool(b)) => Ok(*b),
+        _ => Err(anyhow!("{signature} returned malformed data: {out}")),
+    }
+}
+
+pub async fn eth_call(
+    provider: &Provider<Http>,
+    to: Address,
+    signature: &str,
+    args: &[Token],
+) -> Result<Bytes> {
+    let mut data = id(signature).to_vec();
+    data.extend_from_slice(&abi::encode(args));
+    let tx: TypedTransaction = TransactionRequest::new().to(to).data(data).into();
+    provider
+        .call(&tx, None)
+        .await
+        .with_context(|| format!("eth_call {signature} on {to:?} failed"))
+}
@@ -0,0 +1,93 @@
+//! Safe Transaction Builder batches for multisig-governed deployments.
+//!
+//! A Safe cannot send a bare `CREATE`, so the deploy goes through the `StylusDeployer` factory:
+//! `deploy(bytecode, initData, initValue, salt)` deploys with CREATE2 and activates in the same
+//! call. The value attached pays the activation data fee; the factory refunds the excess to the
+//! caller (the Safe). The batch JSON imports into the Safe{Wallet} Transaction Builder app, which
+//! proposes it to the Safe's owners. Nothing here signs or sends anything.
+
+use ethers::{
+    abi::Token,
+    types::{Address, Bytes, H256, U256},
+    utils::to_checksum,
+};
+use serde_json::{json, Value};
+
+use crate::direct::call_data;
+
+/// Transaction Builder file format version.
+pub const BATCH_VERSION: &str = "1.0";
+
+/// One call in a Safe batch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SafeCall {
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+}
+
+/// `StylusDeployer.deploy(init_code, "", 0, salt)` with `activation_value` attached.
+pub fn factory_deploy(
+    factory: Address,
+    init_code: &[u8],
+    salt: H256,
+    activation_value: U256,
+) -> SafeCall {
+    let data = call_data(
+        "deploy(bytes,bytes,uint256,bytes32)",
+        &[
+            Token::Bytes(init_code.to_vec()),
+            Token::Bytes(Vec::new()),
+            Token::Uint(U256::zero()),
+            Token::FixedBytes(salt.as_bytes().to_vec()),
+        ],
+    );
+    SafeCall {
+        to: factory,
+        value: activation_value,
+        data: data.into(),
+    }
+}
+
+/// A call to `account` carrying prebuilt calldata (eg Kernel `installModule`).
+pub fn account_call(account: Address, calldata: Bytes) -> SafeCall {
+    SafeCall {
+        to: account,
+        value: U256::zero(),
+        data: calldata,
+    }
+}
+
+/// Transaction Builder batch proposing `calls` from `safe` on `chain_id`.
+pub fn transaction_builder_batch(
+    chain_id: u64,
+    safe: Address,
+    name: &str,
+    description: &str,
+    calls: &[SafeCall],
+    created_at_ms: u64,
+) -> Value {
+    let transactions: Vec<Value> = calls
+        .iter()
+        .map(|c| {
+            json!({
+                "to": to_checksum(&c.to, None),
+                "value": c.value.to_string(),
+                "data": c.data.to_string(),
+                "contractMethod": null,
+                "contractInputsValues": null,
+            })
+        })
+        .collect();
+    json!({
+        "version": BATCH_VERSION,
+        "chainId": chain_id.to_string(),
+        "createdAt": created_at_ms,
+        "meta": {
+            "name": name,
+            "description": description,
+            "createdFromSafeAddress": to_checksum(&safe, None),
+        },
+        "transactions": transactions,
+    })
+}
@@ -0,0 +1,173 @@
+//! Dry run of a deployment on a fork before spending real gas.
+//!
+//! The fork must execute Stylus (a Nitro dev node, or a Tenderly fork of an Arbitrum chain);
+//! anvil has no ArbOS and cannot activate programs. On the fork we deploy and activate the WASM,
+//! install a permission on the deployer account itself (as the "wallet"), and `eth_call`
+//! `checkUserOpPolicy` with an envelope signed by the deployer. The sample program only uses
+//! fact-free checks, so placeholder fact sources are enough.
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    providers::Middleware,
+    signers::LocalWallet,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, TransactionRequest, H256,
+        U256,
+    },
+    utils::keccak256,
+};
+use fiet_maker_policy_encoder::{
+    encoder::{encode_envelope, encode_program, policy_intent_digest},
+    opcodes::Check,
+    types::IntentEnvelope,
+};
+
+use crate::{
+    deploy_wasm, direct,
+    install::{policy_init_data, PolicyInitConfig},
+};
+
+/// `POLICY_SUCCESS_UINT` returned by `checkUserOpPolicy`.
+const POLICY_SUCCESS: u64 = 0;
+
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    /// Where the program landed on the fork.
+    pub address: Address,
+    pub deployment_tx: H256,
+    pub install_tx: H256,
+}
+
+/// Deploy `wasm` on the fork behind `client` and exercise `onInstall` + `checkUserOpPolicy`.
+///
+/// `sources` are the fact sources written at install; `None` uses placeholder addresses.
+pub async fn simulate(
+    client: &direct::Client<LocalWallet>,
+    wasm: &[u8],
+    permission_id: H256,
+    sources: Option<[Address; 3]>,
+) -> Result<Simulation> {
+    let wallet = client.address();
+    let deployed = deploy_wasm(client, wasm)
+        .await
+        .context("fork deployment failed")?;
+    let policy = deployed.address;
+
+    let [state_view, vts_orchestrator, liquidity_hub] = sources.unwrap_or([
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        Address::from_low_u64_be(3),
+    ]);
+    let config = PolicyInitConfig {
+        signer: wallet,
+        state_view,
+        vts_orchestrator,
+        liquidity_hub,
+    };
+    let mut install_data = permission_id.as_bytes().to_vec();
+    install_data.extend_from_slice(&policy_init_data(&config));
+    let install = TransactionRequest::new().to(policy).data(direct::call_data(
+        "onInstall(bytes)",
+        &[Token::Bytes(install_data)],
+    ));
+    let install_tx = direct::send(client, install, "fork onInstall")
+        .await?
+        .transaction_hash;
+
+    let result = check_user_op(client, policy, permission_id).await?;
+    if result != U256::from(POLICY_SUCCESS) {
+        return Err(anyhow!(
+            "checkUserOpPolicy on the fork returned {result} (expected {POLICY_SUCCESS})"
+        ));
+    }
+
+    Ok(Simulation {
+        address: policy,
+        deployment_tx: deployed.deployment_tx,
+        install_tx,
+    })
+}
+
+/// `eth_call` `checkUserOpPolicy` from the wallet with a freshly signed sample envelope.
+async fn check_user_op(
+    client: &direct::Client<LocalWallet>,
+    policy: Address,
+    permission_id: H256,
+) -> Result<U256> {
+    let wallet = client.address();
+    let provider = client.provider();
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .context("failed fetching fork chain id")?;
+    let now = provider
+        .get_block(BlockNumber::Latest)
+        .await
+        .context("failed fetching the fork head")?
+        .ok_or_else(|| anyhow!("fork has no latest block"))?
+        .timestamp
+        .as_u64();
+
+    let call_data: Vec<u8> = Vec::new();
+    let call_bundle_hash = keccak256(&call_data);
+    let deadline = now + 3_600;
+    let mut envelope = IntentEnvelope {
+        version: 1,
+        nonce: alloy_primitives::U256::ZERO,
+        deadline,
+        call_bundle_hash: call_bundle_hash.into(),
+        program_bytes: encode_program(&[
+            Check::Deadline { deadline },
+            Check::Nonce {
+                expected: alloy_primitives::U256::ZERO,
+            },
+            Check::CallBundleHash {
+                hash: call_bundle_hash.into(),
+            },
+        ]),
+        signature: Vec::new(),
+        domain_chain_id: chain_id.as_u64(),
+        domain_verifying_contract: policy.0.into(),
+        wallet: wallet.0.into(),
+        permission_id: permission_id.0.into(),
+    };
+    let digest = policy_intent_digest(&envelope);
+    let signature = client
+        .signer()
+        .sign_hash(H256(digest.0))
+        .context("failed signing the sample envelope")?;
+    envelope.signature = signature.to_vec();
+
+    let user_op = Token::Tuple(vec![
+        Token::Address(wallet),
+        Token::Uint(U256::zero()),
+        Token::Bytes(Vec::new()),
+        Token::Bytes(call_data),
+        Token::FixedBytes(vec![0u8; 32]),
+        Token::Uint(U256::zero()),
+        Token::FixedBytes(vec![0u8; 32]),
+        Token::Bytes(Vec::new()),
+        Token::Bytes(encode_envelope(&envelope)),
+    ]);
+    let data = direct::call_data(
+        "checkUserOpPolicy(bytes32,(address,uint256,bytes,bytes,bytes32,uint256,bytes32,bytes,bytes))",
+        &[Token::FixedBytes(permission_id.as_bytes().to_vec()), user_op],
+    );
+    let tx: TypedTransaction = TransactionRequest::new()
+        .from(wallet)
+        .to(policy)
+        .data(data)
+        .into();
+    let out = provider
+        .call(&tx, None)
+        .await
+        .context("checkUserOpPolicy reverted on the fork")?;
+    match abi::decode(&[ParamType::Uint(256)], &out)
+        .context("checkUserOpPolicy returned malformed data")?
+        .first()
+    {
+        Some(Token::Uint(v)) => Ok(*v),
+        _ => Err(anyhow!("checkUserOpPolicy returned malformed data: {out}")),
+    }
+}
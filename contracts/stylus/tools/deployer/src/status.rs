@@ -0,0 +1,179 @@
+//! `status`: a snapshot of a deployed policy, and the changes between two snapshots.
+//!
+//! `--follow` tracks state rather than events, so it also reflects changes made before the policy
+//! logged them: `isInitialized` / `signerOf` for installs, and the `nonce_of` storage slot for
+//! consumed intents (every successful `checkUserOpPolicy` bumps it by one). The policy's logs are
+//! streamed alongside.
+
+use anyhow::{Context, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    providers::{Http, Middleware, Provider},
+    types::{Address, Filter, Log, H256, U256},
+    utils::keccak256,
+};
+use serde::Serialize;
+
+use crate::rpc::{self, ARB_WASM};
+
+/// Storage slot of `nonce_of` (declared after `used_ids` in `IntentPolicy`).
+const NONCE_OF_SLOT: u64 = 1;
+
+/// One (wallet, permission id) instance of the policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Instance {
+    pub wallet: Address,
+    pub permission_id: H256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct InstanceStatus {
+    #[serde(flatten)]
+    pub instance: Instance,
+    pub installed: bool,
+    pub signer: Address,
+    /// Intents consumed so far (the replay nonce).
+    pub nonce: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PolicyStatus {
+    pub policy: Address,
+    pub block: u64,
+    pub code_hash: Option<H256>,
+    /// ArbOS program version, `None` while the program is not activated.
+    pub program_version: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<InstanceStatus>,
+    /// Logs emitted by the policy since following started.
+    pub logs_seen: u64,
+}
+
+/// Read the current status of `policy` (and of `instance`, when given) at the latest block.
+pub async fn snapshot(
+    provider: &Provider<Http>,
+    policy: Address,
+    instance: Option<Instance>,
+) -> Result<PolicyStatus> {
+    let block = provider
+        .get_block_number()
+        .await
+        .context("failed fetching block number")?
+        .as_u64();
+    let code_hash = rpc::code_hash(provider, policy).await?;
+    let program_version = program_version(provider, policy).await;
+    let instance = match instance {
+        Some(instance) => Some(instance_status(provider, policy, instance).await?),
+        None => None,
+    };
+    Ok(PolicyStatus {
+        policy,
+        block,
+        code_hash,
+        program_version,
+        instance,
+        logs_seen: 0,
+    })
+}
+
+/// Logs emitted by `policy` in `from..=to`.
+pub async fn policy_logs(
+    provider: &Provider<Http>,
+    policy: Address,
+    from: u64,
+    to: u64,
+) -> Result<Vec<Log>> {
+    let filter = Filter::new().address(policy).from_block(from).to_block(to);
+    provider
+        .get_logs(&filter)
+        .await
+        .with_context(|| format!("failed fetching policy logs for blocks {from}..={to}"))
+}
+
+/// Human-readable changes from `prev` to `next`, oldest first.
+pub fn changes(prev: &PolicyStatus, next: &PolicyStatus) -> Vec<String> {
+    let mut out = Vec::new();
+    if prev.code_hash != next.code_hash {
+        out.push(match next.code_hash {
+            Some(hash) => format!("code changed to {hash:?}"),
+            None => "code removed".to_string(),
+        });
+    }
+    if prev.program_version != next.program_version {
+        out.push(match next.program_version {
+            Some(v) => format!("program activated (version {v})"),
+            None => "program no longer active".to_string(),
+        });
+    }
+    if let (Some(p), Some(n)) = (&prev.instance, &next.instance) {
+        if !p.installed && n.installed {
+            out.push(format!("installed (signer {:?})", n.signer));
+        } else if p.installed && !n.installed {
+            out.push("uninstalled".to_string());
+        } else if p.signer != n.signer {
+            out.push(format!("signer changed to {:?}", n.signer));
+        }
+        if n.nonce > p.nonce {
+            out.push(format!(
+                "{} intent(s) consumed (nonce {} -> {})",
+                n.nonce - p.nonce,
+                p.nonce,
+                n.nonce
+            ));
+        } else if n.nonce < p.nonce {
+            // Uninstall resets the nonce.
+            out.push(format!("nonce reset ({} -> {})", p.nonce, n.nonce));
+        }
+    }
+    out
+}
+
+async fn instance_status(
+    provider: &Provider<Http>,
+    policy: Address,
+    instance: Instance,
+) -> Result<InstanceStatus> {
+    let config =
+        rpc::policy_config(provider, policy, instance.wallet, instance.permission_id).await?;
+    let nonce = provider
+        .get_storage_at(policy, nonce_slot(instance), None)
+        .await
+        .context("failed reading the policy nonce slot")?;
+    Ok(InstanceStatus {
+        instance,
+        // `on_install` rejects zero fact sources, so a zero state view means "not installed".
+        installed: !config.state_view.is_zero(),
+        signer: config.signer,
+        nonce: U256::from_big_endian(nonce.as_bytes()),
+    })
+}
+
+/// `nonce_of[keccak256(wallet || permissionId)]`, laid out as a Solidity mapping.
+fn nonce_slot(instance: Instance) -> H256 {
+    let mut composite = Vec::with_capacity(20 + 32);
+    composite.extend_from_slice(instance.wallet.as_bytes());
+    composite.extend_from_slice(instance.permission_id.as_bytes());
+    let key = keccak256(composite);
+
+    let mut preimage = key.to_vec();
+    let mut slot = [0u8; 32];
+    U256::from(NONCE_OF_SLOT).to_big_endian(&mut slot);
+    preimage.extend_from_slice(&slot);
+    H256(keccak256(preimage))
+}
+
+async fn program_version(provider: &Provider<Http>, program: Address) -> Option<u16> {
+    // `programVersion` reverts for programs that are not (or no longer) active.
+    let out = rpc::eth_call(
+        provider,
+        ARB_WASM,
+        "programVersion(address)",
+        &[Token::Address(program)],
+    )
+    .await
+    .ok()?;
+    match abi::decode(&[ParamType::Uint(16)], &out).ok()?.first() {
+        Some(Token::Uint(v)) => Some(v.low_u32() as u16),
+        _ => None,
+    }
+}
@@ -0,0 +1,294 @@
+//! Post-deploy checks: the ABI smoke test, `cargo stylus verify`, the containerised reproducible
+//! build, and Arbiscan source verification.
+//!
+//! Each check records its failure on the returned outcome instead of returning an error, so a
+//! deploy that already landed still gets written down; the caller decides what fails the run.
+
+use std::{path::Path, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{providers::Middleware, types::H256};
+use serde_json::{json, Value};
+use tracing::{debug, info, instrument, warn};
+
+use crate::{
+    arbiscan,
+    cargo_stylus::{combined_output, CargoStylus},
+    deployments,
+    redact::Redactor,
+    reproducible, rpc,
+};
+
+/// Result of probing the deployed policy's ABI surface.
+#[derive(Debug)]
+pub struct SmokeTest {
+    /// `None` when every probe answered as expected.
+    pub error: Option<String>,
+}
+
+/// Call `isModuleType(5)` (expect `true`) and `isInitialized(address(0))` (expect `false`).
+///
+/// Both are pure views present on every build of the policy, so a failure here means the WASM
+/// does not expose the Kernel `IPolicy` surface integrators will call.
+#[instrument(name = "smoke_test", skip(rpc_url))]
+pub async fn smoke_test(rpc_url: &str, address: &str) -> SmokeTest {
+    let result = async {
+        let provider = rpc::provider(rpc_url)?;
+        let address = address
+            .parse()
+            .with_context(|| format!("invalid deployed address {address}"))?;
+        rpc::smoke_test_policy(&provider, address).await
+    }
+    .await;
+
+    SmokeTest {
+        error: result.err().map(|e| format!("{e:#}")),
+    }
+}
+
+/// Outcome of `cargo stylus verify` for a deployment.
+#[derive(Debug)]
+pub struct Verification {
+    pub verified: bool,
+    pub deployment_tx: Option<String>,
+    pub output: String,
+    /// Why verification could not run (no deployment tx, `cargo stylus` missing, ...).
+    pub error: Option<String>,
+}
+
+/// Run `cargo stylus verify` against the first of `tx_hashes` (the deployment tx).
+#[instrument(name = "verify", skip_all)]
+pub fn cargo_stylus_verify(
+    stylus: &CargoStylus,
+    tx_hashes: &[String],
+    redactor: &Redactor,
+) -> Verification {
+    let deployment_tx = tx_hashes.first().cloned();
+    let result = match deployment_tx {
+        Some(ref tx) => run_cargo_stylus_verify(stylus, tx),
+        None => Err(anyhow!(
+            "cannot verify: no deployment tx hash was parsed from `cargo stylus deploy` output"
+        )),
+    };
+    match result {
+        Ok((verified, output)) => Verification {
+            verified,
+            deployment_tx,
+            output,
+            error: None,
+        },
+        Err(err) => {
+            warn!("cargo stylus verify failed: {err:#}");
+            Verification {
+                verified: false,
+                deployment_tx,
+                output: String::new(),
+                error: Some(redactor.persist(&format!("{err:#}"), 4_000)),
+            }
+        }
+    }
+}
+
+/// `cargo stylus verify` against `deployment_tx`: whether it verified, and its output.
+fn run_cargo_stylus_verify(stylus: &CargoStylus, deployment_tx: &str) -> Result<(bool, String)> {
+    // cargo-stylus verifies against the deployment tx (not the address): it replays the
+    // reproducible build and compares the resulting code with the tx's init code.
+    let output = stylus
+        .on_chain(&["verify"])
+        .arg("--deployment-tx")
+        .arg(deployment_tx)
+        .output()
+        .context("failed to run `cargo stylus verify`")?;
+    let combined = combined_output(&output);
+
+    debug!(output = %combined, "cargo stylus verify output");
+
+    // A non-zero exit is a verification failure too (eg hash mismatch), so record rather than bail.
+    let lower = combined.to_ascii_lowercase();
+    let verified = output.status.success()
+        && lower.contains("verified")
+        && !lower.contains("did not verify")
+        && !lower.contains("mismatch");
+    Ok((verified, combined))
+}
+
+/// Outcome of rebuilding the contract in a pinned container and comparing it with the deployment.
+#[derive(Debug)]
+pub struct ReproducibleBuild {
+    pub image: String,
+    /// Codehash at the deployment address.
+    pub deployed: Option<H256>,
+    pub rebuild: Option<reproducible::Rebuild>,
+    pub error: Option<String>,
+}
+
+impl ReproducibleBuild {
+    pub fn matches(&self) -> bool {
+        self.rebuild
+            .as_ref()
+            .is_some_and(|r| r.matches(self.deployed))
+    }
+
+    pub fn status(&self) -> &'static str {
+        match (&self.rebuild, self.matches()) {
+            (None, _) => "failed",
+            (Some(_), true) => "match",
+            (Some(_), false) => "mismatch",
+        }
+    }
+
+    pub fn record(&self, checked_at: String) -> deployments::ReproducibleBuild {
+        deployments::ReproducibleBuild {
+            status: self.status().to_string(),
+            image: self.image.clone(),
+            wasm_hash: self.rebuild.as_ref().map(|r| r.wasm_hash),
+            code_hash: self.rebuild.as_ref().map(|r| r.code_hash),
+            deployed_code_hash: self.deployed,
+            checked_at,
+            error: self.error.clone(),
+        }
+    }
+
+    /// Fail unless the rebuild completed and matches the code deployed at `address`.
+    /// `deployments_path` is where the result was recorded, for the error message.
+    pub fn ensure_matches(
+        &self,
+        contract_key: &str,
+        deployments_path: &Path,
+        address: &str,
+    ) -> Result<()> {
+        match self.rebuild {
+            None => Err(anyhow!(
+                "reproducible build of `{}` in {} failed (recorded in {}): {}",
+                contract_key,
+                self.image,
+                deployments_path.display(),
+                self.error.as_deref().unwrap_or("unknown error")
+            )),
+            Some(_) if !self.matches() => Err(anyhow!(
+                "the {} rebuild of `{}` does not match the deployed code at {} (recorded in {})",
+                self.image,
+                contract_key,
+                address,
+                deployments_path.display()
+            )),
+            Some(ref r) => {
+                info!(
+                    "reproduced `{}` in {}: codehash {:?}",
+                    contract_key, self.image, r.code_hash
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Rebuild `contract_dir` in `image` (the toolchain's pinned image when `None`) and compare with
+/// `deployed`.
+#[instrument(name = "reproduce", skip_all)]
+pub fn reproduce(
+    contract_dir: &Path,
+    contract_key: &str,
+    image: Option<&str>,
+    deployed: Option<H256>,
+    redactor: &Redactor,
+) -> ReproducibleBuild {
+    let image = match image {
+        Some(image) => Ok(image.to_string()),
+        None => reproducible::toolchain_image(contract_dir),
+    };
+    let (image, result) = match image {
+        Ok(image) => {
+            info!("rebuilding `{contract_key}` in {image}");
+            let result = reproducible::rebuild(contract_dir, &image);
+            (image, result)
+        }
+        Err(err) => ("unknown".to_string(), Err(err)),
+    };
+    match result {
+        Ok(rebuild) => ReproducibleBuild {
+            image,
+            deployed,
+            rebuild: Some(rebuild),
+            error: None,
+        },
+        Err(err) => {
+            warn!("reproducible build failed: {err:#}");
+            ReproducibleBuild {
+                image,
+                deployed,
+                rebuild: None,
+                error: Some(redactor.persist(&format!("{err:#}"), 4_000)),
+            }
+        }
+    }
+}
+
+/// How to submit the source to Arbiscan.
+#[derive(Debug)]
+pub struct ArbiscanOptions<'a> {
+    pub api_url: &'a str,
+    /// Without a key only the manual-verification details are recorded.
+    pub api_key: Option<&'a str>,
+    pub contract_name: &'a str,
+    pub license_type: u8,
+}
+
+/// Arbiscan verification: always records the manual-verification details, and submits + polls
+/// when an API key is configured.
+#[instrument(name = "arbiscan", skip(contract_dir, rpc_url, options))]
+pub async fn arbiscan(
+    contract_dir: &Path,
+    rpc_url: &str,
+    address: &str,
+    options: &ArbiscanOptions<'_>,
+) -> Value {
+    let source = arbiscan::StylusSource::detect(contract_dir);
+    let chain_id = match rpc::provider(rpc_url) {
+        Ok(p) => p.get_chainid().await.ok().map(|c| c.as_u64()),
+        Err(_) => None,
+    };
+    let mut out = json!({
+        "status": "manual",
+        "explorer_url": chain_id.and_then(|c| arbiscan::explorer_address_url(c, address)),
+        "source": source.to_json(),
+        "contract_name": options.contract_name,
+        "license_type": options.license_type,
+    });
+
+    let (Some(api_key), Some(chain_id)) = (options.api_key, chain_id) else {
+        info!("recorded Arbiscan verification details for manual submission");
+        return out;
+    };
+    let client = arbiscan::Client::new(options.api_url, api_key, chain_id);
+    let result = async {
+        let guid = client
+            .submit(
+                address,
+                options.contract_name,
+                options.license_type,
+                &source,
+            )
+            .await?;
+        let status = client
+            .wait_for_status(&guid, 6, Duration::from_secs(5))
+            .await?;
+        Ok::<_, anyhow::Error>((guid, status))
+    }
+    .await;
+
+    match result {
+        Ok((guid, status)) => {
+            info!("Arbiscan verification {guid}: {status}");
+            out["status"] = json!("submitted");
+            out["guid"] = json!(guid);
+            out["result"] = json!(status);
+        }
+        Err(err) => {
+            warn!("Arbiscan verification failed: {err:#}");
+            out["status"] = json!("failed");
+            out["error"] = json!(format!("{err:#}"));
+        }
+    }
+    out
+}
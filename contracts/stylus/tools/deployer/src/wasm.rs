@@ -0,0 +1,99 @@
+//! Stylus init code built from a compiled WASM, without `cargo stylus get-initcode`.
+//!
+//! Mirrors cargo-stylus: the program is `0xEFF000 || dictionary byte || brotli(wasm)`, wrapped in
+//! a 43-byte EVM prelude (`PUSH32 len; DUP1; PUSH1 43; PUSH1 0; CODECOPY; PUSH1 0; RETURN` plus a
+//! version byte) that returns the program as the runtime code.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use alloy_primitives::keccak256;
+use anyhow::{anyhow, Context, Result};
+
+/// Stylus EOF-style code prefix followed by the "empty dictionary" byte.
+const PROGRAM_PREFIX: [u8; 4] = [0xEF, 0xF0, 0x00, 0x00];
+
+/// Prelude length including the trailing version byte; the program code starts here.
+pub const PRELUDE_LEN: usize = 42 + 1;
+
+/// Brotli settings cargo-stylus compresses with.
+const BROTLI_QUALITY: u32 = 11;
+const BROTLI_WINDOW: u32 = 22;
+
+/// Compress `wasm` into the on-chain Stylus program bytes.
+pub fn program_code(wasm: &[u8]) -> Result<Vec<u8>> {
+    if !wasm.starts_with(b"\0asm") {
+        return Err(anyhow!("input is not a WASM module (missing \\0asm magic)"));
+    }
+    let mut code = PROGRAM_PREFIX.to_vec();
+    {
+        let mut writer =
+            brotli::CompressorWriter::new(&mut code, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+        writer
+            .write_all(wasm)
+            .context("failed brotli-compressing the WASM")?;
+    }
+    Ok(code)
+}
+
+/// Init code that deploys `wasm` as a Stylus program (activation is a separate step).
+pub fn init_code(wasm: &[u8]) -> Result<Vec<u8>> {
+    let code = program_code(wasm)?;
+    let mut len = [0u8; 32];
+    len[24..].copy_from_slice(&(code.len() as u64).to_be_bytes());
+
+    let mut init = Vec::with_capacity(PRELUDE_LEN + code.len());
+    init.push(0x7f); // PUSH32
+    init.extend_from_slice(&len);
+    init.push(0x80); // DUP1
+    init.extend_from_slice(&[0x60, PRELUDE_LEN as u8]); // PUSH1 prelude + version
+    init.extend_from_slice(&[0x60, 0x00]); // PUSH1 0
+    init.push(0x39); // CODECOPY
+    init.extend_from_slice(&[0x60, 0x00]); // PUSH1 0
+    init.push(0xf3); // RETURN
+    init.push(0x00); // version
+    init.extend_from_slice(&code);
+    Ok(init)
+}
+
+/// The single `.wasm` in `contract_dir`'s release target directory, if it has been built.
+pub fn release_wasm(contract_dir: &Path) -> Result<Option<PathBuf>> {
+    let release_dir = contract_dir.join("target/wasm32-unknown-unknown/release");
+    if !release_dir.is_dir() {
+        return Ok(None);
+    }
+    let mut wasm_files = Vec::new();
+    for entry in fs::read_dir(&release_dir)
+        .with_context(|| format!("failed listing {}", release_dir.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("wasm") {
+            wasm_files.push(path);
+        }
+    }
+    match wasm_files.len() {
+        0 => Ok(None),
+        1 => Ok(wasm_files.pop()),
+        _ => Err(anyhow!(
+            "found multiple WASM files in {}; pass --wasm-path to pick one",
+            release_dir.display()
+        )),
+    }
+}
+
+/// Keccak256 (hex, 0x-prefixed) of `wasm_path`, or of the release artefact when `None`; `None`
+/// when there is no artefact to hash.
+pub fn local_hash(wasm_path: Option<&Path>, contract_dir: &Path) -> Result<Option<String>> {
+    let path = match wasm_path {
+        Some(p) => p.to_path_buf(),
+        None => match release_wasm(contract_dir)? {
+            Some(p) => p,
+            None => return Ok(None),
+        },
+    };
+    let bytes = fs::read(&path).with_context(|| format!("failed reading {}", path.display()))?;
+    Ok(Some(format!("{}", keccak256(&bytes))))
+}
@@ -0,0 +1,195 @@
+//! `bootstrap-devnet`: a working intent environment on a local Nitro node in one command.
+//!
+//! Deploys the fact-source mocks, Kernel/EntryPoint and the intent policy, then writes
+//! `deployments/{infra,kernel,stylus}.<network>.json` (the files the `justfile` reads) and merges
+//! everything the Bun harness needs into `e2e/.env`. Without `--rpc-url` a `nitro-node --dev`
+//! container is started and left running.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use ethers::types::H256;
+use stylus_deployer::{deployments, env_out, WasmDeployment};
+use stylus_e2e::{
+    init_logging,
+    nitro::{self, NitroNode, DEFAULT_NITRO_IMAGE, DEV_PRIVATE_KEY},
+    stack::{self, StackConfig},
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing::info;
+
+/// Contract key of the policy in the Stylus deployments file (as `just stylus_deploy_policy`).
+const CONTRACT_KEY: &str = "intent-policy";
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Deploy mocks, Kernel and the intent policy to a devnet and write deployments + .env"
+)]
+struct Cli {
+    /// The `contracts/stylus` directory.
+    #[arg(long, default_value = ".")]
+    stylus_dir: PathBuf,
+
+    /// Node to deploy to; without it a Nitro dev node is started in Docker and left running.
+    #[arg(long, env = "RPC_URL")]
+    rpc_url: Option<String>,
+
+    #[arg(long, env = "NITRO_IMAGE", default_value = DEFAULT_NITRO_IMAGE)]
+    nitro_image: String,
+
+    /// Host port for a started dev node's RPC.
+    #[arg(long, default_value_t = 8547)]
+    port: u16,
+
+    /// Seconds to wait for the node's RPC.
+    #[arg(long, default_value_t = 120)]
+    startup_timeout: u64,
+
+    /// Funded deployer key; defaults to the dev node's prefunded account.
+    #[arg(long, env = "PRIVATE_KEY", hide_env_values = true, default_value = DEV_PRIVATE_KEY, hide_default_value = true)]
+    private_key: String,
+
+    /// Key the Bun harness signs with (defaults to `--private-key`).
+    #[arg(long, env = "OWNER_PRIVATE_KEY", hide_env_values = true)]
+    owner_private_key: Option<String>,
+
+    /// `bytes32` permission id for the harness (defaults to `just env_init_permission_id`'s).
+    #[arg(long, env = "PERMISSION_ID")]
+    permission_id: Option<H256>,
+
+    /// Suffix of the deployments files (`deployments/<kind>.<network>.json`).
+    #[arg(long, default_value = "nitro")]
+    network: String,
+
+    /// `.env` to merge the variables into (relative to `--stylus-dir`).
+    #[arg(long, env = "E2E_ENV_PATH", default_value = "e2e/.env")]
+    env_path: PathBuf,
+
+    /// Prebuilt policy WASM; otherwise built with `cargo stylus check` against the node.
+    #[arg(long)]
+    wasm_path: Option<PathBuf>,
+
+    /// -v for debug logs, -vv for trace.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    init_logging(cli.verbose);
+
+    tokio::select! {
+        res = run(&cli) => res,
+        _ = tokio::signal::ctrl_c() => Err(anyhow!("interrupted")),
+    }
+}
+
+async fn run(cli: &Cli) -> Result<()> {
+    let stylus_dir = cli
+        .stylus_dir
+        .canonicalize()
+        .with_context(|| format!("stylus dir {} not found", cli.stylus_dir.display()))?;
+
+    let rpc_url = match &cli.rpc_url {
+        Some(url) => url.clone(),
+        None => {
+            let node = NitroNode::start(&cli.nitro_image, cli.port)?;
+            let rpc_url = node.rpc_url.clone();
+            let container = node.keep();
+            info!(%rpc_url, %container, "dev node started (stop it with `docker rm -f {container}`)");
+            rpc_url
+        }
+    };
+    let chain_id = nitro::wait_for_rpc(&rpc_url, Duration::from_secs(cli.startup_timeout)).await?;
+    info!(%rpc_url, chain_id, "node is up");
+
+    let stack = stack::deploy(&StackConfig {
+        stylus_dir: &stylus_dir,
+        rpc_url: &rpc_url,
+        chain_id,
+        private_key: &cli.private_key,
+        wasm_path: cli.wasm_path.as_deref(),
+        label: &cli.network,
+    })
+    .await?;
+
+    let stylus_deployments = stylus_dir.join(format!("deployments/stylus.{}.json", cli.network));
+    write_stylus_deployments(&stylus_deployments, &cli.network, &rpc_url, &stack.policy)?;
+
+    let env_path = stylus_dir.join(&cli.env_path);
+    let owner_private_key = cli.owner_private_key.as_deref().unwrap_or(&cli.private_key);
+    let permission_id = cli
+        .permission_id
+        .unwrap_or_else(stack::default_permission_id);
+    env_out::write(
+        &env_path,
+        &stack.env_vars(&rpc_url, chain_id, owner_private_key, permission_id),
+    )?;
+
+    for (name, address) in &stack.addresses {
+        println!("{name}={address:?}");
+    }
+    println!("PERMISSION_ID={permission_id:?}");
+    info!(
+        infra = %stack.infra_deployments.display(),
+        kernel = %stack.kernel_deployments.display(),
+        stylus = %stylus_deployments.display(),
+        env = %env_path.display(),
+        "devnet ready; next: just e2e_test"
+    );
+    Ok(())
+}
+
+/// Record the policy as the only version: a fresh devnet has no earlier deployments to roll
+/// back to, so any previous file for this network is replaced rather than appended to.
+fn write_stylus_deployments(
+    path: &Path,
+    network: &str,
+    rpc_url: &str,
+    policy: &WasmDeployment,
+) -> Result<()> {
+    let now = OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .context("failed formatting timestamp")?;
+    let mut tx_hashes = vec![format!("{:?}", policy.deployment_tx)];
+    tx_hashes.extend(policy.activation_tx.map(|tx| format!("{tx:?}")));
+    let entry = deployments::DeploymentEntry {
+        address: format!("{:?}", policy.address),
+        address_source: Some("receipt".to_string()),
+        rpc_url: Some(rpc_url.to_string()),
+        deployed_at: Some(now.clone()),
+        tx_hashes,
+        version: Some(1),
+        latest: Some(true),
+        activation: Some(deployments::Activation {
+            status: match policy.activation_tx {
+                Some(_) => "activated",
+                None => "already_activated",
+            }
+            .to_string(),
+            tx_hash: policy.activation_tx.map(|tx| format!("{tx:?}")),
+            attempts: 1,
+            last_error: None,
+        }),
+        code_hash: Some(policy.code_hash),
+        smoke_test: Some(deployments::SmokeTest {
+            status: "passed".to_string(),
+            checked_at: now.clone(),
+            error: None,
+        }),
+        ..Default::default()
+    };
+
+    let mut file = deployments::DeploymentsFile::new();
+    file.network = Some(network.to_string());
+    file.updated_at = Some(now);
+    file.history
+        .insert(CONTRACT_KEY.to_string(), vec![entry.clone()]);
+    file.deployments.insert(CONTRACT_KEY.to_string(), entry);
+    deployments::save(path, &file)
+}
@@ -0,0 +1,25 @@
+//! Devnet tooling shared by the `e2e` runner and `bootstrap-devnet`.
+
+use tracing_subscriber::EnvFilter;
+
+pub mod nitro;
+pub mod stack;
+
+/// Log to stderr: `-v` for debug, `-vv` for trace (`RUST_LOG` overrides).
+pub fn init_logging(verbosity: u8) {
+    let level = match verbosity {
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(format!(
+            "warn,e2e={level},bootstrap_devnet={level},stylus_e2e={level},stylus_deployer={level}"
+        ))
+    });
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .init();
+}
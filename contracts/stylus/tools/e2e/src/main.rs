@@ -0,0 +1,175 @@
+//! `e2e`: boot a Nitro dev node, deploy the whole stack, and push a real UserOperation through it.
+//!
+//! 1. Start `nitro-node --dev` in Docker (or attach to `--rpc-url`).
+//! 2. Deploy the fact-source mocks and EntryPoint v0.7 / Kernel v3.3 / MultiChainSigner /
+//!    CallPolicy with the repo's Foundry scripts.
+//! 3. Build the policy, then deploy and activate it with `stylus_deployer::deploy_wasm`.
+//! 4. Run the Bun tests that submit `EntryPoint.handleOps`: the EOA delegates to Kernel (7702),
+//!    enables the permission (signer + CallPolicy + intent policy), signs the intent envelope, and
+//!    Kernel slices the per-policy signature before calling `checkUserOpPolicy`.
+//!
+//! Every step fails the run, so a green run means the Kernel signature-slicing path works end to
+//! end on a fresh chain.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Parser;
+use stylus_e2e::{
+    init_logging,
+    nitro::{self, NitroNode, DEFAULT_NITRO_IMAGE, DEV_PRIVATE_KEY},
+    stack::{self, StackConfig},
+};
+use tokio::process::Command;
+use tracing::{info, info_span, Instrument};
+
+/// Tests that submit `handleOps` through the Kernel permission pipeline.
+const DEFAULT_TESTS: &[&str] = &[
+    "src/tests/mm-position-manager.intent-gated-write.test.ts",
+    "src/tests/policy-aggregation.callpolicy-fails.test.ts",
+];
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Boot a Nitro dev node, deploy Kernel + the intent policy, and submit real UserOps"
+)]
+struct Cli {
+    /// The `contracts/stylus` directory.
+    #[arg(long, default_value = ".")]
+    stylus_dir: PathBuf,
+
+    /// Use an already-running node instead of starting one in Docker.
+    #[arg(long, env = "E2E_RPC_URL")]
+    rpc_url: Option<String>,
+
+    #[arg(long, env = "NITRO_IMAGE", default_value = DEFAULT_NITRO_IMAGE)]
+    nitro_image: String,
+
+    /// Host port for the dev node's RPC.
+    #[arg(long, default_value_t = 8547)]
+    port: u16,
+
+    /// Leave the dev node running after the run (for debugging).
+    #[arg(long)]
+    keep_node: bool,
+
+    /// Funded key for deployments and the 7702 EOA; defaults to the dev node's prefunded account.
+    #[arg(long, env = "E2E_PRIVATE_KEY", hide_env_values = true, default_value = DEV_PRIVATE_KEY, hide_default_value = true)]
+    private_key: String,
+
+    /// Prebuilt policy WASM; otherwise built with `cargo stylus check` against the node.
+    #[arg(long)]
+    wasm_path: Option<PathBuf>,
+
+    /// Bun test files to run (relative to `e2e/`); defaults to the `handleOps` suites.
+    #[arg(long = "test")]
+    tests: Vec<String>,
+
+    /// Seconds to wait for the node's RPC.
+    #[arg(long, default_value_t = 120)]
+    startup_timeout: u64,
+
+    /// -v for debug logs, -vv for trace.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    init_logging(cli.verbose);
+
+    tokio::select! {
+        res = run(&cli) => res,
+        _ = tokio::signal::ctrl_c() => Err(anyhow!("interrupted")),
+    }
+}
+
+async fn run(cli: &Cli) -> Result<()> {
+    let stylus_dir = cli
+        .stylus_dir
+        .canonicalize()
+        .with_context(|| format!("stylus dir {} not found", cli.stylus_dir.display()))?;
+
+    let node = match cli.rpc_url {
+        Some(_) => None,
+        None => Some(NitroNode::start(&cli.nitro_image, cli.port)?),
+    };
+    let rpc_url = match (&cli.rpc_url, &node) {
+        (Some(url), _) => url.clone(),
+        (None, Some(node)) => node.rpc_url.clone(),
+        (None, None) => unreachable!("a node is started when no RPC URL is given"),
+    };
+    // Held until the end of the run; dropping it removes the container.
+    let _node = match node {
+        Some(node) if cli.keep_node => {
+            let container = node.keep();
+            info!(%rpc_url, %container, "dev node will be left running");
+            None
+        }
+        node => node,
+    };
+
+    let chain_id = nitro::wait_for_rpc(&rpc_url, Duration::from_secs(cli.startup_timeout)).await?;
+    info!(%rpc_url, chain_id, "node is up");
+
+    let stack = stack::deploy(&StackConfig {
+        stylus_dir: &stylus_dir,
+        rpc_url: &rpc_url,
+        chain_id,
+        private_key: &cli.private_key,
+        wasm_path: cli.wasm_path.as_deref(),
+        label: "e2e",
+    })
+    .await?;
+    let env = stack.env_vars(
+        &rpc_url,
+        chain_id,
+        &cli.private_key,
+        stack::default_permission_id(),
+    );
+
+    run_bun_tests(&stylus_dir.join("e2e"), &env, &cli.tests)
+        .instrument(info_span!("userops"))
+        .await?;
+    info!("e2e passed");
+    Ok(())
+}
+
+async fn run_bun_tests(e2e_dir: &Path, env: &[(String, String)], tests: &[String]) -> Result<()> {
+    let status = Command::new("bun")
+        .arg("install")
+        .current_dir(e2e_dir)
+        .kill_on_drop(true)
+        .status()
+        .await
+        .context("failed running bun (is it installed?)")?;
+    if !status.success() {
+        bail!("bun install failed ({status})");
+    }
+
+    let tests: Vec<&str> = if tests.is_empty() {
+        DEFAULT_TESTS.to_vec()
+    } else {
+        tests.iter().map(String::as_str).collect()
+    };
+    info!(?tests, "running UserOp tests");
+    // dotenv does not override variables that are already set, so a local `e2e/.env` is ignored
+    // for everything we pass here.
+    let status = Command::new("bunx")
+        .args(["vitest", "run"])
+        .args(&tests)
+        .envs(env.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+        .current_dir(e2e_dir)
+        .kill_on_drop(true)
+        .status()
+        .await
+        .context("failed running bunx vitest")?;
+    if !status.success() {
+        bail!("UserOp tests failed ({status})");
+    }
+    Ok(())
+}
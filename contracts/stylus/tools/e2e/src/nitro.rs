@@ -0,0 +1,99 @@
+//! A throwaway Nitro dev node in Docker.
+//!
+//! `--dev` runs a single-node chain with Stylus enabled and one prefunded account
+//! ([`DEV_PRIVATE_KEY`]). The container is removed when [`NitroNode`] is dropped.
+
+use std::{process::Command, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethers::providers::Middleware;
+use tracing::{debug, info, warn};
+
+/// Nitro release with Stylus and EIP-7702 (ArbOS 40) enabled in dev mode.
+pub const DEFAULT_NITRO_IMAGE: &str = "offchainlabs/nitro-node:v3.7.1-926f1ab";
+
+/// Prefunded account of `nitro-node --dev`.
+pub const DEV_PRIVATE_KEY: &str =
+    "0xb6b15c8cb491557369f3c7d2c287b053eb229daa9c22138887752191c9f8a6f9";
+
+pub struct NitroNode {
+    container: String,
+    pub rpc_url: String,
+}
+
+impl NitroNode {
+    /// Start `image` in dev mode with its RPC published on `port`.
+    pub fn start(image: &str, port: u16) -> Result<Self> {
+        let container = format!("fiet-e2e-nitro-{}", std::process::id());
+        info!(%image, %container, port, "starting nitro dev node");
+        let out = Command::new("docker")
+            .args(["run", "-d", "--rm", "--name", &container, "-p"])
+            .arg(format!("{port}:8547"))
+            .arg(image)
+            .args([
+                "--dev",
+                "--http.addr=0.0.0.0",
+                "--http.port=8547",
+                "--http.vhosts=*",
+                "--http.api=net,web3,eth,arb,debug",
+            ])
+            .output()
+            .context("failed running docker (is it installed and on PATH?)")?;
+        if !out.status.success() {
+            bail!(
+                "docker run {image} failed: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+        }
+        Ok(Self {
+            container,
+            rpc_url: format!("http://127.0.0.1:{port}"),
+        })
+    }
+
+    /// Leave the container running past this process; returns its name for `docker rm -f`.
+    pub fn keep(self) -> String {
+        let container = self.container.clone();
+        std::mem::forget(self);
+        container
+    }
+}
+
+impl Drop for NitroNode {
+    fn drop(&mut self) {
+        info!(container = %self.container, "stopping nitro dev node");
+        match Command::new("docker")
+            .args(["rm", "-f", &self.container])
+            .output()
+        {
+            Ok(out) if out.status.success() => {}
+            Ok(out) => warn!(
+                container = %self.container,
+                stderr = %String::from_utf8_lossy(&out.stderr).trim(),
+                "failed removing nitro container"
+            ),
+            Err(err) => warn!(container = %self.container, %err, "failed running docker rm"),
+        }
+    }
+}
+
+/// Poll `rpc_url` until it answers `eth_chainId`, returning the chain id.
+pub async fn wait_for_rpc(rpc_url: &str, timeout: Duration) -> Result<u64> {
+    let provider = stylus_deployer::rpc::provider(rpc_url)?;
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        match provider.get_chainid().await {
+            Ok(id) => return Ok(id.as_u64()),
+            Err(err) if tokio::time::Instant::now() < deadline => {
+                debug!(%err, "RPC not ready yet");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Err(err) => {
+                return Err(anyhow!(err)).context(format!(
+                    "RPC at {rpc_url} did not come up within {}s",
+                    timeout.as_secs()
+                ))
+            }
+        }
+    }
+}
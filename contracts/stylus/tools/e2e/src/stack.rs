@@ -0,0 +1,213 @@
+//! Deploy the full devnet stack: fact-source mocks, Kernel/EntryPoint, and the intent policy.
+//!
+//! The EVM side goes through the repo's Foundry scripts (so the addresses land in the same
+//! `deployments/*.json` files the `justfile` reads); the policy is built with `cargo stylus check`
+//! and deployed with [`stylus_deployer::deploy_wasm`].
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethers::{
+    middleware::SignerMiddleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, H256},
+    utils::keccak256,
+};
+use stylus_deployer::{deploy_wasm, rpc, WasmDeployment};
+use tokio::process::Command;
+use tracing::{info, info_span, Instrument};
+
+/// Addresses written by `DeployStylusE2EInfra.s.sol`.
+pub const INFRA_KEYS: &[&str] = &[
+    "STATE_VIEW_ADDRESS",
+    "VTS_ORCHESTRATOR_ADDRESS",
+    "LIQUIDITY_HUB_ADDRESS",
+    "MM_POSITION_MANAGER_ADDRESS",
+    "POSITION_MANAGER_ADDRESS",
+];
+
+/// Addresses written by `DeployDevnet.s.sol`.
+pub const KERNEL_KEYS: &[&str] = &[
+    "ENTRYPOINT_ADDRESS",
+    "KERNEL_IMPLEMENTATION_ADDRESS",
+    "MULTICHAIN_SIGNER_ADDRESS",
+    "CALL_POLICY_ADDRESS",
+];
+
+pub struct StackConfig<'a> {
+    /// The `contracts/stylus` directory.
+    pub stylus_dir: &'a Path,
+    pub rpc_url: &'a str,
+    pub chain_id: u64,
+    pub private_key: &'a str,
+    /// Prebuilt policy WASM; built with `cargo stylus check` when `None`.
+    pub wasm_path: Option<&'a Path>,
+    /// Suffix of the Foundry deployments files (`deployments/{infra,kernel}.<label>.json`).
+    pub label: &'a str,
+}
+
+pub struct Stack {
+    /// Every deployed address, keyed by its env var name (`INTENT_POLICY_ADDRESS`, ...).
+    pub addresses: BTreeMap<&'static str, Address>,
+    pub policy: WasmDeployment,
+    pub infra_deployments: PathBuf,
+    pub kernel_deployments: PathBuf,
+}
+
+impl Stack {
+    /// The variables the Bun E2E harness reads (`e2e/src/setup.ts`).
+    pub fn env_vars(
+        &self,
+        rpc_url: &str,
+        chain_id: u64,
+        owner_private_key: &str,
+        permission_id: H256,
+    ) -> Vec<(String, String)> {
+        let mut vars: Vec<(String, String)> = vec![
+            ("RPC_URL".into(), rpc_url.to_string()),
+            ("CHAIN_ID".into(), chain_id.to_string()),
+            ("OWNER_PRIVATE_KEY".into(), owner_private_key.to_string()),
+            ("PERMISSION_ID".into(), format!("{permission_id:?}")),
+        ];
+        vars.extend(
+            self.addresses
+                .iter()
+                .map(|(name, address)| (name.to_string(), format!("{address:?}"))),
+        );
+        vars
+    }
+}
+
+/// Deploy everything, then check each address has code and smoke-test the policy.
+pub async fn deploy(config: &StackConfig<'_>) -> Result<Stack> {
+    let deployments = config.stylus_dir.join("deployments");
+    fs::create_dir_all(&deployments)
+        .with_context(|| format!("failed creating {}", deployments.display()))?;
+    let infra_deployments = deployments.join(format!("infra.{}.json", config.label));
+    let kernel_deployments = deployments.join(format!("kernel.{}.json", config.label));
+
+    forge_script(
+        config,
+        "script/DeployStylusE2EInfra.s.sol:DeployStylusE2EInfra",
+        &infra_deployments,
+    )
+    .instrument(info_span!("infra"))
+    .await?;
+    forge_script(
+        config,
+        "script/DeployDevnet.s.sol:DeployDevnet",
+        &kernel_deployments,
+    )
+    .instrument(info_span!("kernel"))
+    .await?;
+
+    let mut addresses = read_addresses(&infra_deployments, INFRA_KEYS)?;
+    addresses.extend(read_addresses(&kernel_deployments, KERNEL_KEYS)?);
+
+    let policy = deploy_policy(config)
+        .instrument(info_span!("policy"))
+        .await?;
+    addresses.insert("INTENT_POLICY_ADDRESS", policy.address);
+
+    let provider = rpc::provider(config.rpc_url)?;
+    for (name, address) in &addresses {
+        if rpc::code_hash(&provider, *address).await?.is_none() {
+            bail!("{name} {address:?} has no code after deployment");
+        }
+    }
+    rpc::smoke_test_policy(&provider, policy.address).await?;
+
+    Ok(Stack {
+        addresses,
+        policy,
+        infra_deployments,
+        kernel_deployments,
+    })
+}
+
+/// `bytes32` encoding of the default Kernel `PermissionId`, as `just env_init_permission_id`
+/// derives it: the first 4 bytes of `keccak256("fiet-permission-default")`, zero-padded.
+pub fn default_permission_id() -> H256 {
+    let mut id = [0u8; 32];
+    id[..4].copy_from_slice(&keccak256("fiet-permission-default")[..4]);
+    H256(id)
+}
+
+async fn forge_script(
+    config: &StackConfig<'_>,
+    target: &str,
+    deployments_path: &Path,
+) -> Result<()> {
+    info!(%target, "forge script");
+    let status = Command::new("forge")
+        .args(["script", target, "--rpc-url", config.rpc_url, "--broadcast"])
+        .env("PRIVATE_KEY", config.private_key)
+        .env("DEPLOYMENTS_PATH", deployments_path)
+        .current_dir(config.stylus_dir)
+        .kill_on_drop(true)
+        .status()
+        .await
+        .context("failed running forge (is Foundry installed?)")?;
+    if !status.success() {
+        bail!("forge script {target} failed ({status})");
+    }
+    Ok(())
+}
+
+fn read_addresses(path: &Path, keys: &[&'static str]) -> Result<BTreeMap<&'static str, Address>> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("failed reading {}", path.display()))?;
+    let json: serde_json::Value =
+        serde_json::from_str(&raw).with_context(|| format!("failed parsing {}", path.display()))?;
+    keys.iter()
+        .map(|key| {
+            let address = json[*key]
+                .as_str()
+                .ok_or_else(|| anyhow!("{} has no {key}", path.display()))?
+                .parse()
+                .with_context(|| format!("invalid {key} in {}", path.display()))?;
+            Ok((*key, address))
+        })
+        .collect()
+}
+
+async fn deploy_policy(config: &StackConfig<'_>) -> Result<WasmDeployment> {
+    let contract_dir = config.stylus_dir.join("src/fiet-maker-policy");
+    let wasm_path = match config.wasm_path {
+        Some(path) => path.to_path_buf(),
+        None => {
+            info!("cargo stylus check");
+            let status = Command::new("cargo")
+                .args(["stylus", "check", "--endpoint", config.rpc_url])
+                .current_dir(&contract_dir)
+                .kill_on_drop(true)
+                .status()
+                .await
+                .context("failed running cargo stylus")?;
+            if !status.success() {
+                bail!("cargo stylus check failed ({status})");
+            }
+            contract_dir.join("target/wasm32-unknown-unknown/release/fiet_maker_policy.wasm")
+        }
+    };
+    let wasm =
+        fs::read(&wasm_path).with_context(|| format!("failed reading {}", wasm_path.display()))?;
+
+    let wallet: LocalWallet = config.private_key.parse().context("invalid private key")?;
+    let client = SignerMiddleware::new(
+        rpc::provider(config.rpc_url)?,
+        wallet.with_chain_id(config.chain_id),
+    );
+    let deployed = deploy_wasm(&client, &wasm).await?;
+    info!(
+        address = ?deployed.address,
+        deployment_tx = ?deployed.deployment_tx,
+        activation_tx = ?deployed.activation_tx,
+        "policy deployed"
+    );
+    Ok(deployed)
+}
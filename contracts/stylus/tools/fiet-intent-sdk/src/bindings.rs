@@ -0,0 +1,349 @@
+//! Typed bindings for the intent policy's external interface and Kernel's module management.
+//!
+//! [`IIntentPolicy`] is the interface `export-abi` renders (`just stylus_export_abi`), turned into
+//! `sol!` call types: encode a call with `IIntentPolicy::nonceOfCall { .. }.abi_encode()` and
+//! decode its return data with `abi_decode_returns`. [`PolicyReader`] does both over an ethers
+//! client for the views. Regenerate the interface here when the policy's `#[public]` surface
+//! changes.
+
+use alloy_sol_types::{sol, SolCall};
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    providers::Middleware,
+    types::{transaction::eip2718::TypedTransaction, Address, TransactionRequest, H256, U256},
+};
+
+use crate::{convert, kernel::Execution};
+
+sol! {
+    /// The intent policy as exported. Stylus exposes `Vec<u8>` arguments as `uint8[]`.
+    #[derive(Debug, PartialEq, Eq)]
+    interface IIntentPolicy {
+        function onInstall(uint8[] data) external payable;
+        function onUninstall(uint8[] data) external payable;
+        function isModuleType(uint256 module_type_id) external view returns (bool);
+        function isInitialized(address wallet) external view returns (bool);
+        function permissionIdsOf(address wallet) external view returns (bytes32[]);
+        function nonceOf(address wallet, bytes32 permission_id) external view returns (uint256);
+        function signerOf(address wallet, bytes32 permission_id) external view returns (address);
+        function passkeyOf(address wallet, bytes32 permission_id) external view returns (bytes32, bytes32);
+        function domainOf(address wallet, bytes32 permission_id) external view returns (bytes32, bytes32);
+        function factSourcesOf(address wallet, bytes32 permission_id) external view returns (address, address, address);
+        function factSourceCodehashesOf(address wallet, bytes32 permission_id) external view returns (bytes32, bytes32, bytes32);
+        function poolAllowlistOf(address wallet, bytes32 permission_id) external view returns (bytes32[]);
+        function oracleCallsOf(address wallet, bytes32 permission_id) external view returns ((address, bytes4)[]);
+        function sourceOverridesOf(address wallet, bytes32 permission_id) external view returns ((uint8, address)[]);
+        function exportConfig(address wallet, bytes32 permission_id) external view returns (bytes);
+        function signerSetOf(address wallet, bytes32 permission_id) external view returns (uint8, address[]);
+        function isConfigFrozen(address wallet, bytes32 permission_id) external view returns (bool);
+        function freezeConfig(bytes32 permission_id) external;
+        function revokeNonce(address wallet, bytes32 permission_id, uint256 min_nonce) external returns (uint256);
+        function checkUserOpPolicy(bytes32 permission_id, (address, uint256, uint8[], uint8[], bytes32, uint256, bytes32, uint8[], uint8[]) user_op) external payable returns (uint256);
+        function checkSignaturePolicy(bytes32 permission_id, address sender, bytes32 hash, uint8[] sig) external view returns (uint256);
+        function preCheck(address msg_sender, uint256 msg_value, uint8[] msg_data) external payable returns (uint8[]);
+        function postCheck(uint8[] hook_data) external payable;
+        function discardPendingDeltas(bytes32 execution_hash) external;
+        function pendingDeltasOf(address wallet, bytes32 execution_hash) external view returns (uint256);
+        function isHookInstalled(address wallet) external view returns (bool);
+
+        event IntentValidated(
+            address indexed wallet,
+            bytes32 indexed permissionId,
+            bytes32 indexed envelopeDigest,
+            bytes32 programHash,
+            uint256 nonce,
+            uint64 deadline
+        );
+        event PermissionInstalled(
+            address indexed wallet,
+            bytes32 indexed permissionId,
+            address signer,
+            address stateView,
+            address vtsOrchestrator,
+            address liquidityHub
+        );
+        event PermissionUninstalled(address indexed wallet, bytes32 indexed permissionId);
+        event NonceRevoked(
+            address indexed wallet,
+            bytes32 indexed permissionId,
+            address indexed caller,
+            uint256 nonce
+        );
+        event ConfigFrozen(address indexed wallet, bytes32 indexed permissionId);
+
+        error AlreadyInitialized(address smartAccount);
+        error NotInitialized(address smartAccount);
+        error Unauthorized(address caller);
+        error TokenOutflowExceeded(address token, uint256 outflow, uint256 maxOut);
+        error BalanceUnavailable(address token);
+        error WindowSpendExceeded(address token, uint256 spent, uint256 max);
+        error PermissionFrozen(address smartAccount, bytes32 permissionId);
+    }
+
+    /// Kernel v3 module management (`installModule` is `onlyEntryPointOrSelfOrRoot`).
+    #[derive(Debug, PartialEq, Eq)]
+    interface IKernelModules {
+        function installModule(uint256 moduleType, address module, bytes initData) external payable;
+        function uninstallModule(uint256 moduleType, address module, bytes deInitData) external payable;
+        function isModuleInstalled(uint256 moduleType, address module, bytes additionalContext) external view returns (bool);
+    }
+}
+
+/// Kernel module type id of policies.
+pub const MODULE_TYPE_POLICY: u64 = 5;
+
+/// Calldata for Kernel `installModule(5, policy, permissionId || initData)`, to preview or send
+/// from the account itself.
+pub fn install_policy_calldata(policy: Address, permission_id: H256, init_data: &[u8]) -> Vec<u8> {
+    let mut data = permission_id.as_bytes().to_vec();
+    data.extend_from_slice(init_data);
+    IKernelModules::installModuleCall {
+        moduleType: alloy_primitives::U256::from(MODULE_TYPE_POLICY),
+        module: convert::address(policy),
+        initData: data.into(),
+    }
+    .abi_encode()
+}
+
+/// Install `permissionId` that marks a batch of permissions in one `onInstall`.
+pub const BATCH_INSTALL_ID: H256 = H256([0xff; 32]);
+
+/// Calldata for Kernel `installModule(5, policy, ..)` installing every `(permissionId, initData)`
+/// in `permissions` at once (all or none). `None` when the batch is empty, has more than 255
+/// entries or an `initData` longer than `u16::MAX`.
+pub fn batch_install_policy_calldata(
+    policy: Address,
+    permissions: &[(H256, Vec<u8>)],
+) -> Option<Vec<u8>> {
+    if permissions.is_empty() {
+        return None;
+    }
+    let mut batch = vec![u8::try_from(permissions.len()).ok()?];
+    for (permission_id, init_data) in permissions {
+        batch.extend_from_slice(permission_id.as_bytes());
+        batch.extend_from_slice(&u16::try_from(init_data.len()).ok()?.to_be_bytes());
+        batch.extend_from_slice(init_data);
+    }
+    Some(install_policy_calldata(policy, BATCH_INSTALL_ID, &batch))
+}
+
+/// Module type id of hooks.
+pub const MODULE_TYPE_HOOK: u64 = 4;
+
+/// Module init data for ERC-7579 bootstrap / launchpad contracts that install the policy while
+/// creating the account: `abi.encode(5, permissionId || initData)`, or with `with_hook` the
+/// multi-type `abi.encode([4, 5], ["", permissionId || initData])` that also installs it as the
+/// account's hook. Pass it as the module's `data` in the bootstrap config.
+pub fn bootstrap_policy_init_data(
+    permission_id: H256,
+    init_data: &[u8],
+    with_hook: bool,
+) -> Vec<u8> {
+    use alloy_sol_types::{sol_data, SolType};
+
+    let mut data = permission_id.as_bytes().to_vec();
+    data.extend_from_slice(init_data);
+    let policy = alloy_primitives::U256::from(MODULE_TYPE_POLICY);
+    if !with_hook {
+        type SingleType = (sol_data::Uint<256>, sol_data::Bytes);
+        return SingleType::abi_encode_params(&(policy, alloy_primitives::Bytes::from(data)));
+    }
+    type MultiType = (
+        sol_data::Array<sol_data::Uint<256>>,
+        sol_data::Array<sol_data::Bytes>,
+    );
+    MultiType::abi_encode_params(&(
+        vec![alloy_primitives::U256::from(MODULE_TYPE_HOOK), policy],
+        vec![alloy_primitives::Bytes::new(), data.into()],
+    ))
+}
+
+/// Calldata for Kernel `uninstallModule(5, policy, permissionId)`.
+pub fn uninstall_policy_calldata(policy: Address, permission_id: H256) -> Vec<u8> {
+    IKernelModules::uninstallModuleCall {
+        moduleType: alloy_primitives::U256::from(MODULE_TYPE_POLICY),
+        module: convert::address(policy),
+        deInitData: permission_id.as_bytes().to_vec().into(),
+    }
+    .abi_encode()
+}
+
+/// Account self-calls moving a permission from `old_policy` to `new_policy` in one batch:
+/// uninstall it from the old deployment, then install `config` (the old deployment's
+/// `exportConfig`) on the new one. Send them with `execute_batch_calldata`.
+pub fn migrate_policy_executions(
+    wallet: Address,
+    old_policy: Address,
+    new_policy: Address,
+    permission_id: H256,
+    config: &[u8],
+) -> Vec<Execution> {
+    vec![
+        Execution::new(
+            wallet,
+            U256::zero(),
+            uninstall_policy_calldata(old_policy, permission_id),
+        ),
+        Execution::new(
+            wallet,
+            U256::zero(),
+            install_policy_calldata(new_policy, permission_id, config),
+        ),
+    ]
+}
+
+/// `call` to `to` as a transaction request.
+pub fn call_tx<C: SolCall>(to: Address, call: &C) -> TypedTransaction {
+    TransactionRequest::new()
+        .to(to)
+        .data(call.abi_encode())
+        .into()
+}
+
+/// Typed `eth_call`s of the policy's views.
+#[derive(Clone, Debug)]
+pub struct PolicyReader<'a, M> {
+    client: &'a M,
+    policy: Address,
+}
+
+impl<'a, M: Middleware> PolicyReader<'a, M> {
+    pub fn new(client: &'a M, policy: Address) -> Self {
+        Self { client, policy }
+    }
+
+    /// `eth_call` `call` against the policy and decode its return data.
+    pub async fn call<C: SolCall>(&self, call: C) -> Result<C::Return> {
+        let out = self
+            .client
+            .call(&call_tx(self.policy, &call), None)
+            .await
+            .map_err(|err| anyhow!("{err}"))
+            .with_context(|| format!("IntentPolicy.{} failed", C::SIGNATURE))?;
+        C::abi_decode_returns(&out, true)
+            .with_context(|| format!("malformed {} return data", C::SIGNATURE))
+    }
+
+    /// The replay nonce the next envelope must carry (`nonceOf`).
+    pub async fn nonce_of(&self, wallet: Address, permission_id: H256) -> Result<U256> {
+        let nonce = self
+            .call(IIntentPolicy::nonceOfCall {
+                wallet: convert::address(wallet),
+                permission_id: convert::bytes32(permission_id),
+            })
+            .await?
+            ._0;
+        Ok(U256::from_big_endian(&nonce.to_be_bytes::<32>()))
+    }
+
+    /// The permission's envelope signer; zero when not installed or signed by a passkey.
+    pub async fn signer_of(&self, wallet: Address, permission_id: H256) -> Result<Address> {
+        let signer = self
+            .call(IIntentPolicy::signerOfCall {
+                wallet: convert::address(wallet),
+                permission_id: convert::bytes32(permission_id),
+            })
+            .await?
+            ._0;
+        Ok(Address::from(signer.0 .0))
+    }
+
+    /// Whether `wallet` has any permission installed on the policy.
+    pub async fn is_initialized(&self, wallet: Address) -> Result<bool> {
+        Ok(self
+            .call(IIntentPolicy::isInitializedCall {
+                wallet: convert::address(wallet),
+            })
+            .await?
+            ._0)
+    }
+
+    /// The permission ids installed for `wallet`, in no particular order.
+    pub async fn permission_ids_of(&self, wallet: Address) -> Result<Vec<H256>> {
+        let ids = self
+            .call(IIntentPolicy::permissionIdsOfCall {
+                wallet: convert::address(wallet),
+            })
+            .await?
+            ._0;
+        Ok(ids.into_iter().map(|id| H256(id.0)).collect())
+    }
+
+    /// The permission's pool allowlist (empty when none is installed).
+    pub async fn pool_allowlist_of(
+        &self,
+        wallet: Address,
+        permission_id: H256,
+    ) -> Result<Vec<H256>> {
+        let pools = self
+            .call(IIntentPolicy::poolAllowlistOfCall {
+                wallet: convert::address(wallet),
+                permission_id: convert::bytes32(permission_id),
+            })
+            .await?
+            ._0;
+        Ok(pools.into_iter().map(|pool| H256(pool.0)).collect())
+    }
+
+    /// The permission's allowlisted oracle calls `(target, selector)`.
+    pub async fn oracle_calls_of(
+        &self,
+        wallet: Address,
+        permission_id: H256,
+    ) -> Result<Vec<(Address, [u8; 4])>> {
+        let calls = self
+            .call(IIntentPolicy::oracleCallsOfCall {
+                wallet: convert::address(wallet),
+                permission_id: convert::bytes32(permission_id),
+            })
+            .await?
+            ._0;
+        Ok(calls
+            .into_iter()
+            .map(|(target, selector)| (Address::from(target.0 .0), selector.0))
+            .collect())
+    }
+
+    /// The permission's fact-source overrides `(opcode, source)`.
+    pub async fn source_overrides_of(
+        &self,
+        wallet: Address,
+        permission_id: H256,
+    ) -> Result<Vec<(u8, Address)>> {
+        let overrides = self
+            .call(IIntentPolicy::sourceOverridesOfCall {
+                wallet: convert::address(wallet),
+                permission_id: convert::bytes32(permission_id),
+            })
+            .await?
+            ._0;
+        Ok(overrides
+            .into_iter()
+            .map(|(opcode, source)| (opcode, Address::from(source.0 .0)))
+            .collect())
+    }
+
+    /// Whether the permission's config is frozen (`freezeConfig` or the `0x07` install extension).
+    pub async fn is_config_frozen(&self, wallet: Address, permission_id: H256) -> Result<bool> {
+        Ok(self
+            .call(IIntentPolicy::isConfigFrozenCall {
+                wallet: convert::address(wallet),
+                permission_id: convert::bytes32(permission_id),
+            })
+            .await?
+            ._0)
+    }
+
+    /// The permission's config as packed `initData` (`exportConfig`), ready to install on
+    /// another deployment of the policy.
+    pub async fn export_config(&self, wallet: Address, permission_id: H256) -> Result<Vec<u8>> {
+        Ok(self
+            .call(IIntentPolicy::exportConfigCall {
+                wallet: convert::address(wallet),
+                permission_id: convert::bytes32(permission_id),
+            })
+            .await?
+            ._0
+            .to_vec())
+    }
+}
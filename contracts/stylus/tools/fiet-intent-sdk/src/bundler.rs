@@ -0,0 +1,240 @@
+//! ERC-4337 bundler RPC client (EntryPoint v0.7).
+//!
+//! Bundlers take the unpacked v0.7 UserOperation JSON (`factory`/`factoryData`, separate gas
+//! fields, `paymaster*`), not the on-chain `PackedUserOperation`; [`RpcUserOperation`] converts.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethers::{
+    providers::{Http, Provider},
+    types::{Address, Bytes, H256, U256, U64},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::user_op::{pack_u128s, unpack_u128s, PackedUserOperation};
+
+/// Default interval between `eth_getUserOperationReceipt` polls.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Default time to wait for a UserOperation to be included.
+pub const DEFAULT_RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// v0.7 UserOperation as sent over bundler RPC.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcUserOperation {
+    pub sender: Address,
+    pub nonce: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory_data: Option<Bytes>,
+    pub call_data: Bytes,
+    pub call_gas_limit: U256,
+    pub verification_gas_limit: U256,
+    pub pre_verification_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<Bytes>,
+    pub signature: Bytes,
+}
+
+impl TryFrom<&PackedUserOperation> for RpcUserOperation {
+    type Error = anyhow::Error;
+
+    fn try_from(op: &PackedUserOperation) -> Result<Self> {
+        let (factory, factory_data) = match op.init_code.len() {
+            0 => (None, None),
+            n if n < 20 => bail!("initCode shorter than a factory address"),
+            _ => (
+                Some(Address::from_slice(&op.init_code[..20])),
+                Some(Bytes::from(op.init_code[20..].to_vec())),
+            ),
+        };
+        let (paymaster, pm_verification, pm_post_op, paymaster_data) =
+            match op.paymaster_and_data.len() {
+                0 => (None, None, None, None),
+                n if n < 52 => bail!("paymasterAndData shorter than address + gas limits"),
+                _ => {
+                    let pmd = &op.paymaster_and_data;
+                    (
+                        Some(Address::from_slice(&pmd[..20])),
+                        Some(U256::from_big_endian(&pmd[20..36])),
+                        Some(U256::from_big_endian(&pmd[36..52])),
+                        Some(Bytes::from(pmd[52..].to_vec())),
+                    )
+                }
+            };
+        let (verification_gas_limit, call_gas_limit) = unpack_u128s(&op.account_gas_limits);
+        let (max_priority_fee_per_gas, max_fee_per_gas) = unpack_u128s(&op.gas_fees);
+        Ok(Self {
+            sender: op.sender,
+            nonce: op.nonce,
+            factory,
+            factory_data,
+            call_data: op.call_data.clone(),
+            call_gas_limit: U256::from(call_gas_limit),
+            verification_gas_limit: U256::from(verification_gas_limit),
+            pre_verification_gas: op.pre_verification_gas,
+            max_fee_per_gas: U256::from(max_fee_per_gas),
+            max_priority_fee_per_gas: U256::from(max_priority_fee_per_gas),
+            paymaster,
+            paymaster_verification_gas_limit: pm_verification,
+            paymaster_post_op_gas_limit: pm_post_op,
+            paymaster_data,
+            signature: op.signature.clone(),
+        })
+    }
+}
+
+/// `eth_estimateUserOperationGas` result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasEstimate {
+    pub pre_verification_gas: U256,
+    pub verification_gas_limit: U256,
+    pub call_gas_limit: U256,
+    #[serde(default)]
+    pub paymaster_verification_gas_limit: Option<U256>,
+}
+
+impl GasEstimate {
+    /// Write the estimate into `op`'s gas fields (fees are left untouched).
+    pub fn apply(&self, op: &mut PackedUserOperation) -> Result<()> {
+        let verification = u128_limit(self.verification_gas_limit, "verificationGasLimit")?;
+        let call = u128_limit(self.call_gas_limit, "callGasLimit")?;
+        op.account_gas_limits = pack_u128s(verification, call);
+        op.pre_verification_gas = self.pre_verification_gas;
+        Ok(())
+    }
+}
+
+fn u128_limit(value: U256, field: &str) -> Result<u128> {
+    if value.bits() > 128 {
+        bail!("bundler returned {field} {value} that does not fit in uint128");
+    }
+    Ok(value.as_u128())
+}
+
+/// `eth_getUserOperationReceipt` result (only the fields the SDK relies on).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOpReceipt {
+    pub user_op_hash: H256,
+    pub success: bool,
+    pub actual_gas_cost: U256,
+    pub actual_gas_used: U256,
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub receipt: BundleTxReceipt,
+}
+
+/// Receipt of the bundle transaction that included the UserOperation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTxReceipt {
+    pub transaction_hash: H256,
+    #[serde(default)]
+    pub block_number: Option<U64>,
+}
+
+/// JSON-RPC client for a 4337 bundler.
+#[derive(Clone, Debug)]
+pub struct Bundler {
+    provider: Provider<Http>,
+    entry_point: Address,
+    pub poll_interval: Duration,
+    pub receipt_timeout: Duration,
+    /// Refuse programs that break the ERC-7562 validation rules before sending (see
+    /// [`crate::program::Program::lint`]). Turn off for bundlers running in unsafe mode.
+    pub enforce_validation_rules: bool,
+    /// The account is staked in the EntryPoint, which lifts the storage-access rules.
+    pub account_staked: bool,
+}
+
+impl Bundler {
+    pub fn new(url: &str, entry_point: Address) -> Result<Self> {
+        let provider = Provider::<Http>::try_from(url)
+            .with_context(|| format!("invalid bundler URL {url}"))?;
+        Ok(Self {
+            provider,
+            entry_point,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            receipt_timeout: DEFAULT_RECEIPT_TIMEOUT,
+            enforce_validation_rules: true,
+            account_staked: false,
+        })
+    }
+
+    pub fn entry_point(&self) -> Address {
+        self.entry_point
+    }
+
+    /// Fail early if the bundler does not serve our EntryPoint.
+    pub async fn ensure_entry_point_supported(&self) -> Result<()> {
+        let supported: Vec<Address> = self
+            .provider
+            .request("eth_supportedEntryPoints", ())
+            .await
+            .context("eth_supportedEntryPoints failed")?;
+        if !supported.contains(&self.entry_point) {
+            bail!(
+                "bundler does not support EntryPoint {:?} (supported: {supported:?})",
+                self.entry_point
+            );
+        }
+        Ok(())
+    }
+
+    /// `eth_estimateUserOperationGas`. The op should carry a well-formed signature: bundlers
+    /// simulate validation, and the intent policy only evaluates its program for a valid
+    /// envelope.
+    pub async fn estimate_gas(&self, op: &PackedUserOperation) -> Result<GasEstimate> {
+        let rpc_op = RpcUserOperation::try_from(op)?;
+        self.provider
+            .request("eth_estimateUserOperationGas", (rpc_op, self.entry_point))
+            .await
+            .context("eth_estimateUserOperationGas failed")
+    }
+
+    /// `eth_sendUserOperation`; returns the bundler's userOpHash.
+    pub async fn send(&self, op: &PackedUserOperation) -> Result<H256> {
+        let rpc_op = RpcUserOperation::try_from(op)?;
+        self.provider
+            .request("eth_sendUserOperation", (rpc_op, self.entry_point))
+            .await
+            .context("eth_sendUserOperation failed")
+    }
+
+    /// `eth_getUserOperationReceipt`; `None` while the op is pending.
+    pub async fn receipt(&self, user_op_hash: H256) -> Result<Option<UserOpReceipt>> {
+        self.provider
+            .request("eth_getUserOperationReceipt", [user_op_hash])
+            .await
+            .context("eth_getUserOperationReceipt failed")
+    }
+
+    /// Poll [`Self::receipt`] every `poll_interval` until it appears or `receipt_timeout` passes.
+    pub async fn wait_for_receipt(&self, user_op_hash: H256) -> Result<UserOpReceipt> {
+        let deadline = tokio::time::Instant::now() + self.receipt_timeout;
+        loop {
+            if let Some(receipt) = self.receipt(user_op_hash).await? {
+                return Ok(receipt);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow!(
+                    "UserOperation {user_op_hash:?} not included after {:?}",
+                    self.receipt_timeout
+                ));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
@@ -0,0 +1,559 @@
+//! [`IntentClient`]: one call from intent to mined UserOperation.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, TransactionReceipt, TransactionRequest, H256, U256},
+    utils::keccak256,
+};
+
+pub use crate::user_op::GasLimits;
+use fiet_maker_policy_encoder::encoder::lint::{is_compliant, LintContext, Severity};
+
+use crate::{
+    bundler::Bundler,
+    convert,
+    enable::EnableData,
+    envelope::{self, EnvelopeParams},
+    kernel::{self, Execution},
+    nonce,
+    program::Program,
+    reservations::NonceReservations,
+    user_op::{self, pack_u128s, unpack_u128s, PackedUserOperation, UserOpBuilder},
+};
+
+/// Where the permission lives and how UserOperations are routed.
+#[derive(Clone, Debug)]
+pub struct IntentConfig {
+    pub entry_point: Address,
+    /// Intent policy contract (the envelope's EIP-712 verifying contract).
+    pub policy: Address,
+    /// Kernel account (the UserOperation sender).
+    pub account: Address,
+    /// `bytes32` permission id, left-aligned `bytes4` (see [`kernel::permission_id4`]).
+    pub permission_id: H256,
+    /// Position of the intent policy in the permission's policy list (CallPolicy is usually 0).
+    pub policy_index: u8,
+    /// Receives the EntryPoint's gas refund; defaults to the submitting address when `None`.
+    pub beneficiary: Option<Address>,
+    pub gas: GasLimits,
+}
+
+impl IntentConfig {
+    pub fn new(
+        entry_point: Address,
+        policy: Address,
+        account: Address,
+        permission_id: H256,
+    ) -> Self {
+        Self {
+            entry_point,
+            policy,
+            account,
+            permission_id,
+            policy_index: 1,
+            beneficiary: None,
+            gas: GasLimits::default(),
+        }
+    }
+}
+
+/// A single call from the account, gated by a check program.
+#[derive(Clone, Debug)]
+pub struct Intent {
+    pub target: Address,
+    pub value: U256,
+    pub data: Bytes,
+    pub program: Program,
+    /// Envelope deadline (unix seconds).
+    pub deadline: u64,
+}
+
+/// A built and fully signed UserOperation.
+#[derive(Clone, Debug)]
+pub struct SignedIntent {
+    pub user_op: PackedUserOperation,
+    pub user_op_hash: H256,
+    /// Policy nonce the envelope was signed for.
+    pub intent_nonce: U256,
+    /// Encoded, signed policy envelope (the intent policy's signature slice).
+    pub envelope: Vec<u8>,
+}
+
+/// Outcome of [`IntentClient::submit`].
+#[derive(Clone, Debug)]
+pub struct Submission {
+    pub user_op_hash: H256,
+    pub tx_hash: H256,
+    pub intent_nonce: U256,
+    pub actual_gas_used: U256,
+}
+
+/// Builds, signs and submits intent-gated UserOperations for one permission.
+pub struct IntentClient<M> {
+    client: Arc<M>,
+    config: IntentConfig,
+    chain_id: u64,
+    /// Signer installed for the permission (MultiChainSigner owner).
+    owner: LocalWallet,
+    /// Signer the intent policy was installed with.
+    envelope_signer: LocalWallet,
+    reservations: Option<NonceReservations>,
+}
+
+impl<M: Middleware + 'static> IntentClient<M> {
+    /// `client` submits `handleOps`, so it must be able to send transactions (e.g. a
+    /// `SignerMiddleware`).
+    pub async fn new(
+        client: Arc<M>,
+        config: IntentConfig,
+        owner: LocalWallet,
+        envelope_signer: LocalWallet,
+    ) -> Result<Self> {
+        // Reject ids Kernel cannot route before anything is signed.
+        kernel::permission_id4(config.permission_id)?;
+        let chain_id = client
+            .get_chainid()
+            .await
+            .map_err(|err| anyhow!("{err}"))
+            .context("failed fetching chain id")?
+            .as_u64();
+        Ok(Self {
+            client,
+            config,
+            chain_id,
+            owner,
+            envelope_signer,
+            reservations: None,
+        })
+    }
+
+    /// Take intent nonces from a shared reservation file instead of the bare chain nonce, so
+    /// several operators signing for this permission never sign the same nonce.
+    pub fn with_reservations(mut self, reservations: NonceReservations) -> Self {
+        self.reservations = Some(reservations);
+        self
+    }
+
+    pub fn config(&self) -> &IntentConfig {
+        &self.config
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// The policy nonce the next intent will be signed for.
+    pub async fn intent_nonce(&self) -> Result<U256> {
+        nonce::policy_nonce(
+            self.client.as_ref(),
+            self.config.policy,
+            self.config.account,
+            self.config.permission_id,
+        )
+        .await
+    }
+
+    /// The nonce to sign `intent` at: the chain nonce, or with reservations configured the next
+    /// free reserved one.
+    async fn next_intent_nonce(&self, intent: &Intent) -> Result<U256> {
+        let chain_nonce = self.intent_nonce().await?;
+        let Some(reservations) = &self.reservations else {
+            return Ok(chain_nonce);
+        };
+        let nonce = reservations.reserve(
+            self.config.policy,
+            self.config.account,
+            self.config.permission_id,
+            chain_nonce,
+            intent.deadline,
+        )?;
+        if nonce != chain_nonce {
+            tracing::info!(
+                %chain_nonce,
+                reserved = %nonce,
+                "lower nonces are reserved; the envelope is valid once they are consumed"
+            );
+        }
+        Ok(nonce)
+    }
+
+    /// Give a reserved nonce back after signing failed.
+    fn release_intent_nonce(&self, nonce: U256) {
+        if let Some(reservations) = &self.reservations {
+            if let Err(err) = reservations.release(
+                self.config.policy,
+                self.config.account,
+                self.config.permission_id,
+                nonce,
+            ) {
+                tracing::warn!(%nonce, error = %err, "failed releasing reserved nonce");
+            }
+        }
+    }
+
+    /// Build the UserOperation for `intent` and sign both the envelope and the op.
+    pub async fn sign(&self, intent: &Intent) -> Result<SignedIntent> {
+        let key = kernel::permission_nonce_key(self.config.permission_id, 0)?;
+        let op_nonce = nonce::entry_point_nonce(
+            self.client.as_ref(),
+            self.config.entry_point,
+            self.config.account,
+            key,
+        )
+        .await?;
+        let intent_nonce = self.next_intent_nonce(intent).await?;
+        let signed = self.sign_with_nonces(intent, intent_nonce, op_nonce).await;
+        if signed.is_err() {
+            self.release_intent_nonce(intent_nonce);
+        }
+        signed
+    }
+
+    /// [`Self::sign`] with caller-supplied nonces (no RPC reads).
+    pub async fn sign_with_nonces(
+        &self,
+        intent: &Intent,
+        intent_nonce: U256,
+        op_nonce: U256,
+    ) -> Result<SignedIntent> {
+        let builder = self.builder_for(intent, op_nonce);
+        let envelope = self.envelope_for(intent, intent_nonce, &builder)?;
+        let mut signed = SignedIntent {
+            user_op: builder.build_unsigned()?,
+            user_op_hash: H256::zero(),
+            intent_nonce,
+            envelope,
+        };
+        self.sign_user_op(&mut signed).await?;
+        Ok(signed)
+    }
+
+    /// (Re-)sign `signed.user_op` after its gas fields changed.
+    ///
+    /// The envelope binds only the calldata, so it is reused; the signer signature covers the
+    /// whole userOpHash and is redone.
+    pub async fn sign_user_op(&self, signed: &mut SignedIntent) -> Result<()> {
+        let user_op_hash = signed.user_op.hash(self.config.entry_point, self.chain_id);
+        // MultiChainSigner accepts an EIP-191 signature over the raw userOpHash.
+        let signer_sig = self
+            .owner
+            .sign_message(user_op_hash.as_bytes())
+            .await
+            .context("failed signing userOpHash")?;
+        signed.user_op.signature = kernel::pack_permission_signature(
+            &[(self.config.policy_index, signed.envelope.clone())],
+            &signer_sig.to_vec(),
+        )?;
+        signed.user_op_hash = user_op_hash;
+        Ok(())
+    }
+
+    fn builder_for(&self, intent: &Intent, op_nonce: U256) -> UserOpBuilder {
+        UserOpBuilder::new(self.config.account)
+            .nonce(op_nonce)
+            .execution(Execution::new(
+                intent.target,
+                intent.value,
+                intent.data.clone(),
+            ))
+            .gas(self.config.gas)
+    }
+
+    fn envelope_for(
+        &self,
+        intent: &Intent,
+        intent_nonce: U256,
+        builder: &UserOpBuilder,
+    ) -> Result<Vec<u8>> {
+        envelope::signed_envelope(
+            &EnvelopeParams {
+                chain_id: self.chain_id,
+                policy: self.config.policy,
+                wallet: self.config.account,
+                permission_id: self.config.permission_id,
+                nonce: intent_nonce,
+                deadline: intent.deadline,
+                call_bundle_hash: builder.call_bundle_hash()?,
+                program: &intent.program,
+            },
+            &self.envelope_signer,
+        )
+    }
+
+    /// Sign `intent`, submit it with `EntryPoint.handleOps` and wait until it is mined.
+    ///
+    /// Fails if the transaction reverts or the UserOperation's execution did not succeed.
+    pub async fn submit(&self, intent: &Intent) -> Result<Submission> {
+        let signed = self.sign(intent).await?;
+        self.execute(signed.user_op, signed.user_op_hash, signed.intent_nonce)
+            .await
+    }
+
+    /// Like [`Self::submit`] for a permission that is not installed yet: the op runs in Kernel
+    /// enable mode, installing the permission described by `enable` (approved by `root`, the
+    /// account's root signer) before validating the intent.
+    ///
+    /// `enable.permission_id` must equal the configured permission id.
+    pub async fn submit_with_enable(
+        &self,
+        intent: &Intent,
+        enable: &EnableData,
+        root: &LocalWallet,
+    ) -> Result<Submission> {
+        if enable.permission_id != self.config.permission_id {
+            bail!(
+                "enable data is for permission {:?}, client is configured for {:?}",
+                enable.permission_id,
+                self.config.permission_id
+            );
+        }
+        let op_nonce = nonce::entry_point_nonce(
+            self.client.as_ref(),
+            self.config.entry_point,
+            self.config.account,
+            enable.nonce_key(0)?,
+        )
+        .await?;
+        let intent_nonce = self.next_intent_nonce(intent).await?;
+        let (user_op, user_op_hash) = match self
+            .sign_enable_op(intent, intent_nonce, op_nonce, enable, root)
+            .await
+        {
+            Ok(signed) => signed,
+            Err(err) => {
+                self.release_intent_nonce(intent_nonce);
+                return Err(err);
+            }
+        };
+        self.execute(user_op, user_op_hash, intent_nonce).await
+    }
+
+    async fn sign_enable_op(
+        &self,
+        intent: &Intent,
+        intent_nonce: U256,
+        op_nonce: U256,
+        enable: &EnableData,
+        root: &LocalWallet,
+    ) -> Result<(PackedUserOperation, H256)> {
+        let builder = self.builder_for(intent, op_nonce);
+        let envelope = self.envelope_for(intent, intent_nonce, &builder)?;
+        builder
+            .policy_signature(self.config.policy_index, envelope)
+            .sign_enable(
+                self.config.entry_point,
+                self.chain_id,
+                &self.owner,
+                root,
+                enable,
+            )
+            .await
+    }
+
+    async fn execute(
+        &self,
+        user_op: PackedUserOperation,
+        user_op_hash: H256,
+        intent_nonce: U256,
+    ) -> Result<Submission> {
+        let receipt = self.handle_ops(user_op).await?;
+        let event = user_op_event(&receipt, user_op_hash)?;
+        if !event.success {
+            bail!(
+                "UserOperation {user_op_hash:?} executed with success=false (tx {:?})",
+                receipt.transaction_hash
+            );
+        }
+        tracing::info!(
+            user_op_hash = ?user_op_hash,
+            tx = ?receipt.transaction_hash,
+            intent_nonce = %intent_nonce,
+            "intent executed"
+        );
+        Ok(Submission {
+            user_op_hash,
+            tx_hash: receipt.transaction_hash,
+            intent_nonce,
+            actual_gas_used: event.actual_gas_used,
+        })
+    }
+
+    /// Like [`Self::submit`], but through a 4337 bundler instead of a direct `handleOps`.
+    ///
+    /// Zero fees in the config are replaced by the chain's EIP-1559 estimate (bundlers reject
+    /// zero-fee ops), the gas limits come from `eth_estimateUserOperationGas`, and the op is
+    /// re-signed before `eth_sendUserOperation`. Waits for the bundler receipt.
+    ///
+    /// Unless [`Bundler::enforce_validation_rules`] is off, programs a compliant bundler would
+    /// drop (see [`Program::lint`]) are refused before anything is signed.
+    pub async fn submit_via_bundler(
+        &self,
+        intent: &Intent,
+        bundler: &Bundler,
+    ) -> Result<Submission> {
+        if bundler.entry_point() != self.config.entry_point {
+            bail!(
+                "bundler targets EntryPoint {:?}, client is configured for {:?}",
+                bundler.entry_point(),
+                self.config.entry_point
+            );
+        }
+        if bundler.enforce_validation_rules {
+            self.lint_for_bundler(&intent.program, bundler.account_staked)?;
+        }
+        let mut signed = self.sign(intent).await?;
+
+        let (priority_fee, max_fee) = unpack_u128s(&signed.user_op.gas_fees);
+        if max_fee == 0 {
+            let (max_fee, priority_fee) = self
+                .client
+                .estimate_eip1559_fees(None)
+                .await
+                .map_err(|err| anyhow!("{err}"))
+                .context("failed estimating EIP-1559 fees")?;
+            signed.user_op.gas_fees = pack_u128s(priority_fee.as_u128(), max_fee.as_u128());
+            self.sign_user_op(&mut signed).await?;
+        } else if priority_fee > max_fee {
+            bail!("maxPriorityFeePerGas {priority_fee} exceeds maxFeePerGas {max_fee}");
+        }
+
+        // Estimate with a fully signed op so the policy runs its program during simulation.
+        let estimate = bundler.estimate_gas(&signed.user_op).await?;
+        estimate.apply(&mut signed.user_op)?;
+        self.sign_user_op(&mut signed).await?;
+
+        let user_op_hash = bundler.send(&signed.user_op).await?;
+        if user_op_hash != signed.user_op_hash {
+            bail!(
+                "bundler returned userOpHash {user_op_hash:?}, expected {:?}",
+                signed.user_op_hash
+            );
+        }
+        let receipt = bundler.wait_for_receipt(user_op_hash).await?;
+        if !receipt.success {
+            bail!(
+                "UserOperation {user_op_hash:?} executed with success=false (tx {:?}){}",
+                receipt.receipt.transaction_hash,
+                receipt
+                    .reason
+                    .map(|reason| format!(": {reason}"))
+                    .unwrap_or_default()
+            );
+        }
+        tracing::info!(
+            user_op_hash = ?user_op_hash,
+            tx = ?receipt.receipt.transaction_hash,
+            intent_nonce = %signed.intent_nonce,
+            "intent executed via bundler"
+        );
+        Ok(Submission {
+            user_op_hash,
+            tx_hash: receipt.receipt.transaction_hash,
+            intent_nonce: signed.intent_nonce,
+            actual_gas_used: receipt.actual_gas_used,
+        })
+    }
+
+    async fn handle_ops(&self, user_op: PackedUserOperation) -> Result<TransactionReceipt> {
+        let beneficiary = match self.config.beneficiary {
+            Some(beneficiary) => beneficiary,
+            None => self
+                .client
+                .default_sender()
+                .context("no beneficiary configured and the client has no default sender")?,
+        };
+        let data = user_op::handle_ops_calldata(vec![user_op], beneficiary);
+        let tx = TransactionRequest::new()
+            .from(beneficiary)
+            .to(self.config.entry_point)
+            .data(data);
+        let pending = self
+            .client
+            .send_transaction(tx, None)
+            .await
+            .map_err(|err| anyhow!("{err}"))
+            .context("handleOps submission failed")?;
+        let receipt = pending
+            .await
+            .context("failed waiting for the handleOps receipt")?
+            .context("handleOps transaction dropped from the mempool")?;
+        if receipt.status != Some(1.into()) {
+            bail!("handleOps reverted (tx {:?})", receipt.transaction_hash);
+        }
+        Ok(receipt)
+    }
+
+    /// Fail if a bundler would drop the program under the ERC-7562 validation rules; log
+    /// findings that depend on fact-source code the lint cannot see.
+    fn lint_for_bundler(&self, program: &Program, staked: bool) -> Result<()> {
+        let ctx = LintContext {
+            sender: convert::address(self.config.account),
+            staked,
+        };
+        let findings = program.lint(&ctx);
+        for finding in findings.iter().filter(|f| f.severity == Severity::Warning) {
+            tracing::warn!(
+                check = finding.index,
+                opcode = ?finding.opcode,
+                rule = ?finding.rule,
+                "{}",
+                finding.message
+            );
+        }
+        if !is_compliant(&findings) {
+            let errors: Vec<String> = findings
+                .iter()
+                .filter(|f| f.severity == Severity::Error)
+                .map(|f| format!("check {} ({:?}): {}", f.index, f.opcode, f.message))
+                .collect();
+            bail!(
+                "program breaks bundler validation rules:\n  {}",
+                errors.join("\n  ")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Decoded `UserOperationEvent` fields the client cares about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserOpEvent {
+    pub success: bool,
+    pub actual_gas_cost: U256,
+    pub actual_gas_used: U256,
+}
+
+/// Find the `UserOperationEvent` for `user_op_hash` in `receipt`.
+pub fn user_op_event(receipt: &TransactionReceipt, user_op_hash: H256) -> Result<UserOpEvent> {
+    let topic0 = H256(keccak256(
+        "UserOperationEvent(bytes32,address,address,uint256,bool,uint256,uint256)",
+    ));
+    let log = receipt
+        .logs
+        .iter()
+        .find(|log| log.topics.first() == Some(&topic0) && log.topics.get(1) == Some(&user_op_hash))
+        .with_context(|| format!("no UserOperationEvent for {user_op_hash:?} in the receipt"))?;
+    let tokens = abi::decode(
+        &[
+            ParamType::Uint(256),
+            ParamType::Bool,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ],
+        &log.data,
+    )
+    .context("malformed UserOperationEvent data")?;
+    match tokens.as_slice() {
+        [_, Token::Bool(success), Token::Uint(cost), Token::Uint(used)] => Ok(UserOpEvent {
+            success: *success,
+            actual_gas_cost: *cost,
+            actual_gas_used: *used,
+        }),
+        _ => bail!("malformed UserOperationEvent data"),
+    }
+}
@@ -0,0 +1,21 @@
+//! ethers <-> alloy conversions at the encoder boundary (the encoder uses alloy types).
+
+use ethers::types::{Address, H256, U256};
+
+pub(crate) fn address(a: Address) -> alloy_primitives::Address {
+    alloy_primitives::Address::from(a.0)
+}
+
+pub(crate) fn bytes32(h: H256) -> alloy_primitives::FixedBytes<32> {
+    alloy_primitives::FixedBytes(h.0)
+}
+
+pub(crate) fn u256(v: U256) -> alloy_primitives::U256 {
+    let mut be = [0u8; 32];
+    v.to_big_endian(&mut be);
+    alloy_primitives::U256::from_be_bytes(be)
+}
+
+pub(crate) fn from_u256(v: alloy_primitives::U256) -> U256 {
+    U256::from_big_endian(&v.to_be_bytes::<32>())
+}
@@ -0,0 +1,159 @@
+//! Kernel enable mode: install the permission from inside its first UserOperation.
+//!
+//! The UserOperation nonce key carries `mode = ENABLE`, and `userOp.signature` becomes
+//! `hook(20) || abi.encode(validatorData, hookData, selectorData, enableSig, permissionSig)`.
+//! `enableSig` is the root validator's EIP-712 approval of the install (`Enable` struct, Kernel
+//! domain); `permissionSig` is the usual permission signature for the op itself. Mirrors
+//! `buildEnableSignature` in `e2e/src/kernel7702.ts`.
+
+use anyhow::{Context, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    signers::LocalWallet,
+    types::{Address, Bytes, H256, U256},
+    utils::{id, keccak256},
+};
+use fiet_maker_policy_encoder::encoder::permission_validator_data;
+
+use crate::kernel::{self, PermissionModule, VALIDATION_MODE_ENABLE, VALIDATION_TYPE_PERMISSION};
+
+/// Kernel's EIP-712 domain name and version (v3.3).
+pub const KERNEL_DOMAIN_NAME: &str = "Kernel";
+pub const KERNEL_DOMAIN_VERSION: &str = "0.3.3";
+
+const ENABLE_TYPE: &str =
+    "Enable(bytes21 validationId,uint32 nonce,address hook,bytes validatorData,bytes hookData,bytes selectorData)";
+
+/// Hook data Kernel expects when no hook is installed (the `0xff` "no onInstall" marker).
+pub const NO_HOOK_DATA: [u8; 1] = [0xff];
+
+/// Everything the root signer approves when enabling a permission.
+#[derive(Clone, Debug)]
+pub struct EnableData {
+    /// `bytes32` permission id (left-aligned `bytes4`).
+    pub permission_id: H256,
+    /// Policies in install order (eg CallPolicy, then the intent policy).
+    pub policies: Vec<PermissionModule>,
+    pub signer: PermissionModule,
+    /// Hook to install with the permission; `None` installs without one.
+    pub hook: Option<Address>,
+    pub hook_data: Vec<u8>,
+    /// Selector the permission may call (`execute` by default).
+    pub selector: [u8; 4],
+    /// Kernel's `currentNonce()` for the install (1 on a fresh EIP-7702 Kernel).
+    pub nonce: u32,
+}
+
+impl EnableData {
+    pub fn new(
+        permission_id: H256,
+        policies: Vec<PermissionModule>,
+        signer: PermissionModule,
+        nonce: u32,
+    ) -> Self {
+        let mut selector = [0u8; 4];
+        selector.copy_from_slice(&id("execute(bytes32,bytes)"));
+        Self {
+            permission_id,
+            policies,
+            signer,
+            hook: None,
+            hook_data: NO_HOOK_DATA.to_vec(),
+            selector,
+            nonce,
+        }
+    }
+
+    /// `bytes21 validationId = vType || permissionId4 || 16 zero bytes`.
+    pub fn validation_id(&self) -> Result<[u8; 21]> {
+        let id4 = kernel::permission_id4(self.permission_id)?;
+        let mut vid = [0u8; 21];
+        vid[0] = VALIDATION_TYPE_PERMISSION;
+        vid[1..].copy_from_slice(&kernel::permission_validator_id(id4));
+        Ok(vid)
+    }
+
+    /// UserOperation nonce key that selects enable mode for this permission.
+    pub fn nonce_key(&self, parallel_key: u16) -> Result<U256> {
+        let id4 = kernel::permission_id4(self.permission_id)?;
+        Ok(kernel::nonce_key(
+            VALIDATION_MODE_ENABLE,
+            VALIDATION_TYPE_PERMISSION,
+            kernel::permission_validator_id(id4),
+            parallel_key,
+        ))
+    }
+
+    /// `abi.encode(bytes[])` of the policy and signer entries handed to the permission validator.
+    pub fn validator_data(&self) -> Vec<u8> {
+        permission_validator_data(&self.policies, &self.signer)
+    }
+
+    /// EIP-712 digest of the `Enable` struct under `account`'s Kernel domain.
+    pub fn digest(&self, account: Address, chain_id: u64) -> Result<H256> {
+        let domain_separator = keccak256(abi::encode(&[
+            Token::FixedBytes(
+                keccak256(
+                    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+                )
+                .to_vec(),
+            ),
+            Token::FixedBytes(keccak256(KERNEL_DOMAIN_NAME).to_vec()),
+            Token::FixedBytes(keccak256(KERNEL_DOMAIN_VERSION).to_vec()),
+            Token::Uint(U256::from(chain_id)),
+            Token::Address(account),
+        ]));
+        let mut validation_id = [0u8; 32];
+        validation_id[..21].copy_from_slice(&self.validation_id()?);
+        let struct_hash = keccak256(abi::encode(&[
+            Token::FixedBytes(keccak256(ENABLE_TYPE).to_vec()),
+            Token::FixedBytes(validation_id.to_vec()),
+            Token::Uint(U256::from(self.nonce)),
+            Token::Address(self.hook.unwrap_or_default()),
+            Token::FixedBytes(keccak256(self.validator_data()).to_vec()),
+            Token::FixedBytes(keccak256(&self.hook_data).to_vec()),
+            Token::FixedBytes(keccak256(self.selector).to_vec()),
+        ]));
+
+        let mut buf = Vec::with_capacity(2 + 32 + 32);
+        buf.extend_from_slice(b"\x19\x01");
+        buf.extend_from_slice(&domain_separator);
+        buf.extend_from_slice(&struct_hash);
+        Ok(H256(keccak256(buf)))
+    }
+
+    /// Root signer approval (`eth_signTypedData`-style: the digest itself is signed).
+    pub fn sign(&self, account: Address, chain_id: u64, root: &LocalWallet) -> Result<Vec<u8>> {
+        let digest = self.digest(account, chain_id)?;
+        let sig = root
+            .sign_hash(digest)
+            .context("failed signing the Enable digest")?;
+        Ok(sig.to_vec())
+    }
+
+    /// Final `userOp.signature` for enable mode.
+    pub fn encode_signature(&self, enable_sig: &[u8], permission_sig: &[u8]) -> Bytes {
+        let mut out = self.hook.unwrap_or_default().as_bytes().to_vec();
+        out.extend(abi::encode(&[
+            Token::Bytes(self.validator_data()),
+            Token::Bytes(self.hook_data.clone()),
+            Token::Bytes(self.selector.to_vec()),
+            Token::Bytes(enable_sig.to_vec()),
+            Token::Bytes(permission_sig.to_vec()),
+        ]));
+        out.into()
+    }
+}
+
+/// The permission signature inside an enable-mode `userOp.signature` (the last field after the
+/// hook address); the inverse of [`EnableData::encode_signature`] for that field.
+pub fn permission_signature_of(signature: &[u8]) -> Result<Vec<u8>> {
+    let encoded = signature
+        .get(20..)
+        .context("enable-mode signature is shorter than its hook address")?;
+    abi::decode(&vec![ParamType::Bytes; 5], encoded)
+        .context("malformed enable-mode signature")?
+        .pop()
+        .and_then(Token::into_bytes)
+        .context("malformed enable-mode signature")
+}
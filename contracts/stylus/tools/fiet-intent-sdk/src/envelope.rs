@@ -0,0 +1,335 @@
+//! Signed policy envelopes (the intent policy's signature slice).
+
+use std::{convert::Infallible, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip712::{EIP712Domain, Eip712},
+        Address, Signature, H256, U256,
+    },
+    utils::{hex, keccak256},
+};
+use fiet_maker_policy_encoder::{
+    encoder::{
+        cbor::{decode_envelope_cbor, encode_envelope_cbor},
+        encode_envelope, policy_domain_separator_in, policy_intent_digest_in,
+        policy_intent_struct_hash, sign_envelope, PolicyDomain,
+    },
+    opcodes::{Check, Eip712Check},
+    types::IntentEnvelope,
+};
+
+use serde_json::{json, Value};
+
+use crate::{convert, kernel, program::Program};
+
+/// Envelope version understood by the policy.
+pub const ENVELOPE_VERSION: u16 = 1;
+/// Envelope version that signs the checks as a typed `Check[]` (see
+/// [`EnvelopeTypedData::typed_checks`]).
+pub use fiet_maker_policy_encoder::encoder::ENVELOPE_VERSION_TYPED_CHECKS;
+
+/// The policy's default EIP-712 domain name and version (see [`EnvelopeTypedData::with_domain`]).
+pub const POLICY_DOMAIN_NAME: &str = "Fiet Maker Intent Policy";
+pub const POLICY_DOMAIN_VERSION: &str = "1";
+
+const ENVELOPE_TYPE: &str = "IntentPolicyEnvelope(address wallet,bytes32 permissionId,uint256 nonce,uint64 deadline,bytes32 callBundleHash,bytes32 programHash)";
+
+/// Everything the envelope signature binds to.
+#[derive(Clone, Debug)]
+pub struct EnvelopeParams<'a> {
+    pub chain_id: u64,
+    pub policy: Address,
+    pub wallet: Address,
+    pub permission_id: H256,
+    /// The permission's current replay nonce (see [`crate::nonce::policy_nonce`]).
+    pub nonce: U256,
+    pub deadline: u64,
+    /// `keccak256(userOp.callData)`.
+    pub call_bundle_hash: H256,
+    pub program: &'a Program,
+}
+
+/// Sign the envelope with `signer` (the signer installed for the permission) and encode it.
+pub fn signed_envelope(params: &EnvelopeParams<'_>, signer: &LocalWallet) -> Result<Vec<u8>> {
+    Ok(encode_envelope(&sign_local(params, signer)?))
+}
+
+/// Sign the envelope like [`signed_envelope`] but return the deterministic CBOR form (see
+/// [`fiet_maker_policy_encoder::encoder::cbor`]), which also carries the domain and scoping fields.
+///
+/// Use it to store or pass envelopes between services; convert with [`policy_bytes_from_cbor`]
+/// before putting one in a UserOperation.
+pub fn signed_envelope_cbor(params: &EnvelopeParams<'_>, signer: &LocalWallet) -> Result<Vec<u8>> {
+    Ok(encode_envelope_cbor(&sign_local(params, signer)?))
+}
+
+/// Turn a CBOR envelope into the policy's signature-slice bytes.
+pub fn policy_bytes_from_cbor(cbor: &[u8]) -> Result<Vec<u8>> {
+    let envelope =
+        decode_envelope_cbor(cbor).map_err(|err| anyhow!("malformed CBOR envelope: {err:?}"))?;
+    Ok(encode_envelope(&envelope))
+}
+
+fn sign_local(params: &EnvelopeParams<'_>, signer: &LocalWallet) -> Result<IntentEnvelope> {
+    let mut envelope = EnvelopeTypedData::new(params).envelope;
+    sign_envelope(&mut envelope, signer.signer())
+        .map_err(|err| anyhow!("failed signing envelope: {err}"))?;
+    Ok(envelope)
+}
+
+/// Sign the envelope with any ethers [`Signer`] (AWS KMS, Ledger, ...) and encode it.
+///
+/// The signature is checked to recover to `signer.address()`, so a device signing with an
+/// unexpected key or `v` encoding fails here rather than on-chain.
+pub async fn signed_envelope_with<S: Signer>(
+    params: &EnvelopeParams<'_>,
+    signer: &S,
+) -> Result<Vec<u8>> {
+    signed_typed_envelope_with(&EnvelopeTypedData::new(params), signer).await
+}
+
+/// [`signed_envelope_with`] for typed data built by the caller (eg with
+/// [`EnvelopeTypedData::typed_checks`]).
+pub async fn signed_typed_envelope_with<S: Signer>(
+    typed: &EnvelopeTypedData,
+    signer: &S,
+) -> Result<Vec<u8>> {
+    let signature = signer
+        .sign_typed_data(typed)
+        .await
+        .map_err(|err| anyhow!("{err}"))
+        .context("failed signing envelope")?;
+    typed.encode(&signature, signer.address())
+}
+
+/// Text encodings of a signed envelope, for consumers that want bytes they can use directly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnvelopeFormat {
+    /// `0x` hex of the policy signature slice.
+    #[default]
+    Hex,
+    /// Standard (padded) base64 of the policy signature slice.
+    Base64,
+    /// `0x` hex of the slice as Kernel expects it in `userOp.signature`, with the policy's
+    /// `index:1 || len:8` prefix applied (see [`kernel::policy_signature_fragment`]).
+    UserOpSignature,
+}
+
+impl EnvelopeFormat {
+    /// Render `envelope`; `policy_index` is the intent policy's position in the permission and is
+    /// only used by [`EnvelopeFormat::UserOpSignature`].
+    pub fn render(self, envelope: &[u8], policy_index: u8) -> Result<String> {
+        Ok(match self {
+            Self::Hex => format!("0x{}", hex::encode(envelope)),
+            Self::Base64 => STANDARD.encode(envelope),
+            Self::UserOpSignature => {
+                if policy_index == kernel::SIGNER_SIG_PREFIX {
+                    bail!("policy index 0xff is reserved for the signer signature");
+                }
+                let fragment = kernel::policy_signature_fragment(policy_index, envelope);
+                format!("0x{}", hex::encode(fragment))
+            }
+        })
+    }
+}
+
+impl FromStr for EnvelopeFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hex" => Ok(Self::Hex),
+            "base64" => Ok(Self::Base64),
+            "userop-signature" => Ok(Self::UserOpSignature),
+            other => bail!("unknown envelope format `{other}` (hex, base64, userop-signature)"),
+        }
+    }
+}
+
+/// An unsigned envelope as an EIP-712 payload.
+#[derive(Clone, Debug)]
+pub struct EnvelopeTypedData {
+    envelope: IntentEnvelope,
+    domain: PolicyDomain,
+    checks: Vec<Check>,
+}
+
+impl EnvelopeTypedData {
+    pub fn new(params: &EnvelopeParams<'_>) -> Self {
+        let envelope = IntentEnvelope {
+            version: ENVELOPE_VERSION,
+            nonce: convert::u256(params.nonce),
+            deadline: params.deadline,
+            call_bundle_hash: convert::bytes32(params.call_bundle_hash),
+            program_bytes: params.program.encode(),
+            signature: Vec::new(),
+            domain_chain_id: params.chain_id,
+            domain_verifying_contract: convert::address(params.policy),
+            wallet: convert::address(params.wallet),
+            permission_id: convert::bytes32(params.permission_id),
+        };
+        Self {
+            envelope,
+            domain: PolicyDomain::default(),
+            checks: params.program.checks().to_vec(),
+        }
+    }
+
+    /// Sign the checks as a typed EIP-712 `Check[]` (envelope version 2) rather than the program
+    /// hash, so wallets rendering the typed data show the signer every check field by field.
+    pub fn typed_checks(mut self) -> Self {
+        self.envelope.version = ENVELOPE_VERSION_TYPED_CHECKS;
+        self
+    }
+
+    /// Sign under the domain name/version the permission was installed with, when its `initData`
+    /// overrides the policy's default.
+    pub fn with_domain(mut self, domain: PolicyDomain) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// The digest the policy recovers the envelope signer from.
+    pub fn digest(&self) -> H256 {
+        H256(policy_intent_digest_in(&self.envelope, &self.domain).0)
+    }
+
+    /// The `eth_signTypedData_v4` payload for this envelope, for wallets and signers that take the
+    /// typed data rather than a digest.
+    pub fn to_json(&self) -> Value {
+        let envelope = &self.envelope;
+        let mut typed = json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" },
+                ],
+                "IntentPolicyEnvelope": [
+                    { "name": "wallet", "type": "address" },
+                    { "name": "permissionId", "type": "bytes32" },
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "deadline", "type": "uint64" },
+                    { "name": "callBundleHash", "type": "bytes32" },
+                    { "name": "programHash", "type": "bytes32" },
+                ],
+            },
+            "primaryType": "IntentPolicyEnvelope",
+            "domain": {
+                "name": self.domain.name,
+                "version": self.domain.version,
+                "chainId": envelope.domain_chain_id,
+                "verifyingContract": envelope.domain_verifying_contract.to_string(),
+            },
+            "message": {
+                "wallet": envelope.wallet.to_string(),
+                "permissionId": envelope.permission_id.to_string(),
+                "nonce": envelope.nonce.to_string(),
+                "deadline": envelope.deadline,
+                "callBundleHash": envelope.call_bundle_hash.to_string(),
+                "programHash": alloy_primitives::keccak256(&envelope.program_bytes).to_string(),
+            },
+        });
+        if envelope.version == ENVELOPE_VERSION_TYPED_CHECKS {
+            typed["types"]["IntentPolicyEnvelope"][5] =
+                json!({ "name": "checks", "type": "Check[]" });
+            typed["types"]["Check"] = check_type_json();
+            let message = typed["message"]
+                .as_object_mut()
+                .expect("message is an object");
+            message.remove("programHash");
+            message.insert(
+                "checks".to_string(),
+                self.checks.iter().map(check_json).collect(),
+            );
+        }
+        typed
+    }
+
+    /// Encode the envelope with `signature`, which must recover to `expected_signer`. `v` is
+    /// normalised to 27/28, the form the policy's `ecrecover` accepts.
+    pub fn encode(&self, signature: &Signature, expected_signer: Address) -> Result<Vec<u8>> {
+        let mut signature = *signature;
+        signature.v = 27 + u64::from(signature.recovery_id()?.to_byte());
+        let recovered = signature
+            .recover(self.digest())
+            .context("envelope signature does not recover")?;
+        if recovered != expected_signer {
+            bail!("envelope signature recovers to {recovered:?}, expected {expected_signer:?}");
+        }
+        let mut envelope = self.envelope.clone();
+        envelope.signature = signature.to_vec();
+        Ok(encode_envelope(&envelope))
+    }
+}
+
+/// Fields of the EIP-712 `Check` type, parsed from [`EIP712_CHECK_TYPE`](fiet_maker_policy_encoder::opcodes::EIP712_CHECK_TYPE).
+fn check_type_json() -> Value {
+    let fields = fiet_maker_policy_encoder::opcodes::EIP712_CHECK_TYPE
+        .trim_start_matches("Check(")
+        .trim_end_matches(')')
+        .split(',')
+        .map(|field| {
+            let (ty, name) = field.split_once(' ').expect("`type name` field");
+            json!({ "name": name, "type": ty })
+        })
+        .collect();
+    Value::Array(fields)
+}
+
+/// A check as `Check` message JSON. Unsigned integers are decimal strings; the `int24` ticks stay
+/// JSON numbers, which is what wallets parse signed integers from.
+fn check_json(check: &Check) -> Value {
+    let check = Eip712Check::from(check);
+    json!({
+        "kind": check.kind,
+        "id": check.id.to_string(),
+        "target": check.target.to_string(),
+        "account": check.account.to_string(),
+        "min": check.min.to_string(),
+        "max": check.max.to_string(),
+        "min1": check.min1.to_string(),
+        "max1": check.max1.to_string(),
+        "tickMin": check.tick_min,
+        "tickMax": check.tick_max,
+        "window": check.window.to_string(),
+        "bps": check.bps.to_string(),
+        "decimals": check.decimals.to_string(),
+        "op": check.op,
+        "rhs": check.rhs.to_string(),
+        "call": format!("0x{}", hex::encode(&check.call)),
+        "rhsCall": format!("0x{}", hex::encode(&check.rhs_call)),
+    })
+}
+
+impl Eip712 for EnvelopeTypedData {
+    type Error = Infallible;
+
+    fn domain_separator(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(policy_domain_separator_in(&self.envelope, &self.domain).0)
+    }
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(EIP712Domain {
+            name: Some(self.domain.name.clone()),
+            version: Some(self.domain.version.clone()),
+            chain_id: Some(U256::from(self.envelope.domain_chain_id)),
+            verifying_contract: Some(Address::from(self.envelope.domain_verifying_contract.0 .0)),
+            salt: None,
+        })
+    }
+
+    /// The program-hash message type; typed-checks envelopes differ only through `struct_hash`.
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(ENVELOPE_TYPE))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(policy_intent_struct_hash(&self.envelope).0)
+    }
+}
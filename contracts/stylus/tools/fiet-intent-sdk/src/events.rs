@@ -0,0 +1,172 @@
+//! The policy's events: log filters for them, and typed decoding of the logs.
+//!
+//! Every permission's lifecycle is logged: `PermissionInstalled` on install (once per entry of a
+//! batch install), `IntentValidated` for each consumed intent, `NonceRevoked` when outstanding
+//! envelopes are cancelled, `ConfigFrozen` when the config is frozen, and
+//! `PermissionUninstalled`. Wallet and permission id are the first two indexed topics of all of
+//! them, so [`EventFilter`] can narrow any mix of them to one wallet or one permission instance. [`decode_log`] turns a log back into a [`PolicyEvent`].
+
+use alloy_sol_types::{SolEvent, SolEventInterface};
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, Filter, Log, ValueOrArray, H256},
+};
+
+use crate::bindings::IIntentPolicy::{
+    self, ConfigFrozen, IntentValidated, NonceRevoked, PermissionInstalled, PermissionUninstalled,
+};
+
+/// Any event the policy emits.
+pub type PolicyEvent = IIntentPolicy::IIntentPolicyEvents;
+
+/// Which policy event a filter matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Installed,
+    IntentValidated,
+    NonceRevoked,
+    ConfigFrozen,
+    Uninstalled,
+}
+
+impl EventKind {
+    pub const ALL: [Self; 5] = [
+        Self::Installed,
+        Self::IntentValidated,
+        Self::NonceRevoked,
+        Self::ConfigFrozen,
+        Self::Uninstalled,
+    ];
+
+    /// The event's `topic0`.
+    pub fn topic0(self) -> H256 {
+        H256(
+            match self {
+                Self::Installed => PermissionInstalled::SIGNATURE_HASH,
+                Self::IntentValidated => IntentValidated::SIGNATURE_HASH,
+                Self::NonceRevoked => NonceRevoked::SIGNATURE_HASH,
+                Self::ConfigFrozen => ConfigFrozen::SIGNATURE_HASH,
+                Self::Uninstalled => PermissionUninstalled::SIGNATURE_HASH,
+            }
+            .0,
+        )
+    }
+}
+
+/// `eth_getLogs` filter for a policy's events.
+#[derive(Clone, Debug)]
+pub struct EventFilter {
+    policy: Address,
+    kinds: Vec<EventKind>,
+    wallet: Option<Address>,
+    permission_id: Option<H256>,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+}
+
+impl EventFilter {
+    /// Every event of `policy`, over the node's default block range.
+    pub fn new(policy: Address) -> Self {
+        Self {
+            policy,
+            kinds: EventKind::ALL.to_vec(),
+            wallet: None,
+            permission_id: None,
+            from_block: None,
+            to_block: None,
+        }
+    }
+
+    /// Only these events.
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = EventKind>) -> Self {
+        self.kinds = kinds.into_iter().collect();
+        self
+    }
+
+    /// Only events of `wallet`.
+    pub fn wallet(mut self, wallet: Address) -> Self {
+        self.wallet = Some(wallet);
+        self
+    }
+
+    /// Only events of `permission_id` (of any wallet, unless [`Self::wallet`] is set too).
+    pub fn permission_id(mut self, permission_id: H256) -> Self {
+        self.permission_id = Some(permission_id);
+        self
+    }
+
+    pub fn from_block(mut self, block: u64) -> Self {
+        self.from_block = Some(block);
+        self
+    }
+
+    pub fn to_block(mut self, block: u64) -> Self {
+        self.to_block = Some(block);
+        self
+    }
+
+    /// The ethers filter.
+    pub fn filter(&self) -> Filter {
+        let topic0: Vec<H256> = self.kinds.iter().map(|kind| kind.topic0()).collect();
+        let mut filter = Filter::new()
+            .address(self.policy)
+            .topic0(ValueOrArray::Array(topic0));
+        if let Some(wallet) = self.wallet {
+            filter = filter.topic1(H256::from(wallet));
+        }
+        if let Some(permission_id) = self.permission_id {
+            filter = filter.topic2(permission_id);
+        }
+        if let Some(block) = self.from_block {
+            filter = filter.from_block(BlockNumber::Number(block.into()));
+        }
+        if let Some(block) = self.to_block {
+            filter = filter.to_block(BlockNumber::Number(block.into()));
+        }
+        filter
+    }
+
+    /// Fetch the matching logs and decode them, in log order.
+    pub async fn query<M: Middleware>(&self, client: &M) -> Result<Vec<(Log, PolicyEvent)>> {
+        let logs = client
+            .get_logs(&self.filter())
+            .await
+            .map_err(|err| anyhow!("{err}"))
+            .context("eth_getLogs failed")?;
+        logs.into_iter()
+            .map(|log| {
+                let event = decode_log(&log)?;
+                Ok((log, event))
+            })
+            .collect()
+    }
+}
+
+/// Decode a policy log. Fails for logs of other events or with malformed data.
+pub fn decode_log(log: &Log) -> Result<PolicyEvent> {
+    let topics: Vec<_> = log
+        .topics
+        .iter()
+        .map(|topic| alloy_primitives::B256::from(topic.0))
+        .collect();
+    PolicyEvent::decode_raw_log(&topics, &log.data, true).map_err(|err| {
+        anyhow!(
+            "log {:?} of tx {:?} is not a policy event: {err}",
+            log.log_index,
+            log.transaction_hash
+        )
+    })
+}
+
+/// The wallet and permission id an event belongs to.
+pub fn event_scope(event: &PolicyEvent) -> (Address, H256) {
+    let (wallet, permission_id) = match event {
+        PolicyEvent::IntentValidated(e) => (e.wallet, e.permissionId),
+        PolicyEvent::PermissionInstalled(e) => (e.wallet, e.permissionId),
+        PolicyEvent::PermissionUninstalled(e) => (e.wallet, e.permissionId),
+        PolicyEvent::NonceRevoked(e) => (e.wallet, e.permissionId),
+        PolicyEvent::ConfigFrozen(e) => (e.wallet, e.permissionId),
+    };
+    (Address::from(wallet.0 .0), H256(permission_id.0))
+}
@@ -0,0 +1,738 @@
+//! RPC-backed [`FactsProvider`]: the policy's fact reads replayed with `eth_call`.
+//!
+//! Mirrors the policy's `OnchainFactsProvider` (same allowlist, calls and return decoding), pinned
+//! to one block so every check sees the same state, with `block.timestamp` of that block as `now`.
+//!
+//! Reads go through [`RpcEndpoints`], which can retry and time out requests and fail over to
+//! other nodes, so one flaky devnet node does not turn into failed checks. Reverts are answers,
+//! not failures, and are never retried.
+//!
+//! [`RpcFactsProvider::prefetch`] reads every fact a program needs concurrently before it is
+//! evaluated, all at the pinned block, so the evaluation sees the same single snapshot the policy
+//! does on-chain without one round trip per check.
+//!
+//! An install can route an opcode's reads to another source ([`FactSources::overrides`]);
+//! [`RpcFactsProvider::routed`] applies that per check.
+//!
+//! [`RpcFactsProvider::record_calls`] and [`RpcFactsProvider::call_gas`] price those reads for gas
+//! profiling.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alloy_primitives::{Address as AlloyAddress, FixedBytes, U256 as AlloyU256};
+use anyhow::{anyhow, bail, Context, Result};
+use ethers::{
+    providers::{Middleware, MiddlewareError},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, TransactionRequest,
+        H256,
+    },
+    utils::id,
+};
+use fiet_maker_policy_encoder::{
+    facts::{
+        observe_args, twap_tick_from_observe, FactSource, FactsError, FactsProvider, Slot0,
+        OBSERVE_SIG,
+    },
+    opcodes::Opcode,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{runtime::Handle, task::JoinSet};
+
+use crate::bindings::{IIntentPolicy, PolicyReader};
+
+/// The fact-source contracts configured for a permission.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FactSources {
+    pub state_view: Address,
+    pub vts_orchestrator: Address,
+    pub liquidity_hub: Address,
+    /// Opcodes whose reads go to another source, installed with the `0x05` `initData` extension.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<SourceOverride>,
+}
+
+impl FactSources {
+    /// The configured source of kind `source`.
+    pub fn get(&self, source: FactSource) -> Address {
+        match source {
+            FactSource::StateView => self.state_view,
+            FactSource::VtsOrchestrator => self.vts_orchestrator,
+            FactSource::LiquidityHub => self.liquidity_hub,
+        }
+    }
+
+    /// Where checks with `opcode` read their facts, if an override routes them elsewhere.
+    pub fn override_for(&self, opcode: Opcode) -> Option<(FactSource, Address)> {
+        let source = opcode.fact_source()?;
+        self.overrides
+            .iter()
+            .find(|o| o.opcode == opcode as u8)
+            .map(|o| (source, o.source))
+    }
+}
+
+/// Checks with `opcode` read from `source` instead of the configured source of the same kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceOverride {
+    pub opcode: u8,
+    pub source: Address,
+}
+
+/// `IntentPolicy.factSourcesOf(wallet, permissionId)` and its `sourceOverridesOf`; fails if the
+/// permission is not installed.
+pub async fn fact_sources_of<M: Middleware>(
+    client: &M,
+    policy: Address,
+    wallet: Address,
+    permission_id: H256,
+) -> Result<FactSources> {
+    let reader = PolicyReader::new(client, policy);
+    let out = reader
+        .call(IIntentPolicy::factSourcesOfCall {
+            wallet: crate::convert::address(wallet),
+            permission_id: crate::convert::bytes32(permission_id),
+        })
+        .await?;
+    let sources = FactSources {
+        state_view: Address::from(out._0.0 .0),
+        vts_orchestrator: Address::from(out._1.0 .0),
+        liquidity_hub: Address::from(out._2.0 .0),
+        overrides: Vec::new(),
+    };
+    if sources == FactSources::default() {
+        bail!("permission {permission_id:?} is not installed for {wallet:?} on policy {policy:?}");
+    }
+    // Deployments that predate `sourceOverridesOf` revert it; they have no overrides.
+    let overrides = reader
+        .source_overrides_of(wallet, permission_id)
+        .await
+        .unwrap_or_default();
+    Ok(FactSources {
+        overrides: overrides
+            .into_iter()
+            .map(|(opcode, source)| SourceOverride { opcode, source })
+            .collect(),
+        ..sources
+    })
+}
+
+/// Retries and timeouts for each RPC endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per endpoint before moving to the next (at least one).
+    pub attempts: u32,
+    /// Timeout of a single request.
+    pub timeout: Duration,
+    /// Delay before the second attempt, doubled before each later one.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// One attempt per endpoint with a 30s timeout.
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            timeout: Duration::from_secs(30),
+            backoff: Duration::from_millis(250),
+        }
+    }
+}
+
+/// RPC clients tried in order (primary first) with a [`RetryPolicy`].
+///
+/// Every endpoint must serve the same chain; a fallback is only asked when the ones before it
+/// failed or timed out.
+pub struct RpcEndpoints<M> {
+    clients: Vec<Arc<M>>,
+    retry: RetryPolicy,
+}
+
+impl<M> Clone for RpcEndpoints<M> {
+    fn clone(&self) -> Self {
+        Self {
+            clients: self.clients.clone(),
+            retry: self.retry,
+        }
+    }
+}
+
+impl<M> From<Arc<M>> for RpcEndpoints<M> {
+    fn from(primary: Arc<M>) -> Self {
+        Self {
+            clients: vec![primary],
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl<M: Middleware> RpcEndpoints<M> {
+    pub fn new(primary: Arc<M>) -> Self {
+        Self {
+            clients: vec![primary],
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Fail over to `client` after the endpoints added before it.
+    pub fn with_fallback(mut self, client: Arc<M>) -> Self {
+        self.clients.push(client);
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// `eth_blockNumber`.
+    pub async fn block_number(&self) -> Result<u64> {
+        let block = self
+            .request("eth_blockNumber", |client| async move {
+                client.get_block_number().await
+            })
+            .await?;
+        Ok(block.as_u64())
+    }
+
+    /// Timestamp of `block`.
+    pub async fn block_timestamp(&self, block: u64) -> Result<u64> {
+        let what = format!("eth_getBlockByNumber({block})");
+        self.request(&what, |client| async move { client.get_block(block).await })
+            .await?
+            .with_context(|| format!("block {block} not found"))
+            .map(|block| block.timestamp.as_u64())
+    }
+
+    /// `eth_estimateGas` of `tx` at `block`.
+    pub async fn estimate_gas(&self, tx: &TypedTransaction, block: u64) -> Result<u64> {
+        let block = BlockId::Number(BlockNumber::Number(block.into()));
+        let gas = self
+            .request("eth_estimateGas", |client| async move {
+                client.estimate_gas(tx, Some(block)).await
+            })
+            .await?;
+        u64::try_from(gas).map_err(|_| anyhow!("gas estimate {gas} overflows u64"))
+    }
+
+    /// `eth_call` of `tx` at `block`.
+    pub async fn call(&self, tx: &TypedTransaction, block: u64) -> Result<Vec<u8>> {
+        let block = BlockId::Number(BlockNumber::Number(block.into()));
+        let out = self
+            .request("eth_call", |client| async move {
+                client.call(tx, Some(block)).await
+            })
+            .await?;
+        Ok(out.to_vec())
+    }
+
+    async fn request<'a, T, F, Fut>(&'a self, what: &str, op: F) -> Result<T>
+    where
+        F: Fn(&'a M) -> Fut,
+        Fut: Future<Output = Result<T, M::Error>>,
+    {
+        let attempts = self.retry.attempts.max(1);
+        let mut last = anyhow!("no RPC endpoint configured");
+        for (endpoint, client) in self.clients.iter().enumerate() {
+            let mut delay = self.retry.backoff;
+            for attempt in 1..=attempts {
+                match tokio::time::timeout(self.retry.timeout, op(client)).await {
+                    Ok(Ok(out)) => return Ok(out),
+                    Ok(Err(err)) if is_revert(&err) => {
+                        return Err(anyhow!("{err}").context(format!("{what} reverted")));
+                    }
+                    Ok(Err(err)) => last = anyhow!("{err}"),
+                    Err(_) => last = anyhow!("timed out after {:?}", self.retry.timeout),
+                }
+                tracing::debug!(endpoint, attempt, err = %last, "{what} failed");
+                if attempt < attempts {
+                    tokio::time::sleep(delay).await;
+                    delay = delay.saturating_mul(2);
+                }
+            }
+        }
+        Err(last.context(format!("{what} failed")))
+    }
+}
+
+fn is_revert<E: MiddlewareError>(err: &E) -> bool {
+    err.as_error_response().is_some_and(|e| e.is_revert())
+}
+
+/// Rounds of [`RpcFactsProvider::prefetch`]; reads whose arguments come from earlier reads
+/// (the grace period's position then pool) need one round per step.
+pub const MAX_PREFETCH_ROUNDS: usize = 4;
+
+/// `(target, calldata)` of a fact read.
+pub type FactCall = (Address, Vec<u8>);
+
+#[derive(Default)]
+struct CallCache {
+    results: BTreeMap<FactCall, Result<Vec<u8>, FactsError>>,
+    /// Set while prefetching: uncached calls are recorded here and failed instead of made.
+    misses: Option<BTreeSet<FactCall>>,
+    /// Set while [recording](RpcFactsProvider::record_calls): every call, cached or not.
+    log: Option<Vec<FactCall>>,
+}
+
+/// [`FactsProvider`] over JSON-RPC.
+///
+/// `FactsProvider` is synchronous, so each fact not [prefetched](Self::prefetch) blocks on its
+/// `eth_call`: use it from a blocking context (eg `tokio::task::spawn_blocking`), never directly on
+/// a runtime worker. Every read is cached, so a fact read twice is fetched once.
+pub struct RpcFactsProvider<M> {
+    endpoints: RpcEndpoints<M>,
+    handle: Handle,
+    sources: FactSources,
+    block: u64,
+    now: u64,
+    allowlist: BTreeSet<(Address, [u8; 4])>,
+    /// Set while evaluating a check whose opcode is [routed](Self::routed).
+    route: Mutex<Option<(FactSource, Address)>>,
+    cache: Mutex<CallCache>,
+}
+
+impl<M: Middleware + 'static> RpcFactsProvider<M> {
+    /// Pin to the latest block. Must be called inside a tokio runtime.
+    pub async fn latest(client: Arc<M>, sources: FactSources) -> Result<Self> {
+        Self::latest_via(RpcEndpoints::new(client), sources).await
+    }
+
+    /// Pin to `block`. Must be called inside a tokio runtime.
+    pub async fn at_block(client: Arc<M>, sources: FactSources, block: u64) -> Result<Self> {
+        Self::at_block_via(RpcEndpoints::new(client), sources, block).await
+    }
+
+    /// [`Self::latest`] over several endpoints with retries.
+    pub async fn latest_via(endpoints: RpcEndpoints<M>, sources: FactSources) -> Result<Self> {
+        let block = endpoints.block_number().await?;
+        Self::at_block_via(endpoints, sources, block).await
+    }
+
+    /// [`Self::at_block`] over several endpoints with retries.
+    pub async fn at_block_via(
+        endpoints: RpcEndpoints<M>,
+        sources: FactSources,
+        block: u64,
+    ) -> Result<Self> {
+        let now = endpoints.block_timestamp(block).await?;
+
+        let mut allowlist = BTreeSet::new();
+        let routes = sources.overrides.iter().filter_map(|o| {
+            let opcode = Opcode::try_from(o.opcode).ok()?;
+            sources.override_for(opcode)
+        });
+        let configured = [
+            FactSource::StateView,
+            FactSource::VtsOrchestrator,
+            FactSource::LiquidityHub,
+        ]
+        .map(|source| (source, sources.get(source)));
+        for (source, target) in configured.into_iter().chain(routes) {
+            for sig in source_calls(source) {
+                allowlist.insert((target, selector(sig)));
+            }
+        }
+
+        Ok(Self {
+            endpoints,
+            handle: Handle::current(),
+            sources,
+            block,
+            now,
+            allowlist,
+            route: Mutex::default(),
+            cache: Mutex::default(),
+        })
+    }
+
+    /// Also allow `calls`, as the policy does for the oracle calls allowlisted at install.
+    pub fn with_allowed_calls(
+        mut self,
+        calls: impl IntoIterator<Item = (Address, [u8; 4])>,
+    ) -> Self {
+        self.allowlist.extend(calls);
+        self
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.block
+    }
+
+    pub fn sources(&self) -> &FactSources {
+        &self.sources
+    }
+
+    /// Run `evaluate` with the reads of a check with `opcode` going to its override, if the
+    /// permission has one, as the policy routes them.
+    pub fn routed<T>(&self, opcode: Opcode, evaluate: impl FnOnce() -> T) -> T {
+        *self.route.lock().unwrap_or_else(|e| e.into_inner()) = self.sources.override_for(opcode);
+        let out = evaluate();
+        *self.route.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        out
+    }
+
+    /// The source of kind `source` the check being evaluated reads.
+    fn source(&self, source: FactSource) -> Address {
+        match *self.route.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some((routed, target)) if routed == source => target,
+            _ => self.sources.get(source),
+        }
+    }
+
+    /// Fetch every fact `evaluate` reads, concurrently and at the pinned block, and return how
+    /// many calls were made.
+    ///
+    /// `evaluate` runs against the cache only: reads not cached yet are recorded and fail. The
+    /// recorded calls are then fetched in parallel and `evaluate` runs again, until it asks for
+    /// nothing new or [`MAX_PREFETCH_ROUNDS`] is reached. Anything still missing is read on
+    /// demand later. Failed calls are cached as failed, as an on-demand read would report them.
+    pub async fn prefetch(&self, evaluate: impl Fn(&Self)) -> Result<usize> {
+        let mut fetched = 0;
+        for _ in 0..MAX_PREFETCH_ROUNDS {
+            self.lock_cache().misses = Some(BTreeSet::new());
+            evaluate(self);
+            let misses = self.lock_cache().misses.take().unwrap_or_default();
+            if misses.is_empty() {
+                break;
+            }
+
+            let mut calls = JoinSet::new();
+            for (target, data) in misses {
+                let endpoints = self.endpoints.clone();
+                let block = self.block;
+                calls.spawn(async move {
+                    let tx: TypedTransaction = TransactionRequest::new()
+                        .to(target)
+                        .data(data.clone())
+                        .into();
+                    let out = endpoints.call(&tx, block).await;
+                    ((target, data), out)
+                });
+            }
+            while let Some(joined) = calls.join_next().await {
+                let ((target, data), out) = joined.context("fact prefetch task panicked")?;
+                let out = out.map_err(|err| call_failed(target, &data, &err));
+                self.lock_cache().results.insert((target, data), out);
+                fetched += 1;
+            }
+        }
+        Ok(fetched)
+    }
+
+    /// Run `evaluate` and return the fact calls it made, in order and with repeats: the policy
+    /// does not cache reads, so each one is a `STATICCALL` on-chain.
+    pub fn record_calls(&self, evaluate: impl FnOnce(&Self)) -> Vec<FactCall> {
+        self.lock_cache().log = Some(Vec::new());
+        evaluate(self);
+        self.lock_cache().log.take().unwrap_or_default()
+    }
+
+    /// Execution gas of each of `calls` at the pinned block, estimated concurrently.
+    ///
+    /// Each is `eth_estimateGas` of the call minus that of the same calldata sent to an address
+    /// without code, which cancels the intrinsic gas and any L1 data cost the node adds. `None`
+    /// where either estimate failed, eg because the call reverts.
+    pub async fn call_gas(
+        &self,
+        calls: BTreeSet<FactCall>,
+    ) -> Result<BTreeMap<FactCall, Option<u64>>> {
+        let mut estimates = JoinSet::new();
+        for (target, data) in calls {
+            let endpoints = self.endpoints.clone();
+            let block = self.block;
+            estimates.spawn(async move {
+                let estimate = |to: Address| {
+                    let tx: TypedTransaction =
+                        TransactionRequest::new().to(to).data(data.clone()).into();
+                    let endpoints = endpoints.clone();
+                    async move { endpoints.estimate_gas(&tx, block).await }
+                };
+                let gas = match estimate(target).await {
+                    Ok(gas) => estimate(Address::zero())
+                        .await
+                        .map(|baseline| gas.saturating_sub(baseline)),
+                    Err(err) => Err(err),
+                };
+                ((target, data), gas)
+            });
+        }
+        let mut gas = BTreeMap::new();
+        while let Some(joined) = estimates.join_next().await {
+            let ((target, data), out) = joined.context("gas estimate task panicked")?;
+            if let Err(err) = &out {
+                let selector = &data[..4];
+                let err = format!("{err:#}");
+                tracing::debug!(?target, ?selector, %err, "fact gas estimate failed");
+            }
+            gas.insert((target, data), out.ok());
+        }
+        Ok(gas)
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, CallCache> {
+        // The cache holds plain data, so a panic while it was locked cannot leave it torn.
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn staticcall(
+        &self,
+        target: Address,
+        selector: [u8; 4],
+        args: &[u8],
+    ) -> Result<Vec<u8>, FactsError> {
+        if !self.allowlist.contains(&(target, selector)) {
+            return Err(FactsError::ForbiddenCall {
+                target: AlloyAddress::from(target.0),
+                selector,
+            });
+        }
+        let mut data = selector.to_vec();
+        data.extend_from_slice(args);
+        let key = (target, data);
+        {
+            let mut cache = self.lock_cache();
+            if let Some(log) = cache.log.as_mut() {
+                log.push(key.clone());
+            }
+            if let Some(out) = cache.results.get(&key) {
+                return out.clone();
+            }
+            if let Some(misses) = cache.misses.as_mut() {
+                misses.insert(key);
+                return Err(FactsError::CallFailed);
+            }
+        }
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(target)
+            .data(key.1.clone())
+            .into();
+        let out = self
+            .handle
+            .block_on(self.endpoints.call(&tx, self.block))
+            .map_err(|err| call_failed(target, &key.1, &err));
+        self.lock_cache().results.insert(key, out.clone());
+        out
+    }
+
+    fn word_call(
+        &self,
+        target: Address,
+        sig: &str,
+        arg: &[u8],
+        words: usize,
+    ) -> Result<Vec<u8>, FactsError> {
+        let out = self.staticcall(target, selector(sig), arg)?;
+        if out.len() < 32 * words {
+            return Err(FactsError::MalformedReturn);
+        }
+        Ok(out)
+    }
+}
+
+impl<M: Middleware + 'static> FactsProvider for RpcFactsProvider<M> {
+    fn block_timestamp(&self) -> u64 {
+        self.now
+    }
+
+    fn get_slot0(&self, pool_id: FixedBytes<32>) -> Result<Slot0, FactsError> {
+        // (uint160, int24, uint24, uint24)
+        let out = self.word_call(
+            self.source(FactSource::StateView),
+            "getSlot0(bytes32)",
+            pool_id.as_slice(),
+            4,
+        )?;
+        Ok(Slot0 {
+            sqrt_price_x96: word(&out, 0),
+            tick: decode_i24(&out[32..64]),
+            protocol_fee: decode_u24(&out[64..96]),
+            lp_fee: decode_u24(&out[96..128]),
+        })
+    }
+
+    fn twap_tick(&self, pool_id: FixedBytes<32>, window: u32) -> Result<i32, FactsError> {
+        let out = self.staticcall(
+            self.source(FactSource::StateView),
+            selector(OBSERVE_SIG),
+            &observe_args(pool_id, window),
+        )?;
+        twap_tick_from_observe(&out, window)
+    }
+
+    fn is_rfs_closed(&self, position_id: FixedBytes<32>) -> Result<bool, FactsError> {
+        // (uint256 timeOfLastTransition, bool isOpen, uint256, uint256)
+        let out = self.word_call(
+            self.source(FactSource::VtsOrchestrator),
+            "positionToCheckpoint(bytes32)",
+            position_id.as_slice(),
+            4,
+        )?;
+        Ok(word(&out, 1).is_zero())
+    }
+
+    fn queue_amount(
+        &self,
+        lcc: AlloyAddress,
+        owner: AlloyAddress,
+    ) -> Result<AlloyU256, FactsError> {
+        let mut args = [0u8; 64];
+        args[12..32].copy_from_slice(lcc.as_slice());
+        args[44..64].copy_from_slice(owner.as_slice());
+        let out = self.word_call(
+            self.source(FactSource::LiquidityHub),
+            "settleQueue(address,address)",
+            &args,
+            1,
+        )?;
+        Ok(word(&out, 0))
+    }
+
+    fn reserve_of(&self, lcc: AlloyAddress) -> Result<AlloyU256, FactsError> {
+        let mut args = [0u8; 32];
+        args[12..32].copy_from_slice(lcc.as_slice());
+        let out = self.word_call(
+            self.source(FactSource::LiquidityHub),
+            "reserveOfUnderlying(address)",
+            &args,
+            1,
+        )?;
+        Ok(word(&out, 0))
+    }
+
+    fn queue_of_underlying(&self, lcc: AlloyAddress) -> Result<AlloyU256, FactsError> {
+        let mut args = [0u8; 32];
+        args[12..32].copy_from_slice(lcc.as_slice());
+        let out = self.word_call(
+            self.source(FactSource::LiquidityHub),
+            "queueOfUnderlying(address)",
+            &args,
+            1,
+        )?;
+        Ok(word(&out, 0))
+    }
+
+    fn get_settled_amounts(
+        &self,
+        position_id: FixedBytes<32>,
+    ) -> Result<(AlloyU256, AlloyU256), FactsError> {
+        let out = self.word_call(
+            self.source(FactSource::VtsOrchestrator),
+            "getPositionSettledAmounts(bytes32)",
+            position_id.as_slice(),
+            2,
+        )?;
+        Ok((word(&out, 0), word(&out, 1)))
+    }
+
+    fn get_commitment_maxima(
+        &self,
+        position_id: FixedBytes<32>,
+    ) -> Result<(AlloyU256, AlloyU256), FactsError> {
+        let out = self.word_call(
+            self.source(FactSource::VtsOrchestrator),
+            "getCommitmentMaxima(bytes32)",
+            position_id.as_slice(),
+            2,
+        )?;
+        Ok((word(&out, 0), word(&out, 1)))
+    }
+
+    fn grace_period_remaining(&self, position_id: FixedBytes<32>) -> Result<u64, FactsError> {
+        let checkpoint = self.word_call(
+            self.source(FactSource::VtsOrchestrator),
+            "positionToCheckpoint(bytes32)",
+            position_id.as_slice(),
+            4,
+        )?;
+        if word(&checkpoint, 1).is_zero() {
+            // RFS closed: the grace period does not apply.
+            return Ok(u64::MAX);
+        }
+        let time_of_last_transition = word(&checkpoint, 0);
+        let (extension0, extension1) = (word(&checkpoint, 2), word(&checkpoint, 3));
+
+        // Position(owner, poolId, ...) -> Pool(id, currency0, currency1, token0 config, token1 config, ...).
+        let position = self.word_call(
+            self.source(FactSource::VtsOrchestrator),
+            "getPosition(bytes32)",
+            position_id.as_slice(),
+            2,
+        )?;
+        let pool = self.word_call(
+            self.source(FactSource::VtsOrchestrator),
+            "getPool(bytes32)",
+            &position[32..64],
+            12,
+        )?;
+        let total0 = word(&pool, 3).saturating_add(extension0);
+        let total1 = word(&pool, 6).saturating_add(extension1);
+
+        let elapsed = AlloyU256::from(self.now).saturating_sub(time_of_last_transition);
+        let remaining = total0.min(total1).saturating_sub(elapsed);
+        Ok(u64::try_from(remaining).unwrap_or(u64::MAX))
+    }
+
+    fn staticcall_u256(
+        &self,
+        target: AlloyAddress,
+        selector: [u8; 4],
+        args: &[u8],
+    ) -> Result<AlloyU256, FactsError> {
+        let out = self.staticcall(Address::from(target.0 .0), selector, args)?;
+        if out.len() < 32 {
+            return Err(FactsError::MalformedReturn);
+        }
+        Ok(word(&out, 0))
+    }
+}
+
+fn call_failed(target: Address, data: &[u8], err: &anyhow::Error) -> FactsError {
+    let selector = &data[..4];
+    tracing::debug!(?target, ?selector, err = %format!("{err:#}"), "fact eth_call failed");
+    FactsError::CallFailed
+}
+
+/// The calls each kind of fact source is allowlisted for, as in the policy.
+fn source_calls(source: FactSource) -> &'static [&'static str] {
+    match source {
+        FactSource::StateView => &["getSlot0(bytes32)", OBSERVE_SIG],
+        FactSource::VtsOrchestrator => &[
+            "positionToCheckpoint(bytes32)",
+            "getPositionSettledAmounts(bytes32)",
+            "getCommitmentMaxima(bytes32)",
+            "getPosition(bytes32)",
+            "getPool(bytes32)",
+        ],
+        FactSource::LiquidityHub => &[
+            "reserveOfUnderlying(address)",
+            "settleQueue(address,address)",
+            "queueOfUnderlying(address)",
+        ],
+    }
+}
+
+fn selector(sig: &str) -> [u8; 4] {
+    id(sig)
+}
+
+fn word(out: &[u8], index: usize) -> AlloyU256 {
+    AlloyU256::from_be_slice(&out[32 * index..32 * (index + 1)])
+}
+
+fn decode_u24(word: &[u8]) -> u32 {
+    u32::from_be_bytes([0, word[29], word[30], word[31]])
+}
+
+fn decode_i24(word: &[u8]) -> i32 {
+    let raw = decode_u24(word);
+    // Sign-extend from 24 bits.
+    ((raw << 8) as i32) >> 8
+}
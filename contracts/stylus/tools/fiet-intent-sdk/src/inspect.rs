@@ -0,0 +1,190 @@
+//! Forensics for mined UserOperations: which envelope an op carried, and who signed it.
+//!
+//! [`inspect_tx`] reads an `EntryPoint.handleOps` transaction and unpacks every op with
+//! [`inspect_user_op`]: the Kernel validation mode and permission from the nonce key, the
+//! per-policy signature slices, and the first slice that decodes as an intent envelope, with its
+//! program and the address its signature recovers to.
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    providers::Middleware,
+    types::{Address, Bytes, Signature, H256},
+};
+use fiet_maker_policy_encoder::{
+    encoder::{check_opcode, decode::DecodedEnvelope, policy_intent_digest_in, PolicyDomain},
+    opcodes::Check,
+    types::IntentEnvelope,
+};
+use serde_json::Value;
+
+use crate::{
+    convert, enable,
+    kernel::{self, VALIDATION_MODE_ENABLE, VALIDATION_TYPE_PERMISSION},
+    simulate::decode_intent,
+    user_op::{decode_handle_ops, PackedUserOperation},
+};
+
+/// One UserOperation, unpacked.
+#[derive(Clone, Debug)]
+pub struct InspectedUserOp {
+    pub user_op: PackedUserOperation,
+    pub user_op_hash: H256,
+    /// Kernel validation mode from the nonce key (eg [`kernel::VALIDATION_MODE_ENABLE`]).
+    pub validation_mode: u8,
+    /// Kernel validation type from the nonce key.
+    pub validation_type: u8,
+    /// `bytes32` permission id, when the op is routed through a permission.
+    pub permission_id: Option<H256>,
+    /// `(index, sig)` per policy, in signature order (empty outside permissions).
+    pub policy_signatures: Vec<(u8, Bytes)>,
+    /// The permission signer's signature (the whole `signature` outside permissions).
+    pub signer_signature: Bytes,
+    /// The first policy signature that decodes as an intent envelope.
+    pub intent: Option<InspectedIntent>,
+}
+
+/// An intent envelope found in a UserOperation.
+#[derive(Clone, Debug)]
+pub struct InspectedIntent {
+    /// Position of the intent policy in the permission.
+    pub policy_index: u8,
+    pub envelope: DecodedEnvelope,
+    pub checks: Vec<Check>,
+    /// EIP-712 digest the envelope was signed over, when the policy address is known.
+    pub digest: Option<H256>,
+    /// Address the ECDSA signature recovers to over `digest` (`None` for passkey envelopes).
+    pub signer: Option<Address>,
+}
+
+/// Unpack `op`. With `policy`, the envelope digest is computed under `domain` (the policy's
+/// default unless the permission was installed with its own) and the signer recovered from it.
+pub fn inspect_user_op(
+    op: PackedUserOperation,
+    entry_point: Address,
+    chain_id: u64,
+    policy: Option<Address>,
+    domain: &PolicyDomain,
+) -> Result<InspectedUserOp> {
+    let mut nonce = [0u8; 32];
+    op.nonce.to_big_endian(&mut nonce);
+    let (validation_mode, validation_type) = (nonce[0], nonce[1]);
+    let user_op_hash = op.hash(entry_point, chain_id);
+
+    if validation_type != VALIDATION_TYPE_PERMISSION {
+        return Ok(InspectedUserOp {
+            signer_signature: op.signature.clone(),
+            user_op: op,
+            user_op_hash,
+            validation_mode,
+            validation_type,
+            permission_id: None,
+            policy_signatures: Vec::new(),
+            intent: None,
+        });
+    }
+
+    let mut permission_id = H256::zero();
+    permission_id.0[..4].copy_from_slice(&nonce[2..6]);
+    let signature = match validation_mode {
+        VALIDATION_MODE_ENABLE => enable::permission_signature_of(&op.signature)?,
+        _ => op.signature.to_vec(),
+    };
+    let (policy_sigs, signer_sig) = kernel::split_permission_signature(&signature)?;
+
+    let intent = policy_sigs.iter().find_map(|(index, sig)| {
+        let (envelope, checks) = decode_intent(sig).ok()?;
+        let digest = policy.map(|policy| {
+            let envelope = IntentEnvelope {
+                version: envelope.version,
+                nonce: envelope.nonce,
+                deadline: envelope.deadline,
+                call_bundle_hash: envelope.call_bundle_hash,
+                program_bytes: envelope.program_bytes.clone(),
+                signature: envelope.signature.clone(),
+                domain_chain_id: chain_id,
+                domain_verifying_contract: convert::address(policy),
+                wallet: convert::address(op.sender),
+                permission_id: convert::bytes32(permission_id),
+            };
+            H256(policy_intent_digest_in(&envelope, domain).0)
+        });
+        let signer = digest.and_then(|digest| {
+            Signature::try_from(envelope.signature.as_slice())
+                .ok()?
+                .recover(digest)
+                .ok()
+        });
+        Some(InspectedIntent {
+            policy_index: *index,
+            envelope,
+            checks,
+            digest,
+            signer,
+        })
+    });
+
+    Ok(InspectedUserOp {
+        policy_signatures: policy_sigs
+            .iter()
+            .map(|(index, sig)| (*index, Bytes::from(sig.to_vec())))
+            .collect(),
+        signer_signature: Bytes::from(signer_sig.to_vec()),
+        user_op: op,
+        user_op_hash,
+        validation_mode,
+        validation_type,
+        permission_id: Some(permission_id),
+        intent,
+    })
+}
+
+/// Fetch `tx_hash` and unpack every UserOperation in its `handleOps` call (see
+/// [`inspect_user_op`]). The transaction's recipient is taken as the EntryPoint.
+pub async fn inspect_tx<M: Middleware>(
+    client: &M,
+    tx_hash: H256,
+    policy: Option<Address>,
+    domain: &PolicyDomain,
+) -> Result<Vec<InspectedUserOp>> {
+    let tx = client
+        .get_transaction(tx_hash)
+        .await
+        .map_err(|err| anyhow!("{err}"))
+        .context("eth_getTransactionByHash failed")?
+        .ok_or_else(|| anyhow!("transaction {tx_hash:?} not found"))?;
+    let entry_point = tx
+        .to
+        .ok_or_else(|| anyhow!("transaction {tx_hash:?} is a contract creation"))?;
+    let chain_id = match tx.chain_id {
+        Some(chain_id) => chain_id.as_u64(),
+        None => client
+            .get_chainid()
+            .await
+            .map_err(|err| anyhow!("{err}"))
+            .context("failed fetching chain id")?
+            .as_u64(),
+    };
+    let (ops, _) = decode_handle_ops(&tx.input)?;
+    ops.into_iter()
+        .enumerate()
+        .map(|(i, op)| {
+            inspect_user_op(op, entry_point, chain_id, policy, domain)
+                .with_context(|| format!("op {i}"))
+        })
+        .collect()
+}
+
+/// One line per check: index, opcode name, and the check's fields as JSON.
+pub fn disassemble(checks: &[Check]) -> Vec<String> {
+    checks
+        .iter()
+        .enumerate()
+        .map(|(i, check)| {
+            let mut fields = serde_json::to_value(check).unwrap_or(Value::Null);
+            if let Some(fields) = fields.as_object_mut() {
+                fields.remove("kind");
+            }
+            format!("{i:>3}  {:?} {fields}", check_opcode(check))
+        })
+        .collect()
+}
@@ -0,0 +1,231 @@
+//! Kernel v3.3 encodings: nonce keys, `execute` calldata and permission signatures.
+//!
+//! Mirrors `e2e/src/kernel7702.ts`, which is exercised against a live Kernel on devnet.
+
+use anyhow::{anyhow, bail, Result};
+use ethers::{
+    abi::{self, Token},
+    types::{Address, Bytes, H256, U256},
+    utils::id,
+};
+use fiet_maker_policy_encoder::encoder::permission_id_bytes32;
+pub use fiet_maker_policy_encoder::types::PermissionModule;
+
+/// Nonce-key mode for an already installed validator.
+pub const VALIDATION_MODE_DEFAULT: u8 = 0x00;
+/// Nonce-key mode that installs the validator in the same UserOperation.
+pub const VALIDATION_MODE_ENABLE: u8 = 0x01;
+/// Validation type of a permission (policies + signer).
+pub const VALIDATION_TYPE_PERMISSION: u8 = 0x02;
+
+/// Prefix that ends the policy signatures and starts the signer signature.
+pub const SIGNER_SIG_PREFIX: u8 = 0xff;
+
+/// Kernel's `bytes4` permission id from its `bytes32` form (as passed to policies).
+///
+/// Kernel casts the `bytes4` to `bytes32` by right-padding, so only left-aligned ids
+/// (`0xdeadbeef00…00`) round-trip.
+pub fn permission_id4(permission_id: H256) -> Result<[u8; 4]> {
+    if permission_id.0[4..].iter().any(|b| *b != 0) {
+        bail!(
+            "permission id {permission_id:?} must be bytes4 left-aligned (eg 0xdeadbeef00..00) for Kernel v3.3"
+        );
+    }
+    let mut id4 = [0u8; 4];
+    id4.copy_from_slice(&permission_id.0[..4]);
+    Ok(id4)
+}
+
+/// The `bytes32` permission id of a policy/signer set, derived as Kernel's permission tooling
+/// does (see [`fiet_maker_policy_encoder::encoder::permission_id`]).
+pub fn derive_permission_id(policies: &[PermissionModule], signer: &PermissionModule) -> H256 {
+    H256(permission_id_bytes32(policies, signer).0)
+}
+
+/// Fail unless `permission_id` is the id derived from `policies` and `signer`.
+///
+/// A mismatch means UserOperations are routed to a permission that was installed with a
+/// different module set (or not at all).
+pub fn ensure_permission_id(
+    permission_id: H256,
+    policies: &[PermissionModule],
+    signer: &PermissionModule,
+) -> Result<()> {
+    let derived = derive_permission_id(policies, signer);
+    if derived != permission_id {
+        bail!(
+            "permission id {permission_id:?} does not match the policy/signer set (derived {derived:?}); \
+             check module order, flags and initData"
+        );
+    }
+    Ok(())
+}
+
+/// `vIdWithoutType` of a permission validator: `permissionId4 || 16 zero bytes`.
+pub fn permission_validator_id(permission_id4: [u8; 4]) -> [u8; 20] {
+    let mut vid = [0u8; 20];
+    vid[..4].copy_from_slice(&permission_id4);
+    vid
+}
+
+/// Kernel's `uint192` nonce key: `mode:1 || vType:1 || vIdWithoutType:20 || parallelKey:2`.
+pub fn nonce_key(mode: u8, validation_type: u8, validator_id: [u8; 20], parallel_key: u16) -> U256 {
+    let mut packed = [0u8; 24];
+    packed[0] = mode;
+    packed[1] = validation_type;
+    packed[2..22].copy_from_slice(&validator_id);
+    packed[22..].copy_from_slice(&parallel_key.to_be_bytes());
+    U256::from_big_endian(&packed)
+}
+
+/// Nonce key that routes a UserOperation through an installed permission.
+pub fn permission_nonce_key(permission_id: H256, parallel_key: u16) -> Result<U256> {
+    let id4 = permission_id4(permission_id)?;
+    Ok(nonce_key(
+        VALIDATION_MODE_DEFAULT,
+        VALIDATION_TYPE_PERMISSION,
+        permission_validator_id(id4),
+        parallel_key,
+    ))
+}
+
+/// One call made by the account.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Execution {
+    pub target: Address,
+    pub value: U256,
+    pub data: Bytes,
+}
+
+impl Execution {
+    pub fn new(target: Address, value: U256, data: impl Into<Bytes>) -> Self {
+        Self {
+            target,
+            value,
+            data: data.into(),
+        }
+    }
+}
+
+/// ERC-7579 exec mode for a single call (`CALLTYPE_SINGLE`, default exec type).
+pub const EXEC_MODE_SINGLE: [u8; 32] = [0u8; 32];
+
+/// ERC-7579 exec mode for a batch (`CALLTYPE_BATCH = 0x01`, default exec type).
+pub const EXEC_MODE_BATCH: [u8; 32] = {
+    let mut mode = [0u8; 32];
+    mode[0] = 0x01;
+    mode
+};
+
+/// `execute(bytes32 execMode, bytes executionCalldata)` for a single call (exec mode zero).
+///
+/// `ExecLib.encodeSingle` is `abi.encodePacked(target, value, callData)`.
+pub fn execute_single_calldata(to: Address, value: U256, data: &[u8]) -> Bytes {
+    let mut single = Vec::with_capacity(20 + 32 + data.len());
+    single.extend_from_slice(to.as_bytes());
+    let mut value_be = [0u8; 32];
+    value.to_big_endian(&mut value_be);
+    single.extend_from_slice(&value_be);
+    single.extend_from_slice(data);
+    execute_calldata(EXEC_MODE_SINGLE, single)
+}
+
+/// `execute(bytes32 execMode, bytes executionCalldata)` for a batch.
+///
+/// `ExecLib.encodeBatch` is `abi.encode(Execution[])` with `Execution(target, value, callData)`.
+pub fn execute_batch_calldata(executions: &[Execution]) -> Bytes {
+    let batch = abi::encode(&[Token::Array(
+        executions
+            .iter()
+            .map(|e| {
+                Token::Tuple(vec![
+                    Token::Address(e.target),
+                    Token::Uint(e.value),
+                    Token::Bytes(e.data.to_vec()),
+                ])
+            })
+            .collect(),
+    )]);
+    execute_calldata(EXEC_MODE_BATCH, batch)
+}
+
+fn execute_calldata(mode: [u8; 32], execution_calldata: Vec<u8>) -> Bytes {
+    let mut calldata = id("execute(bytes32,bytes)").to_vec();
+    calldata.extend(abi::encode(&[
+        Token::FixedBytes(mode.to_vec()),
+        Token::Bytes(execution_calldata),
+    ]));
+    calldata.into()
+}
+
+/// One policy's slice of a permission's `userOp.signature`: `index:1 || len:8 (big-endian) || sig`.
+pub fn policy_signature_fragment(index: u8, sig: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(9 + sig.len());
+    out.push(index);
+    out.extend_from_slice(&(sig.len() as u64).to_be_bytes());
+    out.extend_from_slice(sig);
+    out
+}
+
+/// Pack a permission's `userOp.signature`.
+///
+/// Each non-empty policy signature is `index:1 || len:8 (big-endian) || sig`, in ascending
+/// index order; the signer signature follows a `0xff` marker. Signature-less policies (such as
+/// CallPolicy) are simply omitted.
+pub fn pack_permission_signature(
+    policy_sigs: &[(u8, Vec<u8>)],
+    signer_sig: &[u8],
+) -> Result<Bytes> {
+    let mut sorted: Vec<&(u8, Vec<u8>)> = policy_sigs
+        .iter()
+        .filter(|(_, sig)| !sig.is_empty())
+        .collect();
+    sorted.sort_by_key(|(index, _)| *index);
+
+    let mut out = Vec::new();
+    let mut previous = None;
+    for (index, sig) in sorted {
+        if *index == SIGNER_SIG_PREFIX {
+            bail!("policy index 0xff is reserved for the signer signature");
+        }
+        if previous == Some(*index) {
+            bail!("duplicate signature for policy index {index}");
+        }
+        previous = Some(*index);
+        out.extend_from_slice(&policy_signature_fragment(*index, sig));
+    }
+    out.push(SIGNER_SIG_PREFIX);
+    out.extend_from_slice(signer_sig);
+    Ok(out.into())
+}
+
+/// Policy signatures (`(index, sig)`, in the order they appear) and the signer signature of a
+/// permission's `userOp.signature`.
+pub type PermissionSignatureParts<'a> = (Vec<(u8, &'a [u8])>, &'a [u8]);
+
+/// Split a permission's `userOp.signature` back into its parts; the inverse of
+/// [`pack_permission_signature`].
+pub fn split_permission_signature(signature: &[u8]) -> Result<PermissionSignatureParts<'_>> {
+    let mut policy_sigs = Vec::new();
+    let mut rest = signature;
+    loop {
+        let Some((&index, tail)) = rest.split_first() else {
+            bail!("permission signature has no 0xff signer marker");
+        };
+        if index == SIGNER_SIG_PREFIX {
+            return Ok((policy_sigs, tail));
+        }
+        if tail.len() < 8 {
+            bail!("policy {index} signature length is truncated");
+        }
+        let (len, tail) = tail.split_at(8);
+        let len = u64::from_be_bytes(len.try_into().expect("8 bytes"));
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= tail.len())
+            .ok_or_else(|| anyhow!("policy {index} signature overruns the permission signature"))?;
+        let (sig, tail) = tail.split_at(len);
+        policy_sigs.push((index, sig));
+        rest = tail;
+    }
+}
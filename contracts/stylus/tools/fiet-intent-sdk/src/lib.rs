@@ -0,0 +1,56 @@
+//! Client SDK for submitting intent-gated UserOperations through a Kernel permission.
+//!
+//! A market-maker service describes what it wants to execute and the on-chain conditions it must
+//! hold under ([`Intent`]); [`IntentClient::submit`] does the rest:
+//!
+//! 1. read the permission's replay nonce from the policy ([`nonce`]),
+//! 2. encode the check program and sign the EIP-712 envelope ([`envelope`]),
+//! 3. wrap the call in Kernel `execute`, pack the per-policy signatures and sign the
+//!    `PackedUserOperation` ([`kernel`], [`user_op`]),
+//! 4. submit it with `EntryPoint.handleOps` and wait for the `UserOperationEvent`, or hand it to a
+//!    4337 bundler with [`IntentClient::submit_via_bundler`] ([`bundler`]).
+//!
+//! The permission (signer + intent policy, usually alongside CallPolicy) must already be
+//! installed on the account. Operators sharing a permission can coordinate nonces through a
+//! reservation file ([`reservations`], [`IntentClient::with_reservations`]).
+//!
+//! [`bindings`] has typed `sol!` calls for the policy's interface and Kernel's `installModule`, and
+//! [`bindings::PolicyReader`] reads the policy's views with them. [`events`] filters and decodes
+//! the policy's install, consumption and revocation logs.
+//!
+//! [`simulate::simulate_envelope`] dry-runs a signed envelope instead: it reads the program's facts
+//! over RPC ([`facts`]) and reports every check's outcome; [`simulate::simulate_with_gas`] also
+//! estimates each check's gas.
+//!
+//! [`inspect::inspect_tx`] goes the other way for forensics: it unpacks the UserOperations of a
+//! mined `handleOps` transaction and decodes the envelope, program and signer each one carried.
+
+pub mod bindings;
+pub mod bundler;
+pub mod client;
+mod convert;
+pub mod enable;
+pub mod envelope;
+pub mod events;
+pub mod facts;
+pub mod inspect;
+pub mod kernel;
+pub mod nonce;
+pub mod program;
+pub mod remote_signer;
+pub mod reservations;
+pub mod simulate;
+pub mod user_op;
+
+pub use bundler::Bundler;
+pub use client::{Intent, IntentClient, IntentConfig, SignedIntent, Submission};
+pub use enable::EnableData;
+pub use fiet_maker_policy_encoder::opcodes::{Check, CompOp};
+pub use kernel::Execution;
+pub use program::Program;
+pub use remote_signer::RemoteSigner;
+pub use reservations::NonceReservations;
+pub use user_op::{GasLimits, PackedUserOperation, UserOpBuilder};
+
+#[cfg(test)]
+mod tests;
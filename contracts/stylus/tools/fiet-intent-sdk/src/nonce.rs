@@ -0,0 +1,125 @@
+//! Nonce reads: the policy's per-permission replay nonce and the EntryPoint's account nonce,
+//! plus `revokeNonce`, which raises the policy nonce past outstanding envelopes.
+
+use anyhow::{bail, Context, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    providers::Middleware,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, TransactionReceipt, TransactionRequest,
+        H256, U256,
+    },
+    utils::{id, keccak256},
+};
+
+use crate::{
+    bindings::{call_tx, IIntentPolicy},
+    convert,
+};
+
+/// Storage slot of `nonce_of` (declared after `used_ids` in `IntentPolicy`).
+const NONCE_OF_SLOT: u64 = 1;
+
+/// `nonce_of[keccak256(wallet || permissionId)]`, laid out as a Solidity mapping.
+pub fn policy_nonce_slot(wallet: Address, permission_id: H256) -> H256 {
+    let mut composite = Vec::with_capacity(20 + 32);
+    composite.extend_from_slice(wallet.as_bytes());
+    composite.extend_from_slice(permission_id.as_bytes());
+    let key = keccak256(composite);
+
+    let mut preimage = key.to_vec();
+    let mut slot = [0u8; 32];
+    U256::from(NONCE_OF_SLOT).to_big_endian(&mut slot);
+    preimage.extend_from_slice(&slot);
+    H256(keccak256(preimage))
+}
+
+/// The nonce the next envelope for `(wallet, permission_id)` must carry.
+///
+/// Reads the `nonce_of` slot directly, which also works on deployments that predate the
+/// `nonceOf` view ([`PolicyReader::nonce_of`](crate::bindings::PolicyReader::nonce_of)).
+pub async fn policy_nonce<M: Middleware>(
+    client: &M,
+    policy: Address,
+    wallet: Address,
+    permission_id: H256,
+) -> Result<U256> {
+    let word = client
+        .get_storage_at(policy, policy_nonce_slot(wallet, permission_id), None)
+        .await
+        .map_err(|err| anyhow::anyhow!("{err}"))
+        .context("failed reading the policy nonce slot")?;
+    Ok(U256::from_big_endian(word.as_bytes()))
+}
+
+/// `IntentPolicy.revokeNonce(wallet, permissionId, minNonce)`: sent from the wallet or the
+/// permission's envelope signer, it raises the policy nonce to at least `min_nonce` (and by at
+/// least one), invalidating every envelope signed below it.
+///
+/// Pass one past the highest nonce handed out (see
+/// [`NonceReservations::highest`](crate::reservations::NonceReservations::highest)) to cancel
+/// envelopes queued ahead of the current nonce too; zero burns only the current one.
+pub fn revoke_nonce_tx(
+    policy: Address,
+    wallet: Address,
+    permission_id: H256,
+    min_nonce: U256,
+) -> TypedTransaction {
+    call_tx(
+        policy,
+        &IIntentPolicy::revokeNonceCall {
+            wallet: convert::address(wallet),
+            permission_id: convert::bytes32(permission_id),
+            min_nonce: convert::u256(min_nonce),
+        },
+    )
+}
+
+/// Send [`revoke_nonce_tx`] from `client`'s account and wait for it to be mined.
+pub async fn revoke_nonce<M: Middleware>(
+    client: &M,
+    policy: Address,
+    wallet: Address,
+    permission_id: H256,
+    min_nonce: U256,
+) -> Result<TransactionReceipt> {
+    let pending = client
+        .send_transaction(
+            revoke_nonce_tx(policy, wallet, permission_id, min_nonce),
+            None,
+        )
+        .await
+        .map_err(|err| anyhow::anyhow!("{err}"))
+        .context("revokeNonce submission failed")?;
+    let receipt = pending
+        .await
+        .context("failed waiting for the revokeNonce receipt")?
+        .context("revokeNonce transaction dropped from the mempool")?;
+    if receipt.status != Some(1.into()) {
+        bail!("revokeNonce reverted (tx {:?})", receipt.transaction_hash);
+    }
+    Ok(receipt)
+}
+
+/// `EntryPoint.getNonce(sender, key)`.
+pub async fn entry_point_nonce<M: Middleware>(
+    client: &M,
+    entry_point: Address,
+    sender: Address,
+    key: U256,
+) -> Result<U256> {
+    let mut data = id("getNonce(address,uint192)").to_vec();
+    data.extend(abi::encode(&[Token::Address(sender), Token::Uint(key)]));
+    let tx: TypedTransaction = TransactionRequest::new().to(entry_point).data(data).into();
+    let out = client
+        .call(&tx, None)
+        .await
+        .map_err(|err| anyhow::anyhow!("{err}"))
+        .context("EntryPoint.getNonce failed")?;
+    let mut decoded =
+        abi::decode(&[ParamType::Uint(256)], &out).context("malformed getNonce return data")?;
+    decoded
+        .pop()
+        .and_then(Token::into_uint)
+        .context("malformed getNonce return data")
+}
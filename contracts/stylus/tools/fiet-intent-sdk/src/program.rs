@@ -0,0 +1,235 @@
+//! Typed builder for check programs.
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use fiet_maker_policy_encoder::{
+    encoder::{
+        encode_program,
+        lint::{lint_program, Finding, LintContext},
+    },
+    opcodes::{canonicalize, Check, CompOp},
+};
+use serde::{Deserialize, Serialize};
+
+/// An ordered list of checks, evaluated by the policy against on-chain facts.
+///
+/// Checks run in insertion order and the first failure rejects the UserOperation.
+///
+/// Serializes as a JSON array of checks in the types crate's representation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Program {
+    checks: Vec<Check>,
+}
+
+impl Program {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an arbitrary check.
+    pub fn check(mut self, check: Check) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// `block.timestamp <= deadline`.
+    pub fn deadline(self, deadline: u64) -> Self {
+        self.check(Check::Deadline { deadline })
+    }
+
+    /// The wallet's `token` balance falls by at most `max_out` across execution (enforced by the
+    /// policy's hook, which must be installed on the permission).
+    pub fn wallet_token_delta_lte(self, token: Address, max_out: U256) -> Self {
+        self.check(Check::WalletTokenDeltaLte { token, max_out })
+    }
+
+    /// The wallet's `token` outflow under this permission totals at most `max` over the trailing
+    /// `window` seconds, this execution included (enforced by the policy's hook).
+    pub fn window_spend_lte(self, token: Address, window: u32, max: U256) -> Self {
+        self.check(Check::WindowSpendLte { token, window, max })
+    }
+
+    /// Pool tick within `[min, max]`.
+    pub fn tick_bounds(self, pool_id: FixedBytes<32>, min: i32, max: i32) -> Self {
+        self.check(Check::Slot0TickBounds { pool_id, min, max })
+    }
+
+    /// Pool tick averaged over the trailing `window` seconds within `[min, max]`; harder to push
+    /// around within one block than [`Program::tick_bounds`].
+    pub fn twap_tick_bounds(
+        self,
+        pool_id: FixedBytes<32>,
+        window: u32,
+        min: i32,
+        max: i32,
+    ) -> Self {
+        self.check(Check::TwapTickBounds {
+            pool_id,
+            window,
+            min,
+            max,
+        })
+    }
+
+    /// Pool spot price within `max_bps` of the `decimals`-decimal price `oracle.selector()`
+    /// returns; the oracle call must be allowlisted when the permission is installed.
+    pub fn oracle_deviation_lte(
+        self,
+        pool_id: FixedBytes<32>,
+        oracle: Address,
+        selector: [u8; 4],
+        decimals: u8,
+        max_bps: u32,
+    ) -> Self {
+        self.check(Check::OracleDeviationLte {
+            pool_id,
+            oracle,
+            selector,
+            decimals,
+            max_bps,
+        })
+    }
+
+    /// Pool `sqrtPriceX96` within `[min, max]`.
+    pub fn sqrt_price_bounds(self, pool_id: FixedBytes<32>, min: U256, max: U256) -> Self {
+        self.check(Check::Slot0SqrtPriceBounds { pool_id, min, max })
+    }
+
+    /// The pool the intent declares it is for is on the permission's install-time pool allowlist,
+    /// as is every pool the call bundle passes.
+    pub fn pool_allowed(self, pool_id: FixedBytes<32>) -> Self {
+        self.check(Check::PoolAllowed { pool_id })
+    }
+
+    /// The position's RFS checkpoint is closed.
+    pub fn rfs_closed(self, position_id: FixedBytes<32>) -> Self {
+        self.check(Check::RfsClosed { position_id })
+    }
+
+    /// `settleQueue[lcc][owner] <= max`.
+    pub fn queue_lte(self, lcc: Address, owner: Address, max: U256) -> Self {
+        self.check(Check::QueueLte { lcc, owner, max })
+    }
+
+    /// `settleQueue[lcc][owner]` summed over `owners` is at most `max`; 1 to
+    /// [`MAX_QUEUE_SUM_OWNERS`](fiet_maker_policy_encoder::opcodes::MAX_QUEUE_SUM_OWNERS) owners.
+    pub fn queue_sum_lte(self, lcc: Address, owners: Vec<Address>, max: U256) -> Self {
+        self.check(Check::QueueSumLte { lcc, owners, max })
+    }
+
+    /// `reserveOfUnderlying[lcc] >= min`.
+    pub fn reserve_gte(self, lcc: Address, min: U256) -> Self {
+        self.check(Check::ReserveGte { lcc, min })
+    }
+
+    /// `reserveOfUnderlying[lcc] * 10_000 >= queueOfUnderlying[lcc] * min_ratio_bps`.
+    pub fn hub_solvency(self, lcc: Address, min_ratio_bps: u32) -> Self {
+        self.check(Check::HubSolvency { lcc, min_ratio_bps })
+    }
+
+    /// Settled amounts of the position are at least `(min_amount0, min_amount1)`.
+    pub fn settled_gte(
+        self,
+        position_id: FixedBytes<32>,
+        min_amount0: U256,
+        min_amount1: U256,
+    ) -> Self {
+        self.check(Check::SettledGte {
+            position_id,
+            min_amount0,
+            min_amount1,
+        })
+    }
+
+    /// Commitment deficit of the position is at most `(max_deficit0, max_deficit1)`.
+    pub fn commitment_deficit_lte(
+        self,
+        position_id: FixedBytes<32>,
+        max_deficit0: U256,
+        max_deficit1: U256,
+    ) -> Self {
+        self.check(Check::CommitmentDeficitLte {
+            position_id,
+            max_deficit0,
+            max_deficit1,
+        })
+    }
+
+    /// At least `min_seconds` of grace period remain for the position.
+    pub fn grace_period_gte(self, position_id: FixedBytes<32>, min_seconds: u64) -> Self {
+        self.check(Check::GracePeriodGte {
+            position_id,
+            min_seconds,
+        })
+    }
+
+    /// `target.staticcall(selector || args)` returns a `uint256` satisfying `op rhs`.
+    pub fn static_call_u256(
+        self,
+        target: Address,
+        selector: [u8; 4],
+        args: Vec<u8>,
+        op: CompOp,
+        rhs: U256,
+    ) -> Self {
+        self.check(Check::StaticCallU256 {
+            target,
+            selector,
+            args,
+            op,
+            rhs,
+        })
+    }
+
+    /// Two allowlisted `uint256` staticcalls satisfy `lhs op rhs * scale_bps / 10_000`
+    /// ([`SCALE_BPS_ONE`](fiet_maker_policy_encoder::opcodes::SCALE_BPS_ONE) compares them unscaled).
+    pub fn static_call_compare(
+        self,
+        lhs: (Address, [u8; 4], Vec<u8>),
+        op: CompOp,
+        rhs: (Address, [u8; 4], Vec<u8>),
+        scale_bps: u32,
+    ) -> Self {
+        let (lhs_target, lhs_selector, lhs_args) = lhs;
+        let (rhs_target, rhs_selector, rhs_args) = rhs;
+        self.check(Check::StaticCallCompare {
+            lhs_target,
+            lhs_selector,
+            lhs_args,
+            op,
+            rhs_target,
+            rhs_selector,
+            rhs_args,
+            scale_bps,
+        })
+    }
+
+    pub fn checks(&self) -> &[Check] {
+        &self.checks
+    }
+
+    /// The same checks in canonical order, sorted by encoding with duplicates dropped, so the
+    /// program hashes the same whichever order it was built in.
+    pub fn canonical(&self) -> Self {
+        Self {
+            checks: canonicalize(&self.checks),
+        }
+    }
+
+    /// Wire encoding signed into the envelope (`programBytes`).
+    pub fn encode(&self) -> Vec<u8> {
+        encode_program(&self.checks)
+    }
+
+    /// ERC-4337 validation-rule findings for this program (see
+    /// [`fiet_maker_policy_encoder::encoder::lint`]).
+    pub fn lint(&self, ctx: &LintContext) -> Vec<Finding> {
+        lint_program(&self.checks, ctx)
+    }
+}
+
+impl From<Vec<Check>> for Program {
+    fn from(checks: Vec<Check>) -> Self {
+        Self { checks }
+    }
+}
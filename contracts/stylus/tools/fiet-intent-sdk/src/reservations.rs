@@ -0,0 +1,170 @@
+//! File-backed policy nonce reservations shared by operators signing for the same permissions.
+//!
+//! An ethers-typed view of the encoder's reservation store
+//! ([`fiet_maker_policy_encoder::encoder::reservations`]), which documents the file format and
+//! reconciliation rules. The `fiet-maker-policy-encoder nonce` subcommands read and write the same
+//! file, so scripts and SDK clients can share one reservation file.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use ethers::types::{Address, H256, U256};
+use fiet_maker_policy_encoder::encoder::reservations as store;
+
+use crate::convert::{address, bytes32, from_u256, u256};
+
+/// A nonce held by one signer until it is consumed on-chain or its envelope expires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reservation {
+    pub nonce: U256,
+    /// Deadline of the envelope signed at this nonce (unix seconds).
+    pub deadline: u64,
+    /// Free-form operator label, for humans reading the file.
+    pub holder: String,
+    pub reserved_at: u64,
+}
+
+impl From<store::Reservation> for Reservation {
+    fn from(r: store::Reservation) -> Self {
+        Self {
+            nonce: from_u256(r.nonce),
+            deadline: r.deadline,
+            holder: r.holder,
+            reserved_at: r.reserved_at,
+        }
+    }
+}
+
+/// A reservation file plus the label recorded on reservations made through it.
+#[derive(Clone, Debug)]
+pub struct NonceReservations {
+    inner: store::NonceReservations,
+}
+
+impl NonceReservations {
+    pub fn new(path: impl Into<PathBuf>, holder: impl Into<String>) -> Self {
+        Self {
+            inner: store::NonceReservations::new(path, holder),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.inner.path()
+    }
+
+    /// Reserve a nonce for an envelope expiring at `deadline`, given the policy's current
+    /// `chain_nonce` (see [`crate::nonce::policy_nonce`]).
+    pub fn reserve(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: H256,
+        chain_nonce: U256,
+        deadline: u64,
+    ) -> Result<U256> {
+        self.inner
+            .reserve(
+                address(policy),
+                address(wallet),
+                bytes32(permission_id),
+                u256(chain_nonce),
+                deadline,
+            )
+            .map(from_u256)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn reserve_at(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: H256,
+        chain_nonce: U256,
+        deadline: u64,
+        now: u64,
+    ) -> Result<U256> {
+        self.inner
+            .reserve_at(
+                address(policy),
+                address(wallet),
+                bytes32(permission_id),
+                u256(chain_nonce),
+                deadline,
+                now,
+            )
+            .map(from_u256)
+    }
+
+    /// Give `nonce` back, eg when signing or submission was abandoned before the envelope left
+    /// the process. Returns whether it was reserved.
+    pub fn release(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: H256,
+        nonce: U256,
+    ) -> Result<bool> {
+        self.inner.release(
+            address(policy),
+            address(wallet),
+            bytes32(permission_id),
+            u256(nonce),
+        )
+    }
+
+    /// Live reservations for a permission after reconciling against `chain_nonce`.
+    pub fn pending(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: H256,
+        chain_nonce: U256,
+    ) -> Result<Vec<Reservation>> {
+        let held = self.inner.pending(
+            address(policy),
+            address(wallet),
+            bytes32(permission_id),
+            u256(chain_nonce),
+        )?;
+        Ok(held.into_iter().map(Reservation::from).collect())
+    }
+
+    /// Highest live reservation for a permission after reconciling against `chain_nonce`, if any.
+    ///
+    /// Envelopes may be signed for every nonce up to it, so a revocation that should cancel all
+    /// of them passes one past it as `revokeNonce`'s `minNonce`.
+    pub fn highest(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: H256,
+        chain_nonce: U256,
+    ) -> Result<Option<U256>> {
+        let highest = self.inner.highest(
+            address(policy),
+            address(wallet),
+            bytes32(permission_id),
+            u256(chain_nonce),
+        )?;
+        Ok(highest.map(from_u256))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn highest_at(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: H256,
+        chain_nonce: U256,
+        now: u64,
+    ) -> Result<Option<U256>> {
+        let highest = self.inner.highest_at(
+            address(policy),
+            address(wallet),
+            bytes32(permission_id),
+            u256(chain_nonce),
+            now,
+        )?;
+        Ok(highest.map(from_u256))
+    }
+}
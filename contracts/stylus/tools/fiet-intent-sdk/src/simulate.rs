@@ -0,0 +1,544 @@
+//! Off-chain dry run of an envelope: every check's outcome against live facts.
+//!
+//! The policy stops at the first failing check and reports a bare `POLICY_FAILED`. This evaluator
+//! follows the same rules (see the policy's `evaluate_program`) but runs every check and records
+//! what it observed, which is what dashboards and pre-trade checks want.
+//!
+//! [`simulate_with_gas`] also estimates each check's gas: the `STATICCALL`s of its fact reads
+//! (EIP-2929 access cost plus the callee's execution, from `eth_estimateGas`), so program authors
+//! can see which checks dominate validation cost. The policy's own Stylus execution is not
+//! included; it is small next to the calls.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use alloy_primitives::{Address as AlloyAddress, U256};
+use anyhow::{anyhow, Context, Result};
+use ethers::providers::Middleware;
+use fiet_maker_policy_encoder::{
+    encoder::{
+        check_opcode,
+        decode::{decode_envelope, decode_program, DecodedEnvelope},
+    },
+    facts::{
+        deviation_bps, solvency_ratio_bps, sqrt_price_x96_to_wad, to_wad, FactsError, FactsProvider,
+    },
+    opcodes::{routed_call_target_count, scale_bps, Check, CompOp, MAX_CALL_TARGETS},
+};
+use serde::Serialize;
+
+use crate::{
+    envelope::{ENVELOPE_VERSION, ENVELOPE_VERSION_TYPED_CHECKS},
+    facts::{FactCall, FactSources, RpcEndpoints, RpcFactsProvider},
+};
+
+/// `STATICCALL` access cost of a target not yet touched in the transaction (EIP-2929).
+pub const COLD_CALL_GAS: u64 = 2_600;
+/// `STATICCALL` access cost of a target already touched.
+pub const WARM_CALL_GAS: u64 = 100;
+
+/// Outcome of one check.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckOutcome {
+    pub index: usize,
+    /// Opcode name, eg `CheckSlot0TickBounds`.
+    pub opcode: String,
+    pub passed: bool,
+    /// The fact value(s) the check compared, when they could be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed: Option<String>,
+    /// Why the check failed: the policy's `ValidationError` name, plus the fact error if a read
+    /// failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Estimated gas of the check's fact reads, when profiled and every estimate succeeded.
+    /// Checks that read nothing cost `0`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<u64>,
+}
+
+/// Result of simulating a whole envelope.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Simulation {
+    /// Block the facts were read at, and its timestamp (`now` for deadline checks).
+    pub block: u64,
+    pub timestamp: u64,
+    pub envelope_version: u16,
+    pub envelope_deadline: u64,
+    /// `false` if the envelope version is unsupported, its deadline has passed, or the program
+    /// with the sources' overrides calls more than `MAX_CALL_TARGETS` distinct contracts.
+    pub envelope_valid: bool,
+    pub checks: Vec<CheckOutcome>,
+    /// Envelope valid and every check passed. The signature and replay nonce are not verified.
+    pub passed: bool,
+    /// Sum of the checks' estimated gas, when profiled; checks without an estimate are left out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<u64>,
+}
+
+/// Evaluate every check against `facts`.
+pub fn evaluate_verbose<F: FactsProvider>(checks: &[Check], facts: &F) -> Vec<CheckOutcome> {
+    checks
+        .iter()
+        .enumerate()
+        .map(|(index, check)| outcome(index, check, evaluate_check(check, facts)))
+        .collect()
+}
+
+/// [`evaluate_verbose`] against RPC facts, each check reading the source its opcode is
+/// [routed](RpcFactsProvider::routed) to.
+pub fn evaluate_routed<M: Middleware + 'static>(
+    checks: &[Check],
+    facts: &RpcFactsProvider<M>,
+) -> Vec<CheckOutcome> {
+    checks
+        .iter()
+        .enumerate()
+        .map(|(index, check)| {
+            let evaluation = facts.routed(check_opcode(check), || evaluate_check(check, facts));
+            outcome(index, check, evaluation)
+        })
+        .collect()
+}
+
+fn outcome(index: usize, check: &Check, evaluation: Evaluation) -> CheckOutcome {
+    let (passed, observed, error) = match evaluation {
+        Ok((observed, None)) => (true, observed, None),
+        Ok((observed, Some(error))) => (false, observed, Some(error.to_string())),
+        Err((error, facts_error)) => (false, None, Some(format!("{error}: {facts_error}"))),
+    };
+    CheckOutcome {
+        index,
+        opcode: format!("{:?}", check_opcode(check)),
+        passed,
+        observed,
+        error,
+        gas: None,
+    }
+}
+
+/// Decode `envelope` (the policy's signature slice) and its program.
+pub fn decode_intent(envelope: &[u8]) -> Result<(DecodedEnvelope, Vec<Check>)> {
+    let envelope =
+        decode_envelope(envelope).map_err(|err| anyhow!("malformed envelope: {err}"))?;
+    let checks = decode_program(&envelope.program_bytes)
+        .map_err(|err| anyhow!("malformed program: {err}"))?;
+    Ok((envelope, checks))
+}
+
+/// Decode and [`simulate`] an encoded envelope.
+pub async fn simulate_envelope<M: Middleware + 'static>(
+    client: impl Into<RpcEndpoints<M>>,
+    envelope: &[u8],
+    sources: FactSources,
+    block: Option<u64>,
+) -> Result<Simulation> {
+    let (envelope, checks) = decode_intent(envelope)?;
+    simulate(client, &envelope, checks, sources, block).await
+}
+
+/// Evaluate a decoded envelope against facts read over RPC at `block` (latest when `None`).
+/// `client` is one provider or [`RpcEndpoints`] with retries and failover.
+pub async fn simulate<M: Middleware + 'static>(
+    client: impl Into<RpcEndpoints<M>>,
+    envelope: &DecodedEnvelope,
+    checks: Vec<Check>,
+    sources: FactSources,
+    block: Option<u64>,
+) -> Result<Simulation> {
+    run(client.into(), envelope, checks, sources, block, false).await
+}
+
+/// [`simulate`], with each check's gas estimated at the same block (two `eth_estimateGas` per
+/// distinct fact read).
+pub async fn simulate_with_gas<M: Middleware + 'static>(
+    client: impl Into<RpcEndpoints<M>>,
+    envelope: &DecodedEnvelope,
+    checks: Vec<Check>,
+    sources: FactSources,
+    block: Option<u64>,
+) -> Result<Simulation> {
+    run(client.into(), envelope, checks, sources, block, true).await
+}
+
+async fn run<M: Middleware + 'static>(
+    endpoints: RpcEndpoints<M>,
+    envelope: &DecodedEnvelope,
+    checks: Vec<Check>,
+    sources: FactSources,
+    block: Option<u64>,
+    profile_gas: bool,
+) -> Result<Simulation> {
+    // The policy counts the overrides a program routes to against the target cap.
+    let overrides = sources
+        .overrides
+        .iter()
+        .map(|o| (o.opcode, AlloyAddress::from(o.source.0)));
+    let within_target_cap = routed_call_target_count(&checks, overrides) <= MAX_CALL_TARGETS;
+    let facts = match block {
+        Some(block) => RpcFactsProvider::at_block_via(endpoints, sources, block).await?,
+        None => RpcFactsProvider::latest_via(endpoints, sources).await?,
+    };
+    // Assume the program's oracles are the ones allowlisted at install; the policy fails the
+    // check with `OracleDeviationExceeded` when they are not.
+    let facts = facts.with_allowed_calls(oracle_calls(&checks));
+    let (block, timestamp) = (facts.block_number(), facts.block_timestamp());
+    let envelope_valid = matches!(
+        envelope.version,
+        ENVELOPE_VERSION | ENVELOPE_VERSION_TYPED_CHECKS
+    ) && timestamp <= envelope.deadline
+        && within_target_cap;
+
+    // Read every fact at once; whatever the prefetch missed blocks on its eth_call, so keep the
+    // evaluation off the runtime workers.
+    facts.prefetch(|facts| drop(evaluate_routed(&checks, facts))).await?;
+    let facts = Arc::new(facts);
+    let evaluation = {
+        let facts = facts.clone();
+        tokio::task::spawn_blocking(move || {
+            let outcomes = evaluate_routed(&checks, facts.as_ref());
+            // Cached by now, so recording costs no extra reads.
+            let calls: Vec<_> = checks
+                .iter()
+                .map(|check| {
+                    facts.record_calls(|facts| {
+                        facts.routed(check_opcode(check), || drop(evaluate_check(check, facts)))
+                    })
+                })
+                .collect();
+            (outcomes, calls)
+        })
+    };
+    let (mut checks, calls) = evaluation.await.context("check evaluation panicked")?;
+    let mut gas = None;
+    if profile_gas {
+        let estimates = fact_gas(&facts, &calls).await?;
+        for (outcome, estimate) in checks.iter_mut().zip(&estimates) {
+            outcome.gas = *estimate;
+        }
+        gas = Some(estimates.iter().flatten().sum());
+    }
+    let passed = envelope_valid && checks.iter().all(|c| c.passed);
+    Ok(Simulation {
+        block,
+        timestamp,
+        envelope_version: envelope.version,
+        envelope_deadline: envelope.deadline,
+        envelope_valid,
+        checks,
+        passed,
+        gas,
+    })
+}
+
+/// Estimated gas of each check's fact reads, given the calls each made in program order (see
+/// [`RpcFactsProvider::record_calls`]).
+///
+/// A call costs the callee's execution plus [`COLD_CALL_GAS`] for the first call to its target
+/// and [`WARM_CALL_GAS`] after that. `None` for a check with a call whose estimate failed. The
+/// policy evaluates cheap checks first, so which check warms a shared target may differ on-chain;
+/// the total does not.
+pub async fn fact_gas<M: Middleware + 'static>(
+    facts: &RpcFactsProvider<M>,
+    calls: &[Vec<FactCall>],
+) -> Result<Vec<Option<u64>>> {
+    let distinct: BTreeSet<FactCall> = calls.iter().flatten().cloned().collect();
+    let execution = facts.call_gas(distinct).await?;
+    let mut warm = BTreeSet::new();
+    Ok(calls
+        .iter()
+        .map(|calls| {
+            let mut total = Some(0u64);
+            for call in calls {
+                let access = if warm.insert(call.0) {
+                    COLD_CALL_GAS
+                } else {
+                    WARM_CALL_GAS
+                };
+                let execution = execution.get(call).copied().flatten();
+                total = total
+                    .zip(execution)
+                    .map(|(total, gas)| total + access + gas);
+            }
+            total
+        })
+        .collect())
+}
+
+/// The `(oracle, selector)` calls `checks` read prices from.
+fn oracle_calls(checks: &[Check]) -> Vec<(ethers::types::Address, [u8; 4])> {
+    checks
+        .iter()
+        .filter_map(|check| match check {
+            Check::OracleDeviationLte {
+                oracle, selector, ..
+            } => Some((ethers::types::Address::from(oracle.0 .0), *selector)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `Ok((observed, None))` on pass, `Ok((observed, Some(error)))` on a failed comparison,
+/// `Err((error, facts_error))` when a fact could not be read.
+type Evaluation = Result<(Option<String>, Option<&'static str>), (&'static str, String)>;
+
+fn evaluate_check<F: FactsProvider>(check: &Check, facts: &F) -> Evaluation {
+    let fail_if = |failed: bool, error: &'static str| failed.then_some(error);
+    match check {
+        Check::Deadline { deadline } => {
+            let now = facts.block_timestamp();
+            Ok((
+                Some(format!("now={now}")),
+                fail_if(now > *deadline, "DeadlineExpired"),
+            ))
+        }
+        // Enforced by the policy itself, outside the program.
+        Check::Nonce { .. } | Check::CallBundleHash { .. } | Check::PoolAllowed { .. } => {
+            Ok((None, None))
+        }
+        // Recorded during validation and enforced by the paired hook after execution. Validation
+        // also fails while the wallet has no hook installed or the UserOp does not go through
+        // `executeUserOp`; assume both hold.
+        Check::WalletTokenDeltaLte { .. } | Check::WindowSpendLte { .. } => Ok((None, None)),
+        Check::UserOpField { field, op, rhs } => {
+            let value = match facts.user_op_field(*field) {
+                Ok(value) => value,
+                // Compared against the UserOperation, which a dry run of the envelope alone does
+                // not have.
+                Err(FactsError::NotImplemented) => return Ok((None, None)),
+                Err(err) => return Err(("UserOpFieldOutOfBounds", format!("{err:?}"))),
+            };
+            Ok((
+                Some(format!("{}={value}", field.name())),
+                fail_if(!compare(value, *op, *rhs), "UserOpFieldOutOfBounds"),
+            ))
+        }
+        // The policy fails closed until it parses the call bundle.
+        Check::TokenAmountLte { .. }
+        | Check::NativeValueLte { .. }
+        | Check::LiquidityDeltaLte { .. } => Ok((None, Some("UnsupportedCheck"))),
+        Check::Slot0TickBounds { pool_id, min, max } => {
+            let slot0 = facts
+                .get_slot0(*pool_id)
+                .map_err(|err| ("TickOutOfBounds", format!("{err:?}")))?;
+            let failed = slot0.tick < *min || slot0.tick > *max;
+            Ok((
+                Some(format!("tick={}", slot0.tick)),
+                fail_if(failed, "TickOutOfBounds"),
+            ))
+        }
+        Check::TwapTickBounds {
+            pool_id,
+            window,
+            min,
+            max,
+        } => {
+            if *window == 0 {
+                return Ok((None, Some("UnsupportedCheck")));
+            }
+            let tick = facts
+                .twap_tick(*pool_id, *window)
+                .map_err(|err| ("TickOutOfBounds", format!("{err:?}")))?;
+            Ok((
+                Some(format!("twapTick={tick}")),
+                fail_if(tick < *min || tick > *max, "TickOutOfBounds"),
+            ))
+        }
+        Check::OracleDeviationLte {
+            pool_id,
+            oracle,
+            selector,
+            decimals,
+            max_bps,
+        } => {
+            let slot0 = facts
+                .get_slot0(*pool_id)
+                .map_err(|err| ("OracleDeviationExceeded", format!("{err:?}")))?;
+            let reference = facts
+                .staticcall_u256(*oracle, *selector, &[])
+                .map_err(|err| ("OracleDeviationExceeded", format!("{err:?}")))?;
+            let spot = sqrt_price_x96_to_wad(slot0.sqrt_price_x96);
+            let deviation = spot
+                .zip(to_wad(reference, *decimals))
+                .and_then(|(spot, reference)| deviation_bps(spot, reference));
+            let Some(deviation) = deviation else {
+                return Err((
+                    "OracleDeviationExceeded",
+                    format!("spot={spot:?} oracle={reference} are not comparable"),
+                ));
+            };
+            Ok((
+                Some(format!("deviationBps={deviation}")),
+                fail_if(deviation > U256::from(*max_bps), "OracleDeviationExceeded"),
+            ))
+        }
+        Check::Slot0SqrtPriceBounds { pool_id, min, max } => {
+            let slot0 = facts
+                .get_slot0(*pool_id)
+                .map_err(|err| ("PriceOutOfBounds", format!("{err:?}")))?;
+            let failed = slot0.sqrt_price_x96 < *min || slot0.sqrt_price_x96 > *max;
+            Ok((
+                Some(format!("sqrtPriceX96={}", slot0.sqrt_price_x96)),
+                fail_if(failed, "PriceOutOfBounds"),
+            ))
+        }
+        Check::RfsClosed { position_id } => {
+            let closed = facts
+                .is_rfs_closed(*position_id)
+                .map_err(|err| ("RfsNotClosed", format!("{err:?}")))?;
+            Ok((
+                Some(format!("closed={closed}")),
+                fail_if(!closed, "RfsNotClosed"),
+            ))
+        }
+        Check::QueueLte { lcc, owner, max } => {
+            let queued = facts
+                .queue_amount(*lcc, *owner)
+                .map_err(|err| ("QueueExceeded", format!("{err:?}")))?;
+            Ok((
+                Some(format!("queued={queued}")),
+                fail_if(queued > *max, "QueueExceeded"),
+            ))
+        }
+        Check::QueueSumLte { lcc, owners, max } => {
+            let mut total = U256::ZERO;
+            for owner in owners {
+                let queued = facts
+                    .queue_amount(*lcc, *owner)
+                    .map_err(|err| ("QueueExceeded", format!("{err:?}")))?;
+                total = total.saturating_add(queued);
+            }
+            Ok((
+                Some(format!("queued={total}")),
+                fail_if(total > *max, "QueueExceeded"),
+            ))
+        }
+        Check::ReserveGte { lcc, min } => {
+            let reserve = facts
+                .reserve_of(*lcc)
+                .map_err(|err| ("ReserveTooLow", format!("{err:?}")))?;
+            Ok((
+                Some(format!("reserve={reserve}")),
+                fail_if(reserve < *min, "ReserveTooLow"),
+            ))
+        }
+        Check::HubSolvency { lcc, min_ratio_bps } => {
+            let reserve = facts
+                .reserve_of(*lcc)
+                .map_err(|err| ("HubInsolvent", format!("{err:?}")))?;
+            let queue = facts
+                .queue_of_underlying(*lcc)
+                .map_err(|err| ("HubInsolvent", format!("{err:?}")))?;
+            let Some(ratio) = solvency_ratio_bps(reserve, queue) else {
+                return Ok((Some(format!("reserve={reserve} queue=0")), None));
+            };
+            Ok((
+                Some(format!("reserve={reserve} queue={queue} ratioBps={ratio}")),
+                fail_if(ratio < U256::from(*min_ratio_bps), "HubInsolvent"),
+            ))
+        }
+        Check::SettledGte {
+            position_id,
+            min_amount0,
+            min_amount1,
+        } => {
+            let (amount0, amount1) = facts
+                .get_settled_amounts(*position_id)
+                .map_err(|err| ("StaticCallFailed", format!("{err:?}")))?;
+            let failed = amount0 < *min_amount0 || amount1 < *min_amount1;
+            Ok((
+                Some(format!("settled0={amount0} settled1={amount1}")),
+                fail_if(failed, "StaticCallFailed"),
+            ))
+        }
+        Check::CommitmentDeficitLte {
+            position_id,
+            max_deficit0,
+            max_deficit1,
+        } => {
+            let (commitment0, commitment1) = facts
+                .get_commitment_maxima(*position_id)
+                .map_err(|err| ("StaticCallFailed", format!("{err:?}")))?;
+            let (settled0, settled1) = facts
+                .get_settled_amounts(*position_id)
+                .map_err(|err| ("StaticCallFailed", format!("{err:?}")))?;
+            let deficit0 = commitment0.saturating_sub(settled0);
+            let deficit1 = commitment1.saturating_sub(settled1);
+            let failed = deficit0 > *max_deficit0 || deficit1 > *max_deficit1;
+            Ok((
+                Some(format!("deficit0={deficit0} deficit1={deficit1}")),
+                fail_if(failed, "StaticCallFailed"),
+            ))
+        }
+        Check::GracePeriodGte {
+            position_id,
+            min_seconds,
+        } => {
+            let remaining = facts
+                .grace_period_remaining(*position_id)
+                .map_err(|err| ("StaticCallFailed", format!("{err:?}")))?;
+            // u64::MAX: RFS closed, no grace period running.
+            let failed = remaining != u64::MAX && remaining < *min_seconds;
+            let observed = if remaining == u64::MAX {
+                "remaining=unbounded (RFS closed)".to_string()
+            } else {
+                format!("remaining={remaining}s")
+            };
+            Ok((Some(observed), fail_if(failed, "StaticCallFailed")))
+        }
+        Check::StaticCallU256 {
+            target,
+            selector,
+            args,
+            op,
+            rhs,
+        } => {
+            let lhs = facts
+                .staticcall_u256(*target, *selector, args)
+                .map_err(|err| ("StaticCallFailed", format!("{err:?}")))?;
+            Ok((
+                Some(format!("value={lhs}")),
+                fail_if(!compare(lhs, *op, *rhs), "StaticCallFailed"),
+            ))
+        }
+        Check::StaticCallCompare {
+            lhs_target,
+            lhs_selector,
+            lhs_args,
+            op,
+            rhs_target,
+            rhs_selector,
+            rhs_args,
+            scale_bps: bps,
+        } => {
+            let lhs = facts
+                .staticcall_u256(*lhs_target, *lhs_selector, lhs_args)
+                .map_err(|err| ("StaticCallFailed", format!("{err:?}")))?;
+            let rhs = facts
+                .staticcall_u256(*rhs_target, *rhs_selector, rhs_args)
+                .map_err(|err| ("StaticCallFailed", format!("{err:?}")))?;
+            let Some(scaled) = scale_bps(rhs, *bps) else {
+                return Err((
+                    "StaticCallFailed",
+                    format!("rhs={rhs} overflows when scaled"),
+                ));
+            };
+            Ok((
+                Some(format!("lhs={lhs} rhs={rhs} scaledRhs={scaled}")),
+                fail_if(!compare(lhs, *op, scaled), "StaticCallFailed"),
+            ))
+        }
+    }
+}
+
+fn compare(lhs: U256, op: CompOp, rhs: U256) -> bool {
+    match op {
+        CompOp::Lt => lhs < rhs,
+        CompOp::Lte => lhs <= rhs,
+        CompOp::Gt => lhs > rhs,
+        CompOp::Gte => lhs >= rhs,
+        CompOp::Eq => lhs == rhs,
+        CompOp::Neq => lhs != rhs,
+    }
+}
@@ -0,0 +1,1479 @@
+use std::sync::Arc;
+
+use ethers::utils::hex;
+
+use ethers::{
+    providers::Provider,
+    signers::{LocalWallet, Signer},
+    types::{transaction::eip712::Eip712, Address, Signature, H256, U256},
+    utils::keccak256,
+};
+use fiet_maker_policy_encoder::{
+    encoder::{
+        encode_envelope, encode_program, policy_domain_separator, policy_intent_digest,
+        PolicyDomain,
+    },
+    facts::{FactsError, FactsProvider, Slot0},
+    types::IntentEnvelope,
+};
+
+use crate::{
+    bundler::{GasEstimate, RpcUserOperation, UserOpReceipt},
+    convert,
+    envelope::{
+        signed_envelope, signed_envelope_with, signed_typed_envelope_with, EnvelopeFormat,
+        EnvelopeParams, EnvelopeTypedData, ENVELOPE_VERSION_TYPED_CHECKS,
+    },
+    facts::{FactSources, RetryPolicy, RpcEndpoints, RpcFactsProvider, SourceOverride},
+    inspect::{disassemble, inspect_user_op},
+    kernel::{self, VALIDATION_MODE_ENABLE, VALIDATION_TYPE_PERMISSION},
+    simulate::{
+        decode_intent, evaluate_routed, evaluate_verbose, fact_gas, COLD_CALL_GAS, WARM_CALL_GAS,
+    },
+    user_op::{decode_handle_ops, handle_ops_calldata, pack_u128s, unpack_u128s},
+    Check, CompOp, EnableData, Execution, GasLimits, Intent, IntentClient, IntentConfig,
+    NonceReservations, PackedUserOperation, Program, UserOpBuilder,
+};
+
+const OWNER_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+const ENVELOPE_KEY: &str = "0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a";
+
+fn permission_id() -> H256 {
+    let mut id = [0u8; 32];
+    id[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+    H256(id)
+}
+
+#[test]
+fn permission_nonce_key_layout() {
+    let key = kernel::permission_nonce_key(permission_id(), 0x0102).unwrap();
+    let mut be = [0u8; 32];
+    key.to_big_endian(&mut be);
+    // uint192: the top 8 bytes of the word are zero.
+    assert_eq!(&be[..8], &[0u8; 8]);
+    assert_eq!(be[8], 0x00, "default mode");
+    assert_eq!(be[9], VALIDATION_TYPE_PERMISSION);
+    assert_eq!(&be[10..14], &[0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(&be[14..30], &[0u8; 16]);
+    assert_eq!(&be[30..], &[0x01, 0x02]);
+
+    let enable = kernel::nonce_key(
+        VALIDATION_MODE_ENABLE,
+        VALIDATION_TYPE_PERMISSION,
+        [0u8; 20],
+        0,
+    );
+    assert_eq!(enable, U256::from(0x0102u64) << 176);
+}
+
+#[test]
+fn rejects_right_aligned_permission_id() {
+    let id = H256::from_low_u64_be(0xdeadbeef);
+    assert!(kernel::permission_id4(id).is_err());
+    assert!(kernel::permission_nonce_key(id, 0).is_err());
+}
+
+#[test]
+fn packs_policy_signatures_in_index_order() {
+    let packed = kernel::pack_permission_signature(
+        &[(2, vec![0xbb]), (0, Vec::new()), (1, vec![0xaa, 0xaa])],
+        &[0xcc; 3],
+    )
+    .unwrap();
+    let mut expected = vec![1];
+    expected.extend_from_slice(&2u64.to_be_bytes());
+    expected.extend_from_slice(&[0xaa, 0xaa]);
+    expected.push(2);
+    expected.extend_from_slice(&1u64.to_be_bytes());
+    expected.push(0xbb);
+    expected.push(0xff);
+    expected.extend_from_slice(&[0xcc; 3]);
+    assert_eq!(packed.to_vec(), expected);
+
+    assert!(kernel::pack_permission_signature(&[(1, vec![1]), (1, vec![2])], &[]).is_err());
+    assert!(kernel::pack_permission_signature(&[(0xff, vec![1])], &[]).is_err());
+}
+
+#[test]
+fn execute_calldata_packs_single_call() {
+    let to = Address::repeat_byte(0x11);
+    let data = kernel::execute_single_calldata(to, U256::from(7u64), &[0xab, 0xcd]);
+    assert_eq!(&data[..4], &ethers::utils::id("execute(bytes32,bytes)"));
+    // execMode (32) + offset (32) + length (32), then `to || value || data`.
+    let len = U256::from_big_endian(&data[4 + 64..4 + 96]).as_usize();
+    assert_eq!(len, 20 + 32 + 2);
+    let single = &data[4 + 96..4 + 96 + len];
+    assert_eq!(&single[..20], to.as_bytes());
+    assert_eq!(U256::from_big_endian(&single[20..52]), U256::from(7u64));
+    assert_eq!(&single[52..], &[0xab, 0xcd]);
+}
+
+#[test]
+fn program_builder_matches_encoder() {
+    let pool = alloy_primitives::FixedBytes::repeat_byte(0x22);
+    let program = Program::new()
+        .deadline(1_700_000_000)
+        .tick_bounds(pool, -60, 60);
+    let checks = vec![
+        Check::Deadline {
+            deadline: 1_700_000_000,
+        },
+        Check::Slot0TickBounds {
+            pool_id: pool,
+            min: -60,
+            max: 60,
+        },
+    ];
+    assert_eq!(program.checks(), checks.as_slice());
+    assert_eq!(program.encode(), encode_program(&checks));
+    assert_eq!(Program::from(checks), program);
+
+    // Built in another order, with a repeat, it canonicalizes to the same program.
+    let reordered = Program::new()
+        .tick_bounds(pool, -60, 60)
+        .deadline(1_700_000_000)
+        .deadline(1_700_000_000);
+    assert_eq!(reordered.canonical(), program.canonical());
+    assert_eq!(program.canonical(), program);
+}
+
+#[test]
+fn program_json_uses_hex_strings() {
+    let program = Program::new()
+        .tick_bounds(alloy_primitives::FixedBytes::repeat_byte(0x22), -60, 60)
+        .check(Check::LiquidityDeltaLte { max: u128::MAX })
+        .static_call_u256(
+            alloy_primitives::Address::repeat_byte(0x07),
+            [0x70, 0xa0, 0x82, 0x31],
+            vec![0xab],
+            CompOp::Gte,
+            alloy_primitives::U256::from(255u64),
+        );
+    let json = serde_json::to_value(&program).unwrap();
+    assert_eq!(json[0]["kind"], "slot0TickBounds");
+    assert_eq!(json[0]["poolId"], format!("0x{}", "22".repeat(32)));
+    assert_eq!(json[0]["min"], -60);
+    assert_eq!(json[1]["max"], format!("0x{:x}", u128::MAX));
+    assert_eq!(json[2]["target"], format!("0x{}", "07".repeat(20)));
+    assert_eq!(json[2]["selector"], "0x70a08231");
+    assert_eq!(json[2]["args"], "0xab");
+    assert_eq!(json[2]["op"], "gte");
+    assert_eq!(json[2]["rhs"], "0xff");
+    assert_eq!(serde_json::from_value::<Program>(json).unwrap(), program);
+
+    let decimal: Check = serde_json::from_str(r#"{"kind":"nativeValueLte","max":"1000"}"#).unwrap();
+    assert_eq!(
+        decimal,
+        Check::NativeValueLte {
+            max: alloy_primitives::U256::from(1000u64)
+        }
+    );
+}
+
+#[test]
+fn user_op_hash_excludes_signature() {
+    let mut op = PackedUserOperation {
+        sender: Address::repeat_byte(0x01),
+        nonce: U256::from(5u64),
+        account_gas_limits: pack_u128s(2, 1),
+        ..Default::default()
+    };
+    let entry_point = Address::repeat_byte(0xee);
+    let hash = op.hash(entry_point, 412346);
+    op.signature = vec![1, 2, 3].into();
+    assert_eq!(op.hash(entry_point, 412346), hash);
+    assert_ne!(op.hash(entry_point, 1), hash);
+    op.nonce = U256::from(6u64);
+    assert_ne!(op.hash(entry_point, 412346), hash);
+}
+
+#[tokio::test]
+async fn signs_envelope_and_user_op() {
+    let (provider, mock) = Provider::mocked();
+    mock.push(U256::from(412346u64)).unwrap();
+    let owner: LocalWallet = OWNER_KEY.parse().unwrap();
+    let envelope_signer: LocalWallet = ENVELOPE_KEY.parse().unwrap();
+    let config = IntentConfig::new(
+        Address::repeat_byte(0xee),
+        Address::repeat_byte(0xaa),
+        Address::repeat_byte(0x0a),
+        permission_id(),
+    );
+    let client = IntentClient::new(
+        Arc::new(provider),
+        config.clone(),
+        owner.clone(),
+        envelope_signer.clone(),
+    )
+    .await
+    .unwrap();
+
+    let intent = Intent {
+        target: Address::repeat_byte(0x33),
+        value: U256::zero(),
+        data: vec![0x12, 0x34].into(),
+        program: Program::new().deadline(2_000_000_000),
+        deadline: 2_000_000_000,
+    };
+    let signed = client
+        .sign_with_nonces(&intent, U256::from(3u64), U256::from(9u64))
+        .await
+        .unwrap();
+    assert_eq!(signed.user_op.nonce, U256::from(9u64));
+    assert_eq!(
+        signed.user_op_hash,
+        signed.user_op.hash(config.entry_point, 412346)
+    );
+
+    // Policy index 1 carries the envelope; the signer signature follows the 0xff marker.
+    let sig = signed.user_op.signature.to_vec();
+    assert_eq!(sig[0], 1);
+    let env_len = u64::from_be_bytes(sig[1..9].try_into().unwrap()) as usize;
+    assert_eq!(sig[9 + env_len], 0xff);
+    let signer_sig = Signature::try_from(&sig[10 + env_len..]).unwrap();
+    signer_sig
+        .verify(signed.user_op_hash.as_bytes(), owner.address())
+        .unwrap();
+
+    // The envelope signature recovers to the envelope signer over the policy digest.
+    let envelope_sig = Signature::try_from(&sig[9 + env_len - 65..9 + env_len]).unwrap();
+    let digest = policy_intent_digest(&IntentEnvelope {
+        version: 1,
+        nonce: convert::u256(U256::from(3u64)),
+        deadline: intent.deadline,
+        call_bundle_hash: convert::bytes32(H256(keccak256(&signed.user_op.call_data))),
+        program_bytes: intent.program.encode(),
+        signature: Vec::new(),
+        domain_chain_id: 412346,
+        domain_verifying_contract: convert::address(config.policy),
+        wallet: convert::address(config.account),
+        permission_id: convert::bytes32(config.permission_id),
+    });
+    assert_eq!(
+        envelope_sig.recover(H256(digest.0)).unwrap(),
+        envelope_signer.address()
+    );
+}
+
+#[test]
+fn rpc_user_op_unpacks_v07_fields() {
+    let mut paymaster_and_data = Address::repeat_byte(0x99).as_bytes().to_vec();
+    paymaster_and_data.extend_from_slice(&50_000u128.to_be_bytes());
+    paymaster_and_data.extend_from_slice(&20_000u128.to_be_bytes());
+    paymaster_and_data.extend_from_slice(&[0xde, 0xad]);
+    let op = PackedUserOperation {
+        sender: Address::repeat_byte(0x01),
+        nonce: U256::from(7u64),
+        call_data: vec![0xaa].into(),
+        account_gas_limits: pack_u128s(300_000, 100_000),
+        pre_verification_gas: U256::from(21_000u64),
+        gas_fees: pack_u128s(1, 2),
+        paymaster_and_data: paymaster_and_data.into(),
+        signature: vec![0xff].into(),
+        ..Default::default()
+    };
+    let rpc = RpcUserOperation::try_from(&op).unwrap();
+    assert_eq!(rpc.verification_gas_limit, U256::from(300_000u64));
+    assert_eq!(rpc.call_gas_limit, U256::from(100_000u64));
+    assert_eq!(rpc.max_priority_fee_per_gas, U256::from(1u64));
+    assert_eq!(rpc.max_fee_per_gas, U256::from(2u64));
+    assert_eq!(rpc.paymaster, Some(Address::repeat_byte(0x99)));
+    assert_eq!(
+        rpc.paymaster_verification_gas_limit,
+        Some(U256::from(50_000u64))
+    );
+    assert_eq!(rpc.paymaster_post_op_gas_limit, Some(U256::from(20_000u64)));
+
+    let json = serde_json::to_value(&rpc).unwrap();
+    assert_eq!(json["callGasLimit"], "0x186a0");
+    assert_eq!(json["paymasterData"], "0xdead");
+    // No initCode: the factory fields are omitted rather than sent as null.
+    assert!(json.get("factory").is_none());
+    assert!(json.get("factoryData").is_none());
+}
+
+#[test]
+fn gas_estimate_overwrites_limits_only() {
+    let estimate: GasEstimate = serde_json::from_value(serde_json::json!({
+        "preVerificationGas": "0xc350",
+        "verificationGasLimit": "0x1e8480",
+        "callGasLimit": "0x30d40",
+    }))
+    .unwrap();
+    let mut op = PackedUserOperation {
+        gas_fees: pack_u128s(3, 4),
+        ..Default::default()
+    };
+    estimate.apply(&mut op).unwrap();
+    assert_eq!(unpack_u128s(&op.account_gas_limits), (2_000_000, 200_000));
+    assert_eq!(op.pre_verification_gas, U256::from(50_000u64));
+    assert_eq!(unpack_u128s(&op.gas_fees), (3, 4));
+}
+
+#[test]
+fn parses_user_op_receipt() {
+    let receipt: Option<UserOpReceipt> = serde_json::from_value(serde_json::json!({
+        "userOpHash": format!("{:?}", H256::repeat_byte(0x01)),
+        "entryPoint": format!("{:?}", Address::repeat_byte(0xee)),
+        "sender": format!("{:?}", Address::repeat_byte(0x0a)),
+        "nonce": "0x1",
+        "success": false,
+        "reason": "0x08c379a0",
+        "actualGasCost": "0x10",
+        "actualGasUsed": "0x20",
+        "logs": [],
+        "receipt": {
+            "transactionHash": format!("{:?}", H256::repeat_byte(0x02)),
+            "blockNumber": "0x5",
+            "logs": [],
+        },
+    }))
+    .unwrap();
+    let receipt = receipt.unwrap();
+    assert!(!receipt.success);
+    assert_eq!(receipt.actual_gas_used, U256::from(0x20u64));
+    assert_eq!(receipt.receipt.transaction_hash, H256::repeat_byte(0x02));
+
+    let pending: Option<UserOpReceipt> = serde_json::from_value(serde_json::Value::Null).unwrap();
+    assert!(pending.is_none());
+}
+
+#[test]
+fn derived_permission_id_matches_abi_encoding() {
+    use ethers::abi::{self, Token};
+
+    let module = |byte: u8, init_data: Vec<u8>| kernel::PermissionModule {
+        flag: 0,
+        module: alloy_primitives::Address::repeat_byte(byte),
+        init_data,
+    };
+    let policies = [module(0x01, vec![0xaa; 40]), module(0x02, vec![0xbb; 81])];
+    let signer = module(0x03, vec![0x04; 20]);
+
+    let entries = policies
+        .iter()
+        .chain(std::iter::once(&signer))
+        .map(|m| {
+            let mut entry = m.flag.to_be_bytes().to_vec();
+            entry.extend_from_slice(m.module.as_slice());
+            entry.extend_from_slice(&m.init_data);
+            Token::Bytes(entry)
+        })
+        .collect();
+    let encoded = abi::encode(&[Token::Array(entries)]);
+    let mut expected = [0u8; 32];
+    expected[..4].copy_from_slice(&keccak256(encoded)[..4]);
+
+    let derived = kernel::derive_permission_id(&policies, &signer);
+    assert_eq!(derived, H256(expected));
+    kernel::ensure_permission_id(derived, &policies, &signer).unwrap();
+    assert!(kernel::ensure_permission_id(permission_id(), &policies, &signer).is_err());
+}
+
+#[test]
+fn builder_batches_multiple_executions() {
+    let calls = [
+        Execution::new(Address::repeat_byte(0x11), U256::from(1u64), vec![0xaa]),
+        Execution::new(Address::repeat_byte(0x22), U256::zero(), vec![0xbb, 0xcc]),
+    ];
+    let single = UserOpBuilder::new(Address::repeat_byte(0x0a)).execution(calls[0].clone());
+    assert_eq!(
+        single.call_data().unwrap(),
+        kernel::execute_single_calldata(calls[0].target, calls[0].value, &calls[0].data)
+    );
+
+    let batch = UserOpBuilder::new(Address::repeat_byte(0x0a)).executions(calls.clone());
+    let data = batch.call_data().unwrap();
+    assert_eq!(&data[..4], &ethers::utils::id("execute(bytes32,bytes)"));
+    assert_eq!(data[4], 0x01, "CALLTYPE_BATCH");
+    let decoded = ethers::abi::decode(
+        &[
+            ethers::abi::ParamType::FixedBytes(32),
+            ethers::abi::ParamType::Bytes,
+        ],
+        &data[4..],
+    )
+    .unwrap();
+    let inner = decoded[1].clone().into_bytes().unwrap();
+    let execs = ethers::abi::decode(
+        &[ethers::abi::ParamType::Array(Box::new(
+            ethers::abi::ParamType::Tuple(vec![
+                ethers::abi::ParamType::Address,
+                ethers::abi::ParamType::Uint(256),
+                ethers::abi::ParamType::Bytes,
+            ]),
+        ))],
+        &inner,
+    )
+    .unwrap();
+    let execs = execs[0].clone().into_array().unwrap();
+    assert_eq!(execs.len(), 2);
+    assert_eq!(
+        execs[1],
+        ethers::abi::Token::Tuple(vec![
+            ethers::abi::Token::Address(calls[1].target),
+            ethers::abi::Token::Uint(U256::zero()),
+            ethers::abi::Token::Bytes(vec![0xbb, 0xcc]),
+        ])
+    );
+
+    assert!(UserOpBuilder::new(Address::zero()).call_data().is_err());
+}
+
+#[tokio::test]
+async fn builder_places_envelope_in_permission_signature() {
+    let owner: LocalWallet = OWNER_KEY.parse().unwrap();
+    let envelope = vec![0x5a; 200];
+    let builder = UserOpBuilder::new(Address::repeat_byte(0x0a))
+        .nonce(U256::from(3u64))
+        .execution(Execution::new(
+            Address::repeat_byte(0x11),
+            U256::zero(),
+            vec![0x01],
+        ))
+        .gas(GasLimits::default());
+    assert!(builder.build(&[0u8; 65]).is_err(), "no envelope yet");
+
+    let builder = builder.policy_signature(1, envelope.clone());
+    let entry_point = Address::repeat_byte(0xee);
+    let (op, hash) = builder.sign(entry_point, 412346, &owner).await.unwrap();
+    assert_eq!(hash, op.hash(entry_point, 412346));
+    assert_eq!(
+        hash,
+        builder.build_unsigned().unwrap().hash(entry_point, 412346)
+    );
+
+    let sig = op.signature.to_vec();
+    assert_eq!(sig[0], 1);
+    assert_eq!(&sig[1..9], &200u64.to_be_bytes());
+    assert_eq!(&sig[9..209], envelope.as_slice());
+    assert_eq!(sig[209], 0xff);
+    Signature::try_from(&sig[210..])
+        .unwrap()
+        .verify(hash.as_bytes(), owner.address())
+        .unwrap();
+}
+
+#[tokio::test]
+async fn inspects_the_envelope_of_a_handle_ops_call() {
+    let (provider, mock) = Provider::mocked();
+    mock.push(U256::from(412346u64)).unwrap();
+    let owner: LocalWallet = OWNER_KEY.parse().unwrap();
+    let envelope_signer: LocalWallet = ENVELOPE_KEY.parse().unwrap();
+    let config = IntentConfig::new(
+        Address::repeat_byte(0xee),
+        Address::repeat_byte(0xaa),
+        Address::repeat_byte(0x0a),
+        permission_id(),
+    );
+    let client = IntentClient::new(
+        Arc::new(provider),
+        config.clone(),
+        owner.clone(),
+        envelope_signer.clone(),
+    )
+    .await
+    .unwrap();
+    let program = Program::new().deadline(2_000_000_000).reserve_gte(
+        alloy_primitives::Address::repeat_byte(0x44),
+        alloy_primitives::U256::from(7u64),
+    );
+    let intent = Intent {
+        target: Address::repeat_byte(0x33),
+        value: U256::zero(),
+        data: vec![0x12, 0x34].into(),
+        program: program.clone(),
+        deadline: 2_000_000_000,
+    };
+    let op_nonce =
+        (kernel::permission_nonce_key(permission_id(), 0).unwrap() << 64) | U256::from(9u64);
+    let signed = client
+        .sign_with_nonces(&intent, U256::from(3u64), op_nonce)
+        .await
+        .unwrap();
+
+    let calldata = handle_ops_calldata(vec![signed.user_op.clone()], Address::repeat_byte(0xbe));
+    let (ops, beneficiary) = decode_handle_ops(&calldata).unwrap();
+    assert_eq!(beneficiary, Address::repeat_byte(0xbe));
+    assert_eq!(ops, vec![signed.user_op.clone()]);
+    assert!(decode_handle_ops(&calldata[4..]).is_err());
+
+    let op = ops.into_iter().next().unwrap();
+    let inspected = inspect_user_op(
+        op,
+        config.entry_point,
+        412346,
+        Some(config.policy),
+        &PolicyDomain::default(),
+    )
+    .unwrap();
+    assert_eq!(inspected.user_op_hash, signed.user_op_hash);
+    assert_eq!(inspected.validation_type, VALIDATION_TYPE_PERMISSION);
+    assert_eq!(inspected.permission_id, Some(permission_id()));
+    assert_eq!(inspected.policy_signatures.len(), 1);
+    Signature::try_from(inspected.signer_signature.as_ref())
+        .unwrap()
+        .verify(signed.user_op_hash.as_bytes(), owner.address())
+        .unwrap();
+
+    let intent = inspected.intent.unwrap();
+    assert_eq!(intent.policy_index, 1);
+    assert_eq!(intent.envelope.nonce, alloy_primitives::U256::from(3u64));
+    assert_eq!(intent.checks, program.checks());
+    assert_eq!(intent.signer, Some(envelope_signer.address()));
+    let lines = disassemble(&intent.checks);
+    assert_eq!(lines.len(), 2);
+    assert!(
+        lines[1].starts_with("  1  CheckReserveGte {"),
+        "{}",
+        lines[1]
+    );
+
+    // Truncated and marker-less permission signatures are rejected.
+    assert!(kernel::split_permission_signature(&[1, 0, 0]).is_err());
+    assert!(kernel::split_permission_signature(&[1, 0, 0, 0, 0, 0, 0, 0, 9, 0xff]).is_err());
+    assert!(kernel::split_permission_signature(&[]).is_err());
+}
+
+fn enable_data() -> EnableData {
+    let module = |byte: u8, init_data: Vec<u8>| kernel::PermissionModule {
+        flag: 0,
+        module: alloy_primitives::Address::repeat_byte(byte),
+        init_data,
+    };
+    EnableData::new(
+        permission_id(),
+        vec![module(0x01, vec![0xaa; 40]), module(0x02, vec![0xbb; 81])],
+        module(0x03, vec![0x04; 20]),
+        1,
+    )
+}
+
+#[test]
+fn enable_digest_matches_typed_data() {
+    use ethers::types::transaction::eip712::{Eip712, TypedData};
+
+    let enable = enable_data();
+    let account = Address::repeat_byte(0x0a);
+    let typed: TypedData = serde_json::from_value(serde_json::json!({
+        "domain": {
+            "name": "Kernel",
+            "version": "0.3.3",
+            "chainId": 412346,
+            "verifyingContract": format!("{account:?}"),
+        },
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" },
+            ],
+            "Enable": [
+                { "name": "validationId", "type": "bytes21" },
+                { "name": "nonce", "type": "uint32" },
+                { "name": "hook", "type": "address" },
+                { "name": "validatorData", "type": "bytes" },
+                { "name": "hookData", "type": "bytes" },
+                { "name": "selectorData", "type": "bytes" },
+            ],
+        },
+        "primaryType": "Enable",
+        "message": {
+            // ethers right-aligns short `bytesN` values; pass the left-aligned word EIP-712
+            // (and viem) actually hash.
+            "validationId": format!("0x{}{}", hex::encode(enable.validation_id().unwrap()), "00".repeat(11)),
+            "nonce": 1,
+            "hook": format!("{:?}", Address::zero()),
+            "validatorData": format!("0x{}", hex::encode(enable.validator_data())),
+            "hookData": "0xff",
+            "selectorData": "0xe9ae5c53",
+        },
+    }))
+    .unwrap();
+    assert_eq!(
+        enable.digest(account, 412346).unwrap(),
+        H256(typed.encode_eip712().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn enable_signature_wraps_permission_signature() {
+    let owner: LocalWallet = OWNER_KEY.parse().unwrap();
+    let root: LocalWallet = ENVELOPE_KEY.parse().unwrap();
+    let enable = enable_data();
+    let account = Address::repeat_byte(0x0a);
+    let entry_point = Address::repeat_byte(0xee);
+    let builder = UserOpBuilder::new(account)
+        .execution(Execution::new(
+            Address::repeat_byte(0x11),
+            U256::zero(),
+            vec![0x01],
+        ))
+        .policy_signature(1, vec![0x5a; 10]);
+
+    // The default-mode key is rejected.
+    let wrong = builder
+        .clone()
+        .nonce(kernel::permission_nonce_key(permission_id(), 0).unwrap() << 64);
+    assert!(wrong
+        .sign_enable(entry_point, 412346, &owner, &root, &enable)
+        .await
+        .is_err());
+
+    let builder = builder.nonce(enable.nonce_key(0).unwrap() << 64);
+    let (op, hash) = builder
+        .sign_enable(entry_point, 412346, &owner, &root, &enable)
+        .await
+        .unwrap();
+    let (plain, plain_hash) = builder.sign(entry_point, 412346, &owner).await.unwrap();
+    assert_eq!(hash, plain_hash);
+
+    let sig = op.signature.to_vec();
+    assert_eq!(&sig[..20], Address::zero().as_bytes());
+    let tail = ethers::abi::decode(&vec![ethers::abi::ParamType::Bytes; 5], &sig[20..]).unwrap();
+    let parts: Vec<Vec<u8>> = tail.into_iter().map(|t| t.into_bytes().unwrap()).collect();
+    assert_eq!(parts[0], enable.validator_data());
+    assert_eq!(parts[1], vec![0xff]);
+    assert_eq!(
+        parts[2],
+        ethers::utils::id("execute(bytes32,bytes)").to_vec()
+    );
+    assert_eq!(parts[4], plain.signature.to_vec());
+    Signature::try_from(parts[3].as_slice())
+        .unwrap()
+        .verify(
+            ethers::types::RecoveryMessage::Hash(enable.digest(account, 412346).unwrap()),
+            root.address(),
+        )
+        .unwrap();
+}
+
+struct StubFacts;
+
+impl FactsProvider for StubFacts {
+    fn block_timestamp(&self) -> u64 {
+        1_000
+    }
+
+    fn get_slot0(&self, _pool_id: alloy_primitives::FixedBytes<32>) -> Result<Slot0, FactsError> {
+        Ok(Slot0 {
+            sqrt_price_x96: alloy_primitives::U256::from(1u64) << 96,
+            tick: 10,
+            protocol_fee: 0,
+            lp_fee: 3_000,
+        })
+    }
+
+    fn reserve_of(
+        &self,
+        _lcc: alloy_primitives::Address,
+    ) -> Result<alloy_primitives::U256, FactsError> {
+        Ok(alloy_primitives::U256::from(5u64))
+    }
+
+    fn queue_of_underlying(
+        &self,
+        _lcc: alloy_primitives::Address,
+    ) -> Result<alloy_primitives::U256, FactsError> {
+        Ok(alloy_primitives::U256::from(4u64))
+    }
+}
+
+#[test]
+fn verbose_evaluation_reports_every_check() {
+    let program = Program::new()
+        .deadline(999)
+        .tick_bounds(alloy_primitives::FixedBytes::ZERO, -5, 5)
+        .reserve_gte(
+            alloy_primitives::Address::repeat_byte(0x01),
+            alloy_primitives::U256::from(3u64),
+        )
+        .hub_solvency(alloy_primitives::Address::repeat_byte(0x01), 13_000)
+        .rfs_closed(alloy_primitives::FixedBytes::ZERO)
+        .check(Check::NativeValueLte {
+            max: alloy_primitives::U256::ZERO,
+        });
+
+    let outcomes = evaluate_verbose(program.checks(), &StubFacts);
+    let summary: Vec<(&str, bool, Option<&str>, Option<&str>)> = outcomes
+        .iter()
+        .map(|o| {
+            (
+                o.opcode.as_str(),
+                o.passed,
+                o.observed.as_deref(),
+                o.error.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                "CheckDeadline",
+                false,
+                Some("now=1000"),
+                Some("DeadlineExpired")
+            ),
+            (
+                "CheckSlot0TickBounds",
+                false,
+                Some("tick=10"),
+                Some("TickOutOfBounds")
+            ),
+            ("CheckReserveGte", true, Some("reserve=5"), None),
+            (
+                "CheckHubSolvency",
+                false,
+                Some("reserve=5 queue=4 ratioBps=12500"),
+                Some("HubInsolvent")
+            ),
+            (
+                "CheckRfsClosed",
+                false,
+                None,
+                Some("RfsNotClosed: NotImplemented")
+            ),
+            ("CheckNativeValueLte", false, None, Some("UnsupportedCheck")),
+        ]
+    );
+}
+
+#[test]
+fn decodes_encoded_envelope() {
+    let program = Program::new()
+        .tick_bounds(alloy_primitives::FixedBytes::ZERO, -5, 5)
+        .static_call_u256(
+            alloy_primitives::Address::repeat_byte(0x07),
+            [0xaa, 0xbb, 0xcc, 0xdd],
+            vec![1, 2, 3],
+            CompOp::Gte,
+            alloy_primitives::U256::from(9u64),
+        );
+    let envelope = IntentEnvelope {
+        version: 1,
+        nonce: convert::u256(U256::from(7u64)),
+        deadline: 1_234,
+        call_bundle_hash: alloy_primitives::FixedBytes::repeat_byte(0x11),
+        program_bytes: program.encode(),
+        signature: vec![0x22; 65],
+        domain_chain_id: 1,
+        domain_verifying_contract: alloy_primitives::Address::ZERO,
+        wallet: alloy_primitives::Address::ZERO,
+        permission_id: alloy_primitives::FixedBytes::ZERO,
+    };
+    let encoded = encode_envelope(&envelope);
+
+    let (decoded, checks) = decode_intent(&encoded).unwrap();
+    assert_eq!(decoded.version, 1);
+    assert_eq!(decoded.deadline, 1_234);
+    assert_eq!(decoded.signature, [0x22; 65]);
+    assert_eq!(checks, program.checks());
+
+    let mut trailing = encoded.clone();
+    trailing.push(0);
+    assert!(decode_intent(&trailing).is_err());
+    assert!(decode_intent(&encoded[..encoded.len() - 1]).is_err());
+}
+
+#[tokio::test]
+async fn typed_data_signing_matches_local_signing() {
+    let signer: LocalWallet = ENVELOPE_KEY.parse().unwrap();
+    let program = Program::new().deadline(1_700_000_000);
+    let params = EnvelopeParams {
+        chain_id: 42161,
+        policy: Address::repeat_byte(0xaa),
+        wallet: Address::repeat_byte(0xbb),
+        permission_id: permission_id(),
+        nonce: U256::from(3u64),
+        deadline: 1_700_000_000,
+        call_bundle_hash: H256::repeat_byte(0x44),
+        program: &program,
+    };
+
+    let typed = EnvelopeTypedData::new(&params);
+    let (envelope, _) = decode_intent(&signed_envelope(&params, &signer).unwrap()).unwrap();
+    let separator = typed.domain().unwrap().separator();
+    let mut unsigned = IntentEnvelope {
+        version: envelope.version,
+        nonce: envelope.nonce,
+        deadline: envelope.deadline,
+        call_bundle_hash: envelope.call_bundle_hash,
+        program_bytes: envelope.program_bytes,
+        signature: Vec::new(),
+        domain_chain_id: 42161,
+        domain_verifying_contract: convert::address(params.policy),
+        wallet: convert::address(params.wallet),
+        permission_id: convert::bytes32(params.permission_id),
+    };
+    assert_eq!(separator, policy_domain_separator(&unsigned).0);
+    assert_eq!(
+        typed.encode_eip712().unwrap(),
+        policy_intent_digest(&unsigned).0
+    );
+    assert_eq!(typed.digest().0, policy_intent_digest(&unsigned).0);
+
+    // RFC 6979 signatures are deterministic, so both paths yield identical envelopes.
+    let generic = signed_envelope_with(&params, &signer).await.unwrap();
+    assert_eq!(generic, signed_envelope(&params, &signer).unwrap());
+
+    let other: LocalWallet = OWNER_KEY.parse().unwrap();
+    let foreign = other.sign_hash(typed.digest()).unwrap();
+    assert!(typed.encode(&foreign, signer.address()).is_err());
+    unsigned.signature = foreign.to_vec();
+    assert_eq!(
+        typed.encode(&foreign, other.address()).unwrap(),
+        encode_envelope(&unsigned)
+    );
+}
+
+#[test]
+fn typed_data_json_hashes_to_the_envelope_digest() {
+    use ethers::types::transaction::eip712::TypedData;
+
+    let program = Program::new().deadline(1_700_000_000);
+    let params = EnvelopeParams {
+        chain_id: 42161,
+        policy: Address::repeat_byte(0xaa),
+        wallet: Address::repeat_byte(0xbb),
+        permission_id: permission_id(),
+        nonce: U256::from(7u64),
+        deadline: 1_700_000_000,
+        call_bundle_hash: H256::repeat_byte(0x44),
+        program: &program,
+    };
+    let domain = PolicyDomain {
+        name: "Acme Policy".to_string(),
+        version: "2".to_string(),
+    };
+
+    // What a remote `eth_signTypedData_v4` signer hashes must be what the policy recovers from.
+    for typed in [
+        EnvelopeTypedData::new(&params),
+        EnvelopeTypedData::new(&params).with_domain(domain),
+    ] {
+        let json: TypedData = serde_json::from_value(typed.to_json()).unwrap();
+        assert_eq!(json.encode_eip712().unwrap(), typed.digest().0);
+    }
+}
+
+#[tokio::test]
+async fn typed_checks_json_shows_each_check() {
+    use ethers::types::transaction::eip712::TypedData;
+
+    let signer: LocalWallet = ENVELOPE_KEY.parse().unwrap();
+    let program = Program::new()
+        .deadline(1_700_000_000)
+        .tick_bounds(
+            alloy_primitives::FixedBytes::repeat_byte(0x0d),
+            -887_272,
+            60,
+        )
+        .reserve_gte(
+            alloy_primitives::Address::repeat_byte(0x0c),
+            alloy_primitives::U256::from(5),
+        );
+    let params = EnvelopeParams {
+        chain_id: 42161,
+        policy: Address::repeat_byte(0xaa),
+        wallet: Address::repeat_byte(0xbb),
+        permission_id: permission_id(),
+        nonce: U256::from(7u64),
+        deadline: 1_700_000_000,
+        call_bundle_hash: H256::repeat_byte(0x44),
+        program: &program,
+    };
+
+    let typed = EnvelopeTypedData::new(&params).typed_checks();
+    assert_ne!(typed.digest(), EnvelopeTypedData::new(&params).digest());
+    let value = typed.to_json();
+    let checks = value["message"]["checks"].as_array().unwrap();
+    assert_eq!(checks.len(), 3);
+    assert_eq!(checks[1]["kind"], "CheckSlot0TickBounds");
+    assert_eq!(checks[1]["tickMin"], -887_272);
+    assert_eq!(checks[2]["min"], "5");
+    assert!(value["message"].get("programHash").is_none());
+    // ethers only parses `int` values as two's-complement hex.
+    let mut value = value;
+    for check in value["message"]["checks"].as_array_mut().unwrap() {
+        for tick in ["tickMin", "tickMax"] {
+            let raw = ethers::types::I256::from(check[tick].as_i64().unwrap()).into_raw();
+            check[tick] = serde_json::to_value(raw).unwrap();
+        }
+    }
+    let json: TypedData = serde_json::from_value(value).unwrap();
+    assert_eq!(json.encode_eip712().unwrap(), typed.digest().0);
+
+    let envelope = signed_typed_envelope_with(&typed, &signer).await.unwrap();
+    let (decoded, _) = decode_intent(&envelope).unwrap();
+    assert_eq!(decoded.version, ENVELOPE_VERSION_TYPED_CHECKS);
+}
+
+#[test]
+fn envelope_formats_render_the_same_bytes() {
+    let envelope = [0xfb, 0xff, 0x01];
+    assert_eq!(
+        EnvelopeFormat::Hex.render(&envelope, 1).unwrap(),
+        "0xfbff01"
+    );
+    assert_eq!(EnvelopeFormat::Base64.render(&envelope, 1).unwrap(), "+/8B");
+    // The fragment is exactly what the intent policy's slice contributes to `userOp.signature`.
+    let packed = kernel::pack_permission_signature(&[(1, envelope.to_vec())], &[]).unwrap();
+    let fragment = EnvelopeFormat::UserOpSignature
+        .render(&envelope, 1)
+        .unwrap();
+    assert_eq!(
+        fragment,
+        format!("0x{}", hex::encode(&packed[..packed.len() - 1]))
+    );
+    assert!(EnvelopeFormat::UserOpSignature
+        .render(&envelope, 0xff)
+        .is_err());
+
+    assert_eq!(
+        "userop-signature".parse::<EnvelopeFormat>().unwrap(),
+        EnvelopeFormat::UserOpSignature
+    );
+    assert!("base58".parse::<EnvelopeFormat>().is_err());
+}
+
+#[test]
+fn typed_data_uses_the_installed_domain() {
+    let program = Program::new().deadline(1_700_000_000);
+    let params = EnvelopeParams {
+        chain_id: 42161,
+        policy: Address::repeat_byte(0xaa),
+        wallet: Address::repeat_byte(0xbb),
+        permission_id: permission_id(),
+        nonce: U256::zero(),
+        deadline: 1_700_000_000,
+        call_bundle_hash: H256::repeat_byte(0x44),
+        program: &program,
+    };
+    let domain = PolicyDomain {
+        name: "Acme Policy".to_string(),
+        version: "2".to_string(),
+    };
+
+    let default = EnvelopeTypedData::new(&params);
+    let typed = EnvelopeTypedData::new(&params).with_domain(domain.clone());
+    assert_eq!(typed.domain().unwrap().name.as_deref(), Some("Acme Policy"));
+    assert_eq!(
+        typed.domain().unwrap().separator(),
+        typed.domain_separator().unwrap()
+    );
+    assert_eq!(typed.encode_eip712().unwrap(), typed.digest().0);
+    assert_ne!(typed.digest(), default.digest());
+}
+
+#[test]
+fn nonce_reservations_hand_out_distinct_nonces() {
+    let path = std::env::temp_dir().join(format!(
+        "fiet-nonce-reservations-{}.json",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let (policy, wallet) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+    let desk_a = NonceReservations::new(&path, "desk-a");
+    let desk_b = NonceReservations::new(&path, "desk-b");
+    let now = 1_000;
+    let reserve = |r: &NonceReservations, chain: u64, deadline: u64| {
+        r.reserve_at(
+            policy,
+            wallet,
+            permission_id(),
+            U256::from(chain),
+            deadline,
+            now,
+        )
+        .unwrap()
+    };
+
+    assert_eq!(reserve(&desk_a, 5, 2_000), U256::from(5u64));
+    assert_eq!(reserve(&desk_b, 5, 1_500), U256::from(6u64));
+    // Another permission has its own nonce stream.
+    assert_eq!(
+        desk_a
+            .reserve_at(policy, wallet, H256::zero(), U256::from(5u64), 2_000, now)
+            .unwrap(),
+        U256::from(5u64)
+    );
+
+    // Nonce 5 consumed on-chain: its reservation is reconciled away, 6 is still held.
+    assert_eq!(reserve(&desk_a, 6, 2_000), U256::from(7u64));
+    // Released nonces are handed out again.
+    assert!(desk_b
+        .release(policy, wallet, permission_id(), U256::from(6u64))
+        .unwrap());
+    assert_eq!(reserve(&desk_b, 6, 2_000), U256::from(6u64));
+    // A revocation has to clear 6 and 7, not just the chain nonce.
+    assert_eq!(
+        desk_a
+            .highest_at(policy, wallet, permission_id(), U256::from(6u64), now)
+            .unwrap(),
+        Some(U256::from(7u64))
+    );
+
+    // Expired envelopes free their nonce; expired deadlines are refused outright.
+    let later = desk_a
+        .reserve_at(
+            policy,
+            wallet,
+            permission_id(),
+            U256::from(6u64),
+            3_000,
+            2_500,
+        )
+        .unwrap();
+    assert_eq!(later, U256::from(6u64));
+    assert_eq!(
+        desk_a
+            .highest_at(policy, wallet, permission_id(), U256::from(7u64), 2_500)
+            .unwrap(),
+        None
+    );
+    assert!(desk_a
+        .reserve_at(policy, wallet, permission_id(), U256::from(6u64), 10, now)
+        .is_err());
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("json.lock"));
+}
+
+fn rpc_error(message: &str) -> ethers::providers::MockResponse {
+    ethers::providers::MockResponse::Error(ethers::providers::JsonRpcError {
+        code: -32000,
+        message: message.into(),
+        data: None,
+    })
+}
+
+fn no_wait(attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        attempts,
+        backoff: std::time::Duration::ZERO,
+        ..RetryPolicy::default()
+    }
+}
+
+#[tokio::test]
+async fn rpc_endpoints_retry_then_fail_over() {
+    let (primary, primary_mock) = Provider::mocked();
+    let (fallback, fallback_mock) = Provider::mocked();
+    primary_mock.push_response(rpc_error("header not found"));
+    primary_mock.push_response(rpc_error("connection reset"));
+    fallback_mock.push(U256::from(42u64)).unwrap();
+
+    let endpoints = RpcEndpoints::new(Arc::new(primary))
+        .with_fallback(Arc::new(fallback))
+        .with_retry(no_wait(2));
+    assert_eq!(endpoints.block_number().await.unwrap(), 42);
+
+    // A single attempt on a failing endpoint with nothing to fall back to is an error.
+    let (primary, primary_mock) = Provider::mocked();
+    primary_mock.push_response(rpc_error("connection reset"));
+    let endpoints = RpcEndpoints::new(Arc::new(primary)).with_retry(no_wait(1));
+    assert!(endpoints.block_number().await.is_err());
+}
+
+#[tokio::test]
+async fn rpc_endpoints_do_not_retry_reverts() {
+    let (primary, primary_mock) = Provider::mocked();
+    let (fallback, fallback_mock) = Provider::mocked();
+    primary_mock.push_response(rpc_error("execution reverted"));
+    fallback_mock
+        .push::<ethers::types::Bytes, _>(ethers::types::Bytes::from(vec![1u8]))
+        .unwrap();
+
+    let endpoints = RpcEndpoints::new(Arc::new(primary))
+        .with_fallback(Arc::new(fallback))
+        .with_retry(no_wait(3));
+    let tx = ethers::types::TransactionRequest::new()
+        .to(Address::repeat_byte(0x11))
+        .into();
+    let err = endpoints.call(&tx, 1).await.unwrap_err();
+    assert!(format!("{err:#}").contains("reverted"), "{err:#}");
+}
+
+#[tokio::test]
+async fn prefetch_serves_repeated_reads_from_one_call() {
+    let (provider, mock) = Provider::mocked();
+    let mut reserve = [0u8; 32];
+    reserve[31] = 7;
+    // Responses are served last-in first-out: the block comes first, then the one reserve read.
+    mock.push::<ethers::types::Bytes, _>(ethers::types::Bytes::from(reserve.to_vec()))
+        .unwrap();
+    let block = ethers::types::Block::<H256> {
+        timestamp: U256::from(1_000u64),
+        ..Default::default()
+    };
+    mock.push(block).unwrap();
+
+    let sources = FactSources {
+        liquidity_hub: Address::repeat_byte(0x33),
+        ..FactSources::default()
+    };
+    let facts = RpcFactsProvider::at_block(Arc::new(provider), sources, 9)
+        .await
+        .unwrap();
+    let lcc = alloy_primitives::Address::repeat_byte(0x44);
+    let checks = [
+        Check::ReserveGte {
+            lcc,
+            min: alloy_primitives::U256::from(5u64),
+        },
+        Check::ReserveGte {
+            lcc,
+            min: alloy_primitives::U256::from(10u64),
+        },
+    ];
+    let fetched = facts
+        .prefetch(|facts| drop(evaluate_verbose(&checks, facts)))
+        .await
+        .unwrap();
+    assert_eq!(fetched, 1);
+
+    // Everything is cached now: evaluating makes no call (the mock has no responses left).
+    let passed: Vec<bool> = evaluate_verbose(&checks, &facts)
+        .iter()
+        .map(|o| o.passed)
+        .collect();
+    assert_eq!(passed, vec![true, false]);
+    assert_eq!(facts.block_timestamp(), 1_000);
+}
+
+#[tokio::test]
+async fn fact_gas_prices_each_checks_reads() {
+    let (provider, mock) = Provider::mocked();
+    // Served last-in first-out: block, slot0 read, then the call's estimate and its baseline.
+    mock.push(U256::from(21_500u64)).unwrap();
+    mock.push(U256::from(30_000u64)).unwrap();
+    mock.push::<ethers::types::Bytes, _>(ethers::types::Bytes::from(vec![0u8; 128]))
+        .unwrap();
+    mock.push(ethers::types::Block::<H256>::default()).unwrap();
+
+    let sources = FactSources {
+        state_view: Address::repeat_byte(0x22),
+        ..FactSources::default()
+    };
+    let facts = RpcFactsProvider::at_block(Arc::new(provider), sources, 9)
+        .await
+        .unwrap();
+    let pool_id = alloy_primitives::FixedBytes::repeat_byte(0x55);
+    let checks = [
+        Check::Deadline { deadline: 1 },
+        Check::Slot0TickBounds {
+            pool_id,
+            min: -10,
+            max: 10,
+        },
+        Check::Slot0SqrtPriceBounds {
+            pool_id,
+            min: alloy_primitives::U256::ZERO,
+            max: alloy_primitives::U256::MAX,
+        },
+    ];
+    facts
+        .prefetch(|facts| drop(evaluate_verbose(&checks, facts)))
+        .await
+        .unwrap();
+    let calls: Vec<_> = checks
+        .iter()
+        .map(|check| facts.record_calls(|facts| drop(evaluate_verbose(&[check.clone()], facts))))
+        .collect();
+    assert_eq!(
+        calls.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![0, 1, 1]
+    );
+
+    // Both slot0 checks make the same call: cold for the first, warm for the second.
+    let gas = fact_gas(&facts, &calls).await.unwrap();
+    assert_eq!(
+        gas,
+        vec![
+            Some(0),
+            Some(COLD_CALL_GAS + 8_500),
+            Some(WARM_CALL_GAS + 8_500)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn source_overrides_route_only_their_opcode() {
+    let (provider, mock) = Provider::mocked();
+    let mut word = [0u8; 32];
+    word[31] = 7;
+    // Served last-in first-out: the block, then the two hub reads.
+    for _ in 0..2 {
+        mock.push::<ethers::types::Bytes, _>(ethers::types::Bytes::from(word.to_vec()))
+            .unwrap();
+    }
+    mock.push(ethers::types::Block::<H256>::default()).unwrap();
+
+    let (hub, migrated_hub) = (Address::repeat_byte(0x33), Address::repeat_byte(0x13));
+    let sources = FactSources {
+        liquidity_hub: hub,
+        overrides: vec![SourceOverride {
+            opcode: 0x32,
+            source: migrated_hub,
+        }],
+        ..FactSources::default()
+    };
+    let facts = RpcFactsProvider::at_block(Arc::new(provider), sources, 9)
+        .await
+        .unwrap();
+    let lcc = alloy_primitives::Address::repeat_byte(0x44);
+    let checks = [
+        Check::ReserveGte {
+            lcc,
+            min: alloy_primitives::U256::from(5u64),
+        },
+        Check::QueueLte {
+            lcc,
+            owner: lcc,
+            max: alloy_primitives::U256::from(10u64),
+        },
+    ];
+    facts
+        .prefetch(|facts| drop(evaluate_routed(&checks, facts)))
+        .await
+        .unwrap();
+    let targets: Vec<Address> = facts
+        .record_calls(|facts| drop(evaluate_routed(&checks, facts)))
+        .into_iter()
+        .map(|(target, _)| target)
+        .collect();
+    assert_eq!(targets, vec![migrated_hub, hub]);
+}
+
+#[tokio::test]
+async fn bindings_encode_the_policy_interface() {
+    use crate::bindings::{
+        batch_install_policy_calldata, bootstrap_policy_init_data, install_policy_calldata,
+        migrate_policy_executions, uninstall_policy_calldata, PolicyReader, BATCH_INSTALL_ID,
+    };
+    use crate::nonce::revoke_nonce_tx;
+
+    let (policy, wallet) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+    let tx = revoke_nonce_tx(policy, wallet, permission_id(), U256::from(7u64));
+    let data = tx.data().unwrap();
+    assert_eq!(
+        data[..4],
+        ethers::utils::id("revokeNonce(address,bytes32,uint256)")
+    );
+    assert_eq!(&data[16..36], wallet.as_bytes());
+    assert_eq!(&data[36..68], permission_id().as_bytes());
+    assert_eq!(U256::from_big_endian(&data[68..100]), U256::from(7u64));
+
+    let calldata = install_policy_calldata(policy, permission_id(), &[0x01, 0x02]);
+    assert_eq!(
+        calldata[..4],
+        ethers::utils::id("installModule(uint256,address,bytes)")
+    );
+    let decoded = ethers::abi::decode(
+        &[
+            ethers::abi::ParamType::Uint(256),
+            ethers::abi::ParamType::Address,
+            ethers::abi::ParamType::Bytes,
+        ],
+        &calldata[4..],
+    )
+    .unwrap();
+    let mut init_data = permission_id().as_bytes().to_vec();
+    init_data.extend_from_slice(&[0x01, 0x02]);
+    assert_eq!(
+        decoded,
+        vec![
+            ethers::abi::Token::Uint(U256::from(5u64)),
+            ethers::abi::Token::Address(policy),
+            ethers::abi::Token::Bytes(init_data),
+        ]
+    );
+
+    let other_id = H256::repeat_byte(0x22);
+    let batch = batch_install_policy_calldata(
+        policy,
+        &[(permission_id(), vec![0x01, 0x02]), (other_id, vec![0x03])],
+    )
+    .unwrap();
+    let mut payload = BATCH_INSTALL_ID.as_bytes().to_vec();
+    payload.push(2);
+    payload.extend_from_slice(permission_id().as_bytes());
+    payload.extend_from_slice(&[0x00, 0x02, 0x01, 0x02]);
+    payload.extend_from_slice(other_id.as_bytes());
+    payload.extend_from_slice(&[0x00, 0x01, 0x03]);
+    assert_eq!(
+        batch,
+        install_policy_calldata(policy, BATCH_INSTALL_ID, &payload[32..])
+    );
+    assert!(batch_install_policy_calldata(policy, &[]).is_none());
+
+    let mut module_data = permission_id().as_bytes().to_vec();
+    module_data.extend_from_slice(&[0x01, 0x02]);
+    let bootstrap = bootstrap_policy_init_data(permission_id(), &[0x01, 0x02], false);
+    assert_eq!(
+        ethers::abi::decode(
+            &[
+                ethers::abi::ParamType::Uint(256),
+                ethers::abi::ParamType::Bytes
+            ],
+            &bootstrap
+        )
+        .unwrap(),
+        vec![
+            ethers::abi::Token::Uint(U256::from(5u64)),
+            ethers::abi::Token::Bytes(module_data.clone()),
+        ]
+    );
+    let bootstrap = bootstrap_policy_init_data(permission_id(), &[0x01, 0x02], true);
+    assert_eq!(
+        ethers::abi::decode(
+            &[
+                ethers::abi::ParamType::Array(Box::new(ethers::abi::ParamType::Uint(256))),
+                ethers::abi::ParamType::Array(Box::new(ethers::abi::ParamType::Bytes)),
+            ],
+            &bootstrap
+        )
+        .unwrap(),
+        vec![
+            ethers::abi::Token::Array(vec![
+                ethers::abi::Token::Uint(U256::from(4u64)),
+                ethers::abi::Token::Uint(U256::from(5u64)),
+            ]),
+            ethers::abi::Token::Array(vec![
+                ethers::abi::Token::Bytes(Vec::new()),
+                ethers::abi::Token::Bytes(module_data.clone()),
+            ]),
+        ]
+    );
+
+    let new_policy = Address::repeat_byte(0xcc);
+    let migration =
+        migrate_policy_executions(wallet, policy, new_policy, permission_id(), &[0x01, 0x02]);
+    assert_eq!(migration.len(), 2);
+    assert!(migration
+        .iter()
+        .all(|e| e.target == wallet && e.value.is_zero()));
+    let uninstall = uninstall_policy_calldata(policy, permission_id());
+    assert_eq!(
+        uninstall[..4],
+        ethers::utils::id("uninstallModule(uint256,address,bytes)")
+    );
+    assert_eq!(migration[0].data.to_vec(), uninstall);
+    assert_eq!(
+        migration[1].data.to_vec(),
+        install_policy_calldata(new_policy, permission_id(), &[0x01, 0x02])
+    );
+
+    let (provider, mock) = Provider::mocked();
+    let oracle_calls =
+        ethers::abi::encode(&[ethers::abi::Token::Array(vec![ethers::abi::Token::Tuple(
+            vec![
+                ethers::abi::Token::Address(Address::repeat_byte(0x0a)),
+                ethers::abi::Token::FixedBytes(vec![0x50, 0xd2, 0x5b, 0xcd]),
+            ],
+        )])]);
+    mock.push::<ethers::types::Bytes, _>(ethers::types::Bytes::from(oracle_calls))
+        .unwrap();
+    let mut nonce = [0u8; 32];
+    nonce[31] = 9;
+    mock.push::<ethers::types::Bytes, _>(ethers::types::Bytes::from(nonce.to_vec()))
+        .unwrap();
+
+    let reader = PolicyReader::new(&provider, policy);
+    assert_eq!(
+        reader.nonce_of(wallet, permission_id()).await.unwrap(),
+        U256::from(9u64)
+    );
+    assert_eq!(
+        reader
+            .oracle_calls_of(wallet, permission_id())
+            .await
+            .unwrap(),
+        vec![(Address::repeat_byte(0x0a), [0x50, 0xd2, 0x5b, 0xcd])]
+    );
+}
+
+#[tokio::test]
+async fn events_filter_and_decode_policy_logs() {
+    use crate::bindings::IIntentPolicy::{NonceRevoked, PermissionInstalled};
+    use crate::events::{decode_log, event_scope, EventFilter, EventKind, PolicyEvent};
+    use alloy_sol_types::SolEvent;
+    use ethers::types::{Log, ValueOrArray};
+
+    let (policy, wallet) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+    let as_log = |data: alloy_primitives::LogData| Log {
+        address: policy,
+        topics: data.topics().iter().map(|t| H256(t.0)).collect(),
+        data: data.data.to_vec().into(),
+        ..Default::default()
+    };
+    let installed = PermissionInstalled {
+        wallet: convert::address(wallet),
+        permissionId: convert::bytes32(permission_id()),
+        signer: alloy_primitives::Address::repeat_byte(0x51),
+        stateView: alloy_primitives::Address::repeat_byte(0x01),
+        vtsOrchestrator: alloy_primitives::Address::repeat_byte(0x02),
+        liquidityHub: alloy_primitives::Address::repeat_byte(0x03),
+    };
+    let revoked = NonceRevoked {
+        wallet: convert::address(wallet),
+        permissionId: convert::bytes32(permission_id()),
+        caller: alloy_primitives::Address::repeat_byte(0x51),
+        nonce: alloy_primitives::U256::from(4u64),
+    };
+    let logs = vec![
+        as_log(installed.encode_log_data()),
+        as_log(revoked.encode_log_data()),
+    ];
+
+    assert_eq!(
+        decode_log(&logs[0]).unwrap(),
+        PolicyEvent::PermissionInstalled(installed)
+    );
+    let event = decode_log(&logs[1]).unwrap();
+    assert_eq!(event, PolicyEvent::NonceRevoked(revoked));
+    assert_eq!(event_scope(&event), (wallet, permission_id()));
+    let mut foreign = logs[1].clone();
+    foreign.topics[0] = H256::repeat_byte(0x01);
+    assert!(decode_log(&foreign).is_err());
+
+    let filter = EventFilter::new(policy)
+        .kinds([EventKind::Installed, EventKind::NonceRevoked])
+        .wallet(wallet)
+        .permission_id(permission_id())
+        .from_block(10);
+    let raw = filter.filter();
+    assert_eq!(
+        raw.topics[0],
+        Some(ValueOrArray::Array(vec![
+            Some(EventKind::Installed.topic0()),
+            Some(EventKind::NonceRevoked.topic0()),
+        ]))
+    );
+    assert_eq!(
+        raw.topics[1],
+        Some(ValueOrArray::Value(Some(H256::from(wallet))))
+    );
+    assert_eq!(
+        raw.topics[2],
+        Some(ValueOrArray::Value(Some(permission_id())))
+    );
+    assert_eq!(
+        EventKind::IntentValidated.topic0(),
+        H256(keccak256(
+            "IntentValidated(address,bytes32,bytes32,bytes32,uint256,uint64)"
+        ))
+    );
+    assert_eq!(
+        EventKind::ConfigFrozen.topic0(),
+        H256(keccak256("ConfigFrozen(address,bytes32)"))
+    );
+
+    let (provider, mock) = Provider::mocked();
+    mock.push::<Vec<Log>, _>(logs.clone()).unwrap();
+    let events = filter.query(&provider).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].0, logs[1]);
+}
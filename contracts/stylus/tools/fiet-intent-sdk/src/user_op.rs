@@ -0,0 +1,353 @@
+//! ERC-4337 v0.7 `PackedUserOperation`, its hash, and a builder for Kernel permission ops.
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, H256, U256},
+    utils::{id, keccak256},
+};
+
+use crate::{
+    enable::EnableData,
+    kernel::{self, Execution},
+};
+
+/// EntryPoint v0.7 `handleOps` signature.
+pub const HANDLE_OPS: &str =
+    "handleOps((address,uint256,bytes,bytes,bytes32,uint256,bytes32,bytes,bytes)[],address)";
+
+/// EntryPoint v0.7 `PackedUserOperation`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackedUserOperation {
+    pub sender: Address,
+    pub nonce: U256,
+    pub init_code: Bytes,
+    pub call_data: Bytes,
+    /// `verificationGasLimit:16 || callGasLimit:16`.
+    pub account_gas_limits: [u8; 32],
+    pub pre_verification_gas: U256,
+    /// `maxPriorityFeePerGas:16 || maxFeePerGas:16`.
+    pub gas_fees: [u8; 32],
+    pub paymaster_and_data: Bytes,
+    pub signature: Bytes,
+}
+
+/// Pack two `uint128`s into one word (`hi:16 || lo:16`), as `accountGasLimits` and `gasFees` are.
+pub fn pack_u128s(hi: u128, lo: u128) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out[..16].copy_from_slice(&hi.to_be_bytes());
+    out[16..].copy_from_slice(&lo.to_be_bytes());
+    out
+}
+
+/// Inverse of [`pack_u128s`].
+pub fn unpack_u128s(word: &[u8; 32]) -> (u128, u128) {
+    let mut hi = [0u8; 16];
+    let mut lo = [0u8; 16];
+    hi.copy_from_slice(&word[..16]);
+    lo.copy_from_slice(&word[16..]);
+    (u128::from_be_bytes(hi), u128::from_be_bytes(lo))
+}
+
+impl PackedUserOperation {
+    /// `EntryPoint.getUserOpHash`: covers every field except `signature`.
+    pub fn hash(&self, entry_point: Address, chain_id: u64) -> H256 {
+        let inner = abi::encode(&[
+            Token::Address(self.sender),
+            Token::Uint(self.nonce),
+            Token::FixedBytes(keccak256(&self.init_code).to_vec()),
+            Token::FixedBytes(keccak256(&self.call_data).to_vec()),
+            Token::FixedBytes(self.account_gas_limits.to_vec()),
+            Token::Uint(self.pre_verification_gas),
+            Token::FixedBytes(self.gas_fees.to_vec()),
+            Token::FixedBytes(keccak256(&self.paymaster_and_data).to_vec()),
+        ]);
+        H256(keccak256(abi::encode(&[
+            Token::FixedBytes(keccak256(inner).to_vec()),
+            Token::Address(entry_point),
+            Token::Uint(U256::from(chain_id)),
+        ])))
+    }
+
+    /// ABI tuple, in field order, for `handleOps`.
+    pub fn into_token(self) -> Token {
+        Token::Tuple(vec![
+            Token::Address(self.sender),
+            Token::Uint(self.nonce),
+            Token::Bytes(self.init_code.to_vec()),
+            Token::Bytes(self.call_data.to_vec()),
+            Token::FixedBytes(self.account_gas_limits.to_vec()),
+            Token::Uint(self.pre_verification_gas),
+            Token::FixedBytes(self.gas_fees.to_vec()),
+            Token::Bytes(self.paymaster_and_data.to_vec()),
+            Token::Bytes(self.signature.to_vec()),
+        ])
+    }
+
+    /// ABI type of [`Self::into_token`].
+    pub fn param_type() -> ParamType {
+        ParamType::Tuple(vec![
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Bytes,
+            ParamType::Bytes,
+            ParamType::FixedBytes(32),
+            ParamType::Uint(256),
+            ParamType::FixedBytes(32),
+            ParamType::Bytes,
+            ParamType::Bytes,
+        ])
+    }
+
+    /// Inverse of [`Self::into_token`].
+    pub fn from_token(token: Token) -> Result<Self> {
+        let malformed = || anyhow!("malformed PackedUserOperation tuple");
+        let fields = token.into_tuple().ok_or_else(malformed)?;
+        let [sender, nonce, init_code, call_data, account_gas_limits, pre_verification_gas, gas_fees, paymaster_and_data, signature]: [Token; 9] =
+            fields.try_into().map_err(|_| malformed())?;
+        let word = |token: Token| -> Result<[u8; 32]> {
+            token
+                .into_fixed_bytes()
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(malformed)
+        };
+        let bytes = |token: Token| -> Result<Bytes> {
+            token.into_bytes().map(Bytes::from).ok_or_else(malformed)
+        };
+        Ok(Self {
+            sender: sender.into_address().ok_or_else(malformed)?,
+            nonce: nonce.into_uint().ok_or_else(malformed)?,
+            init_code: bytes(init_code)?,
+            call_data: bytes(call_data)?,
+            account_gas_limits: word(account_gas_limits)?,
+            pre_verification_gas: pre_verification_gas.into_uint().ok_or_else(malformed)?,
+            gas_fees: word(gas_fees)?,
+            paymaster_and_data: bytes(paymaster_and_data)?,
+            signature: bytes(signature)?,
+        })
+    }
+}
+
+/// `handleOps` calldata (selector included) for `ops`, paying `beneficiary`.
+pub fn handle_ops_calldata(ops: Vec<PackedUserOperation>, beneficiary: Address) -> Bytes {
+    let mut data = id(HANDLE_OPS).to_vec();
+    data.extend(abi::encode(&[
+        Token::Array(
+            ops.into_iter()
+                .map(PackedUserOperation::into_token)
+                .collect(),
+        ),
+        Token::Address(beneficiary),
+    ]));
+    data.into()
+}
+
+/// The ops and beneficiary of `handleOps` calldata (selector included).
+pub fn decode_handle_ops(calldata: &[u8]) -> Result<(Vec<PackedUserOperation>, Address)> {
+    let (selector, args) = calldata
+        .split_first_chunk::<4>()
+        .ok_or_else(|| anyhow!("calldata is shorter than a selector"))?;
+    if selector[..] != id(HANDLE_OPS)[..] {
+        bail!("calldata is not an EntryPoint v0.7 `handleOps` call");
+    }
+    let mut tokens = abi::decode(
+        &[
+            ParamType::Array(Box::new(PackedUserOperation::param_type())),
+            ParamType::Address,
+        ],
+        args,
+    )
+    .context("malformed `handleOps` arguments")?
+    .into_iter();
+    let ops = tokens
+        .next()
+        .and_then(Token::into_array)
+        .expect("decoded as an array");
+    let beneficiary = tokens
+        .next()
+        .and_then(Token::into_address)
+        .expect("decoded as an address");
+    let ops = ops
+        .into_iter()
+        .map(PackedUserOperation::from_token)
+        .collect::<Result<_>>()?;
+    Ok((ops, beneficiary))
+}
+
+/// Gas fields of the UserOperation.
+///
+/// The defaults suit self-submitted `handleOps` on devnet: zero fees mean the account needs no
+/// EntryPoint deposit, and the submitting EOA pays for the outer transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasLimits {
+    pub verification_gas_limit: u128,
+    pub call_gas_limit: u128,
+    pub pre_verification_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    pub max_fee_per_gas: u128,
+}
+
+impl Default for GasLimits {
+    fn default() -> Self {
+        Self {
+            // Stylus validation (policy program + fact reads) runs well above the 4337 defaults.
+            verification_gas_limit: 2_000_000,
+            call_gas_limit: 1_000_000,
+            pre_verification_gas: 100_000,
+            max_priority_fee_per_gas: 0,
+            max_fee_per_gas: 0,
+        }
+    }
+}
+
+/// Builds a complete UserOperation routed through a Kernel permission.
+///
+/// `callData` wraps the executions in Kernel `execute`; `signature` is the composite permission
+/// signature: each policy's slice (`index || uint64 len || sig`, ascending index) followed by
+/// `0xff || signerSig`. The intent policy's envelope signs [`Self::call_bundle_hash`], so build the
+/// envelope from the builder before adding it with [`Self::policy_signature`].
+#[derive(Clone, Debug, Default)]
+pub struct UserOpBuilder {
+    sender: Address,
+    nonce: U256,
+    init_code: Bytes,
+    executions: Vec<Execution>,
+    gas: GasLimits,
+    paymaster_and_data: Bytes,
+    policy_sigs: Vec<(u8, Vec<u8>)>,
+}
+
+impl UserOpBuilder {
+    pub fn new(sender: Address) -> Self {
+        Self {
+            sender,
+            ..Default::default()
+        }
+    }
+
+    /// EntryPoint nonce (key from [`kernel::permission_nonce_key`] in the upper 192 bits).
+    pub fn nonce(mut self, nonce: U256) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    pub fn init_code(mut self, init_code: impl Into<Bytes>) -> Self {
+        self.init_code = init_code.into();
+        self
+    }
+
+    /// Append a call. One execution encodes as a single call, several as a batch.
+    pub fn execution(mut self, execution: Execution) -> Self {
+        self.executions.push(execution);
+        self
+    }
+
+    pub fn executions(mut self, executions: impl IntoIterator<Item = Execution>) -> Self {
+        self.executions.extend(executions);
+        self
+    }
+
+    pub fn gas(mut self, gas: GasLimits) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    pub fn paymaster_and_data(mut self, paymaster_and_data: impl Into<Bytes>) -> Self {
+        self.paymaster_and_data = paymaster_and_data.into();
+        self
+    }
+
+    /// Signature slice for the policy at `index` in the permission (eg the intent envelope at
+    /// index 1 when CallPolicy is index 0). Signature-less policies need no entry.
+    pub fn policy_signature(mut self, index: u8, sig: impl Into<Vec<u8>>) -> Self {
+        self.policy_sigs.push((index, sig.into()));
+        self
+    }
+
+    /// Kernel `execute` calldata for the executions.
+    pub fn call_data(&self) -> Result<Bytes> {
+        match self.executions.as_slice() {
+            [] => bail!("UserOperation has no executions"),
+            [single] => Ok(kernel::execute_single_calldata(
+                single.target,
+                single.value,
+                &single.data,
+            )),
+            batch => Ok(kernel::execute_batch_calldata(batch)),
+        }
+    }
+
+    /// `keccak256(callData)`, the envelope's `callBundleHash`.
+    pub fn call_bundle_hash(&self) -> Result<H256> {
+        Ok(H256(keccak256(self.call_data()?)))
+    }
+
+    /// The op with every field but `signature` filled in.
+    pub fn build_unsigned(&self) -> Result<PackedUserOperation> {
+        Ok(PackedUserOperation {
+            sender: self.sender,
+            nonce: self.nonce,
+            init_code: self.init_code.clone(),
+            call_data: self.call_data()?,
+            account_gas_limits: pack_u128s(
+                self.gas.verification_gas_limit,
+                self.gas.call_gas_limit,
+            ),
+            pre_verification_gas: U256::from(self.gas.pre_verification_gas),
+            gas_fees: pack_u128s(self.gas.max_priority_fee_per_gas, self.gas.max_fee_per_gas),
+            paymaster_and_data: self.paymaster_and_data.clone(),
+            signature: Bytes::new(),
+        })
+    }
+
+    /// The complete op, given the permission signer's signature over its userOpHash.
+    pub fn build(&self, signer_sig: &[u8]) -> Result<PackedUserOperation> {
+        if self.policy_sigs.is_empty() {
+            bail!("no policy signature set; the intent policy rejects ops without an envelope");
+        }
+        let mut op = self.build_unsigned()?;
+        op.signature = kernel::pack_permission_signature(&self.policy_sigs, signer_sig)?;
+        Ok(op)
+    }
+
+    /// [`Self::build`], signing the userOpHash with `owner` (EIP-191, as MultiChainSigner accepts).
+    pub async fn sign(
+        &self,
+        entry_point: Address,
+        chain_id: u64,
+        owner: &LocalWallet,
+    ) -> Result<(PackedUserOperation, H256)> {
+        let user_op_hash = self.build_unsigned()?.hash(entry_point, chain_id);
+        let signer_sig = owner
+            .sign_message(user_op_hash.as_bytes())
+            .await
+            .context("failed signing userOpHash")?;
+        Ok((self.build(&signer_sig.to_vec())?, user_op_hash))
+    }
+
+    /// Enable-mode variant of [`Self::sign`]: the permission signature is wrapped with the
+    /// install approval from `root` (see [`EnableData`]).
+    ///
+    /// The nonce must carry `enable`'s nonce key (see [`EnableData::nonce_key`]).
+    pub async fn sign_enable(
+        &self,
+        entry_point: Address,
+        chain_id: u64,
+        owner: &LocalWallet,
+        root: &LocalWallet,
+        enable: &EnableData,
+    ) -> Result<(PackedUserOperation, H256)> {
+        // Upper 192 bits are the key; compare everything but the 2-byte parallel key.
+        if (self.nonce >> 80) != (enable.nonce_key(0)? >> 16) {
+            bail!(
+                "nonce {:#x} does not use the enable-mode key for this permission",
+                self.nonce
+            );
+        }
+        let (mut op, user_op_hash) = self.sign(entry_point, chain_id, owner).await?;
+        let enable_sig = enable.sign(self.sender, chain_id, root)?;
+        op.signature = enable.encode_signature(&enable_sig, &op.signature);
+        Ok((op, user_op_hash))
+    }
+}
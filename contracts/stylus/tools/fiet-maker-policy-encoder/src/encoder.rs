@@ -1,106 +1,58 @@
-use alloy_primitives::{FixedBytes, U256};
-use k256::ecdsa::{signature::Signer, SigningKey};
+use alloy_primitives::{Address, FixedBytes, U256};
+use k256::ecdsa::SigningKey;
 use sha3::{Digest, Keccak256};
 
-use crate::opcodes::{Check, CompOp, Opcode};
-use crate::types::IntentEnvelope;
+use crate::opcodes::{eip712_checks_hash, Check, Opcode, EIP712_CHECK_TYPE};
+use crate::types::{IntentEnvelope, PermissionModule};
+
+pub mod cbor;
+#[cfg(feature = "borsh")]
+pub mod compact;
+pub mod decode;
+#[cfg(feature = "erc7715")]
+pub mod erc7715;
+pub mod expiry;
+pub mod init_config;
+pub mod lint;
+#[cfg(feature = "reservations")]
+pub mod reservations;
 
 /// Encode a check program from a list of checks.
 pub fn encode_program(checks: &[Check]) -> Vec<u8> {
     let mut buf = Vec::new();
     for check in checks {
-        match check {
-            Check::Deadline { deadline } => {
-                buf.push(Opcode::CheckDeadline as u8);
-                buf.extend_from_slice(&deadline.to_be_bytes());
-            }
-            Check::Nonce { expected } => {
-                buf.push(Opcode::CheckNonce as u8);
-                buf.extend_from_slice(&expected.to_be_bytes::<32>());
-            }
-            Check::CallBundleHash { hash } => {
-                buf.push(Opcode::CheckCallBundleHash as u8);
-                buf.extend_from_slice(hash.as_slice());
-            }
-            Check::TokenAmountLte { token, max } => {
-                buf.push(Opcode::CheckTokenAmountLte as u8);
-                buf.extend_from_slice(token.as_slice());
-                buf.extend_from_slice(&max.to_be_bytes::<32>());
-            }
-            Check::NativeValueLte { max } => {
-                buf.push(Opcode::CheckNativeValueLte as u8);
-                buf.extend_from_slice(&max.to_be_bytes::<32>());
-            }
-            Check::LiquidityDeltaLte { max } => {
-                buf.push(Opcode::CheckLiquidityDeltaLte as u8);
-                buf.extend_from_slice(&max.to_be_bytes());
-            }
-            Check::Slot0TickBounds { pool_id, min, max } => {
-                buf.push(Opcode::CheckSlot0TickBounds as u8);
-                buf.extend_from_slice(pool_id.as_slice());
-                buf.extend_from_slice(&min.to_be_bytes());
-                buf.extend_from_slice(&max.to_be_bytes());
-            }
-            Check::Slot0SqrtPriceBounds { pool_id, min, max } => {
-                buf.push(Opcode::CheckSlot0SqrtPriceBounds as u8);
-                buf.extend_from_slice(pool_id.as_slice());
-                buf.extend_from_slice(&min.to_be_bytes::<32>());
-                buf.extend_from_slice(&max.to_be_bytes::<32>());
-            }
-            Check::RfsClosed { position_id } => {
-                buf.push(Opcode::CheckRfsClosed as u8);
-                buf.extend_from_slice(position_id.as_slice());
-            }
-            Check::QueueLte { lcc, owner, max } => {
-                buf.push(Opcode::CheckQueueLte as u8);
-                buf.extend_from_slice(lcc.as_slice());
-                buf.extend_from_slice(owner.as_slice());
-                buf.extend_from_slice(&max.to_be_bytes::<32>());
-            }
-            Check::ReserveGte { lcc, min } => {
-                buf.push(Opcode::CheckReserveGte as u8);
-                buf.extend_from_slice(lcc.as_slice());
-                buf.extend_from_slice(&min.to_be_bytes::<32>());
-            }
-            Check::SettledGte { position_id, min_amount0, min_amount1 } => {
-                buf.push(Opcode::CheckSettledGte as u8);
-                buf.extend_from_slice(position_id.as_slice());
-                buf.extend_from_slice(&min_amount0.to_be_bytes::<32>());
-                buf.extend_from_slice(&min_amount1.to_be_bytes::<32>());
-            }
-            Check::CommitmentDeficitLte { position_id, max_deficit0, max_deficit1 } => {
-                buf.push(Opcode::CheckCommitmentDeficitLte as u8);
-                buf.extend_from_slice(position_id.as_slice());
-                buf.extend_from_slice(&max_deficit0.to_be_bytes::<32>());
-                buf.extend_from_slice(&max_deficit1.to_be_bytes::<32>());
-            }
-            Check::GracePeriodGte { position_id, min_seconds } => {
-                buf.push(Opcode::CheckGracePeriodGte as u8);
-                buf.extend_from_slice(position_id.as_slice());
-                buf.extend_from_slice(&min_seconds.to_be_bytes());
-            }
-            Check::StaticCallU256 { target, selector, args, op, rhs } => {
-                buf.push(Opcode::CheckStaticCallU256 as u8);
-                buf.extend_from_slice(target.as_slice());
-                buf.extend_from_slice(selector);
-                buf.extend_from_slice(&(args.len() as u16).to_be_bytes());
-                buf.extend_from_slice(args);
-                buf.push(comp_op_to_u8(*op));
-                buf.extend_from_slice(&rhs.to_be_bytes::<32>());
-            }
-        }
+        check.encode_into(&mut buf);
     }
     buf
 }
 
-fn comp_op_to_u8(op: CompOp) -> u8 {
-    match op {
-        CompOp::Lt => 0,
-        CompOp::Lte => 1,
-        CompOp::Gt => 2,
-        CompOp::Gte => 3,
-        CompOp::Eq => 4,
-        CompOp::Neq => 5,
+/// Opcode a check encodes to.
+pub fn check_opcode(check: &Check) -> Opcode {
+    match check {
+        Check::Deadline { .. } => Opcode::CheckDeadline,
+        Check::Nonce { .. } => Opcode::CheckNonce,
+        Check::CallBundleHash { .. } => Opcode::CheckCallBundleHash,
+        Check::TokenAmountLte { .. } => Opcode::CheckTokenAmountLte,
+        Check::NativeValueLte { .. } => Opcode::CheckNativeValueLte,
+        Check::UserOpField { .. } => Opcode::CheckUserOpField,
+        Check::LiquidityDeltaLte { .. } => Opcode::CheckLiquidityDeltaLte,
+        Check::WalletTokenDeltaLte { .. } => Opcode::CheckWalletTokenDeltaLte,
+        Check::WindowSpendLte { .. } => Opcode::CheckWindowSpendLte,
+        Check::Slot0TickBounds { .. } => Opcode::CheckSlot0TickBounds,
+        Check::Slot0SqrtPriceBounds { .. } => Opcode::CheckSlot0SqrtPriceBounds,
+        Check::PoolAllowed { .. } => Opcode::CheckPoolAllowed,
+        Check::TwapTickBounds { .. } => Opcode::CheckTwapTickBounds,
+        Check::OracleDeviationLte { .. } => Opcode::CheckOracleDeviationLte,
+        Check::RfsClosed { .. } => Opcode::CheckRfsClosed,
+        Check::QueueLte { .. } => Opcode::CheckQueueLte,
+        Check::ReserveGte { .. } => Opcode::CheckReserveGte,
+        Check::SettledGte { .. } => Opcode::CheckSettledGte,
+        Check::CommitmentDeficitLte { .. } => Opcode::CheckCommitmentDeficitLte,
+        Check::GracePeriodGte { .. } => Opcode::CheckGracePeriodGte,
+        Check::HubSolvency { .. } => Opcode::CheckHubSolvency,
+        Check::QueueSumLte { .. } => Opcode::CheckQueueSumLte,
+        Check::StaticCallU256 { .. } => Opcode::CheckStaticCallU256,
+        Check::StaticCallCompare { .. } => Opcode::CheckStaticCallCompare,
     }
 }
 
@@ -113,15 +65,167 @@ fn keccak256_bytes(bytes: &[u8]) -> FixedBytes<32> {
     FixedBytes(b)
 }
 
+/// EIP-712 domain name and version the policy verifies envelopes under.
+///
+/// [`Default`] is the policy's built-in domain; an install can override it by appending
+/// [`PolicyDomain::init_data_suffix`] to its `initData`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicyDomain {
+    pub name: String,
+    pub version: String,
+}
+
+impl Default for PolicyDomain {
+    fn default() -> Self {
+        Self { name: "Fiet Maker Intent Policy".to_string(), version: "1".to_string() }
+    }
+}
+
+impl PolicyDomain {
+    /// `initData` extension `0x01 || uint8 nameLen || name || uint8 versionLen || version`, or
+    /// `None` if either is longer than 255 bytes.
+    pub fn init_data_suffix(&self) -> Option<Vec<u8>> {
+        let name_len = u8::try_from(self.name.len()).ok()?;
+        let version_len = u8::try_from(self.version.len()).ok()?;
+        let mut out = Vec::with_capacity(3 + self.name.len() + self.version.len());
+        out.push(0x01);
+        out.push(name_len);
+        out.extend_from_slice(self.name.as_bytes());
+        out.push(version_len);
+        out.extend_from_slice(self.version.as_bytes());
+        Some(out)
+    }
+}
+
+/// `initData` extension `0x02 || stateView || vtsOrchestrator || liquidityHub` pinning each fact
+/// source's expected `extcodehash` (zero leaves a source unpinned).
+///
+/// Extensions must appear in tag order, so append this after [`PolicyDomain::init_data_suffix`].
+pub fn codehash_pins_init_data_suffix(codehashes: [FixedBytes<32>; 3]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + 32 * 3);
+    out.push(0x02);
+    for codehash in codehashes {
+        out.extend_from_slice(codehash.as_slice());
+    }
+    out
+}
+
+/// `initData` extension `0x03 || uint8 count || bytes32[count]` restricting the permission to
+/// these pools; programs must then declare one with `CheckPoolAllowed`, and every call of the
+/// bundle must pass a `PoolKey` of one of them.
+///
+/// Returns `None` for an empty list or more than 255 pools. Append after
+/// [`codehash_pins_init_data_suffix`].
+pub fn pool_allowlist_init_data_suffix(pool_ids: &[FixedBytes<32>]) -> Option<Vec<u8>> {
+    let count = u8::try_from(pool_ids.len()).ok().filter(|count| *count > 0)?;
+    let mut out = Vec::with_capacity(2 + 32 * pool_ids.len());
+    out.push(0x03);
+    out.push(count);
+    for pool_id in pool_ids {
+        out.extend_from_slice(pool_id.as_slice());
+    }
+    Some(out)
+}
+
+/// `initData` extension `0x04 || uint8 count || (bytes20 target || bytes4 selector)[count]`
+/// allowlisting oracle calls for `CheckOracleDeviationLte`.
+///
+/// Returns `None` for an empty list or more than 255 calls. Append after
+/// [`pool_allowlist_init_data_suffix`].
+pub fn oracle_calls_init_data_suffix(calls: &[(Address, [u8; 4])]) -> Option<Vec<u8>> {
+    let count = u8::try_from(calls.len()).ok().filter(|count| *count > 0)?;
+    let mut out = Vec::with_capacity(2 + 24 * calls.len());
+    out.push(0x04);
+    out.push(count);
+    for (target, selector) in calls {
+        out.extend_from_slice(target.as_slice());
+        out.extend_from_slice(selector);
+    }
+    Some(out)
+}
+
+/// `initData` extension `0x05 || uint8 count || (uint8 opcode || bytes20 source)[count]` routing
+/// each opcode's fact reads to `source` instead of the configured source of the same kind.
+///
+/// Each `source` must already have code: the policy refuses the install otherwise, and pins the
+/// source's `extcodehash` then, so routed checks fail if its code changes.
+///
+/// Returns `None` for an empty list, more than 255 overrides, a zero source, an opcode listed twice
+/// or one that reads no fact source ([`Opcode::fact_source`]). Append after
+/// [`oracle_calls_init_data_suffix`].
+pub fn source_overrides_init_data_suffix(overrides: &[(Opcode, Address)]) -> Option<Vec<u8>> {
+    let count = u8::try_from(overrides.len()).ok().filter(|count| *count > 0)?;
+    let mut out = Vec::with_capacity(2 + 21 * overrides.len());
+    out.push(0x05);
+    out.push(count);
+    for (index, (opcode, source)) in overrides.iter().enumerate() {
+        opcode.fact_source()?;
+        if *source == Address::ZERO || overrides[..index].iter().any(|(earlier, _)| earlier == opcode) {
+            return None;
+        }
+        out.push(*opcode as u8);
+        out.extend_from_slice(source.as_slice());
+    }
+    Some(out)
+}
+
+/// `initData` extension `0x08 || uint8 threshold || uint8 count || bytes20[count]` making the
+/// permission k-of-n: envelopes then need a [`threshold_signature`] from `threshold` of `signers`,
+/// and the install's own `signer` must be one of them (v1 installs only).
+///
+/// Returns `None` for an empty set or more than 255 signers, a threshold of zero or above the
+/// set's size, a zero signer or one listed twice. Append after
+/// [`source_overrides_init_data_suffix`].
+pub fn signer_set_init_data_suffix(threshold: u8, signers: &[Address]) -> Option<Vec<u8>> {
+    let count = u8::try_from(signers.len()).ok().filter(|count| *count > 0)?;
+    if threshold == 0 || threshold > count {
+        return None;
+    }
+    let mut out = Vec::with_capacity(3 + 20 * signers.len());
+    out.push(0x08);
+    out.push(threshold);
+    out.push(count);
+    for (index, signer) in signers.iter().enumerate() {
+        if *signer == Address::ZERO || signers[..index].contains(signer) {
+            return None;
+        }
+        out.extend_from_slice(signer.as_slice());
+    }
+    Some(out)
+}
+
 /// Compute the policy EIP-712 digest (must match on-chain `policy_intent_digest`).
 pub fn policy_intent_digest(envelope: &IntentEnvelope) -> FixedBytes<32> {
-    let program_hash: FixedBytes<32> = keccak256_bytes(&envelope.program_bytes);
+    policy_intent_digest_in(envelope, &PolicyDomain::default())
+}
+
+/// [`policy_intent_digest`] for an install with a domain override.
+pub fn policy_intent_digest_in(envelope: &IntentEnvelope, domain: &PolicyDomain) -> FixedBytes<32> {
+    let domain_separator = policy_domain_separator_in(envelope, domain);
+    let struct_hash = policy_intent_struct_hash(envelope);
 
+    let mut final_buf = Vec::with_capacity(2 + 32 + 32);
+    final_buf.extend_from_slice(b"\x19\x01");
+    final_buf.extend_from_slice(domain_separator.as_slice());
+    final_buf.extend_from_slice(struct_hash.as_slice());
+    keccak256_bytes(&final_buf)
+}
+
+/// EIP-712 domain separator of the policy (`domain_chain_id`, `domain_verifying_contract`).
+///
+/// Exposed separately from [`policy_intent_digest`] for signers that take the domain and struct
+/// hashes rather than the digest (eg a Ledger's EIP-712 signing).
+pub fn policy_domain_separator(envelope: &IntentEnvelope) -> FixedBytes<32> {
+    policy_domain_separator_in(envelope, &PolicyDomain::default())
+}
+
+/// [`policy_domain_separator`] for an install with a domain override.
+pub fn policy_domain_separator_in(envelope: &IntentEnvelope, domain: &PolicyDomain) -> FixedBytes<32> {
     let domain_type_hash = keccak256_bytes(
         b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
     );
-    let domain_name_hash = keccak256_bytes(b"Fiet Maker Intent Policy");
-    let domain_version_hash = keccak256_bytes(b"1");
+    let domain_name_hash = keccak256_bytes(domain.name.as_bytes());
+    let domain_version_hash = keccak256_bytes(domain.version.as_bytes());
 
     let mut domain_buf = Vec::with_capacity(32 * 5);
     domain_buf.extend_from_slice(domain_type_hash.as_slice());
@@ -131,11 +235,38 @@ pub fn policy_intent_digest(envelope: &IntentEnvelope) -> FixedBytes<32> {
     let mut vc_padded = [0u8; 32];
     vc_padded[12..32].copy_from_slice(envelope.domain_verifying_contract.as_slice());
     domain_buf.extend_from_slice(&vc_padded);
-    let domain_separator = keccak256_bytes(&domain_buf);
+    keccak256_bytes(&domain_buf)
+}
 
-    let msg_type_hash = keccak256_bytes(
-        b"IntentPolicyEnvelope(address wallet,bytes32 permissionId,uint256 nonce,uint64 deadline,bytes32 callBundleHash,bytes32 programHash)",
-    );
+/// Envelope version whose digest signs `keccak256(program)`.
+pub const ENVELOPE_VERSION_PROGRAM_HASH: u16 = 1;
+/// Envelope version whose digest signs the decoded checks as an EIP-712 `Check[]`
+/// ([`EIP712_CHECK_TYPE`]), so wallets can display them.
+pub const ENVELOPE_VERSION_TYPED_CHECKS: u16 = 2;
+
+/// `IntentPolicyEnvelope` of a typed-checks envelope, without the referenced `Check` type.
+pub const TYPED_ENVELOPE_TYPE: &str = "IntentPolicyEnvelope(address wallet,bytes32 permissionId,uint256 nonce,uint64 deadline,bytes32 callBundleHash,Check[] checks)";
+
+/// EIP-712 struct hash of the `IntentPolicyEnvelope` message.
+///
+/// A [`ENVELOPE_VERSION_TYPED_CHECKS`] envelope whose program does not decode is hashed like a
+/// program-hash one; the policy rejects it either way.
+pub fn policy_intent_struct_hash(envelope: &IntentEnvelope) -> FixedBytes<32> {
+    let typed_checks = (envelope.version == ENVELOPE_VERSION_TYPED_CHECKS)
+        .then(|| decode::decode_program(&envelope.program_bytes).ok())
+        .flatten();
+    let (msg_type_hash, program_hash) = match typed_checks {
+        Some(checks) => (
+            keccak256_bytes(format!("{TYPED_ENVELOPE_TYPE}{EIP712_CHECK_TYPE}").as_bytes()),
+            eip712_checks_hash(&checks),
+        ),
+        None => (
+            keccak256_bytes(
+                b"IntentPolicyEnvelope(address wallet,bytes32 permissionId,uint256 nonce,uint64 deadline,bytes32 callBundleHash,bytes32 programHash)",
+            ),
+            keccak256_bytes(&envelope.program_bytes),
+        ),
+    };
 
     let mut struct_buf = Vec::with_capacity(32 * 7);
     struct_buf.extend_from_slice(msg_type_hash.as_slice());
@@ -149,30 +280,44 @@ pub fn policy_intent_digest(envelope: &IntentEnvelope) -> FixedBytes<32> {
     struct_buf.extend_from_slice(&deadline_padded);
     struct_buf.extend_from_slice(envelope.call_bundle_hash.as_slice());
     struct_buf.extend_from_slice(program_hash.as_slice());
-    let struct_hash = keccak256_bytes(&struct_buf);
-
-    let mut final_buf = Vec::with_capacity(2 + 32 + 32);
-    final_buf.extend_from_slice(b"\x19\x01");
-    final_buf.extend_from_slice(domain_separator.as_slice());
-    final_buf.extend_from_slice(struct_hash.as_slice());
-    keccak256_bytes(&final_buf)
+    keccak256_bytes(&struct_buf)
 }
 
 /// Sign the policy envelope digest and write the 65-byte signature into `envelope.signature`.
 pub fn sign_envelope(envelope: &mut IntentEnvelope, signing_key: &SigningKey) -> Result<(), k256::ecdsa::Error> {
-    let digest = policy_intent_digest(envelope);
-    let signature: k256::ecdsa::Signature = signing_key.sign(&digest.as_slice());
+    sign_envelope_in(envelope, signing_key, &PolicyDomain::default())
+}
+
+/// [`sign_envelope`] for an install with a domain override.
+pub fn sign_envelope_in(
+    envelope: &mut IntentEnvelope,
+    signing_key: &SigningKey,
+    domain: &PolicyDomain,
+) -> Result<(), k256::ecdsa::Error> {
+    let digest = policy_intent_digest_in(envelope, domain);
+    // Sign the digest itself (no extra hashing), as `eth_signTypedData` / `vm.sign` do.
+    let (signature, recovery_id) = signing_key.sign_prehash_recoverable(digest.as_slice())?;
     let (r, s) = signature.split_bytes();
 
     let mut sig_bytes = Vec::with_capacity(65);
     sig_bytes.extend_from_slice(r.as_slice());
     sig_bytes.extend_from_slice(s.as_slice());
-    // v: 27 by default; on-chain verifier tolerates v in {0,1,27,28} by trying candidates.
-    sig_bytes.push(27);
+    sig_bytes.push(27 + recovery_id.to_byte());
     envelope.signature = sig_bytes;
     Ok(())
 }
 
+/// Envelope signature for a k-of-n install: `0xff || (r||s||v)[n]`, one 65-byte signature per
+/// signer (see [`sign_envelope_in`]) in any order.
+pub fn threshold_signature(signatures: &[[u8; 65]]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + 65 * signatures.len());
+    out.push(0xff);
+    for signature in signatures {
+        out.extend_from_slice(signature);
+    }
+    out
+}
+
 /// Encode a policy intent envelope into bytes for use in the policy signature slice.
 ///
 /// Kernel places this into `userOp.signature` (per-policy signature slice) when calling the policy.
@@ -197,11 +342,69 @@ pub fn encode_envelope(envelope: &IntentEnvelope) -> Vec<u8> {
     // bytes program_bytes
     buf.extend_from_slice(&envelope.program_bytes);
 
-    // u16 sig_len (must be 65)
+    // u16 sig_len (65 for ECDSA, otherwise a WebAuthn assertion)
     buf.extend_from_slice(&(envelope.signature.len() as u16).to_be_bytes());
-    // bytes signature (r||s||v)
+    // bytes signature (r||s||v, or the encoded assertion)
     buf.extend_from_slice(&envelope.signature);
 
     buf
 }
 
+/// Kernel permission validator data: `abi.encode(bytes[])` of `PolicyData || initData`, policies
+/// in install order followed by the signer.
+///
+/// This is the `validatorData` Kernel hands to `PermissionValidator` on install/enable.
+pub fn permission_validator_data(
+    policies: &[PermissionModule],
+    signer: &PermissionModule,
+) -> Vec<u8> {
+    let entries: Vec<Vec<u8>> = policies
+        .iter()
+        .chain(core::iter::once(signer))
+        .map(|m| {
+            let mut entry = Vec::with_capacity(22 + m.init_data.len());
+            entry.extend_from_slice(&m.flag.to_be_bytes());
+            entry.extend_from_slice(m.module.as_slice());
+            entry.extend_from_slice(&m.init_data);
+            entry
+        })
+        .collect();
+
+    // abi.encode(bytes[]): head offset, length, per-element offsets, then padded elements.
+    let mut tails = Vec::new();
+    let mut offsets = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        offsets.push(32 * entries.len() + tails.len());
+        tails.extend_from_slice(&U256::from(entry.len()).to_be_bytes::<32>());
+        tails.extend_from_slice(entry);
+        tails.resize(tails.len() + (32 - entry.len() % 32) % 32, 0);
+    }
+    let mut buf = Vec::with_capacity(64 + 32 * entries.len() + tails.len());
+    buf.extend_from_slice(&U256::from(32u64).to_be_bytes::<32>());
+    buf.extend_from_slice(&U256::from(entries.len()).to_be_bytes::<32>());
+    for offset in offsets {
+        buf.extend_from_slice(&U256::from(offset).to_be_bytes::<32>());
+    }
+    buf.extend_from_slice(&tails);
+    buf
+}
+
+/// Kernel `PermissionId` for a policy/signer set: `bytes4(keccak256(validatorData))`, the
+/// derivation used by the Kernel permission tooling (ZeroDev `getPermissionId`).
+///
+/// Order matters: policies are hashed in install order, so the same set installed in a different
+/// order is a different permission.
+pub fn permission_id(policies: &[PermissionModule], signer: &PermissionModule) -> FixedBytes<4> {
+    let hash = keccak256_bytes(&permission_validator_data(policies, signer));
+    FixedBytes::from_slice(&hash[..4])
+}
+
+/// [`permission_id`] widened to the `bytes32` policies receive (`bytes4` left-aligned).
+pub fn permission_id_bytes32(
+    policies: &[PermissionModule],
+    signer: &PermissionModule,
+) -> FixedBytes<32> {
+    let mut out = [0u8; 32];
+    out[..4].copy_from_slice(permission_id(policies, signer).as_slice());
+    FixedBytes(out)
+}
@@ -0,0 +1,193 @@
+//! Deterministic CBOR encoding of [`IntentEnvelope`] for storage and transport between services.
+//!
+//! This is NOT the byte layout the policy reads or the signature commits to: that is
+//! [`encode_envelope`](super::encode_envelope) / [`policy_intent_digest`](super::policy_intent_digest),
+//! which drop the domain and scoping fields. The CBOR form keeps every field so a stored envelope can
+//! be re-verified and re-encoded for the chain without side information.
+//!
+//! The envelope is a definite-length array, in this order:
+//!
+//! | #  | field                       | CBOR                      |
+//! |----|-----------------------------|---------------------------|
+//! | 0  | format version (`1`)        | uint                      |
+//! | 1  | `version`                   | uint                      |
+//! | 2  | `nonce`                     | bstr, 32 bytes big-endian |
+//! | 3  | `deadline`                  | uint                      |
+//! | 4  | `call_bundle_hash`          | bstr, 32 bytes            |
+//! | 5  | `program_bytes`             | bstr                      |
+//! | 6  | `signature`                 | bstr                      |
+//! | 7  | `domain_chain_id`           | uint                      |
+//! | 8  | `domain_verifying_contract` | bstr, 20 bytes            |
+//! | 9  | `wallet`                    | bstr, 20 bytes            |
+//! | 10 | `permission_id`             | bstr, 32 bytes            |
+//!
+//! Encoding follows the RFC 8949 §4.2.1 core deterministic rules (shortest argument encoding,
+//! definite lengths, no maps), so equal envelopes always produce equal bytes. The decoder only
+//! accepts that form, which makes the encoding usable as a storage key.
+
+use alloy_primitives::{Address, FixedBytes, U256};
+
+use crate::types::IntentEnvelope;
+
+/// Format version written as the first array item.
+pub const CBOR_FORMAT_VERSION: u64 = 1;
+
+const ENVELOPE_ITEMS: u64 = 11;
+
+const MAJOR_UINT: u8 = 0;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_ARRAY: u8 = 4;
+
+/// Errors while decoding a CBOR envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CborError {
+    Truncated,
+    /// A data item of a different major type than the field requires.
+    UnexpectedType { field: usize, major: u8 },
+    /// Indefinite lengths, reserved additional info, or an argument not in its shortest form.
+    NonCanonical,
+    UnsupportedFormatVersion(u64),
+    /// The envelope array does not have the expected number of items.
+    BadItemCount(u64),
+    /// A fixed-width field has the wrong byte length.
+    BadLength { field: usize, len: usize },
+    /// An integer field does not fit its Rust type.
+    Overflow { field: usize },
+    /// Bytes left over after the envelope array.
+    TrailingBytes,
+}
+
+/// Encode an envelope, including its signature and domain fields, as deterministic CBOR.
+pub fn encode_envelope_cbor(envelope: &IntentEnvelope) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(256 + envelope.program_bytes.len());
+    head(&mut buf, MAJOR_ARRAY, ENVELOPE_ITEMS);
+    head(&mut buf, MAJOR_UINT, CBOR_FORMAT_VERSION);
+    head(&mut buf, MAJOR_UINT, envelope.version as u64);
+    bytes(&mut buf, &envelope.nonce.to_be_bytes::<32>());
+    head(&mut buf, MAJOR_UINT, envelope.deadline);
+    bytes(&mut buf, envelope.call_bundle_hash.as_slice());
+    bytes(&mut buf, &envelope.program_bytes);
+    bytes(&mut buf, &envelope.signature);
+    head(&mut buf, MAJOR_UINT, envelope.domain_chain_id);
+    bytes(&mut buf, envelope.domain_verifying_contract.as_slice());
+    bytes(&mut buf, envelope.wallet.as_slice());
+    bytes(&mut buf, envelope.permission_id.as_slice());
+    buf
+}
+
+/// Decode [`encode_envelope_cbor`] output, rejecting anything not in deterministic form.
+pub fn decode_envelope_cbor(data: &[u8]) -> Result<IntentEnvelope, CborError> {
+    let mut r = Reader { data, i: 0 };
+    let (major, items) = r.head()?;
+    if major != MAJOR_ARRAY {
+        return Err(CborError::UnexpectedType { field: 0, major });
+    }
+    if items != ENVELOPE_ITEMS {
+        return Err(CborError::BadItemCount(items));
+    }
+    let format = r.uint(0)?;
+    if format != CBOR_FORMAT_VERSION {
+        return Err(CborError::UnsupportedFormatVersion(format));
+    }
+    let version = u16::try_from(r.uint(1)?).map_err(|_| CborError::Overflow { field: 1 })?;
+    let nonce = U256::from_be_bytes(r.fixed::<32>(2)?);
+    let deadline = r.uint(3)?;
+    let call_bundle_hash = FixedBytes(r.fixed::<32>(4)?);
+    let program_bytes = r.bytes(5)?.to_vec();
+    let signature = r.bytes(6)?.to_vec();
+    let domain_chain_id = r.uint(7)?;
+    let domain_verifying_contract = Address::from(r.fixed::<20>(8)?);
+    let wallet = Address::from(r.fixed::<20>(9)?);
+    let permission_id = FixedBytes(r.fixed::<32>(10)?);
+    if r.i != data.len() {
+        return Err(CborError::TrailingBytes);
+    }
+    Ok(IntentEnvelope {
+        version,
+        nonce,
+        deadline,
+        call_bundle_hash,
+        program_bytes,
+        signature,
+        domain_chain_id,
+        domain_verifying_contract,
+        wallet,
+        permission_id,
+    })
+}
+
+/// Write a data item head with the shortest argument encoding.
+fn head(buf: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    if arg < 24 {
+        buf.push(major | arg as u8);
+    } else if arg <= u8::MAX as u64 {
+        buf.push(major | 24);
+        buf.push(arg as u8);
+    } else if arg <= u16::MAX as u64 {
+        buf.push(major | 25);
+        buf.extend_from_slice(&(arg as u16).to_be_bytes());
+    } else if arg <= u32::MAX as u64 {
+        buf.push(major | 26);
+        buf.extend_from_slice(&(arg as u32).to_be_bytes());
+    } else {
+        buf.push(major | 27);
+        buf.extend_from_slice(&arg.to_be_bytes());
+    }
+}
+
+fn bytes(buf: &mut Vec<u8>, value: &[u8]) {
+    head(buf, MAJOR_BYTES, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    i: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CborError> {
+        let end = self.i.checked_add(len).ok_or(CborError::Truncated)?;
+        let out = self.data.get(self.i..end).ok_or(CborError::Truncated)?;
+        self.i = end;
+        Ok(out)
+    }
+
+    /// Read a head, returning `(major type, argument)`.
+    fn head(&mut self) -> Result<(u8, u64), CborError> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let (arg, min) = match info {
+            0..=23 => return Ok((major, info as u64)),
+            24 => (self.take(1)?[0] as u64, 24),
+            25 => (u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64, 1 << 8),
+            26 => (u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64, 1 << 16),
+            27 => (u64::from_be_bytes(self.take(8)?.try_into().unwrap()), 1 << 32),
+            _ => return Err(CborError::NonCanonical),
+        };
+        if arg < min {
+            return Err(CborError::NonCanonical);
+        }
+        Ok((major, arg))
+    }
+
+    fn uint(&mut self, field: usize) -> Result<u64, CborError> {
+        match self.head()? {
+            (MAJOR_UINT, value) => Ok(value),
+            (major, _) => Err(CborError::UnexpectedType { field, major }),
+        }
+    }
+
+    fn bytes(&mut self, field: usize) -> Result<&'a [u8], CborError> {
+        match self.head()? {
+            (MAJOR_BYTES, len) => self.take(usize::try_from(len).map_err(|_| CborError::Truncated)?),
+            (major, _) => Err(CborError::UnexpectedType { field, major }),
+        }
+    }
+
+    fn fixed<const N: usize>(&mut self, field: usize) -> Result<[u8; N], CborError> {
+        let value = self.bytes(field)?;
+        value.try_into().map_err(|_| CborError::BadLength { field, len: value.len() })
+    }
+}
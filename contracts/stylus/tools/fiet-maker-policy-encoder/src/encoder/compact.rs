@@ -0,0 +1,106 @@
+//! Conversion between the canonical policy byte format and the compact Borsh form
+//! (`fiet_maker_policy_types::compact`) used on message buses and in archives.
+//!
+//! Canonical bytes are decoded with the policy's rules first, so only programs and envelopes the
+//! policy would accept are converted, and converting back reproduces the original bytes exactly.
+
+use alloy_primitives::{Address, FixedBytes};
+use borsh::io::ErrorKind;
+
+pub use fiet_maker_policy_types::CompactEnvelope;
+
+use super::decode::{decode_envelope, decode_program, DecodeError, MAX_CHECKS, MAX_PROGRAM_LEN};
+use super::{encode_envelope, encode_program};
+use crate::opcodes::{call_target_count, Check, MAX_CALL_TARGETS};
+use crate::types::IntentEnvelope;
+
+/// Errors while converting between the canonical and compact forms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompactError {
+    /// The canonical bytes (or the compact form's program) would be rejected by the policy.
+    Decode(DecodeError),
+    /// The compact bytes are not valid Borsh for the expected type.
+    Borsh(ErrorKind),
+}
+
+impl From<DecodeError> for CompactError {
+    fn from(err: DecodeError) -> Self {
+        CompactError::Decode(err)
+    }
+}
+
+impl From<borsh::io::Error> for CompactError {
+    fn from(err: borsh::io::Error) -> Self {
+        CompactError::Borsh(err.kind())
+    }
+}
+
+/// Canonical program bytes to the Borsh encoding of its checks.
+pub fn program_to_compact(program_bytes: &[u8]) -> Result<Vec<u8>, CompactError> {
+    let checks = decode_program(program_bytes)?;
+    Ok(borsh::to_vec(&checks)?)
+}
+
+/// Borsh-encoded checks back to canonical program bytes.
+pub fn program_from_compact(bytes: &[u8]) -> Result<Vec<u8>, CompactError> {
+    let checks: Vec<Check> = borsh::from_slice(bytes)?;
+    if checks.len() > MAX_CHECKS {
+        return Err(DecodeError::TooManyChecks.into());
+    }
+    let targets = call_target_count(&checks);
+    if targets > MAX_CALL_TARGETS {
+        return Err(DecodeError::TooManyTargets(targets).into());
+    }
+    let program = encode_program(&checks);
+    if program.len() > MAX_PROGRAM_LEN {
+        return Err(DecodeError::ProgramTooLarge(program.len()).into());
+    }
+    Ok(program)
+}
+
+/// Canonical envelope bytes (`encode_envelope` output) to a [`CompactEnvelope`].
+pub fn envelope_to_compact(envelope_bytes: &[u8]) -> Result<CompactEnvelope, CompactError> {
+    let envelope = decode_envelope(envelope_bytes)?;
+    Ok(CompactEnvelope {
+        version: envelope.version,
+        nonce: envelope.nonce,
+        deadline: envelope.deadline,
+        call_bundle_hash: envelope.call_bundle_hash,
+        program: decode_program(&envelope.program_bytes)?,
+        signature: envelope.signature,
+    })
+}
+
+/// A [`CompactEnvelope`] back to canonical envelope bytes.
+pub fn envelope_from_compact(envelope: &CompactEnvelope) -> Result<Vec<u8>, CompactError> {
+    if envelope.program.len() > MAX_CHECKS {
+        return Err(DecodeError::TooManyChecks.into());
+    }
+    let targets = call_target_count(&envelope.program);
+    if targets > MAX_CALL_TARGETS {
+        return Err(DecodeError::TooManyTargets(targets).into());
+    }
+    Ok(encode_envelope(&IntentEnvelope {
+        version: envelope.version,
+        nonce: envelope.nonce,
+        deadline: envelope.deadline,
+        call_bundle_hash: envelope.call_bundle_hash,
+        program_bytes: encode_program(&envelope.program),
+        signature: envelope.signature.clone(),
+        // Domain and scoping fields are not part of the signature slice.
+        domain_chain_id: 0,
+        domain_verifying_contract: Address::ZERO,
+        wallet: Address::ZERO,
+        permission_id: FixedBytes::ZERO,
+    }))
+}
+
+/// Canonical envelope bytes straight to Borsh bytes.
+pub fn envelope_bytes_to_compact(envelope_bytes: &[u8]) -> Result<Vec<u8>, CompactError> {
+    Ok(borsh::to_vec(&envelope_to_compact(envelope_bytes)?)?)
+}
+
+/// Borsh bytes straight to canonical envelope bytes.
+pub fn envelope_bytes_from_compact(bytes: &[u8]) -> Result<Vec<u8>, CompactError> {
+    envelope_from_compact(&borsh::from_slice(bytes)?)
+}
@@ -0,0 +1,158 @@
+//! Decoding of check programs and policy envelopes (inverse of the encoders).
+//!
+//! Mirrors the policy's `decode_program` and `parse_policy_envelope`, including their limits and
+//! strictness (length, check and call-target caps, signature shape, no trailing bytes), so off-chain tooling rejects
+//! exactly what the policy rejects.
+
+use alloy_primitives::{FixedBytes, U256};
+use fiet_maker_policy_types::{ByteReader, CheckDecodeError, UnexpectedEnd, WebAuthnAssertion};
+
+use crate::opcodes::{call_target_count, Check, MAX_CALL_TARGETS, MAX_QUEUE_SUM_OWNERS};
+
+/// Check and program length caps, shared with the policy's decoder.
+pub use crate::opcodes::{MAX_CHECKS, MAX_PROGRAM_LEN};
+
+/// Errors while decoding a program or envelope.
+///
+/// [`DecodeError::code`] values are stable and match the policy's `DecodeError` codes for the
+/// variants both have (1xx); never renumber a variant, only append.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    UnknownOpcode(u8),
+    UnknownCompOp(u8),
+    Truncated,
+    TooManyChecks,
+    /// The envelope signature is neither 65 bytes of ECDSA, a threshold signature nor a
+    /// well-formed WebAuthn assertion.
+    BadSignatureLength(usize),
+    /// Bytes left over after the envelope signature.
+    TrailingBytes,
+    /// The program is longer than [`MAX_PROGRAM_LEN`] bytes.
+    ProgramTooLarge(usize),
+    /// A `CheckQueueSumLte` owner count outside `1..=MAX_QUEUE_SUM_OWNERS`.
+    BadOwnerCount(u8),
+    /// The program calls out to more than [`MAX_CALL_TARGETS`] distinct contracts.
+    TooManyTargets(usize),
+    /// A `CheckUserOpField` field byte that names no `UserOpField`.
+    BadUserOpField(u8),
+}
+
+impl DecodeError {
+    pub const fn code(&self) -> u16 {
+        match self {
+            DecodeError::UnknownOpcode(_) => 101,
+            DecodeError::Truncated => 102,
+            DecodeError::TooManyChecks => 103,
+            DecodeError::UnknownCompOp(_) => 104,
+            DecodeError::BadSignatureLength(_) => 105,
+            DecodeError::TrailingBytes => 106,
+            DecodeError::ProgramTooLarge(_) => 107,
+            DecodeError::BadOwnerCount(_) => 108,
+            DecodeError::TooManyTargets(_) => 109,
+            DecodeError::BadUserOpField(_) => 110,
+        }
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::UnknownOpcode(op) => write!(f, "unknown opcode 0x{op:02x}"),
+            DecodeError::UnknownCompOp(op) => write!(f, "unknown comparison operator {op}"),
+            DecodeError::Truncated => f.write_str("input truncated"),
+            DecodeError::TooManyChecks => write!(f, "more than {MAX_CHECKS} checks"),
+            DecodeError::BadSignatureLength(len) => write!(f, "signature is {len} bytes but not a threshold signature or WebAuthn assertion, expected 65"),
+            DecodeError::TrailingBytes => f.write_str("trailing bytes after the envelope signature"),
+            DecodeError::ProgramTooLarge(len) => {
+                write!(f, "program is {len} bytes, more than the {MAX_PROGRAM_LEN}-byte limit")
+            }
+            DecodeError::BadOwnerCount(count) => {
+                write!(f, "queue sum over {count} owners, expected 1 to {MAX_QUEUE_SUM_OWNERS}")
+            }
+            DecodeError::TooManyTargets(count) => {
+                write!(f, "program calls {count} distinct contracts, more than {MAX_CALL_TARGETS}")
+            }
+            DecodeError::BadUserOpField(field) => write!(f, "unknown userOp field {field}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<UnexpectedEnd> for DecodeError {
+    fn from(_: UnexpectedEnd) -> Self {
+        DecodeError::Truncated
+    }
+}
+
+impl From<CheckDecodeError> for DecodeError {
+    fn from(err: CheckDecodeError) -> Self {
+        match err {
+            CheckDecodeError::Truncated => DecodeError::Truncated,
+            CheckDecodeError::UnknownOpcode(op) => DecodeError::UnknownOpcode(op),
+            CheckDecodeError::UnknownCompOp(op) => DecodeError::UnknownCompOp(op),
+            CheckDecodeError::BadUserOpField(field) => DecodeError::BadUserOpField(field),
+            CheckDecodeError::BadOwnerCount(count) => DecodeError::BadOwnerCount(count),
+        }
+    }
+}
+
+/// Policy envelope fields carried on the wire (the signature slice Kernel hands the policy).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedEnvelope {
+    pub version: u16,
+    pub nonce: U256,
+    pub deadline: u64,
+    pub call_bundle_hash: FixedBytes<32>,
+    pub program_bytes: Vec<u8>,
+    /// ECDSA (r||s||v), `0xff || (r||s||v)[n]` for k-of-n installs, or an encoded
+    /// [`WebAuthnAssertion`] for passkey installs.
+    pub signature: Vec<u8>,
+}
+
+/// Decode program bytes into checks, capped at [`MAX_PROGRAM_LEN`] bytes, [`MAX_CHECKS`] and
+/// [`MAX_CALL_TARGETS`] distinct call targets.
+pub fn decode_program(bytes: &[u8]) -> Result<Vec<Check>, DecodeError> {
+    if bytes.len() > MAX_PROGRAM_LEN {
+        return Err(DecodeError::ProgramTooLarge(bytes.len()));
+    }
+    let mut r = ByteReader::new(bytes);
+    let mut checks = Vec::new();
+    while !r.is_empty() {
+        if checks.len() >= MAX_CHECKS {
+            return Err(DecodeError::TooManyChecks);
+        }
+        checks.push(Check::decode_from(&mut r)?);
+    }
+    let targets = call_target_count(&checks);
+    if targets > MAX_CALL_TARGETS {
+        return Err(DecodeError::TooManyTargets(targets));
+    }
+    Ok(checks)
+}
+
+/// `0xff` followed by one or more 65-byte signatures, as the policy parses a threshold signature.
+fn is_threshold_signature(signature: &[u8]) -> bool {
+    matches!(signature, [0xff, signatures @ ..] if !signatures.is_empty() && signatures.len() % 65 == 0)
+}
+
+/// Decode an encoded envelope (`encode_envelope` output).
+pub fn decode_envelope(bytes: &[u8]) -> Result<DecodedEnvelope, DecodeError> {
+    let mut r = ByteReader::new(bytes);
+    let version = r.u16()?;
+    let nonce = r.u256()?;
+    let deadline = r.u64()?;
+    let call_bundle_hash = r.b32()?;
+    let program_len = r.u32()? as usize;
+    let program_bytes = r.take(program_len)?.to_vec();
+    let sig_len = r.u16()? as usize;
+    let signature = r.take(sig_len)?.to_vec();
+    if sig_len != 65 && !is_threshold_signature(&signature) && WebAuthnAssertion::decode(&signature).is_none() {
+        return Err(DecodeError::BadSignatureLength(sig_len));
+    }
+    if !r.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(DecodedEnvelope { version, nonce, deadline, call_bundle_hash, program_bytes, signature })
+}
+
@@ -0,0 +1,89 @@
+//! ERC-7715 `wallet_grantPermissions` requests for the intent policy.
+//!
+//! ERC-7715 has no standard permission type for an intent policy, so it is requested under the
+//! custom type [`PERMISSION_TYPE`]. Its `data` carries everything a wallet needs to install the
+//! policy into a Kernel permission: the policy address, the `bytes32` permission id and the packed
+//! `initData` (plus the decoded fields, for display).
+
+use alloy_primitives::{hex, Address, FixedBytes};
+use serde_json::{json, Value};
+
+/// Permission `type` used in the request.
+pub const PERMISSION_TYPE: &str = "fiet-maker-intent-policy";
+
+/// `initData` version understood by `IntentPolicy::on_install`.
+pub const POLICY_INIT_VERSION: u8 = 1;
+
+/// An installed or proposed intent policy configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntentPolicyGrant {
+    pub chain_id: u64,
+    /// Account to grant on; `None` lets the wallet pick.
+    pub account: Option<Address>,
+    /// Unix timestamp after which the permission lapses.
+    pub expiry: u64,
+    /// Key that will sign UserOperations under the permission (the Kernel signer module's owner).
+    pub session_signer: Address,
+    pub policy: Address,
+    pub permission_id: FixedBytes<32>,
+    /// Authorised envelope signer written into the policy config.
+    pub envelope_signer: Address,
+    pub state_view: Address,
+    pub vts_orchestrator: Address,
+    pub liquidity_hub: Address,
+}
+
+impl IntentPolicyGrant {
+    /// Packed `initData` (v1): `uint8 version || signer || stateView || vtsOrchestrator || liquidityHub`.
+    pub fn init_data(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(1 + 20 * 4);
+        out.push(POLICY_INIT_VERSION);
+        out.extend_from_slice(self.envelope_signer.as_slice());
+        out.extend_from_slice(self.state_view.as_slice());
+        out.extend_from_slice(self.vts_orchestrator.as_slice());
+        out.extend_from_slice(self.liquidity_hub.as_slice());
+        out
+    }
+
+    /// The `PermissionRequest` object (one element of the `wallet_grantPermissions` params).
+    pub fn to_request(&self) -> Value {
+        let mut request = json!({
+            "chainId": format!("{:#x}", self.chain_id),
+            "expiry": self.expiry,
+            "signer": {
+                "type": "account",
+                "data": { "id": self.session_signer.to_string() },
+            },
+            "permissions": [{
+                "type": PERMISSION_TYPE,
+                "data": {
+                    "policy": self.policy.to_string(),
+                    "permissionId": self.permission_id.to_string(),
+                    "initData": hex::encode_prefixed(self.init_data()),
+                    "envelopeSigner": self.envelope_signer.to_string(),
+                    "factSources": {
+                        "stateView": self.state_view.to_string(),
+                        "vtsOrchestrator": self.vts_orchestrator.to_string(),
+                        "liquidityHub": self.liquidity_hub.to_string(),
+                    },
+                },
+                "required": true,
+            }],
+            "policies": [],
+        });
+        if let Some(account) = self.account {
+            request["address"] = Value::String(account.to_string());
+        }
+        request
+    }
+
+    /// Full JSON-RPC payload for `wallet_grantPermissions`.
+    pub fn to_rpc_request(&self, id: u64) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "wallet_grantPermissions",
+            "params": [self.to_request()],
+        })
+    }
+}
@@ -0,0 +1,89 @@
+//! Signing-time checks on an envelope's remaining validity.
+//!
+//! The policy compares the deadline against `block.timestamp` at inclusion, so an envelope signed
+//! a few seconds before its deadline is likely dead on arrival. Before signing, compare the
+//! deadline against the latest block's timestamp (fetched by the caller) and the local clock:
+//! whichever is later is taken as "now", and a disagreement larger than the allowed skew means one
+//! of them cannot be trusted.
+
+/// Requirements on the deadline at signing time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExpiryPolicy {
+    /// Seconds the envelope must stay valid for after signing.
+    pub min_remaining: u64,
+    /// Largest tolerated difference between the local clock and the latest block timestamp.
+    pub max_skew: u64,
+}
+
+impl Default for ExpiryPolicy {
+    fn default() -> Self {
+        Self { min_remaining: 30, max_skew: 60 }
+    }
+}
+
+/// Outcome of a passing [`check_expiry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExpiryReport {
+    /// Seconds until the deadline, from the later of the two clocks.
+    pub remaining: u64,
+    /// Local clock minus chain time (negative when the chain is ahead).
+    pub skew: i64,
+}
+
+/// Why a deadline is refused at signing time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpiryError {
+    /// The deadline has passed on either clock; never signable.
+    Expired { deadline: u64, now: u64 },
+    /// Valid for less than [`ExpiryPolicy::min_remaining`].
+    TooShort { remaining: u64, min_remaining: u64 },
+    /// The local clock and chain time disagree by more than [`ExpiryPolicy::max_skew`].
+    ClockSkew { skew: i64, max_skew: u64 },
+}
+
+impl ExpiryError {
+    /// Whether a caller may knowingly sign anyway. An expired deadline can never execute.
+    pub const fn is_overridable(&self) -> bool {
+        !matches!(self, ExpiryError::Expired { .. })
+    }
+}
+
+impl std::fmt::Display for ExpiryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpiryError::Expired { deadline, now } => write!(f, "deadline {deadline} has passed (now {now})"),
+            ExpiryError::TooShort { remaining, min_remaining } => {
+                write!(f, "envelope would be valid for {remaining}s, less than the required {min_remaining}s")
+            }
+            ExpiryError::ClockSkew { skew, max_skew } => {
+                write!(f, "local clock is {skew}s off chain time, more than the allowed {max_skew}s")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExpiryError {}
+
+/// Check `deadline` against chain time (`chain_now`, the latest block timestamp, when known) and
+/// the local clock (`local_now`). Without chain time only the local clock is used and skew is not
+/// checked.
+pub fn check_expiry(
+    deadline: u64,
+    chain_now: Option<u64>,
+    local_now: u64,
+    policy: &ExpiryPolicy,
+) -> Result<ExpiryReport, ExpiryError> {
+    let now = chain_now.map_or(local_now, |chain| chain.max(local_now));
+    if deadline < now {
+        return Err(ExpiryError::Expired { deadline, now });
+    }
+    let skew = chain_now.map_or(0, |chain| local_now as i64 - chain as i64);
+    if skew.unsigned_abs() > policy.max_skew {
+        return Err(ExpiryError::ClockSkew { skew, max_skew: policy.max_skew });
+    }
+    let remaining = deadline - now;
+    if remaining < policy.min_remaining {
+        return Err(ExpiryError::TooShort { remaining, min_remaining: policy.min_remaining });
+    }
+    Ok(ExpiryReport { remaining, skew })
+}
@@ -0,0 +1,78 @@
+//! `abi.encode(InitConfigV2)` install data, for wallet SDKs that can only produce standard
+//! ABI-encoded module init data.
+//!
+//! `IntentPolicy::on_install` tells it from the packed layout by its leading zero byte and
+//! installs it exactly like the equivalent packed `initData` (v1 with `signer`, v2 with a
+//! passkey, plus the extensions for every non-empty optional field).
+
+use alloy_primitives::{Address, FixedBytes};
+use alloy_sol_types::{sol, SolType};
+
+use super::PolicyDomain;
+
+sol! {
+    /// An oracle call allowlisted for `CheckOracleDeviationLte`.
+    #[derive(Debug, PartialEq, Eq)]
+    struct OracleCall {
+        address target;
+        bytes4 selector;
+    }
+
+    /// Mirrors the policy's `InitConfigV2`.
+    #[derive(Debug, PartialEq, Eq)]
+    struct InitConfigV2 {
+        address signer;
+        bytes32 passkeyX;
+        bytes32 passkeyY;
+        address stateView;
+        address vtsOrchestrator;
+        address liquidityHub;
+        string domainName;
+        string domainVersion;
+        bytes32[3] factSourceCodehashes;
+        bytes32[] poolIds;
+        OracleCall[] oracleCalls;
+    }
+}
+
+impl InitConfigV2 {
+    /// A secp256k1 envelope signer with the given fact sources and no extensions.
+    pub fn with_signer(signer: Address, sources: [Address; 3]) -> Self {
+        let [state_view, vts_orchestrator, liquidity_hub] = sources;
+        Self {
+            signer,
+            passkeyX: FixedBytes::ZERO,
+            passkeyY: FixedBytes::ZERO,
+            stateView: state_view,
+            vtsOrchestrator: vts_orchestrator,
+            liquidityHub: liquidity_hub,
+            domainName: String::new(),
+            domainVersion: String::new(),
+            factSourceCodehashes: [FixedBytes::ZERO; 3],
+            poolIds: Vec::new(),
+            oracleCalls: Vec::new(),
+        }
+    }
+
+    /// A passkey (P-256 public key `x`, `y`) envelope signer with the given fact sources.
+    pub fn with_passkey(x: FixedBytes<32>, y: FixedBytes<32>, sources: [Address; 3]) -> Self {
+        Self { passkeyX: x, passkeyY: y, ..Self::with_signer(Address::ZERO, sources) }
+    }
+
+    /// Override the EIP-712 domain envelopes are signed under.
+    pub fn domain(mut self, domain: &PolicyDomain) -> Self {
+        self.domainName = domain.name.clone();
+        self.domainVersion = domain.version.clone();
+        self
+    }
+
+    /// `initData`: `abi.encode(config)`.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        <Self as SolType>::abi_encode(self)
+    }
+
+    /// Decode `abi.encode(config)`.
+    pub fn abi_decode(init_data: &[u8]) -> Option<Self> {
+        <Self as SolType>::abi_decode(init_data, true).ok()
+    }
+}
@@ -0,0 +1,240 @@
+//! ERC-4337 validation-rule lint for check programs.
+//!
+//! The policy evaluates its program inside `validateUserOp`, so every fact read is subject to the
+//! bundler validation rules (ERC-7562). A program that passes `eth_call` simulation can still be
+//! dropped by a bundler that traces it: timestamp-dependent checks use a banned opcode, and most
+//! fact sources read storage that is not associated with the sender.
+//!
+//! The lint is static: it knows which opcode reads what (see `OnchainFactsProvider`), not the
+//! bytecode of the fact sources. Checks that call arbitrary contracts are reported as unverifiable
+//! rather than clean. The envelope deadline is compared against `block.timestamp` by the policy
+//! itself on every UserOperation, independently of the program, and is out of scope here.
+
+use alloy_primitives::Address;
+
+use super::check_opcode;
+use crate::opcodes::{Check, Opcode};
+
+/// The ERC-7562 rule a finding falls under.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// OP-011: the check uses an opcode banned during validation (`TIMESTAMP`, `NUMBER`).
+    BannedOpcode,
+    /// STO-021/STO-033: the check reads storage of a non-entity contract that is not associated
+    /// with the sender. Allowed only when the account is staked.
+    UnassociatedStorage,
+    /// The check calls code the lint cannot see, so neither opcodes nor storage access can be
+    /// verified.
+    UnverifiableCall,
+}
+
+/// How likely a bundler is to drop the UserOperation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Depends on the fact source's code or storage layout; verify with a tracing bundler.
+    Warning,
+    /// Rejected by a compliant bundler.
+    Error,
+}
+
+/// One rule violation, attributed to a check by its position in the program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub index: usize,
+    pub opcode: Opcode,
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// What the lint needs to know about the UserOperation's sender.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LintContext {
+    /// The account (`userOp.sender`); storage keyed by it counts as associated.
+    pub sender: Address,
+    /// Whether the account is staked in the EntryPoint, which lifts the storage restrictions
+    /// (but not the banned opcodes).
+    pub staked: bool,
+}
+
+impl LintContext {
+    pub fn new(sender: Address) -> Self {
+        Self { sender, staked: false }
+    }
+}
+
+/// Lint `checks` against the bundler validation rules. Findings are in program order.
+pub fn lint_program(checks: &[Check], ctx: &LintContext) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (index, check) in checks.iter().enumerate() {
+        let opcode = check_opcode(check);
+        let mut push = |rule: Rule, severity: Severity, message: String| {
+            findings.push(Finding { index, opcode, rule, severity, message });
+        };
+        match check {
+            Check::Nonce { .. }
+            | Check::CallBundleHash { .. }
+            | Check::TokenAmountLte { .. }
+            | Check::NativeValueLte { .. }
+            | Check::UserOpField { .. }
+            | Check::LiquidityDeltaLte { .. }
+            | Check::PoolAllowed { .. } => {}
+            Check::Deadline { .. } => push(
+                Rule::BannedOpcode,
+                Severity::Error,
+                "compares against block.timestamp (TIMESTAMP); use the envelope deadline".into(),
+            ),
+            Check::Slot0TickBounds { .. } | Check::Slot0SqrtPriceBounds { .. } => {
+                if !ctx.staked {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Error,
+                        "StateView.getSlot0 reads PoolManager pool state".into(),
+                    );
+                }
+            }
+            Check::OracleDeviationLte { oracle, selector, .. } => {
+                if !ctx.staked {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Error,
+                        "StateView.getSlot0 reads PoolManager pool state".into(),
+                    );
+                }
+                push(
+                    Rule::UnverifiableCall,
+                    Severity::Warning,
+                    format!(
+                        "oracle staticcall to {oracle} (selector 0x{:02x}{:02x}{:02x}{:02x}) may use banned opcodes or unassociated storage",
+                        selector[0], selector[1], selector[2], selector[3]
+                    ),
+                );
+            }
+            Check::TwapTickBounds { .. } => {
+                push(
+                    Rule::BannedOpcode,
+                    Severity::Error,
+                    "StateView.observe interpolates to block.timestamp (TIMESTAMP)".into(),
+                );
+                if !ctx.staked {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Error,
+                        "StateView.observe reads the pool's oracle observations".into(),
+                    );
+                }
+            }
+            Check::RfsClosed { .. } | Check::SettledGte { .. } | Check::CommitmentDeficitLte { .. } => {
+                if !ctx.staked {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Error,
+                        "reads VTSOrchestrator position state keyed by position id".into(),
+                    );
+                }
+            }
+            Check::GracePeriodGte { .. } => {
+                push(
+                    Rule::BannedOpcode,
+                    Severity::Error,
+                    "grace period is measured against block.timestamp (TIMESTAMP)".into(),
+                );
+                if !ctx.staked {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Error,
+                        "reads VTSOrchestrator position and pool state keyed by id".into(),
+                    );
+                }
+            }
+            Check::QueueLte { owner, .. } => {
+                if ctx.staked {
+                    continue;
+                }
+                if *owner == ctx.sender {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Warning,
+                        "settleQueue is keyed by the sender; compliant only if LiquidityHub's mapping puts the owner in the slot key".into(),
+                    );
+                } else {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Error,
+                        format!("reads LiquidityHub settleQueue of {owner}, not the sender"),
+                    );
+                }
+            }
+            Check::QueueSumLte { owners, .. } => {
+                if ctx.staked {
+                    continue;
+                }
+                for owner in owners.iter().filter(|owner| **owner != ctx.sender) {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Error,
+                        format!("reads LiquidityHub settleQueue of {owner}, not the sender"),
+                    );
+                }
+                if owners.contains(&ctx.sender) {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Warning,
+                        "settleQueue is keyed by the sender; compliant only if LiquidityHub's mapping puts the owner in the slot key".into(),
+                    );
+                }
+            }
+            Check::WalletTokenDeltaLte { token, .. } | Check::WindowSpendLte { token, .. } => {
+                if matches!(check, Check::WindowSpendLte { .. }) {
+                    push(
+                        Rule::BannedOpcode,
+                        Severity::Error,
+                        "buckets spend by block.timestamp (TIMESTAMP)".into(),
+                    );
+                }
+                if !ctx.staked {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Warning,
+                        format!("reads {token}.balanceOf(sender); compliant only if the token keys balances by the account"),
+                    );
+                }
+            }
+            Check::ReserveGte { .. } | Check::HubSolvency { .. } => {
+                if !ctx.staked {
+                    push(
+                        Rule::UnassociatedStorage,
+                        Severity::Error,
+                        "reads LiquidityHub reserves, which are not keyed by the sender".into(),
+                    );
+                }
+            }
+            Check::StaticCallU256 { target, selector, .. } => push(
+                Rule::UnverifiableCall,
+                Severity::Warning,
+                format!(
+                    "staticcall to {target} (selector 0x{:02x}{:02x}{:02x}{:02x}) may use banned opcodes or unassociated storage",
+                    selector[0], selector[1], selector[2], selector[3]
+                ),
+            ),
+            Check::StaticCallCompare { lhs_target, lhs_selector, rhs_target, rhs_selector, .. } => {
+                for (target, selector) in [(lhs_target, lhs_selector), (rhs_target, rhs_selector)] {
+                    push(
+                        Rule::UnverifiableCall,
+                        Severity::Warning,
+                        format!(
+                            "staticcall to {target} (selector 0x{:02x}{:02x}{:02x}{:02x}) may use banned opcodes or unassociated storage",
+                            selector[0], selector[1], selector[2], selector[3]
+                        ),
+                    );
+                }
+            }
+        }
+    }
+    findings
+}
+
+/// `true` if none of the findings is an [`Severity::Error`].
+pub fn is_compliant(findings: &[Finding]) -> bool {
+    findings.iter().all(|f| f.severity < Severity::Error)
+}
@@ -0,0 +1,284 @@
+//! File-backed policy nonce reservations shared by operators signing for the same permissions.
+//!
+//! The policy accepts exactly one envelope per nonce, so two operators that both read nonce `N`
+//! from the chain sign conflicting envelopes and one of them is wasted. Signing through a shared
+//! reservation file hands each signer a distinct nonce: the lowest one at or above the chain's
+//! that nobody holds yet. An envelope at `N + 1` only becomes valid once `N` is consumed.
+//!
+//! Every reservation is reconciled against the chain nonce first: reservations below it were
+//! consumed (or revoked) and reservations whose envelope deadline passed can never execute, so
+//! both are dropped and their nonces become available again.
+//!
+//! Read-modify-write cycles hold an exclusive `flock` on `<path>.lock` and the file is replaced
+//! atomically, so concurrent processes on one host (or a shared filesystem with working locks)
+//! see a consistent view.
+//!
+//! The `fiet-maker-policy-encoder nonce` subcommands (`--features cli`) drive the same file from
+//! the shell, and the SDK's `NonceReservations` wraps it for its ethers-typed client.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use anyhow::{bail, Context, Result};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+const FILE_VERSION: u32 = 1;
+
+/// A nonce held by one signer until it is consumed on-chain or its envelope expires.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reservation {
+    pub nonce: U256,
+    /// Deadline of the envelope signed at this nonce (unix seconds).
+    pub deadline: u64,
+    /// Free-form operator label, for humans reading the file.
+    pub holder: String,
+    pub reserved_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReservationFile {
+    version: u32,
+    /// `policy/wallet/permissionId` -> reservations, ascending by nonce.
+    permissions: BTreeMap<String, Vec<Reservation>>,
+}
+
+/// A reservation file plus the label recorded on reservations made through it.
+#[derive(Clone, Debug)]
+pub struct NonceReservations {
+    path: PathBuf,
+    holder: String,
+}
+
+impl NonceReservations {
+    pub fn new(path: impl Into<PathBuf>, holder: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            holder: holder.into(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reserve a nonce for an envelope expiring at `deadline`, given the policy's current
+    /// `chain_nonce` (its `nonceOf(wallet, permissionId)`).
+    pub fn reserve(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        chain_nonce: U256,
+        deadline: u64,
+    ) -> Result<U256> {
+        self.reserve_at(
+            policy,
+            wallet,
+            permission_id,
+            chain_nonce,
+            deadline,
+            unix_now(),
+        )
+    }
+
+    /// [`Self::reserve`] at a given unix time.
+    pub fn reserve_at(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        chain_nonce: U256,
+        deadline: u64,
+        now: u64,
+    ) -> Result<U256> {
+        if deadline < now {
+            bail!("envelope deadline {deadline} has already passed");
+        }
+        self.update(|file| {
+            let held = file
+                .permissions
+                .entry(key(policy, wallet, permission_id))
+                .or_default();
+            reconcile(held, chain_nonce, now);
+            let mut nonce = chain_nonce;
+            for reservation in held.iter() {
+                if reservation.nonce != nonce {
+                    break;
+                }
+                nonce += U256::from(1);
+            }
+            held.push(Reservation {
+                nonce,
+                deadline,
+                holder: self.holder.clone(),
+                reserved_at: now,
+            });
+            held.sort_by_key(|r| r.nonce);
+            Ok(nonce)
+        })
+    }
+
+    /// Give `nonce` back, eg when signing or submission was abandoned before the envelope left
+    /// the process. Returns whether it was reserved.
+    pub fn release(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        nonce: U256,
+    ) -> Result<bool> {
+        self.update(|file| {
+            let Some(held) = file
+                .permissions
+                .get_mut(&key(policy, wallet, permission_id))
+            else {
+                return Ok(false);
+            };
+            let before = held.len();
+            held.retain(|r| r.nonce != nonce);
+            Ok(held.len() != before)
+        })
+    }
+
+    /// Live reservations for a permission after reconciling against `chain_nonce`.
+    pub fn pending(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        chain_nonce: U256,
+    ) -> Result<Vec<Reservation>> {
+        let now = unix_now();
+        self.update(|file| {
+            let held = file
+                .permissions
+                .entry(key(policy, wallet, permission_id))
+                .or_default();
+            reconcile(held, chain_nonce, now);
+            Ok(held.clone())
+        })
+    }
+
+    /// Highest live reservation for a permission after reconciling against `chain_nonce`, if any.
+    ///
+    /// Envelopes may be signed for every nonce up to it, so a revocation that should cancel all
+    /// of them passes one past it as `revokeNonce`'s `minNonce`.
+    pub fn highest(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        chain_nonce: U256,
+    ) -> Result<Option<U256>> {
+        self.highest_at(policy, wallet, permission_id, chain_nonce, unix_now())
+    }
+
+    /// [`Self::highest`] at a given unix time.
+    pub fn highest_at(
+        &self,
+        policy: Address,
+        wallet: Address,
+        permission_id: FixedBytes<32>,
+        chain_nonce: U256,
+        now: u64,
+    ) -> Result<Option<U256>> {
+        self.update(|file| {
+            let held = file
+                .permissions
+                .entry(key(policy, wallet, permission_id))
+                .or_default();
+            reconcile(held, chain_nonce, now);
+            Ok(held.last().map(|r| r.nonce))
+        })
+    }
+
+    /// Load, apply `f` and save under the file lock.
+    fn update<T>(&self, f: impl FnOnce(&mut ReservationFile) -> Result<T>) -> Result<T> {
+        let _lock = self.lock()?;
+        let mut file = match fs::read_to_string(&self.path) {
+            Ok(raw) => serde_json::from_str::<ReservationFile>(&raw)
+                .with_context(|| format!("malformed reservation file {}", self.path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => ReservationFile {
+                version: FILE_VERSION,
+                ..Default::default()
+            },
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed reading {}", self.path.display()))
+            }
+        };
+        if file.version != FILE_VERSION {
+            bail!(
+                "{} has version {}, expected {FILE_VERSION}",
+                self.path.display(),
+                file.version
+            );
+        }
+        let out = f(&mut file)?;
+        file.permissions.retain(|_, held| !held.is_empty());
+
+        let tmp = with_suffix(&self.path, ".tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&file)?)
+            .with_context(|| format!("failed writing {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed replacing {}", self.path.display()))?;
+        Ok(out)
+    }
+
+    fn lock(&self) -> Result<ReservationLock> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed creating directory {}", parent.display()))?;
+        }
+        let lock_path = with_suffix(&self.path, ".lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("failed opening lock file {}", lock_path.display()))?;
+        file.lock_exclusive()
+            .with_context(|| format!("failed locking {}", lock_path.display()))?;
+        Ok(ReservationLock { file })
+    }
+}
+
+/// Unlocks on drop.
+struct ReservationLock {
+    file: File,
+}
+
+impl Drop for ReservationLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// Drop reservations the chain has moved past or whose envelope can no longer execute.
+fn reconcile(held: &mut Vec<Reservation>, chain_nonce: U256, now: u64) {
+    held.retain(|r| r.nonce >= chain_nonce && r.deadline >= now);
+}
+
+fn key(policy: Address, wallet: Address, permission_id: FixedBytes<32>) -> String {
+    format!("{policy:#x}/{wallet:#x}/{permission_id:#x}")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut out = path.as_os_str().to_os_string();
+    out.push(suffix);
+    PathBuf::from(out)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -1,6 +1,9 @@
 //! Mock facts provider for testing.
 
-pub use fiet_maker_policy_types::{FactsError, FactsProvider, Slot0};
+pub use fiet_maker_policy_types::{
+    deviation_bps, observe_args, solvency_ratio_bps, sqrt_price_x96_to_wad, to_wad, twap_tick_from_observe,
+    FactSource, FactsError, FactsProvider, Slot0, OBSERVE_SIG,
+};
 
 /// Mock facts provider for off-chain testing.
 /// 
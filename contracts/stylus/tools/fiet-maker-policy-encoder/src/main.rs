@@ -0,0 +1,144 @@
+//! `fiet-maker-policy-encoder`: the encoder's command line (build with `--features cli`).
+//!
+//! `nonce` drives the shared reservation file
+//! ([`fiet_maker_policy_encoder::encoder::reservations`]) from the shell, so scripts that sign
+//! envelopes outside the SDK take nonces from the same stream as SDK clients and the watcher. The encoder has no RPC client: commands that reconcile take the policy's current
+//! `nonceOf(wallet, permissionId)` as `--chain-nonce`, eg from
+//! `cast call <policy> "nonceOf(address,bytes32)(uint256)" <wallet> <permissionId>`.
+
+use std::path::PathBuf;
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use fiet_maker_policy_encoder::encoder::reservations::NonceReservations;
+
+#[derive(Parser, Debug)]
+#[command(about = "Fiet maker policy encoder tools")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Reserve, release and list policy nonces in a shared reservation file.
+    Nonce(NonceArgs),
+}
+
+#[derive(Args, Debug)]
+struct NonceArgs {
+    /// Reservation file shared with the other signers (the SDK's `with_reservations` path and the
+    /// watcher's `reservations_path`).
+    #[arg(long, env = "FIET_NONCE_RESERVATIONS")]
+    reservations: PathBuf,
+
+    /// Label recorded on reservations made by this invocation.
+    #[arg(long, env = "FIET_NONCE_HOLDER", default_value = "cli")]
+    holder: String,
+
+    #[command(subcommand)]
+    command: NonceCommand,
+}
+
+/// The permission a nonce belongs to.
+#[derive(Args, Debug)]
+struct Permission {
+    /// Intent policy contract.
+    #[arg(long)]
+    policy: Address,
+
+    /// Kernel wallet the permission is installed on.
+    #[arg(long)]
+    wallet: Address,
+
+    #[arg(long)]
+    permission_id: FixedBytes<32>,
+}
+
+#[derive(Subcommand, Debug)]
+enum NonceCommand {
+    /// Reserve the lowest free nonce at or above the chain nonce and print it.
+    Reserve {
+        #[command(flatten)]
+        permission: Permission,
+
+        /// The policy's current `nonceOf(wallet, permissionId)`.
+        #[arg(long)]
+        chain_nonce: U256,
+
+        /// Deadline of the envelope to be signed at the nonce (unix seconds).
+        #[arg(long)]
+        deadline: u64,
+    },
+    /// Give a reserved nonce back, eg when the envelope was never sent.
+    Release {
+        #[command(flatten)]
+        permission: Permission,
+
+        #[arg(long)]
+        nonce: U256,
+    },
+    /// Print the live reservations, one `nonce deadline holder` line each.
+    Pending {
+        #[command(flatten)]
+        permission: Permission,
+
+        /// The policy's current `nonceOf(wallet, permissionId)`.
+        #[arg(long)]
+        chain_nonce: U256,
+    },
+    /// Print the highest live reservation (nothing when none is held); `revokeNonce` needs one
+    /// past it as `minNonce` to cancel every envelope outstanding.
+    Highest {
+        #[command(flatten)]
+        permission: Permission,
+
+        /// The policy's current `nonceOf(wallet, permissionId)`.
+        #[arg(long)]
+        chain_nonce: U256,
+    },
+}
+
+fn main() -> Result<()> {
+    let Command::Nonce(args) = Cli::parse().command;
+    let reservations = NonceReservations::new(args.reservations, args.holder);
+    match args.command {
+        NonceCommand::Reserve {
+            permission: p,
+            chain_nonce,
+            deadline,
+        } => {
+            let nonce =
+                reservations.reserve(p.policy, p.wallet, p.permission_id, chain_nonce, deadline)?;
+            println!("{nonce}");
+        }
+        NonceCommand::Release {
+            permission: p,
+            nonce,
+        } => {
+            if !reservations.release(p.policy, p.wallet, p.permission_id, nonce)? {
+                eprintln!("nonce {nonce} was not reserved");
+            }
+        }
+        NonceCommand::Pending {
+            permission: p,
+            chain_nonce,
+        } => {
+            for r in reservations.pending(p.policy, p.wallet, p.permission_id, chain_nonce)? {
+                println!("{} {} {}", r.nonce, r.deadline, r.holder);
+            }
+        }
+        NonceCommand::Highest {
+            permission: p,
+            chain_nonce,
+        } => {
+            if let Some(nonce) =
+                reservations.highest(p.policy, p.wallet, p.permission_id, chain_nonce)?
+            {
+                println!("{nonce}");
+            }
+        }
+    }
+    Ok(())
+}
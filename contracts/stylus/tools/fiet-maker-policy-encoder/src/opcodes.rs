@@ -1,2 +1,6 @@
-pub use fiet_maker_policy_types::{Check, CompOp, Opcode};
+pub use fiet_maker_policy_types::{
+    call_target_count, canonicalize, eip712_checks_hash, is_canonical, routed_call_target_count,
+    scale_bps, Check, CompOp, Eip712Check, Opcode, UserOpField, EIP712_CHECK_TYPE,
+    MAX_CALL_TARGETS, MAX_CHECKS, MAX_PROGRAM_LEN, MAX_QUEUE_SUM_OWNERS, SCALE_BPS_ONE,
+};
 
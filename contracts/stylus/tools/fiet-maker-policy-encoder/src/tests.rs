@@ -1,9 +1,10 @@
 #[cfg(test)]
 mod tests {
-    use crate::encoder::{encode_envelope, encode_program};
+    use crate::encoder::{encode_envelope, encode_program, policy_intent_digest, sign_envelope};
     use crate::opcodes::Check;
     use crate::types::IntentEnvelope;
     use alloy_primitives::{Address, FixedBytes, U256};
+    use k256::ecdsa::SigningKey;
 
     #[test]
     fn test_encode_program() {
@@ -45,5 +46,720 @@ mod tests {
         let expected_len = 2 + 32 + 8 + 32 + 4 + 3 + 2 + 65;
         assert_eq!(encoded.len(), expected_len);
     }
-}
 
+    /// Shared with the policy crate, viem and Foundry tests; see `fixture/eip712-vectors.json`.
+    const EIP712_VECTORS: &str = include_str!("../../../fixture/eip712-vectors.json");
+
+    fn hex_field(vector: &serde_json::Value, key: &str) -> Vec<u8> {
+        alloy_primitives::hex::decode(vector[key].as_str().unwrap()).unwrap()
+    }
+
+    fn num_field<T: std::str::FromStr>(vector: &serde_json::Value, key: &str) -> T
+    where
+        T::Err: std::fmt::Debug,
+    {
+        vector[key].as_str().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn test_eip712_golden_vectors() {
+        let fixture: serde_json::Value = serde_json::from_str(EIP712_VECTORS).unwrap();
+        let vectors = fixture["vectors"].as_array().unwrap();
+        assert!(!vectors.is_empty());
+
+        for vector in vectors {
+            let name = vector["name"].as_str().unwrap();
+            let mut envelope = IntentEnvelope {
+                version: 1,
+                nonce: num_field::<U256>(vector, "nonce"),
+                deadline: num_field(vector, "deadline"),
+                call_bundle_hash: FixedBytes::from_slice(&hex_field(vector, "callBundleHash")),
+                program_bytes: hex_field(vector, "programBytes"),
+                signature: Vec::new(),
+                domain_chain_id: num_field(vector, "chainId"),
+                domain_verifying_contract: Address::from_slice(&hex_field(vector, "verifyingContract")),
+                wallet: Address::from_slice(&hex_field(vector, "wallet")),
+                permission_id: FixedBytes::from_slice(&hex_field(vector, "permissionId")),
+            };
+
+            assert_eq!(
+                envelope.call_bundle_hash.as_slice(),
+                alloy_primitives::keccak256(hex_field(vector, "callData")).as_slice(),
+                "{name}: callBundleHash"
+            );
+            assert_eq!(
+                policy_intent_digest(&envelope).as_slice(),
+                hex_field(vector, "digest").as_slice(),
+                "{name}: digest"
+            );
+
+            let key = SigningKey::from_slice(&hex_field(vector, "privateKey")).unwrap();
+            sign_envelope(&mut envelope, &key).unwrap();
+            assert_eq!(envelope.signature, hex_field(vector, "signature"), "{name}: signature");
+            assert_eq!(encode_envelope(&envelope), hex_field(vector, "envelope"), "{name}: envelope");
+        }
+    }
+
+    #[test]
+    fn test_permission_id_derivation() {
+        use crate::encoder::{permission_id, permission_id_bytes32, permission_validator_data};
+        use crate::types::PermissionModule;
+
+        let call_policy = PermissionModule {
+            flag: 0,
+            module: Address::repeat_byte(0x01),
+            init_data: vec![0xaa; 40],
+        };
+        let intent_policy = PermissionModule {
+            flag: 0,
+            module: Address::repeat_byte(0x02),
+            init_data: vec![0xbb; 81],
+        };
+        let signer = PermissionModule {
+            flag: 0,
+            module: Address::repeat_byte(0x03),
+            init_data: Address::repeat_byte(0x04).to_vec(),
+        };
+
+        let data = permission_validator_data(&[call_policy.clone(), intent_policy.clone()], &signer);
+        // offset, length 3, three element offsets, then 22+40 -> 64, 22+81 -> 128, 22+20 -> 64 bytes.
+        assert_eq!(data.len(), 32 * 5 + (32 + 64) + (32 + 128) + (32 + 64));
+        assert_eq!(data[63], 3);
+        assert_eq!(&data[5 * 32 + 32..5 * 32 + 34], &[0, 0]);
+        assert_eq!(&data[5 * 32 + 34..5 * 32 + 54], Address::repeat_byte(0x01).as_slice());
+
+        let id = permission_id(&[call_policy.clone(), intent_policy.clone()], &signer);
+        assert_eq!(id.as_slice(), &alloy_primitives::keccak256(&data)[..4]);
+        let id32 = permission_id_bytes32(&[call_policy.clone(), intent_policy.clone()], &signer);
+        assert_eq!(&id32[..4], id.as_slice());
+        assert_eq!(&id32[4..], &[0u8; 28]);
+
+        // Install order is part of the id.
+        assert_ne!(permission_id(&[intent_policy, call_policy], &signer), id);
+    }
+
+    #[cfg(feature = "erc7715")]
+    #[test]
+    fn test_erc7715_request() {
+        use crate::encoder::erc7715::{IntentPolicyGrant, PERMISSION_TYPE};
+
+        let mut permission_id = [0u8; 32];
+        permission_id[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let grant = IntentPolicyGrant {
+            chain_id: 412346,
+            account: None,
+            expiry: 1_900_000_000,
+            session_signer: Address::repeat_byte(0x05),
+            policy: Address::repeat_byte(0x0a),
+            permission_id: FixedBytes(permission_id),
+            envelope_signer: Address::repeat_byte(0x01),
+            state_view: Address::repeat_byte(0x02),
+            vts_orchestrator: Address::repeat_byte(0x03),
+            liquidity_hub: Address::repeat_byte(0x04),
+        };
+
+        let init_data = grant.init_data();
+        assert_eq!(init_data.len(), 81);
+        assert_eq!(init_data[0], 1);
+        assert_eq!(&init_data[1..21], Address::repeat_byte(0x01).as_slice());
+        assert_eq!(&init_data[61..81], Address::repeat_byte(0x04).as_slice());
+
+        let request = grant.to_request();
+        assert_eq!(request["chainId"], "0x64aba");
+        assert_eq!(request["expiry"], 1_900_000_000u64);
+        assert!(request.get("address").is_none());
+        assert_eq!(request["signer"]["type"], "account");
+        let permission = &request["permissions"][0];
+        assert_eq!(permission["type"], PERMISSION_TYPE);
+        assert_eq!(
+            permission["data"]["permissionId"],
+            "0xdeadbeef00000000000000000000000000000000000000000000000000000000"
+        );
+        assert_eq!(permission["data"]["initData"], format!("0x{}", alloy_primitives::hex::encode(&init_data)));
+
+        let rpc = IntentPolicyGrant { account: Some(Address::repeat_byte(0x0b)), ..grant }.to_rpc_request(1);
+        assert_eq!(rpc["method"], "wallet_grantPermissions");
+        assert_eq!(rpc["params"][0]["address"], Address::repeat_byte(0x0b).to_string());
+    }
+
+    #[test]
+    fn test_lint_program_validation_rules() {
+        use crate::encoder::lint::{is_compliant, lint_program, LintContext, Rule, Severity};
+        use crate::opcodes::{CompOp, Opcode};
+
+        let sender = Address::repeat_byte(0xaa);
+        let checks = vec![
+            Check::Nonce { expected: U256::from(1u64) },
+            Check::Deadline { deadline: 1 },
+            Check::Slot0TickBounds { pool_id: FixedBytes::ZERO, min: -1, max: 1 },
+            Check::QueueLte { lcc: Address::repeat_byte(0x01), owner: sender, max: U256::ZERO },
+            Check::QueueLte { lcc: Address::repeat_byte(0x01), owner: Address::repeat_byte(0xbb), max: U256::ZERO },
+            Check::GracePeriodGte { position_id: FixedBytes::ZERO, min_seconds: 60 },
+            Check::StaticCallU256 {
+                target: Address::repeat_byte(0x05),
+                selector: [0x12, 0x34, 0x56, 0x78],
+                args: vec![],
+                op: CompOp::Gte,
+                rhs: U256::ZERO,
+            },
+        ];
+
+        let findings = lint_program(&checks, &LintContext::new(sender));
+        let summary: Vec<(usize, Rule, Severity)> = findings.iter().map(|f| (f.index, f.rule, f.severity)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, Rule::BannedOpcode, Severity::Error),
+                (2, Rule::UnassociatedStorage, Severity::Error),
+                (3, Rule::UnassociatedStorage, Severity::Warning),
+                (4, Rule::UnassociatedStorage, Severity::Error),
+                (5, Rule::BannedOpcode, Severity::Error),
+                (5, Rule::UnassociatedStorage, Severity::Error),
+                (6, Rule::UnverifiableCall, Severity::Warning),
+            ]
+        );
+        assert_eq!(findings[1].opcode, Opcode::CheckSlot0TickBounds);
+        assert!(!is_compliant(&findings));
+
+        // Staking lifts the storage rules but not the banned opcodes.
+        let staked = LintContext { sender, staked: true };
+        let findings = lint_program(&checks, &staked);
+        assert!(findings.iter().all(|f| f.rule != Rule::UnassociatedStorage));
+        assert_eq!(findings.iter().filter(|f| f.rule == Rule::BannedOpcode).count(), 2);
+
+        let clean = vec![Check::Nonce { expected: U256::ZERO }, Check::CallBundleHash { hash: FixedBytes::ZERO }];
+        assert!(lint_program(&clean, &LintContext::new(sender)).is_empty());
+        assert!(is_compliant(&[]));
+    }
+
+    #[test]
+    fn test_decode_program_roundtrip_and_limits() {
+        use crate::encoder::decode::{decode_program, DecodeError, MAX_CHECKS, MAX_PROGRAM_LEN};
+        use crate::opcodes::{CompOp, UserOpField, MAX_CALL_TARGETS};
+
+        let checks = vec![
+            Check::Deadline { deadline: 7 },
+            Check::UserOpField { field: UserOpField::MaxFeePerGas, op: CompOp::Lte, rhs: U256::from(2_000_000_000u64) },
+            Check::LiquidityDeltaLte { max: u128::MAX },
+            Check::WalletTokenDeltaLte { token: Address::repeat_byte(0x04), max_out: U256::from(9u64) },
+            Check::WindowSpendLte { token: Address::repeat_byte(0x04), window: 86_400, max: U256::from(10u64) },
+            Check::Slot0TickBounds { pool_id: FixedBytes::repeat_byte(0x01), min: -887272, max: 887272 },
+            Check::PoolAllowed { pool_id: FixedBytes::repeat_byte(0x01) },
+            Check::OracleDeviationLte {
+                pool_id: FixedBytes::repeat_byte(0x01),
+                oracle: Address::repeat_byte(0x0a),
+                selector: [0x50, 0xd2, 0x5b, 0xcd],
+                decimals: 8,
+                max_bps: 100,
+            },
+            Check::TwapTickBounds { pool_id: FixedBytes::repeat_byte(0x01), window: 1_800, min: -60, max: 60 },
+            Check::StaticCallCompare {
+                lhs_target: Address::repeat_byte(0x05),
+                lhs_selector: [0xaa, 0xbb, 0xcc, 0xdd],
+                lhs_args: vec![0x01; 32],
+                op: CompOp::Gte,
+                rhs_target: Address::repeat_byte(0x06),
+                rhs_selector: [0x11, 0x22, 0x33, 0x44],
+                rhs_args: Vec::new(),
+                scale_bps: 12_000,
+            },
+            Check::QueueLte { lcc: Address::repeat_byte(0x02), owner: Address::repeat_byte(0x03), max: U256::from(4u64) },
+            Check::QueueSumLte {
+                lcc: Address::repeat_byte(0x02),
+                owners: vec![Address::repeat_byte(0x03), Address::repeat_byte(0x08)],
+                max: U256::from(4u64),
+            },
+            Check::StaticCallU256 {
+                target: Address::repeat_byte(0x05),
+                selector: [0xde, 0xad, 0xbe, 0xef],
+                args: vec![0x06; 40],
+                op: CompOp::Neq,
+                rhs: U256::MAX,
+            },
+        ];
+        let encoded = encode_program(&checks);
+        assert_eq!(decode_program(&encoded).unwrap(), checks);
+
+        assert_eq!(decode_program(&encoded[..encoded.len() - 1]), Err(DecodeError::Truncated));
+        assert_eq!(decode_program(&[0x99]), Err(DecodeError::UnknownOpcode(0x99)));
+        assert_eq!(decode_program(&[0x04, 0x07]), Err(DecodeError::BadUserOpField(0x07)));
+        assert_eq!(DecodeError::BadUserOpField(0x07).code(), 110);
+        assert_eq!(decode_program(&[&[0x37][..], &[0u8; 21]].concat()), Err(DecodeError::BadOwnerCount(0)));
+        assert_eq!(DecodeError::UnknownOpcode(0x99).to_string(), "unknown opcode 0x99");
+        assert_eq!(DecodeError::Truncated.code(), 102);
+        let too_many = encode_program(&vec![Check::Deadline { deadline: 1 }; MAX_CHECKS + 1]);
+        assert_eq!(decode_program(&too_many), Err(DecodeError::TooManyChecks));
+        let too_large = vec![0x01; MAX_PROGRAM_LEN + 1];
+        assert_eq!(decode_program(&too_large), Err(DecodeError::ProgramTooLarge(MAX_PROGRAM_LEN + 1)));
+        assert_eq!(DecodeError::ProgramTooLarge(0).code(), 107);
+        let many_targets: Vec<Check> = (0..=MAX_CALL_TARGETS as u8)
+            .map(|i| Check::WalletTokenDeltaLte { token: Address::repeat_byte(i), max_out: U256::from(1u64) })
+            .collect();
+        let too_many_targets = encode_program(&many_targets);
+        assert_eq!(decode_program(&too_many_targets), Err(DecodeError::TooManyTargets(MAX_CALL_TARGETS + 1)));
+        assert!(decode_program(&too_many_targets[..too_many_targets.len() - 53]).is_ok());
+        assert_eq!(DecodeError::TooManyTargets(0).code(), 109);
+    }
+
+    #[test]
+    fn test_canonical_program_form() {
+        use crate::opcodes::{canonicalize, is_canonical, CompOp};
+
+        let static_call = Check::StaticCallU256 {
+            target: Address::repeat_byte(0x05),
+            selector: [0xde, 0xad, 0xbe, 0xef],
+            args: vec![0x06; 40],
+            op: CompOp::Neq,
+            rhs: U256::MAX,
+        };
+        let checks = vec![
+            static_call.clone(),
+            Check::Deadline { deadline: 9 },
+            Check::PoolAllowed { pool_id: FixedBytes::repeat_byte(0x02) },
+            Check::Deadline { deadline: 7 },
+            Check::PoolAllowed { pool_id: FixedBytes::repeat_byte(0x01) },
+            Check::Deadline { deadline: 9 },
+        ];
+        let canonical = canonicalize(&checks);
+        assert_eq!(
+            canonical,
+            vec![
+                Check::Deadline { deadline: 7 },
+                Check::Deadline { deadline: 9 },
+                Check::PoolAllowed { pool_id: FixedBytes::repeat_byte(0x01) },
+                Check::PoolAllowed { pool_id: FixedBytes::repeat_byte(0x02) },
+                static_call,
+            ]
+        );
+        let reversed: Vec<Check> = checks.iter().rev().cloned().collect();
+        assert_eq!(canonicalize(&reversed), canonical);
+
+        let encoded = encode_program(&canonical);
+        assert!(is_canonical(&encoded));
+        assert!(is_canonical(&[]));
+        assert!(!is_canonical(&encode_program(&checks)));
+        assert!(!is_canonical(&encoded[..encoded.len() - 1]));
+        assert!(!is_canonical(&[0x99]));
+    }
+
+    #[test]
+    fn test_check_ord_and_hash_follow_the_encoding() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let pool = FixedBytes::repeat_byte(0x01);
+        let checks = vec![
+            Check::RfsClosed { position_id: pool },
+            Check::Slot0TickBounds { pool_id: pool, min: -1, max: 1 },
+            Check::Slot0TickBounds { pool_id: pool, min: 1, max: 2 },
+            Check::Deadline { deadline: 7 },
+            Check::RfsClosed { position_id: pool },
+        ];
+        let sorted: Vec<Check> = checks.iter().cloned().collect::<BTreeSet<_>>().into_iter().collect();
+        // By opcode, then by encoded fields: a negative tick encodes (and sorts) above a positive one.
+        assert_eq!(
+            sorted,
+            vec![
+                Check::Deadline { deadline: 7 },
+                Check::Slot0TickBounds { pool_id: pool, min: 1, max: 2 },
+                Check::Slot0TickBounds { pool_id: pool, min: -1, max: 1 },
+                Check::RfsClosed { position_id: pool },
+            ]
+        );
+        assert_eq!(sorted, crate::opcodes::canonicalize(&checks));
+        assert_eq!(checks.iter().collect::<HashSet<_>>().len(), 4);
+        assert_eq!(checks[3].discriminant(), crate::opcodes::Opcode::CheckDeadline as u8);
+    }
+
+    #[test]
+    fn test_check_expiry_uses_the_later_clock() {
+        use crate::encoder::expiry::{check_expiry, ExpiryError, ExpiryPolicy};
+
+        let policy = ExpiryPolicy { min_remaining: 30, max_skew: 10 };
+        let report = check_expiry(1_100, Some(1_000), 1_004, &policy).unwrap();
+        assert_eq!((report.remaining, report.skew), (96, 4));
+        assert_eq!(check_expiry(1_100, None, 1_000, &policy).unwrap().skew, 0);
+
+        assert_eq!(
+            check_expiry(1_020, Some(1_000), 1_000, &policy),
+            Err(ExpiryError::TooShort { remaining: 20, min_remaining: 30 })
+        );
+        let skewed = check_expiry(1_100, Some(1_000), 980, &policy).unwrap_err();
+        assert_eq!(skewed, ExpiryError::ClockSkew { skew: -20, max_skew: 10 });
+        assert!(skewed.is_overridable());
+        // Expired on chain time even though the local clock lags.
+        let expired = check_expiry(1_000, Some(1_005), 999, &policy).unwrap_err();
+        assert_eq!(expired, ExpiryError::Expired { deadline: 1_000, now: 1_005 });
+        assert!(!expired.is_overridable());
+    }
+
+    #[test]
+    fn test_policy_domain_override() {
+        use crate::encoder::{
+            codehash_pins_init_data_suffix, oracle_calls_init_data_suffix, policy_intent_digest_in,
+            pool_allowlist_init_data_suffix, sign_envelope_in, signer_set_init_data_suffix,
+            source_overrides_init_data_suffix, PolicyDomain,
+        };
+        use crate::opcodes::Opcode;
+
+        let mut envelope = IntentEnvelope {
+            version: 1,
+            nonce: U256::ZERO,
+            deadline: 1_700_000_000,
+            call_bundle_hash: FixedBytes::repeat_byte(0x11),
+            program_bytes: Vec::new(),
+            signature: Vec::new(),
+            domain_chain_id: 42161,
+            domain_verifying_contract: Address::repeat_byte(0x33),
+            wallet: Address::repeat_byte(0x44),
+            permission_id: FixedBytes::repeat_byte(0x55),
+        };
+        let acme = PolicyDomain { name: "Acme Policy".to_string(), version: "2".to_string() };
+        assert_eq!(policy_intent_digest_in(&envelope, &PolicyDomain::default()), policy_intent_digest(&envelope));
+        assert_ne!(policy_intent_digest_in(&envelope, &acme), policy_intent_digest(&envelope));
+
+        let mut suffix = vec![0x01, 11];
+        suffix.extend_from_slice(b"Acme Policy");
+        suffix.extend_from_slice(&[1, b'2']);
+        assert_eq!(acme.init_data_suffix(), Some(suffix));
+        let too_long = PolicyDomain { name: "x".repeat(256), version: "1".to_string() };
+        assert_eq!(too_long.init_data_suffix(), None);
+        let pins = codehash_pins_init_data_suffix([FixedBytes::ZERO, FixedBytes::repeat_byte(0x0c), FixedBytes::ZERO]);
+        assert_eq!(pins.len(), 97);
+        assert_eq!((pins[0], pins[33], pins[65]), (0x02, 0x0c, 0x00));
+        let pools = pool_allowlist_init_data_suffix(&[FixedBytes::repeat_byte(0x0d); 2]).unwrap();
+        assert_eq!((pools.len(), pools[0], pools[1], pools[2]), (66, 0x03, 2, 0x0d));
+        assert_eq!(pool_allowlist_init_data_suffix(&[]), None);
+        assert_eq!(pool_allowlist_init_data_suffix(&[FixedBytes::ZERO; 256]), None);
+        let oracles = oracle_calls_init_data_suffix(&[(Address::repeat_byte(0x0a), [0x50, 0xd2, 0x5b, 0xcd])]).unwrap();
+        assert_eq!((oracles.len(), oracles[0], oracles[1], oracles[2], oracles[22]), (26, 0x04, 1, 0x0a, 0x50));
+        assert_eq!(oracle_calls_init_data_suffix(&[]), None);
+        let overrides = source_overrides_init_data_suffix(&[(Opcode::CheckReserveGte, Address::repeat_byte(0x13))]).unwrap();
+        assert_eq!((overrides.len(), overrides[0], overrides[1], overrides[2], overrides[3]), (23, 0x05, 1, 0x32, 0x13));
+        assert_eq!(source_overrides_init_data_suffix(&[(Opcode::CheckDeadline, Address::repeat_byte(0x13))]), None);
+        assert_eq!(source_overrides_init_data_suffix(&[(Opcode::CheckQueueLte, Address::repeat_byte(0x13)); 2]), None);
+        assert_eq!(source_overrides_init_data_suffix(&[(Opcode::CheckQueueLte, Address::ZERO)]), None);
+        let members = [Address::repeat_byte(0x14), Address::repeat_byte(0x15)];
+        let signer_set = signer_set_init_data_suffix(2, &members).unwrap();
+        assert_eq!((signer_set.len(), signer_set[0], signer_set[1], signer_set[2], signer_set[23]), (43, 0x08, 2, 2, 0x15));
+        assert_eq!(signer_set_init_data_suffix(0, &members), None);
+        assert_eq!(signer_set_init_data_suffix(3, &members), None);
+        assert_eq!(signer_set_init_data_suffix(1, &[members[0]; 2]), None);
+        assert_eq!(signer_set_init_data_suffix(1, &[Address::ZERO]), None);
+
+        let key = SigningKey::from_slice(&[0x42; 32]).unwrap();
+        sign_envelope_in(&mut envelope, &key, &acme).unwrap();
+        let mut default_signed = envelope.clone();
+        sign_envelope(&mut default_signed, &key).unwrap();
+        assert_ne!(envelope.signature, default_signed.signature);
+    }
+
+    #[test]
+    fn test_abi_init_config() {
+        use crate::encoder::{
+            init_config::{InitConfigV2, OracleCall},
+            PolicyDomain,
+        };
+
+        let sources = [Address::repeat_byte(0x01), Address::repeat_byte(0x02), Address::repeat_byte(0x03)];
+        let acme = PolicyDomain { name: "Acme Policy".to_string(), version: "2".to_string() };
+        let mut config = InitConfigV2::with_signer(Address::repeat_byte(0x51), sources).domain(&acme);
+        config.poolIds = vec![FixedBytes::repeat_byte(0x0d); 2];
+        config.oracleCalls = vec![OracleCall { target: Address::repeat_byte(0x0a), selector: FixedBytes([0x50, 0xd2, 0x5b, 0xcd]) }];
+
+        let init_data = config.abi_encode();
+        // The head offset of the dynamic struct: a leading zero byte, where packed data starts
+        // with its version.
+        assert_eq!(&init_data[..32], &U256::from(32).to_be_bytes::<32>());
+        assert_eq!(&init_data[44..64], Address::repeat_byte(0x51).as_slice());
+        assert_eq!(InitConfigV2::abi_decode(&init_data), Some(config));
+        assert_eq!(InitConfigV2::abi_decode(&init_data[1..]), None);
+
+        let passkey = InitConfigV2::with_passkey(FixedBytes::repeat_byte(0x0e), FixedBytes::repeat_byte(0x0f), sources);
+        assert_eq!(passkey.signer, Address::ZERO);
+        assert_eq!(passkey.abi_encode()[0], 0);
+    }
+
+    #[test]
+    fn test_typed_checks_digest_matches_sol_struct() {
+        use crate::encoder::{policy_intent_digest_in, PolicyDomain, ENVELOPE_VERSION_TYPED_CHECKS};
+        use crate::opcodes::{CompOp, Eip712Check};
+        use alloy_sol_types::{eip712_domain, SolStruct};
+
+        // The EIP-712 types, hashed independently by alloy.
+        mod typed {
+            alloy_sol_types::sol! {
+                struct Check {
+                    string kind;
+                    bytes32 id;
+                    address target;
+                    address account;
+                    uint256 min;
+                    uint256 max;
+                    uint256 min1;
+                    uint256 max1;
+                    int24 tickMin;
+                    int24 tickMax;
+                    uint32 window;
+                    uint32 bps;
+                    uint8 decimals;
+                    string op;
+                    uint256 rhs;
+                    bytes call;
+                    bytes rhsCall;
+                }
+                struct IntentPolicyEnvelope {
+                    address wallet;
+                    bytes32 permissionId;
+                    uint256 nonce;
+                    uint64 deadline;
+                    bytes32 callBundleHash;
+                    Check[] checks;
+                }
+            }
+        }
+
+        let checks = vec![
+            Check::Deadline { deadline: 1_700_000_060 },
+            Check::Slot0TickBounds { pool_id: FixedBytes::repeat_byte(0x0d), min: -887_272, max: 60 },
+            Check::StaticCallU256 {
+                target: Address::repeat_byte(0x0a),
+                selector: [0x70, 0xa0, 0x82, 0x31],
+                args: vec![0x01; 32],
+                op: CompOp::Gte,
+                rhs: U256::from(5),
+            },
+        ];
+        let envelope = IntentEnvelope {
+            version: ENVELOPE_VERSION_TYPED_CHECKS,
+            nonce: U256::from(7),
+            deadline: 1_700_000_060,
+            call_bundle_hash: FixedBytes::repeat_byte(0x11),
+            program_bytes: encode_program(&checks),
+            signature: Vec::new(),
+            domain_chain_id: 42161,
+            domain_verifying_contract: Address::repeat_byte(0x33),
+            wallet: Address::repeat_byte(0x44),
+            permission_id: FixedBytes::repeat_byte(0x55),
+        };
+
+        let message = typed::IntentPolicyEnvelope {
+            wallet: envelope.wallet,
+            permissionId: envelope.permission_id,
+            nonce: envelope.nonce,
+            deadline: envelope.deadline,
+            callBundleHash: envelope.call_bundle_hash,
+            checks: checks
+                .iter()
+                .map(|check| {
+                    let eip712 = Eip712Check::from(check);
+                    typed::Check {
+                        kind: eip712.kind.to_string(),
+                        id: eip712.id,
+                        target: eip712.target,
+                        account: eip712.account,
+                        min: eip712.min,
+                        max: eip712.max,
+                        min1: eip712.min1,
+                        max1: eip712.max1,
+                        tickMin: eip712.tick_min.try_into().unwrap(),
+                        tickMax: eip712.tick_max.try_into().unwrap(),
+                        window: eip712.window,
+                        bps: eip712.bps,
+                        decimals: eip712.decimals,
+                        op: eip712.op.to_string(),
+                        rhs: eip712.rhs,
+                        call: eip712.call.into(),
+                        rhsCall: eip712.rhs_call.into(),
+                    }
+                })
+                .collect(),
+        };
+        let domain = eip712_domain! {
+            name: "Fiet Maker Intent Policy",
+            version: "1",
+            chain_id: 42161,
+            verifying_contract: Address::repeat_byte(0x33),
+        };
+        let typed_digest = policy_intent_digest_in(&envelope, &PolicyDomain::default());
+        assert_eq!(typed_digest, message.eip712_signing_hash(&domain));
+
+        // The same program under version 1 signs its hash instead.
+        let program_hash = IntentEnvelope { version: 1, ..envelope.clone() };
+        assert_ne!(policy_intent_digest(&program_hash), typed_digest);
+        // An undecodable version 2 program falls back to the program-hash digest.
+        let garbled = IntentEnvelope { program_bytes: vec![0xee], ..envelope };
+        let garbled_v1 = IntentEnvelope { version: 1, ..garbled.clone() };
+        assert_eq!(policy_intent_digest(&garbled), policy_intent_digest(&garbled_v1));
+    }
+
+    #[test]
+    fn test_decode_envelope_signature_schemes() {
+        use crate::encoder::decode::{decode_envelope, DecodeError};
+        use crate::encoder::threshold_signature;
+        use fiet_maker_policy_types::{base64url_challenge, WebAuthnAssertion};
+
+        let mut envelope = IntentEnvelope {
+            version: 1,
+            nonce: U256::ZERO,
+            deadline: 1_700_000_000,
+            call_bundle_hash: FixedBytes::repeat_byte(0x11),
+            program_bytes: Vec::new(),
+            signature: vec![0x22; 65],
+            domain_chain_id: 42161,
+            domain_verifying_contract: Address::repeat_byte(0x33),
+            wallet: Address::repeat_byte(0x44),
+            permission_id: FixedBytes::repeat_byte(0x55),
+        };
+        assert_eq!(decode_envelope(&encode_envelope(&envelope)).unwrap().signature, [0x22; 65]);
+
+        let digest = policy_intent_digest(&envelope);
+        let challenge = base64url_challenge(&digest);
+        assert_eq!(challenge.len(), 43);
+        let mut client_data_json = br#"{"type":"webauthn.get","challenge":""#.to_vec();
+        client_data_json.extend_from_slice(&challenge);
+        client_data_json.extend_from_slice(br#"","origin":"https://app.fiet.finance"}"#);
+        let mut authenticator_data = vec![0u8; 37];
+        authenticator_data[32] = 0x01;
+        let assertion = WebAuthnAssertion {
+            authenticator_data,
+            client_data_json,
+            challenge_index: 23,
+            type_index: 1,
+            r: FixedBytes::repeat_byte(0x0a),
+            s: FixedBytes::repeat_byte(0x0b),
+        };
+        assert!(assertion.is_for(&digest));
+        assert!(!assertion.is_for(&FixedBytes::repeat_byte(0x66)));
+
+        envelope.signature = assertion.encode();
+        let decoded = decode_envelope(&encode_envelope(&envelope)).unwrap();
+        assert_eq!(WebAuthnAssertion::decode(&decoded.signature), Some(assertion));
+
+        envelope.signature = vec![0x22; 64];
+        assert_eq!(decode_envelope(&encode_envelope(&envelope)), Err(DecodeError::BadSignatureLength(64)));
+
+        envelope.signature = threshold_signature(&[[0x22; 65], [0x33; 65]]);
+        assert_eq!(envelope.signature.len(), 131);
+        assert_eq!(decode_envelope(&encode_envelope(&envelope)).unwrap().signature, envelope.signature);
+        envelope.signature.pop();
+        assert_eq!(decode_envelope(&encode_envelope(&envelope)), Err(DecodeError::BadSignatureLength(130)));
+    }
+
+    #[test]
+    fn test_envelope_cbor_roundtrip_and_canonical_form() {
+        use crate::encoder::cbor::{decode_envelope_cbor, encode_envelope_cbor, CborError};
+
+        let envelope = IntentEnvelope {
+            version: 1,
+            nonce: U256::from(300u64),
+            deadline: 1_700_000_000,
+            call_bundle_hash: FixedBytes::repeat_byte(0x11),
+            program_bytes: encode_program(&[Check::Deadline { deadline: 1_700_000_000 }]),
+            signature: vec![0x22; 65],
+            domain_chain_id: 42161,
+            domain_verifying_contract: Address::repeat_byte(0x33),
+            wallet: Address::repeat_byte(0x44),
+            permission_id: FixedBytes::repeat_byte(0x55),
+        };
+        let cbor = encode_envelope_cbor(&envelope);
+        // array(11), format 1, version 1, then the nonce as bstr(32).
+        assert_eq!(&cbor[..5], &[0x8b, 0x01, 0x01, 0x58, 0x20]);
+        assert_eq!(decode_envelope_cbor(&cbor).unwrap(), envelope);
+        assert_eq!(encode_envelope_cbor(&envelope.clone()), cbor);
+        assert_ne!(cbor, encode_envelope(&envelope));
+
+        let mut trailing = cbor.clone();
+        trailing.push(0);
+        assert_eq!(decode_envelope_cbor(&trailing), Err(CborError::TrailingBytes));
+        assert_eq!(decode_envelope_cbor(&cbor[..cbor.len() - 1]), Err(CborError::Truncated));
+        // Version 1 written with a one-byte argument instead of inline.
+        let mut long_form = cbor.clone();
+        long_form.splice(2..3, [0x18, 0x01]);
+        assert_eq!(decode_envelope_cbor(&long_form), Err(CborError::NonCanonical));
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_compact_roundtrip_to_canonical_bytes() {
+        use crate::encoder::compact::{
+            envelope_bytes_from_compact, envelope_bytes_to_compact, program_from_compact, program_to_compact,
+            CompactError,
+        };
+        use crate::encoder::decode::DecodeError;
+        use crate::opcodes::CompOp;
+
+        let program = encode_program(&[
+            Check::Deadline { deadline: 1_700_000_000 },
+            Check::Slot0TickBounds { pool_id: FixedBytes::repeat_byte(0x01), min: -60, max: 60 },
+            Check::StaticCallU256 {
+                target: Address::repeat_byte(0x02),
+                selector: [0x70, 0xa0, 0x82, 0x31],
+                args: vec![0x03; 32],
+                op: CompOp::Gte,
+                rhs: U256::from(5u64),
+            },
+        ]);
+        let compact = program_to_compact(&program).unwrap();
+        assert_eq!(program_from_compact(&compact).unwrap(), program);
+        assert_eq!(program_to_compact(&[0x99]), Err(CompactError::Decode(DecodeError::UnknownOpcode(0x99))));
+
+        let envelope = encode_envelope(&IntentEnvelope {
+            version: 1,
+            nonce: U256::from(9u64),
+            deadline: 1_700_000_000,
+            call_bundle_hash: FixedBytes::repeat_byte(0x04),
+            program_bytes: program,
+            signature: vec![0x05; 65],
+            domain_chain_id: 1,
+            domain_verifying_contract: Address::ZERO,
+            wallet: Address::ZERO,
+            permission_id: FixedBytes::ZERO,
+        });
+        let compact = envelope_bytes_to_compact(&envelope).unwrap();
+        assert_eq!(envelope_bytes_from_compact(&compact).unwrap(), envelope);
+        assert!(matches!(envelope_bytes_from_compact(&compact[..compact.len() - 1]), Err(CompactError::Borsh(_))));
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_envelopes_decode() {
+        use crate::encoder::decode::{decode_envelope, decode_program};
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let seed: Vec<u8> = (0..8192u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let mut u = Unstructured::new(&seed);
+        let mut generated = 0;
+        while let Ok(envelope) = IntentEnvelope::arbitrary(&mut u) {
+            generated += 1;
+            let checks = decode_program(&envelope.program_bytes).unwrap();
+            assert_eq!(encode_program(&checks), envelope.program_bytes);
+            assert_eq!(decode_envelope(&encode_envelope(&envelope)).unwrap().program_bytes, envelope.program_bytes);
+        }
+        assert!(generated > 0);
+    }
+
+    #[cfg(feature = "reservations")]
+    #[test]
+    fn test_reservations_read_files_written_by_the_sdk() {
+        use crate::encoder::reservations::NonceReservations;
+
+        let path = std::env::temp_dir().join(format!("fiet-encoder-reservations-{}.json", std::process::id()));
+        let (policy, wallet) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let permission_id = FixedBytes::repeat_byte(0x11);
+        // Keys are lowercase `0x` hex and nonces `0x` quantities, as the ethers-typed store wrote them.
+        let key = format!("0x{}/0x{}/0x{}", "aa".repeat(20), "bb".repeat(20), "11".repeat(32));
+        let legacy = format!(
+            r#"{{"version":1,"permissions":{{"{key}":[{{"nonce":"0x5","deadline":2000,"holder":"desk-a","reservedAt":900}}]}}}}"#
+        );
+        std::fs::write(&path, legacy).unwrap();
+
+        let desk_b = NonceReservations::new(&path, "desk-b");
+        assert_eq!(desk_b.reserve_at(policy, wallet, permission_id, U256::from(5u64), 1_500, 1_000).unwrap(), U256::from(6u64));
+        assert_eq!(desk_b.highest_at(policy, wallet, permission_id, U256::from(5u64), 1_000).unwrap(), Some(U256::from(6u64)));
+        // Past desk-b's deadline only desk-a's reservation is live.
+        assert_eq!(desk_b.highest_at(policy, wallet, permission_id, U256::from(5u64), 1_600).unwrap(), Some(U256::from(5u64)));
+        assert!(desk_b.release(policy, wallet, permission_id, U256::from(5u64)).unwrap());
+        assert_eq!(desk_b.highest_at(policy, wallet, permission_id, U256::from(5u64), 1_600).unwrap(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,7 +1,7 @@
 use alloy_primitives::{Address, FixedBytes, U256};
 
 /// Intent policy envelope that is interpreted on-chain (policy-local signature slice).
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IntentEnvelope {
     /// Protocol version for forwards compatibility.
     pub version: u16,
@@ -14,7 +14,8 @@ pub struct IntentEnvelope {
     /// Encoded check program (opcode + operands).
     pub program_bytes: Vec<u8>,
 
-    /// ECDSA signature (r||s||v) over the EIP-712 digest of the envelope (policy-specific).
+    /// ECDSA signature (r||s||v) over the EIP-712 digest of the envelope (policy-specific), or
+    /// an encoded `WebAuthnAssertion` whose challenge is that digest, for passkey installs.
     pub signature: Vec<u8>,
 
     /// Domain separation parameters (used for digest construction).
@@ -26,3 +27,42 @@ pub struct IntentEnvelope {
     pub permission_id: FixedBytes<32>,
 }
 
+/// Envelopes with a well-formed program (at most `MAX_CHECKS` checks, `MAX_PROGRAM_LEN` bytes and
+/// `MAX_CALL_TARGETS` distinct call targets) and a 65-byte signature.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for IntentEnvelope {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        use crate::encoder::{
+            decode::{MAX_CHECKS, MAX_PROGRAM_LEN},
+            encode_program,
+        };
+        use crate::opcodes::{call_target_count, Check, MAX_CALL_TARGETS};
+
+        let mut checks = (0..u.int_in_range(0..=MAX_CHECKS)?).map(|_| u.arbitrary::<Check>()).collect::<Result<Vec<_>, _>>()?;
+        while encode_program(&checks).len() > MAX_PROGRAM_LEN || call_target_count(&checks) > MAX_CALL_TARGETS {
+            checks.pop();
+        }
+        Ok(IntentEnvelope {
+            version: u.arbitrary()?,
+            nonce: U256::from_be_bytes::<32>(u.arbitrary()?),
+            deadline: u.arbitrary()?,
+            call_bundle_hash: FixedBytes(u.arbitrary()?),
+            program_bytes: encode_program(&checks),
+            signature: u.arbitrary::<[u8; 65]>()?.to_vec(),
+            domain_chain_id: u.arbitrary()?,
+            domain_verifying_contract: Address::from(u.arbitrary::<[u8; 20]>()?),
+            wallet: Address::from(u.arbitrary::<[u8; 20]>()?),
+            permission_id: FixedBytes(u.arbitrary()?),
+        })
+    }
+}
+
+/// One module of a Kernel permission (a policy, or the signer), as installed by
+/// `PermissionValidator`: `bytes22 PolicyData (flag:2 || module:20) || initData`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PermissionModule {
+    /// Kernel `PassFlag` bits (`0x0001` skip userOp, `0x0002` skip signature).
+    pub flag: u16,
+    pub module: Address,
+    pub init_data: Vec<u8>,
+}
@@ -0,0 +1,166 @@
+//! Key backends envelopes are signed with.
+//!
+//! Every backend goes through [`signed_envelope_with`], which checks the signature recovers to the
+//! backend's address before the envelope leaves the daemon.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::Address,
+};
+use fiet_intent_sdk::{
+    envelope::{signed_envelope_with, EnvelopeParams},
+    remote_signer::{signed_envelope_remote, RemoteSigner},
+};
+
+/// Password env var consulted when no password file is given (never accepted as a flag).
+pub const PASSWORD_ENV: &str = "KEYSTORE_PASSWORD";
+
+/// Bearer token env var for the remote signer (never accepted as a flag).
+pub const REMOTE_TOKEN_ENV: &str = "REMOTE_SIGNER_TOKEN";
+
+/// Where the signing key lives, as selected on the command line.
+#[derive(Clone, Debug)]
+pub enum BackendConfig {
+    /// eth-keystore V3 JSON file.
+    File {
+        keystore: PathBuf,
+        password_file: Option<PathBuf>,
+    },
+    /// AWS KMS `ECC_SECG_P256K1` key; credentials and region come from the usual AWS env vars.
+    Kms { key_id: String },
+    /// Ledger Live account `m/44'/60'/<index>'/0/0`.
+    Ledger { index: usize },
+    /// External JSON-RPC signer answering `eth_signTypedData_v4` for `address`.
+    Remote { url: String, address: Address },
+}
+
+pub enum Backend {
+    File(LocalWallet),
+    #[cfg(feature = "kms")]
+    Kms(ethers::signers::AwsSigner),
+    #[cfg(feature = "ledger")]
+    Ledger(ethers::signers::Ledger),
+    Remote(RemoteSigner),
+}
+
+impl Backend {
+    pub async fn connect(config: &BackendConfig) -> Result<Self> {
+        match config {
+            BackendConfig::File {
+                keystore,
+                password_file,
+            } => Ok(Self::File(unlock(keystore, password_file.as_deref())?)),
+            BackendConfig::Kms { key_id } => kms(key_id).await,
+            BackendConfig::Ledger { index } => ledger(*index).await,
+            BackendConfig::Remote { url, address } => {
+                let token = std::env::var(REMOTE_TOKEN_ENV).ok();
+                Ok(Self::Remote(RemoteSigner::new(
+                    url,
+                    *address,
+                    token.as_deref(),
+                )?))
+            }
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::File(_) => "file",
+            #[cfg(feature = "kms")]
+            Self::Kms(_) => "kms",
+            #[cfg(feature = "ledger")]
+            Self::Ledger(_) => "ledger",
+            Self::Remote(_) => "remote",
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        match self {
+            Self::File(wallet) => wallet.address(),
+            #[cfg(feature = "kms")]
+            Self::Kms(signer) => signer.address(),
+            #[cfg(feature = "ledger")]
+            Self::Ledger(signer) => signer.address(),
+            Self::Remote(signer) => signer.address(),
+        }
+    }
+
+    /// Sign and encode the envelope described by `params`.
+    pub async fn sign_envelope(&self, params: &EnvelopeParams<'_>) -> Result<Vec<u8>> {
+        match self {
+            Self::File(wallet) => signed_envelope_with(params, wallet).await,
+            #[cfg(feature = "kms")]
+            Self::Kms(signer) => signed_envelope_with(params, signer).await,
+            #[cfg(feature = "ledger")]
+            Self::Ledger(signer) => signed_envelope_with(params, signer).await,
+            Self::Remote(signer) => signed_envelope_remote(params, signer).await,
+        }
+    }
+}
+
+/// Decrypt `path`, taking the password from `password_file`, `KEYSTORE_PASSWORD`, or a prompt.
+fn unlock(path: &Path, password_file: Option<&Path>) -> Result<LocalWallet> {
+    let password = match password_file {
+        Some(p) => std::fs::read_to_string(p)
+            .with_context(|| format!("failed reading keystore password from {}", p.display()))?
+            .trim_end_matches(['\r', '\n'])
+            .to_string(),
+        None => match std::env::var(PASSWORD_ENV) {
+            Ok(p) => p,
+            Err(_) => rpassword::prompt_password(format!(
+                "Password for keystore {}: ",
+                path.display()
+            ))
+            .with_context(|| {
+                format!("failed reading keystore password (set {PASSWORD_ENV} when not on a TTY)")
+            })?,
+        },
+    };
+    LocalWallet::decrypt_keystore(path, &password).map_err(|e| {
+        anyhow!(
+            "failed decrypting keystore {} (wrong password?): {e}",
+            path.display()
+        )
+    })
+}
+
+// Signers are built with chain id 0: the chain id only matters for transaction signing, and each
+// envelope carries its own in the EIP-712 domain.
+
+#[cfg(feature = "kms")]
+async fn kms(key_id: &str) -> Result<Backend> {
+    use rusoto_core::Region;
+    use rusoto_kms::KmsClient;
+
+    let client = KmsClient::new(Region::default());
+    let signer = ethers::signers::AwsSigner::new(client, key_id, 0)
+        .await
+        .with_context(|| format!("failed loading KMS key {key_id}"))?;
+    Ok(Backend::Kms(signer))
+}
+
+#[cfg(not(feature = "kms"))]
+async fn kms(_key_id: &str) -> Result<Backend> {
+    Err(anyhow!(
+        "--kms-key-id requires a signer built with `--features kms`"
+    ))
+}
+
+#[cfg(feature = "ledger")]
+async fn ledger(index: usize) -> Result<Backend> {
+    use ethers::signers::{HDPath, Ledger};
+    let signer = Ledger::new(HDPath::LedgerLive(index), 0)
+        .await
+        .context("failed connecting to the Ledger (unlocked, Ethereum app open?)")?;
+    Ok(Backend::Ledger(signer))
+}
+
+#[cfg(not(feature = "ledger"))]
+async fn ledger(_index: usize) -> Result<Backend> {
+    Err(anyhow!(
+        "--ledger requires a signer built with `--features ledger`"
+    ))
+}
@@ -0,0 +1,309 @@
+//! `fiet-signer`: a gRPC daemon that signs intent policy envelopes.
+//!
+//! Trading systems in any language call `fiet.signer.v1.EnvelopeSigner/SignEnvelope` (see
+//! `proto/signer.proto`) with the envelope fields and get back the encoded, signed envelope for the
+//! permission's policy signature; the key stays in this process, a KMS, on a Ledger, or with an
+//! external `eth_signTypedData_v4` signer.
+//!
+//! Programs are decoded with the policy's own rules before signing, deadlines must leave a minimum
+//! validity against chain time (with `--rpc-url`) and the local clock, and the daemon can be
+//! limited to given chains and policy contracts. Anyone who can reach the port can get envelopes signed,
+//! so bind it to localhost or a private network.
+
+mod backend;
+
+mod proto {
+    tonic::include_proto!("fiet.signer.v1");
+}
+
+use std::{net::SocketAddr, path::PathBuf, time::SystemTime};
+
+use anyhow::Context;
+use clap::{ArgGroup, Parser};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Address, BlockNumber, H256, U256},
+};
+use fiet_intent_sdk::{
+    envelope::{EnvelopeParams, EnvelopeTypedData},
+    Program,
+};
+use fiet_maker_policy_encoder::encoder::{
+    decode::decode_program,
+    expiry::{check_expiry, ExpiryPolicy},
+};
+use tonic::{transport::Server, Request, Response, Status};
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+use backend::{Backend, BackendConfig};
+use proto::{
+    envelope_signer_server::{EnvelopeSigner, EnvelopeSignerServer},
+    GetSignerRequest, GetSignerResponse, SignEnvelopeRequest, SignEnvelopeResponse,
+};
+
+#[derive(Parser, Debug)]
+#[command(about = "gRPC daemon that signs intent policy envelopes")]
+#[command(group(ArgGroup::new("backend").required(true).args(["keystore", "kms_key_id", "ledger", "remote_signer_url"])))]
+struct Cli {
+    #[arg(long, env = "SIGNER_LISTEN", default_value = "127.0.0.1:8651")]
+    listen: SocketAddr,
+
+    /// Sign with an eth-keystore V3 file (password from `--keystore-password-file`,
+    /// `KEYSTORE_PASSWORD`, or a prompt).
+    #[arg(long, env = "SIGNER_KEYSTORE")]
+    keystore: Option<PathBuf>,
+
+    #[arg(long, requires = "keystore")]
+    keystore_password_file: Option<PathBuf>,
+
+    /// Sign with an AWS KMS secp256k1 key. Requires building with `--features kms`.
+    #[arg(long, env = "SIGNER_KMS_KEY_ID")]
+    kms_key_id: Option<String>,
+
+    /// Sign on a Ledger. Requires building with `--features ledger`.
+    #[arg(long)]
+    ledger: bool,
+
+    /// Ledger Live account index used with `--ledger` (`m/44'/60'/<index>'/0/0`).
+    #[arg(long, default_value_t = 0, requires = "ledger")]
+    ledger_index: usize,
+
+    /// Forward envelopes to an external JSON-RPC signer with `eth_signTypedData_v4` (a wallet
+    /// daemon or custody API), sending `REMOTE_SIGNER_TOKEN` as a bearer token when set.
+    #[arg(long, env = "SIGNER_REMOTE_URL", requires = "remote_signer_address")]
+    remote_signer_url: Option<String>,
+
+    /// Address the remote signer signs as; every signature is checked to recover to it.
+    #[arg(long, env = "SIGNER_REMOTE_ADDRESS", requires = "remote_signer_url")]
+    remote_signer_address: Option<Address>,
+
+    /// Chain ids envelopes may be signed for (repeatable). Any chain when omitted.
+    #[arg(long = "chain-id", env = "SIGNER_CHAIN_IDS", value_delimiter = ',')]
+    chain_ids: Vec<u64>,
+
+    /// Intent policy contracts envelopes may be signed for (repeatable). Any policy when omitted.
+    #[arg(long = "policy", env = "SIGNER_POLICIES", value_delimiter = ',')]
+    policies: Vec<Address>,
+
+    /// RPC whose latest block timestamp deadlines are checked against, for requests on its chain.
+    /// Other chains (or no RPC) are checked against the local clock only.
+    #[arg(long, env = "SIGNER_RPC_URL")]
+    rpc_url: Option<String>,
+
+    /// Seconds an envelope must remain valid for when signed.
+    #[arg(long, env = "SIGNER_MIN_VALIDITY", default_value_t = 30)]
+    min_validity: u64,
+
+    /// Largest tolerated difference, in seconds, between the local clock and chain time.
+    #[arg(long, env = "SIGNER_MAX_CLOCK_SKEW", default_value_t = 60)]
+    max_clock_skew: u64,
+}
+
+impl Cli {
+    fn backend(&self) -> BackendConfig {
+        if let Some(keystore) = &self.keystore {
+            BackendConfig::File {
+                keystore: keystore.clone(),
+                password_file: self.keystore_password_file.clone(),
+            }
+        } else if let Some(key_id) = &self.kms_key_id {
+            BackendConfig::Kms {
+                key_id: key_id.clone(),
+            }
+        } else if let (Some(url), Some(address)) =
+            (&self.remote_signer_url, self.remote_signer_address)
+        {
+            BackendConfig::Remote {
+                url: url.clone(),
+                address,
+            }
+        } else {
+            BackendConfig::Ledger {
+                index: self.ledger_index,
+            }
+        }
+    }
+}
+
+struct SignerService {
+    backend: Backend,
+    chain_ids: Vec<u64>,
+    policies: Vec<Address>,
+    /// `--rpc-url` and its chain id.
+    chain_clock: Option<(Provider<Http>, u64)>,
+    expiry: ExpiryPolicy,
+}
+
+impl SignerService {
+    /// Latest block timestamp on `chain_id`, if the daemon has an RPC for it.
+    async fn chain_now(&self, chain_id: u64) -> Result<Option<u64>, Status> {
+        let Some((provider, rpc_chain_id)) = &self.chain_clock else {
+            return Ok(None);
+        };
+        if *rpc_chain_id != chain_id {
+            return Ok(None);
+        }
+        let block = provider
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|err| Status::unavailable(format!("failed fetching the latest block: {err}")))?
+            .ok_or_else(|| Status::unavailable("RPC returned no latest block"))?;
+        Ok(Some(block.timestamp.as_u64()))
+    }
+}
+
+#[tonic::async_trait]
+impl EnvelopeSigner for SignerService {
+    async fn sign_envelope(
+        &self,
+        request: Request<SignEnvelopeRequest>,
+    ) -> Result<Response<SignEnvelopeResponse>, Status> {
+        let req = request.into_inner();
+        let policy = address(&req.policy, "policy").map_err(Status::invalid_argument)?;
+        if !self.chain_ids.is_empty() && !self.chain_ids.contains(&req.chain_id) {
+            return Err(Status::permission_denied(format!(
+                "chain {} is not in the signer's allowlist",
+                req.chain_id
+            )));
+        }
+        if !self.policies.is_empty() && !self.policies.contains(&policy) {
+            return Err(Status::permission_denied(format!(
+                "policy {policy:?} is not in the signer's allowlist"
+            )));
+        }
+        if req.nonce.len() > 32 {
+            return Err(Status::invalid_argument("nonce must be at most 32 bytes"));
+        }
+        let chain_now = self.chain_now(req.chain_id).await?;
+        let mut warnings = Vec::new();
+        match check_expiry(req.deadline, chain_now, unix_now(), &self.expiry) {
+            Ok(_) => {}
+            Err(err) if err.is_overridable() && req.allow_short_validity => {
+                warn!(deadline = req.deadline, reason = %err, "signing despite expiry check");
+                warnings.push(err.to_string());
+            }
+            Err(err) => return Err(Status::failed_precondition(err.to_string())),
+        }
+        let checks = decode_program(&req.program)
+            .map_err(|err| Status::invalid_argument(format!("malformed program: {err}")))?;
+        let program = Program::from(checks);
+
+        let params = EnvelopeParams {
+            chain_id: req.chain_id,
+            policy,
+            wallet: address(&req.wallet, "wallet").map_err(Status::invalid_argument)?,
+            permission_id: bytes32(&req.permission_id, "permission_id")
+                .map_err(Status::invalid_argument)?,
+            nonce: U256::from_big_endian(&req.nonce),
+            deadline: req.deadline,
+            call_bundle_hash: bytes32(&req.call_bundle_hash, "call_bundle_hash")
+                .map_err(Status::invalid_argument)?,
+            program: &program,
+        };
+        let envelope = self.backend.sign_envelope(&params).await.map_err(|err| {
+            warn!(error = %format!("{err:#}"), "signing failed");
+            Status::internal(format!("{err:#}"))
+        })?;
+        let digest = EnvelopeTypedData::new(&params).digest();
+        info!(
+            chain_id = params.chain_id,
+            wallet = ?params.wallet,
+            permission_id = ?params.permission_id,
+            nonce = %params.nonce,
+            checks = program.checks().len(),
+            ?digest,
+            "signed envelope"
+        );
+        Ok(Response::new(SignEnvelopeResponse {
+            envelope,
+            digest: digest.as_bytes().to_vec(),
+            signer: self.backend.address().as_bytes().to_vec(),
+            warnings,
+        }))
+    }
+
+    async fn get_signer(
+        &self,
+        _request: Request<GetSignerRequest>,
+    ) -> Result<Response<GetSignerResponse>, Status> {
+        Ok(Response::new(GetSignerResponse {
+            signer: self.backend.address().as_bytes().to_vec(),
+            backend: self.backend.kind().to_string(),
+        }))
+    }
+}
+
+fn address(bytes: &[u8], field: &str) -> Result<Address, String> {
+    if bytes.len() != 20 {
+        return Err(format!("{field} must be 20 bytes, got {}", bytes.len()));
+    }
+    Ok(Address::from_slice(bytes))
+}
+
+fn bytes32(bytes: &[u8], field: &str) -> Result<H256, String> {
+    if bytes.len() != 32 {
+        return Err(format!("{field} must be 32 bytes, got {}", bytes.len()));
+    }
+    Ok(H256::from_slice(bytes))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("warn,fiet_signer=info")),
+        )
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .init();
+
+    let backend = Backend::connect(&cli.backend()).await?;
+    info!(
+        backend = backend.kind(),
+        signer = ?backend.address(),
+        "loaded signing key"
+    );
+    let chain_clock = match &cli.rpc_url {
+        Some(url) => {
+            let provider = Provider::<Http>::try_from(url.as_str())
+                .with_context(|| format!("invalid --rpc-url {url}"))?;
+            let chain_id = provider
+                .get_chainid()
+                .await
+                .context("failed fetching the chain id from --rpc-url")?
+                .as_u64();
+            info!(chain_id, "checking deadlines against chain time");
+            Some((provider, chain_id))
+        }
+        None => None,
+    };
+    let service = SignerService {
+        backend,
+        chain_ids: cli.chain_ids,
+        policies: cli.policies,
+        chain_clock,
+        expiry: ExpiryPolicy {
+            min_remaining: cli.min_validity,
+            max_skew: cli.max_clock_skew,
+        },
+    };
+
+    info!(listen = %cli.listen, "fiet-signer listening");
+    Server::builder()
+        .add_service(EnvelopeSignerServer::new(service))
+        .serve_with_shutdown(cli.listen, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("server error")
+}
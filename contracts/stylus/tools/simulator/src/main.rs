@@ -0,0 +1,212 @@
+//! `fiet-simulator`: HTTP dry runs of signed intent envelopes.
+//!
+//! `POST /simulate` takes an encoded envelope (the intent policy's signature slice) and an RPC URL,
+//! reads the program's facts over that RPC at one block, and returns every check's outcome (see
+//! `fiet_intent_sdk::simulate`). Fact sources are given explicitly or looked up from the policy
+//! for `(wallet, permissionId)`. With `"gas": true` each check also reports its estimated gas.
+//!
+//! Fact reads can fail over to `fallbackRpcUrls`; `--rpc-attempts` and `--rpc-timeout-secs` set
+//! how hard each endpoint is tried before moving on.
+//!
+//! The envelope signature and replay nonce are not verified; this answers "would the program pass
+//! right now", not "would the UserOperation validate".
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use clap::Parser;
+use ethers::{
+    providers::{Http, Provider},
+    types::{Address, Bytes, H256},
+};
+use fiet_intent_sdk::{
+    facts::{fact_sources_of, FactSources, RetryPolicy, RpcEndpoints},
+    simulate::{decode_intent, simulate, simulate_with_gas, Simulation},
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Parser, Debug)]
+#[command(about = "HTTP service that dry-runs intent envelopes against live facts")]
+struct Cli {
+    #[arg(long, env = "SIMULATOR_LISTEN", default_value = "127.0.0.1:8650")]
+    listen: SocketAddr,
+
+    /// RPC URLs requests may use (repeatable). Without any, every URL is accepted, so only expose
+    /// the service on a trusted network.
+    #[arg(
+        long = "allow-rpc",
+        env = "SIMULATOR_ALLOWED_RPCS",
+        value_delimiter = ','
+    )]
+    allowed_rpcs: Vec<String>,
+
+    /// Attempts per RPC endpoint before failing over to the next.
+    #[arg(
+        long,
+        env = "SIMULATOR_RPC_ATTEMPTS",
+        default_value_t = 2,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    rpc_attempts: u32,
+
+    /// Timeout of a single RPC request.
+    #[arg(
+        long,
+        env = "SIMULATOR_RPC_TIMEOUT_SECS",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    rpc_timeout_secs: u64,
+}
+
+#[derive(Clone)]
+struct AppState {
+    allowed_rpcs: Arc<Vec<String>>,
+    retry: RetryPolicy,
+}
+
+/// `POST /simulate` body.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SimulateRequest {
+    rpc_url: String,
+    /// Endpoints fact reads fail over to, in order, when `rpcUrl` keeps failing.
+    #[serde(default)]
+    fallback_rpc_urls: Vec<String>,
+    /// Hex-encoded envelope.
+    envelope: Bytes,
+    /// Fact sources; when absent they are read from `policy` for `wallet` and `permissionId`.
+    #[serde(default)]
+    fact_sources: Option<FactSources>,
+    #[serde(default)]
+    policy: Option<Address>,
+    #[serde(default)]
+    wallet: Option<Address>,
+    #[serde(default)]
+    permission_id: Option<H256>,
+    /// Block to read facts at; latest when absent.
+    #[serde(default)]
+    block: Option<u64>,
+    /// Estimate each check's gas (two extra `eth_estimateGas` per distinct fact read).
+    #[serde(default)]
+    gas: bool,
+}
+
+struct ApiError(StatusCode, anyhow::Error);
+
+impl ApiError {
+    fn bad_request(err: anyhow::Error) -> Self {
+        Self(StatusCode::BAD_REQUEST, err)
+    }
+
+    fn upstream(err: anyhow::Error) -> Self {
+        Self(StatusCode::BAD_GATEWAY, err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": format!("{:#}", self.1) }))).into_response()
+    }
+}
+
+async fn simulate_handler(
+    State(state): State<AppState>,
+    Json(req): Json<SimulateRequest>,
+) -> Result<Json<Simulation>, ApiError> {
+    if let Some(url) = std::iter::once(&req.rpc_url)
+        .chain(&req.fallback_rpc_urls)
+        .find(|url| !state.allowed_rpcs.is_empty() && !state.allowed_rpcs.contains(url))
+    {
+        return Err(ApiError::bad_request(anyhow!(
+            "{url} is not in the simulator's allowlist"
+        )));
+    }
+    let (envelope, checks) = decode_intent(&req.envelope).map_err(ApiError::bad_request)?;
+    let connect = |url: &str| {
+        Provider::<Http>::try_from(url)
+            .map(Arc::new)
+            .with_context(|| format!("invalid RPC URL {url}"))
+            .map_err(ApiError::bad_request)
+    };
+    let client = connect(&req.rpc_url)?;
+    let mut endpoints = RpcEndpoints::new(client.clone()).with_retry(state.retry);
+    for url in &req.fallback_rpc_urls {
+        endpoints = endpoints.with_fallback(connect(url)?);
+    }
+
+    let sources = match (req.fact_sources, req.policy, req.wallet, req.permission_id) {
+        (Some(sources), ..) => sources,
+        (None, Some(policy), Some(wallet), Some(permission_id)) => {
+            fact_sources_of(client.as_ref(), policy, wallet, permission_id)
+                .await
+                .map_err(ApiError::upstream)?
+        }
+        _ => {
+            return Err(ApiError::bad_request(anyhow!(
+                "pass factSources, or policy + wallet + permissionId to look them up"
+            )))
+        }
+    };
+
+    let simulation = if req.gas {
+        simulate_with_gas(endpoints, &envelope, checks, sources, req.block).await
+    } else {
+        simulate(endpoints, &envelope, checks, sources, req.block).await
+    }
+    .map_err(ApiError::upstream)?;
+    info!(
+        block = simulation.block,
+        checks = simulation.checks.len(),
+        passed = simulation.passed,
+        gas = simulation.gas,
+        "simulated envelope"
+    );
+    Ok(Json(simulation))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("warn,fiet_simulator=info")),
+        )
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .init();
+
+    let state = AppState {
+        allowed_rpcs: Arc::new(cli.allowed_rpcs),
+        retry: RetryPolicy {
+            attempts: cli.rpc_attempts,
+            timeout: Duration::from_secs(cli.rpc_timeout_secs),
+            ..RetryPolicy::default()
+        },
+    };
+    let app = Router::new()
+        .route("/simulate", post(simulate_handler))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(cli.listen)
+        .await
+        .with_context(|| format!("failed binding {}", cli.listen))?;
+    info!(listen = %cli.listen, "fiet-simulator listening");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("server error")
+}
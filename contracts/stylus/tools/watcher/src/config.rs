@@ -0,0 +1,158 @@
+//! `watcher.toml`: the permissions to guard and the fact thresholds that revoke them.
+//!
+//! ```toml
+//! rpc_url            = "https://arb1.arbitrum.io/rpc"
+//! fallback_rpc_urls  = ["https://arbitrum.publicnode.com"]
+//! policy             = "0x..."
+//! keystore_path      = ".keys/envelope-signer.json"
+//! poll_interval_secs = 12
+//! reservations_path  = "nonce-reservations.json"
+//!
+//! [[permissions]]
+//! wallet        = "0x..."
+//! permission_id = "0x..."
+//!
+//! [[permissions.triggers]]
+//! kind = "reserve_below"
+//! lcc  = "0x..."
+//! min  = "1000000000"
+//! ```
+//!
+//! Fact reads go to `rpc_url` first and fail over to `fallback_rpc_urls` in order, each endpoint
+//! tried `rpc_attempts` times with a `rpc_timeout_secs` timeout per request.
+//!
+//! The key must be the wallet or the permission's envelope signer, the only callers
+//! `revokeNonce` accepts. Raw private keys are not accepted here; use a key file or keystore.
+//!
+//! `reservations_path` is the nonce reservation file the signing desks share
+//! (`fiet_intent_sdk::reservations`). With it, a revocation jumps past the highest reserved nonce,
+//! cancelling envelopes queued ahead of the current one; without it only the current nonce is
+//! burnt.
+
+use std::{fs, path::Path};
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use anyhow::{bail, Context, Result};
+use fiet_maker_policy_encoder::opcodes::Check;
+use serde::Deserialize;
+
+/// Config file looked up in the working directory when `--config` is not given.
+pub const DEFAULT_CONFIG_PATH: &str = "watcher.toml";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatcherConfig {
+    pub rpc_url: String,
+    /// Endpoints fact reads fail over to when `rpc_url` keeps failing.
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<String>,
+    #[serde(default = "default_rpc_attempts")]
+    pub rpc_attempts: u32,
+    #[serde(default = "default_rpc_timeout")]
+    pub rpc_timeout_secs: u64,
+    pub policy: Address,
+    /// File holding the hex private key of the revoking account.
+    pub private_key_path: Option<String>,
+    /// eth-keystore V3 file of the revoking account (password from `KEYSTORE_PASSWORD` or a
+    /// prompt).
+    pub keystore_path: Option<String>,
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// Shared nonce reservation file; revocations go past the highest nonce reserved in it.
+    pub reservations_path: Option<String>,
+    pub permissions: Vec<WatchedPermission>,
+}
+
+fn default_poll_interval() -> u64 {
+    12
+}
+
+fn default_rpc_attempts() -> u32 {
+    2
+}
+
+fn default_rpc_timeout() -> u64 {
+    10
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchedPermission {
+    pub wallet: Address,
+    pub permission_id: FixedBytes<32>,
+    /// Any trigger firing revokes the permission's outstanding envelopes.
+    pub triggers: Vec<Trigger>,
+}
+
+/// A fact threshold. Amounts are decimal or `0x` hex strings.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Trigger {
+    /// `LiquidityHub.reserveOfUnderlying(lcc) < min`.
+    ReserveBelow { lcc: Address, min: U256 },
+    /// `LiquidityHub.reserveOfUnderlying(lcc)` covers less than `min_ratio_bps` of
+    /// `queueOfUnderlying(lcc)`.
+    SolvencyBelow { lcc: Address, min_ratio_bps: u32 },
+    /// `LiquidityHub.settleQueue(lcc, owner) > max`.
+    QueueAbove {
+        lcc: Address,
+        owner: Address,
+        max: U256,
+    },
+    /// Pool tick outside `[min, max]`.
+    TickOutside {
+        pool_id: FixedBytes<32>,
+        min: i32,
+        max: i32,
+    },
+}
+
+impl Trigger {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ReserveBelow { .. } => "reserve_below",
+            Self::SolvencyBelow { .. } => "solvency_below",
+            Self::QueueAbove { .. } => "queue_above",
+            Self::TickOutside { .. } => "tick_outside",
+        }
+    }
+
+    /// The check that holds while the trigger has not fired.
+    pub fn guard(&self) -> Check {
+        match self.clone() {
+            Self::ReserveBelow { lcc, min } => Check::ReserveGte { lcc, min },
+            Self::SolvencyBelow { lcc, min_ratio_bps } => Check::HubSolvency { lcc, min_ratio_bps },
+            Self::QueueAbove { lcc, owner, max } => Check::QueueLte { lcc, owner, max },
+            Self::TickOutside { pool_id, min, max } => Check::Slot0TickBounds { pool_id, min, max },
+        }
+    }
+}
+
+impl WatcherConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("failed reading {}", path.display()))?;
+        let config: Self =
+            toml::from_str(&raw).with_context(|| format!("failed parsing {}", path.display()))?;
+        if config.permissions.is_empty() {
+            bail!("{} lists no permissions", path.display());
+        }
+        if let Some(p) = config.permissions.iter().find(|p| p.triggers.is_empty()) {
+            bail!(
+                "permission {} for {} has no triggers",
+                p.permission_id,
+                p.wallet
+            );
+        }
+        if config.private_key_path.is_some() == config.keystore_path.is_some() {
+            bail!("set exactly one of private_key_path and keystore_path");
+        }
+        if config.poll_interval_secs == 0 {
+            bail!("poll_interval_secs must be at least 1");
+        }
+        if config.rpc_attempts == 0 || config.rpc_timeout_secs == 0 {
+            bail!("rpc_attempts and rpc_timeout_secs must be at least 1");
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,302 @@
+//! `fiet-watcher`: revokes intent nonces when on-chain risk thresholds are crossed.
+//!
+//! A signed envelope stays executable until its deadline, so reserves can drain, the settle queue
+//! can grow or the pool can move between signing and execution. The watcher polls each configured
+//! permission's facts over RPC (the reads the policy itself makes, see `fiet_intent_sdk::facts`)
+//! and, when a trigger fires, sends `IntentPolicy.revokeNonce` so every envelope signed at the
+//! current nonce is dead. With a shared reservation file the revocation goes past the highest
+//! reserved nonce, so envelopes queued ahead of the current one die as well.
+//!
+//! A permission is revoked once per breach and re-armed when all its triggers hold again.
+
+mod config;
+
+use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Parser;
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    types::{Address, H256, U256},
+};
+use fiet_intent_sdk::{
+    facts::{fact_sources_of, FactSources, RetryPolicy, RpcEndpoints, RpcFactsProvider},
+    nonce::{policy_nonce, revoke_nonce, revoke_nonce_tx},
+    reservations::NonceReservations,
+    simulate::evaluate_routed,
+};
+use fiet_maker_policy_encoder::opcodes::Check;
+use tracing::{debug, info, warn};
+use tracing_subscriber::EnvFilter;
+
+use config::{WatchedPermission, WatcherConfig, DEFAULT_CONFIG_PATH};
+
+/// Password env var consulted for `keystore_path` (never accepted as a flag).
+const PASSWORD_ENV: &str = "KEYSTORE_PASSWORD";
+
+type Client = SignerMiddleware<Arc<Provider<Http>>, LocalWallet>;
+
+#[derive(Parser, Debug)]
+#[command(about = "Revoke intent nonces when reserve, queue or tick thresholds are crossed")]
+struct Cli {
+    #[arg(long, env = "WATCHER_CONFIG", default_value = DEFAULT_CONFIG_PATH)]
+    config: PathBuf,
+
+    /// Run one poll and exit (non-zero if a revocation failed), eg from cron.
+    #[arg(long)]
+    once: bool,
+
+    /// Log fired triggers without sending `revokeNonce`.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// A permission under watch.
+struct Watch {
+    permission: WatchedPermission,
+    sources: FactSources,
+    guards: Vec<Check>,
+    /// Cleared after a revocation; set again once every trigger holds.
+    armed: bool,
+}
+
+impl Watch {
+    fn wallet(&self) -> Address {
+        Address::from(self.permission.wallet.0 .0)
+    }
+
+    fn permission_id(&self) -> H256 {
+        H256(self.permission.permission_id.0)
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("warn,fiet_watcher=info")),
+        )
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .init();
+
+    let config = WatcherConfig::load(&cli.config)?;
+    let policy = Address::from(config.policy.0 .0);
+    let provider =
+        Arc::new(Provider::<Http>::try_from(config.rpc_url.as_str()).context("invalid rpc_url")?);
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .context("eth_chainId failed")?
+        .as_u64();
+    let mut endpoints = RpcEndpoints::new(provider.clone()).with_retry(RetryPolicy {
+        attempts: config.rpc_attempts,
+        timeout: Duration::from_secs(config.rpc_timeout_secs),
+        ..RetryPolicy::default()
+    });
+    for url in &config.fallback_rpc_urls {
+        let fallback = Provider::<Http>::try_from(url.as_str())
+            .with_context(|| format!("invalid fallback_rpc_urls entry {url}"))?;
+        endpoints = endpoints.with_fallback(Arc::new(fallback));
+    }
+    let key = load_key(&config)?.with_chain_id(chain_id);
+    let revoker = key.address();
+    let client = SignerMiddleware::new(provider.clone(), key);
+    let reservations = config
+        .reservations_path
+        .as_ref()
+        .map(|path| NonceReservations::new(path, "fiet-watcher"));
+    if reservations.is_none() {
+        warn!("no reservations_path: revocations burn only the current nonce");
+    }
+
+    let mut watches = Vec::with_capacity(config.permissions.len());
+    for permission in config.permissions {
+        let wallet = Address::from(permission.wallet.0 .0);
+        let permission_id = H256(permission.permission_id.0);
+        let sources = fact_sources_of(provider.as_ref(), policy, wallet, permission_id).await?;
+        // `revokeNonce` reverts for any caller but the wallet and the envelope signer; catch a
+        // wrong key now rather than at the first breach.
+        let mut preflight = revoke_nonce_tx(policy, wallet, permission_id, U256::zero());
+        preflight.set_from(revoker);
+        if let Err(err) = provider.estimate_gas(&preflight, None).await {
+            bail!(
+                "{revoker:?} cannot revokeNonce for permission {permission_id:?} of {wallet:?} \
+                 (not the wallet or its envelope signer?): {err}"
+            );
+        }
+        info!(
+            ?wallet,
+            ?permission_id,
+            triggers = permission.triggers.len(),
+            "watching"
+        );
+        watches.push(Watch {
+            guards: permission.triggers.iter().map(|t| t.guard()).collect(),
+            permission,
+            sources,
+            armed: true,
+        });
+    }
+
+    let interval = Duration::from_secs(config.poll_interval_secs);
+    info!(?revoker, ?policy, interval = ?interval, dry_run = cli.dry_run, "fiet-watcher started");
+    loop {
+        let mut failed = false;
+        for watch in &mut watches {
+            let revoke = Revoke {
+                client: &client,
+                policy,
+                reservations: reservations.as_ref(),
+            };
+            if let Err(err) = poll(watch, &endpoints, &revoke, cli.dry_run).await {
+                warn!(
+                    wallet = ?watch.wallet(),
+                    permission_id = ?watch.permission_id(),
+                    error = %format!("{err:#}"),
+                    "poll failed"
+                );
+                failed = true;
+            }
+        }
+        if cli.once {
+            if failed {
+                bail!("one or more permissions could not be checked or revoked");
+            }
+            return Ok(());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Where and how far revocations go.
+struct Revoke<'a> {
+    client: &'a Client,
+    policy: Address,
+    reservations: Option<&'a NonceReservations>,
+}
+
+impl Revoke<'_> {
+    /// Revoke every envelope outstanding for the permission: the current nonce and, when a
+    /// reservation file is configured, every nonce reserved past it.
+    async fn send(&self, wallet: Address, permission_id: H256) -> Result<()> {
+        let (client, policy) = (self.client, self.policy);
+        let nonce = policy_nonce(client.inner().as_ref(), policy, wallet, permission_id).await?;
+        let highest = match self.reservations {
+            Some(reservations) => reservations.highest(policy, wallet, permission_id, nonce)?,
+            None => None,
+        };
+        let min_nonce = highest.map_or(U256::zero(), |n| n + 1);
+        let receipt = revoke_nonce(client, policy, wallet, permission_id, min_nonce).await?;
+        info!(
+            ?wallet,
+            ?permission_id,
+            revoked_nonce = %nonce,
+            revoked_through = %highest.unwrap_or(nonce),
+            tx = ?receipt.transaction_hash,
+            "revoked outstanding envelopes"
+        );
+        Ok(())
+    }
+}
+
+/// Read one permission's facts at the latest block and revoke if a trigger fired.
+async fn poll(
+    watch: &mut Watch,
+    endpoints: &RpcEndpoints<Provider<Http>>,
+    revoke: &Revoke<'_>,
+    dry_run: bool,
+) -> Result<()> {
+    let facts = RpcFactsProvider::latest_via(endpoints.clone(), watch.sources.clone()).await?;
+    let block = facts.block_number();
+    let guards = watch.guards.clone();
+    // Read every fact at once; whatever the prefetch missed blocks on its eth_call, so keep the
+    // evaluation off the runtime workers.
+    facts
+        .prefetch(|facts| drop(evaluate_routed(&guards, facts)))
+        .await?;
+    let outcomes = tokio::task::spawn_blocking(move || evaluate_routed(&guards, &facts))
+        .await
+        .context("trigger evaluation panicked")?;
+
+    // A failed read leaves `observed` empty. The policy fails closed on those anyway, so they are
+    // reported rather than treated as a breach.
+    let mut fired = Vec::new();
+    for outcome in outcomes.iter().filter(|o| !o.passed) {
+        let trigger = watch.permission.triggers[outcome.index].kind();
+        match &outcome.observed {
+            Some(observed) => fired.push((trigger, observed.as_str())),
+            None => warn!(
+                wallet = ?watch.wallet(),
+                trigger,
+                error = outcome.error.as_deref().unwrap_or_default(),
+                block,
+                "could not read trigger fact"
+            ),
+        }
+    }
+
+    if fired.is_empty() {
+        if !watch.armed {
+            info!(
+                wallet = ?watch.wallet(),
+                permission_id = ?watch.permission_id(),
+                block,
+                "triggers clear; re-armed"
+            );
+            watch.armed = true;
+        }
+        return Ok(());
+    }
+    if !watch.armed {
+        debug!(wallet = ?watch.wallet(), block, "still breached; already revoked");
+        return Ok(());
+    }
+
+    for (trigger, observed) in &fired {
+        warn!(
+            wallet = ?watch.wallet(),
+            permission_id = ?watch.permission_id(),
+            trigger,
+            observed,
+            block,
+            "trigger fired"
+        );
+    }
+    if dry_run {
+        warn!(wallet = ?watch.wallet(), "dry run: not revoking");
+    } else {
+        revoke.send(watch.wallet(), watch.permission_id()).await?;
+    }
+    watch.armed = false;
+    Ok(())
+}
+
+fn load_key(config: &WatcherConfig) -> Result<LocalWallet> {
+    if let Some(path) = &config.private_key_path {
+        let raw =
+            std::fs::read_to_string(path).with_context(|| format!("failed reading {path}"))?;
+        return LocalWallet::from_str(raw.trim())
+            .map_err(|err| anyhow!("invalid private key in {path}: {err}"));
+    }
+    let path = config
+        .keystore_path
+        .as_deref()
+        .context("no revoking key configured")?;
+    let password = match std::env::var(PASSWORD_ENV) {
+        Ok(p) => p,
+        Err(_) => rpassword::prompt_password(format!("Password for keystore {path}: "))
+            .with_context(|| {
+                format!("failed reading keystore password (set {PASSWORD_ENV} when not on a TTY)")
+            })?,
+    };
+    LocalWallet::decrypt_keystore(path, &password)
+        .map_err(|err| anyhow!("failed decrypting keystore {path} (wrong password?): {err}"))
+}